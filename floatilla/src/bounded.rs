@@ -0,0 +1,274 @@
+use super::*;
+use crate::error::OutOfBoundsError;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Formatter},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+/// Type-level range of floats, for `Bounded`.
+///
+/// Both bounds are inclusive, and must be real, with `MIN <= MAX`. Define
+/// these with the `bounds!` macro.
+pub trait Bounds<F: Fp> {
+    /// Lower bound, inclusive.
+    const MIN: F;
+    /// Upper bound, inclusive.
+    const MAX: F;
+}
+
+/// Define types which implement `Bounds`.
+///
+/// ```
+/// use floatilla::{bounds, bounded::Bounded};
+///
+/// bounds! {
+///     /// Field of view, in degrees.
+///     pub FovBounds: f32 = 30.0..=150.0;
+/// }
+///
+/// let fov = Bounded::<f32, FovBounds>::new(90.0);
+/// assert!(Bounded::<f32, FovBounds>::try_new(200.0).is_err());
+/// assert_eq!(Bounded::<f32, FovBounds>::clamped(200.0).get(), 150.0);
+/// assert!(fov.try_add(70.0).is_err());
+/// ```
+#[macro_export]
+macro_rules! bounds {
+    ($(
+        $(#[$attr:meta])*
+        $vis:vis $name:ident: $f:ty = $min:literal ..= $max:literal;
+    )*) => {$(
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+        $vis struct $name;
+
+        impl $crate::bounded::Bounds<$f> for $name {
+            const MIN: $f = $min;
+            const MAX: $f = $max;
+        }
+    )*};
+}
+
+/// Bounds of strictly positive, finite floats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Positive;
+
+/// Bounds of non-negative, finite floats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct NonNegative;
+
+/// Bounds of floats from 0 to 1.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct UnitInterval;
+
+macro_rules! impl_std_bounds {
+    ($f:ident) => {
+        impl Bounds<$f> for Positive {
+            // the smallest positive normal float
+            const MIN: $f = $f::MIN_POSITIVE;
+            const MAX: $f = $f::MAX;
+        }
+
+        impl Bounds<$f> for NonNegative {
+            const MIN: $f = 0.0;
+            const MAX: $f = $f::MAX;
+        }
+
+        impl Bounds<$f> for UnitInterval {
+            const MIN: $f = 0.0;
+            const MAX: $f = 1.0;
+        }
+    };
+}
+
+impl_std_bounds!(f32);
+impl_std_bounds!(f64);
+
+/// `Real` which is within the bounds `B`.
+///
+/// Construction, deserialization, and arithmetic are checked, so a
+/// `Bounded` is always within its bounds.
+pub struct Bounded<F: Fp, B: Bounds<F>>(Real<F>, PhantomData<B>);
+
+impl<F: Fp, B: Bounds<F>> Bounded<F, B> {
+    /// Construct from a float, or error if it's outside the bounds, or
+    /// non-real.
+    pub fn try_new(f: F) -> Result<Self, OutOfBoundsError<F>> {
+        if f >= B::MIN && f <= B::MAX {
+            let real = Real::try_new(f).map_err(|_| Self::error(f))?;
+            Ok(Bounded(real, PhantomData))
+        } else {
+            Err(Self::error(f))
+        }
+    }
+
+    /// Construct from a float, or panic if it's outside the bounds, or
+    /// non-real.
+    pub fn new(f: F) -> Self {
+        match Bounded::try_new(f) {
+            Ok(b) => b,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Construct from a float, clamping it to the bounds.
+    ///
+    /// Infinities clamp to the nearest bound. Panics if `f` is NaN.
+    pub fn clamped(f: F) -> Self {
+        assert!(!f.is_nan(), "attempt to clamp NaN to bounds");
+        Bounded::new(f.max(B::MIN).min(B::MAX))
+    }
+
+    /// The lower bound.
+    pub fn min_value() -> F {
+        B::MIN
+    }
+
+    /// The upper bound.
+    pub fn max_value() -> F {
+        B::MAX
+    }
+
+    fn error(f: F) -> OutOfBoundsError<F> {
+        OutOfBoundsError {
+            value: f,
+            min: B::MIN,
+            max: B::MAX,
+        }
+    }
+
+    /// Extract the raw float.
+    pub fn get(self) -> F {
+        self.0.to_float()
+    }
+
+    /// Extract the `Real`.
+    pub fn to_real(self) -> Real<F> {
+        self.0
+    }
+
+    /// Map the inner float, or error if the output is outside the bounds.
+    pub fn try_map<M>(self, map: M) -> Result<Self, OutOfBoundsError<F>>
+    where
+        M: FnOnce(F) -> F
+    {
+        Bounded::try_new(map(self.get()))
+    }
+
+    /// Add, or error if the sum is outside the bounds.
+    pub fn try_add(self, rhs: F) -> Result<Self, OutOfBoundsError<F>> {
+        self.try_map(|f| f + rhs)
+    }
+
+    /// Subtract, or error if the difference is outside the bounds.
+    pub fn try_sub(self, rhs: F) -> Result<Self, OutOfBoundsError<F>> {
+        self.try_map(|f| f - rhs)
+    }
+
+    /// Multiply, or error if the product is outside the bounds.
+    pub fn try_mul(self, rhs: F) -> Result<Self, OutOfBoundsError<F>> {
+        self.try_map(|f| f * rhs)
+    }
+
+    /// Divide, or error if the quotient is outside the bounds.
+    pub fn try_div(self, rhs: F) -> Result<Self, OutOfBoundsError<F>> {
+        self.try_map(|f| f / rhs)
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> Copy for Bounded<F, B> {}
+
+impl<F: Fp, B: Bounds<F>> Clone for Bounded<F, B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> Eq for Bounded<F, B> {}
+
+impl<F: Fp, B: Bounds<F>> PartialEq for Bounded<F, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> Ord for Bounded<F, B> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> PartialOrd for Bounded<F, B> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> Hash for Bounded<F, B> {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        self.0.hash(h)
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> fmt::Display for Bounded<F, B> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> fmt::Debug for Bounded<F, B> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> From<Bounded<F, B>> for Real<F> {
+    fn from(b: Bounded<F, B>) -> Self {
+        b.to_real()
+    }
+}
+
+impl<F: Fp, B: Bounds<F>> core::convert::TryFrom<Real<F>> for Bounded<F, B> {
+    type Error = OutOfBoundsError<F>;
+
+    fn try_from(real: Real<F>) -> Result<Self, OutOfBoundsError<F>> {
+        Bounded::try_new(real.to_float())
+    }
+}
+
+macro_rules! impl_into_float {
+    ($f:ident) => {
+        impl<B: Bounds<$f>> From<Bounded<$f, B>> for $f {
+            fn from(b: Bounded<$f, B>) -> Self {
+                b.get()
+            }
+        }
+    };
+}
+
+impl_into_float!(f32);
+impl_into_float!(f64);
+
+#[cfg(feature = "serde")]
+impl<F, B> serde::Serialize for Bounded<F, B>
+where
+    F: Fp + serde::Serialize,
+    B: Bounds<F>,
+{
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(s)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, F, B> serde::Deserialize<'de> for Bounded<F, B>
+where
+    F: Fp + serde::Deserialize<'de>,
+    B: Bounds<F>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let f = F::deserialize(d)?;
+        Bounded::try_new(f).map_err(serde::de::Error::custom)
+    }
+}
@@ -65,8 +65,31 @@ impl<F: Fp> Display for MathOp<F> {
     }
 }
 
+/// Error when a float is outside the bounds of a `Bounded`, or non-real.
+#[derive(Copy, Clone, Debug)]
+pub struct OutOfBoundsError<F: Fp> {
+    pub value: F,
+    pub min: F,
+    pub max: F,
+}
+
+impl<F: Fp> Display for OutOfBoundsError<F> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("attempt to construct Bounded from out-of-bounds float: ")?;
+        Display::fmt(&self.value, f)?;
+        f.write_str(" not in ")?;
+        Display::fmt(&self.min, f)?;
+        f.write_str("..=")?;
+        Display::fmt(&self.max, f)?;
+        Ok(())
+    }
+}
+
 #[cfg(feature = "std")]
 impl<F: Fp> Error for NotRealError<F> {}
 
 #[cfg(feature = "std")]
 impl<F: Fp> Error for MathError<F> {}
+
+#[cfg(feature = "std")]
+impl<F: Fp> Error for OutOfBoundsError<F> {}
@@ -8,6 +8,8 @@
 // TODO: num_trait implementations for FpRepr and TryMath
 
 extern crate num_traits;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 use core::{
     hint::unreachable_unchecked,
@@ -32,6 +34,9 @@ pub mod try_math;
 /// Float-variants errors.
 pub mod error;
 
+/// Floats restricted to a range.
+pub mod bounded;
+
 /// Trait for valid inner float types. 
 ///
 /// Unlike `num_traits::FloatCore`, this is unsafe, because incorrect float
@@ -0,0 +1,384 @@
+//! Derive macros for the shader interface traits in `game::graphics::util`.
+//!
+//! These implement `GenericVertex` and `GenericUniforms`, and are declared
+//! with attributes on an ordinary struct:
+//!
+//! ```ignore
+//! #[derive(Copy, Clone, Debug, Vertex)]
+//! pub struct Vertex {
+//!     #[attr(location = 0, glsl = "vec3")]
+//!     pub pos: Vec3<f32>,
+//!     #[attr(location = 1, glsl = "vec2")]
+//!     pub tex_coord: Vec2<f32>,
+//! }
+//!
+//! #[derive(Copy, Clone, Debug, Uniforms)]
+//! #[attr(size = 68)]
+//! pub struct Uniforms {
+//!     #[attr(glsl = "mat4")]
+//!     pub corr_proj_view: Mat4<f32>,
+//!     #[attr(glsl = "float")]
+//!     pub gamma: f32,
+//! }
+//! ```
+//!
+//! These must be used within the `game` crate, because the generated code
+//! refers to `crate::graphics::util`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{
+    parse_macro_input,
+    spanned::Spanned,
+    Attribute,
+    Data,
+    DeriveInput,
+    Error,
+    Fields,
+    Ident,
+    Lit,
+    LitInt,
+    LitStr,
+    Meta,
+    NestedMeta,
+    Result,
+    Type,
+};
+
+/// GLSL types which can be vertex attributes.
+const VERTEX_GLSL_TYPES: &[&str] = &[
+    "uint", "uvec2", "uvec3", "uvec4",
+    "int", "ivec2", "ivec3", "ivec4",
+    "float", "vec2", "vec3", "vec4",
+];
+
+/// Derive `GenericVertex`, and an inherent `encode` method.
+///
+/// Every field needs `#[attr(location = _, glsl = "_")]`, and fields are
+/// packed in declaration order.
+#[proc_macro_derive(Vertex, attributes(attr))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_vertex(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Derive `GenericUniforms`, and an inherent `encode` method.
+///
+/// Every field needs `#[attr(glsl = "_")]`, and fields are laid out with
+/// std140 rules in declaration order. It's a compile error if a field's
+/// `glsl` type doesn't match its Rust type. The struct may have
+/// `#[attr(size = _)]`, in which case it's a compile error if the std140
+/// size differs from that.
+#[proc_macro_derive(Uniforms, attributes(attr))]
+pub fn derive_uniforms(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_uniforms(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Contents of an `#[attr(...)]` attribute.
+#[derive(Default)]
+struct Attr {
+    location: Option<LitInt>,
+    glsl: Option<LitStr>,
+    size: Option<LitInt>,
+}
+
+impl Attr {
+    /// Parse and merge all `#[attr(...)]` attributes.
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut parsed = Attr::default();
+        for attr in attrs {
+            if !attr.path.is_ident("attr") {
+                continue;
+            }
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new(
+                    meta.span(),
+                    "expected #[attr(key = value, ...)]",
+                )),
+            };
+            for nested in list.nested {
+                let name_value = match nested {
+                    NestedMeta::Meta(Meta::NameValue(name_value)) => name_value,
+                    nested => return Err(Error::new(
+                        nested.span(),
+                        "expected `key = value`",
+                    )),
+                };
+                let key = name_value.path
+                    .get_ident()
+                    .map(Ident::to_string)
+                    .unwrap_or_default();
+                match (key.as_str(), name_value.lit) {
+                    ("location", Lit::Int(lit)) => set_once(&mut parsed.location, lit)?,
+                    ("glsl", Lit::Str(lit)) => set_once(&mut parsed.glsl, lit)?,
+                    ("size", Lit::Int(lit)) => set_once(&mut parsed.size, lit)?,
+                    ("location", lit) | ("size", lit) => return Err(Error::new(
+                        lit.span(),
+                        "expected integer literal",
+                    )),
+                    ("glsl", lit) => return Err(Error::new(
+                        lit.span(),
+                        "expected string literal",
+                    )),
+                    _ => return Err(Error::new(
+                        name_value.path.span(),
+                        "unknown key, expected `location`, `glsl`, or `size`",
+                    )),
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn set_once<L: Spanned>(slot: &mut Option<L>, lit: L) -> Result<()> {
+    if slot.is_some() {
+        return Err(Error::new(lit.span(), "duplicate key"));
+    }
+    *slot = Some(lit);
+    Ok(())
+}
+
+/// A field of a derive input struct, with its attribute.
+struct AttrField {
+    ident: Ident,
+    ty: Type,
+    attr: Attr,
+    span: Span,
+}
+
+/// Get the fields of a struct with named fields and no generics.
+fn attr_fields(input: &DeriveInput, derive: &str) -> Result<Vec<AttrField>> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            format!("#[derive({})] does not support generics", derive),
+        ));
+    }
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(Error::new(
+                input.ident.span(),
+                format!("#[derive({})] requires named fields", derive),
+            )),
+        },
+        _ => return Err(Error::new(
+            input.ident.span(),
+            format!("#[derive({})] only supports structs", derive),
+        )),
+    };
+    fields
+        .iter()
+        .map(|field| Ok(AttrField {
+            ident: field.ident.clone().unwrap(),
+            ty: field.ty.clone(),
+            attr: Attr::parse(&field.attrs)?,
+            span: field.span(),
+        }))
+        .collect()
+}
+
+fn expand_vertex(input: DeriveInput) -> Result<TokenStream2> {
+    if let Some(size) = Attr::parse(&input.attrs)?.size {
+        return Err(Error::new(size.span(), "`size` is not supported on vertices"));
+    }
+
+    let mut locations: Vec<u32> = Vec::new();
+    let mut field_idents = Vec::new();
+    let mut field_types = Vec::new();
+    let mut field_locations = Vec::new();
+    let mut field_glsl = Vec::new();
+    for field in attr_fields(&input, "Vertex")? {
+        let span = field.span;
+        let location = field.attr.location
+            .ok_or_else(|| Error::new(span, "missing #[attr(location = _)]"))?;
+        let glsl = field.attr.glsl
+            .ok_or_else(|| Error::new(span, "missing #[attr(glsl = \"_\")]"))?;
+        if field.attr.size.is_some() {
+            return Err(Error::new(span, "`size` is not supported on fields"));
+        }
+
+        let location_value = location.base10_parse::<u32>()?;
+        if locations.contains(&location_value) {
+            return Err(Error::new(location.span(), "duplicate location"));
+        }
+        locations.push(location_value);
+
+        if !VERTEX_GLSL_TYPES.contains(&glsl.value().as_str()) {
+            return Err(Error::new(
+                glsl.span(),
+                format!(
+                    "invalid GLSL vertex attribute type, expected one of: {}",
+                    VERTEX_GLSL_TYPES.join(", "),
+                ),
+            ));
+        }
+
+        field_idents.push(field.ident);
+        field_types.push(field.ty);
+        field_locations.push(location);
+        field_glsl.push(glsl);
+    }
+
+    let ident = &input.ident;
+    Ok(quote! {
+        impl #ident {
+            /// Write `self`'s binary representation to a fixed-size byte array.
+            pub fn encode(&self) -> [u8; <Self as crate::graphics::util::vertex::GenericVertex>::SIZE] {
+                let mut array = [0_u8; <Self as crate::graphics::util::vertex::GenericVertex>::SIZE];
+                <Self as crate::graphics::util::vertex::GenericVertex>::encode_to(self, &mut array);
+                array
+            }
+        }
+
+        impl crate::graphics::util::vertex::GenericVertex for #ident {
+            const SIZE: usize = 0 #(
+                + std::mem::size_of::<
+                    <#field_types as crate::graphics::util::vertex::IntoVertexAttrib>::Into
+                >()
+            )*;
+
+            fn attributes() -> std::borrow::Cow<'static, [crate::wgpu::VertexAttributeDescriptor]> {
+                let mut vec: Vec<crate::wgpu::VertexAttributeDescriptor> = Vec::new();
+                let mut curr_offset: u64 = 0;
+
+                #({
+                    type Attrib = <#field_types as crate::graphics::util::vertex::IntoVertexAttrib>::Into;
+                    let attr_format = crate::graphics::util::vertex::deduce_vertex_format::<Attrib>(#field_glsl)
+                        .unwrap_or_else(|e| {
+                            ::tracing::error!("{}", e);
+                            panic!();
+                        });
+                    vec.push(crate::wgpu::VertexAttributeDescriptor {
+                        offset: curr_offset,
+                        format: attr_format,
+                        shader_location: #field_locations,
+                    });
+                    curr_offset += std::mem::size_of::<Attrib>() as u64;
+                })*
+
+                debug_assert_eq!(curr_offset, Self::SIZE as u64);
+                std::borrow::Cow::Owned(vec)
+            }
+
+            fn encode_to(&self, mut bytes: &mut [u8]) {
+                assert_eq!(bytes.len(), Self::SIZE, "wrong size");
+
+                #({
+                    let attr = <
+                            #field_types
+                            as
+                            crate::graphics::util::vertex::IntoVertexAttrib
+                        >::into_vertex_attrib(self.#field_idents.clone());
+                    let attr_bytes = crate::bytemuck::bytes_of(&attr);
+                    bytes[..attr_bytes.len()].copy_from_slice(attr_bytes);
+                    bytes = &mut bytes[attr_bytes.len()..];
+                })*
+
+                debug_assert!(bytes.is_empty());
+            }
+        }
+    })
+}
+
+fn expand_uniforms(input: DeriveInput) -> Result<TokenStream2> {
+    let struct_attr = Attr::parse(&input.attrs)?;
+    if let Some(lit) = struct_attr.location {
+        return Err(Error::new(lit.span(), "`location` is not supported on uniforms"));
+    }
+    if let Some(lit) = struct_attr.glsl {
+        return Err(Error::new(lit.span(), "`glsl` is not supported on the struct"));
+    }
+
+    let mut field_idents = Vec::new();
+    let mut field_types = Vec::new();
+    let mut field_glsl = Vec::new();
+    for field in attr_fields(&input, "Uniforms")? {
+        let span = field.span;
+        let glsl = field.attr.glsl
+            .ok_or_else(|| Error::new(span, "missing #[attr(glsl = \"_\")]"))?;
+        if let Some(lit) = field.attr.location {
+            return Err(Error::new(lit.span(), "`location` is not supported on uniforms"));
+        }
+        if let Some(lit) = field.attr.size {
+            return Err(Error::new(lit.span(), "`size` is not supported on fields"));
+        }
+
+        field_idents.push(field.ident);
+        field_types.push(field.ty);
+        field_glsl.push(glsl);
+    }
+
+    let ident = &input.ident;
+    let glsl_assertions = field_types.iter().zip(&field_glsl).map(|(ty, glsl)| quote_spanned! {glsl.span()=>
+        // fails to compile if the GLSL type doesn't match the Rust type
+        const _: () = assert!(
+            crate::graphics::util::uniform::glsl_type_eq(
+                <#ty as crate::graphics::util::uniform::UniformField>::GLSL_TYPE,
+                #glsl,
+            ),
+            "GLSL type doesn't match the field's Rust type",
+        );
+    });
+    let size_assertion = struct_attr.size.map(|size| quote! {
+        // fails to compile if the size doesn't match the shader's
+        const _: [(); #size] = [(); <#ident as crate::graphics::util::uniform::GenericUniforms>::SIZE];
+    });
+    Ok(quote! {
+        impl #ident {
+            /// Write `self`'s binary representation to a fixed-size byte array.
+            pub fn encode(&self) -> [u8; <Self as crate::graphics::util::uniform::GenericUniforms>::SIZE] {
+                let mut array = [0_u8; <Self as crate::graphics::util::uniform::GenericUniforms>::SIZE];
+                <Self as crate::graphics::util::uniform::GenericUniforms>::encode_to(self, &mut array);
+                array
+            }
+        }
+
+        impl crate::graphics::util::uniform::GenericUniforms for #ident {
+            const SIZE: usize = {
+                #[allow(unused_mut)]
+                let mut end = 0;
+                #(
+                end = crate::graphics::util::uniform::std140_offset(
+                    end,
+                    <#field_types as crate::graphics::util::uniform::UniformField>::STD140_ALIGN,
+                ) + <#field_types as crate::graphics::util::uniform::UniformField>::STD140_SIZE;
+                )*
+                end
+            };
+
+            fn encode_to(&self, bytes: &mut [u8]) {
+                assert_eq!(bytes.len(), Self::SIZE, "wrong size");
+
+                // zero the padding
+                for b in bytes.iter_mut() {
+                    *b = 0;
+                }
+
+                #[allow(unused_mut)]
+                let mut end = 0;
+                #(
+                end = crate::graphics::util::uniform::write_std140_field::<#field_types>(
+                    bytes,
+                    end,
+                    &self.#field_idents,
+                );
+                )*
+                debug_assert_eq!(end, Self::SIZE);
+            }
+        }
+
+        #(#glsl_assertions)*
+        #size_assertion
+    })
+}
@@ -0,0 +1,483 @@
+//! Loading assets from disk in the background. 
+//!
+//! `Assets` reads and decodes files on a pool of worker threads, so that
+//! many assets load at once, without blocking the thread which requested
+//! them. Loading an asset returns a typed `Handle` right away, which is used
+//! to get the asset once it has loaded, or to wait for it. 
+//!
+//! Asset paths are relative to a root directory, and each file is only
+//! loaded once per asset type. Assets can be reloaded, such as after their
+//! file was modified, in which case the old version stays available until
+//! the new one has loaded. Finished loads are reported as `AssetEvent`s. 
+
+use crate::graphics::{ModelData, load_gltf, load_obj};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    fs,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc,
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::SystemTime,
+};
+use image::RgbaImage;
+use pear::*;
+
+/// Default number of worker threads. 
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// First word of every SPIR-V module. 
+const SPIRV_MAGIC: u32 = 0x07230203;
+
+/// Type of asset which can be decoded from a file. 
+pub trait Asset: Sized + Send + Sync + 'static {
+    /// Decode the contents of the file at `path`. 
+    fn decode(bytes: Vec<u8>, path: &Path) -> Result<Self>;
+}
+
+/// Decoded image, such as from a PNG file. 
+#[derive(Clone, Debug)]
+pub struct Texture(pub RgbaImage);
+
+impl Asset for Texture {
+    fn decode(bytes: Vec<u8>, _: &Path) -> Result<Self> {
+        Ok(Texture(image::load_from_memory(&bytes)?.into_rgba()))
+    }
+}
+
+/// Compiled SPIR-V shader, as words. 
+#[derive(Clone, Debug)]
+pub struct Shader(pub Vec<u32>);
+
+impl Asset for Shader {
+    fn decode(bytes: Vec<u8>, _: &Path) -> Result<Self> {
+        if bytes.len() % 4 != 0 {
+            return Err(pear!({len=bytes.len()}, "SPIR-V length not a multiple of 4"));
+        }
+        let words = bytes
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect::<Vec<u32>>();
+        if words.first() != Some(&SPIRV_MAGIC) {
+            return Err(pear!({}, "not a SPIR-V module"));
+        }
+        Ok(Shader(words))
+    }
+}
+
+/// Model mesh data, from a Wavefront OBJ or glTF file, as decided by its
+/// extension. 
+///
+/// OBJ models are untextured until textured with `ModelData::set_texture`. 
+#[derive(Clone, Debug)]
+pub struct Model(pub ModelData);
+
+impl Asset for Model {
+    fn decode(bytes: Vec<u8>, path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match ext.as_ref().map(String::as_str) {
+            Some("obj") => load_obj(&bytes, None).map(Model),
+            Some("gltf") | Some("glb") => load_gltf(&bytes).map(Model),
+            _ => Err(pear!({ext=ext}, "unknown model file extension")),
+        }
+    }
+}
+
+/// Sound file. 
+///
+/// There's no audio playback yet, so sounds are kept as the encoded
+/// contents of their file. 
+#[derive(Clone, Debug)]
+pub struct Sound(pub Vec<u8>);
+
+impl Asset for Sound {
+    fn decode(bytes: Vec<u8>, _: &Path) -> Result<Self> {
+        Ok(Sound(bytes))
+    }
+}
+
+/// Identifier of a loaded asset, of any type. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(u64);
+
+/// Handle to an asset of type `T`. 
+pub struct Handle<T> {
+    id: AssetId,
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// Get the asset's untyped ID. 
+    pub fn id(self) -> AssetId {
+        self.id
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+impl<T> Debug for Handle<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.id.0).finish()
+    }
+}
+
+/// Whether an asset has loaded. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    /// Loaded, though it may be reloading. 
+    Loaded,
+    /// Failed to load, and no earlier version loaded either. 
+    Failed,
+}
+
+/// Finished load of an asset. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AssetEvent {
+    /// An asset loaded for the first time. 
+    Loaded(AssetId),
+    /// A new version of an asset loaded. 
+    Reloaded(AssetId),
+    /// An asset failed to load or reload. Its error is available from
+    /// `Assets::error`. 
+    Failed(AssetId),
+}
+
+type Loaded = Arc<dyn Any + Send + Sync>;
+type DecodeFn = fn(Vec<u8>, &Path) -> Result<Loaded>;
+
+fn decode_any<T: Asset>(bytes: Vec<u8>, path: &Path) -> Result<Loaded> {
+    T::decode(bytes, path).map(|asset| Arc::new(asset) as Loaded)
+}
+
+struct Entry {
+    path: PathBuf,
+    decode: DecodeFn,
+    // latest version which loaded
+    value: Option<Loaded>,
+    // error of the latest load, if it failed
+    error: Option<Error>,
+    loading: bool,
+    // modification time of the file when it was last read
+    modified: Option<SystemTime>,
+}
+
+struct Job {
+    id: AssetId,
+    path: PathBuf,
+    decode: DecodeFn,
+}
+
+struct Done {
+    id: AssetId,
+    // modification time of the file before it was read, even if it failed
+    modified: Option<SystemTime>,
+    result: Result<Loaded>,
+}
+
+/// Asset manager, which loads assets on background threads. 
+pub struct Assets {
+    root: PathBuf,
+    entries: HashMap<AssetId, Entry>,
+    by_path: HashMap<(PathBuf, TypeId), AssetId>,
+    next_id: u64,
+    jobs: mpsc::Sender<Job>,
+    done_recv: mpsc::Receiver<Done>,
+    events: Vec<AssetEvent>,
+}
+
+impl Assets {
+    /// Construct with assets in the `root` directory, and `DEFAULT_WORKERS`
+    /// worker threads. 
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Assets::with_workers(root, DEFAULT_WORKERS)
+    }
+
+    /// Construct with assets in the `root` directory, and a number of
+    /// worker threads. 
+    ///
+    /// Panics if `workers` is 0. 
+    pub fn with_workers<P: Into<PathBuf>>(root: P, workers: usize) -> Self {
+        assert!(workers > 0, "asset manager needs at least one worker");
+        let (jobs, jobs_recv) = mpsc::channel::<Job>();
+        let jobs_recv = Arc::new(Mutex::new(jobs_recv));
+        let (done_send, done_recv) = mpsc::channel();
+        for i in 0..workers {
+            let jobs_recv = jobs_recv.clone();
+            let done_send = done_send.clone();
+            thread::Builder::new()
+                .name(format!("asset loader {}", i))
+                .spawn(move || load_loop(jobs_recv, done_send))
+                .expect("failed to spawn asset loader thread");
+        }
+        Assets {
+            root: root.into(),
+            entries: HashMap::new(),
+            by_path: HashMap::new(),
+            next_id: 0,
+            jobs,
+            done_recv,
+            events: Vec::new(),
+        }
+    }
+
+    /// Start loading an asset from a path relative to the root directory, or
+    /// get the handle of the asset if it's already loaded. 
+    pub fn load<T: Asset, P: AsRef<Path>>(&mut self, path: P) -> Handle<T> {
+        let path = path.as_ref().to_owned();
+        let key = (path.clone(), TypeId::of::<T>());
+        let id = match self.by_path.get(&key) {
+            Some(&id) => id,
+            None => {
+                let id = AssetId(self.next_id);
+                self.next_id += 1;
+                self.by_path.insert(key, id);
+                self.entries.insert(id, Entry {
+                    path,
+                    decode: decode_any::<T>,
+                    value: None,
+                    error: None,
+                    loading: false,
+                    modified: None,
+                });
+                self.reload(id);
+                id
+            }
+        };
+        Handle {
+            id,
+            _asset: PhantomData,
+        }
+    }
+
+    /// Get an asset, if any version of it has loaded. 
+    pub fn get<T: Asset>(&self, handle: Handle<T>) -> Option<Arc<T>> {
+        self.entries[&handle.id].value
+            .clone()
+            .and_then(|value| value.downcast::<T>().ok())
+    }
+
+    /// Get whether an asset has loaded. 
+    pub fn state(&self, id: AssetId) -> LoadState {
+        let entry = &self.entries[&id];
+        if entry.value.is_some() {
+            LoadState::Loaded
+        } else if entry.loading {
+            LoadState::Loading
+        } else {
+            LoadState::Failed
+        }
+    }
+
+    /// Get the error of an asset's latest load, if it failed. 
+    pub fn error(&self, id: AssetId) -> Option<&Error> {
+        self.entries[&id].error.as_ref()
+    }
+
+    /// Get an asset's path, relative to the root directory. 
+    pub fn path(&self, id: AssetId) -> &Path {
+        &self.entries[&id].path
+    }
+
+    /// Get the number of assets which are loading or reloading. 
+    pub fn pending(&self) -> usize {
+        self.entries.values().filter(|entry| entry.loading).count()
+    }
+
+    /// Block until an asset has finished loading, then get it. 
+    ///
+    /// If it's reloading, this waits for the reload. Errors if it failed to
+    /// load. 
+    pub fn wait<T: Asset>(&mut self, handle: Handle<T>) -> Result<Arc<T>> {
+        while self.entries[&handle.id].loading {
+            // never disconnects, since workers only exit when this is dropped
+            let done = self.done_recv.recv().unwrap();
+            self.finish(done);
+        }
+        match self.entries[&handle.id].error {
+            Some(ref e) => Err(e.clone()),
+            None => Ok(self.get(handle).unwrap()),
+        }
+    }
+
+    /// Start reloading an asset from its file, unless it's already loading. 
+    pub fn reload(&mut self, id: AssetId) {
+        let entry = self.entries.get_mut(&id).unwrap();
+        if entry.loading {
+            return;
+        }
+        entry.loading = true;
+        let job = Job {
+            id,
+            path: self.root.join(&entry.path),
+            decode: entry.decode,
+        };
+        // workers only exit when this is dropped
+        let _ = self.jobs.send(job);
+    }
+
+    /// Start reloading every asset whose file was modified since it was last
+    /// read. Return how many are reloading. 
+    pub fn reload_modified(&mut self) -> usize {
+        let modified = self.entries
+            .iter()
+            .filter(|&(_, entry)| !entry.loading)
+            .filter(|&(_, entry)| {
+                let modified = fs::metadata(self.root.join(&entry.path))
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+                modified.is_some() && modified != entry.modified
+            })
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for &id in &modified {
+            self.reload(id);
+        }
+        modified.len()
+    }
+
+    /// Receive assets which finished loading, without blocking. 
+    ///
+    /// This should be called regularly, such as once per frame. 
+    pub fn maintain(&mut self) {
+        while let Ok(done) = self.done_recv.try_recv() {
+            self.finish(done);
+        }
+    }
+
+    /// Take the events of loads which finished since the last call. 
+    pub fn take_events(&mut self) -> Vec<AssetEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    fn finish(&mut self, done: Done) {
+        let entry = self.entries.get_mut(&done.id).unwrap();
+        entry.loading = false;
+        // a broken file is only retried once it's modified again
+        entry.modified = done.modified;
+        match done.result {
+            Ok(value) => {
+                self.events.push(match entry.value {
+                    Some(_) => AssetEvent::Reloaded(done.id),
+                    None => AssetEvent::Loaded(done.id),
+                });
+                entry.value = Some(value);
+                entry.error = None;
+            }
+            Err(e) => {
+                self.events.push(AssetEvent::Failed(done.id));
+                entry.error = Some(e);
+            }
+        }
+    }
+}
+
+impl Debug for Assets {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Assets")
+            .field("root", &self.root)
+            .field("assets", &self.entries.len())
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+/// Worker thread loop, which exits once the `Assets` is dropped. 
+fn load_loop(jobs: Arc<Mutex<mpsc::Receiver<Job>>>, done: mpsc::Sender<Done>) {
+    loop {
+        let job = match jobs.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(mpsc::RecvError) => return,
+        };
+        let modified = fs::metadata(&job.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let result = load_file(&job.path, job.decode);
+        if done.send(Done { id: job.id, modified, result }).is_err() {
+            return;
+        }
+    }
+}
+
+/// Read and decode an asset file. 
+fn load_file(path: &Path, decode: DecodeFn) -> Result<Loaded> {
+    puffin::profile_function!();
+    let bytes = fs::read(path)
+        .map_err(Error::from)
+        .wrap_err(|| pear!({path=path}, "failed to read asset file"))?;
+    decode(bytes, path)
+        .wrap_err(|| pear!({path=path}, "failed to decode asset file"))
+}
+
+#[test]
+fn assets_test() {
+    let root = std::env::temp_dir().join(format!("assets_test_{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.ogg"), b"sound").unwrap();
+    fs::write(root.join("bad.spv"), [1, 2, 3, 4]).unwrap();
+
+    let mut assets = Assets::with_workers(&root, 2);
+    let sound = assets.load::<Sound, _>("a.ogg");
+    assert_eq!(assets.load::<Sound, _>("a.ogg"), sound);
+    let shader = assets.load::<Shader, _>("bad.spv");
+    let missing = assets.load::<Texture, _>("missing.png");
+    assert_eq!(assets.wait(sound).unwrap().0, b"sound");
+    assert!(assets.wait(shader).is_err());
+    assert!(assets.wait(missing).is_err());
+    assert_eq!(assets.state(sound.id()), LoadState::Loaded);
+    assert_eq!(assets.state(missing.id()), LoadState::Failed);
+    assert!(assets.error(missing.id()).is_some());
+    let mut events = assets.take_events();
+    events.sort_by_key(|event| match *event {
+        AssetEvent::Loaded(id) | AssetEvent::Reloaded(id) | AssetEvent::Failed(id) => id,
+    });
+    assert_eq!(events, vec![
+        AssetEvent::Loaded(sound.id()),
+        AssetEvent::Failed(shader.id()),
+        AssetEvent::Failed(missing.id()),
+    ]);
+
+    // broken files aren't retried until they're modified again
+    assert_eq!(assets.reload_modified(), 0);
+
+    // the old version stays available while reloading
+    fs::write(root.join("a.ogg"), b"new sound").unwrap();
+    assets.reload(sound.id());
+    assert_eq!(assets.get(sound).unwrap().0, b"sound");
+    assert_eq!(assets.wait(sound).unwrap().0, b"new sound");
+    assert_eq!(assets.take_events(), vec![AssetEvent::Reloaded(sound.id())]);
+    assert_eq!(assets.pending(), 0);
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,323 @@
+//! User settings, stored in a TOML file. 
+//!
+//! Every field is optional, and missing fields take their default values, so
+//! a settings file only needs to contain the settings which were changed:
+//!
+//! ```toml
+//! [graphics]
+//! fullscreen = "borderless"
+//! vsync = true
+//! fov = 100.0
+//! render_distance = 8
+//! fps_cap = 144
+//!
+//! [controls]
+//! mouse_sensitivity = 0.15
+//!
+//! [controls.keys]
+//! move_forward = "Up"
+//! move_back = "Down"
+//! ```
+//!
+//! Keys are named by their `winit::event::VirtualKeyCode` variant. 
+
+use crate::{
+    graphics::{
+        Graphics,
+        FullscreenMode,
+        PowerPreference,
+        builder::GraphicsBuilder,
+    },
+    input::{
+        Key,
+        InputManagerBuilder,
+        KeyBind,
+        AxisBind,
+    },
+    util::{
+        angle::*,
+        camera::Projection,
+    },
+};
+use std::{
+    fs,
+    io,
+    path::Path,
+};
+use serde::{Serialize, Deserialize};
+use pear::*;
+use arraymap::ArrayMap;
+use floatilla::{bounds, bounded::Bounded};
+
+bounds! {
+    /// Bounds of `GraphicsSettings::fov`. 
+    pub FovBounds: f32 = 30.0..=150.0;
+    /// Bounds of `GraphicsSettings::gamma`. 
+    pub GammaBounds: f32 = 0.5..=3.0;
+    /// Bounds of `ControlSettings::mouse_sensitivity`. 
+    pub SensitivityBounds: f32 = 0.01..=1.0;
+}
+
+/// All user settings. 
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Rendering settings. 
+    pub graphics: GraphicsSettings,
+    /// Input settings. 
+    pub controls: ControlSettings,
+}
+
+/// Rendering settings. 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphicsSettings {
+    /// Which kind of GPU to prefer, if there are several. 
+    pub gpu: GpuPreference,
+    /// Window fullscreen mode. 
+    pub fullscreen: FullscreenMode,
+    /// Whether to wait for vertical sync before presenting frames. 
+    pub vsync: bool,
+    /// Horizontal field of view, in degrees. 
+    pub fov: Bounded<f32, FovBounds>,
+    /// Gamma adjustment. See `Graphics::set_gamma`. 
+    pub gamma: Bounded<f32, GammaBounds>,
+    /// Render distance, in chunks, or unlimited if absent. 
+    pub render_distance: Option<u32>,
+    /// Maximum frames per second, or unlimited if absent. 
+    pub fps_cap: Option<u32>,
+    /// Whether to show the performance overlay. 
+    pub show_perf_overlay: bool,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        GraphicsSettings {
+            gpu: GpuPreference::Default,
+            fullscreen: FullscreenMode::Windowed,
+            vsync: false,
+            fov: Bounded::new(90.0),
+            gamma: Bounded::new(1.0),
+            render_distance: None,
+            fps_cap: None,
+            show_perf_overlay: false,
+        }
+    }
+}
+
+/// Which kind of GPU to prefer, if there are several. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuPreference {
+    /// Let the graphics backend decide. 
+    Default,
+    /// Prefer an integrated GPU. 
+    LowPower,
+    /// Prefer a discrete GPU. 
+    HighPerformance,
+}
+
+impl From<GpuPreference> for PowerPreference {
+    fn from(gpu: GpuPreference) -> Self {
+        match gpu {
+            GpuPreference::Default => PowerPreference::Default,
+            GpuPreference::LowPower => PowerPreference::LowPower,
+            GpuPreference::HighPerformance => PowerPreference::HighPerformance,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    /// Apply the settings which must be chosen before the renderer is built. 
+    pub fn apply_builder(&self, builder: &mut GraphicsBuilder) {
+        builder.power_preference(self.gpu.into());
+        builder.vsync(self.vsync);
+    }
+
+    /// Apply the settings which can be changed after the renderer is built. 
+    pub fn apply(&self, graphics: &mut Graphics) {
+        if graphics.fullscreen() != self.fullscreen {
+            graphics.set_fullscreen(self.fullscreen);
+        }
+        *graphics.cam_projection_mut() = Projection::Perspective {
+            fov: deg(self.fov.get()),
+        };
+        graphics.set_gamma(self.gamma.get());
+        graphics.set_render_distance(self.render_distance);
+        graphics.show_perf_overlay(self.show_perf_overlay);
+    }
+}
+
+/// Input settings. 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlSettings {
+    /// Camera rotation per pixel of mouse movement, in degrees. 
+    pub mouse_sensitivity: Bounded<f32, SensitivityBounds>,
+    /// Key bindings. 
+    pub keys: KeySettings,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        ControlSettings {
+            mouse_sensitivity: Bounded::new(0.1),
+            keys: KeySettings::default(),
+        }
+    }
+}
+
+/// Key bindings. 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeySettings {
+    pub move_forward: Key,
+    pub move_back: Key,
+    pub move_left: Key,
+    pub move_right: Key,
+    /// Fly up, or jump while walking. 
+    pub move_up: Key,
+    /// Fly down. 
+    pub move_down: Key,
+    /// Switch between flying and walking. 
+    pub toggle_walk: Key,
+    pub toggle_perf_overlay: Key,
+    /// Pause, or resume while paused. 
+    pub pause: Key,
+    /// Open the command console. 
+    pub console: Key,
+    /// Open or close the inspector GUI. 
+    pub toggle_inspector: Key,
+    /// Show or hide the profiler's flame graph. 
+    pub toggle_profiler: Key,
+    /// Select each hotbar slot. 
+    pub hotbar: [Key; 9],
+}
+
+impl Default for KeySettings {
+    fn default() -> Self {
+        KeySettings {
+            move_forward: Key::W,
+            move_back: Key::S,
+            move_left: Key::A,
+            move_right: Key::D,
+            move_up: Key::Space,
+            move_down: Key::LShift,
+            toggle_walk: Key::F,
+            toggle_perf_overlay: Key::F3,
+            pause: Key::Escape,
+            console: Key::Grave,
+            toggle_inspector: Key::F1,
+            toggle_profiler: Key::F2,
+            hotbar: [
+                Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+                Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+            ],
+        }
+    }
+}
+
+/// Bindings created from `KeySettings`. 
+#[derive(Copy, Clone, Debug)]
+pub struct Binds {
+    pub move_forward: AxisBind,
+    pub move_right: AxisBind,
+    pub move_up: AxisBind,
+    pub toggle_walk: KeyBind,
+    pub toggle_perf_overlay: KeyBind,
+    pub pause: KeyBind,
+    pub console: KeyBind,
+    pub toggle_inspector: KeyBind,
+    pub toggle_profiler: KeyBind,
+    pub hotbar: [KeyBind; 9],
+}
+
+impl KeySettings {
+    /// Bind these keys in an input manager. 
+    pub fn bind(&self, input: &mut InputManagerBuilder) -> Binds {
+        let hotbar = self.hotbar.map(|&key| input.bind(key));
+        Binds {
+            move_forward: input.bind_axis("move forward", self.move_back, self.move_forward),
+            move_right: input.bind_axis("move right", self.move_left, self.move_right),
+            move_up: input.bind_axis("move up", self.move_down, self.move_up),
+            toggle_walk: input.bind(self.toggle_walk),
+            toggle_perf_overlay: input.bind(self.toggle_perf_overlay),
+            pause: input.bind(self.pause),
+            console: input.bind(self.console),
+            toggle_inspector: input.bind(self.toggle_inspector),
+            toggle_profiler: input.bind(self.toggle_profiler),
+            hotbar,
+        }
+    }
+}
+
+impl Settings {
+    /// Parse settings from TOML. 
+    pub fn parse(toml: &str) -> Result<Self> {
+        toml::from_str(toml)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "invalid settings file"))
+    }
+
+    /// Read and parse a settings file. 
+    ///
+    /// If the file doesn't exist, the default settings are written to it,
+    /// so that there's a file to edit. 
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(toml) => Self::parse(&toml)
+                .wrap_err(|| pear!({path=path}, "failed to load settings")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                info!("creating default settings file at {:?}", path);
+                let settings = Settings::default();
+                settings.save(path)?;
+                Ok(settings)
+            }
+            Err(e) => Err(Error::from(e)
+                .wrap(pear!({path=path}, "failed to read settings file"))),
+        }
+    }
+
+    /// Write these settings to a file, replacing it atomically. 
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let toml = toml::to_string_pretty(self)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "failed to serialize settings"))?;
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, toml)
+            .and_then(|()| fs::rename(&tmp_path, path))
+            .map_err(Error::from)
+            .wrap_err(|| pear!({path=path}, "failed to write settings file"))
+    }
+}
+
+#[test]
+fn settings_test() {
+    // missing fields are defaulted
+    let settings = Settings::parse(r#"
+        [graphics]
+        fullscreen = "borderless"
+        render_distance = 8
+
+        [controls.keys]
+        move_forward = "Up"
+    "#).unwrap();
+    assert_eq!(settings.graphics.fullscreen, FullscreenMode::Borderless);
+    assert_eq!(settings.graphics.render_distance, Some(8));
+    assert_eq!(settings.graphics.fps_cap, None);
+    assert_eq!(settings.controls.keys.move_forward, Key::Up);
+    assert_eq!(settings.controls.keys.move_back, Key::S);
+    assert_eq!(settings.controls.mouse_sensitivity.get(), 0.1);
+
+    // saved settings load back the same
+    let toml = toml::to_string_pretty(&settings).unwrap();
+    assert_eq!(Settings::parse(&toml).unwrap(), settings);
+
+    assert!(Settings::parse("[graphics]\nfov = \"wide\"").is_err());
+    assert!(Settings::parse("[graphics]\nfov = 400.0").is_err());
+    assert!(Settings::parse("[controls]\nmouse_sensitivity = 0.0").is_err());
+    assert_eq!(Settings::parse("[graphics]\nfov = 150.0").unwrap().graphics.fov.get(), 150.0);
+    assert!(Settings::parse("[controls.keys]\nmove_up = \"NotAKey\"").is_err());
+}
@@ -0,0 +1,261 @@
+//! Simple mob behavior: wandering around on the ground, and spawning and
+//! despawning around a point. 
+
+use super::{
+    Ecs,
+    Entity,
+    components::*,
+};
+use crate::{
+    graphics::ModelId,
+    util::physics::VoxelGrid,
+};
+use std::f32::consts::PI;
+use rand::Rng;
+use vek::{Vec2, Vec3, Quaternion};
+
+/// Behavior of wandering randomly, which alternates between walking in a
+/// random direction and standing still. 
+///
+/// Entities also need a `Transform`, `Velocity`, and `Collider`, which
+/// `wander_system` uses to walk along the ground and jump up single-block
+/// steps. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Wander {
+    /// Walking speed, in blocks per second. 
+    pub speed: f32,
+    /// Upwards speed when jumping up a step. 
+    pub jump_speed: f32,
+    /// Current horizontal walking direction, or zero while standing still. 
+    pub heading: Vec2<f32>,
+    /// Seconds until a new heading is chosen. 
+    pub timer: f32,
+}
+
+impl Wander {
+    /// Construct, standing still, with a given walking speed. 
+    pub fn new(speed: f32) -> Self {
+        Wander {
+            speed,
+            jump_speed: 9.0,
+            heading: Vec2::zero(),
+            timer: 0.0,
+        }
+    }
+}
+
+/// Marker for entities which were spawned by a `MobSpawner`. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mob;
+
+/// Update wandering entities' headings, velocities, and orientations, and
+/// make them jump when walking into a step they can climb. 
+pub fn wander_system<W, R>(ecs: &mut Ecs, world: &W, rng: &mut R, dt: f32)
+where
+    W: VoxelGrid,
+    R: Rng,
+{
+    ecs.register::<Wander>();
+    ecs.register::<Transform>();
+    ecs.register::<Velocity>();
+    ecs.register::<Collider>();
+
+    let mut wanders = ecs.borrow_mut::<Wander>();
+    let mut transforms = ecs.borrow_mut::<Transform>();
+    let mut velocities = ecs.borrow_mut::<Velocity>();
+    let colliders = ecs.borrow::<Collider>();
+    for (entity, wander) in wanders.iter_mut() {
+        let (transform, velocity, collider) = match (
+            transforms.get_mut(entity),
+            velocities.get_mut(entity),
+            colliders.get(entity),
+        ) {
+            (Some(transform), Some(velocity), Some(collider)) => {
+                (transform, velocity, collider)
+            }
+            _ => continue,
+        };
+
+        // pick a new heading, sometimes standing still
+        wander.timer -= dt;
+        if wander.timer <= 0.0 {
+            wander.timer = rng.gen_range(1.0, 4.0);
+            wander.heading = if rng.gen_bool(0.3) {
+                Vec2::zero()
+            } else {
+                let angle = rng.gen_range(0.0, 2.0 * PI);
+                Vec2::new(angle.cos(), angle.sin())
+            };
+        }
+        velocity.0.x = wander.heading.x * wander.speed;
+        velocity.0.z = wander.heading.y * wander.speed;
+
+        if wander.heading == Vec2::zero() {
+            continue;
+        }
+
+        // face the walking direction, where the model faces +Z
+        let yaw = wander.heading.x.atan2(wander.heading.y);
+        transform.rot = Quaternion::rotation_y(yaw);
+
+        // jump up steps one block high
+        if collider.on_ground {
+            let reach = collider.size.x.max(collider.size.z) / 2.0 + 0.2;
+            let ahead = transform.pos
+                + Vec3::new(wander.heading.x, 0.0, wander.heading.y) * reach;
+            let foot = Vec3::new(
+                ahead.x.floor() as i32,
+                (transform.pos.y + 0.01).floor() as i32,
+                ahead.z.floor() as i32,
+            );
+            let height = collider.size.y.ceil() as i32;
+            let blocked = world.is_solid(foot);
+            let clear = (1..=height).all(|dy| !world.is_solid(foot + Vec3::new(0, dy, 0)));
+            if blocked && clear {
+                velocity.0.y = wander.jump_speed;
+            }
+        }
+    }
+}
+
+/// Spawns wandering mobs around a center point, such as the player, and
+/// despawns them once they're far enough away. 
+#[derive(Debug, Clone)]
+pub struct MobSpawner {
+    /// Model to draw mobs as. 
+    pub model: ModelId,
+    /// Maximum number of mobs at once. 
+    pub max_mobs: usize,
+    /// Maximum horizontal distance from the center to spawn mobs at. 
+    pub spawn_radius: f32,
+    /// Distance from the center beyond which mobs are despawned. 
+    pub despawn_radius: f32,
+    /// Seconds between spawn attempts. 
+    pub spawn_interval: f32,
+    /// Size of the mobs' collision boxes. 
+    pub size: Vec3<f32>,
+    timer: f32,
+}
+
+impl MobSpawner {
+    /// Construct with default parameters. 
+    pub fn new(model: ModelId) -> Self {
+        MobSpawner {
+            model,
+            max_mobs: 10,
+            spawn_radius: 24.0,
+            despawn_radius: 48.0,
+            spawn_interval: 1.0,
+            size: Vec3::new(0.8, 0.8, 0.8),
+            timer: 0.0,
+        }
+    }
+
+    /// Despawn far away mobs, and try to spawn a new one if it's time to. 
+    pub fn update<W, R>(
+        &mut self,
+        ecs: &mut Ecs,
+        world: &W,
+        rng: &mut R,
+        center: Vec3<f32>,
+        dt: f32,
+    )
+    where
+        W: VoxelGrid,
+        R: Rng,
+    {
+        ecs.register::<Mob>();
+        ecs.register::<Transform>();
+
+        // despawn
+        let far = {
+            let mobs = ecs.borrow::<Mob>();
+            let transforms = ecs.borrow::<Transform>();
+            mobs.iter()
+                .map(|(entity, _)| entity)
+                .filter(|&entity| transforms.get(entity)
+                    .map(|transform| {
+                        transform.pos.distance_squared(center)
+                            > self.despawn_radius * self.despawn_radius
+                    })
+                    .unwrap_or(true))
+                .collect::<Vec<Entity>>()
+        };
+        for entity in far {
+            ecs.despawn(entity);
+        }
+
+        // spawn
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return;
+        }
+        self.timer = self.spawn_interval;
+        if ecs.borrow::<Mob>().len() >= self.max_mobs {
+            return;
+        }
+        let offset = Vec2::new(
+            rng.gen_range(-self.spawn_radius, self.spawn_radius),
+            rng.gen_range(-self.spawn_radius, self.spawn_radius),
+        );
+        let column = (Vec2::new(center.x, center.z) + offset).map(|n| n.floor() as i32);
+        let height = self.size.y.ceil() as i32;
+        let top = center.y.floor() as i32 + self.spawn_radius as i32;
+        let bottom = center.y.floor() as i32 - self.spawn_radius as i32;
+        let ground = (bottom..=top).rev().find(|&y| {
+            world.is_solid(Vec3::new(column.x, y, column.y))
+                && (1..=height).all(|dy| !world.is_solid(Vec3::new(column.x, y + dy, column.y)))
+        });
+        if let Some(y) = ground {
+            let pos = Vec3::new(column.x as f32 + 0.5, (y + 1) as f32, column.y as f32 + 0.5);
+            self.spawn(ecs, pos);
+        }
+    }
+
+    /// Spawn a mob at a position. 
+    pub fn spawn(&self, ecs: &mut Ecs, pos: Vec3<f32>) -> Entity {
+        let entity = ecs.spawn();
+        ecs.insert(entity, Mob);
+        ecs.insert(entity, Transform::from_pos(pos));
+        ecs.insert(entity, Velocity::default());
+        ecs.insert(entity, Collider::new(self.size));
+        ecs.insert(entity, Renderable { model: self.model });
+        ecs.insert(entity, Wander::new(2.0));
+        entity
+    }
+}
+
+#[test]
+fn wander_step_up_test() {
+    use super::systems::collision_system;
+    use rand::SeedableRng;
+
+    // a floor, with a one-block step at x >= 2
+    let world = |block: Vec3<i32>| block.y < 0 || (block.x >= 2 && block.y == 0);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut ecs = Ecs::new();
+    let mut spawner = MobSpawner::new(ModelId(0));
+    let mob = spawner.spawn(&mut ecs, Vec3::new(0.5, 0.0, 0.5));
+    ecs.insert(mob, Wander {
+        heading: Vec2::new(1.0, 0.0),
+        timer: 100.0,
+        ..Wander::new(2.0)
+    });
+
+    for _ in 0..120 {
+        wander_system(&mut ecs, &world, &mut rng, 1.0 / 60.0);
+        collision_system(&mut ecs, &world, 1.0 / 60.0);
+    }
+    let pos = ecs.get::<Transform>(mob).unwrap().pos;
+    assert!(pos.x > 2.5, "{:?}", pos);
+    assert!((pos.y - 1.0).abs() < 1e-3, "{:?}", pos);
+
+    // far away mobs are despawned, and new ones spawn on the ground
+    spawner.max_mobs = 1;
+    spawner.update(&mut ecs, &world, &mut rng, Vec3::new(1000.0, 0.0, 0.0), 1.0);
+    assert!(!ecs.is_alive(mob));
+    let mob = ecs.borrow::<Mob>().iter().next().map(|(entity, _)| entity).unwrap();
+    let pos = ecs.get::<Transform>(mob).unwrap().pos;
+    assert!(pos.y == 0.0 || pos.y == 1.0, "{:?}", pos);
+    assert!((pos.x - 1000.0).abs() <= spawner.spawn_radius + 1.0);
+}
@@ -0,0 +1,78 @@
+//! Common component types. 
+
+use crate::graphics::ModelId;
+use vek::{Vec3, Mat4, Quaternion};
+
+/// Position, orientation, and scale of an entity. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    /// Position. For entities with a `Collider`, this is the bottom center of
+    /// the collision box. 
+    pub pos: Vec3<f32>,
+    /// Orientation. 
+    pub rot: Quaternion<f32>,
+    /// Scale along each axis, applied before rotation. 
+    pub scale: Vec3<f32>,
+}
+
+impl Transform {
+    /// Construct at a position, with no rotation or scaling. 
+    pub fn from_pos(pos: Vec3<f32>) -> Self {
+        Transform {
+            pos,
+            rot: Quaternion::identity(),
+            scale: Vec3::one(),
+        }
+    }
+
+    /// Get the transformation matrix from model space to world space. 
+    pub fn to_mat(&self) -> Mat4<f32> {
+        let rot: Mat4<f32> = self.rot.into();
+        Mat4::<f32>::translation_3d(self.pos) * rot * Mat4::<f32>::scaling_3d(self.scale)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::from_pos(Vec3::zero())
+    }
+}
+
+/// Linear velocity of an entity, in blocks per second. 
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Velocity(pub Vec3<f32>);
+
+/// Model which an entity is drawn as, transformed by its `Transform`. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Renderable {
+    pub model: ModelId,
+}
+
+/// Box which an entity collides with blocks as, and which falls under
+/// gravity. 
+///
+/// Entities with a collider are moved by `collision_system` rather than
+/// `movement_system`. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Collider {
+    /// Size of the box. 
+    pub size: Vec3<f32>,
+    /// Downwards acceleration, in blocks per second squared. 
+    pub gravity: f32,
+    /// Maximum downwards speed, in blocks per second. 
+    pub max_fall_speed: f32,
+    /// Whether the entity was resting on a solid block after the last move. 
+    pub on_ground: bool,
+}
+
+impl Collider {
+    /// Construct with a given size and default gravity. 
+    pub fn new(size: Vec3<f32>) -> Self {
+        Collider {
+            size,
+            gravity: 32.0,
+            max_fall_speed: 60.0,
+            on_ground: false,
+        }
+    }
+}
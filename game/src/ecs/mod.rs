@@ -0,0 +1,354 @@
+//! Minimal entity-component system, for objects in the world which aren't
+//! blocks. 
+//!
+//! Entities are generational indices, so a despawned entity's handle never
+//! refers to an entity later spawned in its slot. Each component type is
+//! stored in its own `Storage`, which is borrowed dynamically, so a system can
+//! mutably borrow some component types while sharing others. 
+
+use std::{
+    any::{Any, TypeId},
+    cell::{RefCell, Ref, RefMut},
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+};
+
+pub mod components;
+pub mod systems;
+pub mod ai;
+
+/// Handle to an entity. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// Index of the entity's slot, which is reused after it's despawned. 
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Pack into a single integer, unique among all entities ever spawned
+    /// from the same `Ecs`, such as for use as a graphics instance ID. 
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    /// Unpack from `to_bits`. 
+    pub fn from_bits(bits: u64) -> Self {
+        Entity {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+/// Components of a single type, by entity. 
+pub struct Storage<T> {
+    // indexed by entity index
+    slots: Vec<Option<(u32, T)>>,
+    len: usize,
+}
+
+impl<T> Storage<T> {
+    fn new() -> Self {
+        Storage {
+            slots: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Get an entity's component, if it has one. 
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.slots.get(entity.index as usize) {
+            Some(&Some((generation, ref comp))) if generation == entity.generation => Some(comp),
+            _ => None,
+        }
+    }
+
+    /// Mutably get an entity's component, if it has one. 
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.slots.get_mut(entity.index as usize) {
+            Some(&mut Some((generation, ref mut comp))) if generation == entity.generation => {
+                Some(comp)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether an entity has this component. 
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    /// Number of entities with this component. 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no entities have this component. 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over entities and their components. 
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item=(Entity, &'s T)> + 's {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.as_ref().map(|&(generation, ref comp)| {
+                (Entity { index: index as u32, generation }, comp)
+            })
+        })
+    }
+
+    /// Iterate over entities and their components, mutably. 
+    pub fn iter_mut<'s>(&'s mut self) -> impl Iterator<Item=(Entity, &'s mut T)> + 's {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            slot.as_mut().map(|&mut (generation, ref mut comp)| {
+                (Entity { index: index as u32, generation }, comp)
+            })
+        })
+    }
+
+    fn insert(&mut self, entity: Entity, comp: T) -> Option<T> {
+        let i = entity.index as usize;
+        if self.slots.len() <= i {
+            self.slots.resize_with(i + 1, || None);
+        }
+        let old = self.slots[i].replace((entity.generation, comp));
+        match old {
+            Some((generation, old)) if generation == entity.generation => Some(old),
+            _ => {
+                // a component left by a previous entity in this slot is
+                // never observable, since despawning removes components
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slots.get_mut(entity.index as usize)?;
+        match *slot {
+            Some((generation, _)) if generation == entity.generation => {
+                self.len -= 1;
+                slot.take().map(|(_, comp)| comp)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T: Debug> Debug for Storage<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Type-erased component storage. 
+trait AnyStorage {
+    fn remove_entity(&self, entity: Entity);
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: 'static> AnyStorage for RefCell<Storage<T>> {
+    fn remove_entity(&self, entity: Entity) {
+        self.borrow_mut().remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Collection of entities and their components. 
+#[derive(Default)]
+pub struct Ecs {
+    // generation of each slot, and whether it's alive
+    generations: Vec<(u32, bool)>,
+    free: Vec<u32>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    despawned: Vec<Entity>,
+}
+
+impl Ecs {
+    /// Construct with no entities. 
+    pub fn new() -> Self {
+        Ecs::default()
+    }
+
+    /// Create a new entity with no components. 
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.generations[index as usize];
+                slot.0 += 1;
+                slot.1 = true;
+                Entity {
+                    index,
+                    generation: slot.0,
+                }
+            }
+            None => {
+                self.generations.push((0, true));
+                Entity {
+                    index: self.generations.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Destroy an entity and all its components. Return whether it was
+    /// alive. 
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        for storage in self.storages.values() {
+            storage.remove_entity(entity);
+        }
+        self.generations[entity.index as usize].1 = false;
+        self.free.push(entity.index);
+        self.despawned.push(entity);
+        true
+    }
+
+    /// Whether an entity has been spawned and not yet despawned. 
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&(entity.generation, true))
+    }
+
+    /// Number of living entities. 
+    pub fn len(&self) -> usize {
+        self.generations.len() - self.free.len()
+    }
+
+    /// Whether there are no living entities. 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the list of entities despawned since the last call, so that
+    /// resources associated with them elsewhere, such as model instances,
+    /// can be cleaned up. 
+    pub fn take_despawned(&mut self) -> Vec<Entity> {
+        std::mem::replace(&mut self.despawned, Vec::new())
+    }
+
+    /// Give an entity a component, and return the component of that type
+    /// which it had before. 
+    ///
+    /// Panics if the entity isn't alive. 
+    pub fn insert<T: 'static>(&mut self, entity: Entity, comp: T) -> Option<T> {
+        assert!(self.is_alive(entity), "insert component into dead entity");
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(RefCell::new(Storage::<T>::new())));
+        self.borrow_mut::<T>().insert(entity, comp)
+    }
+
+    /// Remove a component from an entity, and return it. 
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        if self.storages.contains_key(&TypeId::of::<T>()) {
+            self.borrow_mut::<T>().remove(entity)
+        } else {
+            None
+        }
+    }
+
+    /// Get a copy of an entity's component, if it has one. 
+    ///
+    /// Panics if that component storage is mutably borrowed. 
+    pub fn get<T: Clone + 'static>(&self, entity: Entity) -> Option<T> {
+        self.storage::<T>().and_then(|storage| storage.borrow().get(entity).cloned())
+    }
+
+    /// Borrow the storage of a component type. 
+    ///
+    /// Panics if that component storage is mutably borrowed, or no entity
+    /// has ever had that component. 
+    pub fn borrow<T: 'static>(&self) -> Ref<'_, Storage<T>> {
+        self.storage::<T>()
+            .expect("no storage for component type")
+            .borrow()
+    }
+
+    /// Mutably borrow the storage of a component type. 
+    ///
+    /// Panics if that component storage is borrowed, or no entity has ever
+    /// had that component. 
+    pub fn borrow_mut<T: 'static>(&self) -> RefMut<'_, Storage<T>> {
+        self.storage::<T>()
+            .expect("no storage for component type")
+            .borrow_mut()
+    }
+
+    /// Ensure that storage exists for a component type, so that it can be
+    /// borrowed even if no entity has had that component. 
+    pub fn register<T: 'static>(&mut self) {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(RefCell::new(Storage::<T>::new())));
+    }
+
+    fn storage<T: 'static>(&self) -> Option<&RefCell<Storage<T>>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .map(|storage| storage.as_any().downcast_ref().unwrap())
+    }
+}
+
+impl Debug for Ecs {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Ecs")
+            .field("entities", &self.len())
+            .field("component_types", &self.storages.len())
+            .finish()
+    }
+}
+
+#[test]
+fn ecs_test() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn();
+    let b = ecs.spawn();
+    assert_eq!(ecs.insert(a, 1u32), None);
+    assert_eq!(ecs.insert(a, 2u32), Some(1));
+    ecs.insert(b, 3u32);
+    ecs.insert(b, "b");
+
+    assert_eq!(ecs.get::<u32>(a), Some(2));
+    assert_eq!(ecs.get::<&str>(a), None);
+    assert_eq!(ecs.borrow::<u32>().len(), 2);
+
+    // different component types can be borrowed at once
+    {
+        let mut numbers = ecs.borrow_mut::<u32>();
+        let names = ecs.borrow::<&str>();
+        for (entity, _) in names.iter() {
+            *numbers.get_mut(entity).unwrap() += 10;
+        }
+    }
+    assert_eq!(ecs.get::<u32>(b), Some(13));
+
+    // despawned entities lose their components, and stale handles don't
+    // refer to entities later spawned in the same slot
+    assert!(ecs.despawn(b));
+    assert!(!ecs.despawn(b));
+    assert_eq!(ecs.borrow::<u32>().len(), 1);
+    let c = ecs.spawn();
+    assert_eq!(c.index(), b.index());
+    assert!(!ecs.is_alive(b));
+    ecs.insert(c, 5u32);
+    assert_eq!(ecs.get::<u32>(b), None);
+    assert_eq!(ecs.get::<u32>(c), Some(5));
+    assert_eq!(ecs.take_despawned(), vec![b]);
+    assert!(ecs.take_despawned().is_empty());
+
+    assert_eq!(Entity::from_bits(c.to_bits()), c);
+    assert_eq!(ecs.remove::<u32>(a), Some(2));
+    assert_eq!(ecs.len(), 2);
+}
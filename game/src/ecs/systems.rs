@@ -0,0 +1,116 @@
+//! Systems which update common components. 
+
+use super::{
+    Ecs,
+    components::*,
+};
+use crate::{
+    graphics::Graphics,
+    util::physics::{KinematicBody, VoxelGrid},
+};
+
+/// Move entities which have a velocity but no collider. 
+pub fn movement_system(ecs: &mut Ecs, dt: f32) {
+    ecs.register::<Transform>();
+    ecs.register::<Velocity>();
+    ecs.register::<Collider>();
+
+    let mut transforms = ecs.borrow_mut::<Transform>();
+    let velocities = ecs.borrow::<Velocity>();
+    let colliders = ecs.borrow::<Collider>();
+    for (entity, &Velocity(vel)) in velocities.iter() {
+        if colliders.contains(entity) {
+            continue;
+        }
+        if let Some(transform) = transforms.get_mut(entity) {
+            transform.pos += vel * dt;
+        }
+    }
+}
+
+/// Move entities which have a collider by their velocity and gravity,
+/// stopping at solid blocks. 
+pub fn collision_system<W: VoxelGrid>(ecs: &mut Ecs, world: &W, dt: f32) {
+    ecs.register::<Transform>();
+    ecs.register::<Velocity>();
+    ecs.register::<Collider>();
+
+    let mut transforms = ecs.borrow_mut::<Transform>();
+    let mut velocities = ecs.borrow_mut::<Velocity>();
+    let mut colliders = ecs.borrow_mut::<Collider>();
+    for (entity, collider) in colliders.iter_mut() {
+        let (transform, velocity) = match (
+            transforms.get_mut(entity),
+            velocities.get_mut(entity),
+        ) {
+            (Some(transform), Some(velocity)) => (transform, velocity),
+            _ => continue,
+        };
+
+        let mut body = KinematicBody {
+            pos: transform.pos,
+            size: collider.size,
+            vel: velocity.0,
+            gravity: collider.gravity,
+            max_fall_speed: collider.max_fall_speed,
+            on_ground: collider.on_ground,
+        };
+        body.move_and_slide(world, dt);
+        transform.pos = body.pos;
+        velocity.0 = body.vel;
+        collider.on_ground = body.on_ground;
+    }
+}
+
+/// Update model instances to match renderable entities, and remove the
+/// instances of despawned entities. 
+///
+/// Entities' model instance IDs are their `Entity::to_bits`. 
+pub fn render_system(ecs: &mut Ecs, graphics: &mut Graphics) {
+    ecs.register::<Transform>();
+    ecs.register::<Renderable>();
+
+    for entity in ecs.take_despawned() {
+        graphics.remove_model_instance(entity.to_bits());
+    }
+
+    let transforms = ecs.borrow::<Transform>();
+    let renderables = ecs.borrow::<Renderable>();
+    for (entity, renderable) in renderables.iter() {
+        match transforms.get(entity) {
+            Some(transform) => graphics.set_model_instance(
+                entity.to_bits(),
+                renderable.model,
+                transform.to_mat(),
+            ),
+            None => graphics.remove_model_instance(entity.to_bits()),
+        }
+    }
+}
+
+#[test]
+fn collision_system_test() {
+    use vek::Vec3;
+
+    let floor = |block: Vec3<i32>| block.y < 0;
+    let mut ecs = Ecs::new();
+    let falling = ecs.spawn();
+    ecs.insert(falling, Transform::from_pos(Vec3::new(0.5, 10.0, 0.5)));
+    ecs.insert(falling, Velocity(Vec3::new(1.0, 0.0, 0.0)));
+    ecs.insert(falling, Collider::new(Vec3::new(0.8, 0.8, 0.8)));
+    let flying = ecs.spawn();
+    ecs.insert(flying, Transform::from_pos(Vec3::new(0.5, 10.0, 0.5)));
+    ecs.insert(flying, Velocity(Vec3::new(0.0, -1.0, 0.0)));
+
+    for _ in 0..60 {
+        movement_system(&mut ecs, 1.0 / 60.0);
+        collision_system(&mut ecs, &floor, 1.0 / 60.0);
+    }
+
+    let falling_pos = ecs.get::<Transform>(falling).unwrap().pos;
+    assert!(falling_pos.y.abs() < 1e-4);
+    assert!((falling_pos.x - 1.5).abs() < 1e-3);
+    assert!(ecs.get::<Collider>(falling).unwrap().on_ground);
+    let flying_pos = ecs.get::<Transform>(flying).unwrap().pos;
+    assert!((flying_pos.y - 9.0).abs() < 1e-3);
+}
@@ -0,0 +1,133 @@
+//! `Blit` subsystem factory. 
+
+use super::*;
+
+/// `Blit` subsystem factory. 
+pub struct BlitBuilder {
+    source: RenderTargetId,
+}
+
+impl BlitBuilder {
+    /// Create a new `BlitBuilder` which copies from `source`. 
+    pub fn new(source: RenderTargetId) -> Self {
+        BlitBuilder {
+            source,
+        }
+    }
+
+    /// Attempt to initialize the `Blit` subsystem.  
+    pub fn build(&self, gfx: &mut Graphics) -> Result<Blit> {
+        let sampler = gfx.device
+            .create_sampler(&SamplerDescriptor {
+                label: label("blit sampler"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                lod_min_clamp: -100.0,
+                lod_max_clamp: 100.0,
+                compare: None,
+                anisotropy_clamp: None,
+            });
+
+        // shaders
+        let vert_module = gfx.create_shader_module(
+            "blit/shader.vert.spv",
+            include_shader!("shader.vert.spv"),
+        );
+        let frag_module = gfx.create_shader_module(
+            "blit/shader.frag.spv",
+            include_shader!("shader.frag.spv"),
+        );
+
+        // binding and pipeline
+        let bind_group_layout = gfx.device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: label("blit bind group layout"),
+                entries: cowslice![
+                    // source texture
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStage::FRAGMENT,
+                        ty: BindingType::SampledTexture {
+                            dimension: TextureViewDimension::D2,
+                            component_type: TextureComponentType::Float,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    // source sampler
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStage::FRAGMENT,
+                        ty: BindingType::Sampler {
+                            comparison: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let source = gfx.render_target(self.source);
+        let bind_group = create_bind_group(
+            &gfx.device,
+            &bind_group_layout,
+            source.color_view(),
+            &sampler,
+        );
+        let bind_group_generation = source.generation();
+        let pipeline_layout = gfx.device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                bind_group_layouts: cowslice![&bind_group_layout],
+                push_constant_ranges: cowslice![],
+            });
+        let pipeline = gfx.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                layout: &pipeline_layout,
+                vertex_stage: ProgrammableStageDescriptor {
+                    module: &vert_module,
+                    entry_point: cowstr("main"),
+                },
+                fragment_stage: Some(ProgrammableStageDescriptor {
+                    module: &frag_module,
+                    entry_point: cowstr("main"),
+                }),
+                rasterization_state: Some(RasterizationStateDescriptor {
+                    front_face: FrontFace::Ccw,
+                    cull_mode: CullMode::None,
+                    clamp_depth: false,
+                    depth_bias: 0,
+                    depth_bias_slope_scale: 0.0,
+                    depth_bias_clamp: 0.0,
+                }),
+                primitive_topology: PrimitiveTopology::TriangleList,
+                color_states: cowslice![
+                    ColorStateDescriptor {
+                        format: SWAPCHAIN_FMT,
+                        color_blend: BlendDescriptor::REPLACE,
+                        alpha_blend: BlendDescriptor::REPLACE,
+                        write_mask: ColorWrite::ALL,
+                    },
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    // there are no vertex buffers at all
+                    index_format: IndexFormat::Uint16,
+                    vertex_buffers: cowslice![],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        Ok(Blit {
+            source: self.source,
+            pipeline,
+            bind_group_layout,
+            sampler,
+            bind_group,
+            bind_group_generation,
+        })
+    }
+}
@@ -0,0 +1,84 @@
+//! Graphics subsystem for copying a render target onto the screen. 
+
+use super::*;
+use crate::graphics::render_graph::{
+    PassDecl,
+    RenderTargetId,
+};
+
+pub mod builder;
+
+/// Graphics subsystem for copying a render target onto the screen. 
+pub struct Blit {
+    source: RenderTargetId,
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    bind_group: BindGroup,
+    // render target generation which `bind_group` was created for
+    bind_group_generation: u64,
+}
+
+impl RenderNode for Blit {
+    fn name(&self) -> &'static str {
+        "blit"
+    }
+
+    fn pass_decl(&self) -> PassDecl {
+        PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            reads: vec![Attachment::TargetColor(self.source)],
+            ..Default::default()
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        gfx: &Graphics,
+        _uploader: &mut Uploader,
+        _command_encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        // re-bind the source if it was re-created
+        let source = gfx.render_target(self.source);
+        if source.generation() != self.bind_group_generation {
+            self.bind_group = create_bind_group(
+                &gfx.device,
+                &self.bind_group_layout,
+                source.color_view(),
+                &self.sampler,
+            );
+            self.bind_group_generation = source.generation();
+        }
+        Ok(())
+    }
+
+    fn render<'a>(&'a self, _gfx: &'a Graphics, pass: &mut RenderPass<'a>) -> Result<()> {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    source: &TextureView,
+    sampler: &Sampler,
+) -> BindGroup {
+    device
+        .create_bind_group(&BindGroupDescriptor {
+            label: label("blit bind group"),
+            layout,
+            entries: cowslice![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+}
@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform texture2D u_Source;
+layout(set = 0, binding = 1) uniform sampler u_Sampler;
+
+void main() {
+    o_Target = texture(sampler2D(u_Source, u_Sampler), v_TexCoord);
+}
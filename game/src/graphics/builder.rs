@@ -1,16 +1,51 @@
 //! Game renderer factory. 
 
 use super::*;
-use crate::graphics::draw_blocks::builder::DrawBlocksBuilder;
+use crate::graphics::{
+    draw_blocks::builder::DrawBlocksBuilder,
+    draw_models::builder::DrawModelsBuilder,
+    draw_sky::builder::DrawSkyBuilder,
+    draw_sprites::builder::DrawSpritesBuilder,
+    draw_gui::builder::DrawGuiBuilder,
+    blit::builder::BlitBuilder,
+    util::{
+        render_target::RenderTargetSize,
+        headless::HeadlessOutput,
+    },
+};
+use crate::assets::Shader;
 use vek::*;
-use std::sync::{
-    mpsc,
-    Arc,
+use image::RgbaImage;
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc,
+        Arc,
+    },
+    path::Path,
 };
 
+/// Time of day that `Graphics` starts at, which is mid-morning. 
+const DEFAULT_TIME_OF_DAY: f32 = 0.35;
+/// Default length of a full day, in seconds. 
+const DEFAULT_DAY_LENGTH: f32 = 20.0 * 60.0;
+
 /// Game renderer factory. 
 pub struct GraphicsBuilder {
     draw_blocks: DrawBlocksBuilder,
+    draw_models: DrawModelsBuilder,
+    draw_sky: DrawSkyBuilder,
+    draw_sprites: DrawSpritesBuilder,
+    draw_gui: DrawGuiBuilder,
+    render_targets: Vec<(RenderTargetSize, bool)>,
+    blits: Vec<BlitBuilder>,
+    backends: BackendBit,
+    power_preference: PowerPreference,
+    adapter_filter: Option<Box<dyn Fn(&AdapterInfo) -> bool + Send>>,
+    reverse_z: bool,
+    vsync: bool,
+    // shaders loaded at runtime, by name
+    shaders: HashMap<String, Arc<Shader>>,
 }
 
 impl GraphicsBuilder {
@@ -18,9 +53,96 @@ impl GraphicsBuilder {
     pub fn new() -> Self {
         GraphicsBuilder {
             draw_blocks: DrawBlocksBuilder::new(),
+            draw_models: DrawModelsBuilder::new(),
+            draw_sky: DrawSkyBuilder::new(),
+            draw_sprites: DrawSpritesBuilder::new(),
+            draw_gui: DrawGuiBuilder::new(),
+            render_targets: Vec::new(),
+            blits: Vec::new(),
+            backends: BackendBit::PRIMARY,
+            power_preference: PowerPreference::Default,
+            adapter_filter: None,
+            reverse_z: false,
+            vsync: false,
+            shaders: HashMap::new(),
         }
     }
 
+    /// Set which graphics backends may be used. 
+    ///
+    /// By default, this is `BackendBit::PRIMARY`. This can be used to force 
+    /// a particular backend, such as DX12, Metal, or GL, for example to work 
+    /// around a broken Vulkan driver. 
+    pub fn backend(&mut self, backends: BackendBit) {
+        self.backends = backends;
+    }
+
+    /// Set whether to prefer a low power or high performance adapter, on 
+    /// systems with more than one. 
+    pub fn power_preference(&mut self, power_preference: PowerPreference) {
+        self.power_preference = power_preference;
+    }
+
+    /// Only use an adapter which `filter` accepts, such as to choose a 
+    /// specific device by name. 
+    ///
+    /// If multiple adapters are accepted, the power preference decides 
+    /// between them. Adapters are not checked for compatibility with the 
+    /// window's surface when a filter is set. 
+    pub fn adapter_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&AdapterInfo) -> bool + Send + 'static,
+    {
+        self.adapter_filter = Some(Box::new(filter));
+    }
+
+    /// Add an off-screen render target, which subsystems can render into. 
+    ///
+    /// The render target has a depth texture if `depth` is true. 
+    pub fn add_render_target(&mut self, size: RenderTargetSize, depth: bool) -> RenderTargetId {
+        let id = RenderTargetId(self.render_targets.len());
+        self.render_targets.push((size, depth));
+        id
+    }
+
+    /// Render blocks into an off-screen render target instead of the screen. 
+    ///
+    /// The render target must have a depth texture. 
+    pub fn draw_blocks_into(&mut self, target: RenderTargetId) {
+        assert!(self.render_targets[target.0].1, "render target has no depth texture");
+        self.draw_blocks.set_attachments(
+            Attachment::TargetColor(target),
+            Attachment::TargetDepth(target),
+        );
+    }
+
+    /// Render models into an off-screen render target instead of the screen. 
+    ///
+    /// The render target must have a depth texture. 
+    pub fn draw_models_into(&mut self, target: RenderTargetId) {
+        assert!(self.render_targets[target.0].1, "render target has no depth texture");
+        self.draw_models.set_attachments(
+            Attachment::TargetColor(target),
+            Attachment::TargetDepth(target),
+        );
+    }
+
+    /// Render the sky into an off-screen render target instead of the screen. 
+    ///
+    /// The render target must have a depth texture. 
+    pub fn draw_sky_into(&mut self, target: RenderTargetId) {
+        assert!(self.render_targets[target.0].1, "render target has no depth texture");
+        self.draw_sky.set_attachments(
+            Attachment::TargetColor(target),
+            Attachment::TargetDepth(target),
+        );
+    }
+
+    /// Add a pass which copies the color of a render target onto the screen. 
+    pub fn blit_to_screen(&mut self, source: RenderTargetId) {
+        self.blits.push(BlitBuilder::new(source));
+    }
+
     /// Add a block texture to the block texture array. Return its index. 
     ///
     /// The parameter, `bytes`, is the contents of an image file, such as PNG 
@@ -29,6 +151,94 @@ impl GraphicsBuilder {
         self.draw_blocks.add_block_texture(bytes)
     }
 
+    /// Add a named block texture to the block texture array. Return its 
+    /// index. 
+    ///
+    /// The index can later be looked up by name with 
+    /// `Graphics::block_texture_index`. 
+    pub fn add_block_texture_named(&mut self, name: &str, bytes: &[u8]) -> Result<u32> {
+        self.draw_blocks.add_block_texture_named(name, bytes)
+    }
+
+    /// Add every image file in a directory as a named block texture, named 
+    /// by its file stem. Return their indices. 
+    pub fn load_block_texture_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<u32>> {
+        self.draw_blocks.load_block_texture_dir(path)
+    }
+
+    /// Add the block textures listed in a texture manifest file, then 
+    /// resolve its per-face block texture assignments. 
+    ///
+    /// See the `texture_manifest` module for the file format. 
+    pub fn load_block_texture_manifest<P: AsRef<Path>>(&mut self, path: P) -> Result<FaceTextureMap> {
+        self.draw_blocks.load_block_texture_manifest(path)
+    }
+
+    /// Set whether to use a reverse-Z depth buffer, which greatly reduces 
+    /// z-fighting far from the camera. 
+    ///
+    /// See `Camera::reverse_z`. This is disabled by default. 
+    pub fn reverse_z(&mut self, reverse_z: bool) {
+        self.reverse_z = reverse_z;
+    }
+
+    /// Set whether to wait for vertical sync before presenting frames, 
+    /// which caps the frame rate at the monitor's refresh rate and prevents 
+    /// tearing. 
+    ///
+    /// This is disabled by default. 
+    pub fn vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
+    /// Load a glTF model. Return its ID. 
+    ///
+    /// The model must be self-contained, as either binary glTF or glTF with 
+    /// embedded buffers and images. 
+    pub fn add_model_gltf(&mut self, bytes: &[u8]) -> Result<ModelId> {
+        self.draw_models.add_model_gltf(bytes)
+    }
+
+    /// Load a Wavefront OBJ model, textured with an optional image file. 
+    /// Return its ID. 
+    pub fn add_model_obj(&mut self, bytes: &[u8], texture: Option<&[u8]>) -> Result<ModelId> {
+        self.draw_models.add_model_obj(bytes, texture)
+    }
+
+    /// Add an already-decoded model, such as one loaded with `Assets`. 
+    /// Return its ID. 
+    pub fn add_model(&mut self, model: ModelData) -> ModelId {
+        self.draw_models.add_model(model)
+    }
+
+    /// Add an already-decoded sprite texture, such as one loaded with
+    /// `Assets`, to the sprite texture array. Return its index. 
+    pub fn add_sprite_texture_image(&mut self, image: RgbaImage) -> u32 {
+        self.draw_sprites.add_sprite_texture_image(image)
+    }
+
+    /// Use a shader loaded at runtime, such as from disk with `Assets`, in
+    /// place of the built-in shader of the same name. 
+    ///
+    /// See `SHADERS` for the names of shaders. 
+    pub fn add_shader(&mut self, name: &str, shader: Arc<Shader>) {
+        self.shaders.insert(name.to_owned(), shader);
+    }
+
+    /// Replace the image of an existing block texture, so that it's kept if 
+    /// the subsystems are re-built after device loss. 
+    pub(super) fn replace_block_texture(&mut self, layer: u32, image: RgbaImage) {
+        self.draw_blocks.replace_block_texture(layer, image)
+    }
+
+    /// Add a sprite texture to the sprite texture array. Return its index. 
+    ///
+    /// The parameter, `bytes`, is the contents of an image file, such as PNG 
+    /// or JPEG. This makes an educated guess about which format it is.  
+    pub fn add_sprite_texture(&mut self, bytes: &[u8]) -> Result<u32> {
+        self.draw_sprites.add_sprite_texture(bytes)
+    }
+
     /// Attempt to construct a renderer. 
     /// 
     /// Once everything is initialized, the actual rendering should be done in 
@@ -36,40 +246,84 @@ impl GraphicsBuilder {
     pub fn build(self) -> Result<(Graphics, MainHijacker)> {
         let event_loop = EventLoop::new();
         let window = Window::new(&event_loop)?;
-        let instance = Instance::new(BackendBit::PRIMARY);
+        let instance = Instance::new(self.backends);
         let surface = unsafe {
             instance.create_surface(&window)
         };
-        let adapter = block_on({
-                instance.request_adapter(
-                    &RequestAdapterOptions {
-                        power_preference: PowerPreference::Default,
-                        compatible_surface: Some(&surface),
-                    })
-            })
-            .ok_or_else(|| pear!({}, "no graphics adapter found"))?;
-        let (device, queue) = block_on(
-            adapter.request_device(
-                &DeviceDescriptor {
-                    features: Features::empty(),
-                    shader_validation: false,
-                    limits: Limits::default(),
-                },
-                None)
-            )?;
+        let (adapter, device, queue) = self.request_device(&instance, Some(&surface))?;
         let window_size = window.inner_size();
         let swapchain_desc = SwapChainDescriptor {
             usage: TextureUsage::OUTPUT_ATTACHMENT,
             format: SWAPCHAIN_FMT,
             width: window_size.width,
             height: window_size.height,
-            present_mode: PresentMode::Mailbox,
+            present_mode: if self.vsync {
+                PresentMode::Fifo
+            } else {
+                PresentMode::Mailbox
+            },
         };
         let swapchain = device.create_swap_chain(&surface, &swapchain_desc);
+        let output = Output::Window {
+            window: Arc::new(window),
+            surface,
+            swapchain_desc,
+            swapchain,
+        };
+
+        let (events_send, events_recv) = mpsc::channel();
+        let gfx = self.assemble(
+            instance,
+            (adapter, device, queue),
+            output,
+            window_size,
+            events_recv,
+        )?;
+
+        let hijacker = MainHijacker {
+            event_loop,
+            events_send,
+            timestep: FixedTimestep::default(),
+        };
+        Ok((gfx, hijacker))
+    }
+
+    /// Attempt to construct a renderer which renders into an off-screen 
+    /// texture of the given size, rather than a window. 
+    ///
+    /// This does not need a display server, so it can be used in tests. 
+    /// Frames are drawn by calling `Graphics::draw` directly, and can then 
+    /// be read back with `Graphics::read_frame`. 
+    pub fn headless<E: Into<Extent2<u32>>>(self, size: E) -> Result<Graphics> {
+        let size = size.into();
+        let instance = Instance::new(self.backends);
+        let (adapter, device, queue) = self.request_device(&instance, None)?;
+        let output = Output::Headless(HeadlessOutput::new(&device, size, SWAPCHAIN_FMT));
+
+        // no window, so no events will ever be sent
+        let (_, events_recv) = mpsc::channel();
+        self.assemble(
+            instance,
+            (adapter, device, queue),
+            output,
+            PhysicalSize::new(size.w, size.h),
+            events_recv,
+        )
+    }
+
+    /// Construct the renderer and its subsystems around an output. 
+    fn assemble(
+        self,
+        instance: Instance,
+        (adapter, device, queue): (Adapter, Device, Queue),
+        output: Output,
+        window_size: PhysicalSize<u32>,
+        events_recv: mpsc::Receiver<WinitEvent>,
+    ) -> Result<Graphics> {
         let depth_texture_desc = TextureDescriptor {
             size: Extent3d {
-                width: swapchain_desc.width,
-                height: swapchain_desc.height,
+                width: window_size.width,
+                height: window_size.height,
                 depth: 1,
             },
             mip_level_count: 1,
@@ -81,43 +335,181 @@ impl GraphicsBuilder {
         };
         let depth_texture = device.create_texture(&depth_texture_desc);
         let depth_texture_view = depth_texture.create_default_view();
+        let render_targets = self.create_render_targets(&device, window_size);
         
-        let (events_send, events_recv) = mpsc::channel();
         let mut gfx = Graphics {
-            window: Arc::new(window),
-            surface,
+            instance,
+            output,
             adapter,
             device,
             queue,
-            swapchain_desc,
-            swapchain,
             depth_texture_desc,
             depth_texture_view,
             window_size,
             window_size_changed: false,
+            fullscreen: FullscreenMode::Windowed,
             events_recv,
-            cam: Camera::default(),
+            render_targets,
+            uploader: Some(Uploader::new()),
+            shaders: self.shaders.clone(),
+            cam: Camera {
+                reverse_z: self.reverse_z,
+                ..Default::default()
+            },
+            cam_effects: CameraEffects::new(),
+            view_cam: Default::default(),
+            time_of_day: DEFAULT_TIME_OF_DAY,
+            day_length: Some(DEFAULT_DAY_LENGTH),
+            last_draw: None,
+            gamma: 1.0,
+            clear_color: DEFAULT_CLEAR_COLOR,
+            chunks: ChunkTracker::new(),
+            chunk_event_sends: Vec::new(),
+            perf_overlay: None,
+            draw_calls: 0,
             subsystems: None,
+            render_graph: RenderGraph::default(),
+            builder: None,
         };
 
+        let subsystems = self.build_subsystems(&mut gfx)?;
+        gfx.render_graph = RenderGraph::compile(
+            subsystems.nodes()
+                .into_iter()
+                .map(|node| (node.name(), node.pass_decl()))
+        )?;
+        gfx.subsystems = Some(subsystems);
+        gfx.builder = Some(self);
+        Ok(gfx)
+    }
+
+    /// Create the off-screen render targets. 
+    pub(super) fn create_render_targets(
+        &self,
+        device: &Device,
+        window_size: PhysicalSize<u32>,
+    ) -> Vec<RenderTarget> {
+        self.render_targets
+            .iter()
+            .map(|&(size, depth)| RenderTarget::new(
+                device,
+                size,
+                Extent2::new(window_size.width, window_size.height),
+                SWAPCHAIN_FMT,
+                if depth { Some(DEPTH_FMT) } else { None },
+                label("render target"),
+            ))
+            .collect()
+    }
+
+    /// Build all subsystems, and submit their initialization commands. 
+    ///
+    /// This does not consume `self`, so that subsystems can be re-built from 
+    /// retained CPU-side data after device loss. 
+    pub(super) fn build_subsystems(&self, gfx: &mut Graphics) -> Result<Subsystems> {
         // command encoder for subsystems to load
         let mut command_encoder = gfx.device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: label("initialize command encoder"),
             });
-        let draw_blocks = self.draw_blocks.build(&mut gfx, &mut command_encoder)?;
+        let draw_blocks = self.draw_blocks.build(gfx, &mut command_encoder)?;
+        let draw_models = self.draw_models.build(gfx, &mut command_encoder)?;
+        let draw_sky = self.draw_sky.build(gfx)?;
+        let blits = self.blits
+            .iter()
+            .map(|blit| blit.build(gfx))
+            .collect::<Result<Vec<_>>>()?;
+        let draw_sprites = self.draw_sprites.build(gfx, &mut command_encoder)?;
+        let draw_gui = self.draw_gui.build(gfx)?;
 
         gfx.queue.submit(once(command_encoder.finish()));
 
-        let subsystems = Subsystems {
+        Ok(Subsystems {
             draw_blocks,
-        };
-        gfx.subsystems = Some(subsystems);
+            draw_models,
+            draw_sky,
+            blits,
+            draw_sprites,
+            draw_gui,
+        })
+    }
 
-        let hijacker = MainHijacker {
-            event_loop,
-            events_send,
+    /// Select an adapter, then request a device and queue. 
+    ///
+    /// Unless there is an adapter filter, the adapter must be compatible 
+    /// with `surface`, if any. 
+    pub(super) fn request_device(
+        &self,
+        instance: &Instance,
+        surface: Option<&Surface>,
+    ) -> Result<(Adapter, Device, Queue)> {
+        let adapter = match self.adapter_filter {
+            Some(ref filter) => instance
+                .enumerate_adapters(self.backends)
+                .filter(|adapter| filter(&adapter.get_info()))
+                .min_by_key(|adapter| power_rank(
+                    self.power_preference,
+                    adapter.get_info().device_type,
+                )),
+            None => block_on({
+                instance.request_adapter(
+                    &RequestAdapterOptions {
+                        power_preference: self.power_preference,
+                        compatible_surface: surface,
+                    })
+            }),
         };
-        Ok((gfx, hijacker))
+        let adapter = adapter
+            .ok_or_else(|| pear!(
+                { backends = self.backends },
+                "no graphics adapter found",
+            ))?;
+        let info = adapter.get_info();
+        info!(
+            name = %info.name,
+            vendor = info.vendor,
+            device = info.device,
+            device_type = ?info.device_type,
+            backend = ?info.backend,
+            "using graphics adapter"
+        );
+
+        // push constants are optional, subsystems fall back to uniform buffers
+        let (features, limits) =
+            if adapter.features().contains(Features::PUSH_CONSTANTS) {
+                debug!("push constants supported");
+                let limits = Limits {
+                    max_push_constant_size: adapter.limits().max_push_constant_size,
+                    ..Limits::default()
+                };
+                (Features::PUSH_CONSTANTS, limits)
+            } else {
+                debug!("push constants not supported");
+                (Features::empty(), Limits::default())
+            };
+        let (device, queue) = block_on(
+            adapter.request_device(
+                &DeviceDescriptor {
+                    features,
+                    shader_validation: false,
+                    limits,
+                },
+                None)
+            )?;
+        Ok((adapter, device, queue))
     }
-}
\ No newline at end of file
+}
+
+/// How preferable an adapter of a given type is, lower being better. 
+fn power_rank(power_preference: PowerPreference, device_type: DeviceType) -> u8 {
+    match (power_preference, device_type) {
+        (PowerPreference::Default, _) => 0,
+        (PowerPreference::HighPerformance, DeviceType::DiscreteGpu) => 0,
+        (PowerPreference::HighPerformance, DeviceType::IntegratedGpu) => 1,
+        (PowerPreference::LowPower, DeviceType::IntegratedGpu) => 0,
+        (PowerPreference::LowPower, DeviceType::DiscreteGpu) => 1,
+        (_, DeviceType::VirtualGpu) => 2,
+        (_, DeviceType::Cpu) => 3,
+        (_, DeviceType::Other) => 4,
+    }
+}
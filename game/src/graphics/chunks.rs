@@ -0,0 +1,199 @@
+//! Render distance management, which tracks which chunks of blocks are
+//! within range of the camera.
+
+use std::collections::{HashMap, HashSet};
+use vek::*;
+
+/// Side length of a chunk, in blocks.
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Notification that a chunk has come into or gone out of render distance.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ChunkEvent {
+    /// A chunk came within render distance, and its block meshes should be
+    /// set.
+    ChunkNeeded(Vec3<i32>),
+    /// A chunk went beyond render distance, and its block meshes were
+    /// removed.
+    ChunkEvicted(Vec3<i32>),
+}
+
+/// Get the chunk which contains a block.
+pub fn block_chunk(block: Vec3<i32>) -> Vec3<i32> {
+    block.map(|n| n.div_euclid(CHUNK_SIZE))
+}
+
+/// Tracks which chunks are within render distance, and which blocks have
+/// meshes in each chunk.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkTracker {
+    // radius in chunks, or none for unlimited
+    render_distance: Option<u32>,
+    // chunk which the camera was in as of the last update
+    center: Option<Vec3<i32>>,
+    // chunks which were within render distance as of the last update
+    loaded: HashSet<Vec3<i32>>,
+    // chunk -> blocks with meshes in that chunk
+    blocks: HashMap<Vec3<i32>, HashSet<Vec3<i32>>>,
+}
+
+impl ChunkTracker {
+    /// Create a tracker with unlimited render distance.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get the render distance, in chunks.
+    pub fn render_distance(&self) -> Option<u32> {
+        self.render_distance
+    }
+
+    /// Set the render distance, in chunks, or `None` for unlimited.
+    ///
+    /// This takes effect on the next `update`.
+    pub fn set_render_distance(&mut self, render_distance: Option<u32>) {
+        if render_distance != self.render_distance {
+            self.render_distance = render_distance;
+            // force re-evaluation
+            self.center = None;
+        }
+    }
+
+    /// Record that a block has a mesh.
+    pub fn block_meshed(&mut self, block: Vec3<i32>) {
+        self.blocks
+            .entry(block_chunk(block))
+            .or_insert_with(HashSet::new)
+            .insert(block);
+    }
+
+    /// Record that a block no longer has a mesh.
+    pub fn block_unmeshed(&mut self, block: Vec3<i32>) {
+        let chunk = block_chunk(block);
+        if let Some(blocks) = self.blocks.get_mut(&chunk) {
+            blocks.remove(&block);
+            if blocks.is_empty() {
+                self.blocks.remove(&chunk);
+            }
+        }
+    }
+
+    /// Whether a block has a mesh.
+    pub fn is_block_meshed(&self, block: Vec3<i32>) -> bool {
+        self.blocks
+            .get(&block_chunk(block))
+            .map(|blocks| blocks.contains(&block))
+            .unwrap_or(false)
+    }
+
+    /// Whether a chunk is within render distance of a center chunk.
+    fn in_range(&self, center: Vec3<i32>, chunk: Vec3<i32>) -> bool {
+        match self.render_distance {
+            Some(radius) => {
+                let r = radius as i64;
+                let d = (chunk - center).map(|n| n as i64);
+                d.x * d.x + d.y * d.y + d.z * d.z <= r * r
+            }
+            None => true,
+        }
+    }
+
+    /// Update the camera position.
+    ///
+    /// Pushes events for chunks which came into or went out of range to
+    /// `events`, and pushes the blocks whose meshes should be removed to
+    /// `evicted_blocks`. Does nothing if render distance is unlimited.
+    pub fn update(
+        &mut self,
+        cam_pos: Vec3<f32>,
+        events: &mut Vec<ChunkEvent>,
+        evicted_blocks: &mut Vec<Vec3<i32>>,
+    ) {
+        let radius = match self.render_distance {
+            Some(radius) => radius as i32,
+            None => {
+                self.center = None;
+                self.loaded.clear();
+                return;
+            }
+        };
+        let center = cam_pos
+            .map(|n| (n / CHUNK_SIZE as f32).floor() as i32);
+        if self.center == Some(center) {
+            return;
+        }
+        self.center = Some(center);
+
+        // evict chunks which are out of range
+        let evict = self.loaded.iter()
+            .chain(self.blocks.keys())
+            .copied()
+            .filter(|&chunk| !self.in_range(center, chunk))
+            .collect::<HashSet<_>>();
+        for chunk in evict {
+            self.loaded.remove(&chunk);
+            if let Some(blocks) = self.blocks.remove(&chunk) {
+                evicted_blocks.extend(blocks);
+            }
+            events.push(ChunkEvent::ChunkEvicted(chunk));
+        }
+
+        // request chunks which came into range
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                for z in -radius..=radius {
+                    let chunk = center + Vec3::new(x, y, z);
+                    if self.in_range(center, chunk) && self.loaded.insert(chunk) {
+                        events.push(ChunkEvent::ChunkNeeded(chunk));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn chunk_tracker_test() {
+    let mut tracker = ChunkTracker::new();
+    let mut events = Vec::new();
+    let mut evicted = Vec::new();
+
+    // unlimited render distance does nothing
+    tracker.block_meshed(Vec3::new(100, 0, 0));
+    tracker.update(Vec3::zero(), &mut events, &mut evicted);
+    assert!(events.is_empty());
+    assert!(evicted.is_empty());
+
+    // limiting render distance evicts far chunks, and requests near ones
+    tracker.set_render_distance(Some(1));
+    tracker.update(Vec3::zero(), &mut events, &mut evicted);
+    assert_eq!(evicted, vec![Vec3::new(100, 0, 0)]);
+    assert!(events.contains(&ChunkEvent::ChunkEvicted(Vec3::new(6, 0, 0))));
+    let needed = events.iter()
+        .filter(|event| match event {
+            ChunkEvent::ChunkNeeded(_) => true,
+            _ => false,
+        })
+        .count();
+    assert_eq!(needed, 7);
+
+    // moving within the same chunk does nothing
+    events.clear();
+    evicted.clear();
+    tracker.update(Vec3::new(15.0, 15.0, 15.0), &mut events, &mut evicted);
+    assert!(events.is_empty());
+
+    // moving one chunk over loads and evicts a layer
+    tracker.block_meshed(Vec3::new(-16, 0, 0));
+    tracker.update(Vec3::new(16.0, 0.0, 0.0), &mut events, &mut evicted);
+    assert_eq!(evicted, vec![Vec3::new(-16, 0, 0)]);
+    assert!(events.contains(&ChunkEvent::ChunkNeeded(Vec3::new(2, 0, 0))));
+    assert!(events.contains(&ChunkEvent::ChunkEvicted(Vec3::new(-1, 0, 0))));
+    assert!(!events.contains(&ChunkEvent::ChunkNeeded(Vec3::new(1, 0, 0))));
+
+    // blocks can be unmeshed
+    tracker.block_meshed(Vec3::new(20, 0, 0));
+    assert!(tracker.is_block_meshed(Vec3::new(20, 0, 0)));
+    tracker.block_unmeshed(Vec3::new(20, 0, 0));
+    assert!(!tracker.is_block_meshed(Vec3::new(20, 0, 0)));
+}
@@ -1,34 +1,22 @@
 //! `DrawBlocks` subsystem factory. 
 
 use super::*;
-use crate::graphics::util::texture_array::TextureArrayBuilder;
+use crate::graphics::util::{
+    texture_array::TextureArrayBuilder,
+    texture_manifest::FaceTextureMap,
+};
+use std::path::Path;
+use image::RgbaImage;
 use core::num::NonZeroU64;
 use vek::*;
 
-const BLOCK_TEXTURE_SIZE: u32 = 16;
+pub(super) const BLOCK_TEXTURE_SIZE: u32 = 16;
 
 /// `DrawBlocks` subsystem factory. 
 pub struct DrawBlocksBuilder {
     block_textures: TextureArrayBuilder,
-}
-
-/// Expresses a `ShaderModuleSource<'static>`
-macro_rules! include_shader {
-    ($file:expr)=>{{
-        let bytes: &[u8] = include_bytes!($file);
-        assert_eq!(bytes.len() % 4, 0);
-        let words: &[u32] = unsafe {
-            std::slice::from_raw_parts(
-                bytes.as_ptr() as *const u8 as *const u32,
-                bytes.len() / 4,
-            )
-        };
-        wgpu::ShaderModuleSource::SpirV(
-            std::borrow::Cow::Borrowed(
-                words
-            )
-        )
-    }};
+    color_attachment: Attachment,
+    depth_attachment: Attachment,
 }
 
 impl DrawBlocksBuilder {
@@ -37,9 +25,19 @@ impl DrawBlocksBuilder {
         let block_textures = TextureArrayBuilder::new([BLOCK_TEXTURE_SIZE; 2]);
         DrawBlocksBuilder {
             block_textures,
+            color_attachment: Attachment::SwapchainColor,
+            depth_attachment: Attachment::Depth,
         }
     }
 
+    /// Set the color and depth attachments to render into. 
+    ///
+    /// By default, this is the swapchain and the shared depth buffer. 
+    pub fn set_attachments(&mut self, color: Attachment, depth: Attachment) {
+        self.color_attachment = color;
+        self.depth_attachment = depth;
+    }
+
     /// Add a block texture to the block texture array. Return its index. 
     ///
     /// The parameter, `bytes`, is the contents of an image file, such as PNG 
@@ -49,8 +47,49 @@ impl DrawBlocksBuilder {
         self.block_textures.add_layer(bytes)
     }
 
+    /// Add a named block texture to the block texture array. Return its 
+    /// index. 
+    ///
+    /// The index can later be looked up by name with 
+    /// `DrawBlocks::block_texture_index`. 
+    pub fn add_block_texture_named(&mut self, name: &str, bytes: &[u8]) -> Result<u32> {
+        trace!(name, "adding block texture");
+        self.block_textures.add_layer_named(name, bytes)
+    }
+
+    /// Add every image file in a directory as a named block texture. Return 
+    /// their indices. 
+    ///
+    /// See `TextureArrayBuilder::load_dir`. 
+    pub fn load_block_texture_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<u32>> {
+        trace!(path = ?path.as_ref(), "loading block texture directory");
+        self.block_textures.load_dir(path)
+    }
+
+    /// Add the block textures listed in a texture manifest, then resolve its 
+    /// per-face assignments. 
+    ///
+    /// See `TextureArrayBuilder::load_manifest`. 
+    pub fn load_block_texture_manifest<P: AsRef<Path>>(&mut self, path: P) -> Result<FaceTextureMap> {
+        trace!(path = ?path.as_ref(), "loading block texture manifest");
+        self.block_textures.load_manifest(path)
+    }
+
+    /// Look up a named block texture's index. 
+    pub fn block_texture_index(&self, name: &str) -> Option<u32> {
+        self.block_textures.layer_index(name)
+    }
+
+    /// Replace the image of an existing block texture, for subsystems built 
+    /// afterwards. 
+    ///
+    /// See `TextureArrayBuilder::replace_layer_image`. 
+    pub fn replace_block_texture(&mut self, layer: u32, image: RgbaImage) {
+        self.block_textures.replace_layer_image(layer, image)
+    }
+
     /// Attempt to initialize the `DrawBlocks` subsystem.  
-    pub fn build(self, gfx: &mut Graphics, command_encoder: &mut CommandEncoder) -> Result<DrawBlocks> {
+    pub fn build(&self, gfx: &mut Graphics, command_encoder: &mut CommandEncoder) -> Result<DrawBlocks> {
         // buffers and textures
         let vertex_buffer = BufferVec::new(
                 &gfx.device, 
@@ -58,9 +97,10 @@ impl DrawBlocksBuilder {
                 label("blocks vertex buffer"),
             );
         let (
+            block_texture,
             block_texture_array, 
             block_sampler_array,
-        ) = self.block_textures.build(&gfx.device, command_encoder);
+        ) = self.block_textures.build_texture(&gfx.device, command_encoder);
         let uniform_buffer = gfx.device
             .create_buffer(&BufferDescriptor {
                 label: label("draw blocks uniform buffer"),
@@ -70,10 +110,14 @@ impl DrawBlocksBuilder {
             });
 
         // shaders
-        let vert_module = gfx.device
-            .create_shader_module(include_shader!("shader.vert.spv"));
-        let frag_module = gfx.device
-            .create_shader_module(include_shader!("shader.frag.spv"));
+        let vert_module = gfx.create_shader_module(
+            "draw_blocks/shader.vert.spv",
+            include_shader!("shader.vert.spv"),
+        );
+        let frag_module = gfx.create_shader_module(
+            "draw_blocks/shader.frag.spv",
+            include_shader!("shader.frag.spv"),
+        );
 
         // binding and pileline
         let bind_group_layout = gfx.device
@@ -167,7 +211,7 @@ impl DrawBlocksBuilder {
                 depth_stencil_state: Some(DepthStencilStateDescriptor {
                     format: DEPTH_FMT,
                     depth_write_enabled: true,
-                    depth_compare: CompareFunction::LessEqual,
+                    depth_compare: gfx.depth_compare(),
                     stencil_front: StencilStateFaceDescriptor::IGNORE,
                     stencil_back: StencilStateFaceDescriptor::IGNORE,
                     stencil_read_mask: 0,
@@ -195,8 +239,12 @@ impl DrawBlocksBuilder {
             uniform_buffer,
             vertex_buffer,
             mesh_differ: MeshDiffer::new(),
+            block_texture,
             block_texture_array,
-            block_sampler_array
+            block_sampler_array,
+            block_texture_map: self.block_textures.layer_map(),
+            color_attachment: self.color_attachment,
+            depth_attachment: self.depth_attachment,
         })
     }
 }
\ No newline at end of file
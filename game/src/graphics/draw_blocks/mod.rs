@@ -1,14 +1,19 @@
 //! Graphics subsystem for drawing the block grid. 
 
 use super::*;
-use crate::graphics::util::{
-    vertex::GenericVertex,
-    uniform::GenericUniforms,
-    mesh_diff::MeshDiffer,
-    buffer_vec::BufferVec,
-    CORR,
+use crate::graphics::{
+    render_graph::PassDecl,
+    util::{
+        vertex::GenericVertex,
+        uniform::GenericUniforms,
+        mesh_diff::MeshDiffer,
+        buffer_vec::BufferVec,
+        texture_array::{TextureArrayMap, write_layer},
+    },
 };
+use image::RgbaImage;
 use vek::*;
+use game_derive::{Vertex, Uniforms};
 use crate::arraymap::ArrayMap;
 
 pub mod builder;
@@ -20,73 +25,124 @@ pub struct DrawBlocks {
     uniform_buffer: Buffer,
     mesh_differ: MeshDiffer<[i32; 3], [[u8; Vertex::SIZE]; 3]>,
     vertex_buffer: BufferVec<[[u8; Vertex::SIZE]; 3]>,
+    // kept so that layers can be overwritten
+    block_texture: Texture,
     block_texture_array: TextureView,
     block_sampler_array: Sampler,
+    block_texture_map: TextureArrayMap,
+    color_attachment: Attachment,
+    depth_attachment: Attachment,
 }
 
-impl DrawBlocks {
-    /// Draw a frame. 
-    pub fn draw(
+impl RenderNode for DrawBlocks {
+    fn name(&self) -> &'static str {
+        "draw blocks"
+    }
+
+    fn pass_decl(&self) -> PassDecl {
+        PassDecl {
+            color: vec![self.color_attachment],
+            depth: Some(self.depth_attachment),
+            depth_write: true,
+            ..Default::default()
+        }
+    }
+
+    fn prepare(
         &mut self,
-        gfx: &mut Graphics, 
-        frame: &mut SwapChainFrame, 
+        gfx: &Graphics,
+        uploader: &mut Uploader,
         command_encoder: &mut CommandEncoder,
     ) -> Result<()> {
         // update mesh
         let patch = self.mesh_differ.commit();
-        self.vertex_buffer.apply_patch(&patch, &gfx.device, command_encoder);
+        self.vertex_buffer.apply_patch(&patch, &gfx.device, uploader, command_encoder);
+        pear_debug_assert_eq!(
+            self.vertex_buffer.len_elems(),
+            patch.new_len(),
+            {},
+            "block vertex buffer out of sync with mesh",
+        );
 
         // set uniforms
+        let sky = gfx.sky_state();
         let uniforms = Uniforms {
-            corr_proj_view: CORR * gfx.cam.proj() * gfx.cam.view(),
+            corr_proj_view: gfx.corr_proj() * gfx.view_cam.view(),
+            cam_pos: Vec4::from_point(gfx.view_cam.pos),
+            sun_dir: Vec4::from_direction(sky.sun_dir),
+            sun_color: Rgba::from_opaque(sky.sun_color),
+            ambient_color: Rgba::from_opaque(sky.ambient_color),
+            gamma: gfx.gamma,
         };
-        let uniforms_copy_src = gfx.device
-            .create_buffer_init(&BufferInitDescriptor {
-                label: Some("uniforms copy src"),
-                contents: &uniforms.encode(),
-                usage: BufferUsage::COPY_SRC,
-            });
-        command_encoder
-            .copy_buffer_to_buffer(
-                &uniforms_copy_src,
-                0,
-                &self.uniform_buffer,
-                0,
-                Uniforms::SIZE as u64,
-            );
-
-        // render pass
-        let mut pass = command_encoder
-            .begin_render_pass(&RenderPassDescriptor {
-                color_attachments: cowslice![
-                    RenderPassColorAttachmentDescriptor {
-                        attachment: &frame.output.view,
-                        resolve_target: None,
-                        ops: Operations {
-                            load: LoadOp::Clear(CLEAR_COLOR),
-                            store: true,
-                        }
-                    },
-                ],
-                depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
-                    attachment: &gfx.depth_texture_view,
-                    depth_ops: Some(Operations {
-                        load: LoadOp::Clear(CLEAR_DEPTH),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
+        uploader.upload(
+            &gfx.device,
+            command_encoder,
+            &self.uniform_buffer,
+            0,
+            &uniforms.encode(),
+        );
+
+        Ok(())
+    }
+
+    fn render<'a>(&'a self, _gfx: &'a Graphics, pass: &mut RenderPass<'a>) -> Result<()> {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.bind_group, &[]);
         pass.set_vertex_buffer(0, self.vertex_buffer.as_buffer_slice());
 
-        let num_prims = self.vertex_buffer.len_elems() as u32 * 3;
-        pass.draw(0..num_prims, 0..1);
+        // block meshes vary in size, so the buffer may have holes
+        for range in self.mesh_differ.live_ranges() {
+            pass.draw(range.start as u32 * 3..range.end as u32 * 3, 0..1);
+        }
 
         Ok(())
     }
 
+    fn draw_calls(&self) -> usize {
+        self.mesh_differ.live_ranges().count()
+    }
+}
+
+impl DrawBlocks {
+    /// Take the mesh from a `DrawBlocks` built on a lost device, and upload it 
+    /// to this one. 
+    pub fn restore_mesh(
+        &mut self,
+        old: DrawBlocks,
+        device: &Device,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        self.mesh_differ = old.mesh_differ;
+        let patch = self.mesh_differ.full_patch();
+        self.vertex_buffer.apply_patch(&patch, device, uploader, command_encoder);
+    }
+
+    /// Look up a named block texture's index. 
+    pub fn block_texture_index(&self, name: &str) -> Option<u32> {
+        self.block_texture_map.layer_index(name)
+    }
+
+    /// Overwrite a block texture's image in place. 
+    pub fn write_block_texture(
+        &self,
+        layer: u32,
+        image: RgbaImage,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let size = builder::BLOCK_TEXTURE_SIZE;
+        write_layer(&self.block_texture, Extent2::new(size, size), layer, image, device, command_encoder);
+    }
+
+    /// Get memory and churn statistics for the block mesh. 
+    pub fn mesh_stats(&self) -> MeshStats {
+        MeshStats {
+            differ: self.mesh_differ.stats(),
+            buffer: self.vertex_buffer.stats(),
+        }
+    }
+
     /// Set the mesh geometry for a single block. 
     ///
     /// Override any existing mesh geometry for that block. 
@@ -102,30 +158,42 @@ impl DrawBlocks {
 }
 
 /// Block vertex type.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Vertex)]
 pub struct Vertex {
+    #[attr(location = 0, glsl = "vec3")]
     pub pos: Vec3<f32>,
+    #[attr(location = 1, glsl = "vec2")]
     pub tex_coord: Vec2<f32>,
+    #[attr(location = 2, glsl = "uint")]
     pub tex_index: u32,
-}
-
-vertex! {
-    Vertex {
-        layout(location = 0) in vec3 pos: Vec3<f32>,
-        layout(location = 1) in vec2 tex_coord: Vec2<f32>,
-        layout(location = 2) in uint tex_index: u32,
-    }
+    /// Skylight and block light levels, from 0 to 1. 
+    #[attr(location = 3, glsl = "vec2")]
+    pub light: Vec2<f32>,
+    /// Color the texture is multiplied by, such as the biome's grass color. 
+    #[attr(location = 4, glsl = "vec3")]
+    pub tint: Rgb<f32>,
 }
 
 /// Draw terrain uniform type. 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Uniforms)]
+#[attr(size = 132)]
 pub struct Uniforms {
     /// Correction * Projection * View
+    #[attr(glsl = "mat4")]
     pub corr_proj_view: Mat4<f32>,
-}
-
-uniforms! {
-    Uniforms {
-        mat4 corr_proj_view: Mat4<f32>,
-    }
+    /// Camera position, with w unused. 
+    #[attr(glsl = "vec4")]
+    pub cam_pos: Vec4<f32>,
+    /// Direction towards the sun, with w unused. 
+    #[attr(glsl = "vec4")]
+    pub sun_dir: Vec4<f32>,
+    /// Direct sunlight color, with alpha unused. 
+    #[attr(glsl = "vec4")]
+    pub sun_color: Rgba<f32>,
+    /// Ambient light color, with alpha unused. 
+    #[attr(glsl = "vec4")]
+    pub ambient_color: Rgba<f32>,
+    /// Gamma adjustment. 
+    #[attr(glsl = "float")]
+    pub gamma: f32,
 }
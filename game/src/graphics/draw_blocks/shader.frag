@@ -3,15 +3,52 @@
 layout(location = 0) in vec3 v_Pos;
 layout(location = 1) in vec2 v_TexCoord;
 layout(location = 2) flat in uint v_TexIndex;
+layout(location = 3) in vec2 v_Light;
+layout(location = 4) in vec3 v_Tint;
 
 layout(location = 0) out vec4 o_Target;
 
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 u_CorrProjView;
+    vec4 u_CamPos;
+    vec4 u_SunDir;
+    vec4 u_SunColor;
+    vec4 u_AmbientColor;
+    float u_Gamma;
+};
+
 layout(set = 0, binding = 1) uniform texture2DArray u_BlockTextureArray;
 layout(set = 0, binding = 2) uniform sampler u_BlockSamplerArray;
 
+// color of light from light-emitting blocks
+const vec3 BLOCK_LIGHT_COLOR = vec3(1.0, 0.8, 0.55);
+// light level which is enough to barely see by
+const float MIN_LIGHT = 0.02;
+
+// brightness of a light level from 0 to 1, which falls off quickly so that
+// light fades out with distance from its source
+float brightness(float level) {
+    return mix(MIN_LIGHT, 1.0, pow(level, 3.0));
+}
+
+// flat-shaded directional and ambient lighting, reaching as far as skylight
+// does, plus light from light-emitting blocks
+vec3 lighting(vec3 pos) {
+    vec3 normal = normalize(cross(dFdx(pos), dFdy(pos)));
+    // faces are culled if they face away, so make the normal face the camera
+    if (dot(normal, u_CamPos.xyz - pos) < 0.0) {
+        normal = -normal;
+    }
+    float diffuse = max(dot(normal, u_SunDir.xyz), 0.0);
+    vec3 sky = u_AmbientColor.rgb + u_SunColor.rgb * diffuse;
+    return sky * brightness(v_Light.x) + BLOCK_LIGHT_COLOR * brightness(v_Light.y);
+}
+
 void main() {
-    o_Target = texture(
+    vec4 color = texture(
         sampler2DArray(u_BlockTextureArray, u_BlockSamplerArray),
         vec3(v_TexCoord, v_TexIndex)
     );
+    vec3 lit = color.rgb * v_Tint * lighting(v_Pos);
+    o_Target = vec4(pow(lit, vec3(1.0 / u_Gamma)), color.a);
 }
\ No newline at end of file
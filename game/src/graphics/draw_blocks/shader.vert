@@ -3,19 +3,30 @@
 layout(location = 0) in vec3 a_Pos;
 layout(location = 1) in vec2 a_TexCoord;
 layout(location = 2) in uint a_TexIndex;
+layout(location = 3) in vec2 a_Light;
+layout(location = 4) in vec3 a_Tint;
 
 layout(location = 0) out vec3 v_Pos;
 layout(location = 1) out vec2 v_TexCoord;
 layout(location = 2) out uint v_TexIndex;
+layout(location = 3) out vec2 v_Light;
+layout(location = 4) out vec3 v_Tint;
 
 layout(set = 0, binding = 0) uniform Locals {
     mat4 u_CorrProjView;
+    vec4 u_CamPos;
+    vec4 u_SunDir;
+    vec4 u_SunColor;
+    vec4 u_AmbientColor;
+    float u_Gamma;
 };
 
 void main() {
     v_Pos = a_Pos;
     v_TexCoord = a_TexCoord;
     v_TexIndex = a_TexIndex;
+    v_Light = a_Light;
+    v_Tint = a_Tint;
     gl_Position = u_CorrProjView * vec4(a_Pos, 1.0);
     //gl_Position = vec4(a_Pos.xy, 0.5, 1.0);
 }
@@ -0,0 +1,194 @@
+//! `DrawGui` subsystem factory. 
+
+use super::*;
+use core::num::NonZeroU64;
+
+/// `DrawGui` subsystem factory. 
+pub struct DrawGuiBuilder;
+
+impl DrawGuiBuilder {
+    /// Create a new `DrawGuiBuilder` in its default state. 
+    pub fn new() -> Self {
+        DrawGuiBuilder
+    }
+
+    /// Attempt to initialize the `DrawGui` subsystem. 
+    pub fn build(&self, gfx: &mut Graphics) -> Result<DrawGui> {
+        // buffers, which are rewritten whenever the GUI changes
+        let vertex_buffer = BufferVec::new(
+                &gfx.device,
+                BufferUsage::VERTEX,
+                label("gui vertex buffer"),
+            );
+        let index_buffer = BufferVec::new(
+                &gfx.device,
+                BufferUsage::INDEX,
+                label("gui index buffer"),
+            );
+        let font_sampler = gfx.device
+            .create_sampler(&SamplerDescriptor {
+                label: label("gui font sampler"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                lod_min_clamp: -100.0,
+                lod_max_clamp: 100.0,
+                compare: None,
+                anisotropy_clamp: None,
+            });
+
+        // pass uniforms as push constants if possible, to avoid copying them
+        // into a uniform buffer every frame
+        let push_constants = gfx
+            .supports_push_constants(<Uniforms as GenericPushConstants>::SIZE);
+        let uniform_buffer = match push_constants {
+            true => None,
+            false => Some(gfx.device
+                .create_buffer(&BufferDescriptor {
+                    label: label("draw gui uniform buffer"),
+                    size: <Uniforms as GenericUniforms>::SIZE as u64,
+                    usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+                    mapped_at_creation: false,
+                })),
+        };
+
+        // shaders
+        let vert_module = match push_constants {
+            true => gfx.create_shader_module(
+                "draw_gui/shader_push.vert.spv",
+                include_shader!("shader_push.vert.spv"),
+            ),
+            false => gfx.create_shader_module(
+                "draw_gui/shader.vert.spv",
+                include_shader!("shader.vert.spv"),
+            ),
+        };
+        let frag_module = gfx.create_shader_module(
+            "draw_gui/shader.frag.spv",
+            include_shader!("shader.frag.spv"),
+        );
+
+        // binding and pipeline, though the bind group waits for the font
+        // texture
+        let mut bind_group_layout_entries = Vec::new();
+        if uniform_buffer.is_some() {
+            // uniform buffer
+            bind_group_layout_entries.push(BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::VERTEX,
+                ty: BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: Some(NonZeroU64::new(<Uniforms as GenericUniforms>::SIZE as u64).unwrap()),
+                },
+                count: None,
+            });
+        }
+        // font texture
+        bind_group_layout_entries.push(BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStage::FRAGMENT,
+            ty: BindingType::SampledTexture {
+                dimension: TextureViewDimension::D2,
+                component_type: TextureComponentType::Float,
+                multisampled: false,
+            },
+            count: None,
+        });
+        // font texture sampler
+        bind_group_layout_entries.push(BindGroupLayoutEntry {
+            binding: 2,
+            visibility: ShaderStage::FRAGMENT,
+            ty: BindingType::Sampler {
+                comparison: false,
+            },
+            count: None,
+        });
+
+        let bind_group_layout = gfx.device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: label("gui bind group layout"),
+                entries: Cow::Owned(bind_group_layout_entries),
+            });
+        let push_constant_ranges = match push_constants {
+            true => vec![<Uniforms as GenericPushConstants>::range()],
+            false => Vec::new(),
+        };
+        let pipeline_layout = gfx.device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                bind_group_layouts: cowslice![&bind_group_layout],
+                push_constant_ranges: Cow::Owned(push_constant_ranges),
+            });
+        let pipeline = gfx.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                layout: &pipeline_layout,
+                vertex_stage: ProgrammableStageDescriptor {
+                    module: &vert_module,
+                    entry_point: cowstr("main"),
+                },
+                fragment_stage: Some(ProgrammableStageDescriptor {
+                    module: &frag_module,
+                    entry_point: cowstr("main"),
+                }),
+                rasterization_state: Some(RasterizationStateDescriptor {
+                    front_face: FrontFace::Ccw,
+                    // egui's winding order is inconsistent
+                    cull_mode: CullMode::None,
+                    clamp_depth: false,
+                    depth_bias: 0,
+                    depth_bias_slope_scale: 0.0,
+                    depth_bias_clamp: 0.0,
+                }),
+                primitive_topology: PrimitiveTopology::TriangleList,
+                color_states: cowslice![
+                    ColorStateDescriptor {
+                        format: SWAPCHAIN_FMT,
+                        // colors are premultiplied by alpha
+                        color_blend: BlendDescriptor {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha_blend: BlendDescriptor {
+                            src_factor: BlendFactor::OneMinusDstAlpha,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                        write_mask: ColorWrite::ALL,
+                    },
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: IndexFormat::Uint32,
+                    vertex_buffers: cowslice![
+                        VertexBufferDescriptor {
+                            stride: GuiVertex::SIZE as u64,
+                            step_mode: InputStepMode::Vertex,
+                            attributes: GuiVertex::attributes(),
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        Ok(DrawGui {
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            font_sampler,
+            bind_group: None,
+            uploaded_font_version: None,
+            font_texture: None,
+            meshes: Vec::new(),
+            meshes_changed: false,
+            pixels_per_point: 1.0,
+            vertex_buffer,
+            index_buffer,
+            draws: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,311 @@
+//! Graphics subsystem for drawing an egui user interface. 
+
+use super::*;
+use crate::graphics::{
+    render_graph::PassDecl,
+    util::{
+        vertex::GenericVertex,
+        uniform::GenericUniforms,
+        push_constant::GenericPushConstants,
+        buffer_vec::BufferVec,
+    },
+};
+use std::ops::Range;
+use vek::*;
+use game_derive::{Vertex, Uniforms};
+
+pub mod builder;
+
+/// Graphics subsystem for drawing an egui user interface. 
+///
+/// The GUI is drawn over everything else, including sprites. Only meshes
+/// textured with egui's font texture are drawn. 
+pub struct DrawGui {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    // none if uniforms are passed as push constants instead
+    uniform_buffer: Option<Buffer>,
+    font_sampler: Sampler,
+    // none until a font texture is uploaded
+    bind_group: Option<BindGroup>,
+    uploaded_font_version: Option<u64>,
+    font_texture: Option<Arc<egui::Texture>>,
+    meshes: Vec<egui::ClippedMesh>,
+    meshes_changed: bool,
+    pixels_per_point: f32,
+    vertex_buffer: BufferVec<[u8; GuiVertex::SIZE]>,
+    index_buffer: BufferVec<u32>,
+    draws: Vec<GuiDraw>,
+}
+
+/// Range of the index buffer to draw with a scissor rectangle. 
+#[derive(Clone, Debug)]
+struct GuiDraw {
+    // in physical pixels
+    scissor: Rect<u32, u32>,
+    indices: Range<u32>,
+    base_vertex: i32,
+}
+
+impl RenderNode for DrawGui {
+    fn name(&self) -> &'static str {
+        "draw gui"
+    }
+
+    fn pass_decl(&self) -> PassDecl {
+        PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            ..Default::default()
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        gfx: &Graphics,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.upload_font_texture(gfx);
+        if self.meshes_changed {
+            self.meshes_changed = false;
+            self.upload_meshes(gfx, uploader, command_encoder);
+        }
+
+        // set uniforms
+        let uniform_buffer = match self.uniform_buffer {
+            Some(ref uniform_buffer) => uniform_buffer,
+            None => return Ok(()),
+        };
+        let uniforms = Uniforms::new(gfx, self.pixels_per_point);
+        uploader.upload(
+            &gfx.device,
+            command_encoder,
+            uniform_buffer,
+            0,
+            &uniforms.encode(),
+        );
+
+        Ok(())
+    }
+
+    fn render<'a>(&'a self, gfx: &'a Graphics, pass: &mut RenderPass<'a>) -> Result<()> {
+        let bind_group = match self.bind_group {
+            Some(ref bind_group) => bind_group,
+            None => return Ok(()),
+        };
+        if self.draws.is_empty() {
+            return Ok(());
+        }
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        if self.uniform_buffer.is_none() {
+            Uniforms::new(gfx, self.pixels_per_point).set(pass);
+        }
+        pass.set_vertex_buffer(0, self.vertex_buffer.as_buffer_slice());
+        pass.set_index_buffer(self.index_buffer.as_buffer_slice());
+        for draw in &self.draws {
+            let s = draw.scissor;
+            pass.set_scissor_rect(s.x, s.y, s.w, s.h);
+            pass.draw_indexed(draw.indices.clone(), draw.base_vertex, 0..1);
+        }
+        // later nodes in the same pass shouldn't be clipped
+        pass.set_scissor_rect(0, 0, gfx.window_size.width, gfx.window_size.height);
+
+        Ok(())
+    }
+
+    fn draw_calls(&self) -> usize {
+        match self.bind_group {
+            Some(_) => self.draws.len(),
+            None => 0,
+        }
+    }
+}
+
+impl DrawGui {
+    /// Take the GUI from a `DrawGui` built on a lost device, and upload it
+    /// to this one upon the next frame. 
+    pub fn restore_gui(&mut self, old: DrawGui) {
+        self.font_texture = old.font_texture;
+        self.meshes = old.meshes;
+        self.meshes_changed = true;
+        self.pixels_per_point = old.pixels_per_point;
+    }
+
+    /// Set the GUI to draw, as tessellated by egui, until it's next set. 
+    ///
+    /// `font_texture` is egui's font texture, which is only re-uploaded when
+    /// its version changes. 
+    pub fn set_gui(
+        &mut self,
+        meshes: Vec<egui::ClippedMesh>,
+        font_texture: Arc<egui::Texture>,
+        pixels_per_point: f32,
+    ) {
+        self.meshes = meshes;
+        self.meshes_changed = true;
+        self.font_texture = Some(font_texture);
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    fn upload_font_texture(&mut self, gfx: &Graphics) {
+        let font = match self.font_texture {
+            Some(ref font) => font,
+            None => return,
+        };
+        if self.uploaded_font_version == Some(font.version) {
+            return;
+        }
+        trace!("uploading gui font texture");
+
+        let size = Extent3d {
+            width: font.width as u32,
+            height: font.height as u32,
+            depth: 1,
+        };
+        let texture = gfx.device
+            .create_texture(&TextureDescriptor {
+                label: label("gui font texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+            });
+        let pixels = font.srgba_pixels(1.0)
+            .flat_map(|color| color.to_array().to_vec())
+            .collect::<Vec<u8>>();
+        gfx.queue.write_texture(
+            TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                },
+            },
+            &pixels,
+            TextureDataLayout {
+                offset: 0,
+                bytes_per_row: size.width * 4,
+                rows_per_image: size.height,
+            },
+            size,
+        );
+        let texture_view = texture.create_default_view();
+
+        let mut entries = Vec::new();
+        if let Some(ref uniform_buffer) = self.uniform_buffer {
+            entries.push(BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(uniform_buffer.slice(..)),
+            });
+        }
+        entries.push(BindGroupEntry {
+            binding: 1,
+            resource: BindingResource::TextureView(&texture_view),
+        });
+        entries.push(BindGroupEntry {
+            binding: 2,
+            resource: BindingResource::Sampler(&self.font_sampler),
+        });
+        self.bind_group = Some(gfx.device
+            .create_bind_group(&BindGroupDescriptor {
+                label: label("gui bind group"),
+                layout: &self.bind_group_layout,
+                entries: Cow::Owned(entries),
+            }));
+        self.uploaded_font_version = Some(font.version);
+    }
+
+    fn upload_meshes(
+        &mut self,
+        gfx: &Graphics,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let window_size = Vec2::new(gfx.window_size.width, gfx.window_size.height);
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        self.draws.clear();
+        for &egui::ClippedMesh(clip, ref mesh) in &self.meshes {
+            if mesh.texture_id != egui::TextureId::Egui || mesh.indices.is_empty() {
+                continue;
+            }
+
+            // clip rectangle from points to pixels, within the window
+            let min = Vec2::new(clip.min.x, clip.min.y) * self.pixels_per_point;
+            let max = Vec2::new(clip.max.x, clip.max.y) * self.pixels_per_point;
+            let min = min.map2(window_size, |n, size| (n.round().max(0.0) as u32).min(size));
+            let max = max.map2(window_size, |n, size| (n.round().max(0.0) as u32).min(size));
+            if min.x >= max.x || min.y >= max.y {
+                continue;
+            }
+
+            let base_vertex = vertices.len() as i32;
+            let start = indices.len() as u32;
+            vertices.extend(mesh.vertices
+                .iter()
+                .map(|vertex| GuiVertex {
+                    pos: Vec2::new(vertex.pos.x, vertex.pos.y),
+                    tex: Vec2::new(vertex.uv.x, vertex.uv.y),
+                    color: Rgba::from(vertex.color.to_array()),
+                }.encode()));
+            indices.extend_from_slice(&mesh.indices);
+            self.draws.push(GuiDraw {
+                scissor: Rect::new(min.x, min.y, max.x - min.x, max.y - min.y),
+                indices: start..indices.len() as u32,
+                base_vertex,
+            });
+        }
+
+        self.vertex_buffer.clear();
+        self.vertex_buffer.extend_from_slice(&vertices);
+        self.vertex_buffer.flush(&gfx.device, uploader, command_encoder);
+        self.index_buffer.clear();
+        self.index_buffer.extend_from_slice(&indices);
+        self.index_buffer.flush(&gfx.device, uploader, command_encoder);
+    }
+}
+
+/// GUI vertex type. 
+#[derive(Copy, Clone, Debug, Vertex)]
+struct GuiVertex {
+    // in points
+    #[attr(location = 0, glsl = "vec2")]
+    pos: Vec2<f32>,
+    #[attr(location = 1, glsl = "vec2")]
+    tex: Vec2<f32>,
+    // sRGB, premultiplied by alpha
+    #[attr(location = 2, glsl = "vec4")]
+    color: Rgba<u8>,
+}
+
+/// Draw GUI uniform type. 
+#[derive(Copy, Clone, Debug, Uniforms)]
+#[attr(size = 8)]
+pub struct Uniforms {
+    /// Window size in points, which are pixels divided by the GUI scale. 
+    #[attr(glsl = "vec2")]
+    pub screen_size: Vec2<f32>,
+}
+
+impl Uniforms {
+    fn new(gfx: &Graphics, pixels_per_point: f32) -> Self {
+        Uniforms {
+            screen_size: Vec2::new(
+                gfx.window_size.width as f32,
+                gfx.window_size.height as f32,
+            ) / pixels_per_point,
+        }
+    }
+}
+
+push_constants! {
+    Uniforms in VERTEX {
+        vec2 screen_size: Vec2<f32>,
+    }
+}
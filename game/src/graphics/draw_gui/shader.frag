@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+layout(location = 1) in vec4 v_Color;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 1) uniform texture2D u_FontTexture;
+layout(set = 0, binding = 2) uniform sampler u_FontSampler;
+
+void main() {
+    // colors are premultiplied by alpha
+    o_Target = v_Color * texture(sampler2D(u_FontTexture, u_FontSampler), v_TexCoord);
+}
@@ -0,0 +1,29 @@
+#version 450
+
+layout(location = 0) in vec2 i_Pos;
+layout(location = 1) in vec2 i_TexCoord;
+layout(location = 2) in vec4 i_Color;
+
+layout(location = 0) out vec2 v_TexCoord;
+layout(location = 1) out vec4 v_Color;
+
+layout(push_constant, std140) uniform u_PushConstants {
+    vec2 u_ScreenSize;
+};
+
+// sRGB to linear, since vertex colors aren't converted automatically
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / 12.92;
+    vec3 higher = pow((srgb + 0.055) / 1.055, vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+    // points, with the origin at the top left, to normalized device coordinates
+    vec2 ndc = i_Pos / u_ScreenSize * 2.0 - 1.0;
+
+    v_TexCoord = i_TexCoord;
+    v_Color = vec4(linear_from_srgb(i_Color.rgb), i_Color.a);
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
@@ -0,0 +1,297 @@
+//! `DrawModels` subsystem factory.
+
+use super::{
+    *,
+    load::{
+        ModelData,
+        NO_TEXTURE,
+        load_gltf,
+        load_obj,
+    },
+};
+use crate::graphics::util::texture_array::TextureArrayBuilder;
+use core::num::NonZeroU64;
+use image::RgbaImage;
+use vek::*;
+
+const MODEL_TEXTURE_SIZE: u32 = 64;
+
+/// Texture array layer which is solid white, for untextured models.
+const WHITE_MODEL_TEXTURE: u32 = 0;
+
+/// `DrawModels` subsystem factory.
+pub struct DrawModelsBuilder {
+    model_textures: TextureArrayBuilder,
+    // vertices, with texture indices into `model_textures`
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    // (base vertex, index range) of each model
+    models: Vec<(i32, Range<u32>)>,
+    color_attachment: Attachment,
+    depth_attachment: Attachment,
+}
+
+impl DrawModelsBuilder {
+    /// Create a new `DrawModelsBuilder` in its default state.
+    pub fn new() -> Self {
+        let mut model_textures = TextureArrayBuilder::new([MODEL_TEXTURE_SIZE; 2]);
+        let white = model_textures.add_layer_image(RgbaImage::from_pixel(
+            MODEL_TEXTURE_SIZE,
+            MODEL_TEXTURE_SIZE,
+            image::Rgba([0xFF; 4]),
+        ));
+        debug_assert_eq!(white, WHITE_MODEL_TEXTURE);
+        DrawModelsBuilder {
+            model_textures,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            models: Vec::new(),
+            color_attachment: Attachment::SwapchainColor,
+            depth_attachment: Attachment::Depth,
+        }
+    }
+
+    /// Set the color and depth attachments to render into.
+    ///
+    /// By default, this is the swapchain and the shared depth buffer.
+    pub fn set_attachments(&mut self, color: Attachment, depth: Attachment) {
+        self.color_attachment = color;
+        self.depth_attachment = depth;
+    }
+
+    /// Load a glTF model. Return its ID.
+    ///
+    /// The model must be self-contained, as either binary glTF or glTF with
+    /// embedded buffers and images.
+    pub fn add_model_gltf(&mut self, bytes: &[u8]) -> Result<ModelId> {
+        trace!("adding glTF model");
+        let model = load_gltf(bytes)?;
+        Ok(self.add_model(model))
+    }
+
+    /// Load a Wavefront OBJ model, textured with an optional image file.
+    /// Return its ID.
+    pub fn add_model_obj(&mut self, bytes: &[u8], texture: Option<&[u8]>) -> Result<ModelId> {
+        trace!("adding OBJ model");
+        let model = load_obj(bytes, texture)?;
+        Ok(self.add_model(model))
+    }
+
+    /// Add a model from CPU-side mesh data, such as one loaded in the
+    /// background. Return its ID.
+    pub fn add_model(&mut self, model: ModelData) -> ModelId {
+        let layers = model.textures
+            .into_iter()
+            .map(|texture| self.model_textures.add_layer_image(texture))
+            .collect::<Vec<u32>>();
+
+        let base_vertex = self.vertices.len() as i32;
+        self.vertices.extend(model.vertices
+            .into_iter()
+            .map(|vertex| Vertex {
+                tex_index: match vertex.tex_index {
+                    NO_TEXTURE => WHITE_MODEL_TEXTURE,
+                    i => layers[i as usize],
+                },
+                ..vertex
+            }));
+        let start = self.indices.len() as u32;
+        self.indices.extend(model.indices);
+        let end = self.indices.len() as u32;
+
+        let id = ModelId(self.models.len());
+        self.models.push((base_vertex, start..end));
+        id
+    }
+
+    /// Attempt to initialize the `DrawModels` subsystem.
+    pub fn build(&self, gfx: &mut Graphics, command_encoder: &mut CommandEncoder) -> Result<DrawModels> {
+        // buffers and textures
+        let mut vertex_bytes = Vec::with_capacity(self.vertices.len() * Vertex::SIZE);
+        for vertex in &self.vertices {
+            vertex_bytes.extend_from_slice(&vertex.encode());
+        }
+        let mut index_bytes = bytemuck::cast_slice::<u32, u8>(&self.indices).to_vec();
+        // wgpu doesn't like empty buffers
+        vertex_bytes.resize(vertex_bytes.len().max(Vertex::SIZE), 0);
+        index_bytes.resize(index_bytes.len().max(4), 0);
+        let vertex_buffer = gfx.device
+            .create_buffer_init(&BufferInitDescriptor {
+                label: Some("models vertex buffer"),
+                contents: &vertex_bytes,
+                usage: BufferUsage::VERTEX,
+            });
+        let index_buffer = gfx.device
+            .create_buffer_init(&BufferInitDescriptor {
+                label: Some("models index buffer"),
+                contents: &index_bytes,
+                usage: BufferUsage::INDEX,
+            });
+        let models = self.models
+            .iter()
+            .map(|&(base_vertex, ref indices)| Model {
+                base_vertex,
+                indices: indices.clone(),
+                instance_differ: MeshDiffer::new(),
+                instance_buffer: BufferVec::new(
+                    &gfx.device,
+                    BufferUsage::VERTEX,
+                    label("models instance buffer"),
+                ),
+            })
+            .collect();
+        let (
+            model_texture_array,
+            model_sampler_array,
+        ) = self.model_textures.build(&gfx.device, command_encoder);
+        let uniform_buffer = gfx.device
+            .create_buffer(&BufferDescriptor {
+                label: label("draw models uniform buffer"),
+                size: Uniforms::SIZE as u64,
+                usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+        // shaders
+        let vert_module = gfx.create_shader_module(
+            "draw_models/shader.vert.spv",
+            include_shader!("shader.vert.spv"),
+        );
+        let frag_module = gfx.create_shader_module(
+            "draw_models/shader.frag.spv",
+            include_shader!("shader.frag.spv"),
+        );
+
+        // binding and pipeline
+        let bind_group_layout = gfx.device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: label("models bind group layout"),
+                entries: cowslice![
+                    // uniform buffer
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStage::VERTEX | ShaderStage::FRAGMENT,
+                        ty: BindingType::UniformBuffer {
+                            dynamic: false,
+                            min_binding_size: Some(NonZeroU64::new(Uniforms::SIZE as u64).unwrap()),
+                        },
+                        count: None,
+                    },
+                    // model texture array
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStage::FRAGMENT,
+                        ty: BindingType::SampledTexture {
+                            dimension: TextureViewDimension::D2Array,
+                            component_type: TextureComponentType::Float,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    // model texture sampler
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStage::FRAGMENT,
+                        ty: BindingType::Sampler {
+                            comparison: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let bind_group = gfx.device
+            .create_bind_group(&BindGroupDescriptor {
+                label: label("models bind group"),
+                layout: &bind_group_layout,
+                entries: cowslice![
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(uniform_buffer.slice(..)),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&model_texture_array),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(&model_sampler_array),
+                    },
+                ],
+            });
+        let pipeline_layout = gfx.device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                bind_group_layouts: cowslice![&bind_group_layout],
+                push_constant_ranges: cowslice![],
+            });
+        let pipeline = gfx.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                layout: &pipeline_layout,
+                vertex_stage: ProgrammableStageDescriptor {
+                    module: &vert_module,
+                    entry_point: cowstr("main"),
+                },
+                fragment_stage: Some(ProgrammableStageDescriptor {
+                    module: &frag_module,
+                    entry_point: cowstr("main"),
+                }),
+                rasterization_state: Some(RasterizationStateDescriptor {
+                    front_face: FrontFace::Ccw,
+                    cull_mode: CullMode::Back,
+                    clamp_depth: false,
+                    depth_bias: 0,
+                    depth_bias_slope_scale: 0.0,
+                    depth_bias_clamp: 0.0,
+                }),
+                primitive_topology: PrimitiveTopology::TriangleList,
+                color_states: cowslice![
+                    ColorStateDescriptor {
+                        format: SWAPCHAIN_FMT,
+                        color_blend: BlendDescriptor::REPLACE,
+                        alpha_blend: BlendDescriptor::REPLACE,
+                        write_mask: ColorWrite::ALL,
+                    },
+                ],
+                depth_stencil_state: Some(DepthStencilStateDescriptor {
+                    format: DEPTH_FMT,
+                    depth_write_enabled: true,
+                    depth_compare: gfx.depth_compare(),
+                    stencil_front: StencilStateFaceDescriptor::IGNORE,
+                    stencil_back: StencilStateFaceDescriptor::IGNORE,
+                    stencil_read_mask: 0,
+                    stencil_write_mask: 0,
+                }),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: IndexFormat::Uint32,
+                    vertex_buffers: cowslice![
+                        VertexBufferDescriptor {
+                            stride: Vertex::SIZE as u64,
+                            step_mode: InputStepMode::Vertex,
+                            attributes: Vertex::attributes(),
+                        },
+                        VertexBufferDescriptor {
+                            stride: Instance::SIZE as u64,
+                            step_mode: InputStepMode::Instance,
+                            attributes: Instance::attributes(),
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        Ok(DrawModels {
+            pipeline,
+            bind_group,
+            uniform_buffer,
+            vertex_buffer,
+            index_buffer,
+            models,
+            instance_models: HashMap::new(),
+            model_texture_array,
+            model_sampler_array,
+            color_attachment: self.color_attachment,
+            depth_attachment: self.depth_attachment,
+        })
+    }
+}
@@ -0,0 +1,192 @@
+//! Loading model files into CPU-side mesh data.
+
+use super::Vertex;
+use std::{
+    collections::HashMap,
+    io::Cursor,
+};
+use image::{
+    DynamicImage,
+    RgbaImage,
+    RgbImage,
+};
+use pear::*;
+use vek::*;
+
+/// Value of `Vertex::tex_index` for vertices with no texture.
+pub const NO_TEXTURE: u32 = !0;
+
+/// CPU-side mesh data for a model.
+#[derive(Clone, Debug, Default)]
+pub struct ModelData {
+    /// Vertices, where `tex_index` indexes into `textures`, or is
+    /// `NO_TEXTURE`.
+    pub vertices: Vec<Vertex>,
+    /// Triangle list indices into `vertices`.
+    pub indices: Vec<u32>,
+    /// Textures used by this model.
+    pub textures: Vec<RgbaImage>,
+}
+
+impl ModelData {
+    /// Texture the whole model with a single image, replacing its textures.
+    pub fn set_texture(&mut self, texture: RgbaImage) {
+        self.textures = vec![texture];
+        for vertex in &mut self.vertices {
+            vertex.tex_index = 0;
+        }
+    }
+}
+
+/// Load a glTF model.
+///
+/// The model must be self-contained, as either binary glTF or glTF with
+/// embedded buffers and images. Node transforms of the default scene are
+/// baked into the vertices, and each primitive is textured with its base
+/// color texture.
+pub fn load_gltf(bytes: &[u8]) -> Result<ModelData> {
+    let (document, buffers, images) = gltf::import_slice(bytes)?;
+    let scene = document.default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| pear!({}, "glTF document has no scenes"))?;
+
+    let mut model = ModelData::default();
+    // glTF image index -> model texture index
+    let mut texture_indices: HashMap<usize, u32> = HashMap::new();
+
+    let mut stack: Vec<(gltf::Node, Mat4<f32>)> = scene.nodes()
+        .map(|node| (node, Mat4::identity()))
+        .collect();
+    while let Some((node, parent_transform)) = stack.pop() {
+        let transform = parent_transform
+            * Mat4::from_col_arrays(node.transform().matrix());
+        for child in node.children() {
+            stack.push((child, transform));
+        }
+
+        let mesh = match node.mesh() {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                warn!(mode = ?primitive.mode(), "skipping non-triangle glTF primitive");
+                continue;
+            }
+
+            // texture
+            let image_index = primitive.material()
+                .pbr_metallic_roughness()
+                .base_color_texture()
+                .map(|info| info.texture().source().index());
+            let tex_index = match image_index {
+                Some(image_index) => match texture_indices.get(&image_index) {
+                    Some(&tex_index) => tex_index,
+                    None => {
+                        let tex_index = model.textures.len() as u32;
+                        model.textures.push(gltf_image_to_rgba(&images[image_index])?);
+                        texture_indices.insert(image_index, tex_index);
+                        tex_index
+                    }
+                },
+                None => NO_TEXTURE,
+            };
+
+            // vertices
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()][..]));
+            let positions = reader.read_positions()
+                .ok_or_else(|| pear!({}, "glTF primitive has no positions"))?
+                .collect::<Vec<[f32; 3]>>();
+            let tex_coords = reader.read_tex_coords(0)
+                .map(|tex_coords| tex_coords.into_f32().collect::<Vec<[f32; 2]>>())
+                .unwrap_or_else(|| vec![[0.0; 2]; positions.len()]);
+
+            let base = model.vertices.len() as u32;
+            for (&pos, &tex_coord) in positions.iter().zip(&tex_coords) {
+                let pos = transform.mul_point(Vec3::from(pos));
+                model.vertices.push(Vertex {
+                    pos,
+                    tex_coord: tex_coord.into(),
+                    tex_index,
+                });
+            }
+
+            // indices
+            match reader.read_indices() {
+                Some(indices) => model.indices
+                    .extend(indices.into_u32().map(|i| base + i)),
+                None => model.indices
+                    .extend(base..base + positions.len() as u32),
+            }
+        }
+    }
+
+    Ok(model)
+}
+
+/// Load a Wavefront OBJ model.
+///
+/// Material files are ignored. Instead, the whole model is textured with
+/// `texture`, which is the contents of an image file, such as PNG or JPEG.
+pub fn load_obj(bytes: &[u8], texture: Option<&[u8]>) -> Result<ModelData> {
+    let (models, _) = tobj::load_obj_buf(
+        &mut Cursor::new(bytes),
+        true,
+        |_| Ok((Vec::new(), HashMap::new())),
+    )?;
+
+    let mut model = ModelData::default();
+    let tex_index = match texture {
+        Some(bytes) => {
+            model.textures.push(image::load_from_memory(bytes)?.into_rgba());
+            0
+        }
+        None => NO_TEXTURE,
+    };
+
+    for obj_model in models {
+        let mesh = obj_model.mesh;
+        let base = model.vertices.len() as u32;
+        for i in 0..mesh.positions.len() / 3 {
+            let pos = Vec3::new(
+                mesh.positions[i * 3],
+                mesh.positions[i * 3 + 1],
+                mesh.positions[i * 3 + 2],
+            );
+            // OBJ texture coordinates have their origin at the bottom left
+            let tex_coord = match mesh.texcoords.get(i * 2..i * 2 + 2) {
+                Some(tex_coord) => Vec2::new(tex_coord[0], 1.0 - tex_coord[1]),
+                None => Vec2::zero(),
+            };
+            model.vertices.push(Vertex {
+                pos,
+                tex_coord,
+                tex_index,
+            });
+        }
+        model.indices.extend(mesh.indices.iter().map(|&i| base + i));
+    }
+
+    Ok(model)
+}
+
+fn gltf_image_to_rgba(data: &gltf::image::Data) -> Result<RgbaImage> {
+    use gltf::image::Format;
+
+    let image = match data.format {
+        Format::R8G8B8A8 => RgbaImage::from_raw(data.width, data.height, data.pixels.clone())
+            .map(DynamicImage::ImageRgba8),
+        Format::R8G8B8 => RgbImage::from_raw(data.width, data.height, data.pixels.clone())
+            .map(DynamicImage::ImageRgb8),
+        format => return Err(pear!(
+            { format = format },
+            "unsupported glTF image format",
+        )),
+    };
+    image
+        .map(DynamicImage::into_rgba)
+        .ok_or_else(|| pear!(
+            { width = data.width, height = data.height },
+            "glTF image data has wrong length",
+        ))
+}
@@ -0,0 +1,217 @@
+//! Graphics subsystem for drawing static models in the world.
+
+use super::*;
+use crate::graphics::{
+    render_graph::PassDecl,
+    util::{
+        vertex::GenericVertex,
+        uniform::GenericUniforms,
+        mesh_diff::MeshDiffer,
+        buffer_vec::BufferVec,
+    },
+};
+use std::{
+    collections::HashMap,
+    ops::Range,
+};
+use vek::*;
+use game_derive::{Vertex, Uniforms};
+
+pub mod builder;
+pub(super) mod load;
+
+/// Identifier for a model loaded into the `DrawModels` subsystem.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ModelId(pub(crate) usize);
+
+/// Graphics subsystem for drawing static models in the world.
+///
+/// All models share a vertex buffer and an index buffer, which are fixed
+/// once built. Each model has its own buffer of instance transforms.
+pub struct DrawModels {
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    uniform_buffer: Buffer,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    models: Vec<Model>,
+    // instance id -> model it's an instance of
+    instance_models: HashMap<u64, ModelId>,
+    model_texture_array: TextureView,
+    model_sampler_array: Sampler,
+    color_attachment: Attachment,
+    depth_attachment: Attachment,
+}
+
+/// A single model within the shared buffers, and its instances.
+struct Model {
+    base_vertex: i32,
+    indices: Range<u32>,
+    instance_differ: MeshDiffer<u64, [u8; Instance::SIZE]>,
+    instance_buffer: BufferVec<[u8; Instance::SIZE]>,
+}
+
+impl RenderNode for DrawModels {
+    fn name(&self) -> &'static str {
+        "draw models"
+    }
+
+    fn pass_decl(&self) -> PassDecl {
+        PassDecl {
+            color: vec![self.color_attachment],
+            depth: Some(self.depth_attachment),
+            depth_write: true,
+            ..Default::default()
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        gfx: &Graphics,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        // update instances
+        for model in &mut self.models {
+            let patch = model.instance_differ.commit();
+            model.instance_buffer.apply_patch(&patch, &gfx.device, uploader, command_encoder);
+        }
+
+        // set uniforms
+        let sky = gfx.sky_state();
+        let uniforms = Uniforms {
+            corr_proj_view: gfx.corr_proj() * gfx.view_cam.view(),
+            cam_pos: Vec4::from_point(gfx.view_cam.pos),
+            sun_dir: Vec4::from_direction(sky.sun_dir),
+            sun_color: Rgba::from_opaque(sky.sun_color),
+            ambient_color: Rgba::from_opaque(sky.ambient_color),
+            gamma: gfx.gamma,
+        };
+        uploader.upload(
+            &gfx.device,
+            command_encoder,
+            &self.uniform_buffer,
+            0,
+            &uniforms.encode(),
+        );
+
+        Ok(())
+    }
+
+    fn render<'a>(&'a self, _gfx: &'a Graphics, pass: &mut RenderPass<'a>) -> Result<()> {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..));
+
+        for model in &self.models {
+            let num_instances = model.instance_buffer.len_elems() as u32;
+            if num_instances == 0 {
+                continue;
+            }
+            pass.set_vertex_buffer(1, model.instance_buffer.as_buffer_slice());
+            pass.draw_indexed(model.indices.clone(), model.base_vertex, 0..num_instances);
+        }
+
+        Ok(())
+    }
+
+    fn draw_calls(&self) -> usize {
+        self.models
+            .iter()
+            .filter(|model| model.instance_buffer.len_elems() > 0)
+            .count()
+    }
+}
+
+impl DrawModels {
+    /// Take the instances from a `DrawModels` built on a lost device, and
+    /// upload them to this one.
+    pub fn restore_instances(
+        &mut self,
+        old: DrawModels,
+        device: &Device,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        self.instance_models = old.instance_models;
+        for (model, old_model) in self.models.iter_mut().zip(old.models) {
+            model.instance_differ = old_model.instance_differ;
+            let patch = model.instance_differ.full_patch();
+            model.instance_buffer.apply_patch(&patch, device, uploader, command_encoder);
+        }
+    }
+
+    /// Set the instance with a given ID to be an instance of `model` with the
+    /// given transform, overriding any existing instance with that ID.
+    pub fn set_instance(&mut self, id: u64, model: ModelId, transform: Mat4<f32>) {
+        if let Some(old_model) = self.instance_models.insert(id, model) {
+            if old_model != model {
+                self.models[old_model.0].instance_differ.stage(id, None);
+            }
+        }
+        let cols = transform.into_col_arrays();
+        let instance = Instance {
+            model_0: cols[0].into(),
+            model_1: cols[1].into(),
+            model_2: cols[2].into(),
+            model_3: cols[3].into(),
+        };
+        self.models[model.0].instance_differ.stage(id, Some(instance.encode()));
+    }
+
+    /// Remove the instance with a given ID, if it exists.
+    pub fn remove_instance(&mut self, id: u64) {
+        if let Some(model) = self.instance_models.remove(&id) {
+            self.models[model.0].instance_differ.stage(id, None);
+        }
+    }
+}
+
+/// Model vertex type.
+#[derive(Copy, Clone, Debug, Vertex)]
+pub struct Vertex {
+    #[attr(location = 0, glsl = "vec3")]
+    pub pos: Vec3<f32>,
+    #[attr(location = 1, glsl = "vec2")]
+    pub tex_coord: Vec2<f32>,
+    #[attr(location = 2, glsl = "uint")]
+    pub tex_index: u32,
+}
+
+/// Model instance type, which is the columns of the model matrix.
+#[derive(Copy, Clone, Debug, Vertex)]
+struct Instance {
+    #[attr(location = 3, glsl = "vec4")]
+    model_0: Vec4<f32>,
+    #[attr(location = 4, glsl = "vec4")]
+    model_1: Vec4<f32>,
+    #[attr(location = 5, glsl = "vec4")]
+    model_2: Vec4<f32>,
+    #[attr(location = 6, glsl = "vec4")]
+    model_3: Vec4<f32>,
+}
+
+/// Draw models uniform type.
+#[derive(Copy, Clone, Debug, Uniforms)]
+#[attr(size = 132)]
+pub struct Uniforms {
+    /// Correction * Projection * View
+    #[attr(glsl = "mat4")]
+    pub corr_proj_view: Mat4<f32>,
+    /// Camera position, with w unused.
+    #[attr(glsl = "vec4")]
+    pub cam_pos: Vec4<f32>,
+    /// Direction towards the sun, with w unused.
+    #[attr(glsl = "vec4")]
+    pub sun_dir: Vec4<f32>,
+    /// Direct sunlight color, with alpha unused.
+    #[attr(glsl = "vec4")]
+    pub sun_color: Rgba<f32>,
+    /// Ambient light color, with alpha unused.
+    #[attr(glsl = "vec4")]
+    pub ambient_color: Rgba<f32>,
+    /// Gamma adjustment.
+    #[attr(glsl = "float")]
+    pub gamma: f32,
+}
@@ -0,0 +1,39 @@
+#version 450
+
+layout(location = 0) in vec3 v_Pos;
+layout(location = 1) in vec2 v_TexCoord;
+layout(location = 2) flat in uint v_TexIndex;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 u_CorrProjView;
+    vec4 u_CamPos;
+    vec4 u_SunDir;
+    vec4 u_SunColor;
+    vec4 u_AmbientColor;
+    float u_Gamma;
+};
+
+layout(set = 0, binding = 1) uniform texture2DArray u_ModelTextureArray;
+layout(set = 0, binding = 2) uniform sampler u_ModelSamplerArray;
+
+// flat-shaded directional and ambient lighting
+vec3 lighting(vec3 pos) {
+    vec3 normal = normalize(cross(dFdx(pos), dFdy(pos)));
+    // faces are culled if they face away, so make the normal face the camera
+    if (dot(normal, u_CamPos.xyz - pos) < 0.0) {
+        normal = -normal;
+    }
+    float diffuse = max(dot(normal, u_SunDir.xyz), 0.0);
+    return u_AmbientColor.rgb + u_SunColor.rgb * diffuse;
+}
+
+void main() {
+    vec4 color = texture(
+        sampler2DArray(u_ModelTextureArray, u_ModelSamplerArray),
+        vec3(v_TexCoord, v_TexIndex)
+    );
+    vec3 lit = color.rgb * lighting(v_Pos);
+    o_Target = vec4(pow(lit, vec3(1.0 / u_Gamma)), color.a);
+}
@@ -0,0 +1,33 @@
+#version 450
+
+layout(location = 0) in vec3 a_Pos;
+layout(location = 1) in vec2 a_TexCoord;
+layout(location = 2) in uint a_TexIndex;
+
+// per-instance model matrix, by column
+layout(location = 3) in vec4 i_Model0;
+layout(location = 4) in vec4 i_Model1;
+layout(location = 5) in vec4 i_Model2;
+layout(location = 6) in vec4 i_Model3;
+
+layout(location = 0) out vec3 v_Pos;
+layout(location = 1) out vec2 v_TexCoord;
+layout(location = 2) flat out uint v_TexIndex;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 u_CorrProjView;
+    vec4 u_CamPos;
+    vec4 u_SunDir;
+    vec4 u_SunColor;
+    vec4 u_AmbientColor;
+    float u_Gamma;
+};
+
+void main() {
+    mat4 model = mat4(i_Model0, i_Model1, i_Model2, i_Model3);
+    vec4 pos = model * vec4(a_Pos, 1.0);
+    v_Pos = pos.xyz / pos.w;
+    v_TexCoord = a_TexCoord;
+    v_TexIndex = a_TexIndex;
+    gl_Position = u_CorrProjView * pos;
+}
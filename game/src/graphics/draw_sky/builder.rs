@@ -0,0 +1,139 @@
+//! `DrawSky` subsystem factory. 
+
+use super::*;
+use core::num::NonZeroU64;
+
+/// `DrawSky` subsystem factory. 
+pub struct DrawSkyBuilder {
+    color_attachment: Attachment,
+    depth_attachment: Attachment,
+}
+
+impl DrawSkyBuilder {
+    /// Create a new `DrawSkyBuilder` in its default state. 
+    pub fn new() -> Self {
+        DrawSkyBuilder {
+            color_attachment: Attachment::SwapchainColor,
+            depth_attachment: Attachment::Depth,
+        }
+    }
+
+    /// Set the color and depth attachments to render into. 
+    ///
+    /// By default, this is the swapchain and the shared depth buffer. 
+    pub fn set_attachments(&mut self, color: Attachment, depth: Attachment) {
+        self.color_attachment = color;
+        self.depth_attachment = depth;
+    }
+
+    /// Attempt to initialize the `DrawSky` subsystem.  
+    pub fn build(&self, gfx: &mut Graphics) -> Result<DrawSky> {
+        // buffers
+        let uniform_buffer = gfx.device
+            .create_buffer(&BufferDescriptor {
+                label: label("draw sky uniform buffer"),
+                size: Uniforms::SIZE as u64,
+                usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+        // shaders
+        let vert_module = gfx.create_shader_module(
+            "draw_sky/shader.vert.spv",
+            include_shader!("shader.vert.spv"),
+        );
+        let frag_module = gfx.create_shader_module(
+            "draw_sky/shader.frag.spv",
+            include_shader!("shader.frag.spv"),
+        );
+
+        // binding and pipeline
+        let bind_group_layout = gfx.device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: label("sky bind group layout"),
+                entries: cowslice![
+                    // uniform buffer
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStage::VERTEX | ShaderStage::FRAGMENT,
+                        ty: BindingType::UniformBuffer {
+                            dynamic: false,
+                            min_binding_size: Some(NonZeroU64::new(Uniforms::SIZE as u64).unwrap()),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let bind_group = gfx.device
+            .create_bind_group(&BindGroupDescriptor {
+                label: label("sky bind group"),
+                layout: &bind_group_layout,
+                entries: cowslice![
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(uniform_buffer.slice(..)),
+                    },
+                ],
+            });
+        let pipeline_layout = gfx.device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                bind_group_layouts: cowslice![&bind_group_layout],
+                push_constant_ranges: cowslice![],
+            });
+        let pipeline = gfx.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                layout: &pipeline_layout,
+                vertex_stage: ProgrammableStageDescriptor {
+                    module: &vert_module,
+                    entry_point: cowstr("main"),
+                },
+                fragment_stage: Some(ProgrammableStageDescriptor {
+                    module: &frag_module,
+                    entry_point: cowstr("main"),
+                }),
+                rasterization_state: Some(RasterizationStateDescriptor {
+                    front_face: FrontFace::Ccw,
+                    cull_mode: CullMode::None,
+                    clamp_depth: false,
+                    depth_bias: 0,
+                    depth_bias_slope_scale: 0.0,
+                    depth_bias_clamp: 0.0,
+                }),
+                primitive_topology: PrimitiveTopology::TriangleList,
+                color_states: cowslice![
+                    ColorStateDescriptor {
+                        format: SWAPCHAIN_FMT,
+                        color_blend: BlendDescriptor::REPLACE,
+                        alpha_blend: BlendDescriptor::REPLACE,
+                        write_mask: ColorWrite::ALL,
+                    },
+                ],
+                // only draw where the depth buffer is still clear
+                depth_stencil_state: Some(DepthStencilStateDescriptor {
+                    format: DEPTH_FMT,
+                    depth_write_enabled: false,
+                    depth_compare: gfx.depth_compare(),
+                    stencil_front: StencilStateFaceDescriptor::IGNORE,
+                    stencil_back: StencilStateFaceDescriptor::IGNORE,
+                    stencil_read_mask: 0,
+                    stencil_write_mask: 0,
+                }),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    // there are no vertex buffers at all
+                    index_format: IndexFormat::Uint16,
+                    vertex_buffers: cowslice![],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        Ok(DrawSky {
+            pipeline,
+            bind_group,
+            uniform_buffer,
+            color_attachment: self.color_attachment,
+            depth_attachment: self.depth_attachment,
+        })
+    }
+}
@@ -0,0 +1,93 @@
+//! Day/night cycle math, independent of rendering. 
+
+use crate::util::interp::smoothstep;
+use std::f32::consts::PI;
+use vek::*;
+
+/// Sky and sunlight parameters at a particular time of day. 
+///
+/// Colors are linear RGB. 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SkyState {
+    /// Unit vector pointing towards the sun. 
+    pub sun_dir: Vec3<f32>,
+    /// Color of the sky straight up. 
+    pub zenith_color: Rgb<f32>,
+    /// Color of the sky at the horizon. 
+    pub horizon_color: Rgb<f32>,
+    /// Color and intensity of direct sunlight. 
+    pub sun_color: Rgb<f32>,
+    /// Color and intensity of ambient light. 
+    pub ambient_color: Rgb<f32>,
+    /// Star visibility, from 0 to 1. 
+    pub star_visibility: f32,
+}
+
+const DAY_ZENITH: Rgb<f32> = Rgb { r: 0.15, g: 0.35, b: 0.85 };
+const DAY_HORIZON: Rgb<f32> = Rgb { r: 0.6, g: 0.75, b: 0.95 };
+const NIGHT_ZENITH: Rgb<f32> = Rgb { r: 0.002, g: 0.003, b: 0.01 };
+const NIGHT_HORIZON: Rgb<f32> = Rgb { r: 0.01, g: 0.015, b: 0.03 };
+const SUNSET_HORIZON: Rgb<f32> = Rgb { r: 0.9, g: 0.4, b: 0.15 };
+const DAY_SUN: Rgb<f32> = Rgb { r: 1.0, g: 0.95, b: 0.85 };
+const SUNSET_SUN: Rgb<f32> = Rgb { r: 1.0, g: 0.5, b: 0.2 };
+const DAY_AMBIENT: Rgb<f32> = Rgb { r: 0.35, g: 0.4, b: 0.45 };
+const NIGHT_AMBIENT: Rgb<f32> = Rgb { r: 0.04, g: 0.05, b: 0.08 };
+
+impl SkyState {
+    /// Compute the sky state at a time of day. 
+    ///
+    /// Time of day ranges from 0 to 1, and wraps around. 0 is midnight, 0.25 
+    /// is sunrise, 0.5 is noon, and 0.75 is sunset. The sun rises towards +X 
+    /// and sets towards -X. 
+    pub fn at(time_of_day: f32) -> Self {
+        let angle = (time_of_day.rem_euclid(1.0) - 0.25) * 2.0 * PI;
+        // tilt the sun's path slightly, so it's never directly overhead
+        let sun_dir = Vec3::new(angle.cos(), angle.sin(), 0.2).normalized();
+
+        // 0 at night, 1 during the day
+        let day = smoothstep(-0.1, 0.2, sun_dir.y);
+        // 1 when the sun is near the horizon
+        let sunset = 1.0 - (sun_dir.y.abs() / 0.3).min(1.0);
+
+        let zenith_color = Lerp::lerp(NIGHT_ZENITH, DAY_ZENITH, day);
+        let horizon_color = Lerp::lerp(
+            Lerp::lerp(NIGHT_HORIZON, DAY_HORIZON, day),
+            SUNSET_HORIZON,
+            sunset * 0.6,
+        );
+        let sun_color = Lerp::lerp(DAY_SUN, SUNSET_SUN, sunset)
+            * smoothstep(-0.05, 0.1, sun_dir.y);
+        let ambient_color = Lerp::lerp(NIGHT_AMBIENT, DAY_AMBIENT, day);
+        let star_visibility = 1.0 - smoothstep(-0.2, 0.05, sun_dir.y);
+
+        SkyState {
+            sun_dir,
+            zenith_color,
+            horizon_color,
+            sun_color,
+            ambient_color,
+            star_visibility,
+        }
+    }
+}
+
+#[test]
+fn sky_state_test() {
+    let noon = SkyState::at(0.5);
+    let midnight = SkyState::at(0.0);
+    let sunrise = SkyState::at(0.25);
+
+    assert!(noon.sun_dir.y > 0.9);
+    assert!(midnight.sun_dir.y < -0.9);
+    assert!(sunrise.sun_dir.x > 0.9);
+    assert!(sunrise.sun_dir.y.abs() < 0.001);
+
+    assert_eq!(noon.star_visibility, 0.0);
+    assert_eq!(midnight.star_visibility, 1.0);
+    assert_eq!(midnight.sun_color, Rgb::zero());
+    assert!(noon.ambient_color.r > midnight.ambient_color.r);
+
+    // wraps around
+    assert_eq!(SkyState::at(1.5), noon);
+    assert_eq!(SkyState::at(-0.5), noon);
+}
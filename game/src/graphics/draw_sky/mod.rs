@@ -0,0 +1,107 @@
+//! Graphics subsystem for drawing the sky, with a day/night cycle. 
+
+use super::*;
+use crate::graphics::{
+    render_graph::PassDecl,
+    util::uniform::GenericUniforms,
+};
+use vek::*;
+use game_derive::Uniforms;
+
+pub mod builder;
+pub mod cycle;
+
+/// Graphics subsystem for drawing the sky, with a day/night cycle. 
+///
+/// The sky is drawn at the far plane, wherever nothing else was drawn. 
+pub struct DrawSky {
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    uniform_buffer: Buffer,
+    color_attachment: Attachment,
+    depth_attachment: Attachment,
+}
+
+impl RenderNode for DrawSky {
+    fn name(&self) -> &'static str {
+        "draw sky"
+    }
+
+    fn pass_decl(&self) -> PassDecl {
+        PassDecl {
+            color: vec![self.color_attachment],
+            depth: Some(self.depth_attachment),
+            depth_write: false,
+            ..Default::default()
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        gfx: &Graphics,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        // set uniforms
+        let sky = gfx.sky_state();
+        // view matrix without translation, so the sky follows the camera
+        let view_rot = gfx.view_cam.view() * Mat4::translation_3d(gfx.view_cam.pos);
+        let uniforms = Uniforms {
+            inv_corr_proj_view_rot: (gfx.corr_proj() * view_rot).inverted(),
+            sun_dir: Vec4::from_direction(sky.sun_dir),
+            zenith_color: Rgba::from_opaque(sky.zenith_color),
+            horizon_color: Rgba::from_opaque(sky.horizon_color),
+            sun_color: Rgba::from_opaque(sky.sun_color),
+            star_visibility: sky.star_visibility,
+            gamma: gfx.gamma,
+            far_depth: gfx.clear_depth(),
+        };
+        uploader.upload(
+            &gfx.device,
+            command_encoder,
+            &self.uniform_buffer,
+            0,
+            &uniforms.encode(),
+        );
+
+        Ok(())
+    }
+
+    fn render<'a>(&'a self, _gfx: &'a Graphics, pass: &mut RenderPass<'a>) -> Result<()> {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+/// Draw sky uniform type. 
+#[derive(Copy, Clone, Debug, Uniforms)]
+#[attr(size = 140)]
+pub struct Uniforms {
+    /// Inverse of (Correction * Projection * View), where the view matrix has 
+    /// no translation. 
+    #[attr(glsl = "mat4")]
+    pub inv_corr_proj_view_rot: Mat4<f32>,
+    /// Direction towards the sun, with w unused. 
+    #[attr(glsl = "vec4")]
+    pub sun_dir: Vec4<f32>,
+    /// Sky color straight up, with alpha unused. 
+    #[attr(glsl = "vec4")]
+    pub zenith_color: Rgba<f32>,
+    /// Sky color at the horizon, with alpha unused. 
+    #[attr(glsl = "vec4")]
+    pub horizon_color: Rgba<f32>,
+    /// Direct sunlight color, with alpha unused. 
+    #[attr(glsl = "vec4")]
+    pub sun_color: Rgba<f32>,
+    /// Star visibility, from 0 to 1. 
+    #[attr(glsl = "float")]
+    pub star_visibility: f32,
+    /// Gamma adjustment. 
+    #[attr(glsl = "float")]
+    pub gamma: f32,
+    /// Depth of the far plane, which the sky is drawn at. 
+    #[attr(glsl = "float")]
+    pub far_depth: f32,
+}
@@ -0,0 +1,43 @@
+#version 450
+
+layout(location = 0) in vec3 v_Ray;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 u_InvCorrProjViewRot;
+    vec4 u_SunDir;
+    vec4 u_ZenithColor;
+    vec4 u_HorizonColor;
+    vec4 u_SunColor;
+    float u_StarVisibility;
+    float u_Gamma;
+    float u_FarDepth;
+};
+
+float hash(vec3 p) {
+    p = fract(p * 0.3183099 + 0.1);
+    p *= 17.0;
+    return fract(p.x * p.y * p.z * (p.x + p.y + p.z));
+}
+
+void main() {
+    vec3 ray = normalize(v_Ray);
+
+    // gradient from horizon to zenith, darkening below the horizon
+    float up = clamp(ray.y, 0.0, 1.0);
+    vec3 color = mix(u_HorizonColor.rgb, u_ZenithColor.rgb, sqrt(up));
+    color *= 1.0 - 0.5 * clamp(-ray.y * 4.0, 0.0, 1.0);
+
+    // stars
+    float star = step(0.9985, hash(floor(ray * 300.0)));
+    color += vec3(star * u_StarVisibility * clamp(ray.y * 8.0, 0.0, 1.0));
+
+    // sun disc, with a glow around it
+    float sun_dot = dot(ray, u_SunDir.xyz);
+    float sun = smoothstep(0.9990, 0.9995, sun_dot);
+    float glow = pow(max(sun_dot, 0.0), 64.0) * 0.3;
+    color += u_SunColor.rgb * (sun * 4.0 + glow);
+
+    o_Target = vec4(pow(color, vec3(1.0 / u_Gamma)), 1.0);
+}
@@ -0,0 +1,25 @@
+#version 450
+
+layout(location = 0) out vec3 v_Ray;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 u_InvCorrProjViewRot;
+    vec4 u_SunDir;
+    vec4 u_ZenithColor;
+    vec4 u_HorizonColor;
+    vec4 u_SunColor;
+    float u_StarVisibility;
+    float u_Gamma;
+    float u_FarDepth;
+};
+
+// single triangle which covers the whole screen, at the far plane
+void main() {
+    vec2 pos = vec2(
+        float((gl_VertexIndex << 1) & 2),
+        float(gl_VertexIndex & 2)
+    ) * 2.0 - 1.0;
+    vec4 world = u_InvCorrProjViewRot * vec4(pos, 1.0, 1.0);
+    v_Ray = world.xyz / world.w;
+    gl_Position = vec4(pos, u_FarDepth, 1.0);
+}
@@ -0,0 +1,222 @@
+//! `DrawSprites` subsystem factory. 
+
+use super::*;
+use crate::graphics::util::texture_array::TextureArrayBuilder;
+use core::num::NonZeroU64;
+use image::RgbaImage;
+use vek::*;
+
+const SPRITE_TEXTURE_SIZE: u32 = 32;
+
+/// `DrawSprites` subsystem factory. 
+pub struct DrawSpritesBuilder {
+    sprite_textures: TextureArrayBuilder,
+}
+
+impl DrawSpritesBuilder {
+    /// Create a new `DrawSpritesBuilder` in its default state. 
+    pub fn new() -> Self {
+        let mut sprite_textures = TextureArrayBuilder::new([SPRITE_TEXTURE_SIZE; 2]);
+        let white = sprite_textures.add_layer_image(RgbaImage::from_pixel(
+            SPRITE_TEXTURE_SIZE,
+            SPRITE_TEXTURE_SIZE,
+            image::Rgba([0xFF; 4]),
+        ));
+        debug_assert_eq!(white, WHITE_SPRITE_TEXTURE);
+        DrawSpritesBuilder {
+            sprite_textures,
+        }
+    }
+
+    /// Add a sprite texture to the sprite texture array. Return its index. 
+    ///
+    /// The parameter, `bytes`, is the contents of an image file, such as PNG 
+    /// or JPEG. This makes an educated guess about which format it is.  
+    pub fn add_sprite_texture(&mut self, bytes: &[u8]) -> Result<u32> {
+        trace!("adding sprite texture");
+        self.sprite_textures.add_layer(bytes)
+    }
+
+    /// Add an already-decoded sprite texture to the sprite texture array. 
+    /// Return its index. 
+    pub fn add_sprite_texture_image(&mut self, image: RgbaImage) -> u32 {
+        trace!("adding sprite texture");
+        self.sprite_textures.add_layer_image(image)
+    }
+
+    /// Attempt to initialize the `DrawSprites` subsystem.  
+    pub fn build(&self, gfx: &mut Graphics, command_encoder: &mut CommandEncoder) -> Result<DrawSprites> {
+        // buffers and textures
+        let instance_buffer = BufferVec::new(
+                &gfx.device, 
+                BufferUsage::VERTEX,
+                label("sprites instance buffer"),
+            );
+        let (
+            sprite_texture_array, 
+            sprite_sampler_array,
+        ) = self.sprite_textures.build(&gfx.device, command_encoder);
+
+        // pass uniforms as push constants if possible, to avoid copying them
+        // into a uniform buffer every frame
+        let push_constants = gfx
+            .supports_push_constants(<Uniforms as GenericPushConstants>::SIZE);
+        let uniform_buffer = match push_constants {
+            true => None,
+            false => Some(gfx.device
+                .create_buffer(&BufferDescriptor {
+                    label: label("draw sprites uniform buffer"),
+                    size: <Uniforms as GenericUniforms>::SIZE as u64,
+                    usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+                    mapped_at_creation: false,
+                })),
+        };
+
+        // shaders
+        let vert_module = match push_constants {
+            true => gfx.create_shader_module(
+                "draw_sprites/shader_push.vert.spv",
+                include_shader!("shader_push.vert.spv"),
+            ),
+            false => gfx.create_shader_module(
+                "draw_sprites/shader.vert.spv",
+                include_shader!("shader.vert.spv"),
+            ),
+        };
+        let frag_module = gfx.create_shader_module(
+            "draw_sprites/shader.frag.spv",
+            include_shader!("shader.frag.spv"),
+        );
+
+        // binding and pipeline
+        let mut bind_group_layout_entries = Vec::new();
+        let mut bind_group_entries = Vec::new();
+        if let Some(ref uniform_buffer) = uniform_buffer {
+            // uniform buffer
+            bind_group_layout_entries.push(BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::VERTEX,
+                ty: BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: Some(NonZeroU64::new(<Uniforms as GenericUniforms>::SIZE as u64).unwrap()),
+                },
+                count: None,
+            });
+            bind_group_entries.push(BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(uniform_buffer.slice(..)),
+            });
+        }
+        // sprite texture array
+        bind_group_layout_entries.push(BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStage::FRAGMENT,
+            ty: BindingType::SampledTexture {
+                dimension: TextureViewDimension::D2Array,
+                component_type: TextureComponentType::Float,
+                multisampled: false,
+            },
+            count: None,
+        });
+        bind_group_entries.push(BindGroupEntry {
+            binding: 1,
+            resource: BindingResource::TextureView(&sprite_texture_array),
+        });
+        // sprite texture sampler
+        bind_group_layout_entries.push(BindGroupLayoutEntry {
+            binding: 2,
+            visibility: ShaderStage::FRAGMENT,
+            ty: BindingType::Sampler {
+                comparison: false,
+            },
+            count: None,
+        });
+        bind_group_entries.push(BindGroupEntry {
+            binding: 2,
+            resource: BindingResource::Sampler(&sprite_sampler_array),
+        });
+
+        let bind_group_layout = gfx.device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: label("sprites bind group layout"),
+                entries: Cow::Owned(bind_group_layout_entries),
+            });
+        let bind_group = gfx.device
+            .create_bind_group(&BindGroupDescriptor {
+                label: label("sprites bind group"),
+                layout: &bind_group_layout,
+                entries: Cow::Owned(bind_group_entries),
+            });
+        let push_constant_ranges = match push_constants {
+            true => vec![<Uniforms as GenericPushConstants>::range()],
+            false => Vec::new(),
+        };
+        let pipeline_layout = gfx.device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                bind_group_layouts: cowslice![&bind_group_layout],
+                push_constant_ranges: Cow::Owned(push_constant_ranges),
+            });
+        let pipeline = gfx.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                layout: &pipeline_layout,
+                vertex_stage: ProgrammableStageDescriptor {
+                    module: &vert_module,
+                    entry_point: cowstr("main"),
+                },
+                fragment_stage: Some(ProgrammableStageDescriptor {
+                    module: &frag_module,
+                    entry_point: cowstr("main"),
+                }),
+                rasterization_state: Some(RasterizationStateDescriptor {
+                    front_face: FrontFace::Ccw,
+                    cull_mode: CullMode::None,
+                    clamp_depth: false,
+                    depth_bias: 0,
+                    depth_bias_slope_scale: 0.0,
+                    depth_bias_clamp: 0.0,
+                }),
+                primitive_topology: PrimitiveTopology::TriangleList,
+                color_states: cowslice![
+                    ColorStateDescriptor {
+                        format: SWAPCHAIN_FMT,
+                        color_blend: BlendDescriptor {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha_blend: BlendDescriptor {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        write_mask: ColorWrite::ALL,
+                    },
+                ],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    // it's actually not indexed at all
+                    index_format: IndexFormat::Uint16, 
+                    vertex_buffers: cowslice![
+                        VertexBufferDescriptor {
+                            stride: SpriteInstance::SIZE as u64,
+                            step_mode: InputStepMode::Instance,
+                            attributes: SpriteInstance::attributes(),
+                        },
+                    ],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+        Ok(DrawSprites {
+            pipeline,
+            bind_group,
+            uniform_buffer,
+            instance_buffer,
+            mesh_differ: MeshDiffer::new(),
+            sprite_texture_array,
+            sprite_sampler_array,
+        })
+    }
+}
@@ -0,0 +1,174 @@
+//! Graphics subsystem for drawing 2D sprites in screen space. 
+
+use super::*;
+use crate::graphics::{
+    render_graph::PassDecl,
+    util::{
+        vertex::GenericVertex,
+        uniform::GenericUniforms,
+        push_constant::GenericPushConstants,
+        mesh_diff::MeshDiffer,
+        buffer_vec::BufferVec,
+    },
+};
+use vek::*;
+use game_derive::{Vertex, Uniforms};
+
+pub mod builder;
+
+/// Index of the sprite texture which is solid white. 
+///
+/// This is always present, and is useful for drawing solid-colored rectangles.
+pub const WHITE_SPRITE_TEXTURE: u32 = 0;
+
+/// Graphics subsystem for drawing 2D sprites in screen space. 
+///
+/// Sprites are drawn over everything else, with alpha blending. The order 
+/// in which overlapping sprites are drawn is unspecified. 
+pub struct DrawSprites {
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    // none if uniforms are passed as push constants instead
+    uniform_buffer: Option<Buffer>,
+    mesh_differ: MeshDiffer<u64, [u8; SpriteInstance::SIZE]>,
+    instance_buffer: BufferVec<[u8; SpriteInstance::SIZE]>,
+    sprite_texture_array: TextureView,
+    sprite_sampler_array: Sampler,
+}
+
+impl RenderNode for DrawSprites {
+    fn name(&self) -> &'static str {
+        "draw sprites"
+    }
+
+    fn pass_decl(&self) -> PassDecl {
+        PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            ..Default::default()
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        gfx: &Graphics,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        // update instances
+        let patch = self.mesh_differ.commit();
+        self.instance_buffer.apply_patch(&patch, &gfx.device, uploader, command_encoder);
+
+        // set uniforms
+        let uniform_buffer = match self.uniform_buffer {
+            Some(ref uniform_buffer) => uniform_buffer,
+            None => return Ok(()),
+        };
+        let uniforms = Uniforms::new(gfx);
+        uploader.upload(
+            &gfx.device,
+            command_encoder,
+            uniform_buffer,
+            0,
+            &uniforms.encode(),
+        );
+
+        Ok(())
+    }
+
+    fn render<'a>(&'a self, gfx: &'a Graphics, pass: &mut RenderPass<'a>) -> Result<()> {
+        let num_sprites = self.instance_buffer.len_elems() as u32;
+        if num_sprites == 0 {
+            return Ok(());
+        }
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        if self.uniform_buffer.is_none() {
+            Uniforms::new(gfx).set(pass);
+        }
+        pass.set_vertex_buffer(0, self.instance_buffer.as_buffer_slice());
+        pass.draw(0..6, 0..num_sprites);
+
+        Ok(())
+    }
+
+    fn draw_calls(&self) -> usize {
+        (self.instance_buffer.len_elems() > 0) as usize
+    }
+}
+
+impl DrawSprites {
+    /// Take the sprites from a `DrawSprites` built on a lost device, and 
+    /// upload them to this one. 
+    pub fn restore_sprites(
+        &mut self,
+        old: DrawSprites,
+        device: &Device,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        self.mesh_differ = old.mesh_differ;
+        let patch = self.mesh_differ.full_patch();
+        self.instance_buffer.apply_patch(&patch, device, uploader, command_encoder);
+    }
+
+    /// Set the sprite with a given ID, overriding any existing sprite with 
+    /// that ID. 
+    ///
+    /// `rect` is in pixels, with the origin at the top-left of the window. 
+    /// `tex` is an index into the sprite texture array, and the texture is 
+    /// multiplied by `color`. 
+    pub fn set_sprite(&mut self, id: u64, rect: Rect<f32, f32>, tex: u32, color: Rgba<f32>) {
+        let instance = SpriteInstance {
+            min: Vec2::new(rect.x, rect.y),
+            max: Vec2::new(rect.x + rect.w, rect.y + rect.h),
+            tex_index: tex,
+            color: color.map(|c| (c.max(0.0).min(1.0) * 255.0).round() as u8),
+        };
+        self.mesh_differ.stage(id, once(instance.encode()));
+    }
+
+    /// Remove the sprite with a given ID, if it exists. 
+    pub fn remove_sprite(&mut self, id: u64) {
+        self.mesh_differ.stage(id, None);
+    }
+}
+
+/// Sprite instance type.
+#[derive(Copy, Clone, Debug, Vertex)]
+struct SpriteInstance {
+    #[attr(location = 0, glsl = "vec2")]
+    min: Vec2<f32>,
+    #[attr(location = 1, glsl = "vec2")]
+    max: Vec2<f32>,
+    #[attr(location = 2, glsl = "uint")]
+    tex_index: u32,
+    #[attr(location = 3, glsl = "vec4")]
+    color: Rgba<u8>,
+}
+
+/// Draw sprites uniform type. 
+#[derive(Copy, Clone, Debug, Uniforms)]
+#[attr(size = 8)]
+pub struct Uniforms {
+    /// Window size in pixels. 
+    #[attr(glsl = "vec2")]
+    pub screen_size: Vec2<f32>,
+}
+
+impl Uniforms {
+    fn new(gfx: &Graphics) -> Self {
+        Uniforms {
+            screen_size: Vec2::new(
+                gfx.window_size.width as f32,
+                gfx.window_size.height as f32,
+            ),
+        }
+    }
+}
+
+push_constants! {
+    Uniforms in VERTEX {
+        vec2 screen_size: Vec2<f32>,
+    }
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+layout(location = 1) flat in uint v_TexIndex;
+layout(location = 2) in vec4 v_Color;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 0, binding = 1) uniform texture2DArray u_SpriteTextureArray;
+layout(set = 0, binding = 2) uniform sampler u_SpriteSamplerArray;
+
+void main() {
+    o_Target = v_Color * texture(
+        sampler2DArray(u_SpriteTextureArray, u_SpriteSamplerArray),
+        vec3(v_TexCoord, v_TexIndex)
+    );
+}
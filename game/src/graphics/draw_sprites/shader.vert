@@ -0,0 +1,37 @@
+#version 450
+
+layout(location = 0) in vec2 i_Min;
+layout(location = 1) in vec2 i_Max;
+layout(location = 2) in uint i_TexIndex;
+layout(location = 3) in vec4 i_Color;
+
+layout(location = 0) out vec2 v_TexCoord;
+layout(location = 1) flat out uint v_TexIndex;
+layout(location = 2) out vec4 v_Color;
+
+layout(set = 0, binding = 0) uniform u_Uniforms {
+    vec2 u_ScreenSize;
+};
+
+// two triangles which cover the unit square
+const vec2 CORNERS[6] = vec2[6](
+    vec2(0.0, 0.0),
+    vec2(0.0, 1.0),
+    vec2(1.0, 1.0),
+    vec2(0.0, 0.0),
+    vec2(1.0, 1.0),
+    vec2(1.0, 0.0)
+);
+
+void main() {
+    vec2 corner = CORNERS[gl_VertexIndex];
+
+    // pixels, with the origin at the top left, to normalized device coordinates
+    vec2 pixel = mix(i_Min, i_Max, corner);
+    vec2 ndc = pixel / u_ScreenSize * 2.0 - 1.0;
+
+    v_TexCoord = corner;
+    v_TexIndex = i_TexIndex;
+    v_Color = i_Color;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
@@ -2,25 +2,61 @@
 
 use crate::util::{
     angle::*,
-    camera::{YawPitch, Camera},
+    camera::{YawPitch, Camera, Projection},
+    camera_effects::CameraEffects,
+    timestep::FixedTimestep,
 };
 use self::{
+    builder::GraphicsBuilder,
+    chunks::{
+        ChunkTracker,
+        ChunkEvent,
+    },
     draw_blocks::DrawBlocks,
+    draw_models::DrawModels,
+    draw_sky::{
+        DrawSky,
+        cycle::SkyState,
+    },
+    draw_sprites::DrawSprites,
+    draw_gui::DrawGui,
+    blit::Blit,
+    perf_overlay::{
+        PerfOverlay,
+        PerfStats,
+    },
+    render_graph::{
+        RenderGraph,
+        RenderNode,
+        Attachment,
+        RenderTargetId,
+    },
     util::{
         label,
         cowstr,
+        CORR,
+        render_target::RenderTarget,
+        uploader::Uploader,
+        headless::{
+            HeadlessOutput,
+            poll_device_until_ready,
+        },
     },
 };
+use crate::assets::Shader;
 use std::{
     borrow::Cow,
+    collections::HashMap,
     time::Instant,
     iter::once,
+    thread,
     sync::{
         mpsc, 
         Arc,
     },
 };
 use pear::*;
+use serde::{Serialize, Deserialize};
 use wgpu::{
     *, 
     util::{
@@ -30,8 +66,15 @@ use wgpu::{
 };
 use winit::{
     dpi::PhysicalSize,
-    event_loop::EventLoop,
-    window::Window,
+    event_loop::{
+        EventLoop,
+        EventLoopProxy,
+    },
+    window::{
+        Window,
+        Fullscreen,
+    },
+    monitor::MonitorHandle,
     event::{
         KeyboardInput,
         VirtualKeyCode,
@@ -39,51 +82,196 @@ use winit::{
     },
 };
 use futures::executor::block_on;
+use image::RgbaImage;
 use vek::*;
 
 pub mod builder;
+pub mod chunks;
+pub mod render_graph;
 #[macro_use]
 mod util;
 mod draw_blocks;
+mod draw_models;
+mod draw_sky;
+mod draw_sprites;
+mod draw_gui;
+mod blit;
+mod perf_overlay;
 
 pub use draw_blocks::Vertex as DrawBlocksVertex;
+pub use draw_models::{
+    ModelId,
+    load::{ModelData, load_gltf, load_obj},
+};
+pub use draw_sky::cycle::SkyState;
+pub use draw_sprites::WHITE_SPRITE_TEXTURE;
+pub use perf_overlay::PERF_OVERLAY_SPRITE_BASE;
+pub use util::{
+    render_target::RenderTargetSize,
+    mesh_diff::MeshDifferStats,
+    buffer_vec::BufferVecStats,
+    texture_manifest::{FaceTextureMap, ManifestFaces, TextureManifest},
+    bitmap_font::text_rects,
+};
+pub use wgpu::{
+    BackendBit,
+    PowerPreference,
+    AdapterInfo,
+    DeviceType,
+};
+
+/// Names of the shaders which subsystems use, which are their paths relative
+/// to the graphics source directory. 
+///
+/// Shaders are built into the executable, but can be replaced with ones
+/// loaded at runtime with `GraphicsBuilder::add_shader`. 
+pub const SHADERS: &[&str] = &[
+    "blit/shader.vert.spv",
+    "blit/shader.frag.spv",
+    "draw_blocks/shader.vert.spv",
+    "draw_blocks/shader.frag.spv",
+    "draw_gui/shader.vert.spv",
+    "draw_gui/shader_push.vert.spv",
+    "draw_gui/shader.frag.spv",
+    "draw_models/shader.vert.spv",
+    "draw_models/shader.frag.spv",
+    "draw_sky/shader.vert.spv",
+    "draw_sky/shader.frag.spv",
+    "draw_sprites/shader.vert.spv",
+    "draw_sprites/shader_push.vert.spv",
+    "draw_sprites/shader.frag.spv",
+];
 
 /// Texture format we use for the swapchain color.
 const SWAPCHAIN_FMT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
 /// Texture format we use for the depth buffer. 
 const DEPTH_FMT: TextureFormat = TextureFormat::Depth32Float;
-/// Swapchain clear color. 
-const CLEAR_COLOR: Color = Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 };
-/// Depth buffer clear value. 
+/// Default color attachment clear color. 
+const DEFAULT_CLEAR_COLOR: Rgba<f32> = Rgba { r: 0.1, g: 0.2, b: 0.3, a: 1.0 };
+/// Depth buffer clear value, which is the far plane. 
 const CLEAR_DEPTH: f32 = 1.0;
+/// Depth buffer clear value in reverse-Z mode. 
+const CLEAR_DEPTH_REVERSE_Z: f32 = 0.0;
 
 /// Game renderer. 
 pub struct Graphics {
     // core things
-    window: Arc<Window>,
-    surface: Surface,
+    instance: Instance,
+    output: Output,
     adapter: Adapter,
     device: Device,
     queue: Queue,
-    swapchain_desc: SwapChainDescriptor,
-    swapchain: SwapChain,
     depth_texture_desc: TextureDescriptor<'static>,
     depth_texture_view: TextureView,
     window_size: PhysicalSize<u32>,
     window_size_changed: bool,
+    fullscreen: FullscreenMode,
     events_recv: mpsc::Receiver<WinitEvent>,
+    render_targets: Vec<RenderTarget>,
+    uploader: Option<Uploader>,
+    // shaders loaded at runtime, by name
+    shaders: HashMap<String, Arc<Shader>>,
 
     // not so core things
     cam: Camera,
+    cam_effects: CameraEffects,
+    // camera with effects applied, which is what's rendered
+    view_cam: Camera,
+    time_of_day: f32,
+    day_length: Option<f32>,
+    last_draw: Option<Instant>,
+    gamma: f32,
+    clear_color: Rgba<f32>,
+    chunks: ChunkTracker,
+    chunk_event_sends: Vec<mpsc::Sender<ChunkEvent>>,
+    perf_overlay: Option<PerfOverlay>,
+    draw_calls: usize,
 
     // subsystems
     subsystems: Option<Subsystems>,
+    render_graph: RenderGraph,
+
+    // retained for re-initializing after device loss
+    builder: Option<GraphicsBuilder>,
+}
+
+/// Where `Graphics` renders frames to. 
+enum Output {
+    /// A window, through a swapchain. 
+    Window {
+        window: Arc<Window>,
+        surface: Surface,
+        swapchain_desc: SwapChainDescriptor,
+        swapchain: SwapChain,
+    },
+    /// An off-screen texture, with no window. 
+    Headless(HeadlessOutput),
+}
+
+/// Memory and churn statistics for a mesh. 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MeshStats {
+    /// Statistics of the CPU-side mesh. 
+    pub differ: MeshDifferStats,
+    /// Statistics of the GPU buffer. 
+    pub buffer: BufferVecStats,
+}
+
+/// Window fullscreen mode. 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FullscreenMode {
+    /// A normal window. 
+    Windowed,
+    /// A borderless window which covers the whole monitor. 
+    Borderless,
+    /// Exclusive fullscreen, using the monitor's largest video mode. 
+    Exclusive,
 }
 
 /// Subsystems get detached from `Graphics` so that we can call the subsystems 
 /// while also mutably passing them `Graphics`. 
 struct Subsystems {
     draw_blocks: DrawBlocks,
+    draw_models: DrawModels,
+    draw_sky: DrawSky,
+    blits: Vec<Blit>,
+    draw_sprites: DrawSprites,
+    draw_gui: DrawGui,
+}
+
+impl Subsystems {
+    /// View all subsystems as render graph nodes. 
+    ///
+    /// The index of each node in this list is its render graph node index. 
+    fn nodes(&self) -> Vec<&dyn RenderNode> {
+        let mut nodes: Vec<&dyn RenderNode> = vec![
+            &self.draw_blocks,
+            &self.draw_models,
+            &self.draw_sky,
+        ];
+        for blit in &self.blits {
+            nodes.push(blit);
+        }
+        nodes.push(&self.draw_sprites);
+        nodes.push(&self.draw_gui);
+        nodes
+    }
+
+    /// View all subsystems as mutable render graph nodes. 
+    fn nodes_mut(&mut self) -> Vec<&mut dyn RenderNode> {
+        let mut nodes: Vec<&mut dyn RenderNode> = vec![
+            &mut self.draw_blocks,
+            &mut self.draw_models,
+            &mut self.draw_sky,
+        ];
+        for blit in &mut self.blits {
+            nodes.push(blit);
+        }
+        nodes.push(&mut self.draw_sprites);
+        nodes.push(&mut self.draw_gui);
+        nodes
+    }
 }
 
 macro_rules! subsys {
@@ -93,6 +281,8 @@ macro_rules! subsys {
 impl Graphics {
     /// Draw a frame. 
     pub fn draw(&mut self) -> Result<()> {
+        puffin::profile_function!();
+
         // process events
         while let Ok(event) = self.events_recv.try_recv() {
             // detect resize
@@ -105,6 +295,43 @@ impl Graphics {
             }
         }
 
+        // advance time of day
+        let now = Instant::now();
+        let delta = self.last_draw
+            .map(|last_draw| (now - last_draw).as_secs_f32())
+            .unwrap_or(0.0);
+        if let Some(day_length) = self.day_length {
+            self.time_of_day = (self.time_of_day + delta / day_length).rem_euclid(1.0);
+        }
+        self.last_draw = Some(now);
+
+        // advance camera effects
+        self.cam_effects.update(delta);
+
+        // update performance overlay
+        if let Some(ref mut perf_overlay) = self.perf_overlay {
+            let subsystems = self.subsystems.as_mut().unwrap();
+            let stats = PerfStats {
+                draw_calls: self.draw_calls,
+                block_mesh: subsystems.draw_blocks.mesh_stats(),
+            };
+            perf_overlay.update(now, &stats, &mut subsystems.draw_sprites);
+        }
+
+        // evict and request chunks based on render distance
+        {
+            puffin::profile_scope!("chunks");
+            let mut chunk_events = Vec::new();
+            let mut evicted_blocks = Vec::new();
+            self.chunks.update(self.cam.pos, &mut chunk_events, &mut evicted_blocks);
+            for block in evicted_blocks {
+                subsys!(self,draw_blocks).set_block_mesh(block, None);
+            }
+            for event in chunk_events {
+                self.chunk_event_sends.retain(|send| send.send(event).is_ok());
+            }
+        }
+
         // create command encoder
         let mut command_encoder = self.device
             .create_command_encoder(&CommandEncoderDescriptor {
@@ -113,42 +340,347 @@ impl Graphics {
 
         // handle resize
         if self.window_size_changed {
-            self.swapchain_desc.width = self.window_size.width;
-            self.swapchain_desc.height = self.window_size.height;
-            self.swapchain = self.device.create_swap_chain(&self.surface, &self.swapchain_desc);
+            if let Output::Window { ref mut swapchain_desc, .. } = self.output {
+                swapchain_desc.width = self.window_size.width;
+                swapchain_desc.height = self.window_size.height;
+            }
+            self.recreate_swapchain();
             
             self.depth_texture_desc.size.width = self.window_size.width;
             self.depth_texture_desc.size.height = self.window_size.height;
             let depth_texture = self.device.create_texture(&self.depth_texture_desc);
             self.depth_texture_view = depth_texture.create_default_view();
 
+            let window_size = Extent2::new(self.window_size.width, self.window_size.height);
+            for target in &mut self.render_targets {
+                target.window_resized(&self.device, window_size);
+            }
+
             self.cam.aspect_ratio = self.window_size.width as f32 / self.window_size.height as f32;
         }
         self.window_size_changed = false;
 
         // create frame
         // must be done AFTER rebuilding swapchain
-        let mut frame = self.swapchain.get_current_frame()?;
+        let frame = match self.output {
+            Output::Window { .. } => match self.next_frame()? {
+                Some(frame) => Some(frame),
+                None => return Ok(()),
+            },
+            Output::Headless(_) => None,
+        };
+
+        // the aspect ratio may have just changed
+        self.view_cam = self.cam_effects.apply(&self.cam);
 
         // draw subsystems
         let mut subsystems = self.subsystems.take().unwrap();
+        let mut uploader = self.uploader.take().unwrap();
         let mut subsys_errs = Vec::new();
-        subsystems.draw_blocks.draw(self, &mut frame, &mut command_encoder)
+        for node in subsystems.nodes_mut() {
+            puffin::profile_scope!("prepare", node.name());
+            node.prepare(self, &mut uploader, &mut command_encoder)
+                .push_err(&mut subsys_errs);
+        }
+        self.draw_calls = subsystems.nodes()
+            .iter()
+            .map(|node| node.draw_calls())
+            .sum();
+        self.render_passes(&subsystems, frame.as_ref(), &mut command_encoder)
             .push_err(&mut subsys_errs);
         self.subsystems = Some(subsystems);
+        self.uploader = Some(uploader);
         if !subsys_errs.is_empty() {
             return Err(subsys_errs.wrap(pear!({}, "drawing subsystem failure")));
         }
 
         // submit command encoder
+        puffin::profile_scope!("submit");
+        let uploader = self.uploader.as_mut().unwrap();
+        uploader.finish();
         self.queue.submit(once(command_encoder.finish()));
+        uploader.recall(&self.device);
 
         // request redraw
-        self.window.request_redraw();
+        self.request_redraw();
+
+        Ok(())
+    }
+
+    /// Get the next swapchain frame, or `None` if this frame should be 
+    /// skipped. 
+    ///
+    /// Handles an outdated or lost swapchain, and re-initializes if the 
+    /// device is lost. 
+    fn next_frame(&mut self) -> Result<Option<SwapChainFrame>> {
+        let next = |output: &mut Output| match output {
+            Output::Window { swapchain, .. } => swapchain.get_current_frame(),
+            Output::Headless(_) => unreachable!("headless output has no swapchain"),
+        };
+        match next(&mut self.output) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(SwapChainError::Outdated) => {
+                debug!("swapchain outdated, skipping frame");
+                self.recreate_swapchain();
+                self.request_redraw();
+                Ok(None)
+            }
+            Err(SwapChainError::Timeout) => {
+                warn!("swapchain timed out, skipping frame");
+                self.request_redraw();
+                Ok(None)
+            }
+            Err(SwapChainError::Lost) => {
+                warn!("swapchain lost, recreating");
+                self.recreate_swapchain();
+                match next(&mut self.output) {
+                    Ok(frame) => Ok(Some(frame)),
+                    Err(e) => {
+                        warn!(error = %e, "swapchain lost again, re-initializing device");
+                        self.reinitialize()?;
+                        self.request_redraw();
+                        Ok(None)
+                    }
+                }
+            }
+            Err(SwapChainError::OutOfMemory) => {
+                warn!("swapchain out of memory, re-initializing device");
+                self.reinitialize()?;
+                self.request_redraw();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Re-create the swapchain with the current swapchain descriptor, if 
+    /// rendering to a window. 
+    fn recreate_swapchain(&mut self) {
+        if let Output::Window {
+            ref surface,
+            ref swapchain_desc,
+            ref mut swapchain,
+            ..
+        } = self.output {
+            *swapchain = self.device.create_swap_chain(surface, swapchain_desc);
+        }
+    }
+
+    /// Request that the window be redrawn, if rendering to a window. 
+    fn request_redraw(&self) {
+        if let Output::Window { ref window, .. } = self.output {
+            window.request_redraw();
+        }
+    }
+
+    /// Re-create the surface, device, and all GPU resources. 
+    ///
+    /// This is done automatically when the device is lost. Subsystems are 
+    /// re-built from the retained `GraphicsBuilder`, and block meshes are 
+    /// re-uploaded from their CPU-side copies. 
+    pub fn reinitialize(&mut self) -> Result<()> {
+        info!("re-initializing graphics device");
+        let builder = self.builder.take()
+            .ok_or_else(|| pear!({}, "graphics builder missing, re-initialization failed"))?;
+
+        // core things
+        let (adapter, device, queue) = match self.output {
+            Output::Window { ref window, ref mut surface, .. } => {
+                *surface = unsafe {
+                    self.instance.create_surface(&**window)
+                };
+                self.window_size = window.inner_size();
+                builder.request_device(&self.instance, Some(&*surface))?
+            }
+            Output::Headless(_) => builder.request_device(&self.instance, None)?,
+        };
+        self.adapter = adapter;
+        self.device = device;
+        self.queue = queue;
+        match self.output {
+            Output::Window { ref mut swapchain_desc, .. } => {
+                swapchain_desc.width = self.window_size.width;
+                swapchain_desc.height = self.window_size.height;
+            }
+            Output::Headless(ref mut headless) => {
+                *headless = HeadlessOutput::new(
+                    &self.device,
+                    Extent2::new(self.window_size.width, self.window_size.height),
+                    SWAPCHAIN_FMT,
+                );
+            }
+        }
+        self.recreate_swapchain();
+        self.depth_texture_desc.size.width = self.window_size.width;
+        self.depth_texture_desc.size.height = self.window_size.height;
+        let depth_texture = self.device.create_texture(&self.depth_texture_desc);
+        self.depth_texture_view = depth_texture.create_default_view();
+        self.render_targets = builder.create_render_targets(&self.device, self.window_size);
+        self.uploader = Some(Uploader::new());
+        self.window_size_changed = false;
+
+        // subsystems
+        let old_subsystems = self.subsystems.take().unwrap();
+        let built = builder.build_subsystems(self);
+        self.builder = Some(builder);
+        let mut subsystems = built?;
+
+        let mut command_encoder = self.device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: label("restore command encoder"),
+            });
+        let uploader = self.uploader.as_mut().unwrap();
+        subsystems.draw_blocks.restore_mesh(
+            old_subsystems.draw_blocks,
+            &self.device,
+            uploader,
+            &mut command_encoder,
+        );
+        subsystems.draw_models.restore_instances(
+            old_subsystems.draw_models,
+            &self.device,
+            uploader,
+            &mut command_encoder,
+        );
+        subsystems.draw_sprites.restore_sprites(
+            old_subsystems.draw_sprites,
+            &self.device,
+            uploader,
+            &mut command_encoder,
+        );
+        subsystems.draw_gui.restore_gui(old_subsystems.draw_gui);
+        uploader.finish();
+        self.queue.submit(once(command_encoder.finish()));
+        uploader.recall(&self.device);
+        self.subsystems = Some(subsystems);
 
         Ok(())
     }
 
+    /// Record the render graph's render passes, in order. 
+    ///
+    /// Attachments are cleared as the render graph dictates, and each render 
+    /// pass is shared by all of its nodes. 
+    fn render_passes(
+        &self,
+        subsystems: &Subsystems,
+        frame: Option<&SwapChainFrame>,
+        command_encoder: &mut CommandEncoder,
+    ) -> std::result::Result<(), Vec<Error>> {
+        let nodes = subsystems.nodes();
+        let mut errors = Vec::new();
+        for p in 0..self.render_graph.passes().len() {
+            let pass_nodes = self.render_graph.pass_nodes(p);
+            let decl = self.render_graph.decl(pass_nodes[0]);
+            let color_attachments = decl.color
+                .iter()
+                .map(|&attachment| RenderPassColorAttachmentDescriptor {
+                    attachment: self.attachment_view(attachment, frame),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: if self.render_graph.pass_clears(p, attachment) {
+                            LoadOp::Clear(Color {
+                                r: self.clear_color.r as f64,
+                                g: self.clear_color.g as f64,
+                                b: self.clear_color.b as f64,
+                                a: self.clear_color.a as f64,
+                            })
+                        } else {
+                            LoadOp::Load
+                        },
+                        store: true,
+                    },
+                })
+                .collect::<Vec<_>>();
+            let depth_stencil_attachment = decl.depth
+                .map(|attachment| RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: self.attachment_view(attachment, frame),
+                    depth_ops: Some(Operations {
+                        load: if self.render_graph.pass_clears(p, attachment) {
+                            LoadOp::Clear(self.clear_depth())
+                        } else {
+                            LoadOp::Load
+                        },
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                });
+            let mut pass = command_encoder
+                .begin_render_pass(&RenderPassDescriptor {
+                    color_attachments: Cow::Owned(color_attachments),
+                    depth_stencil_attachment,
+                });
+            for &i in pass_nodes {
+                puffin::profile_scope!("render", self.render_graph.name(i));
+                nodes[i].render(self, &mut pass)
+                    .wrap_err(|| pear!(
+                        { node = self.render_graph.name(i) },
+                        "render graph node failure",
+                    ))
+                    .push_err(&mut errors);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Depth of the far plane, which the depth buffer is cleared to. 
+    fn clear_depth(&self) -> f32 {
+        if self.cam.reverse_z {
+            CLEAR_DEPTH_REVERSE_Z
+        } else {
+            CLEAR_DEPTH
+        }
+    }
+
+    /// Depth comparison for pipelines which depth test, passing if the 
+    /// fragment is at least as near as the depth buffer. 
+    fn depth_compare(&self) -> CompareFunction {
+        if self.cam.reverse_z {
+            CompareFunction::GreaterEqual
+        } else {
+            CompareFunction::LessEqual
+        }
+    }
+
+    /// Compute the corrected projection matrix. 
+    ///
+    /// The reverse-Z projection is already zero-to-one, so it is not 
+    /// corrected. 
+    fn corr_proj(&self) -> Mat4<f32> {
+        if self.cam.reverse_z {
+            self.cam.proj()
+        } else {
+            CORR * self.cam.proj()
+        }
+    }
+
+    /// Resolve a render graph attachment to a texture view. 
+    fn attachment_view<'a>(
+        &'a self,
+        attachment: Attachment,
+        frame: Option<&'a SwapChainFrame>,
+    ) -> &'a TextureView {
+        match attachment {
+            Attachment::SwapchainColor => match (frame, &self.output) {
+                (Some(frame), _) => &frame.output.view,
+                (None, &Output::Headless(ref headless)) => headless.view(),
+                (None, &Output::Window { .. }) => unreachable!("no swapchain frame"),
+            },
+            Attachment::Depth => &self.depth_texture_view,
+            Attachment::TargetColor(id) => self.render_target(id).color_view(),
+            Attachment::TargetDepth(id) => self.render_target(id).depth_view()
+                .expect("render target has no depth texture"),
+        }
+    }
+
+    /// Get an off-screen render target. 
+    pub fn render_target(&self, id: RenderTargetId) -> &RenderTarget {
+        &self.render_targets[id.0]
+    }
+
     /// Get the camera position. 
     pub fn cam_pos(&self) -> Vec3<f32> {
         self.cam.pos
@@ -169,19 +701,262 @@ impl Graphics {
         &mut self.cam.dir
     }
 
-    /// Get the camera field of view. 
-    pub fn cam_fov(&self) -> Angle<f32> {
-        self.cam.fov
+    /// Get the camera roll. 
+    pub fn cam_roll(&self) -> Angle<f32> {
+        self.cam.roll
+    }
+
+    /// Get the camera roll by mutable reference. 
+    pub fn cam_roll_mut(&mut self) -> &mut Angle<f32> {
+        &mut self.cam.roll
+    }
+
+    /// Get the camera projection mode. 
+    pub fn cam_projection(&self) -> Projection {
+        self.cam.projection
+    }
+
+    /// Get the camera projection mode by mutable reference. 
+    pub fn cam_projection_mut(&mut self) -> &mut Projection {
+        &mut self.cam.projection
+    }
+
+    /// Get the camera effects, which are composited onto the camera when 
+    /// it's rendered. 
+    pub fn camera_effects(&self) -> &CameraEffects {
+        &self.cam_effects
+    }
+
+    /// Get the camera effects by mutable reference, such as to shake the 
+    /// camera with `add_shake`. 
+    pub fn camera_effects_mut(&mut self) -> &mut CameraEffects {
+        &mut self.cam_effects
+    }
+
+    /// Get the time of day. 
+    ///
+    /// Time of day ranges from 0 to 1. 0 is midnight, 0.25 is sunrise, 0.5 is 
+    /// noon, and 0.75 is sunset. 
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    /// Set the time of day, wrapping it into the range 0 to 1. 
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
     }
 
-    /// Get the camera field of view by mutable reference. 
-    pub fn cam_fov_mut(&mut self) -> &mut Angle<f32> {
-        &mut self.cam.fov
+    /// Get the length of a full day, in seconds, or `None` if the time of day 
+    /// is not advanced automatically. 
+    pub fn day_length(&self) -> Option<f32> {
+        self.day_length
+    }
+
+    /// Set the length of a full day, in seconds, or `None` to stop the time 
+    /// of day from advancing automatically. 
+    pub fn set_day_length(&mut self, day_length: Option<f32>) {
+        self.day_length = day_length;
+    }
+
+    /// Get the sky and sunlight parameters for the current time of day. 
+    pub fn sky_state(&self) -> SkyState {
+        SkyState::at(self.time_of_day)
+    }
+
+    /// Get the gamma adjustment. 
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+
+    /// Set the gamma adjustment, which is applied to the world but not to 
+    /// sprites. 
+    ///
+    /// Values above 1 brighten dark colors, and values below 1 darken them. 
+    /// The default is 1, which does nothing. Panics if `gamma` is not a 
+    /// positive number. 
+    pub fn set_gamma(&mut self, gamma: f32) {
+        assert!(gamma > 0.0 && gamma.is_finite(), "invalid gamma: {}", gamma);
+        self.gamma = gamma;
+    }
+
+    /// Get the color which color attachments are cleared to each frame. 
+    pub fn clear_color(&self) -> Rgba<f32> {
+        self.clear_color
+    }
+
+    /// Set the color which color attachments are cleared to each frame. 
+    ///
+    /// This is the background wherever nothing else is drawn, such as in 
+    /// render targets which the sky isn't drawn into. It can be changed every 
+    /// frame, for example to tint the background underwater or at night. 
+    pub fn set_clear_color(&mut self, color: Rgba<f32>) {
+        self.clear_color = color;
+    }
+
+    /// Show or hide an overlay in the top-left corner of the window, which 
+    /// displays the FPS, frame time, draw calls, and block mesh buffer size. 
+    ///
+    /// The overlay is drawn with sprites, using the IDs from 
+    /// `PERF_OVERLAY_SPRITE_BASE` up. 
+    pub fn show_perf_overlay(&mut self, show: bool) {
+        match (show, self.perf_overlay.take()) {
+            (true, perf_overlay) => {
+                self.perf_overlay = Some(perf_overlay.unwrap_or_else(PerfOverlay::new));
+            }
+            (false, Some(mut perf_overlay)) => {
+                perf_overlay.clear(&mut subsys!(self,draw_sprites));
+            }
+            (false, None) => (),
+        }
+    }
+
+    /// Get the number of draw calls recorded in the last frame. 
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    /// Create a shader module from the shader added with
+    /// `GraphicsBuilder::add_shader` under `name`, or from the built-in
+    /// shader `builtin` if there isn't one. 
+    pub(crate) fn create_shader_module(
+        &self,
+        name: &str,
+        builtin: ShaderModuleSource<'static>,
+    ) -> ShaderModule {
+        match self.shaders.get(name) {
+            Some(shader) => self.device
+                .create_shader_module(ShaderModuleSource::SpirV(Cow::Borrowed(&shader.0[..]))),
+            None => self.device.create_shader_module(builtin),
+        }
+    }
+
+    /// Whether push constants of a given size can be used, which requires 
+    /// the `PUSH_CONSTANTS` feature. 
+    pub fn supports_push_constants(&self, size: usize) -> bool {
+        self.device.features().contains(Features::PUSH_CONSTANTS)
+            && size as u64 <= self.device.limits().max_push_constant_size as u64
+    }
+
+    /// Get the render distance, in chunks, or `None` if unlimited. 
+    pub fn render_distance(&self) -> Option<u32> {
+        self.chunks.render_distance()
+    }
+
+    /// Set the render distance, in chunks, or `None` for unlimited. 
+    ///
+    /// When limited, block meshes in chunks beyond the render distance from 
+    /// the camera are removed automatically. See 
+    /// `Graphics::subscribe_chunk_events` to be notified of which chunks to 
+    /// load and unload. 
+    pub fn set_render_distance(&mut self, render_distance: Option<u32>) {
+        self.chunks.set_render_distance(render_distance);
+    }
+
+    /// Subscribe to notifications of chunks coming into and going out of 
+    /// render distance. 
+    ///
+    /// Events are sent while drawing. 
+    pub fn subscribe_chunk_events(&mut self) -> mpsc::Receiver<ChunkEvent> {
+        let (send, recv) = mpsc::channel();
+        self.chunk_event_sends.push(send);
+        recv
     }
 
     /// Get the winit window. 
+    ///
+    /// Panics if rendering headlessly. 
     pub fn window(&self) -> &Arc<Window> {
-        &self.window
+        match self.output {
+            Output::Window { ref window, .. } => window,
+            Output::Headless(_) => panic!("headless graphics has no window"),
+        }
+    }
+
+    /// Whether this renders into an off-screen texture rather than a window. 
+    ///
+    /// See `GraphicsBuilder::headless`. 
+    pub fn is_headless(&self) -> bool {
+        match self.output {
+            Output::Window { .. } => false,
+            Output::Headless(_) => true,
+        }
+    }
+
+    /// Read back the most recently drawn frame, when rendering headlessly. 
+    ///
+    /// This blocks until the GPU has finished drawing. Errors if not 
+    /// rendering headlessly. 
+    pub fn read_frame(&self) -> Result<RgbaImage> {
+        match self.output {
+            Output::Headless(ref headless) => Ok(poll_device_until_ready(
+                &self.device,
+                headless.read_back(&self.device, &self.queue),
+            )),
+            Output::Window { .. } => Err(pear!({}, "can only read frames when headless")),
+        }
+    }
+
+    /// Get the current fullscreen mode. 
+    pub fn fullscreen(&self) -> FullscreenMode {
+        self.fullscreen
+    }
+
+    /// Set the fullscreen mode, on the monitor the window is currently on. 
+    ///
+    /// Panics if rendering headlessly. 
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        let monitor = self.window().current_monitor();
+        self.set_fullscreen_on(mode, monitor);
+    }
+
+    /// Set the fullscreen mode, on a particular monitor. 
+    ///
+    /// See `Graphics::monitors` to list the available monitors. Panics if 
+    /// rendering headlessly. 
+    pub fn set_fullscreen_on(&mut self, mode: FullscreenMode, monitor: MonitorHandle) {
+        let fullscreen = match mode {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            FullscreenMode::Exclusive => {
+                let video_mode = monitor.video_modes()
+                    .max_by_key(|video_mode| (
+                        video_mode.size().width * video_mode.size().height,
+                        video_mode.refresh_rate(),
+                        video_mode.bit_depth(),
+                    ));
+                match video_mode {
+                    Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                    None => {
+                        warn!("monitor has no video modes, using borderless fullscreen");
+                        Some(Fullscreen::Borderless(monitor))
+                    }
+                }
+            }
+        };
+        debug!(?mode, "setting fullscreen mode");
+        self.window().set_fullscreen(fullscreen);
+        self.fullscreen = mode;
+
+        // don't wait for the OS to send a resize event
+        self.window_size = self.window().inner_size();
+        self.window_size_changed = true;
+    }
+
+    /// Toggle between windowed and borderless fullscreen. 
+    ///
+    /// If currently in exclusive fullscreen, this switches to windowed. 
+    pub fn toggle_fullscreen(&mut self) {
+        match self.fullscreen {
+            FullscreenMode::Windowed => self.set_fullscreen(FullscreenMode::Borderless),
+            _ => self.set_fullscreen(FullscreenMode::Windowed),
+        }
+    }
+
+    /// List the available monitors. 
+    ///
+    /// Panics if rendering headlessly. 
+    pub fn monitors(&self) -> Vec<MonitorHandle> {
+        self.window().available_monitors().collect()
     }
 
     /// Set the mesh geometry for a single block. 
@@ -191,16 +966,105 @@ impl Graphics {
     where
         I: IntoIterator<Item=[DrawBlocksVertex; 3]>
     {
+        self.chunks.block_meshed(pos);
         subsys!(self,draw_blocks).set_block_mesh(pos, mesh)
     }
+
+    /// Remove the mesh geometry for a single block, if it has any. 
+    pub fn remove_block_mesh(&mut self, pos: Vec3<i32>) {
+        if self.chunks.is_block_meshed(pos) {
+            self.chunks.block_unmeshed(pos);
+            subsys!(self,draw_blocks).set_block_mesh(pos, None);
+        }
+    }
+
+    /// Look up a named block texture's index. 
+    pub fn block_texture_index(&self, name: &str) -> Option<u32> {
+        self.subsystems.as_ref().unwrap().draw_blocks.block_texture_index(name)
+    }
+
+    /// Replace a named block texture's image while running, such as after 
+    /// its file was modified. 
+    ///
+    /// The texture is overwritten in place, so block meshes which use it 
+    /// don't need to change. Errors if there's no block texture with that 
+    /// name. 
+    pub fn reload_block_texture(&mut self, name: &str, image: RgbaImage) -> Result<()> {
+        let layer = self.block_texture_index(name)
+            .ok_or_else(|| pear!({name=name}, "no block texture with that name"))?;
+        debug!(name, layer, "reloading block texture");
+        if let Some(ref mut builder) = self.builder {
+            builder.replace_block_texture(layer, image.clone());
+        }
+        let mut command_encoder = self.device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: label("texture reload command encoder"),
+            });
+        self.subsystems.as_ref().unwrap().draw_blocks
+            .write_block_texture(layer, image, &self.device, &mut command_encoder);
+        self.queue.submit(once(command_encoder.finish()));
+        Ok(())
+    }
+
+    /// Get memory and churn statistics for the block mesh. 
+    pub fn block_mesh_stats(&self) -> MeshStats {
+        self.subsystems.as_ref().unwrap().draw_blocks.mesh_stats()
+    }
+
+    /// Set the model instance with a given ID to be an instance of `model` 
+    /// with the given transform, overriding any existing instance with that 
+    /// ID. 
+    pub fn set_model_instance(&mut self, id: u64, model: ModelId, transform: Mat4<f32>) {
+        subsys!(self,draw_models).set_instance(id, model, transform)
+    }
+
+    /// Remove the model instance with a given ID, if it exists. 
+    pub fn remove_model_instance(&mut self, id: u64) {
+        subsys!(self,draw_models).remove_instance(id)
+    }
+
+    /// Set the sprite with a given ID, overriding any existing sprite with 
+    /// that ID. 
+    ///
+    /// `rect` is in pixels, with the origin at the top-left of the window. 
+    /// `tex` is an index into the sprite texture array, and the texture is 
+    /// multiplied by `color`. Use `WHITE_SPRITE_TEXTURE` to draw a solid 
+    /// color. IDs from `PERF_OVERLAY_SPRITE_BASE` up are reserved. 
+    pub fn set_sprite(&mut self, id: u64, rect: Rect<f32, f32>, tex: u32, color: Rgba<f32>) {
+        subsys!(self,draw_sprites).set_sprite(id, rect, tex, color)
+    }
+
+    /// Remove the sprite with a given ID, if it exists. 
+    pub fn remove_sprite(&mut self, id: u64) {
+        subsys!(self,draw_sprites).remove_sprite(id)
+    }
+
+    /// Set the egui user interface to draw over everything else, until it's 
+    /// next set. 
+    ///
+    /// `meshes` are as tessellated by egui, and `font_texture` is egui's font 
+    /// texture, which is only re-uploaded when its version changes. Meshes 
+    /// are in points, which are `pixels_per_point` pixels each. 
+    pub fn set_gui(
+        &mut self,
+        meshes: Vec<egui::ClippedMesh>,
+        font_texture: Arc<egui::Texture>,
+        pixels_per_point: f32,
+    ) {
+        subsys!(self,draw_gui).set_gui(meshes, font_texture, pixels_per_point)
+    }
 }
 
 /// Wrapper around `winit::event_loop::EventLoop`. 
 ///
-/// Hijacks the main thread and never returns. 
+/// Hijacks the main thread and never returns. The main thread only pumps 
+/// events, while frames are handled on a separate render thread, so that 
+/// the OS blocking the event loop, such as while the window is being 
+/// dragged, doesn't freeze the game. 
 pub struct MainHijacker {
     event_loop: EventLoop<()>,
     events_send: mpsc::Sender<WinitEvent>,
+    timestep: FixedTimestep,
 }
 
 /// Winit event type. 
@@ -213,65 +1077,98 @@ pub type ControlFlow = winit::event_loop::ControlFlow;
 pub type WindowEvent = winit::event::WindowEvent<'static>;
 
 /// Handle for a `MainHijacker` frame. 
-pub trait FrameHandler: 'static {
-    /// Handle a single frame, with all the events that have accumulated since 
-    /// last frame. 
+///
+/// This runs on the render thread, so it must be `Send`. 
+///
+/// The simulation is advanced in fixed ticks, decoupled from the frame rate. 
+/// Each frame, `events` is called once, then `update` is called for however 
+/// many ticks have elapsed, which may be none, then `render` is called once. 
+pub trait FrameHandler: Send + 'static {
+    /// Handle all the events that have accumulated since last frame. 
+    fn events(&mut self, events: &[WinitEvent]) -> Result<()> {
+        let _ = events;
+        Ok(())
+    }
+
+    /// Advance the simulation by a single tick. 
     ///
-    /// - `events` is all the events that have accumulated since last frame.
-    /// - `delta` is measured in seconds. the first frame, it is 0. 
-    fn frame(&mut self, events: &[WinitEvent], delta: f32) -> Result<ControlFlow>;
-}
+    /// `tick_dt` is the duration of a tick, measured in seconds. 
+    fn update(&mut self, tick_dt: f32) -> Result<()>;
 
-impl<F> FrameHandler for F 
-where
-    F: FnMut(&[WinitEvent], f32) -> Result<ControlFlow> + 'static
-{
-    fn frame(&mut self, events: &[WinitEvent], delta: f32) -> Result<ControlFlow>
-    {
-        self(events, delta)
-    }
+    /// Render a frame. 
+    ///
+    /// `alpha` is the fraction of a tick, from 0 to 1, which has elapsed 
+    /// since the last `update`. This can be used to interpolate between the 
+    /// last two simulated states, so motion is smooth regardless of how the 
+    /// frame rate and tick rate line up. 
+    ///
+    /// The returned `ControlFlow` controls the render thread. `Exit` stops 
+    /// it, `WaitUntil` delays the next frame, and otherwise the next frame 
+    /// begins immediately. 
+    fn render(&mut self, alpha: f32) -> Result<ControlFlow>;
 }
 
 impl MainHijacker {
+    /// Set the simulation tick rate, and the maximum number of ticks to run 
+    /// per frame. Defaults to `FixedTimestep::default()`. 
+    pub fn set_timestep(&mut self, timestep: FixedTimestep) {
+        self.timestep = timestep;
+    }
+
     /// Take control of the main thread and enter the draw loop. 
     ///
-    /// This draws until `handler` returns `Err` or `ControlFlow::Exit`.
-    pub fn hijack<H: FrameHandler>(self, mut handler: H) -> !
+    /// This draws until `handler` returns `Err` or `ControlFlow::Exit`, at 
+    /// which point the render thread asks the event loop to exit. When the 
+    /// event loop exits for any reason, it waits for the render thread to 
+    /// stop before the process exits. 
+    pub fn hijack<H: FrameHandler>(self, handler: H) -> !
     {
         let mut events = Vec::new();
-        let mut last_instant: Option<Instant> = None;
         let MainHijacker {
             event_loop,
             events_send,
+            timestep,
         } = self;
+
+        let (batches_send, batches_recv) = mpsc::channel();
+        let exit_proxy = event_loop.create_proxy();
+        let mut batches_send = Some(batches_send);
+        let mut render_thread = Some(thread::Builder::new()
+            .name("render".into())
+            .spawn(move || render_loop(handler, timestep, batches_recv, exit_proxy))
+            .expect("failed to spawn render thread"));
+
         event_loop.run(move |event, _, curr_flow| {
+            *curr_flow = ControlFlow::Wait;
             match event.to_static() {
                 Some(WinitEvent::MainEventsCleared) => {
-                    let instant = Instant::now();
-                    let delta: f32 = last_instant
-                        .map(|last| { 
-                            (
-                                (instant - last).as_nanos() as f64 
-                                / 1000000000.0
-                            ) as f32
-                        })
-                        .unwrap_or(0.0);
-                    last_instant = Some(instant);
-
+                    if events.is_empty() {
+                        return;
+                    }
                     for event in events.iter().cloned() {
                         let _ = events_send.send(event);
                     }
-                    match handler.frame(&events, delta) {
-                        Ok(flow) => {
-                            *curr_flow = flow;
-                        }
-                        Err(error) => {
-                            error!("frame handler returned error:");
-                            error!("{}", error);
-                            *curr_flow = ControlFlow::Exit;
+                    let batch = std::mem::replace(&mut events, Vec::new());
+                    let sent = batches_send.as_ref()
+                        .map(|send| send.send(batch).is_ok())
+                        .unwrap_or(false);
+                    if !sent {
+                        *curr_flow = ControlFlow::Exit;
+                    }
+                }
+                Some(WinitEvent::UserEvent(())) => {
+                    // render thread requested exit
+                    *curr_flow = ControlFlow::Exit;
+                }
+                Some(WinitEvent::LoopDestroyed) => {
+                    // disconnecting the channel tells the render thread to 
+                    // stop, if it hasn't already
+                    batches_send = None;
+                    if let Some(render_thread) = render_thread.take() {
+                        if render_thread.join().is_err() {
+                            error!("render thread panicked");
                         }
-                    };
-                    events.clear();
+                    }
                 }
                 Some(event) => {
                     events.push(event);
@@ -284,3 +1181,68 @@ impl MainHijacker {
     }
 }
 
+/// Render thread body for `MainHijacker::hijack`. 
+///
+/// Receives batches of events from the main thread, and returns when the 
+/// handler exits or the main thread disconnects. 
+fn render_loop<H: FrameHandler>(
+    mut handler: H,
+    mut timestep: FixedTimestep,
+    batches_recv: mpsc::Receiver<Vec<WinitEvent>>,
+    exit_proxy: EventLoopProxy<()>,
+) {
+    let mut last_instant: Option<Instant> = None;
+    let mut events = Vec::new();
+    loop {
+        // gather all event batches sent since last frame
+        loop {
+            match batches_recv.try_recv() {
+                Ok(batch) => events.extend(batch),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+        }
+
+        let instant = Instant::now();
+        let elapsed = last_instant
+            .map(|last| instant - last)
+            .unwrap_or_default();
+        last_instant = Some(instant);
+
+        // each iteration is a frame for the profiler
+        puffin::GlobalProfiler::lock().new_frame();
+        let result = {
+            puffin::profile_scope!("events");
+            handler.events(&events)
+        }
+            .and_then(|()| {
+                for _ in 0..timestep.advance(elapsed) {
+                    puffin::profile_scope!("update");
+                    handler.update(timestep.tick_secs())?;
+                }
+                Ok(())
+            })
+            .and_then(|()| {
+                puffin::profile_scope!("render");
+                handler.render(timestep.alpha())
+            });
+        events.clear();
+        match result {
+            Ok(ControlFlow::Exit) => break,
+            Ok(ControlFlow::WaitUntil(until)) => {
+                let now = Instant::now();
+                if until > now {
+                    thread::sleep(until - now);
+                }
+            }
+            Ok(ControlFlow::Wait) | Ok(ControlFlow::Poll) => (),
+            Err(error) => {
+                error!("frame handler returned error:");
+                error!("{}", error);
+                break;
+            }
+        };
+    }
+    // the event loop may have already exited, in which case this fails
+    let _ = exit_proxy.send_event(());
+}
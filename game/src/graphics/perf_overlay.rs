@@ -0,0 +1,99 @@
+//! On-screen performance overlay. 
+
+use super::*;
+use crate::util::fps_tracker::FpsTracker;
+use crate::graphics::util::bitmap_font::text_rects;
+use std::time::Duration;
+
+/// First sprite ID used by the performance overlay. 
+///
+/// All sprite IDs from this up are reserved. 
+pub const PERF_OVERLAY_SPRITE_BASE: u64 = u64::MAX - 0xffff;
+
+/// How often the overlay text is refreshed, so it's readable. 
+const REFRESH_PERIOD: Duration = Duration::from_millis(250);
+/// Top-left corner of the overlay text, in pixels. 
+const ORIGIN: Vec2<f32> = Vec2 { x: 8.0, y: 8.0 };
+/// Size of a font pixel, in pixels. 
+const SCALE: f32 = 2.0;
+/// Overlay text color. 
+const COLOR: Rgba<f32> = Rgba { r: 1.0, g: 1.0, b: 1.0, a: 0.9 };
+/// Weight of the newest frame in the smoothed frame time. 
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+/// Statistics displayed by the performance overlay, other than timing. 
+pub(super) struct PerfStats {
+    pub draw_calls: usize,
+    pub block_mesh: MeshStats,
+}
+
+/// State of the performance overlay. 
+pub(super) struct PerfOverlay {
+    fps_tracker: FpsTracker,
+    last_frame: Option<Instant>,
+    // smoothed, in seconds
+    frame_time: f32,
+    last_refresh: Option<Instant>,
+    num_sprites: u64,
+}
+
+impl PerfOverlay {
+    /// Create a new, empty overlay. 
+    pub fn new() -> Self {
+        PerfOverlay {
+            fps_tracker: FpsTracker::default(),
+            last_frame: None,
+            frame_time: 0.0,
+            last_refresh: None,
+            num_sprites: 0,
+        }
+    }
+
+    /// Register a frame happening at `now`, and refresh the overlay's 
+    /// sprites if it's due. 
+    pub fn update(&mut self, now: Instant, stats: &PerfStats, draw_sprites: &mut DrawSprites) {
+        self.fps_tracker.log_frame();
+        if let Some(last_frame) = self.last_frame {
+            let delta = (now - last_frame).as_secs_f32();
+            self.frame_time += (delta - self.frame_time) * FRAME_TIME_SMOOTHING;
+        }
+        self.last_frame = Some(now);
+
+        if self.last_refresh.map(|last| now - last < REFRESH_PERIOD).unwrap_or(false) {
+            return;
+        }
+        self.last_refresh = Some(now);
+
+        let text = format!(
+            "FPS {}\nFRAME {:.1} MS\nDRAWS {}\nBLOCKS {}/{} KB",
+            self.fps_tracker.get_fps(),
+            self.frame_time * 1000.0,
+            stats.draw_calls,
+            stats.block_mesh.buffer.len_bytes / 1024,
+            stats.block_mesh.buffer.capacity_bytes / 1024,
+        );
+        let rects = text_rects(&text, ORIGIN, SCALE);
+        for (i, &rect) in rects.iter().enumerate() {
+            draw_sprites.set_sprite(
+                PERF_OVERLAY_SPRITE_BASE + i as u64,
+                rect,
+                WHITE_SPRITE_TEXTURE,
+                COLOR,
+            );
+        }
+        self.set_num_sprites(rects.len() as u64, draw_sprites);
+    }
+
+    /// Remove all of the overlay's sprites. 
+    pub fn clear(&mut self, draw_sprites: &mut DrawSprites) {
+        self.set_num_sprites(0, draw_sprites);
+    }
+
+    /// Remove sprites beyond the new number of sprites. 
+    fn set_num_sprites(&mut self, num_sprites: u64, draw_sprites: &mut DrawSprites) {
+        for i in num_sprites..self.num_sprites {
+            draw_sprites.remove_sprite(PERF_OVERLAY_SPRITE_BASE + i);
+        }
+        self.num_sprites = num_sprites;
+    }
+}
@@ -0,0 +1,388 @@
+//! Render graph, which orders subsystem render passes by the attachments
+//! they read and write.
+
+use super::*;
+use std::{
+    collections::HashMap,
+    ops::Range,
+};
+
+/// An attachment which render passes may read or write.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Attachment {
+    /// The swapchain color texture for the current frame.
+    SwapchainColor,
+    /// The shared depth buffer.
+    Depth,
+    /// The color texture of an off-screen render target.
+    TargetColor(RenderTargetId),
+    /// The depth texture of an off-screen render target.
+    TargetDepth(RenderTargetId),
+}
+
+/// Identifier for an off-screen render target owned by `Graphics`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct RenderTargetId(pub(crate) usize);
+
+/// Declaration of which attachments a node's render pass accesses.
+#[derive(Clone, Debug, Default)]
+pub struct PassDecl {
+    /// Attachments bound as color attachments, which are written to.
+    pub color: Vec<Attachment>,
+    /// Attachment bound as the depth attachment, if any.
+    pub depth: Option<Attachment>,
+    /// Whether the pass writes to its depth attachment, as opposed to only
+    /// testing against it.
+    pub depth_write: bool,
+    /// Attachments which are read within the pass, such as by sampling.
+    pub reads: Vec<Attachment>,
+}
+
+impl PassDecl {
+    /// Iterate over the attachments this pass writes to.
+    pub fn writes<'s>(&'s self) -> impl Iterator<Item=Attachment> + 's {
+        self.color.iter().copied()
+            .chain(self.depth.filter(|_| self.depth_write))
+    }
+
+    /// Whether this pass binds the same color and depth attachments as
+    /// another, such that they can be recorded into a single render pass.
+    pub fn same_attachments(&self, other: &PassDecl) -> bool {
+        self.color == other.color && self.depth == other.depth
+    }
+
+    /// Iterate over the attachments this pass reads from.
+    pub fn reads<'s>(&'s self) -> impl Iterator<Item=Attachment> + 's {
+        self.reads.iter().copied()
+            .chain(self.depth.filter(|_| !self.depth_write))
+    }
+}
+
+/// A subsystem which renders as a node in the render graph.
+pub trait RenderNode {
+    /// Name of this node, for debugging.
+    fn name(&self) -> &'static str;
+
+    /// Declare which attachments this node's render pass accesses.
+    ///
+    /// This is called once, when the render graph is compiled.
+    fn pass_decl(&self) -> PassDecl;
+
+    /// Record commands which must occur outside of any render pass, such as
+    /// buffer uploads.
+    ///
+    /// All nodes are prepared before any render passes begin.
+    fn prepare(
+        &mut self,
+        gfx: &Graphics,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let _ = (gfx, uploader, command_encoder);
+        Ok(())
+    }
+
+    /// Record draw commands into this node's render pass.
+    ///
+    /// The render pass may be shared with adjacent nodes which bind the same
+    /// attachments, so this must set all pipeline and binding state it uses
+    /// rather than relying on what earlier nodes left behind.
+    fn render<'a>(&'a self, gfx: &'a Graphics, pass: &mut RenderPass<'a>) -> Result<()>;
+
+    /// Number of draw calls `render` would currently record, for statistics.
+    fn draw_calls(&self) -> usize {
+        1
+    }
+}
+
+/// Compiled ordering of render graph nodes.
+///
+/// Nodes are identified by their index in the list they were compiled from.
+/// For each attachment, the nodes which write to it are ordered in the
+/// sequence they were declared, and all nodes which read it are ordered
+/// after all nodes which write it. The first node to write to an attachment
+/// is responsible for clearing it. An attachment which no node writes is
+/// cleared by the first node which uses it, which must bind it as its depth
+/// attachment, since otherwise it'd be read with undefined contents.
+///
+/// Consecutive nodes which bind the same attachments are grouped into a
+/// single render pass, which clears the attachments any of them are
+/// responsible for clearing. Rendering each node into its own pass would
+/// have the same result, but split the frame into many more passes.
+#[derive(Clone, Debug, Default)]
+pub struct RenderGraph {
+    names: Vec<&'static str>,
+    decls: Vec<PassDecl>,
+    order: Vec<usize>,
+    clears: Vec<Vec<Attachment>>,
+    passes: Vec<Range<usize>>,
+}
+
+impl RenderGraph {
+    /// Compile a render graph from (name, pass declaration) tuples.
+    ///
+    /// Errors if the declarations contain a dependency cycle, or if a node
+    /// samples an attachment which is never written or cleared before it.
+    pub fn compile<I>(nodes: I) -> Result<Self>
+    where
+        I: IntoIterator<Item=(&'static str, PassDecl)>,
+    {
+        let (names, decls): (Vec<_>, Vec<_>) = nodes.into_iter().unzip();
+
+        // dependency edges
+        let mut writers: HashMap<Attachment, Vec<usize>> = HashMap::new();
+        let mut readers: HashMap<Attachment, Vec<usize>> = HashMap::new();
+        for (i, decl) in decls.iter().enumerate() {
+            for attachment in decl.writes() {
+                writers.entry(attachment).or_insert_with(Vec::new).push(i);
+            }
+            for attachment in decl.reads() {
+                readers.entry(attachment).or_insert_with(Vec::new).push(i);
+            }
+        }
+
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); decls.len()];
+        let mut in_degree: Vec<usize> = vec![0; decls.len()];
+        let mut add_edge = |from: usize, to: usize| {
+            if from != to && !edges[from].contains(&to) {
+                edges[from].push(to);
+                in_degree[to] += 1;
+            }
+        };
+        for (attachment, attachment_writers) in writers.iter() {
+            for pair in attachment_writers.windows(2) {
+                add_edge(pair[0], pair[1]);
+            }
+            for &reader in readers.get(attachment).into_iter().flatten() {
+                for &writer in attachment_writers {
+                    add_edge(writer, reader);
+                }
+            }
+        }
+
+        // topological sort, breaking ties by declaration order
+        let mut order = Vec::new();
+        let mut done = vec![false; decls.len()];
+        while order.len() < decls.len() {
+            let next = (0..decls.len())
+                .find(|&i| !done[i] && in_degree[i] == 0)
+                .ok_or_else(|| pear!(
+                    {
+                        nodes = names,
+                        ordered = order.iter()
+                            .map(|&i| names[i])
+                            .collect::<Vec<_>>(),
+                    },
+                    "render graph contains a cycle",
+                ))?;
+            done[next] = true;
+            for &to in &edges[next] {
+                in_degree[to] -= 1;
+            }
+            order.push(next);
+        }
+
+        // determine which node clears each attachment
+        let mut clears = vec![Vec::new(); decls.len()];
+        for (&attachment, _) in writers.iter() {
+            let first = order.iter().copied()
+                .find(|&i| decls[i].writes().any(|a| a == attachment))
+                .unwrap();
+            clears[first].push(attachment);
+        }
+        for (&attachment, attachment_readers) in readers.iter() {
+            if writers.contains_key(&attachment) {
+                continue;
+            }
+            let first = order.iter().copied()
+                .find(|i| attachment_readers.contains(i))
+                .unwrap();
+            if decls[first].depth == Some(attachment) {
+                clears[first].push(attachment);
+            } else {
+                return Err(pear!(
+                    { node = names[first], attachment = attachment },
+                    "render graph node reads an attachment which is never written",
+                ));
+            }
+        }
+
+        // group consecutive nodes with the same attachments into passes
+        let mut passes: Vec<Range<usize>> = Vec::new();
+        for (pos, &i) in order.iter().enumerate() {
+            match passes.last_mut() {
+                Some(pass) if {
+                    let first = &decls[order[pass.start]];
+                    first.same_attachments(&decls[i])
+                        && !decls[i].reads.iter().any(|a| first.color.contains(a))
+                } => pass.end = pos + 1,
+                _ => passes.push(pos..pos + 1),
+            }
+        }
+
+        Ok(RenderGraph {
+            names,
+            decls,
+            order,
+            clears,
+            passes,
+        })
+    }
+
+    /// Get node indices in the order their passes should be recorded.
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Get the name of a node.
+    pub fn name(&self, node: usize) -> &'static str {
+        self.names[node]
+    }
+
+    /// Get the pass declaration of a node.
+    pub fn decl(&self, node: usize) -> &PassDecl {
+        &self.decls[node]
+    }
+
+    /// Whether a node is responsible for clearing an attachment.
+    pub fn clears(&self, node: usize, attachment: Attachment) -> bool {
+        self.clears[node].contains(&attachment)
+    }
+
+    /// Get the render passes to record, as ranges of indices into `order`.
+    ///
+    /// All nodes within a pass bind the same attachments.
+    pub fn passes(&self) -> &[Range<usize>] {
+        &self.passes
+    }
+
+    /// Get the node indices within a render pass, in order.
+    pub fn pass_nodes(&self, pass: usize) -> &[usize] {
+        &self.order[self.passes[pass].clone()]
+    }
+
+    /// Whether a render pass must clear an attachment.
+    pub fn pass_clears(&self, pass: usize, attachment: Attachment) -> bool {
+        self.pass_nodes(pass).iter().any(|&i| self.clears(i, attachment))
+    }
+}
+
+#[test]
+fn render_graph_order_test() {
+    let graph = RenderGraph::compile(vec![
+        ("blocks", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            depth: Some(Attachment::Depth),
+            depth_write: true,
+            ..Default::default()
+        }),
+        ("sky", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            depth: Some(Attachment::Depth),
+            depth_write: false,
+            ..Default::default()
+        }),
+    ]).unwrap();
+
+    assert_eq!(graph.order(), &[0, 1]);
+    assert!(graph.clears(0, Attachment::SwapchainColor));
+    assert!(graph.clears(0, Attachment::Depth));
+    assert!(!graph.clears(1, Attachment::SwapchainColor));
+    assert!(!graph.clears(1, Attachment::Depth));
+}
+
+#[test]
+fn render_graph_passes_test() {
+    let target = RenderTargetId(0);
+    let graph = RenderGraph::compile(vec![
+        ("blocks", PassDecl {
+            color: vec![Attachment::TargetColor(target)],
+            depth: Some(Attachment::TargetDepth(target)),
+            depth_write: true,
+            ..Default::default()
+        }),
+        ("models", PassDecl {
+            color: vec![Attachment::TargetColor(target)],
+            depth: Some(Attachment::TargetDepth(target)),
+            depth_write: true,
+            ..Default::default()
+        }),
+        ("blit", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            reads: vec![Attachment::TargetColor(target)],
+            ..Default::default()
+        }),
+        ("sprites", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            ..Default::default()
+        }),
+    ]).unwrap();
+
+    assert_eq!(graph.order(), &[0, 1, 2, 3]);
+    assert_eq!(graph.passes().len(), 2);
+    assert_eq!(graph.pass_nodes(0), &[0, 1]);
+    assert_eq!(graph.pass_nodes(1), &[2, 3]);
+    assert!(graph.pass_clears(0, Attachment::TargetColor(target)));
+    assert!(graph.pass_clears(0, Attachment::TargetDepth(target)));
+    assert!(graph.pass_clears(1, Attachment::SwapchainColor));
+
+    // nodes which bind different depth attachments can't share a pass
+    let graph = RenderGraph::compile(vec![
+        ("blocks", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            ..Default::default()
+        }),
+        ("sky", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            depth: Some(Attachment::Depth),
+            ..Default::default()
+        }),
+    ]).unwrap();
+    assert_eq!(graph.passes().len(), 2);
+}
+
+#[test]
+fn render_graph_unwritten_test() {
+    // a depth attachment which is only tested against is cleared first
+    let graph = RenderGraph::compile(vec![
+        ("sky", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            depth: Some(Attachment::Depth),
+            depth_write: false,
+            ..Default::default()
+        }),
+    ]).unwrap();
+    assert!(graph.clears(0, Attachment::Depth));
+
+    // but one which is only sampled can't be
+    let target = RenderTargetId(0);
+    let graph = RenderGraph::compile(vec![
+        ("blit", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            reads: vec![Attachment::TargetColor(target)],
+            ..Default::default()
+        }),
+    ]);
+    assert!(graph.is_err());
+}
+
+#[test]
+fn render_graph_cycle_test() {
+    // overlay writes the swapchain first, so it must precede blocks, but it
+    // also reads the depth which blocks writes
+    let graph = RenderGraph::compile(vec![
+        ("overlay", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            reads: vec![Attachment::Depth],
+            ..Default::default()
+        }),
+        ("blocks", PassDecl {
+            color: vec![Attachment::SwapchainColor],
+            depth: Some(Attachment::Depth),
+            depth_write: true,
+            ..Default::default()
+        }),
+    ]);
+
+    assert!(graph.is_err());
+}
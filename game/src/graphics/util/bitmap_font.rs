@@ -0,0 +1,112 @@
+//! Tiny built-in bitmap font, for drawing text out of solid rectangles. 
+
+use vek::*;
+
+/// Width of a glyph, in font pixels. 
+pub const GLYPH_WIDTH: u32 = 3;
+/// Height of a glyph, in font pixels. 
+pub const GLYPH_HEIGHT: u32 = 5;
+
+/// Get the rows of a glyph, top to bottom, with the most significant of the 
+/// lower 3 bits being the leftmost pixel. 
+///
+/// Lowercase letters are drawn as uppercase, and unsupported characters are 
+/// drawn as a box. 
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        _ => [0b111, 0b101, 0b101, 0b101, 0b111],
+    }
+}
+
+/// Lay out text as a list of rectangles, in pixels, with the top-left 
+/// corner of the text at `origin`. 
+///
+/// Each font pixel is `scale` pixels wide. Glyphs are separated by one font 
+/// pixel, lines by two, and horizontal runs of pixels are merged into a 
+/// single rectangle. 
+pub fn text_rects(text: &str, origin: Vec2<f32>, scale: f32) -> Vec<Rect<f32, f32>> {
+    let mut rects = Vec::new();
+    for (line_i, line) in text.lines().enumerate() {
+        let line_y = line_i as u32 * (GLYPH_HEIGHT + 2);
+        for (char_i, c) in line.chars().enumerate() {
+            let char_x = char_i as u32 * (GLYPH_WIDTH + 1);
+            for (row_i, &row) in glyph(c).iter().enumerate() {
+                let mut col = 0;
+                while col < GLYPH_WIDTH {
+                    let lit = |col: u32| row & (1 << (GLYPH_WIDTH - 1 - col)) != 0;
+                    if !lit(col) {
+                        col += 1;
+                        continue;
+                    }
+                    let start = col;
+                    while col < GLYPH_WIDTH && lit(col) {
+                        col += 1;
+                    }
+                    rects.push(Rect::new(
+                        origin.x + (char_x + start) as f32 * scale,
+                        origin.y + (line_y + row_i as u32) as f32 * scale,
+                        (col - start) as f32 * scale,
+                        scale,
+                    ));
+                }
+            }
+        }
+    }
+    rects
+}
+
+#[test]
+fn text_rects_test() {
+    // "1" is one run per row, with the serif merged into the stem
+    let one = text_rects("1", Vec2::new(10.0, 20.0), 2.0);
+    assert_eq!(one.len(), 5);
+    assert_eq!(one[0], Rect::new(12.0, 20.0, 2.0, 2.0));
+    assert_eq!(one[1], Rect::new(10.0, 22.0, 4.0, 2.0));
+    assert_eq!(one[4], Rect::new(10.0, 28.0, 6.0, 2.0));
+
+    // second character and second line are offset
+    let text = text_rects(" 1\n1", Vec2::zero(), 1.0);
+    assert_eq!(text.len(), 10);
+    assert_eq!(text[0].x, 5.0);
+    assert_eq!(text[5].y, 7.0);
+
+    assert!(text_rects("  ", Vec2::zero(), 1.0).is_empty());
+}
@@ -1,24 +1,29 @@
 //! Resizeable GPU buffer. 
 
-use super::mesh_diff::MeshPatch;
+use super::{
+    mesh_diff::{
+        IndexedMeshPatch,
+        MeshPatch,
+        MeshWrites,
+    },
+    uploader::Uploader,
+};
 use crate::graphics::label;
 use std::{
     borrow::Cow,
     mem::size_of,
     marker::PhantomData,
+    future::Future,
+    iter::once,
 };
 use bytemuck::{self, Pod};
-use wgpu::{
-    *, 
-    util::{
-        DeviceExt,
-        BufferInitDescriptor,
-    },
-};
+use wgpu::*;
 
 /// Resizeable GPU buffer. 
 ///
-/// Designed to work in tandem with `MeshDiffer`. 
+/// Designed to work in tandem with `MeshDiffer`. Alternatively, elements may 
+/// be modified with `Vec`-like methods such as `push`, which are batched 
+/// together and recorded on the next call to `flush`. 
 pub struct BufferVec<T: Pod> {
     label: Option<Cow<'static, str>>,
     usage: BufferUsage,
@@ -28,20 +33,105 @@ pub struct BufferVec<T: Pod> {
     len: usize,
     // capacity in elements
     capacity: usize,
+    // writes which have been requested but not yet recorded
+    pending: Option<MeshWrites<T>>,
+    policy: GrowthPolicy,
+    num_reallocs: u64,
 
     p: PhantomData<T>,
 }
 
+/// Memory statistics for a `BufferVec`. 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BufferVecStats {
+    /// Current length in bytes. 
+    pub len_bytes: usize,
+    /// Current GPU capacity in bytes. 
+    pub capacity_bytes: usize,
+    /// Total number of re-allocations. 
+    pub num_reallocs: u64,
+}
+
 const BUFFER_VEC_DEFAULT_CAPACITY: usize = 512;
 
+/// Policy for when, and by how much, a `BufferVec` reallocates. 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GrowthPolicy {
+    /// When growing, capacity is multiplied by this until it fits. 
+    ///
+    /// Must be greater than 1. 
+    pub growth_factor: f32,
+    /// Shrink once capacity exceeds length times this, or never if `None`. 
+    ///
+    /// Upon shrinking, capacity becomes length times `growth_factor`, so this 
+    /// must be greater than `growth_factor` to avoid thrashing. 
+    pub shrink_threshold: Option<f32>,
+    /// Capacity never goes below this, in elements. 
+    pub min_capacity: usize,
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> Self {
+        GrowthPolicy {
+            growth_factor: 2.0,
+            shrink_threshold: Some(4.0),
+            min_capacity: BUFFER_VEC_DEFAULT_CAPACITY,
+        }
+    }
+}
+
+impl GrowthPolicy {
+    /// Panic if this policy is invalid. 
+    fn validate(&self) {
+        assert!(self.growth_factor > 1.0, "growth factor must be greater than 1");
+        if let Some(shrink_threshold) = self.shrink_threshold {
+            assert!(
+                shrink_threshold > self.growth_factor,
+                "shrink threshold must be greater than growth factor",
+            );
+        }
+        assert!(self.min_capacity > 0, "min capacity must be positive");
+    }
+
+    /// Determine the capacity to have when the length changes. 
+    pub fn new_capacity(&self, capacity: usize, new_len: usize) -> usize {
+        if capacity < new_len {
+            let mut new_capacity = capacity.max(1);
+            while new_capacity < new_len {
+                new_capacity = (new_capacity as f64 * self.growth_factor as f64).ceil() as usize;
+            }
+            new_capacity.max(self.min_capacity)
+        } else if self.shrink_threshold
+            .map(|threshold| capacity as f64 > new_len as f64 * threshold as f64)
+            .unwrap_or(false)
+        {
+            ((new_len as f64 * self.growth_factor as f64).ceil() as usize)
+                .max(self.min_capacity)
+        } else {
+            capacity
+        }
+    }
+}
+
 impl<T: Pod> BufferVec<T> {
     /// Create a new, empty `BufferVec`. 
     pub fn new(device: &Device, usage: BufferUsage, label: Option<Cow<'static, str>>) -> Self {
+        Self::with_growth_policy(device, usage, label, GrowthPolicy::default())
+    }
+
+    /// Create a new, empty `BufferVec` with a non-default `GrowthPolicy`. 
+    pub fn with_growth_policy(
+        device: &Device,
+        usage: BufferUsage,
+        label: Option<Cow<'static, str>>,
+        policy: GrowthPolicy,
+    ) -> Self {
+        policy.validate();
         let usage = usage | BufferUsage::COPY_DST | BufferUsage::COPY_SRC;
 
         let buffer = device.create_buffer(&BufferDescriptor {
             label: label.clone(),
-            size: (BUFFER_VEC_DEFAULT_CAPACITY * size_of::<T>()) as u64,
+            size: copy_size(policy.min_capacity * size_of::<T>()),
             usage,
             mapped_at_creation: false,
         });
@@ -49,12 +139,27 @@ impl<T: Pod> BufferVec<T> {
             label,
             usage,
             buffer,
-            capacity: BUFFER_VEC_DEFAULT_CAPACITY,
+            capacity: policy.min_capacity,
             len: 0,
+            pending: None,
+            policy,
+            num_reallocs: 0,
             p: PhantomData,
         }
     }
 
+    /// Get the `GrowthPolicy`. 
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.policy
+    }
+
+    /// Set the `GrowthPolicy`, which takes effect upon the next length 
+    /// change. 
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        policy.validate();
+        self.policy = policy;
+    }
+
     /// Access the current underlying buffer. 
     pub fn as_inner(&self) -> &Buffer {
         &self.buffer
@@ -66,10 +171,19 @@ impl<T: Pod> BufferVec<T> {
     }
 
     /// Current length in elements. 
+    ///
+    /// This does not include changes which have not been flushed. 
     pub fn len_elems(&self) -> usize {
         self.len
     }
 
+    /// Length in elements after the next `flush`. 
+    pub fn pending_len_elems(&self) -> usize {
+        self.pending.as_ref()
+            .map(|pending| pending.new_len)
+            .unwrap_or(self.len)
+    }
+
     /// Current length in bytes.
     pub fn len_bytes(&self) -> usize {
         self.len * size_of::<T>()
@@ -85,6 +199,60 @@ impl<T: Pod> BufferVec<T> {
         self.capacity * size_of::<T>()
     }
 
+    /// Get memory statistics. 
+    pub fn stats(&self) -> BufferVecStats {
+        BufferVecStats {
+            len_bytes: self.len_bytes(),
+            capacity_bytes: self.capacity_bytes(),
+            num_reallocs: self.num_reallocs,
+        }
+    }
+
+    /// Read the current elements back from the GPU. 
+    ///
+    /// This submits a copy into a `MAP_READ` staging buffer immediately, and 
+    /// the future resolves once it's mapped, which requires the device to be 
+    /// polled. Changes which have not been flushed are not included. 
+    pub fn read_back(&self, device: &Device, queue: &Queue) -> impl Future<Output=Vec<T>> {
+        let len = self.len;
+        let len_bytes = self.len_bytes();
+        let staging = device.create_buffer(&BufferDescriptor {
+            label: label("buffer vec read back"),
+            // avoid creating an empty buffer
+            size: copy_size(len_bytes).max(COPY_BUFFER_ALIGNMENT),
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        if len > 0 {
+            let mut command_encoder = device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: label("buffer vec read back command encoder"),
+                });
+            command_encoder.copy_buffer_to_buffer(
+                &self.buffer,
+                0,
+                &staging,
+                0,
+                copy_size(len_bytes),
+            );
+            queue.submit(once(command_encoder.finish()));
+        }
+
+        async move {
+            if len == 0 {
+                return Vec::new();
+            }
+
+            let slice = staging.slice(..);
+            slice.map_async(MapMode::Read).await
+                .expect("failed to map buffer vec read back");
+            // the copy may include padding past the last element
+            let elems = bytemuck::cast_slice::<u8, T>(&slice.get_mapped_range()[..len_bytes]).to_vec();
+            staging.unmap();
+            elems
+        }
+    }
+
     /// Unconditionally reallocate. 
     fn realloc(
         &mut self,
@@ -96,7 +264,7 @@ impl<T: Pod> BufferVec<T> {
 
         let new_buffer = device.create_buffer(&BufferDescriptor {
             label: self.label.clone(),
-            size: (new_capacity * size_of::<T>()) as u64,
+            size: copy_size(new_capacity * size_of::<T>()),
             usage: self.usage,
             mapped_at_creation: false,
         });
@@ -106,11 +274,17 @@ impl<T: Pod> BufferVec<T> {
             0,
             &new_buffer,
             0,
-            self.len_bytes() as u64,
+            copy_size(self.len_bytes()),
         );
 
+        debug!(
+            old_capacity = self.capacity_bytes(),
+            new_capacity = new_capacity * size_of::<T>(),
+            "buffer vec re-allocated",
+        );
         self.buffer = new_buffer;
         self.capacity = new_capacity;
+        self.num_reallocs += 1;
     }
 
     /// Set the current length (in elems), which may trigger a re-allocation. 
@@ -124,60 +298,218 @@ impl<T: Pod> BufferVec<T> {
         device: &Device,
         command_encoder: &mut CommandEncoder,
     ) {
-        let mut new_capacity = self.capacity_elems();
-        if new_capacity < new_len {
-            while new_capacity < new_len {
-                new_capacity *= 2;
-            }
-        } else if new_capacity > new_len * 4 {
-            new_capacity /= 4;
-            if new_capacity < BUFFER_VEC_DEFAULT_CAPACITY {
-                new_capacity = BUFFER_VEC_DEFAULT_CAPACITY;
-            }
+        let new_capacity = self.policy.new_capacity(self.capacity, new_len);
+        if new_capacity != self.capacity {
+            // elements past the new length needn't be copied
+            self.len = self.len.min(new_len);
+            self.realloc(new_capacity, device, command_encoder);
+        }
+
+        self.len = new_len;
+    }
+
+    /// Ensure capacity for at least `additional` more elements beyond the 
+    /// current length, which may trigger a re-allocation. 
+    pub fn reserve_elems(
+        &mut self,
+        additional: usize,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let needed = self.len + additional;
+        if needed > self.capacity {
+            let new_capacity = self.policy.new_capacity(self.capacity, needed);
+            self.realloc(new_capacity, device, command_encoder);
         }
+    }
 
-        if new_capacity != self.capacity_elems() {
+    /// Shrink capacity to the current length, or the policy's minimum 
+    /// capacity, whichever is greater. 
+    pub fn shrink_to_fit(&mut self, device: &Device, command_encoder: &mut CommandEncoder) {
+        let new_capacity = self.len.max(self.policy.min_capacity);
+        if new_capacity != self.capacity {
             self.realloc(new_capacity, device, command_encoder);
         }
+    }
 
-        self.len = new_len;
+    fn pending_mut(&mut self) -> &mut MeshWrites<T> {
+        let len = self.len;
+        self.pending.get_or_insert_with(|| MeshWrites {
+            new_len: len,
+            writes_data: Vec::new(),
+            writes_indices: Vec::new(),
+        })
+    }
+
+    /// Append an element, upon the next `flush`. 
+    pub fn push(&mut self, elem: T) {
+        let pending = self.pending_mut();
+        pending.writes_indices.push(pending.new_len);
+        pending.writes_data.push(elem);
+        pending.new_len += 1;
+    }
+
+    /// Append elements, upon the next `flush`. 
+    pub fn extend_from_slice(&mut self, elems: &[T]) {
+        let pending = self.pending_mut();
+        let start = pending.new_len;
+        pending.writes_indices.extend(start..start + elems.len());
+        pending.writes_data.extend_from_slice(elems);
+        pending.new_len += elems.len();
+    }
+
+    /// Remove all elements, upon the next `flush`. 
+    ///
+    /// This discards any other changes which have not been flushed. 
+    pub fn clear(&mut self) {
+        self.pending = Some(MeshWrites {
+            new_len: 0,
+            writes_data: Vec::new(),
+            writes_indices: Vec::new(),
+        });
+    }
+
+    /// Overwrite elements starting at `index`, upon the next `flush`. 
+    ///
+    /// Panics if this would write past the end, including pending pushes. 
+    pub fn write_at(&mut self, index: usize, elems: &[T]) {
+        let pending = self.pending_mut();
+        assert!(
+            index + elems.len() <= pending.new_len,
+            "write out of bounds",
+        );
+        pending.writes_indices.extend(index..index + elems.len());
+        pending.writes_data.extend_from_slice(elems);
+    }
+
+    /// Record all changes made with `push`, `extend_from_slice`, `clear`, 
+    /// and `write_at`, in a single upload. 
+    pub fn flush(
+        &mut self,
+        device: &Device,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        if let Some(pending) = self.pending.take() {
+            self.apply_writes(&pending, device, uploader, command_encoder);
+        }
     }
 
     /// Apply a `mesh_diff::MeshPatch` to `self`.
+    ///
+    /// Any changes which have not been flushed are flushed first. 
     pub fn apply_patch(
         &mut self, 
         patch: &MeshPatch<T>,
         device: &Device,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        self.flush(device, uploader, command_encoder);
+
+        match patch {
+            &MeshPatch::Writes(ref writes) => {
+                self.apply_writes(writes, device, uploader, command_encoder);
+            }
+            &MeshPatch::Reset(ref data) => {
+                trace!("resetting buffer vec");
+
+                // the old contents needn't survive a re-allocation
+                self.len = 0;
+                self.set_len(data.len(), device, command_encoder);
+                uploader.upload(device, command_encoder, &self.buffer, 0, data);
+            }
+        }
+    }
+
+    /// Apply a `mesh_diff::IndexedMeshPatch` to `self` as the vertex buffer 
+    /// and `indices` as the index buffer. 
+    ///
+    /// Both are recorded into the same `CommandEncoder`, so the GPU never sees 
+    /// indices which refer to the old vertex layout. Any changes to either 
+    /// which have not been flushed are flushed first. 
+    pub fn apply_indexed_patch(
+        &mut self,
+        indices: &mut BufferVec<u32>,
+        patch: &IndexedMeshPatch<T>,
+        device: &Device,
+        uploader: &mut Uploader,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        self.apply_patch(&patch.vertices, device, uploader, command_encoder);
+        indices.apply_patch(&patch.indices, device, uploader, command_encoder);
+    }
+
+    fn apply_writes(
+        &mut self,
+        writes: &MeshWrites<T>,
+        device: &Device,
+        uploader: &mut Uploader,
         command_encoder: &mut CommandEncoder,
     ) {
         // there may be a length change even if there's no writes
-        self.set_len(patch.new_len, device, command_encoder);
+        self.set_len(writes.new_len, device, command_encoder);
 
         // cheap case
-        if patch.writes_data.is_empty() {
+        if writes.writes_data.is_empty() {
             return;
         }
 
         trace!("writing patches to buffer vec");
 
-        // copy data, with a single src buffer
-        let copy_src_bytes: &[u8] = bytemuck::cast_slice(&patch.writes_data);
-        let copy_src_buffer = device
-            .create_buffer_init(&BufferInitDescriptor {
-                label: Some("buffer vec patch source"),
-                contents: copy_src_bytes,
-                usage: BufferUsage::COPY_SRC,
-            });
-
-        for part in patch.iter_contiguous() {
-            command_encoder
-                .copy_buffer_to_buffer(
-                    &copy_src_buffer,
-                    (part.src_start * size_of::<T>()) as u64,
-                    self.as_inner(),
-                    (part.dst_start * size_of::<T>()) as u64,
-                    (part.len * size_of::<T>()) as u64,
-                );
+        // copy data through the staging belt
+        for part in writes.iter_contiguous() {
+            uploader.upload(
+                device,
+                command_encoder,
+                &self.buffer,
+                (part.dst_start * size_of::<T>()) as u64,
+                &writes.writes_data[part.src_start..part.src_start + part.len],
+            );
         }
     }
 }
+
+/// Round a size in bytes up to `COPY_BUFFER_ALIGNMENT`, as buffer copies 
+/// require. Buffers are allocated with rounded sizes, so that their contents 
+/// can always be copied whole, even if their elements' total size isn't a 
+/// multiple of 4, such as an odd number of `u16` indices. 
+fn copy_size(bytes: usize) -> u64 {
+    // the alignment is a power of two
+    let align = COPY_BUFFER_ALIGNMENT as usize;
+    ((bytes + align - 1) & !(align - 1)) as u64
+}
+
+#[test]
+fn growth_policy_test() {
+    let policy = GrowthPolicy {
+        growth_factor: 1.5,
+        shrink_threshold: Some(3.0),
+        min_capacity: 10,
+    };
+    policy.validate();
+
+    // no change within bounds
+    assert_eq!(policy.new_capacity(10, 0), 10);
+    assert_eq!(policy.new_capacity(30, 10), 30);
+    // growth
+    assert_eq!(policy.new_capacity(10, 11), 15);
+    assert_eq!(policy.new_capacity(10, 20), 23);
+    // shrinking
+    assert_eq!(policy.new_capacity(31, 10), 15);
+    assert_eq!(policy.new_capacity(100, 0), 10);
+
+    let never_shrink = GrowthPolicy {
+        shrink_threshold: None,
+        ..policy
+    };
+    assert_eq!(never_shrink.new_capacity(100, 0), 100);
+}
+
+#[test]
+fn copy_size_test() {
+    assert_eq!(copy_size(0), 0);
+    assert_eq!(copy_size(6), 8);
+    assert_eq!(copy_size(8), 8);
+    assert_eq!(copy_size(3 * size_of::<u16>()), 8);
+}
@@ -0,0 +1,268 @@
+//! Compute shader utilities.
+
+use super::{
+    label,
+    cowstr,
+};
+use std::{
+    borrow::Cow,
+    mem::size_of,
+    marker::PhantomData,
+    num::NonZeroU64,
+};
+use bytemuck::{self, Pod};
+use wgpu::{
+    *,
+    util::{
+        DeviceExt,
+        BufferInitDescriptor,
+    },
+};
+use vek::*;
+
+/// Kind of resource bound to a compute shader.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ComputeBinding {
+    /// Uniform buffer of a certain size in bytes.
+    Uniform {
+        size: u64,
+    },
+    /// Storage buffer, which is writeable unless `readonly`.
+    Storage {
+        readonly: bool,
+    },
+}
+
+/// Declare the bindings of a compute shader with GLSL-like syntax.
+///
+/// Evaluates to a `Vec<(u32, ComputeBinding)>`, for `ComputeKernel::new`.
+///
+/// ```
+/// compute_bindings! {
+///     layout(binding = 0) uniform Uniforms;
+///     layout(binding = 1) readonly buffer;
+///     layout(binding = 2) buffer;
+/// }
+/// ```
+macro_rules! compute_bindings {
+    (@acc [$($acc:expr,)*])=>{
+        vec![$($acc,)*]
+    };
+    (@acc [$($acc:expr,)*] layout(binding = $binding:expr) uniform $uniforms:ty; $($rest:tt)*)=>{
+        compute_bindings!(@acc [$($acc,)* (
+            $binding,
+            $crate::graphics::util::compute::ComputeBinding::Uniform {
+                size: <
+                    $uniforms
+                    as
+                    $crate::graphics::util::uniform::GenericUniforms
+                >::SIZE as u64,
+            },
+        ),] $($rest)*)
+    };
+    (@acc [$($acc:expr,)*] layout(binding = $binding:expr) readonly buffer; $($rest:tt)*)=>{
+        compute_bindings!(@acc [$($acc,)* (
+            $binding,
+            $crate::graphics::util::compute::ComputeBinding::Storage {
+                readonly: true,
+            },
+        ),] $($rest)*)
+    };
+    (@acc [$($acc:expr,)*] layout(binding = $binding:expr) buffer; $($rest:tt)*)=>{
+        compute_bindings!(@acc [$($acc,)* (
+            $binding,
+            $crate::graphics::util::compute::ComputeBinding::Storage {
+                readonly: false,
+            },
+        ),] $($rest)*)
+    };
+    ($($rest:tt)*)=>{
+        compute_bindings!(@acc [] $($rest)*)
+    };
+}
+
+/// Compute pipeline with a single bind group, and a fixed workgroup size.
+pub struct ComputeKernel {
+    pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    workgroup_size: Vec3<u32>,
+}
+
+impl ComputeKernel {
+    /// Create a compute pipeline.
+    ///
+    /// `bindings` describes bind group 0, and is typically created with
+    /// `compute_bindings!`. `workgroup_size` must match the `local_size`
+    /// declared in the shader. The entry point is `main`.
+    pub fn new<V: Into<Vec3<u32>>>(
+        device: &Device,
+        shader: ShaderModuleSource,
+        bindings: &[(u32, ComputeBinding)],
+        workgroup_size: V,
+        label: &str,
+    ) -> Self {
+        let workgroup_size = workgroup_size.into();
+        let module = device.create_shader_module(shader);
+        let entries = bindings
+            .iter()
+            .map(|&(binding, kind)| BindGroupLayoutEntry {
+                binding,
+                visibility: ShaderStage::COMPUTE,
+                ty: match kind {
+                    ComputeBinding::Uniform { size } => BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: NonZeroU64::new(size),
+                    },
+                    ComputeBinding::Storage { readonly } => BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                        readonly,
+                    },
+                },
+                count: None,
+            })
+            .collect::<Vec<_>>();
+        let bind_group_layout = device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some(Cow::Owned(format!("{} bind group layout", label))),
+                entries: Cow::Owned(entries),
+            });
+        let pipeline_layout = device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                bind_group_layouts: Cow::Borrowed(&[&bind_group_layout]),
+                push_constant_ranges: Cow::Borrowed(&[]),
+            });
+        let pipeline = device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                layout: &pipeline_layout,
+                compute_stage: ProgrammableStageDescriptor {
+                    module: &module,
+                    entry_point: cowstr("main"),
+                },
+            });
+        ComputeKernel {
+            pipeline,
+            bind_group_layout,
+            workgroup_size,
+        }
+    }
+
+    /// Get the bind group layout, to create bind groups manually.
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Create a bind group from (binding, resource) pairs.
+    pub fn bind_group<'a, I>(&self, device: &Device, resources: I) -> BindGroup
+    where
+        I: IntoIterator<Item=(u32, BindingResource<'a>)>,
+    {
+        let entries = resources
+            .into_iter()
+            .map(|(binding, resource)| BindGroupEntry {
+                binding,
+                resource,
+            })
+            .collect::<Vec<_>>();
+        device
+            .create_bind_group(&BindGroupDescriptor {
+                label: label("compute bind group"),
+                layout: &self.bind_group_layout,
+                entries: Cow::Owned(entries),
+            })
+    }
+
+    /// Record a compute pass which runs at least `invocations` invocations,
+    /// rounded up to whole workgroups.
+    pub fn dispatch<V: Into<Vec3<u32>>>(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        bind_group: &BindGroup,
+        invocations: V,
+    ) {
+        let workgroups = workgroups(invocations.into(), self.workgroup_size);
+        if workgroups.x == 0 || workgroups.y == 0 || workgroups.z == 0 {
+            return;
+        }
+        let mut pass = command_encoder.begin_compute_pass();
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.dispatch(workgroups.x, workgroups.y, workgroups.z);
+    }
+}
+
+/// Number of workgroups needed to run at least `invocations` invocations.
+pub fn workgroups(invocations: Vec3<u32>, workgroup_size: Vec3<u32>) -> Vec3<u32> {
+    let div_ceil = |n: u32, size: u32| (n + size - 1) / size;
+    Vec3::new(
+        div_ceil(invocations.x, workgroup_size.x),
+        div_ceil(invocations.y, workgroup_size.y),
+        div_ceil(invocations.z, workgroup_size.z),
+    )
+}
+
+/// Fixed-length GPU buffer, usable as a compute storage buffer.
+pub struct StorageBuffer<T: Pod> {
+    buffer: Buffer,
+    len: usize,
+    p: PhantomData<T>,
+}
+
+impl<T: Pod> StorageBuffer<T> {
+    /// Create a zeroed storage buffer with `len` elements.
+    ///
+    /// `usage` is in addition to `STORAGE`, `COPY_DST`, and `COPY_SRC`.
+    pub fn new(device: &Device, len: usize, usage: BufferUsage, label: Option<Cow<'static, str>>) -> Self {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label,
+            size: (len * size_of::<T>()) as u64,
+            usage: usage | BufferUsage::STORAGE | BufferUsage::COPY_DST | BufferUsage::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        StorageBuffer {
+            buffer,
+            len,
+            p: PhantomData,
+        }
+    }
+
+    /// Create a storage buffer initialized with `data`.
+    ///
+    /// `usage` is in addition to `STORAGE`, `COPY_DST`, and `COPY_SRC`.
+    pub fn from_slice(device: &Device, data: &[T], usage: BufferUsage, label: &str) -> Self {
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(data),
+            usage: usage | BufferUsage::STORAGE | BufferUsage::COPY_DST | BufferUsage::COPY_SRC,
+        });
+        StorageBuffer {
+            buffer,
+            len: data.len(),
+            p: PhantomData,
+        }
+    }
+
+    /// Access the underlying buffer.
+    pub fn as_inner(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Length in elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Bind the whole buffer.
+    pub fn binding(&self) -> BindingResource {
+        BindingResource::Buffer(self.buffer.slice(..))
+    }
+}
+
+#[test]
+fn workgroups_test() {
+    let size = Vec3::new(64, 8, 1);
+    assert_eq!(workgroups(Vec3::new(0, 0, 0), size), Vec3::new(0, 0, 0));
+    assert_eq!(workgroups(Vec3::new(1, 1, 1), size), Vec3::new(1, 1, 1));
+    assert_eq!(workgroups(Vec3::new(64, 8, 3), size), Vec3::new(1, 1, 3));
+    assert_eq!(workgroups(Vec3::new(65, 9, 3), size), Vec3::new(2, 2, 3));
+}
@@ -0,0 +1,174 @@
+//! Off-screen output for headless rendering.
+
+use super::texture_array::{
+    padded_bytes_per_row,
+    BYTES_PER_TEXEL,
+};
+use crate::graphics::label;
+use std::{
+    future::Future,
+    iter::once,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures::task::noop_waker_ref;
+use image::RgbaImage;
+use wgpu::*;
+use vek::*;
+
+/// Off-screen color texture which takes the place of the swapchain when
+/// rendering headlessly, and which can be read back to the CPU.
+pub struct HeadlessOutput {
+    texture: Texture,
+    view: TextureView,
+    size: Extent2<u32>,
+    format: TextureFormat,
+}
+
+impl HeadlessOutput {
+    /// Create a new headless output.
+    ///
+    /// `format` must be a 4-byte BGRA or RGBA format.
+    pub fn new(device: &Device, size: Extent2<u32>, format: TextureFormat) -> Self {
+        let texture = device
+            .create_texture(&TextureDescriptor {
+                label: label("headless output"),
+                size: Extent3d {
+                    width: size.w,
+                    height: size.h,
+                    depth: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC,
+            });
+        let view = texture.create_default_view();
+        HeadlessOutput {
+            texture,
+            view,
+            size,
+            format,
+        }
+    }
+
+    /// Get the view of the color texture.
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// Get the size.
+    pub fn size(&self) -> Extent2<u32> {
+        self.size
+    }
+
+    /// Copy the current contents of the texture back to the CPU.
+    ///
+    /// The copy is submitted immediately. The returned future only makes
+    /// progress while the device is polled, see `poll_device_until_ready`.
+    pub fn read_back(&self, device: &Device, queue: &Queue) -> impl Future<Output=RgbaImage> {
+        let size = self.size;
+        let bytes_per_row = padded_bytes_per_row(size.w);
+        let bgra = matches!(
+            self.format,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+        );
+        let staging = device.create_buffer(&BufferDescriptor {
+            label: label("headless output read back"),
+            size: bytes_per_row as u64 * size.h as u64,
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut command_encoder = device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: label("headless output read back command encoder"),
+            });
+        command_encoder.copy_texture_to_buffer(
+            TextureCopyView {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                },
+            },
+            BufferCopyView {
+                buffer: &staging,
+                layout: TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row,
+                    rows_per_image: size.h,
+                },
+            },
+            Extent3d {
+                width: size.w,
+                height: size.h,
+                depth: 1,
+            },
+        );
+        queue.submit(once(command_encoder.finish()));
+
+        async move {
+            let slice = staging.slice(..);
+            slice.map_async(MapMode::Read).await
+                .expect("failed to map headless output read back");
+            let data = unpad_rows(&slice.get_mapped_range(), size, bytes_per_row, bgra);
+            staging.unmap();
+            RgbaImage::from_raw(size.w, size.h, data)
+                .expect("headless output read back has wrong size")
+        }
+    }
+}
+
+/// Block on a future which depends on the device being polled, such as one
+/// waiting for a buffer to be mapped.
+pub fn poll_device_until_ready<F: Future>(device: &Device, future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let mut cx = Context::from_waker(noop_waker_ref());
+    loop {
+        if let Poll::Ready(output) = Pin::as_mut(&mut future).poll(&mut cx) {
+            return output;
+        }
+        device.poll(Maintain::Wait);
+    }
+}
+
+/// Strip the padding from rows of texels copied out of a texture, converting
+/// BGRA to RGBA if `bgra` is true.
+fn unpad_rows(padded: &[u8], size: Extent2<u32>, bytes_per_row: u32, bgra: bool) -> Vec<u8> {
+    let row_len = (size.w * BYTES_PER_TEXEL) as usize;
+    let mut data = Vec::with_capacity(row_len * size.h as usize);
+    for row in padded.chunks(bytes_per_row as usize).take(size.h as usize) {
+        data.extend_from_slice(&row[..row_len]);
+    }
+    if bgra {
+        for texel in data.chunks_exact_mut(BYTES_PER_TEXEL as usize) {
+            texel.swap(0, 2);
+        }
+    }
+    data
+}
+
+#[test]
+fn unpad_rows_test() {
+    let size = Extent2::new(3, 2);
+    let bytes_per_row = padded_bytes_per_row(size.w);
+    let mut padded = vec![0xff; (bytes_per_row * size.h) as usize];
+    for y in 0..size.h {
+        for x in 0..size.w {
+            let i = (y * bytes_per_row + x * 4) as usize;
+            padded[i..i + 4].copy_from_slice(&[x as u8, y as u8, 7, 255]);
+        }
+    }
+
+    let rgba = unpad_rows(&padded, size, bytes_per_row, false);
+    assert_eq!(rgba.len(), 3 * 2 * 4);
+    assert_eq!(&rgba[..8], &[0, 0, 7, 255, 1, 0, 7, 255]);
+    assert_eq!(&rgba[20..], &[2, 1, 7, 255]);
+
+    let bgra = unpad_rows(&padded, size, bytes_per_row, true);
+    assert_eq!(&bgra[..8], &[7, 0, 0, 255, 7, 0, 1, 255]);
+    assert_eq!(&bgra[20..], &[7, 1, 2, 255]);
+}
@@ -1,12 +1,13 @@
 //! Data structure for making small modifications to a mesh, then applying 
 //! those changes to VRAM with minimal operations. 
 
-use crate::util::pool::{Pool, PoolLogic};
 use std::{
-    cmp::Ordering,
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    hash::Hash,
     iter::FromIterator,
-    ops::{RangeBounds, Range},
+    mem::{self, size_of},
+    ops::Range,
+    sync::mpsc,
 };
 
 /// Data structure which stores a mesh, and tracks changes. 
@@ -24,198 +25,785 @@ use std::{
 /// 2. `commit`, where you generate a `MeshMeshPatch` which contains instructions 
 ///    for modifying a `Vec<P>`-like representation from the state of `self` 
 ///    the last time `commit` was called to the current state of `self`.  
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+///
+/// The primitives for each key occupy a contiguous range, given by 
+/// `range_of`, so that keys may be drawn individually. Removing or shrinking 
+/// a key may leave a hole, the contents of which are unspecified, so the 
+/// mesh should be drawn with `live_ranges` rather than as a whole. When every 
+/// key has the same number of primitives, there are never holes. 
+///
+/// If a commit would write to more than a certain fraction of the mesh, or 
+/// holes make up more than that fraction, it instead compacts the mesh and 
+/// produces a `MeshPatch::Reset`, since one large contiguous upload is faster 
+/// than many scattered ones. 
+///
+/// Entries may also be staged from other threads through a 
+/// `MeshDifferHandle`. 
+///
+/// Staged and committed state are both indexed by hashing the key, so the 
+/// cost of staging and committing is proportional to the number of 
+/// primitives changed, not the size of the mesh. 
+///
+/// A mesh created with `new_indexed` also maintains an index buffer, in 
+/// which each key has a contiguous range of indices into its own vertices. 
+/// When a key's vertices move to different slots, its indices are 
+/// re-written to match, and `commit_indexed` produces a patch for both 
+/// buffers. 
+#[derive(Debug)]
 pub struct MeshDiffer<K, P>
 where
-    K: Ord + Clone,
+    K: Ord + Hash + Clone,
     P: Ord + Clone,
 {
-    tree: BTreeMap<Entry<K, P>, usize>,
-    array: Vec<Entry<K, P>>,
+    // committed primitives, with `None` in holes
+    array: Vec<Option<P>>,
+    // committed range of each key
+    ranges: HashMap<K, Range<usize>>,
+    // key of the range starting at each index
+    starts: BTreeMap<usize, K>,
+    // holes as start -> len, which are never adjacent or at the end
+    holes: BTreeMap<usize, usize>,
+    // free list of the same holes as (len, start), for best-fit allocation
+    free_list: BTreeSet<(usize, usize)>,
+    // number of non-hole elements of `array`
+    num_live: usize,
+
+    // primitives staged for each key, with an empty list to remove
+    staged: HashMap<K, Vec<P>>,
+    // whether staged primitives are sorted, which indexed meshes can't be
+    sort_staged: bool,
 
-    alter_entries: BTreeSet<Entry<K, P>>,
-    alter_keys: BTreeSet<K>,
+    // index buffer, if indexed
+    indexed: Option<Box<IndexState<K>>>,
 
-    entry_vec_pool: Pool<Vec<Entry<K, P>>, VecPool>,
+    reset_threshold: Option<f32>,
+
+    // entries staged through handles, created upon the first handle
+    submissions: Option<(mpsc::Sender<(K, Vec<P>)>, mpsc::Receiver<(K, Vec<P>)>)>,
+
+    // churn statistics, with the primitive and hole counts not kept current
+    stats: MeshDifferStats,
+}
+
+/// Index buffer of an indexed `MeshDiffer`. 
+#[derive(Debug)]
+struct IndexState<K>
+where
+    K: Ord + Hash + Clone,
+{
+    // absolute indices, in a range for each key
+    differ: MeshDiffer<K, u32>,
+    // committed indices of each key, relative to its vertex range
+    local: HashMap<K, Vec<u32>>,
+    // relative indices staged for each key
+    staged: HashMap<K, Vec<u32>>,
+    // keys whose vertex ranges were linked since the last commit
+    relinked: Vec<K>,
+}
+
+/// Memory and churn statistics for a `MeshDiffer`. 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MeshDifferStats {
+    /// Number of committed primitives. 
+    pub num_primitives: usize,
+    /// Number of committed holes, in primitives. 
+    pub num_holes: usize,
+    /// Bytes of primitives staged directly since the last commit. 
+    pub bytes_staged: usize,
+    /// Number of primitives written by the last commit. 
+    pub last_commit_writes: usize,
+    /// Whether the last commit was a `MeshPatch::Reset`. 
+    pub last_commit_reset: bool,
+    /// Total number of commits. 
+    pub num_commits: u64,
+}
+
+/// Handle for staging entries into a `MeshDiffer` from other threads. 
+///
+/// If the `MeshDiffer` has been dropped, staged entries are discarded. 
+#[derive(Debug)]
+pub struct MeshDifferHandle<K, P> {
+    send: mpsc::Sender<(K, Vec<P>)>,
+}
+
+impl<K, P> Clone for MeshDifferHandle<K, P> {
+    fn clone(&self) -> Self {
+        MeshDifferHandle {
+            send: self.send.clone(),
+        }
+    }
 }
 
+impl<K, P> MeshDifferHandle<K, P> {
+    /// Insert a `K -> [P]` entry upon the next commit, overriding any 
+    /// existing entry. 
+    pub fn stage<I>(&self, key: K, primitives: I)
+    where
+        I: IntoIterator<Item = P>,
+    {
+        let _ = self.send.send((key, primitives.into_iter().collect()));
+    }
+
+    /// Remove the entry for `key` upon the next commit, if any. 
+    pub fn remove(&self, key: K) {
+        let _ = self.send.send((key, Vec::new()));
+    }
+}
+
+/// Default fraction of the mesh above which a commit produces a 
+/// `MeshPatch::Reset`. 
+pub const DEFAULT_RESET_THRESHOLD: f32 = 0.5;
+
 impl<K, P> MeshDiffer<K, P>
 where
-    K: Ord + Clone,
+    K: Ord + Hash + Clone,
     P: Ord + Clone,
 {
     /// Create an empty mesh. 
     pub fn new() -> Self {
         MeshDiffer {
-            tree: BTreeMap::new(),
             array: Vec::new(),
-            alter_entries: BTreeSet::new(),
-            alter_keys: BTreeSet::new(),
-            entry_vec_pool: Pool::new(VecPool, VEC_POOL_SIZE),
+            ranges: HashMap::new(),
+            starts: BTreeMap::new(),
+            holes: BTreeMap::new(),
+            free_list: BTreeSet::new(),
+            num_live: 0,
+            staged: HashMap::new(),
+            sort_staged: true,
+            indexed: None,
+            reset_threshold: Some(DEFAULT_RESET_THRESHOLD),
+            submissions: None,
+            stats: MeshDifferStats::default(),
+        }
+    }
+
+    /// Create an empty indexed mesh, in which primitives are vertices. 
+    ///
+    /// Entries should be staged with `stage_indexed` and committed with 
+    /// `commit_indexed`. Entries staged without indices, including through 
+    /// handles, are given the indices of their vertices in order. 
+    pub fn new_indexed() -> Self {
+        let mut indices = MeshDiffer::new();
+        indices.sort_staged = false;
+        MeshDiffer {
+            sort_staged: false,
+            indexed: Some(Box::new(IndexState {
+                differ: indices,
+                local: HashMap::new(),
+                staged: HashMap::new(),
+                relinked: Vec::new(),
+            })),
+            ..MeshDiffer::new()
+        }
+    }
+
+    /// Whether this mesh was created with `new_indexed`. 
+    pub fn is_indexed(&self) -> bool {
+        self.indexed.is_some()
+    }
+
+    /// Set the fraction of the mesh which a commit may write to, or which 
+    /// may be holes, before it produces a `MeshPatch::Reset` instead, or 
+    /// `None` to never do so. 
+    ///
+    /// For an indexed mesh, this applies to the vertex and index buffers 
+    /// separately. 
+    pub fn set_reset_threshold(&mut self, reset_threshold: Option<f32>) {
+        self.reset_threshold = reset_threshold;
+        if let Some(ref mut state) = self.indexed {
+            state.differ.set_reset_threshold(reset_threshold);
+        }
+    }
+
+    /// Create a handle for staging entries from other threads. 
+    ///
+    /// Entries staged through handles are received upon the next `commit`, 
+    /// after any entries staged directly. 
+    pub fn handle(&mut self) -> MeshDifferHandle<K, P> {
+        let send = &self.submissions
+            .get_or_insert_with(mpsc::channel)
+            .0;
+        MeshDifferHandle {
+            send: send.clone(),
         }
     }
 
     /// Insert a `K -> [P]` entry, overriding any existing entry. 
+    ///
+    /// For an indexed mesh, the vertices are indexed in order. 
     pub fn stage<I>(&mut self, key: K, primitives: I)
     where
         I: IntoIterator<Item = P>,
     {
-        if !self.alter_keys.insert(key.clone()) {
-            let mut to_remove = self.entry_vec_pool.get();
-            to_remove.extend(
-                self.alter_entries.range(key_range_hack(&key)).cloned(),
-            );
-            for entry in to_remove.drain(..) {
-                self.alter_entries.remove(&entry);
-            }
+        let len = self.stage_primitives(key.clone(), primitives);
+        if let Some(ref mut state) = self.indexed {
+            let staged = state.staged.entry(key).or_default();
+            staged.clear();
+            staged.extend(0..len as u32);
         }
+    }
 
-        let mut to_insert = self.entry_vec_pool.get();
-        to_insert.extend(
-            primitives
-                .into_iter()
-                .map(|primitive| Entry::new(key.clone(), primitive, 0)),
+    /// Insert a `K -> [P]` entry of vertices, with indices into those 
+    /// vertices, overriding any existing entry. 
+    ///
+    /// Panics if this mesh isn't indexed, or an index is out of bounds. 
+    pub fn stage_indexed<I, J>(&mut self, key: K, vertices: I, indices: J)
+    where
+        I: IntoIterator<Item = P>,
+        J: IntoIterator<Item = u32>,
+    {
+        assert!(self.indexed.is_some(), "mesh differ is not indexed");
+        let len = self.stage_primitives(key.clone(), vertices);
+        let state = self.indexed.as_mut().unwrap();
+        let staged = state.staged.entry(key).or_default();
+        staged.clear();
+        staged.extend(indices);
+        assert!(
+            staged.iter().all(|&index| (index as usize) < len),
+            "mesh differ index out of bounds",
         );
+        self.stats.bytes_staged += staged.len() * size_of::<u32>();
+    }
+
+    /// Stage primitives for a key, and return how many there are. 
+    fn stage_primitives<I>(&mut self, key: K, primitives: I) -> usize
+    where
+        I: IntoIterator<Item = P>,
+    {
+        // re-staging a key re-uses its list's allocation
+        let staged = self.staged.entry(key).or_default();
+        staged.clear();
+        staged.extend(primitives);
+        if self.sort_staged {
+            staged.sort();
+        }
+        self.stats.bytes_staged += staged.len() * size_of::<P>();
+        staged.len()
+    }
+
+    /// Remove the entry for `key`, if any. 
+    ///
+    /// Equivalent to staging an empty list of primitives, but without 
+    /// allocating. 
+    pub fn remove(&mut self, key: &K) {
+        match self.staged.get_mut(key) {
+            Some(staged) => staged.clear(),
+            None => {
+                self.staged.insert(key.clone(), Vec::new());
+            }
+        }
+        if let Some(ref mut state) = self.indexed {
+            match state.staged.get_mut(key) {
+                Some(staged) => staged.clear(),
+                None => {
+                    state.staged.insert(key.clone(), Vec::new());
+                }
+            }
+        }
+    }
+
+    /// Remove the entries for all keys for which `f` returns false. 
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let mut to_remove: Vec<K> = Vec::new();
 
-        to_insert.sort();
-        assign_ordinals(to_insert.as_mut_slice());
+        // committed keys
+        for key in self.ranges.keys() {
+            if !self.staged.contains_key(key) && !f(key) {
+                to_remove.push(key.clone());
+            }
+        }
+
+        // staged keys, which are removed only if they have staged entries
+        for (key, staged) in self.staged.iter() {
+            if !staged.is_empty() && !f(key) {
+                to_remove.push(key.clone());
+            }
+        }
 
-        for entry in to_insert.drain(..) {
-            self.alter_entries.insert(entry);
+        for key in to_remove {
+            self.remove(&key);
         }
     }
 
+    /// Iterate the committed primitives for `key`. 
+    ///
+    /// This does not include changes which have not been committed. 
+    pub fn get<'s>(&'s self, key: &K) -> impl Iterator<Item = &'s P> + 's {
+        self.ranges
+            .get(key)
+            .into_iter()
+            .flat_map(move |range| self.array[range.clone()].iter())
+            .map(|primitive| primitive.as_ref().unwrap())
+    }
+
+    /// Iterate the keys with committed primitives, in sorted order. 
+    pub fn keys<'s>(&'s self) -> impl Iterator<Item = &'s K> + 's {
+        let mut keys = self.ranges.keys().collect::<Vec<_>>();
+        keys.sort();
+        keys.into_iter()
+    }
+
+    /// Whether `key` has any committed primitives. 
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.ranges.contains_key(key)
+    }
+
+    /// Total number of committed primitives. 
+    pub fn len_primitives(&self) -> usize {
+        self.num_live
+    }
+
+    /// Get memory and churn statistics. 
+    pub fn stats(&self) -> MeshDifferStats {
+        MeshDifferStats {
+            num_primitives: self.num_live,
+            num_holes: self.array.len() - self.num_live,
+            ..self.stats
+        }
+    }
+
+    /// The committed range of indices occupied by `key`'s primitives. 
+    pub fn range_of(&self, key: &K) -> Option<Range<usize>> {
+        self.ranges.get(key).cloned()
+    }
+
+    /// Iterate the committed ranges of indices which are not holes, in 
+    /// order, with adjacent ranges merged. 
+    pub fn live_ranges<'s>(&'s self) -> impl Iterator<Item = Range<usize>> + 's {
+        let mut starts = self.starts.keys().copied().peekable();
+        let holes = &self.holes;
+        let len = self.array.len();
+        std::iter::from_fn(move || {
+            let start = starts.next()?;
+            // a live range extends until the next hole
+            let end = holes
+                .range(start..)
+                .next()
+                .map(|(&hole_start, _)| hole_start)
+                .unwrap_or(len);
+            while starts.peek().map(|&next| next < end).unwrap_or(false) {
+                starts.next();
+            }
+            Some(start..end)
+        })
+    }
+
     /// Generate a `MeshMeshPatch` to modify the previously committed state into 
     /// the current state. 
+    ///
+    /// Panics if this mesh is indexed, in which case `commit_indexed` should 
+    /// be used instead. 
     #[must_use = "patch should be applied to something"]
     pub fn commit(&mut self) -> MeshPatch<P> {
-        let mut added = self.entry_vec_pool.get();
-        let mut removed = self.entry_vec_pool.get();
-
-        for key in self.alter_keys.iter() {
-            let mut before_iter = self
-                .tree
-                .range(key_range_hack(key))
-                .map(|(entry, _)| entry)
-                .peekable();
-            let mut after_iter =
-                self.alter_entries.range(key_range_hack(key)).peekable();
-
-            loop {
-                match (before_iter.peek(), after_iter.peek()) {
-                    (Some(&before), Some(&after)) => {
-                        match Ord::cmp(before, after) {
-                            Ordering::Equal => {
-                                before_iter.next();
-                                after_iter.next();
-                            }
-                            Ordering::Greater => {
-                                added.push(after.clone());
-                                after_iter.next();
-                            }
-                            Ordering::Less => {
-                                removed.push(before.clone());
-                                before_iter.next();
-                            }
-                        }
-                    }
-                    (Some(&before), None) => {
-                        removed.push(before.clone());
-                        before_iter.next();
-                    }
-                    (None, Some(&after)) => {
-                        added.push(after.clone());
-                        after_iter.next();
-                    }
-                    (None, None) => break,
-                }
+        assert!(self.indexed.is_none(), "indexed mesh differ committed without indices");
+        self.commit_primitives()
+    }
+
+    /// Generate an `IndexedMeshPatch` to modify the previously committed 
+    /// vertex and index buffers into their current state. 
+    ///
+    /// Panics if this mesh isn't indexed. 
+    #[must_use = "patch should be applied to something"]
+    pub fn commit_indexed(&mut self) -> IndexedMeshPatch<P> {
+        assert!(self.indexed.is_some(), "mesh differ is not indexed");
+        let vertices = self.commit_primitives();
+
+        let ranges = &self.ranges;
+        let state = &mut **self.indexed.as_mut().unwrap();
+        for (key, local) in state.staged.drain() {
+            if ranges.contains_key(&key) {
+                state.local.insert(key.clone(), local);
+                state.relinked.push(key);
+            } else {
+                state.local.remove(&key);
+                state.differ.remove(&key);
+            }
+        }
+
+        // re-write the indices of keys whose vertices were staged or moved
+        state.relinked.sort_unstable();
+        state.relinked.dedup();
+        for key in state.relinked.drain(..) {
+            if let (Some(range), Some(local)) = (ranges.get(&key), state.local.get(&key)) {
+                let base = range.start as u32;
+                state.differ.stage(key, local.iter().map(|&index| base + index));
+            }
+        }
+        let indices = state.differ.commit();
+
+        IndexedMeshPatch {
+            vertices,
+            indices,
+        }
+    }
+
+    /// Commit staged primitives, without updating indices. 
+    fn commit_primitives(&mut self) -> MeshPatch<P> {
+        // receive entries staged through handles
+        let mut submissions = Vec::new();
+        if let Some((_, ref recv)) = self.submissions {
+            submissions.extend(recv.try_iter());
+        }
+        for (key, primitives) in submissions {
+            if primitives.is_empty() {
+                self.remove(&key);
+            } else {
+                self.stage(key, primitives);
             }
         }
 
-        let mut writes: Vec<(P, usize)> = Vec::new();
-        let post_edit_len = self.array.len() + added.len() - removed.len();
+        // commit in key order, so that the layout is deterministic
+        let mut staged = self.staged.drain().collect::<Vec<_>>();
+        staged.sort_unstable_by(|a, b| a.0.cmp(&b.0));
 
-        for (add, remove) in Iterator::zip(added.iter(), removed.iter()) {
-            let index = self.tree.remove(remove).unwrap();
-            self.tree.insert(add.clone(), index);
-            self.array[index] = add.clone();
+        // indices which were written to, which may be duplicated or holes
+        let mut touched: Vec<usize> = Vec::new();
+        for (key, primitives) in staged {
+            self.commit_key(key, &primitives, &mut touched);
+        }
 
-            writes.push((
-                add.primitive().clone(),
-                index,
-            ));
+        touched.sort_unstable();
+        touched.dedup();
+        let array = &self.array;
+        touched.retain(|&index| index < array.len() && array[index].is_some());
+
+        let num_holes = self.array.len() - self.num_live;
+        let reset = self.reset_threshold
+            .map(|threshold| {
+                touched.len() as f32 > self.num_live as f32 * threshold
+                    || num_holes as f32 > self.array.len() as f32 * threshold
+            })
+            .unwrap_or(false);
+        self.stats.bytes_staged = 0;
+        self.stats.num_commits += 1;
+        self.stats.last_commit_reset = reset;
+        if reset {
+            self.compact();
+            self.stats.last_commit_writes = self.num_live;
+            trace!(primitives = self.num_live, "mesh differ committed reset");
+            return self.full_patch();
         }
+        self.stats.last_commit_writes = touched.len();
+        trace!(
+            writes = touched.len(),
+            primitives = self.num_live,
+            holes = num_holes,
+            "mesh differ committed",
+        );
 
-        if added.len() > removed.len() {
-            for add in added.iter().skip(removed.len()) {
-                let index = self.array.len();
-                self.array.push(add.clone());
-                self.tree.insert(add.clone(), index);
-                writes.push((
-                    add.primitive().clone(),
-                    index,
-                ));
+        let writes_data = touched
+            .iter()
+            .map(|&index| self.array[index].clone().unwrap())
+            .collect();
+        MeshPatch::Writes(MeshWrites {
+            new_len: self.array.len(),
+            writes_data,
+            writes_indices: touched,
+        })
+    }
+
+    /// Generate a `MeshPatch` to build the previously committed state from an 
+    /// empty `Vec<P>`-like. 
+    ///
+    /// This does not commit anything. It is useful for re-uploading a mesh 
+    /// after its VRAM copy was lost. 
+    #[must_use = "patch should be applied to something"]
+    pub fn full_patch(&self) -> MeshPatch<P> {
+        if self.holes.is_empty() {
+            MeshPatch::Reset(self.array
+                .iter()
+                .map(|primitive| primitive.clone().unwrap())
+                .collect())
+        } else {
+            let mut writes_data = Vec::new();
+            let mut writes_indices = Vec::new();
+            for (index, primitive) in self.array.iter().enumerate() {
+                if let &Some(ref primitive) = primitive {
+                    writes_data.push(primitive.clone());
+                    writes_indices.push(index);
+                }
             }
-        } else if removed.len() > added.len() {
-            for remove in removed.iter().skip(added.len()) {
-                //
-                // swap remove
-                //
-                let index = self.tree.remove(remove).unwrap();
-                if index + 1 == self.array.len() {
-                    self.array.pop();
-                } else {
-                    let relocate_entry = self.array.pop().unwrap();
-                    assert_eq!(
-                        self.tree.remove(&relocate_entry).unwrap(),
-                        self.array.len()
-                    );
-                    self.array[index] = relocate_entry.clone();
-                    self.tree.insert(relocate_entry.clone(), index);
-                    writes.push((
-                        relocate_entry.primitive().clone(),
-                        index,
-                    ));
+            MeshPatch::Writes(MeshWrites {
+                new_len: self.array.len(),
+                writes_data,
+                writes_indices,
+            })
+        }
+    }
+
+    /// Generate an `IndexedMeshPatch` to build the previously committed vertex 
+    /// and index buffers from empty. 
+    ///
+    /// Panics if this mesh isn't indexed. 
+    #[must_use = "patch should be applied to something"]
+    pub fn full_indexed_patch(&self) -> IndexedMeshPatch<P> {
+        let state = self.indexed.as_ref().expect("mesh differ is not indexed");
+        IndexedMeshPatch {
+            vertices: self.full_patch(),
+            indices: state.differ.full_patch(),
+        }
+    }
+
+    /// The committed range of the index buffer occupied by `key`'s indices. 
+    ///
+    /// Panics if this mesh isn't indexed. 
+    pub fn index_range_of(&self, key: &K) -> Option<Range<usize>> {
+        let state = self.indexed.as_ref().expect("mesh differ is not indexed");
+        state.differ.range_of(key)
+    }
+
+    /// Iterate the committed ranges of the index buffer which are not holes, 
+    /// in order, with adjacent ranges merged. 
+    ///
+    /// Panics if this mesh isn't indexed. 
+    pub fn index_live_ranges<'s>(&'s self) -> impl Iterator<Item = Range<usize>> + 's {
+        let state = self.indexed.as_ref().expect("mesh differ is not indexed");
+        state.differ.live_ranges()
+    }
+
+    /// Commit the new primitives for a single key. 
+    fn commit_key(&mut self, key: K, primitives: &[P], touched: &mut Vec<usize>) {
+        let old = self.unlink(&key);
+        let start = match old {
+            // shrink in place
+            Some(range) if primitives.len() <= range.len() => {
+                if !primitives.is_empty() {
+                    self.link(key, range.start..range.start + primitives.len());
+                }
+                self.free(range.start + primitives.len()..range.end, touched);
+                range.start
+            }
+            // grow in place
+            Some(range) if self.room_after(range.end) >= primitives.len() - range.len() => {
+                self.claim(range.end..range.start + primitives.len());
+                self.link(key, range.start..range.start + primitives.len());
+                range.start
+            }
+            // relocate
+            old => {
+                if let Some(range) = old {
+                    self.free(range, touched);
+                }
+                if primitives.is_empty() {
+                    return;
+                }
+                let start = self.alloc(primitives.len());
+                self.link(key, start..start + primitives.len());
+                start
+            }
+        };
+
+        for (i, primitive) in primitives.iter().enumerate() {
+            let slot = &mut self.array[start + i];
+            if slot.as_ref() != Some(primitive) {
+                if slot.is_none() {
+                    self.num_live += 1;
                 }
+                *slot = Some(primitive.clone());
+                touched.push(start + i);
             }
         }
+    }
 
-        assert_eq!(post_edit_len, self.array.len());
-        assert_eq!(self.array.len(), self.tree.len());
+    fn link(&mut self, key: K, range: Range<usize>) {
+        if let Some(ref mut state) = self.indexed {
+            state.relinked.push(key.clone());
+        }
+        self.starts.insert(range.start, key.clone());
+        self.ranges.insert(key, range);
+    }
 
-        writes.sort_unstable_by_key(|&(_, index)| index);
+    fn unlink(&mut self, key: &K) -> Option<Range<usize>> {
+        let range = self.ranges.remove(key)?;
+        self.starts.remove(&range.start);
+        Some(range)
+    }
 
-        let mut writes_data = Vec::new();
-        let mut writes_indices = Vec::new();
-        for (a, b) in writes {
-            writes_data.push(a);
-            writes_indices.push(b);
+    /// Number of elements which a range ending at `end` could grow by. 
+    fn room_after(&self, end: usize) -> usize {
+        if end == self.array.len() {
+            usize::MAX
+        } else {
+            self.holes.get(&end).copied().unwrap_or(0)
         }
+    }
 
-        MeshPatch {
-            new_len: post_edit_len,
-            writes_data,
-            writes_indices,
+    /// Take a range from the hole or end which it starts at. 
+    fn claim(&mut self, range: Range<usize>) {
+        if range.start == self.array.len() {
+            self.array.resize_with(range.end, || None);
+        } else {
+            let hole_len = self.remove_hole(range.start).unwrap();
+            if hole_len > range.len() {
+                self.insert_hole(range.end, hole_len - range.len());
+            }
         }
     }
-}
 
+    /// Find a range of `len` elements, in the smallest hole that fits or 
+    /// else at the end, and take it. 
+    fn alloc(&mut self, len: usize) -> usize {
+        let fit = self.free_list
+            .range((len, 0)..)
+            .next()
+            .map(|&(_, start)| start);
+        let start = fit.unwrap_or(self.array.len());
+        self.claim(start..start + len);
+        start
+    }
+
+    /// Turn an unlinked range into a hole, or fill it with the last range if 
+    /// that fits. 
+    fn free(&mut self, range: Range<usize>, touched: &mut Vec<usize>) {
+        let mut hole = range;
+        for slot in &mut self.array[hole.clone()] {
+            if slot.take().is_some() {
+                self.num_live -= 1;
+            }
+        }
+
+        // since holes are never at the end, the last range ends at the end
+        let last = self.starts
+            .iter()
+            .next_back()
+            .map(|(&start, key)| (start, key.clone()));
+        if let Some((last_start, last_key)) = last {
+            let last_len = self.array.len() - last_start;
+            if hole.end <= last_start && last_len <= hole.len() {
+                self.unlink(&last_key);
+                for i in 0..last_len {
+                    self.array[hole.start + i] = self.array[last_start + i].take();
+                    touched.push(hole.start + i);
+                }
+                self.link(last_key, hole.start..hole.start + last_len);
+                hole.start += last_len;
+
+                // dropping the last range may expose a hole at the end
+                self.array.truncate(last_start);
+                let trailing = self.holes
+                    .iter()
+                    .next_back()
+                    .filter(|&(&start, &len)| start + len == last_start)
+                    .map(|(&start, _)| start);
+                if let Some(start) = trailing {
+                    self.remove_hole(start);
+                    self.array.truncate(start);
+                }
+            }
+        }
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-struct VecPool;
+        self.add_hole(hole);
+    }
 
-const VEC_POOL_SIZE: usize = 3;
+    /// Add a hole, merging it with adjacent holes and dropping it if it's 
+    /// at the end. 
+    fn add_hole(&mut self, mut hole: Range<usize>) {
+        if hole.start >= hole.end {
+            return;
+        }
+        let before = self.holes
+            .range(..hole.start)
+            .next_back()
+            .filter(|&(&start, &len)| start + len == hole.start)
+            .map(|(&start, _)| start);
+        if let Some(start) = before {
+            self.remove_hole(start);
+            hole.start = start;
+        }
+        if let Some(len) = self.remove_hole(hole.end) {
+            hole.end += len;
+        }
 
-impl<T> PoolLogic<Vec<T>> for VecPool {
-    fn create(&self) -> Vec<T> { Vec::new() }
+        if hole.end >= self.array.len() {
+            self.array.truncate(hole.start);
+        } else {
+            self.insert_hole(hole.start, hole.len());
+        }
+    }
 
-    fn recycle(&self, vec: &mut Vec<T>) { vec.clear() }
+    fn insert_hole(&mut self, start: usize, len: usize) {
+        self.holes.insert(start, len);
+        self.free_list.insert((len, start));
+    }
+
+    fn remove_hole(&mut self, start: usize) -> Option<usize> {
+        let len = self.holes.remove(&start)?;
+        self.free_list.remove(&(len, start));
+        Some(len)
+    }
+
+    /// Remove all holes, re-arranging the ranges in key order. 
+    fn compact(&mut self) {
+        let mut array = Vec::with_capacity(self.num_live);
+        let mut ranges = mem::take(&mut self.ranges)
+            .into_iter()
+            .collect::<Vec<_>>();
+        ranges.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        self.starts.clear();
+        self.holes.clear();
+        self.free_list.clear();
+        for (key, range) in ranges {
+            let start = array.len();
+            array.extend(self.array[range].iter_mut().map(Option::take));
+            self.link(key, start..array.len());
+        }
+        self.array = array;
+    }
 }
 
+
 /// Instructions for modifying a `MeshDiffer` representation from one commit 
 /// to the next. 
 #[derive(Clone, Debug)]
-pub struct MeshPatch<P> {
+pub enum MeshPatch<P> {
+    /// Resize, then write data at certain indices. 
+    Writes(MeshWrites<P>),
+    /// Replace the entire contents. 
+    Reset(Vec<P>),
+}
+
+impl<P> MeshPatch<P> {
+    /// The new length of the `Vec<P>`-like. 
+    pub fn new_len(&self) -> usize {
+        match self {
+            &MeshPatch::Writes(ref writes) => writes.new_len,
+            &MeshPatch::Reset(ref data) => data.len(),
+        }
+    }
+
+    /// Iterate the `(index, primitive)` writes to perform after resizing. 
+    pub fn iter_writes<'s>(&'s self) -> Box<dyn Iterator<Item = (usize, &'s P)> + 's> {
+        match self {
+            &MeshPatch::Writes(ref writes) => Box::new(writes.writes_indices
+                .iter()
+                .copied()
+                .zip(&writes.writes_data)),
+            &MeshPatch::Reset(ref data) => Box::new(data.iter().enumerate()),
+        }
+    }
+}
+
+/// Instructions for modifying the vertex and index buffers of an indexed 
+/// `MeshDiffer` from one commit to the next. 
+///
+/// Both should be applied together, such as with 
+/// `BufferVec::apply_indexed_patch`, since the indices refer to the new 
+/// vertex layout. 
+#[derive(Clone, Debug)]
+pub struct IndexedMeshPatch<P> {
+    /// Patch for the vertex buffer. 
+    pub vertices: MeshPatch<P>,
+    /// Patch for the index buffer. 
+    pub indices: MeshPatch<u32>,
+}
+
+/// Instructions to resize a `Vec<P>`-like, then write data at certain 
+/// indices. 
+#[derive(Clone, Debug)]
+pub struct MeshWrites<P> {
     /// The new length of the `Vec<P>`-like. 
     pub new_len: usize,
     /// Instructions to write data at certain indices. Corresponds to `writes_indices`. 
@@ -224,7 +812,7 @@ pub struct MeshPatch<P> {
     pub writes_indices: Vec<usize>,
 }
 
-/// Location of contiguous writes in a `MeshPatch`. 
+/// Location of contiguous writes in a `MeshWrites`. 
 #[derive(Copy, Clone, Debug)]
 pub struct Contiguous {
     pub src_start: usize,
@@ -232,7 +820,7 @@ pub struct Contiguous {
     pub len: usize,
 }
 
-impl<P> MeshPatch<P> {
+impl<P> MeshWrites<P> {
     /// Iterate non-empty slices of contiguous writes. 
     pub fn iter_contiguous<'s>(
         &'s self,
@@ -275,117 +863,34 @@ impl<P> MeshPatch<P> {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-struct Entry<K, P>
-where
-    K: Ord + Clone,
-    P: Ord + Clone,
-{
-    key: K,
-    primitive: OrdHack<P>,
-    ordinal: usize,
-}
-
-impl<K, P> Entry<K, P>
-where
-    K: Ord + Clone,
-    P: Ord + Clone,
-{
-    fn new(key: K, primitive: P, ordinal: usize) -> Self {
-        Entry {
-            key,
-            primitive: OrdHack::Real(primitive),
-            ordinal,
-        }
-    }
-
-    fn primitive(&self) -> &P {
-        match &self.primitive {
-            &OrdHack::Real(ref p) => p,
-            _ => panic!(),
-        }
-    }
-}
-
-#[derive(PartialOrd, Ord, PartialEq, Eq, Clone, Hash, Debug)]
-enum OrdHack<T: Ord> {
-    NegInf,
-    Real(T),
-    PosInf,
-}
-
-fn key_range_hack<K, P>(key: &K) -> impl RangeBounds<Entry<K, P>>
-where
-    K: Ord + Clone,
-    P: Ord + Clone,
-{
-    let min = Entry {
-        key: key.clone(),
-        primitive: OrdHack::NegInf,
-        ordinal: 0xDEADBEEF,
-    };
-    let max = Entry {
-        key: key.clone(),
-        primitive: OrdHack::PosInf,
-        ordinal: 0xDEADBEEF,
-    };
-    min..max
-}
-
-fn assign_ordinals<K, P>(array: &mut [Entry<K, P>])
-where
-    K: Ord + Clone,
-    P: Ord + Clone,
-{
-    let mut curr_key: Option<K> = None;
-    let mut curr_prim: Option<P> = None;
-    let mut curr_ordinal: usize = 0;
-
-    for entry in array {
-        if Some(&entry.key) == curr_key.as_ref()
-            && Some(entry.primitive()) == curr_prim.as_ref()
-        {
-            curr_ordinal += 1;
-        } else {
-            curr_key = Some(entry.key.clone());
-            curr_prim = Some(entry.primitive().clone());
-            curr_ordinal = 0;
-        }
-        entry.ordinal = curr_ordinal;
-    }
-}
-
 impl<K, P> FromIterator<(K, P)> for MeshDiffer<K, P>
 where
-    K: Ord + Clone,
+    K: Ord + Hash + Clone,
     P: Ord + Clone,
 {
     fn from_iter<I>(iter: I) -> Self
     where
         I: IntoIterator<Item = (K, P)>,
     {
-        let mut array = iter
-            .into_iter()
-            .map(|(key, primitive)| Entry::new(key, primitive, 0))
-            .collect::<Vec<_>>();
+        let mut pairs = iter.into_iter().collect::<Vec<_>>();
+        pairs.sort();
 
-        array.sort();
-        assign_ordinals(&mut array);
-
-        let tree = array
-            .iter()
-            .cloned()
-            .enumerate()
-            .map(|(index, entry)| (entry, index))
-            .collect::<BTreeMap<_, _>>();
-
-        MeshDiffer {
-            tree,
-            array,
-            alter_entries: BTreeSet::new(),
-            alter_keys: BTreeSet::new(),
-            entry_vec_pool: Pool::new(VecPool, VEC_POOL_SIZE),
+        let mut differ = MeshDiffer::new();
+        let mut start = 0;
+        while start < pairs.len() {
+            let mut end = start + 1;
+            while end < pairs.len() && pairs[end].0 == pairs[start].0 {
+                end += 1;
+            }
+            differ.link(pairs[start].0.clone(), start..end);
+            start = end;
         }
+        differ.array = pairs
+            .into_iter()
+            .map(|(_, primitive)| Some(primitive))
+            .collect();
+        differ.num_live = differ.array.len();
+        differ
     }
 }
 
@@ -393,7 +898,7 @@ where
 fn test() {
     use rand::{prelude::*, random};
 
-    let mut plain: Vec<Vec<u8>> = vec![vec![]; 10];
+    let mut plain: Vec<Vec<u8>> = vec![vec![]; 20];
 
     let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new();
     let mut follower: Vec<u8> = Vec::new();
@@ -401,14 +906,14 @@ fn test() {
     for i in 0..10000 {
         // randomly generate some modifications
         let mut scramble: Vec<(usize, Vec<u8>)> = Vec::new();
-        for index in 0..10 {
+        for index in 0..plain.len() {
             if random::<bool>() {
                 let len = random::<usize>() % 10;
                 let mut bin: Vec<u8> = Vec::new();
                 for _ in 0..len {
                     bin.push(random::<u8>());
                 }
-                scramble.push((len, bin));
+                scramble.push((index, bin));
             }
         }
 
@@ -417,29 +922,52 @@ fn test() {
             plain[index] = bin.clone();
         }
 
-        // stage them in delta
+        // stage them in delta, sometimes over an entry staged earlier in the 
+        // same commit
         for &(index, ref bin) in &scramble {
+            if random::<u8>() % 4 == 0 {
+                delta.stage(index, vec![random::<u8>(); random::<usize>() % 10]);
+            }
             delta.stage(index, bin.iter().copied());
         }
 
+        // remove some keys, which may have been staged or committed
+        for _ in 0..random::<usize>() % 3 {
+            let index = random::<usize>() % plain.len();
+            plain[index].clear();
+            delta.remove(&index);
+        }
+        if random::<u8>() % 8 == 0 {
+            let modulus = random::<usize>() % 5 + 2;
+            for (index, bin) in plain.iter_mut().enumerate() {
+                if index % modulus == 0 {
+                    bin.clear();
+                }
+            }
+            delta.retain(|&index| index % modulus != 0);
+        }
+
         // commit the change
         let patch = delta.commit();
 
         // apply the patch to follower
-        while follower.len() > patch.new_len {
+        while follower.len() > patch.new_len() {
             follower.pop();
         }
-        while follower.len() < patch.new_len {
+        while follower.len() < patch.new_len() {
             follower.push(0);
         }
-        for (&index, &primitive) in patch.writes_indices.iter().zip(&patch.writes_data) {
+        for (index, &primitive) in patch.iter_writes() {
             follower[index] = primitive;
         }
 
-        // compare the set of primitives in plain and follower
+        // compare the set of primitives in plain and the live parts of follower
         let mut plain_indices: Vec<u8> =
             plain.iter().flatten().copied().collect();
-        let mut follower_indices: Vec<u8> = follower.clone();
+        let mut follower_indices: Vec<u8> = delta
+            .live_ranges()
+            .flat_map(|range| follower[range].iter().copied())
+            .collect();
         plain_indices.sort();
         follower_indices.sort();
 
@@ -449,5 +977,336 @@ fn test() {
             "desynchronization on iteration {}",
             i
         );
+
+        // assert each key's primitives are in its range
+        for (key, bin) in plain.iter().enumerate() {
+            let mut expected = bin.clone();
+            expected.sort();
+            let mut actual = delta
+                .range_of(&key)
+                .map(|range| follower[range].to_vec())
+                .unwrap_or_default();
+            actual.sort();
+            assert_eq!(
+                expected, actual,
+                "key {} desynchronization on iteration {}",
+                key, i
+            );
+            assert_eq!(
+                expected,
+                delta.get(&key).copied().collect::<Vec<_>>(),
+                "key {} get desynchronization on iteration {}",
+                key, i
+            );
+        }
+
+        // assert the committed keys and counts match
+        let plain_keys: Vec<usize> = (0..plain.len())
+            .filter(|&key| !plain[key].is_empty())
+            .collect();
+        assert_eq!(delta.keys().copied().collect::<Vec<_>>(), plain_keys);
+        let stats = delta.stats();
+        assert_eq!(stats.num_primitives, plain_indices.len());
+        assert_eq!(stats.num_primitives + stats.num_holes, follower.len());
+    }
+}
+
+#[test]
+fn full_patch_test() {
+    let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new();
+    delta.stage(0, vec![1, 2, 3]);
+    delta.stage(1, vec![4, 5]);
+    let _ = delta.commit();
+    delta.stage(0, vec![6]);
+    let _ = delta.commit();
+
+    // stage something uncommitted, which should not appear in the patch
+    delta.stage(2, vec![7]);
+
+    let patch = delta.full_patch();
+    assert_eq!(patch.new_len(), 3);
+    let mut data = match patch {
+        MeshPatch::Reset(data) => data,
+        MeshPatch::Writes(_) => panic!("full patch should be a reset"),
+    };
+    data.sort();
+    assert_eq!(data, vec![4, 5, 6]);
+}
+
+#[test]
+fn remove_retain_test() {
+    // apply the patch to follower, and return its sorted live contents
+    fn sorted(
+        patch: MeshPatch<u8>,
+        delta: &MeshDiffer<usize, u8>,
+        follower: &mut Vec<u8>,
+    ) -> Vec<u8> {
+        follower.resize(patch.new_len(), 0);
+        for (index, &primitive) in patch.iter_writes() {
+            follower[index] = primitive;
+        }
+        let mut data: Vec<u8> = delta
+            .live_ranges()
+            .flat_map(|range| follower[range].iter().copied())
+            .collect();
+        data.sort();
+        data
+    }
+
+    let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new();
+    let mut follower = Vec::new();
+    for key in 0..5 {
+        delta.stage(key, vec![key as u8 * 10, key as u8 * 10 + 1]);
+    }
+    let _ = sorted(delta.commit(), &delta, &mut follower);
+
+    // remove a committed key
+    delta.remove(&1);
+    assert_eq!(sorted(delta.commit(), &delta, &mut follower), vec![0, 1, 20, 21, 30, 31, 40, 41]);
+
+    // remove a key which was only staged
+    delta.stage(7, vec![70]);
+    delta.remove(&7);
+    assert_eq!(sorted(delta.commit(), &delta, &mut follower), vec![0, 1, 20, 21, 30, 31, 40, 41]);
+
+    // retain with both committed and staged keys
+    delta.stage(5, vec![50]);
+    delta.stage(6, vec![60]);
+    delta.stage(2, vec![22]);
+    delta.retain(|&key| key % 2 == 0);
+    assert_eq!(sorted(delta.commit(), &delta, &mut follower), vec![0, 1, 22, 40, 41, 60]);
+}
+
+#[test]
+fn query_test() {
+    let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new();
+    delta.stage(3, vec![2, 1]);
+    delta.stage(1, vec![5]);
+    delta.stage(2, vec![]);
+    let _ = delta.commit();
+
+    // staged but uncommitted changes are invisible
+    delta.stage(4, vec![9]);
+    delta.remove(&1);
+
+    assert_eq!(delta.get(&3).copied().collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(delta.get(&2).count(), 0);
+    assert_eq!(delta.keys().copied().collect::<Vec<_>>(), vec![1, 3]);
+    assert!(delta.contains_key(&1));
+    assert!(!delta.contains_key(&2));
+    assert!(!delta.contains_key(&4));
+    assert_eq!(delta.len_primitives(), 3);
+}
+
+#[test]
+fn reset_test() {
+    let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new();
+    for key in 0..10 {
+        delta.stage(key, vec![key as u8]);
+    }
+    assert!(matches!(delta.commit(), MeshPatch::Reset(_)));
+
+    // a small edit writes in place
+    delta.stage(3, vec![30]);
+    match delta.commit() {
+        MeshPatch::Writes(writes) => {
+            assert_eq!(writes.new_len, 10);
+            assert_eq!(writes.writes_data, vec![30]);
+            assert_eq!(writes.iter_contiguous().count(), 1);
+        }
+        MeshPatch::Reset(_) => panic!("small edit should not reset"),
+    }
+
+    // a mass edit resets
+    for key in 0..6 {
+        delta.stage(key, vec![key as u8 + 100]);
+    }
+    assert!(matches!(delta.commit(), MeshPatch::Reset(_)));
+
+    // unless disabled
+    delta.set_reset_threshold(None);
+    for key in 0..10 {
+        delta.stage(key, vec![key as u8 + 200]);
+    }
+    match delta.commit() {
+        MeshPatch::Writes(writes) => assert_eq!(writes.writes_indices.len(), 10),
+        MeshPatch::Reset(_) => panic!("reset should be disabled"),
+    }
+}
+
+#[test]
+fn range_test() {
+    let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new();
+    delta.set_reset_threshold(None);
+    delta.stage(0, vec![1, 2]);
+    delta.stage(1, vec![3, 4, 5]);
+    delta.stage(2, vec![6]);
+    let _ = delta.commit();
+    assert_eq!(delta.range_of(&0), Some(0..2));
+    assert_eq!(delta.range_of(&1), Some(2..5));
+    assert_eq!(delta.range_of(&2), Some(5..6));
+    assert_eq!(delta.live_ranges().collect::<Vec<_>>(), vec![0..6]);
+
+    // removing a key moves the last key into its hole
+    delta.remove(&0);
+    let _ = delta.commit();
+    assert_eq!(delta.range_of(&0), None);
+    assert_eq!(delta.range_of(&2), Some(0..1));
+    assert_eq!(delta.live_ranges().collect::<Vec<_>>(), vec![0..1, 2..5]);
+
+    // a new key fills the remaining hole
+    delta.stage(3, vec![7]);
+    let _ = delta.commit();
+    assert_eq!(delta.range_of(&3), Some(1..2));
+    assert_eq!(delta.live_ranges().collect::<Vec<_>>(), vec![0..5]);
+
+    // the last key grows in place
+    delta.stage(1, vec![3, 4, 5, 8]);
+    let _ = delta.commit();
+    assert_eq!(delta.range_of(&1), Some(2..6));
+    assert_eq!(delta.get(&1).copied().collect::<Vec<_>>(), vec![3, 4, 5, 8]);
+    assert_eq!(delta.len_primitives(), 6);
+}
+
+#[test]
+fn handle_test() {
+    use std::thread;
+
+    let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new();
+    delta.stage(0, vec![1]);
+    delta.stage(1, vec![2]);
+    let _ = delta.commit();
+
+    let handle = delta.handle();
+    let threads = (2..6)
+        .map(|key| {
+            let handle = handle.clone();
+            thread::spawn(move || handle.stage(key, vec![key as u8 * 10]))
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    handle.remove(0);
+
+    // staged directly, but overridden by the handle
+    delta.stage(0, vec![3]);
+
+    // nothing is received until commit
+    assert_eq!(delta.len_primitives(), 2);
+    let _ = delta.commit();
+    assert_eq!(delta.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    assert_eq!(delta.get(&4).copied().collect::<Vec<_>>(), vec![40]);
+}
+
+#[test]
+fn stats_test() {
+    let mut delta: MeshDiffer<usize, u16> = MeshDiffer::new();
+    delta.set_reset_threshold(None);
+    delta.stage(0, vec![1, 2, 3]);
+    delta.stage(1, vec![4]);
+    assert_eq!(delta.stats().bytes_staged, 8);
+    let _ = delta.commit();
+
+    delta.stage(0, vec![1, 2]);
+    let _ = delta.commit();
+    assert_eq!(delta.stats(), MeshDifferStats {
+        num_primitives: 3,
+        num_holes: 0,
+        bytes_staged: 0,
+        // the last key moves into the freed slot
+        last_commit_writes: 1,
+        last_commit_reset: false,
+        num_commits: 2,
+    });
+}
+
+#[test]
+fn indexed_test() {
+    use rand::random;
+
+    // apply a patch to a follower
+    fn apply<T: Copy + Default>(patch: &MeshPatch<T>, follower: &mut Vec<T>) {
+        follower.resize(patch.new_len(), T::default());
+        for (index, &primitive) in patch.iter_writes() {
+            follower[index] = primitive;
+        }
+    }
+
+    // vertices and indices of each key
+    let mut plain: Vec<(Vec<u8>, Vec<u32>)> = vec![(vec![], vec![]); 10];
+
+    let mut delta: MeshDiffer<usize, u8> = MeshDiffer::new_indexed();
+    let mut vertices: Vec<u8> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for i in 0..2000 {
+        for key in 0..plain.len() {
+            match random::<u8>() % 8 {
+                0 => {
+                    plain[key] = (vec![], vec![]);
+                    delta.remove(&key);
+                }
+                1 => {
+                    // indexed in order
+                    let bin = vec![random::<u8>(); random::<usize>() % 6];
+                    let order = (0..bin.len() as u32).collect();
+                    delta.stage(key, bin.iter().copied());
+                    plain[key] = (bin, order);
+                }
+                2 | 3 => {
+                    let len = random::<usize>() % 6 + 1;
+                    let bin = (0..len).map(|_| random::<u8>()).collect::<Vec<_>>();
+                    let order = (0..random::<usize>() % 12)
+                        .map(|_| (random::<usize>() % len) as u32)
+                        .collect::<Vec<_>>();
+                    delta.stage_indexed(key, bin.iter().copied(), order.iter().copied());
+                    plain[key] = (bin, order);
+                }
+                _ => (),
+            }
+        }
+
+        let patch = delta.commit_indexed();
+        apply(&patch.vertices, &mut vertices);
+        apply(&patch.indices, &mut indices);
+
+        // assert each key's indices resolve to its vertices, in order
+        for (key, &(ref bin, ref order)) in plain.iter().enumerate() {
+            let expected = order
+                .iter()
+                .map(|&index| bin[index as usize])
+                .collect::<Vec<_>>();
+            let vertex_range = delta.range_of(&key).unwrap_or(0..0);
+            let actual = delta
+                .index_range_of(&key)
+                .map(|range| indices[range].to_vec())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|index| {
+                    assert!(
+                        vertex_range.contains(&(index as usize)),
+                        "key {} index outside its vertices on iteration {}",
+                        key, i
+                    );
+                    vertices[index as usize]
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                expected, actual,
+                "key {} desynchronization on iteration {}",
+                key, i
+            );
+        }
+
+        // the full patch builds the same buffers
+        if i % 100 == 0 {
+            let full = delta.full_indexed_patch();
+            let mut full_indices = Vec::new();
+            apply(&full.indices, &mut full_indices);
+            for range in delta.index_live_ranges() {
+                assert_eq!(full_indices[range.clone()], indices[range]);
+            }
+        }
     }
 }
@@ -8,9 +8,18 @@ pub mod pod_fields;
 pub mod vertex;
 #[macro_use]
 pub mod uniform;
+#[macro_use]
+pub mod push_constant;
 pub mod mesh_diff;
 pub mod buffer_vec;
+pub mod uploader;
 pub mod texture_array;
+pub mod texture_manifest;
+pub mod bitmap_font;
+pub mod render_target;
+pub mod headless;
+#[macro_use]
+pub mod compute;
 
 /// Helper function. 
 pub fn label(label: &str) -> Option<Cow<str>> {
@@ -31,6 +40,25 @@ macro_rules! cowslice {
     };
 }
 
+/// Expresses a `ShaderModuleSource<'static>`
+macro_rules! include_shader {
+    ($file:expr)=>{{
+        let bytes: &[u8] = include_bytes!($file);
+        assert_eq!(bytes.len() % 4, 0);
+        let words: &[u32] = unsafe {
+            std::slice::from_raw_parts(
+                bytes.as_ptr() as *const u8 as *const u32,
+                bytes.len() / 4,
+            )
+        };
+        wgpu::ShaderModuleSource::SpirV(
+            std::borrow::Cow::Borrowed(
+                words
+            )
+        )
+    }};
+}
+
 /// Const-friendly replacement for `Mat4::new`. 
 ///
 /// Assumes column-major. 
@@ -54,7 +82,8 @@ macro_rules! mat4 {
 
 /// Correction matrix. 
 ///
-/// I don't understand why this needs to exist. 
+/// I don't understand why this needs to exist. It is not applied to the 
+/// reverse-Z projection, see `Camera::reverse_z`. 
 pub const CORR: Mat4<f32> = mat4! {
     1.0, 0.0, 0.0, 0.0,
     0.0, 1.0, 0.0, 0.0,
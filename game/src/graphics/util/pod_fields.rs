@@ -12,17 +12,50 @@ use bytemuck::{Pod, bytes_of};
 /// for types with padding, this is an entirely safe trait which builds off 
 /// of `Pod` and can convert structs with padding into fully initialized
 /// byte arrays. 
+///
+/// Fields may themselves be `PodFields`, such as an array of structs, in 
+/// which case they're visited with `visit_nested`. 
 pub trait PodFields {
     fn visit_fields<V: PodFieldVisitor>(&self, visitor: &mut V);
 }
 
 pub trait PodFieldVisitor {
     fn visit<F: Pod>(&mut self, field: &F);
+
+    /// Visit a field which is itself `PodFields`, by visiting each of its 
+    /// fields. 
+    fn visit_nested<F: PodFields>(&mut self, field: &F)
+    where
+        Self: Sized,
+    {
+        field.visit_fields(self);
+    }
+}
+
+macro_rules! array_pod_fields {
+    ($($n:literal)*)=>{
+        $(
+        impl<P: PodFields> PodFields for [P; $n] {
+            fn visit_fields<V: PodFieldVisitor>(&self, visitor: &mut V) {
+                for elem in self.iter() {
+                    visitor.visit_nested(elem);
+                }
+            }
+        }
+        )*
+    };
+}
+
+array_pod_fields! {
+    1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
 }
 
 /// Convert an `impl PodFields` into a fully initialized byte array. 
 ///
-/// `write_to` must be equal in length to `std::mem::size_of::<P>()`.
+/// `write_to` must be equal in length to `std::mem::size_of::<P>()`. Nested 
+/// fields are written at their offset relative to the start of `structure`, 
+/// and padding bytes are left as they were. 
 pub fn copy_bytes_to<P>(structure: &P, write_to: &mut [u8])
 where
     P: PodFields,
@@ -84,4 +117,53 @@ fn test_pod_fields() {
     copy_bytes_to(&foo, &mut foo_bytes);
     let correct = [0xAB, 0x00, 0xEF, 0xCD];
     assert_eq!(foo_bytes, correct);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_nested_pod_fields() {
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    struct Light {
+        intensity: u8,
+        color: [u8; 3],
+        range: u16,
+    }
+
+    impl PodFields for Light {
+        fn visit_fields<V: PodFieldVisitor>(&self, visitor: &mut V) {
+            visitor.visit(&self.intensity);
+            visitor.visit(&self.color);
+            visitor.visit(&self.range);
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    struct Lights {
+        count: u8,
+        lights: [Light; 2],
+    }
+
+    impl PodFields for Lights {
+        fn visit_fields<V: PodFieldVisitor>(&self, visitor: &mut V) {
+            visitor.visit(&self.count);
+            visitor.visit_nested(&self.lights);
+        }
+    }
+
+    let lights = Lights {
+        count: 2,
+        lights: [
+            Light { intensity: 0x11, color: [0x12, 0x13, 0x14], range: 0x1516 },
+            Light { intensity: 0x21, color: [0x22, 0x23, 0x24], range: 0x2526 },
+        ],
+    };
+    let mut bytes = [0; 14];
+    copy_bytes_to(&lights, &mut bytes);
+    let correct = [
+        0x02, 0x00,
+        0x11, 0x12, 0x13, 0x14, 0x16, 0x15,
+        0x21, 0x22, 0x23, 0x24, 0x26, 0x25,
+    ];
+    assert_eq!(bytes, correct);
+}
@@ -0,0 +1,142 @@
+//! Push constant utilities.
+//!
+//! Push constants are small blocks of data which are recorded directly into
+//! a render pass, rather than being copied into a uniform buffer. They
+//! require the `PUSH_CONSTANTS` feature, which not all adapters support.
+
+use wgpu::{
+    PushConstantRange,
+    RenderPass,
+    ShaderStage,
+};
+
+/// Trait for shader push constant blocks.
+pub trait GenericPushConstants {
+    /// Binary size of the push constant block, rounded up to a multiple of 4.
+    const SIZE: usize;
+
+    /// Shader stages which can see the push constant block.
+    const STAGES: ShaderStage;
+
+    /// Write `self`'s binary representation to a word array.
+    ///
+    /// `words` must be `Self::SIZE / 4` in length.
+    fn encode_to(&self, words: &mut [u32]);
+
+    /// The push constant range to put in a pipeline layout.
+    fn range() -> PushConstantRange {
+        PushConstantRange {
+            stages: Self::STAGES,
+            range: 0..Self::SIZE as u32,
+        }
+    }
+
+    /// Set these push constants in a render pass.
+    fn set<'a>(&self, pass: &mut RenderPass<'a>) {
+        let mut words = vec![0_u32; Self::SIZE / 4];
+        self.encode_to(&mut words);
+        pass.set_push_constants(Self::STAGES, 0, &words);
+    }
+}
+
+/// Round a byte size up to a whole number of 4-byte words.
+pub const fn word_aligned(size: usize) -> usize {
+    (size + 3) / 4 * 4
+}
+
+/// Implement `GenericPushConstants` on a type with GLSL-like syntax.
+///
+/// Fields use the same `UniformField` conversions and std140 layout as
+/// `#[derive(Uniforms)]`, so the shader should declare the block with
+/// `layout(push_constant, std140)`.
+///
+/// Example:
+/// ```
+/// use vek::*;
+///
+/// struct ChunkOffset {
+///     offset: Vec3<i32>,
+/// }
+///
+/// push_constants! {
+///     ChunkOffset in VERTEX {
+///         ivec3 offset: Vec3<i32>,
+///     }
+/// }
+/// ```
+macro_rules! push_constants {
+    (
+    $push_constants:ident in $($stage:ident)|+ {$(
+        $glsl_type:ident $rust_field:ident: $rust_type:ty,
+    )*}
+    )=>{
+        impl $crate::graphics::util::push_constant::GenericPushConstants for $push_constants {
+            const SIZE: usize = {
+                #[allow(unused_mut)]
+                let mut end = 0;
+                $(
+                end = $crate::graphics::util::uniform::std140_offset(
+                    end,
+                    <
+                        $rust_type
+                        as
+                        $crate::graphics::util::uniform::UniformField
+                    >::STD140_ALIGN,
+                ) + <
+                    $rust_type
+                    as
+                    $crate::graphics::util::uniform::UniformField
+                >::STD140_SIZE;
+                )*
+                $crate::graphics::util::push_constant::word_aligned(end)
+            };
+
+            const STAGES: wgpu::ShaderStage = wgpu::ShaderStage::from_bits_truncate(
+                0 $( | wgpu::ShaderStage::$stage.bits() )+
+            );
+
+            fn encode_to(&self, words: &mut [u32]) {
+                assert_eq!(words.len() * 4, Self::SIZE, "wrong size");
+
+                let mut bytes = [0_u8; <Self as $crate::graphics::util::push_constant::GenericPushConstants>::SIZE];
+                #[allow(unused_mut)]
+                let mut end = 0;
+                $(
+                end = $crate::graphics::util::uniform::write_std140_field::<$rust_type>(
+                    &mut bytes,
+                    end,
+                    &self.$rust_field,
+                );
+                )*
+                let _ = end;
+
+                for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+                    *word = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                }
+            }
+        }
+    };
+}
+
+#[test]
+fn macro_test() {
+    use vek::*;
+
+    struct Example {
+        a: Vec3<i32>,
+        b: f32,
+    }
+
+    push_constants! {
+        Example in VERTEX | FRAGMENT {
+            ivec3 a: Vec3<i32>,
+            float b: f32,
+        }
+    }
+
+    assert_eq!(Example::SIZE, 16);
+    assert_eq!(Example::STAGES, ShaderStage::VERTEX | ShaderStage::FRAGMENT);
+    let mut words = [0; 4];
+    Example { a: Vec3::new(1, 2, 3), b: 1.0 }.encode_to(&mut words);
+    assert_eq!(words, [1, 2, 3, 1.0_f32.to_bits()]);
+}
@@ -0,0 +1,163 @@
+//! Off-screen render targets.
+
+use crate::graphics::label;
+use std::borrow::Cow;
+use wgpu::*;
+use vek::*;
+
+/// How a `RenderTarget` is sized.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenderTargetSize {
+    /// Always the same size as the window, resized along with it.
+    Window,
+    /// A fixed size, only changed by explicitly calling `resize`.
+    Fixed(Extent2<u32>),
+}
+
+/// Off-screen color texture, with an optional depth texture, which can be
+/// rendered into and then sampled from.
+pub struct RenderTarget {
+    label: Option<Cow<'static, str>>,
+    size_mode: RenderTargetSize,
+    size: Extent2<u32>,
+    color_format: TextureFormat,
+    depth_format: Option<TextureFormat>,
+    color_view: TextureView,
+    depth_view: Option<TextureView>,
+    generation: u64,
+}
+
+impl RenderTarget {
+    /// Create a new render target.
+    ///
+    /// `window_size` is used if `size_mode` is `RenderTargetSize::Window`.
+    pub fn new(
+        device: &Device,
+        size_mode: RenderTargetSize,
+        window_size: Extent2<u32>,
+        color_format: TextureFormat,
+        depth_format: Option<TextureFormat>,
+        label: Option<Cow<'static, str>>,
+    ) -> Self {
+        let size = match size_mode {
+            RenderTargetSize::Window => window_size,
+            RenderTargetSize::Fixed(size) => size,
+        };
+        let (color_view, depth_view) = create_views(
+            device,
+            size,
+            color_format,
+            depth_format,
+        );
+        RenderTarget {
+            label,
+            size_mode,
+            size,
+            color_format,
+            depth_format,
+            color_view,
+            depth_view,
+            generation: 0,
+        }
+    }
+
+    /// Re-create the underlying textures with a new size.
+    ///
+    /// The contents of the textures are discarded.
+    pub fn resize(&mut self, device: &Device, size: Extent2<u32>) {
+        if size == self.size {
+            return;
+        }
+        trace!(label = ?self.label, ?size, "resizing render target");
+
+        let (color_view, depth_view) = create_views(
+            device,
+            size,
+            self.color_format,
+            self.depth_format,
+        );
+        self.size = size;
+        self.color_view = color_view;
+        self.depth_view = depth_view;
+        self.generation += 1;
+    }
+
+    /// Handle the window being resized, resizing `self` if it follows the
+    /// window size.
+    pub fn window_resized(&mut self, device: &Device, window_size: Extent2<u32>) {
+        if self.size_mode == RenderTargetSize::Window {
+            self.resize(device, window_size);
+        }
+    }
+
+    /// Get the current size.
+    pub fn size(&self) -> Extent2<u32> {
+        self.size
+    }
+
+    /// Get the color texture format.
+    pub fn color_format(&self) -> TextureFormat {
+        self.color_format
+    }
+
+    /// Get the depth texture format, if there is a depth texture.
+    pub fn depth_format(&self) -> Option<TextureFormat> {
+        self.depth_format
+    }
+
+    /// Get the view of the color texture.
+    pub fn color_view(&self) -> &TextureView {
+        &self.color_view
+    }
+
+    /// Get the view of the depth texture, if there is one.
+    pub fn depth_view(&self) -> Option<&TextureView> {
+        self.depth_view.as_ref()
+    }
+
+    /// Counter which increments each time the underlying textures are
+    /// re-created.
+    ///
+    /// Anything which binds the texture views, such as a bind group, must be
+    /// re-created when this changes.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+fn create_views(
+    device: &Device,
+    size: Extent2<u32>,
+    color_format: TextureFormat,
+    depth_format: Option<TextureFormat>,
+) -> (TextureView, Option<TextureView>) {
+    let extent = Extent3d {
+        width: size.w,
+        height: size.h,
+        depth: 1,
+    };
+    let color_view = device
+        .create_texture(&TextureDescriptor {
+            label: label("render target color"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: color_format,
+            usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+        })
+        .create_default_view();
+    let depth_view = depth_format
+        .map(|format| device
+            .create_texture(&TextureDescriptor {
+                label: label("render target depth"),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+            })
+            .create_default_view());
+    (color_view, depth_view)
+}
@@ -1,6 +1,16 @@
 //! Texture array utilities. 
 
-use std::iter::repeat;
+use super::texture_manifest::{
+    TextureManifest,
+    FaceTextureMap,
+};
+use std::{
+    iter::repeat,
+    collections::HashMap,
+    sync::Arc,
+    fs,
+    path::Path,
+};
 use crate::graphics::label;
 use wgpu::{
     *, 
@@ -12,6 +22,7 @@ use wgpu::{
 use vek::*;
 use image::{
     self,
+    RgbaImage,
     imageops::{
         self,
         FilterType,
@@ -19,8 +30,25 @@ use image::{
 };
 use pear::*;
 
+/// Bytes per texel, in the `Rgba8UnormSrgb` format. 
+pub(crate) const BYTES_PER_TEXEL: u32 = 4;
+
+/// Number of bytes each row of a layer occupies in the upload buffer, 
+/// given the layer's width in texels. 
+///
+/// This is padded up to a multiple of `COPY_BYTES_PER_ROW_ALIGNMENT`, as 
+/// required by `copy_buffer_to_texture`. 
+pub(crate) fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * BYTES_PER_TEXEL;
+    let align = COPY_BYTES_PER_ROW_ALIGNMENT;
+    (unpadded + align - 1) / align * align
+}
+
 /// Utility for arranging texture array data, then uploading it to WGPU. 
 ///
+/// Layers may have any non-zero dimensions, including non-square ones, 
+/// but all layers in an array share the same dimensions. 
+///
 /// Currently, this is hard-coded to use the `Rgba8UnormSrgb` format. 
 #[derive(Clone)]
 pub struct TextureArrayBuilder {
@@ -28,23 +56,45 @@ pub struct TextureArrayBuilder {
     layers: u32,
     data: Vec<u8>,
     bytes_per_row: u32,
+    names: HashMap<String, u32>,
+}
+
+/// Mapping from layer names to indices in a texture array, which is retained 
+/// after the texture array is built. 
+#[derive(Clone, Debug, Default)]
+pub struct TextureArrayMap {
+    names: Arc<HashMap<String, u32>>,
+}
+
+impl TextureArrayMap {
+    /// Look up a layer's index by its name. 
+    pub fn layer_index(&self, name: &str) -> Option<u32> {
+        self.names.get(name).copied()
+    }
+
+    /// Iterate the named layers, in no particular order. 
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item=(&'s str, u32)> + 's {
+        self.names.iter().map(|(name, &index)| (name.as_str(), index))
+    }
 }
 
 impl TextureArrayBuilder {
-    /// Begin building a texture array with the specified dimensions. 
+    /// Begin building a texture array with the specified layer dimensions, 
+    /// in texels. 
+    ///
+    /// Panics if either dimension is zero. 
     pub fn new<E: Into<Extent2<u32>>>(dim: E) -> Self {
         let dim = dim.into();
+        assert!(dim.w > 0 && dim.h > 0, "texture array dimensions must be non-zero");
 
-        let bytes_per_row = match dim.w % COPY_BYTES_PER_ROW_ALIGNMENT {
-            0 => dim.w,
-            rem => (dim.w - rem + 1) * COPY_BYTES_PER_ROW_ALIGNMENT,
-        };
+        let bytes_per_row = padded_bytes_per_row(dim.w);
 
         TextureArrayBuilder {
-            dim: dim.into(),
+            dim,
             layers: 0,
             data: Vec::new(),
             bytes_per_row,
+            names: HashMap::new(),
         }
     }
 
@@ -53,36 +103,112 @@ impl TextureArrayBuilder {
     /// The parameter, `bytes`, is the contents of an image file, such as PNG 
     /// or JPEG. This makes an educated guess about which format it is.  
     pub fn add_layer(&mut self, bytes: &[u8]) -> Result<u32> {
-        let mut image = image::load_from_memory(bytes)?
+        let image = image::load_from_memory(bytes)?
             .into_rgba();
+        Ok(self.add_layer_image(image))
+    }
 
-        if image.dimensions() != self.dim.into_tuple() {
-            warn!(
-                image_dimensions = ?image.dimensions(),
-                texture_array_dimensions = ?self.dim,
-                "incorrect layer size, resizing"
-            );
-            image = imageops::resize(
-                &image,
-                self.dim.w,
-                self.dim.h,
-                FilterType::Nearest,
-            );
+    /// Add a layer to the texture array with a name, which can be used to 
+    /// look up its index. Return its index. 
+    ///
+    /// Errors if a layer with that name already exists. 
+    pub fn add_layer_named(&mut self, name: &str, bytes: &[u8]) -> Result<u32> {
+        if self.names.contains_key(name) {
+            return Err(pear!({name=name}, "duplicate texture array layer name"));
         }
+        let layer = self.add_layer(bytes)
+            .wrap_err(|| pear!({name=name}, "failed to load named texture array layer"))?;
+        self.names.insert(name.to_owned(), layer);
+        Ok(layer)
+    }
+
+    /// Add every image file in a directory as a layer, named by its file 
+    /// stem, in order of file name. Return their indices. 
+    pub fn load_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<u32>> {
+        let path = path.as_ref();
+        let mut files = Vec::new();
+        for entry in fs::read_dir(path)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({path=path}, "failed to read texture directory"))?
+        {
+            let file = entry?.path();
+            let is_image = file.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| image::ImageFormat::from_extension(ext).is_some())
+                .unwrap_or(false);
+            if file.is_file() && is_image {
+                files.push(file);
+            }
+        }
+        files.sort();
 
-        let raw = image.into_raw();
-        for row in 0..self.dim.h {
-            let i1 = (row * self.dim.w * 4) as usize;
-            let i2 = ((row + 1) * self.dim.w * 4) as usize;
-            let pad = (self.bytes_per_row - (self.dim.w * 4)) as usize;
+        let mut layers = Vec::new();
+        for file in files {
+            let name = file.file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| pear!({file=file}, "texture file name is not UTF-8"))?;
+            let bytes = fs::read(&file)
+                .map_err(Error::from)
+                .wrap_err(|| pear!({file=file}, "failed to read texture file"))?;
+            layers.push(self.add_layer_named(name, &bytes)?);
+        }
+        Ok(layers)
+    }
 
-            self.data.extend(raw[i1..i2].iter().copied());
-            self.data.extend(repeat(0).take(pad));
+    /// Add the layers listed in a texture manifest file, then resolve its 
+    /// face assignments. 
+    ///
+    /// See the `texture_manifest` module for the file format. 
+    pub fn load_manifest<P: AsRef<Path>>(&mut self, path: P) -> Result<FaceTextureMap> {
+        let path = path.as_ref();
+        let manifest = TextureManifest::read(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for layer in &manifest.layers {
+            let file = layer.file_path(dir);
+            let bytes = fs::read(&file)
+                .map_err(Error::from)
+                .wrap_err(|| pear!({file=file}, "failed to read texture file"))?;
+            self.add_layer_named(&layer.name, &bytes)?;
         }
+        manifest.resolve_faces(self)
+    }
+
+    /// Look up a layer's index by its name. 
+    pub fn layer_index(&self, name: &str) -> Option<u32> {
+        self.names.get(name).copied()
+    }
+
+    /// Get a `TextureArrayMap` of the currently named layers. 
+    pub fn layer_map(&self) -> TextureArrayMap {
+        TextureArrayMap {
+            names: Arc::new(self.names.clone()),
+        }
+    }
+
+    /// Add an already-decoded layer to the texture array. Return its index. 
+    pub fn add_layer_image(&mut self, image: RgbaImage) -> u32 {
+        self.data.extend(layer_data(image, self.dim));
+        debug_assert_eq!(
+            self.data.len(),
+            self.layer_offset(self.layers + 1) as usize,
+        );
 
         let layer = self.layers;
         self.layers += 1;
-        Ok(layer)
+        layer
+    }
+
+    /// Replace the image of an existing layer, such as after its file was 
+    /// modified. 
+    ///
+    /// This only affects texture arrays built afterwards. To update one 
+    /// which was already built, see `write_layer`. Panics if the layer 
+    /// doesn't exist. 
+    pub fn replace_layer_image(&mut self, layer: u32, image: RgbaImage) {
+        assert!(layer < self.layers, "texture array layer out of bounds");
+        let start = self.layer_offset(layer) as usize;
+        let end = self.layer_offset(layer + 1) as usize;
+        self.data[start..end].copy_from_slice(&layer_data(image, self.dim));
     }
 
     /// Get the number of layers loaded into this texture array. 
@@ -95,12 +221,34 @@ impl TextureArrayBuilder {
         self.dim
     }
 
+    /// Get the number of bytes each row of a layer occupies in the upload 
+    /// buffer, including padding. 
+    pub fn bytes_per_row(&self) -> u32 {
+        self.bytes_per_row
+    }
+
+    /// Byte offset of a layer in the upload buffer. 
+    fn layer_offset(&self, layer: u32) -> u64 {
+        layer as u64 * self.bytes_per_row as u64 * self.dim.h as u64
+    }
+
     /// Upload data to the GPU, creating a `TextureView` and `Sampler` for this texture array. 
     pub fn build(
         &self, 
         device: &Device, 
         command_encoder: &mut CommandEncoder,
     ) -> (TextureView, Sampler) {
+        let (_, texture_view, sampler) = self.build_texture(device, command_encoder);
+        (texture_view, sampler)
+    }
+
+    /// Like `build`, but also return the `Texture`, so that its layers can be 
+    /// overwritten later with `write_layer`. 
+    pub fn build_texture(
+        &self, 
+        device: &Device, 
+        command_encoder: &mut CommandEncoder,
+    ) -> (Texture, TextureView, Sampler) {
         trace!("uploading texture array to WGPU");
 
         let texture = device
@@ -126,31 +274,14 @@ impl TextureArrayBuilder {
                 usage: BufferUsage::COPY_SRC,
             });
         for layer in 0..self.layers {
-            command_encoder
-                .copy_buffer_to_texture(
-                    BufferCopyView {
-                        buffer: &copy_src,
-                        layout: TextureDataLayout {
-                            offset: (layer * self.bytes_per_row * self.dim.h) as u64,
-                            bytes_per_row: self.bytes_per_row,
-                            rows_per_image: self.dim.h,
-                        },
-                    },
-                    TextureCopyView {
-                        texture: &texture,
-                        mip_level: 0,
-                        origin: Origin3d {
-                            x: 0,
-                            y: 0,
-                            z: layer,
-                        },
-                    },
-                    Extent3d {
-                        width: self.dim.w,
-                        height: self.dim.h,
-                        depth: 1,
-                    }
-                );
+            copy_layer(
+                command_encoder,
+                &copy_src,
+                self.layer_offset(layer),
+                &texture,
+                self.dim,
+                layer,
+            );
         }
 
         let texture_view = texture.create_default_view();
@@ -169,6 +300,153 @@ impl TextureArrayBuilder {
                 anisotropy_clamp: None,
             });
 
-        (texture_view, sampler)
+        (texture, texture_view, sampler)
+    }
+}
+
+/// Resize an image to a texture array's layer dimensions if necessary, and 
+/// lay out its rows with the padding the upload buffer needs. 
+fn layer_data(mut image: RgbaImage, dim: Extent2<u32>) -> Vec<u8> {
+    if image.dimensions() != dim.into_tuple() {
+        warn!(
+            image_dimensions = ?image.dimensions(),
+            texture_array_dimensions = ?dim,
+            "incorrect layer size, resizing"
+        );
+        image = imageops::resize(
+            &image,
+            dim.w,
+            dim.h,
+            FilterType::Nearest,
+        );
+    }
+
+    let raw = image.into_raw();
+    let bytes_per_row = padded_bytes_per_row(dim.w) as usize;
+    let row_len = (dim.w * BYTES_PER_TEXEL) as usize;
+    let pad = bytes_per_row - row_len;
+    let mut data = Vec::with_capacity(bytes_per_row * dim.h as usize);
+    for row in raw.chunks_exact(row_len) {
+        data.extend(row.iter().copied());
+        data.extend(repeat(0).take(pad));
+    }
+    data
+}
+
+/// Record a copy of one layer's data from an upload buffer into a texture 
+/// array. 
+fn copy_layer(
+    command_encoder: &mut CommandEncoder,
+    buffer: &Buffer,
+    offset: BufferAddress,
+    texture: &Texture,
+    dim: Extent2<u32>,
+    layer: u32,
+) {
+    command_encoder
+        .copy_buffer_to_texture(
+            BufferCopyView {
+                buffer,
+                layout: TextureDataLayout {
+                    offset,
+                    bytes_per_row: padded_bytes_per_row(dim.w),
+                    rows_per_image: dim.h,
+                },
+            },
+            TextureCopyView {
+                texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+            },
+            Extent3d {
+                width: dim.w,
+                height: dim.h,
+                depth: 1,
+            }
+        );
+}
+
+/// Overwrite one layer of an already-built texture array with an image, by 
+/// copying it through a staging buffer. 
+///
+/// `dim` must be the texture array's layer dimensions. The image is resized 
+/// if it doesn't match them. 
+pub fn write_layer(
+    texture: &Texture,
+    dim: Extent2<u32>,
+    layer: u32,
+    image: RgbaImage,
+    device: &Device,
+    command_encoder: &mut CommandEncoder,
+) {
+    trace!(layer, "overwriting texture array layer");
+    let staging = device
+        .create_buffer_init(&BufferInitDescriptor {
+            label: Some("texture array layer staging buffer"),
+            contents: &layer_data(image, dim),
+            usage: BufferUsage::COPY_SRC,
+        });
+    copy_layer(command_encoder, &staging, 0, texture, dim, layer);
+}
+
+#[test]
+fn padded_bytes_per_row_test() {
+    let align = COPY_BYTES_PER_ROW_ALIGNMENT;
+    for &width in &[1, 15, 16, 17, 48, 63, 64, 65, 96, 100, 256, 1000] {
+        let bytes_per_row = padded_bytes_per_row(width);
+        assert_eq!(bytes_per_row % align, 0, "width {}", width);
+        assert!(bytes_per_row >= width * BYTES_PER_TEXEL, "width {}", width);
+        assert!(bytes_per_row - width * BYTES_PER_TEXEL < align, "width {}", width);
+    }
+    assert_eq!(padded_bytes_per_row(16), 256);
+    assert_eq!(padded_bytes_per_row(96), 512);
+    assert_eq!(padded_bytes_per_row(256), 1024);
+}
+
+#[test]
+fn non_square_layer_test() {
+    let dim = Extent2::new(48, 5);
+    let mut builder = TextureArrayBuilder::new(dim);
+    for layer in 0..2u8 {
+        let image = RgbaImage::from_fn(dim.w, dim.h, |x, y| {
+            image::Rgba([x as u8, y as u8, layer, 255])
+        });
+        assert_eq!(builder.add_layer_image(image), layer as u32);
+    }
+
+    let bytes_per_row = builder.bytes_per_row() as usize;
+    assert_eq!(builder.data.len(), bytes_per_row * 5 * 2);
+    for layer in 0..2 {
+        for y in 0..5 {
+            let row = &builder.data[(layer * 5 + y) * bytes_per_row..][..bytes_per_row];
+            for x in 0..48 {
+                assert_eq!(&row[x * 4..x * 4 + 4], &[x as u8, y as u8, layer as u8, 255]);
+            }
+            assert!(row[48 * 4..].iter().all(|&b| b == 0));
+        }
+    }
+}
+
+#[test]
+fn replace_layer_test() {
+    let dim = Extent2::new(4, 4);
+    let mut builder = TextureArrayBuilder::new(dim);
+    let solid = |value| RgbaImage::from_pixel(dim.w, dim.h, image::Rgba([value; 4]));
+    for value in 0..3 {
+        builder.add_layer_image(solid(value));
+    }
+    // wrong-sized replacements are resized
+    builder.replace_layer_image(1, RgbaImage::from_pixel(8, 2, image::Rgba([9; 4])));
+
+    let layer_len = builder.bytes_per_row() as usize * dim.h as usize;
+    assert_eq!(builder.data.len(), layer_len * 3);
+    for (layer, value) in [0, 9, 2].iter().enumerate() {
+        for row in builder.data[layer * layer_len..][..layer_len].chunks(builder.bytes_per_row() as usize) {
+            assert!(row[..16].iter().all(|b| b == value));
+        }
     }
 }
@@ -0,0 +1,199 @@
+//! Texture manifests, for loading texture arrays from the filesystem. 
+//!
+//! A manifest is a TOML file which lists named layers, and optionally
+//! assigns layers to the faces of named blocks:
+//!
+//! ```toml
+//! [[layers]]
+//! name = "grass"
+//!
+//! [[layers]]
+//! name = "grass_side"
+//! # defaults to "<name>.png", relative to the manifest
+//! file = "grass_side.png"
+//!
+//! [faces.grass]
+//! top = "grass"
+//! sides = "grass_side"
+//! # fallback for any face not otherwise specified
+//! all = "dirt"
+//! ```
+//!
+//! Faces may be specified by `top`, `bottom`, `east`, `west`, `north`,
+//! `south`, `sides` (all horizontal faces), or `all`, with more specific
+//! keys taking priority. 
+
+use super::texture_array::TextureArrayBuilder;
+use crate::util::axis_unit::AxisUnit3;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use serde::Deserialize;
+use pear::*;
+
+/// Parsed contents of a texture manifest file. 
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextureManifest {
+    /// Layers to load, in order. 
+    #[serde(default)]
+    pub layers: Vec<ManifestLayer>,
+    /// Face assignments, by block name. 
+    #[serde(default)]
+    pub faces: BTreeMap<String, ManifestFaces>,
+}
+
+/// A named layer in a texture manifest. 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestLayer {
+    /// Name of the layer. 
+    pub name: String,
+    /// Image file path, relative to the manifest. Defaults to `<name>.png`. 
+    pub file: Option<String>,
+}
+
+impl ManifestLayer {
+    /// Get the path of the layer's image file, given the directory which
+    /// contains the manifest. 
+    pub fn file_path(&self, dir: &Path) -> PathBuf {
+        match self.file {
+            Some(ref file) => dir.join(file),
+            None => dir.join(format!("{}.png", self.name)),
+        }
+    }
+}
+
+/// Face assignments for a block in a texture manifest, as layer names. 
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestFaces {
+    pub all: Option<String>,
+    pub sides: Option<String>,
+    pub top: Option<String>,
+    pub bottom: Option<String>,
+    pub east: Option<String>,
+    pub west: Option<String>,
+    pub north: Option<String>,
+    pub south: Option<String>,
+}
+
+impl ManifestFaces {
+    /// Get the layer name assigned to a face, if any. 
+    pub fn face(&self, face: AxisUnit3) -> Option<&str> {
+        let (specific, horizontal) = match face {
+            AxisUnit3::EAST => (&self.east, true),
+            AxisUnit3::WEST => (&self.west, true),
+            AxisUnit3::NORTH => (&self.north, true),
+            AxisUnit3::SOUTH => (&self.south, true),
+            AxisUnit3::UP => (&self.top, false),
+            _ => (&self.bottom, false),
+        };
+        specific.as_ref()
+            .or(self.sides.as_ref().filter(|_| horizontal))
+            .or(self.all.as_ref())
+            .map(String::as_str)
+    }
+}
+
+/// Block face texture indices, by block name. 
+///
+/// Each block's faces are indexed by `AxisUnit3::to_index`. 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FaceTextureMap {
+    faces: BTreeMap<String, [u32; 6]>,
+}
+
+impl FaceTextureMap {
+    /// Get the face texture indices for a block. 
+    pub fn get(&self, block: &str) -> Option<[u32; 6]> {
+        self.faces.get(block).copied()
+    }
+
+    /// Number of blocks with face assignments. 
+    pub fn len(&self) -> usize {
+        self.faces.len()
+    }
+
+    /// Whether there are no blocks with face assignments. 
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    /// Iterate blocks and their face texture indices, sorted by name. 
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item=(&'s str, [u32; 6])> + 's {
+        self.faces.iter().map(|(name, &faces)| (name.as_str(), faces))
+    }
+}
+
+impl TextureManifest {
+    /// Parse a manifest from TOML. 
+    pub fn parse(toml: &str) -> Result<Self> {
+        toml::from_str(toml)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "invalid texture manifest"))
+    }
+
+    /// Read and parse a manifest file. 
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({path=path}, "failed to read texture manifest"))?;
+        Self::parse(&toml)
+    }
+
+    /// Resolve face assignments to layer indices, using the names in
+    /// `textures`. 
+    pub fn resolve_faces(&self, textures: &TextureArrayBuilder) -> Result<FaceTextureMap> {
+        let mut errors = Vec::new();
+        let mut faces = BTreeMap::new();
+        for (block, manifest_faces) in &self.faces {
+            let mut indices = [0; 6];
+            for (i, index) in indices.iter_mut().enumerate() {
+                let face = AxisUnit3::from_index(i);
+                match manifest_faces.face(face) {
+                    Some(name) => match textures.layer_index(name) {
+                        Some(layer) => *index = layer,
+                        None => errors.push(pear!(
+                            {block=block, face=face, layer=name},
+                            "texture manifest face references unknown layer",
+                        )),
+                    },
+                    None => errors.push(pear!(
+                        {block=block, face=face},
+                        "texture manifest face has no layer",
+                    )),
+                }
+            }
+            faces.insert(block.clone(), indices);
+        }
+
+        if errors.is_empty() {
+            Ok(FaceTextureMap { faces })
+        } else {
+            Err(errors.wrap(pear!({}, "invalid texture manifest face assignments")))
+        }
+    }
+}
+
+#[test]
+fn face_fallback_test() {
+    let manifest = TextureManifest::parse(r#"
+        [faces.grass]
+        top = "grass"
+        sides = "grass_side"
+        west = "special"
+        all = "dirt"
+    "#).unwrap();
+    let faces = &manifest.faces["grass"];
+    assert_eq!(faces.face(AxisUnit3::UP), Some("grass"));
+    assert_eq!(faces.face(AxisUnit3::DOWN), Some("dirt"));
+    assert_eq!(faces.face(AxisUnit3::EAST), Some("grass_side"));
+    assert_eq!(faces.face(AxisUnit3::WEST), Some("special"));
+    assert_eq!(faces.face(AxisUnit3::SOUTH), Some("grass_side"));
+
+    assert!(TextureManifest::parse("[faces.stone]\ncolor = \"grey\"").is_err());
+}
@@ -1,6 +1,11 @@
 //! Uniform buffer utilities, especially mucking around with bytes. 
+//!
+//! Uniform buffers are laid out according to GLSL's std140 rules, which 
+//! differ from Rust's `repr(C)` rules. For example, a `vec3` is 16-byte 
+//! aligned, and each column of a matrix is padded to 16 bytes. 
 
 use bytemuck::{Pod, bytes_of};
+use vek::*;
 
 /// Trait for shader uniform buffers. 
 pub trait GenericUniforms {
@@ -14,109 +19,64 @@ pub trait GenericUniforms {
 }
 
 /// Types which can convert into an ABI-compatible GLSL uniform struct field. 
-///
-/// There are some implementations which could exist, but do not currently, 
-/// like booleans and non-square matrices. 
 pub trait UniformField {
     /// The corresponding GLSL type. 
+    ///
+    /// For arrays, this is the element type. 
     const GLSL_TYPE: &'static str;
 
-    /// Binary-compatible representation. 
-    type Abi: Pod;
+    /// Base alignment of this type in std140 layout. 
+    const STD140_ALIGN: usize;
 
-    /// Convert to binary-compatible representation. 
-    fn to_abi(&self) -> Self::Abi;
+    /// Size of this type in std140 layout, including any padding within the 
+    /// type, such as between matrix columns or array elements. 
+    const STD140_SIZE: usize;
+
+    /// Write `self`'s std140 representation to a byte array. 
+    ///
+    /// `bytes` must be `Self::STD140_SIZE` in length. 
+    fn write_std140(&self, bytes: &mut [u8]);
 }
 
-/// Implement `GenericUniform` on a type with GLSL-like syntax. 
+/// Round `offset` up to a multiple of `align`. 
 ///
-/// Example:
-/// ```
-/// use vek::*;
-/// 
-/// struct Example {
-///     a: i32,
-///     b: Mat3<f32>,
-///     c: Vec2<u32>,
-///     d: Rgba<f64>,
-/// }
-/// 
-/// uniform! {
-///     Example {
-///         int a: i32,
-///         mat3 b: Mat3<f32>,
-///         uvec2 c: Vec2<u32>,
-///         dvec4 d: Rgba<f64>,
-///     }
-/// }
-/// ```
-macro_rules! uniforms {
-    (
-    $uniform:ident {$(
-        $glsl_type:ident $rust_field:ident: $rust_type:ty,
-    )*}
-    )=>{
-        impl $uniform {
-            /// Write `self`'s binary representation to a fixed-size byte array. 
-            pub fn encode(&self) -> [u8; <Self as $crate::graphics::util::uniform::GenericUniforms>::SIZE] {
-                let mut array = [0_u8; <Self as $crate::graphics::util::uniform::GenericUniforms>::SIZE];
-                <Self as $crate::graphics::util::uniform::GenericUniforms>::encode_to(self, &mut array);
-                array
-            }
-        }
+/// This is the std140 offset of a field with base alignment `align`, 
+/// placed after a field which ends at `offset`. 
+pub const fn std140_offset(offset: usize, align: usize) -> usize {
+    (offset + align - 1) / align * align
+}
 
-        impl $crate::graphics::util::uniform::GenericUniforms for $uniform {
-            const SIZE: usize = {
-                #[repr(C)]
-                struct Abi {$(
-                    $rust_field: <
-                        $rust_type 
-                        as 
-                        $crate::graphics::util::uniform::UniformField
-                    >::Abi,
-                )*}
-
-                std::mem::size_of::<Abi>()
-            };
+/// Write a field to `bytes` at its std140 offset, given where the previous 
+/// field ended. Return where this field ends. 
+pub fn write_std140_field<F: UniformField>(bytes: &mut [u8], offset: usize, field: &F) -> usize {
+    let start = std140_offset(offset, F::STD140_ALIGN);
+    let end = start + F::STD140_SIZE;
+    field.write_std140(&mut bytes[start..end]);
+    end
+}
 
-            fn encode_to(&self, bytes: &mut [u8]) {
-                assert_eq!(bytes.len(), Self::SIZE, "wrong size");
-
-                #[repr(C)]
-                struct Abi {$(
-                    $rust_field: <
-                        $rust_type 
-                        as 
-                        $crate::graphics::util::uniform::UniformField
-                    >::Abi,
-                )*}
-
-                impl $crate::graphics::util::pod_fields::PodFields for Abi {
-                    fn visit_fields<V>(&self, visitor: &mut V)
-                    where
-                        V: $crate::graphics::util::pod_fields::PodFieldVisitor,
-                    {
-                        $(
-                        visitor.visit(&self.$rust_field);
-                        )*
-                    }
-                }
+/// Write a plain-old-data value's bytes, which must be exactly as long as 
+/// `bytes`. 
+fn write_pod<P: Pod>(bytes: &mut [u8], pod: &P) {
+    bytes.copy_from_slice(bytes_of(pod));
+}
 
-                let abi = Abi {$(
-                    $rust_field: <
-                        $rust_type 
-                        as 
-                        $crate::graphics::util::uniform::UniformField
-                    >::to_abi(&self.$rust_field),
-                )*};
-
-                $crate::graphics::util::pod_fields::copy_bytes_to(
-                    &abi,
-                    bytes,
-                );
-            }
+/// Whether two GLSL type names are equal, in a const context, so that the 
+/// `Uniforms` derive can check its `glsl` attributes at compile time. 
+#[doc(hidden)]
+pub const fn glsl_type_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
         }
-    };
+        i += 1;
+    }
+    true
 }
 
 macro_rules! self_uniform_field {
@@ -127,10 +87,12 @@ macro_rules! self_uniform_field {
         impl UniformField for $type {
             const GLSL_TYPE: &'static str = $glsl;
 
-            type Abi = Self;
+            const STD140_ALIGN: usize = std::mem::size_of::<$type>();
+
+            const STD140_SIZE: usize = std::mem::size_of::<$type>();
 
-            fn to_abi(&self) -> Self {
-                *self
+            fn write_std140(&self, bytes: &mut [u8]) {
+                write_pod(bytes, self);
             }
         }
         )*
@@ -144,18 +106,65 @@ self_uniform_field! {
     "double" f64,
 }
 
+impl UniformField for bool {
+    const GLSL_TYPE: &'static str = "bool";
+
+    // GLSL booleans are 32 bits wide
+    const STD140_ALIGN: usize = 4;
+
+    const STD140_SIZE: usize = 4;
+
+    fn write_std140(&self, bytes: &mut [u8]) {
+        write_pod(bytes, &(*self as u32));
+    }
+}
+
+macro_rules! array_uniform_field {
+    ($($n:literal)*)=>{
+        $(
+        impl<T: UniformField> UniformField for [T; $n] {
+            const GLSL_TYPE: &'static str = T::GLSL_TYPE;
+
+            // array elements are aligned to at least a four-component vector
+            const STD140_ALIGN: usize = std140_offset(T::STD140_ALIGN, 16);
+
+            const STD140_SIZE: usize = std140_offset(T::STD140_SIZE, Self::STD140_ALIGN) * $n;
+
+            fn write_std140(&self, bytes: &mut [u8]) {
+                let stride = Self::STD140_SIZE / $n;
+                for (elem, elem_bytes) in self.iter().zip(bytes.chunks_mut(stride)) {
+                    elem.write_std140(&mut elem_bytes[..T::STD140_SIZE]);
+                }
+            }
+        }
+        )*
+    };
+}
+
+array_uniform_field! {
+    1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
 macro_rules! vec_uniform_field {
     ($(
-        ($glsl:literal $($vek:tt)*) -> $array:ty,
+        ($glsl:literal $($vek:tt)*) -> [$scalar:ty; $n:tt],
     )*)=>{
         $(
         impl UniformField for vek::vec::repr_c::$($vek)* {
             const GLSL_TYPE: &'static str = $glsl;
 
-            type Abi = $array;
+            // two-component vectors are aligned to their size, three- and 
+            // four-component vectors are aligned to four components
+            const STD140_ALIGN: usize = std::mem::size_of::<$scalar>() * match $n {
+                2 => 2,
+                _ => 4,
+            };
+
+            const STD140_SIZE: usize = std::mem::size_of::<[$scalar; $n]>();
 
-            fn to_abi(&self) -> $array {
-                self.into_array()
+            fn write_std140(&self, bytes: &mut [u8]) {
+                write_pod(bytes, &self.into_array());
             }
         }
 
@@ -163,10 +172,17 @@ macro_rules! vec_uniform_field {
         impl UniformField for vek::vec::repr_simd::$($vek)* {
             const GLSL_TYPE: &'static str = $glsl;
 
-            type Abi = $array;
+            // two-component vectors are aligned to their size, three- and 
+            // four-component vectors are aligned to four components
+            const STD140_ALIGN: usize = std::mem::size_of::<$scalar>() * match $n {
+                2 => 2,
+                _ => 4,
+            };
 
-            fn to_abi(&self) -> $array {
-                self.into_array()
+            const STD140_SIZE: usize = std::mem::size_of::<[$scalar; $n]>();
+
+            fn write_std140(&self, bytes: &mut [u8]) {
+                write_pod(bytes, &self.into_array());
             }
         }
         )*
@@ -219,28 +235,47 @@ vec_uniform_field! {
     ("dvec3" Extent3<f64>) -> [f64; 3],
 }
 
+/// Copy column-major matrix elements into columns which may be padded. 
+fn pad_columns<T: Copy, C: AsMut<[T]>>(flat: &[T], rows: usize, columns: &mut [C]) {
+    for (i, column) in columns.iter_mut().enumerate() {
+        column.as_mut()[..rows].copy_from_slice(&flat[i * rows..(i + 1) * rows]);
+    }
+}
+
 macro_rules! mat_uniform_field {
     ($(
-        ($glsl:literal $($vek:tt)*) -> $array:ty,
+        ($glsl:literal $($vek:tt)*) -> [[$scalar:ty; $padded:tt]; $cols:tt] with $rows:tt rows,
     )*)=>{
         $(
         impl UniformField for vek::mat::repr_c::column_major::$($vek)* {
             const GLSL_TYPE: &'static str = $glsl;
 
-            type Abi = $array;
+            // each column is padded to the alignment of a four-component 
+            // vector
+            const STD140_ALIGN: usize = std::mem::size_of::<[$scalar; $padded]>();
+
+            const STD140_SIZE: usize = std::mem::size_of::<[[$scalar; $padded]; $cols]>();
 
-            fn to_abi(&self) -> $array {
-                self.into_col_array()
+            fn write_std140(&self, bytes: &mut [u8]) {
+                let mut columns = [[0.0 as $scalar; $padded]; $cols];
+                pad_columns(&self.into_col_array(), $rows, &mut columns);
+                write_pod(bytes, &columns);
             }
         }
 
         impl UniformField for vek::mat::repr_c::row_major::$($vek)* {
             const GLSL_TYPE: &'static str = $glsl;
 
-            type Abi = $array;
+            // each column is padded to the alignment of a four-component 
+            // vector
+            const STD140_ALIGN: usize = std::mem::size_of::<[$scalar; $padded]>();
+
+            const STD140_SIZE: usize = std::mem::size_of::<[[$scalar; $padded]; $cols]>();
 
-            fn to_abi(&self) -> $array {
-                self.into_col_array()
+            fn write_std140(&self, bytes: &mut [u8]) {
+                let mut columns = [[0.0 as $scalar; $padded]; $cols];
+                pad_columns(&self.into_col_array(), $rows, &mut columns);
+                write_pod(bytes, &columns);
             }
         }
 
@@ -248,10 +283,16 @@ macro_rules! mat_uniform_field {
         impl UniformField for vek::mat::repr_simd::column_major::$($vek)* {
             const GLSL_TYPE: &'static str = $glsl;
 
-            type Abi = $array;
+            // each column is padded to the alignment of a four-component 
+            // vector
+            const STD140_ALIGN: usize = std::mem::size_of::<[$scalar; $padded]>();
 
-            fn to_abi(&self) -> $array {
-                self.into_col_array()
+            const STD140_SIZE: usize = std::mem::size_of::<[[$scalar; $padded]; $cols]>();
+
+            fn write_std140(&self, bytes: &mut [u8]) {
+                let mut columns = [[0.0 as $scalar; $padded]; $cols];
+                pad_columns(&self.into_col_array(), $rows, &mut columns);
+                write_pod(bytes, &columns);
             }
         }
 
@@ -259,10 +300,16 @@ macro_rules! mat_uniform_field {
         impl UniformField for vek::mat::repr_simd::row_major::$($vek)* {
             const GLSL_TYPE: &'static str = $glsl;
 
-            type Abi = $array;
+            // each column is padded to the alignment of a four-component 
+            // vector
+            const STD140_ALIGN: usize = std::mem::size_of::<[$scalar; $padded]>();
+
+            const STD140_SIZE: usize = std::mem::size_of::<[[$scalar; $padded]; $cols]>();
 
-            fn to_abi(&self) -> $array {
-                self.into_col_array()
+            fn write_std140(&self, bytes: &mut [u8]) {
+                let mut columns = [[0.0 as $scalar; $padded]; $cols];
+                pad_columns(&self.into_col_array(), $rows, &mut columns);
+                write_pod(bytes, &columns);
             }
         }
         )*
@@ -270,32 +317,267 @@ macro_rules! mat_uniform_field {
 }
 
 mat_uniform_field! {
-    ("mat2" Mat2<f32>) -> [f32; 4],
-    ("mat3" Mat3<f32>) -> [f32; 9],
-    ("mat4" Mat4<f32>) -> [f32; 16],
+    ("mat2" Mat2<f32>) -> [[f32; 4]; 2] with 2 rows,
+    ("mat3" Mat3<f32>) -> [[f32; 4]; 3] with 3 rows,
+    ("mat4" Mat4<f32>) -> [[f32; 4]; 4] with 4 rows,
+
+    ("dmat2" Mat2<f64>) -> [[f64; 2]; 2] with 2 rows,
+    ("dmat3" Mat3<f64>) -> [[f64; 4]; 3] with 3 rows,
+    ("dmat4" Mat4<f64>) -> [[f64; 4]; 4] with 4 rows,
+}
+
+macro_rules! non_square_mat {
+    ($(
+        $(#[$attr:meta])*
+        $mat:ident ($glsl:literal, $dglsl:literal) = [$col:ident; $cols:tt] with $rows:tt rows,
+    )*)=>{
+        $(
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        pub struct $mat<T> {
+            /// Columns, from left to right. 
+            pub cols: [$col<T>; $cols],
+        }
+
+        impl UniformField for $mat<f32> {
+            const GLSL_TYPE: &'static str = $glsl;
+
+            // each column is padded to the alignment of a four-component 
+            // vector
+            const STD140_ALIGN: usize = 16;
+
+            const STD140_SIZE: usize = 16 * $cols;
+
+            fn write_std140(&self, bytes: &mut [u8]) {
+                let mut columns = [[0.0_f32; 4]; $cols];
+                for (column, col) in columns.iter_mut().zip(&self.cols) {
+                    column[..$rows].copy_from_slice(&col.into_array());
+                }
+                write_pod(bytes, &columns);
+            }
+        }
+
+        impl UniformField for $mat<f64> {
+            const GLSL_TYPE: &'static str = $dglsl;
+
+            // two-row columns are aligned like a dvec2, otherwise like a 
+            // dvec4
+            const STD140_ALIGN: usize = match $rows {
+                2 => 16,
+                _ => 32,
+            };
+
+            const STD140_SIZE: usize = Self::STD140_ALIGN * $cols;
+
+            fn write_std140(&self, bytes: &mut [u8]) {
+                let stride = Self::STD140_ALIGN;
+                for (col, col_bytes) in self.cols.iter().zip(bytes.chunks_mut(stride)) {
+                    write_pod(&mut col_bytes[..8 * $rows], &col.into_array());
+                }
+            }
+        }
+        )*
+    };
+}
+
+non_square_mat! {
+    /// Column-major matrix with 2 columns and 3 rows, for uniforms. 
+    Mat2x3 ("mat2x3", "dmat2x3") = [Vec3; 2] with 3 rows,
+    /// Column-major matrix with 2 columns and 4 rows, for uniforms. 
+    Mat2x4 ("mat2x4", "dmat2x4") = [Vec4; 2] with 4 rows,
+    /// Column-major matrix with 3 columns and 2 rows, for uniforms. 
+    Mat3x2 ("mat3x2", "dmat3x2") = [Vec2; 3] with 2 rows,
+    /// Column-major matrix with 3 columns and 4 rows, for uniforms. 
+    Mat3x4 ("mat3x4", "dmat3x4") = [Vec4; 3] with 4 rows,
+    /// Column-major matrix with 4 columns and 2 rows, for uniforms. 
+    Mat4x2 ("mat4x2", "dmat4x2") = [Vec2; 4] with 2 rows,
+    /// Column-major matrix with 4 columns and 3 rows, for uniforms. 
+    ///
+    /// This can compactly store an affine transform. 
+    Mat4x3 ("mat4x3", "dmat4x3") = [Vec3; 4] with 3 rows,
+}
 
-    ("dmat2" Mat2<f64>) -> [f64; 4],
-    ("dmat3" Mat3<f64>) -> [f64; 9],
-    ("dmat4" Mat4<f64>) -> [f64; 16],
+impl<T: Copy> From<Mat4<T>> for Mat4x3<T> {
+    /// Drop the bottom row of a 4x4 matrix, which is `0 0 0 1` for an affine 
+    /// transform. 
+    fn from(mat: Mat4<T>) -> Self {
+        let cols = mat.into_col_arrays();
+        Mat4x3 {
+            cols: [
+                Vec3::new(cols[0][0], cols[0][1], cols[0][2]),
+                Vec3::new(cols[1][0], cols[1][1], cols[1][2]),
+                Vec3::new(cols[2][0], cols[2][1], cols[2][2]),
+                Vec3::new(cols[3][0], cols[3][1], cols[3][2]),
+            ],
+        }
+    }
+}
+
+impl<T: Copy> From<Mat4<T>> for Mat3x4<T> {
+    /// Take the top three rows of a 4x4 matrix, as the columns of a 3x4 
+    /// matrix. This is the transpose of an affine transform, which a shader 
+    /// can apply with `vec4(pos, 1.0) * mat`. 
+    fn from(mat: Mat4<T>) -> Self {
+        let rows = mat.into_row_arrays();
+        Mat3x4 {
+            cols: [
+                Vec4::from(rows[0]),
+                Vec4::from(rows[1]),
+                Vec4::from(rows[2]),
+            ],
+        }
+    }
 }
 
 #[test]
-fn macro_test() {
-    use vek::*;
+fn derive_test() {
+    use game_derive::Uniforms;
 
+    #[derive(Uniforms)]
+    #[attr(size = 128)]
     struct Example {
+        #[attr(glsl = "int")]
         a: i32,
+        #[attr(glsl = "mat3")]
         b: Mat3<f32>,
+        #[attr(glsl = "uvec2")]
         c: Vec2<u32>,
+        #[attr(glsl = "dvec4")]
         d: Rgba<f64>,
     }
 
-    uniforms! {
-        Example {
-            int a: i32,
-            mat3 b: Mat3<f32>,
-            uvec2 c: Vec2<u32>,
-            dvec4 d: Rgba<f64>,
+    let example = Example {
+        a: 7,
+        b: Mat3::identity(),
+        c: Vec2::new(1, 2),
+        d: Rgba::new(1.0, 2.0, 3.0, 4.0),
+    };
+    let bytes = example.encode();
+    let word = |i: usize| u32::from_ne_bytes([
+        bytes[i * 4],
+        bytes[i * 4 + 1],
+        bytes[i * 4 + 2],
+        bytes[i * 4 + 3],
+    ]);
+
+    // int at 0, followed by padding
+    assert_eq!(word(0), 7);
+    assert_eq!(&bytes[4..16], &[0; 12]);
+    // mat3 at 16, with each column padded to 16 bytes
+    for col in 0..3 {
+        for row in 0..4 {
+            let expected = if row == col { 1.0_f32.to_bits() } else { 0 };
+            assert_eq!(word(4 + col * 4 + row), expected);
         }
     }
-}
\ No newline at end of file
+    // uvec2 at 64
+    assert_eq!((word(16), word(17)), (1, 2));
+    // dvec4 at 96, aligned to 32
+    assert_eq!(&bytes[72..96], &[0; 24]);
+    assert_eq!(&bytes[96..104], &1.0_f64.to_ne_bytes());
+    assert_eq!(&bytes[120..128], &4.0_f64.to_ne_bytes());
+}
+
+#[test]
+fn array_bool_test() {
+    use game_derive::Uniforms;
+
+    #[derive(Uniforms)]
+    #[attr(size = 132)]
+    struct Lights {
+        #[attr(glsl = "uint")]
+        count: u32,
+        #[attr(glsl = "vec4")]
+        colors: [Vec4<f32>; 2],
+        #[attr(glsl = "float")]
+        intensities: [f32; 3],
+        #[attr(glsl = "bool")]
+        flags: [bool; 2],
+        #[attr(glsl = "bool")]
+        enabled: bool,
+    }
+
+    let lights = Lights {
+        count: 2,
+        colors: [Vec4::new(1.0, 2.0, 3.0, 4.0), Vec4::new(5.0, 6.0, 7.0, 8.0)],
+        intensities: [0.5, 1.5, 2.5],
+        flags: [true, false],
+        enabled: true,
+    };
+    let bytes = lights.encode();
+    let word = |i: usize| u32::from_ne_bytes([
+        bytes[i * 4],
+        bytes[i * 4 + 1],
+        bytes[i * 4 + 2],
+        bytes[i * 4 + 3],
+    ]);
+
+    assert_eq!(word(0), 2);
+    // vec4 array at 16, with a stride of 16
+    assert_eq!(word(4), 1.0_f32.to_bits());
+    assert_eq!(word(11), 8.0_f32.to_bits());
+    // float array at 48, with a stride of 16
+    assert_eq!(word(12), 0.5_f32.to_bits());
+    assert_eq!(&bytes[52..64], &[0; 12]);
+    assert_eq!(word(16), 1.5_f32.to_bits());
+    assert_eq!(word(20), 2.5_f32.to_bits());
+    // bool array at 96, with a stride of 16
+    assert_eq!(word(24), 1);
+    assert_eq!(word(28), 0);
+    // bool at 128, right after the array
+    assert_eq!(word(32), 1);
+}
+
+#[test]
+fn std140_offset_test() {
+    assert_eq!(std140_offset(0, 16), 0);
+    assert_eq!(std140_offset(4, 16), 16);
+    // a float may be packed into the end of a vec3
+    assert_eq!(std140_offset(12, 4), 12);
+    assert_eq!(std140_offset(12, 8), 16);
+}
+
+#[test]
+fn non_square_mat_test() {
+    use game_derive::Uniforms;
+
+    #[derive(Uniforms)]
+    #[attr(size = 160)]
+    struct Example {
+        #[attr(glsl = "mat2x3")]
+        a: Mat2x3<f32>,
+        #[attr(glsl = "mat4x3")]
+        b: Mat4x3<f32>,
+        #[attr(glsl = "float")]
+        c: f32,
+        #[attr(glsl = "dmat3x2")]
+        d: Mat3x2<f64>,
+    }
+
+    let translation = Mat4::<f32>::translation_3d(Vec3::new(5.0, 6.0, 7.0));
+    let example = Example {
+        a: Mat2x3 { cols: [Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0)] },
+        b: translation.into(),
+        c: 9.0,
+        d: Mat3x2 { cols: [Vec2::new(1.0, 2.0); 3] },
+    };
+    let bytes = example.encode();
+    let word = |i: usize| f32::from_bits(u32::from_ne_bytes([
+        bytes[i * 4],
+        bytes[i * 4 + 1],
+        bytes[i * 4 + 2],
+        bytes[i * 4 + 3],
+    ]));
+
+    // mat2x3 at 0, with columns padded to 16
+    assert_eq!([word(0), word(1), word(2), word(3)], [1.0, 2.0, 3.0, 0.0]);
+    assert_eq!([word(4), word(5), word(6), word(7)], [4.0, 5.0, 6.0, 0.0]);
+    // mat4x3 at 32, with the translation in the last column
+    assert_eq!([word(20), word(21), word(22), word(23)], [5.0, 6.0, 7.0, 0.0]);
+    // float at 96
+    assert_eq!(word(24), 9.0);
+    // dmat3x2 at 112, with columns 16 bytes apart
+    assert_eq!(&bytes[112..120], &1.0_f64.to_ne_bytes());
+    assert_eq!(&bytes[144..152], &1.0_f64.to_ne_bytes());
+    assert_eq!(&bytes[152..160], &2.0_f64.to_ne_bytes());
+}
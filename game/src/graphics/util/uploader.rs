@@ -0,0 +1,84 @@
+//! Staging belt upload path.
+
+use std::mem::size_of;
+use bytemuck::{self, Pod};
+use futures::{
+    executor::LocalPool,
+    task::LocalSpawnExt,
+};
+use wgpu::{
+    *,
+    util::StagingBelt,
+};
+
+/// Size of each staging buffer chunk, in bytes.
+const UPLOADER_CHUNK_SIZE: BufferAddress = 1 << 20;
+
+/// Shared uploader for writing data into GPU buffers.
+///
+/// Rather than creating a fresh `COPY_SRC` buffer for each upload, this
+/// sub-allocates from a ring of mapped staging buffers, which are recycled
+/// once the GPU has finished copying from them.
+///
+/// Each frame, uploads are recorded with `upload`, then `finish` must be
+/// called before the command encoder is submitted, and `recall` after.
+pub struct Uploader {
+    belt: StagingBelt,
+    // drives the belt's recall futures
+    pool: LocalPool,
+}
+
+impl Uploader {
+    /// Create a new `Uploader` with no staging buffers allocated yet.
+    pub fn new() -> Self {
+        Uploader {
+            belt: StagingBelt::new(UPLOADER_CHUNK_SIZE),
+            pool: LocalPool::new(),
+        }
+    }
+
+    /// Record a copy of `data` into `target`, at the byte offset `offset`.
+    ///
+    /// The offset and data size must be multiples of `COPY_BUFFER_ALIGNMENT`.
+    pub fn upload<T: Pod>(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        target: &Buffer,
+        offset: BufferAddress,
+        data: &[T],
+    ) {
+        let size = match BufferSize::new((data.len() * size_of::<T>()) as u64) {
+            Some(size) => size,
+            None => return,
+        };
+        debug_assert_eq!(offset % COPY_BUFFER_ALIGNMENT, 0, "misaligned upload offset");
+        debug_assert_eq!(size.get() % COPY_BUFFER_ALIGNMENT, 0, "misaligned upload size");
+
+        self.belt
+            .write_buffer(command_encoder, target, offset, size, device)
+            .copy_from_slice(bytemuck::cast_slice(data));
+    }
+
+    /// Close the staging buffers used this frame, so that they may be read
+    /// by the GPU.
+    ///
+    /// Must be called before submitting the command encoders used with
+    /// `upload`.
+    pub fn finish(&mut self) {
+        self.belt.finish();
+    }
+
+    /// Recycle staging buffers which the GPU has finished with.
+    ///
+    /// Must be called after submitting the command encoders used with
+    /// `upload`.
+    pub fn recall(&mut self, device: &Device) {
+        let recall = self.belt.recall();
+        self.pool.spawner()
+            .spawn_local(recall)
+            .expect("failed to spawn staging belt recall");
+        device.poll(Maintain::Poll);
+        self.pool.run_until_stalled();
+    }
+}
@@ -35,116 +35,6 @@ pub trait IntoVertexAttrib {
     fn into_vertex_attrib(self) -> Self::Into;
 }
 
-/// Implement `GenericVertex` on a type with GLSL-like syntax. 
-///
-/// ```
-/// use vek::*;
-/// 
-/// struct Example {
-///     pos: Vec3<f32>,
-///     tex_coord: Vec2<f32>,
-///     tex_index: u32,
-/// }
-/// 
-/// vertex! {
-///     Example {
-///         layout(location = 0) in vec3 pos: Vec3<f32>,
-///         layout(location = 1) in vec2 tex_coord: Vec2<f32>,
-///         layout(location = 2) in uint tex_index: u32,
-///     }
-/// }
-/// ```
-macro_rules! vertex {
-    (
-    $vertex:ident {$(
-        layout(location = $location:expr) in $glsl_type:ident $rust_field:ident : $rust_type:ty,
-    )*}
-    )=>{
-        impl $vertex {
-            /// Write `self`'s binary representation to a fixed-size byte array. 
-            pub fn encode(&self) -> [u8; <Self as $crate::graphics::util::vertex::GenericVertex>::SIZE] {
-                let mut array = [0_u8; <Self as $crate::graphics::util::vertex::GenericVertex>::SIZE];
-                <Self as $crate::graphics::util::vertex::GenericVertex>::encode_to(self, &mut array);
-                array
-            }
-        }
-
-        impl $crate::graphics::util::vertex::GenericVertex for $vertex {
-            const SIZE: usize = {
-                0 $( + 
-                    std::mem::size_of::<
-                        <
-                            $rust_type 
-                            as 
-                            $crate::graphics::util::vertex::IntoVertexAttrib
-                        >::Into
-                    >()
-                )*
-            };
-
-            fn attributes() -> std::borrow::Cow<'static, [$crate::wgpu::VertexAttributeDescriptor]> {
-                use $crate::wgpu::{
-                    VertexAttributeDescriptor,
-                    VertexFormat,
-                };
-
-                let mut vec: Vec<VertexAttributeDescriptor> = Vec::new();
-                let mut curr_offset: u64 = 0;
-
-                $({
-                    let attr_size = std::mem::size_of::<
-                        <
-                            $rust_type 
-                            as 
-                            $crate::graphics::util::vertex::IntoVertexAttrib
-                        >::Into
-                    >() as u64;
-                    let attr_format = $crate::graphics::util::vertex::deduce_vertex_format::<
-                            <
-                                $rust_type 
-                                as 
-                                $crate::graphics::util::vertex::IntoVertexAttrib
-                            >::Into
-                        >(stringify!($glsl_type))
-                        .unwrap_or_else(|e| {
-                            error!("{}", e);
-                            panic!();
-                        });
-
-                    vec.push(VertexAttributeDescriptor {
-                        offset: curr_offset,
-                        format: attr_format,
-                        shader_location: $location,
-                    });
-                    curr_offset += attr_size;
-                })*
-
-                debug_assert_eq!(curr_offset, Self::SIZE as u64);
-                std::borrow::Cow::Owned(vec)
-            }
-
-            fn encode_to(&self, mut bytes: &mut [u8]) {
-                assert_eq!(bytes.len(), Self::SIZE, "wrong size");
-
-                $({
-                    let attr = <
-                            $rust_type 
-                            as 
-                            $crate::graphics::util::vertex::IntoVertexAttrib
-                        >::into_vertex_attrib(self.$rust_field.clone());
-                    let attr_bytes = $crate::bytemuck::bytes_of(&attr);
-                    for (i, b) in attr_bytes.iter().copied().enumerate() {
-                        bytes[i] = b;
-                    }
-                    bytes = &mut bytes[attr_bytes.len()..];
-                })*
-
-                debug_assert!(bytes.is_empty());
-            }
-        }
-    };
-}
-
 /// Determine a vertex attribute format from a rust type and a GLSL type.
 pub fn deduce_vertex_format<A: VertexAttrib>(glsl_type: &str) -> Result<VertexFormat>
 {
@@ -322,20 +212,36 @@ vek_into_vertex_attrib! {
 }
 
 #[test]
-fn macro_test() {
+fn derive_test() {
     use vek::*;
-    
+    use game_derive::Vertex;
+
+    #[derive(Vertex)]
     struct Example {
+        #[attr(location = 0, glsl = "vec3")]
         pos: Vec3<f32>,
-        tex_coord: Vec2<f32>,
+        #[attr(location = 1, glsl = "vec4")]
+        color: Rgba<u8>,
+        #[attr(location = 2, glsl = "uint")]
         tex_index: u32,
     }
-    
-    vertex! {
-        Example {
-            layout(location = 0) in vec3 pos: Vec3<f32>,
-            layout(location = 1) in vec2 tex_coord: Vec2<f32>,
-            layout(location = 2) in uint tex_index: u32,
-        }
-    }
-}
\ No newline at end of file
+
+    let example = Example {
+        pos: Vec3::new(1.0, 2.0, 3.0),
+        color: Rgba::new(4, 5, 6, 7),
+        tex_index: 8,
+    };
+    let bytes = example.encode();
+    assert_eq!(Example::SIZE, 20);
+    assert_eq!(&bytes[0..12], bytes_of(&[1.0_f32, 2.0, 3.0]));
+    assert_eq!(&bytes[12..16], &[4, 5, 6, 7]);
+    assert_eq!(&bytes[16..20], &8_u32.to_ne_bytes());
+
+    let attributes = Example::attributes();
+    let offsets = attributes.iter()
+        .map(|attr| (attr.shader_location, attr.offset))
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, vec![(0, 0), (1, 12), (2, 16)]);
+    assert_eq!(attributes[0].format, VertexFormat::Float3);
+    assert_eq!(attributes[2].format, VertexFormat::Uint);
+}
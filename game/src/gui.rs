@@ -0,0 +1,301 @@
+//! Developer GUI, using egui, with an inspector panel for the camera, chunk
+//! statistics, and settings. 
+
+use crate::{
+    graphics::{Graphics, MeshStats},
+    input::{InputManager, InputEvent, MouseButton, CursorIcon},
+    config::Settings,
+    util::camera::YawPitch,
+};
+use std::{
+    ops::RangeInclusive,
+    time::Instant,
+};
+use vek::*;
+use floatilla::bounded::{Bounded, Bounds};
+
+/// egui context, fed from an `InputManager`, and drawn by `Graphics`. 
+pub struct Gui {
+    ctx: egui::CtxRef,
+    start: Instant,
+    // whether anything was drawn last frame
+    shown: bool,
+    // whether the current frame receives input
+    interactive: bool,
+    // whether text input was begun for a text field
+    text_input: bool,
+}
+
+impl Gui {
+    /// Construct, with nothing shown yet. 
+    pub fn new() -> Self {
+        Gui {
+            ctx: egui::CtxRef::default(),
+            start: Instant::now(),
+            shown: false,
+            interactive: false,
+            text_input: false,
+        }
+    }
+
+    /// Begin a frame with the input since the last frame, and return the
+    /// context to build the frame's GUI in. 
+    ///
+    /// If not `interactive`, the GUI is only drawn, and the mouse and
+    /// keyboard are left alone, such as for overlays shown while playing. 
+    pub fn begin_frame(
+        &mut self,
+        input: &InputManager,
+        window_size: Vec2<u32>,
+        interactive: bool,
+    ) -> &egui::CtxRef {
+        self.interactive = interactive;
+        let pixels_per_point = input.scale_factor() as f32;
+        let to_points = |pixels: Vec2<f64>| egui::pos2(
+            pixels.x as f32 / pixels_per_point,
+            pixels.y as f32 / pixels_per_point,
+        );
+        let pos = to_points(input.cursor_position());
+
+        let mut events = Vec::new();
+        let input_events = match interactive {
+            true => input.events(),
+            false => &[],
+        };
+        if interactive {
+            events.push(egui::Event::PointerMoved(pos));
+        }
+        for &(event, _) in input_events {
+            let (button, pressed) = match event {
+                InputEvent::Click(button) => (button, true),
+                InputEvent::Unclick(button) => (button, false),
+                _ => continue,
+            };
+            let button = match button {
+                MouseButton::Left => egui::PointerButton::Primary,
+                MouseButton::Right => egui::PointerButton::Secondary,
+                MouseButton::Middle => egui::PointerButton::Middle,
+                MouseButton::Other(_) => continue,
+            };
+            events.push(egui::Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                modifiers: Default::default(),
+            });
+        }
+        if interactive && !input.text_input().is_empty() {
+            events.push(egui::Event::Text(input.text_input().to_owned()));
+        }
+
+        // scrolling is in lines, which are about this many points
+        let scroll = match interactive {
+            true => input.mouse_scroll() * 24.0,
+            false => Vec2::zero(),
+        };
+        self.ctx.begin_frame(egui::RawInput {
+            scroll_delta: egui::vec2(scroll.x as f32, scroll.y as f32),
+            screen_rect: Some(egui::Rect::from_min_max(
+                egui::Pos2::ZERO,
+                to_points(window_size.map(|n| n as f64)),
+            )),
+            pixels_per_point: Some(pixels_per_point),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            events,
+            ..Default::default()
+        });
+        &self.ctx
+    }
+
+    /// End the frame begun by `begin_frame`, and give it to `graphics` to
+    /// draw. 
+    ///
+    /// If interactive, text input is activated while a text field has
+    /// focus, and the cursor icon follows what's under it. 
+    pub fn end_frame(&mut self, graphics: &mut Graphics, input: &mut InputManager) {
+        let (output, shapes) = self.ctx.end_frame();
+        let meshes = self.ctx.tessellate(shapes);
+        self.shown = !meshes.is_empty();
+        graphics.set_gui(meshes, self.ctx.texture(), self.ctx.pixels_per_point());
+
+        if !self.interactive {
+            self.release_input(input);
+            return;
+        }
+        let wants_text = self.ctx.wants_keyboard_input();
+        if wants_text != self.text_input {
+            self.text_input = wants_text;
+            match wants_text {
+                true => input.begin_text_input(),
+                false => input.end_text_input(),
+            }
+        }
+        input.set_cursor_icon(cursor_icon(output.cursor_icon));
+    }
+
+    /// Stop drawing the GUI, until the next frame. 
+    pub fn hide(&mut self, graphics: &mut Graphics, input: &mut InputManager) {
+        if self.shown {
+            self.shown = false;
+            graphics.set_gui(Vec::new(), self.ctx.texture(), self.ctx.pixels_per_point());
+        }
+        self.release_input(input);
+    }
+
+    // undo changes to the input manager made while interactive
+    fn release_input(&mut self, input: &mut InputManager) {
+        if self.text_input {
+            self.text_input = false;
+            input.end_text_input();
+        }
+        if input.cursor_icon() != CursorIcon::Default {
+            input.set_cursor_icon(CursorIcon::Default);
+        }
+    }
+}
+
+fn cursor_icon(icon: egui::CursorIcon) -> CursorIcon {
+    match icon {
+        egui::CursorIcon::PointingHand => CursorIcon::Hand,
+        egui::CursorIcon::Text => CursorIcon::Text,
+        egui::CursorIcon::ResizeHorizontal => CursorIcon::EwResize,
+        egui::CursorIcon::ResizeVertical => CursorIcon::NsResize,
+        egui::CursorIcon::ResizeNeSw => CursorIcon::NeswResize,
+        egui::CursorIcon::ResizeNwSe => CursorIcon::NwseResize,
+        egui::CursorIcon::Grab => CursorIcon::Grab,
+        egui::CursorIcon::Grabbing => CursorIcon::Grabbing,
+        egui::CursorIcon::NotAllowed | egui::CursorIcon::NoDrop => CursorIcon::NotAllowed,
+        _ => CursorIcon::Default,
+    }
+}
+
+/// Read-only state shown in the inspector. 
+#[derive(Copy, Clone, Debug)]
+pub struct InspectorStats {
+    pub cam_pos: Vec3<f32>,
+    pub cam_dir: YawPitch<f32>,
+    /// Whether the player is walking, rather than flying. 
+    pub walking: bool,
+    /// Number of chunks in the world which contain any blocks. 
+    pub chunks: usize,
+    /// Number of chunks modified since they were last saved. 
+    pub dirty_chunks: usize,
+    pub block_mesh: MeshStats,
+    pub draw_calls: usize,
+}
+
+/// Show the inspector window, with sliders which edit the settings in place. 
+///
+/// Returns whether any settings were changed, in which case they should be
+/// applied and saved. 
+pub fn inspector(ctx: &egui::CtxRef, stats: &InspectorStats, settings: &mut Settings) -> bool {
+    let mut changed = false;
+    egui::Window::new("inspector").show(ctx, |ui| {
+        egui::CollapsingHeader::new("camera")
+            .default_open(true)
+            .show(ui, |ui| {
+                let pos = stats.cam_pos;
+                ui.label(format!("position: {:.2}, {:.2}, {:.2}", pos.x, pos.y, pos.z));
+                ui.label(format!(
+                    "yaw: {:.1}°, pitch: {:.1}°",
+                    stats.cam_dir.yaw.deg(), stats.cam_dir.pitch.deg(),
+                ));
+                ui.label(if stats.walking { "walking" } else { "flying" });
+            });
+        egui::CollapsingHeader::new("chunks")
+            .default_open(true)
+            .show(ui, |ui| {
+                let mesh = stats.block_mesh;
+                ui.label(format!("chunks: {} ({} unsaved)", stats.chunks, stats.dirty_chunks));
+                ui.label(format!(
+                    "block mesh: {} quads, {} holes",
+                    mesh.differ.num_primitives, mesh.differ.num_holes,
+                ));
+                ui.label(format!(
+                    "block buffer: {} / {} KiB, {} reallocations",
+                    mesh.buffer.len_bytes / 1024,
+                    mesh.buffer.capacity_bytes / 1024,
+                    mesh.buffer.num_reallocs,
+                ));
+                ui.label(format!("draw calls: {}", stats.draw_calls));
+            });
+        egui::CollapsingHeader::new("settings")
+            .default_open(true)
+            .show(ui, |ui| {
+                let graphics = &mut settings.graphics;
+                changed |= bounded_slider(ui, &mut graphics.fov, "fov");
+                changed |= bounded_slider(ui, &mut graphics.gamma, "gamma");
+                changed |= optional_slider(
+                    ui, &mut graphics.render_distance, 1..=32, 8, "render distance",
+                );
+                changed |= optional_slider(ui, &mut graphics.fps_cap, 10..=360, 60, "fps cap");
+                changed |= ui
+                    .checkbox(&mut graphics.show_perf_overlay, "performance overlay")
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut graphics.vsync, "vsync (applies on restart)")
+                    .changed();
+
+                let controls = &mut settings.controls;
+                changed |= bounded_slider(ui, &mut controls.mouse_sensitivity, "mouse sensitivity");
+            });
+    });
+    changed
+}
+
+/// Show a slider over a bounded setting's range. Returns whether it was
+/// changed. 
+fn bounded_slider<B: Bounds<f32>>(
+    ui: &mut egui::Ui,
+    value: &mut Bounded<f32, B>,
+    text: &str,
+) -> bool {
+    let mut f = value.get();
+    let changed = ui.add(egui::Slider::new(&mut f, B::MIN..=B::MAX).text(text)).changed();
+    *value = Bounded::clamped(f);
+    changed
+}
+
+/// Show a checkbox for whether a setting is limited, and a slider for its
+/// limit if so. Returns whether it was changed. 
+fn optional_slider(
+    ui: &mut egui::Ui,
+    value: &mut Option<u32>,
+    range: RangeInclusive<u32>,
+    default: u32,
+    text: &str,
+) -> bool {
+    let mut limited = value.is_some();
+    let mut limit = value.unwrap_or(default);
+    let mut changed = ui.checkbox(&mut limited, format!("limit {}", text)).changed();
+    if limited {
+        changed |= ui.add(egui::Slider::new(&mut limit, range).text(text)).changed();
+    }
+    *value = if limited { Some(limit) } else { None };
+    changed
+}
+
+#[test]
+fn inspector_test() {
+    let stats = InspectorStats {
+        cam_pos: Vec3::new(1.0, 2.0, 3.0),
+        cam_dir: YawPitch {
+            yaw: crate::util::angle::deg(90.0).into(),
+            pitch: crate::util::angle::deg(0.0),
+        },
+        walking: false,
+        chunks: 10,
+        dirty_chunks: 1,
+        block_mesh: MeshStats::default(),
+        draw_calls: 4,
+    };
+    let mut settings = Settings::default();
+    let mut ctx = egui::CtxRef::default();
+    for _ in 0..2 {
+        ctx.begin_frame(egui::RawInput::default());
+        assert!(!inspector(&ctx, &stats, &mut settings));
+        let (_, shapes) = ctx.end_frame();
+        assert!(!ctx.tessellate(shapes).is_empty());
+    }
+    assert_eq!(settings, Settings::default());
+}
@@ -8,15 +8,23 @@ use std::{
     },
     sync::Arc,
     borrow::Borrow,
+    time::{
+        Duration,
+        Instant,
+    },
     convert::identity,
 };
 use winit::{
-    window::Window,
+    window::{
+        Window,
+        WindowId,
+    },
     event::{
         WindowEvent,
         DeviceEvent,
         KeyboardInput,
         MouseScrollDelta,
+        ModifiersState,
         ElementState,
     },
     dpi::{
@@ -30,19 +38,81 @@ use smallvec::SmallVec;
 /// Factory pattern for an `InputManager`. Used to bind keys. 
 #[derive(Clone)]
 pub struct InputManagerBuilder {
-    bindings: Vec<Button>,
+    bindings: Vec<Chord>,
+    axes: Vec<Axis>,
+    double_click_threshold: Duration,
+    hold_threshold: Duration,
+    drag_threshold: f64,
 }
 
+/// Default maximum time between clicks for them to form a double-click. 
+pub const DEFAULT_DOUBLE_CLICK_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Default time a binded key must be pressed for it to be held. 
+pub const DEFAULT_HOLD_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Default distance, in physical pixels, the cursor must move while a mouse 
+/// button is pressed for it to begin a drag. 
+pub const DEFAULT_DRAG_THRESHOLD: f64 = 4.0;
+
 impl InputManagerBuilder {
     /// Start building an `InputManager`. 
     pub fn new() -> Self {
         InputManagerBuilder {
-            bindings: Vec::new()
+            bindings: Vec::new(),
+            axes: Vec::new(),
+            double_click_threshold: DEFAULT_DOUBLE_CLICK_THRESHOLD,
+            hold_threshold: DEFAULT_HOLD_THRESHOLD,
+            drag_threshold: DEFAULT_DRAG_THRESHOLD,
         }
     }
 
-    /// Create a binding to a button. 
-    pub fn bind<B: Into<Button>>(&mut self, bind_to: B) -> KeyBind {
+    /// Create an axis binding, which is -1 while the negative button is 
+    /// pressed, 1 while the positive button is pressed, and 0 while neither or 
+    /// both are. 
+    pub fn bind_axis<N, P>(
+        &mut self, 
+        name: &'static str, 
+        negative: N, 
+        positive: P,
+    ) -> AxisBind
+    where
+        N: Into<Button>,
+        P: Into<Button>,
+    {
+        let axis_int = self.axes.len();
+        assert!(axis_int < u16::MAX as usize, "too many axis bindings");
+        let negative = self.bind(negative.into());
+        let positive = self.bind(positive.into());
+        self.axes.push(Axis {
+            name,
+            negative,
+            positive,
+        });
+        AxisBind(axis_int as u16)
+    }
+
+    /// Set the maximum time between two clicks of a mouse button for them to 
+    /// produce an `InputEvent::DoubleClick`. 
+    pub fn double_click_threshold(&mut self, threshold: Duration) {
+        self.double_click_threshold = threshold;
+    }
+
+    /// Set how long a binded key must be pressed for it to produce an 
+    /// `InputEvent::Hold`. 
+    pub fn hold_threshold(&mut self, threshold: Duration) {
+        self.hold_threshold = threshold;
+    }
+
+    /// Set how far, in physical pixels, the cursor must move while a mouse 
+    /// button is pressed for it to produce an `InputEvent::DragStart`. 
+    pub fn drag_threshold(&mut self, threshold: f64) {
+        self.drag_threshold = threshold;
+    }
+
+    /// Create a binding to a button, or to a `Chord` of modifiers and a 
+    /// button. 
+    pub fn bind<C: Into<Chord>>(&mut self, bind_to: C) -> KeyBind {
         let bind_int = self.bindings.len();
         assert!(bind_int < u16::MAX as usize, "too many key bindings");
         self.bindings.push(bind_to.into());
@@ -53,29 +123,49 @@ impl InputManagerBuilder {
     pub fn build(self, window: Arc<Window>) -> InputManager {
         let mut bindings = HashMap::new();
         let mut bindings_rev = HashMap::new();
-        for (bind_int, button) in self.bindings.iter().copied().enumerate() {
+        for (bind_int, chord) in self.bindings.iter().copied().enumerate() {
             let bind_int = bind_int as u16;
 
             bindings
-                .entry(button)
+                .entry(chord.button)
                 .or_insert_with(Vec::new)
                 .push(KeyBind(bind_int));
-            bindings_rev.insert(KeyBind(bind_int), button);
+            bindings_rev.insert(KeyBind(bind_int), chord);
         }
 
         InputManager {
             state: WindowState::Unfocused,
             bindings,
             bindings_rev,
+            axes: self.axes,
             pressed: HashSet::new(),
+            pressed_binds: HashSet::new(),
+            modifiers: Modifiers::empty(),
+            double_click_threshold: self.double_click_threshold,
+            hold_threshold: self.hold_threshold,
+            drag_threshold: self.drag_threshold,
+            drags: HashMap::new(),
+            cursor_position: [0.0; 2].into(),
+            last_click: HashMap::new(),
+            press_times: HashMap::new(),
             window_size: window.inner_size(),
             window_closing: false,
+            cursor_icon: CursorIcon::Default,
+            cursor_visible: true,
+            cursor_confined: false,
             window_scale_factor: window.scale_factor() as f64,
             cursor_location: [0.0; 2].into(),
+            frame: 0,
 
             events: Vec::new(),
             mouse_captured_movement: [0.0; 2].into(),
             mouse_scroll: [0.0; 2].into(), 
+            fullscreen_toggled: false,
+            text_input: String::new(),
+            text_submitted: None,
+
+            text_input_active: false,
+            composition: String::new(),
 
             window,
         }
@@ -91,6 +181,12 @@ pub type Key = winit::event::VirtualKeyCode;
 /// A button on a mouse. 
 pub type MouseButton = winit::event::MouseButton;
 
+/// An icon the cursor may display. 
+pub type CursorIcon = winit::window::CursorIcon;
+
+/// Set of modifier keys which are held down. 
+pub type Modifiers = ModifiersState;
+
 /// A hardware button which can be pressed and released. 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Button {
@@ -110,16 +206,77 @@ impl From<MouseButton> for Button {
     }
 }
 
-/// A virtual "game" key, which may be bound to any `Button`. 
+/// A button pressed while holding a set of modifier keys, such as `Ctrl+S`. 
+///
+/// A chord with no modifiers matches regardless of which modifiers are held, 
+/// so that plain bindings keep working while, say, sprinting with Shift. 
+/// Otherwise, the held modifiers must match exactly, so that `Ctrl+S` does 
+/// not fire for `Ctrl+Shift+S`. 
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub button: Button,
+}
+
+impl Chord {
+    /// Construct a chord from modifiers and a button. 
+    pub fn new<B: Into<Button>>(modifiers: Modifiers, button: B) -> Self {
+        Chord {
+            modifiers,
+            button: button.into(),
+        }
+    }
+
+    /// Whether pressing this chord's button while holding the given 
+    /// modifiers should activate this chord. 
+    pub fn matches(&self, held: Modifiers) -> bool {
+        self.modifiers.is_empty() || self.modifiers == held
+    }
+}
+
+impl From<Button> for Chord {
+    fn from(button: Button) -> Chord {
+        Chord::new(Modifiers::empty(), button)
+    }
+}
+
+impl From<Key> for Chord {
+    fn from(key: Key) -> Chord {
+        Chord::new(Modifiers::empty(), key)
+    }
+}
+
+impl From<MouseButton> for Chord {
+    fn from(button: MouseButton) -> Chord {
+        Chord::new(Modifiers::empty(), button)
+    }
+}
+
+/// A virtual "game" key, which may be bound to any `Chord`. 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct KeyBind(u16);
 
+/// A virtual "game" axis, which may be bound to a pair of `Button`s. 
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct AxisBind(u16);
+
+/// An axis binding, as a pair of key bindings. 
+#[derive(Copy, Clone, Debug)]
+struct Axis {
+    name: &'static str,
+    negative: KeyBind,
+    positive: KeyBind,
+}
+
 /// Input state at a moment of time. 
 #[derive(Clone, Debug)]
 pub struct InputSnapshot {
     state: WindowState,
     cursor_location: Vec2<f64>,
+    modifiers: Modifiers,
     pressed: SmallVec<[KeyBind; 10]>,
+    time: Instant,
+    frame: u64,
 }
 
 impl InputSnapshot {
@@ -136,6 +293,27 @@ impl InputSnapshot {
         self.pressed.contains(&bind)
     }
 
+    /// Return the modifier keys held at this snapshot. 
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Return the time this snapshot was taken. 
+    ///
+    /// For an event's snapshot, this is when the `InputManager` processed the 
+    /// event, which may be somewhat after the event occured. 
+    pub fn time(&self) -> Instant {
+        self.time
+    }
+
+    /// Return the index of the frame this snapshot was taken in. 
+    ///
+    /// This is the number of times `InputManager::update` was called before 
+    /// the snapshot was taken, and so increases monotonically. 
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
     /// Return the window's `WindowState` at this snapshot.
     pub fn state(&self) -> WindowState {
         self.state
@@ -146,8 +324,14 @@ impl InputSnapshot {
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum InputEvent {
     /// A binded key was pressed. 
+    ///
+    /// For a `Chord` binding, this only occurs if the held modifiers matched 
+    /// when its button was pressed. 
     Press(KeyBind),
     /// A binded key was unpressed. 
+    ///
+    /// This occurs when the button is released, for every binding which was 
+    /// pressed, even if the modifiers were released first. 
     Unpress(KeyBind),
     /// A mouse button was clicked. This is independent of whether it was 
     /// binded. We suggest to bind the mouse for captured input, but use 
@@ -157,6 +341,46 @@ pub enum InputEvent {
     /// binded. It's suggested to bind the mouse for captured input, but use 
     /// raw clicks for gui input. 
     Unclick(MouseButton),
+    /// A mouse button was clicked for the second time within the double-click 
+    /// threshold. This follows the corresponding `Click`. 
+    ///
+    /// A third click begins a new double-click, rather than producing another 
+    /// event. 
+    DoubleClick(MouseButton),
+    /// A binded key has been pressed for longer than the hold threshold, for 
+    /// the given duration. 
+    ///
+    /// This occurs once per press, in the first frame after the threshold 
+    /// elapses. 
+    Hold(KeyBind, Duration),
+    /// The cursor moved further than the drag threshold while a mouse button 
+    /// was pressed. 
+    ///
+    /// Positions and deltas are in physical pixels. This is followed by a 
+    /// `DragMove` to the cursor's current position. 
+    DragStart {
+        button: MouseButton,
+        start: Vec2<i32>,
+    },
+    /// The cursor moved during a drag, to `start + delta`. 
+    DragMove {
+        button: MouseButton,
+        start: Vec2<i32>,
+        delta: Vec2<i32>,
+    },
+    /// The mouse button of a drag was released, at `start + delta`. 
+    DragEnd {
+        button: MouseButton,
+        start: Vec2<i32>,
+        delta: Vec2<i32>,
+    },
+}
+
+/// State of a pressed mouse button, which may become a drag. 
+#[derive(Copy, Clone, Debug)]
+struct Drag {
+    start: Vec2<f64>,
+    active: bool,
 }
 
 /// A state the `InputManager` can be in.
@@ -171,23 +395,51 @@ pub enum WindowState {
 }
 
 /// Utility for tracking `winit::window::Window` input. 
+///
+/// Each `InputManager` tracks a single window, and ignores window events for 
+/// other windows, so an application with several windows should create one 
+/// manager per window. Device events, such as captured mouse movement, are 
+/// only tracked while the manager's window is captured. 
 pub struct InputManager {
     window: Arc<Window>,
 
     // state which is tracking continually
     state: WindowState,
     bindings: HashMap<Button, Vec<KeyBind>>,
-    bindings_rev: HashMap<KeyBind, Button>,
+    bindings_rev: HashMap<KeyBind, Chord>,
+    axes: Vec<Axis>,
     pressed: HashSet<Button>,
+    pressed_binds: HashSet<KeyBind>,
+    modifiers: Modifiers,
+    double_click_threshold: Duration,
+    hold_threshold: Duration,
+    drag_threshold: f64,
+    drags: HashMap<MouseButton, Drag>,
+    cursor_position: Vec2<f64>,
+    last_click: HashMap<MouseButton, Instant>,
+    // press time, and whether a hold event has been emitted
+    press_times: HashMap<KeyBind, (Instant, bool)>,
     window_size: PhysicalSize<u32>,
     window_closing: bool,
     window_scale_factor: f64,
+    // cursor appearance while not captured
+    cursor_icon: CursorIcon,
+    cursor_visible: bool,
+    cursor_confined: bool,
     cursor_location: Vec2<f64>,
+    frame: u64,
 
     // accumulators which reset every frame
     events: Vec<(InputEvent, InputSnapshot)>,
     mouse_captured_movement: Vec2<f64>,
     mouse_scroll: Vec2<f64>,
+    fullscreen_toggled: bool,
+    text_input: String,
+    text_submitted: Option<String>,
+
+    // text entry state
+    text_input_active: bool,
+    composition: String,
 }
 
 impl InputManager {
@@ -200,13 +452,23 @@ impl InputManager {
         I: IntoIterator,
         <I as IntoIterator>::Item: Borrow<WinitEvent>,
     {
+        self.frame += 1;
+
         // reset accumulators
         self.events.clear();
         self.mouse_captured_movement = [0.0; 2].into();
         self.mouse_scroll = [0.0; 2].into();
+        self.fullscreen_toggled = false;
+        self.text_input.clear();
+        self.text_submitted = None;
 
+        let window_id = self.window.id();
         for event in winit_events
             .into_iter()
+            .filter(|e| match e.borrow() {
+                &WinitEvent::WindowEvent { window_id: id, .. } => id == window_id,
+                _ => true,
+            })
             .filter_map(|e| InterestEvent::option_from(e.borrow()))
         {
             match event {
@@ -214,61 +476,71 @@ impl InputManager {
                     state: ElementState::Pressed, 
                     key,
                 } => {
-                    if let Some(binds) = self.bindings.get(&key.into()) {
-                        for &bind in binds {
-                            self.events.push((
-                                InputEvent::Press(bind),
-                                self.snapshot(),
-                            ));
-                        }
+                    // typing shouldn't trigger game actions
+                    if !(self.text_input_active && is_text_key(key)) {
+                        self.press_binds(key.into());
                     }
-                    self.pressed.insert(key.into());
+                    let repeat = !self.pressed.insert(key.into());
 
                     // automatically un-capture the mouse when Esc is pressed
                     if key == Key::Escape {
                         self.uncapture_mouse();
                     }
+
+                    // F11 is the default fullscreen toggle
+                    if key == Key::F11 && !repeat {
+                        self.fullscreen_toggled = true;
+                    }
                 }
                 InterestEvent::KeyStateChange {
                     state: ElementState::Released,
                     key,
                 } => {
-                    if let Some(binds) = self.bindings.get(&key.into()) {
-                        for &bind in binds {
-                            self.events.push((
-                                InputEvent::Unpress(bind),
-                                self.snapshot(),
-                            ));
-                        }
-                    }
+                    self.unpress_binds(key.into());
                     self.pressed.remove(&key.into());
                 }
                 InterestEvent::MouseStateChange {
                     state: ElementState::Pressed,
                     button,
                 } => {
-                    if let Some(binds) = self.bindings.get(&button.into()) {
-                        for &bind in binds {
-                            self.events.push((
-                                InputEvent::Press(bind),
-                                self.snapshot(),
-                            ));
-                        }
-                    }
+                    self.press_binds(button.into());
                     self.events.push((
                         InputEvent::Click(button),
                         self.snapshot(),
                     ));
+                    let now = Instant::now();
+                    let double = self.last_click.get(&button)
+                        .map(|&last| now - last <= self.double_click_threshold)
+                        .unwrap_or(false);
+                    if double {
+                        self.events.push((
+                            InputEvent::DoubleClick(button),
+                            self.snapshot(),
+                        ));
+                        self.last_click.remove(&button);
+                    } else {
+                        self.last_click.insert(button, now);
+                    }
                     self.pressed.insert(button.into());
+                    self.drags.insert(button, Drag {
+                        start: self.cursor_position,
+                        active: false,
+                    });
                 }
                 InterestEvent::MouseStateChange {
                     state: ElementState::Released,
                     button,
                 } => {
-                    if let Some(binds) = self.bindings.get(&button.into()) {
-                        for &bind in binds {
+                    self.unpress_binds(button.into());
+                    if let Some(drag) = self.drags.remove(&button) {
+                        if drag.active {
                             self.events.push((
-                                InputEvent::Unpress(bind),
+                                InputEvent::DragEnd {
+                                    button,
+                                    start: drag.start.map(|n| n as i32),
+                                    delta: (self.cursor_position - drag.start)
+                                        .map(|n| n as i32),
+                                },
                                 self.snapshot(),
                             ));
                         }
@@ -279,6 +551,14 @@ impl InputManager {
                     ));
                     self.pressed.remove(&button.into());
                 }
+                InterestEvent::CharacterReceived { c } => {
+                    if self.text_input_active {
+                        self.receive_char(c);
+                    }
+                }
+                InterestEvent::ModifiersChanged { modifiers } => {
+                    self.modifiers = modifiers;
+                }
                 InterestEvent::NewCursorPosition { position } => {
                     let position = Vec2::new(
                         position.x as f64, 
@@ -289,6 +569,8 @@ impl InputManager {
                         self.window_size.height as f64,
                     );
                     self.cursor_location = size / position;
+                    self.cursor_position = position;
+                    self.move_drags();
                 }
                 InterestEvent::MouseMovement { x, y } => {
                     if self.state == WindowState::Captured {
@@ -337,6 +619,23 @@ impl InputManager {
                 }
             };
         }
+
+        // emit hold events for binds which crossed the threshold
+        let now = Instant::now();
+        let mut held: SmallVec<[(KeyBind, Duration); 4]> = SmallVec::new();
+        for (&bind, (pressed_at, hold_emitted)) in self.press_times.iter_mut() {
+            let duration = now - *pressed_at;
+            if !*hold_emitted && duration >= self.hold_threshold {
+                *hold_emitted = true;
+                held.push((bind, duration));
+            }
+        }
+        for (bind, duration) in held {
+            self.events.push((
+                InputEvent::Hold(bind, duration),
+                self.snapshot(),
+            ));
+        }
     }
 
     /// Return the window's current `WindowState`.
@@ -344,11 +643,22 @@ impl InputManager {
         self.state
     }
 
+    /// Return the index of the current frame, which is the number of times 
+    /// `update` has been called. 
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Return the ID of the window this manager tracks. 
+    pub fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
     /// Access the input events that occured in the last frame. 
     ///
     /// These are a (event, snapshot) tuples, which store the exact
-    /// input state when the event occured. This can be used to implement 
-    /// modifiers.  
+    /// input state when the event occured, as well as its time and frame 
+    /// index. They are in the order the events occured. 
     pub fn events(&self) -> &[(InputEvent, InputSnapshot)] {
         &self.events
     }
@@ -361,9 +671,46 @@ impl InputManager {
         self.cursor_location
     }
 
+    /// Return the cursor position, in physical pixels from the top-left of 
+    /// the window. 
+    ///
+    /// If the mouse is captured, or if the window is unfocused, this
+    /// may return non-useful data. 
+    pub fn cursor_position(&self) -> Vec2<f64> {
+        self.cursor_position
+    }
+
     /// Return whether a binded key is pressed at this snapshot. 
     pub fn is_pressed(&self, bind: KeyBind) -> bool {
-        self.pressed.contains(&self.bindings_rev[&bind])
+        self.pressed_binds.contains(&bind)
+    }
+
+    /// Return the value of a binded axis, from -1 to 1. 
+    pub fn axis(&self, bind: AxisBind) -> f64 {
+        let axis = self.axes[bind.0 as usize];
+        let mut value = 0.0;
+        if self.is_pressed(axis.negative) {
+            value -= 1.0;
+        }
+        if self.is_pressed(axis.positive) {
+            value += 1.0;
+        }
+        value
+    }
+
+    /// Return the name a binded axis was created with. 
+    pub fn axis_name(&self, bind: AxisBind) -> &'static str {
+        self.axes[bind.0 as usize].name
+    }
+
+    /// Return the chord a binded key is bound to. 
+    pub fn chord(&self, bind: KeyBind) -> Chord {
+        self.bindings_rev[&bind]
+    }
+
+    /// Return the modifier keys which are currently held. 
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
     }
 
     /// Return whether the window is being closed or destroyed. 
@@ -379,6 +726,57 @@ impl InputManager {
         self.mouse_captured_movement
     }
 
+    /// Return whether fullscreen was toggled (by pressing F11) since the last 
+    /// frame. 
+    pub fn fullscreen_toggled(&self) -> bool {
+        self.fullscreen_toggled
+    }
+
+    /// Begin collecting typed characters as text, such as for a chat box. 
+    ///
+    /// While text input is active, bindings to keys which produce text, or 
+    /// which edit it (such as backspace and enter), are not pressed. 
+    pub fn begin_text_input(&mut self) {
+        self.text_input_active = true;
+    }
+
+    /// Stop collecting typed characters as text, and clear the composition. 
+    pub fn end_text_input(&mut self) {
+        self.text_input_active = false;
+        self.composition.clear();
+    }
+
+    /// Return whether text input is active. 
+    pub fn is_text_input_active(&self) -> bool {
+        self.text_input_active
+    }
+
+    /// Return the text typed since the last frame. 
+    ///
+    /// Backspaces remove characters typed in the same frame, but are otherwise 
+    /// only reflected in the `composition`. 
+    pub fn text_input(&self) -> &str {
+        &self.text_input
+    }
+
+    /// Return the text composed since text input began or was last 
+    /// submitted, with backspaces applied. 
+    pub fn composition(&self) -> &str {
+        &self.composition
+    }
+
+    /// Replace the composition, such as to pre-fill or edit it. 
+    pub fn set_composition(&mut self, composition: String) {
+        self.composition = composition;
+    }
+
+    /// Return the composition if enter was pressed since the last frame. 
+    ///
+    /// Submitting clears the composition, but text input remains active. 
+    pub fn text_submitted(&self) -> Option<&str> {
+        self.text_submitted.as_ref().map(String::as_str)
+    }
+
     /// Return the sum of mouse scrolling since the last frame.
     pub fn mouse_scroll(&self) -> Vec2<f64> {
         self.mouse_scroll
@@ -416,23 +814,191 @@ impl InputManager {
 
             self.state = WindowState::Focused;
         }
-        let _ = self.window.set_cursor_grab(false);
-        self.window.set_cursor_visible(true);
+        self.apply_cursor_appearance();
+    }
+
+    /// Set the cursor's icon. 
+    ///
+    /// While the cursor is captured, this takes effect upon uncapture. 
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.cursor_icon = icon;
+        if self.state != WindowState::Captured {
+            self.window.set_cursor_icon(icon);
+        }
+    }
+
+    /// Return the cursor's icon. 
+    pub fn cursor_icon(&self) -> CursorIcon {
+        self.cursor_icon
+    }
+
+    /// Set whether the cursor is visible. 
+    ///
+    /// The cursor is always hidden while captured, so while captured, this 
+    /// takes effect upon uncapture. 
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+        if self.state != WindowState::Captured {
+            self.window.set_cursor_visible(visible);
+        }
+    }
+
+    /// Return whether the cursor is visible while not captured. 
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Set whether the cursor is confined to the window, without capturing 
+    /// it. 
+    ///
+    /// While the cursor is captured, this takes effect upon uncapture. 
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        self.cursor_confined = confined;
+        if self.state != WindowState::Captured {
+            if let Err(e) = self.window.set_cursor_grab(confined) {
+                warn!("failed to set cursor confinement: {}", e);
+            }
+        }
+    }
+
+    /// Return whether the cursor is confined to the window while not 
+    /// captured. 
+    pub fn is_cursor_confined(&self) -> bool {
+        self.cursor_confined
+    }
+
+    /// Restore the cursor's icon, visibility, and confinement from before it 
+    /// was captured. 
+    fn apply_cursor_appearance(&self) {
+        let _ = self.window.set_cursor_grab(self.cursor_confined);
+        self.window.set_cursor_visible(self.cursor_visible);
+        self.window.set_cursor_icon(self.cursor_icon);
     }
 
     /// Capture the current state of input. 
     pub fn snapshot(&self) -> InputSnapshot {
-        let pressed = self.pressed
-            .iter()
-            .filter_map(|button| self.bindings.get(button))
-            .flat_map(identity)
-            .copied()
-            .collect();
         InputSnapshot {
             state: self.state,
             cursor_location: self.cursor_location,
-            pressed,
+            modifiers: self.modifiers,
+            pressed: self.pressed_binds.iter().copied().collect(),
+            time: Instant::now(),
+            frame: self.frame,
+        }
+    }
+
+    /// Start or continue drags after the cursor moves. 
+    fn move_drags(&mut self) {
+        let mut moved: SmallVec<[(MouseButton, bool, Drag); 2]> = SmallVec::new();
+        for (&button, drag) in self.drags.iter_mut() {
+            let delta = self.cursor_position - drag.start;
+            let started = !drag.active 
+                && delta.magnitude() >= self.drag_threshold;
+            drag.active |= started;
+            if drag.active {
+                moved.push((button, started, *drag));
+            }
+        }
+        for (button, started, drag) in moved {
+            let start = drag.start.map(|n| n as i32);
+            if started {
+                self.events.push((
+                    InputEvent::DragStart { button, start },
+                    self.snapshot(),
+                ));
+            }
+            self.events.push((
+                InputEvent::DragMove {
+                    button,
+                    start,
+                    delta: (self.cursor_position - drag.start).map(|n| n as i32),
+                },
+                self.snapshot(),
+            ));
+        }
+    }
+
+    /// Apply a received character to the text input state. 
+    fn receive_char(&mut self, c: char) {
+        match c {
+            // backspace
+            '\u{8}' => {
+                self.text_input.pop();
+                self.composition.pop();
+            }
+            '\r' | '\n' => {
+                let text = std::mem::replace(&mut self.composition, String::new());
+                self.text_submitted = Some(text);
+            }
+            c if c.is_control() => (),
+            c => {
+                self.text_input.push(c);
+                self.composition.push(c);
+            }
+        }
+    }
+
+    /// Fire press events for the bindings of a pressed button whose chords 
+    /// match the held modifiers. 
+    fn press_binds(&mut self, button: Button) {
+        let binds: SmallVec<[KeyBind; 4]> = self.bindings
+            .get(&button)
+            .into_iter()
+            .flat_map(identity)
+            .copied()
+            .filter(|bind| self.bindings_rev[bind].matches(self.modifiers))
+            .collect();
+        let now = Instant::now();
+        for &bind in &binds {
+            self.events.push((
+                InputEvent::Press(bind),
+                self.snapshot(),
+            ));
+            // key repeats don't restart the hold
+            self.press_times.entry(bind).or_insert((now, false));
         }
+        self.pressed_binds.extend(binds);
+    }
+
+    /// Fire unpress events for the bindings of a released button which were 
+    /// pressed. 
+    fn unpress_binds(&mut self, button: Button) {
+        let binds: SmallVec<[KeyBind; 4]> = self.bindings
+            .get(&button)
+            .into_iter()
+            .flat_map(identity)
+            .copied()
+            .filter(|bind| self.pressed_binds.contains(bind))
+            .collect();
+        for &bind in &binds {
+            self.events.push((
+                InputEvent::Unpress(bind),
+                self.snapshot(),
+            ));
+        }
+        for bind in binds {
+            self.pressed_binds.remove(&bind);
+            self.press_times.remove(&bind);
+        }
+    }
+}
+
+/// Whether a key produces or edits text, and so shouldn't trigger bindings 
+/// during text input. 
+fn is_text_key(key: Key) -> bool {
+    use winit::event::VirtualKeyCode::*;
+    match key {
+        Key1 | Key2 | Key3 | Key4 | Key5 | Key6 | Key7 | Key8 | Key9 | Key0 
+        | A | B | C | D | E | F | G | H | I | J | K | L | M 
+        | N | O | P | Q | R | S | T | U | V | W | X | Y | Z 
+        | Numpad0 | Numpad1 | Numpad2 | Numpad3 | Numpad4 
+        | Numpad5 | Numpad6 | Numpad7 | Numpad8 | Numpad9 
+        | Space | Back | Return | NumpadEnter 
+        | Add | Subtract | Multiply | Divide | Decimal 
+        | NumpadComma | NumpadEquals 
+        | Apostrophe | At | Backslash | Colon | Comma | Equals | Grave 
+        | LBracket | RBracket | Minus | Period | Semicolon | Slash => true,
+        _ => false,
     }
 }
 
@@ -495,6 +1061,18 @@ match_simplifier! {
             state: ElementState,
             button: MouseButton,
         },
+        &WinitEvent::WindowEvent {
+            event: WindowEvent::ReceivedCharacter(c),
+            ..
+        } => CharacterReceived {
+            c: char,
+        },
+        &WinitEvent::WindowEvent {
+            event: WindowEvent::ModifiersChanged(modifiers),
+            ..
+        } => ModifiersChanged {
+            modifiers: Modifiers,
+        },
         &WinitEvent::WindowEvent {
             event: WindowEvent::CursorMoved {
                 position,
@@ -0,0 +1,247 @@
+//! The player's inventory of items, and its hotbar HUD. 
+
+use crate::{
+    graphics::{Graphics, text_rects, WHITE_SPRITE_TEXTURE},
+    world::{BlockId, AIR},
+};
+use std::collections::HashMap;
+use vek::*;
+
+/// Number of slots in the hotbar, which are the first slots of the
+/// inventory. 
+pub const HOTBAR_SIZE: usize = 9;
+
+/// Total number of inventory slots, including the hotbar. 
+pub const INVENTORY_SIZE: usize = 36;
+
+/// Maximum number of items in a stack. 
+pub const MAX_STACK: u32 = 64;
+
+/// Some number of a single kind of item. 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ItemStack {
+    /// Block which the items are. 
+    pub block: BlockId,
+    /// Number of items, which is never zero. 
+    pub count: u32,
+}
+
+/// Slots which hold item stacks, with a selected hotbar slot. 
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+    selected: usize,
+}
+
+impl Inventory {
+    /// Construct empty, with the first hotbar slot selected. 
+    pub fn new() -> Self {
+        Inventory {
+            slots: vec![None; INVENTORY_SIZE],
+            selected: 0,
+        }
+    }
+
+    /// Get all slots, starting with the hotbar. 
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    /// Get the hotbar slots. 
+    pub fn hotbar(&self) -> &[Option<ItemStack>] {
+        &self.slots[..HOTBAR_SIZE]
+    }
+
+    /// Get the index of the selected hotbar slot. 
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Select a hotbar slot. 
+    ///
+    /// Panics if the index is out of range. 
+    pub fn select(&mut self, index: usize) {
+        assert!(index < HOTBAR_SIZE, "hotbar slot index out of range");
+        self.selected = index;
+    }
+
+    /// Move the hotbar selection by some number of slots, wrapping around. 
+    pub fn scroll(&mut self, steps: i32) {
+        self.selected = (self.selected as i32 + steps).rem_euclid(HOTBAR_SIZE as i32) as usize;
+    }
+
+    /// Get the stack in the selected hotbar slot. 
+    pub fn selected(&self) -> Option<ItemStack> {
+        self.slots[self.selected]
+    }
+
+    /// Add items, first to existing stacks of that item, then to empty slots,
+    /// each in slot order. Return the number of items which didn't fit. 
+    pub fn add(&mut self, block: BlockId, mut count: u32) -> u32 {
+        if block == AIR {
+            return 0;
+        }
+        for slot in self.slots.iter_mut() {
+            if let Some(ref mut stack) = *slot {
+                if stack.block == block && stack.count < MAX_STACK {
+                    let n = count.min(MAX_STACK - stack.count);
+                    stack.count += n;
+                    count -= n;
+                }
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if count == 0 {
+                break;
+            }
+            if slot.is_none() {
+                let n = count.min(MAX_STACK);
+                *slot = Some(ItemStack { block, count: n });
+                count -= n;
+            }
+        }
+        count
+    }
+
+    /// Remove one item from the selected hotbar slot, and return which item
+    /// it was. 
+    pub fn take_selected(&mut self) -> Option<BlockId> {
+        let slot = &mut self.slots[self.selected];
+        let stack = slot.as_mut()?;
+        let block = stack.block;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(block)
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory::new()
+    }
+}
+
+/// First sprite ID used by the hotbar HUD. 
+///
+/// Sprite IDs from this up to `HOTBAR_SPRITE_BASE + 0xffff` are reserved. 
+pub const HOTBAR_SPRITE_BASE: u64 = 0x10000;
+
+/// Size of a hotbar slot, in pixels. 
+const SLOT_SIZE: f32 = 44.0;
+/// Space between and around hotbar slots, in pixels. 
+const SLOT_GAP: f32 = 4.0;
+/// Space between a slot's edge and its item icon, in pixels. 
+const ICON_INSET: f32 = 6.0;
+/// Size of a font pixel in item counts, in pixels. 
+const COUNT_SCALE: f32 = 2.0;
+const SLOT_COLOR: Rgba<f32> = Rgba { r: 0.0, g: 0.0, b: 0.0, a: 0.5 };
+const SELECTED_SLOT_COLOR: Rgba<f32> = Rgba { r: 1.0, g: 1.0, b: 1.0, a: 0.6 };
+const COUNT_COLOR: Rgba<f32> = Rgba { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+
+/// Draws the hotbar at the bottom of the screen with sprites. 
+#[derive(Debug, Clone, Default)]
+pub struct HotbarHud {
+    num_sprites: u64,
+}
+
+impl HotbarHud {
+    /// Construct, with no sprites drawn yet. 
+    pub fn new() -> Self {
+        HotbarHud::default()
+    }
+
+    /// Update the hotbar's sprites to show an inventory. 
+    ///
+    /// `icons` maps blocks to the sprite textures to draw their items as. 
+    /// Items with no icon are drawn as a white square. 
+    pub fn update(
+        &mut self,
+        graphics: &mut Graphics,
+        inventory: &Inventory,
+        icons: &HashMap<BlockId, u32>,
+    ) {
+        let window_size = graphics.window().inner_size();
+        let width = HOTBAR_SIZE as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+        let origin = Vec2::new(
+            (window_size.width as f32 - width) / 2.0,
+            window_size.height as f32 - SLOT_SIZE - SLOT_GAP,
+        );
+
+        let mut id = HOTBAR_SPRITE_BASE;
+        let mut sprite = |graphics: &mut Graphics, rect, tex, color| {
+            graphics.set_sprite(id, rect, tex, color);
+            id += 1;
+        };
+        for (i, slot) in inventory.hotbar().iter().enumerate() {
+            let corner = origin + Vec2::new(i as f32 * (SLOT_SIZE + SLOT_GAP), 0.0);
+            let color = if i == inventory.selected_index() {
+                SELECTED_SLOT_COLOR
+            } else {
+                SLOT_COLOR
+            };
+            sprite(
+                graphics,
+                Rect::new(corner.x, corner.y, SLOT_SIZE, SLOT_SIZE),
+                WHITE_SPRITE_TEXTURE,
+                color,
+            );
+
+            let stack = match *slot {
+                Some(stack) => stack,
+                None => continue,
+            };
+            let icon_size = SLOT_SIZE - ICON_INSET * 2.0;
+            sprite(
+                graphics,
+                Rect::new(corner.x + ICON_INSET, corner.y + ICON_INSET, icon_size, icon_size),
+                icons.get(&stack.block).copied().unwrap_or(WHITE_SPRITE_TEXTURE),
+                Rgba::white(),
+            );
+            if stack.count > 1 {
+                let text = stack.count.to_string();
+                // right-align the count in the bottom of the slot
+                let text_width = (text.len() as f32 * 4.0 - 1.0) * COUNT_SCALE;
+                let text_origin = corner
+                    + Vec2::new(SLOT_SIZE - 3.0 - text_width, SLOT_SIZE - 3.0 - 5.0 * COUNT_SCALE);
+                for rect in text_rects(&text, text_origin, COUNT_SCALE) {
+                    sprite(graphics, rect, WHITE_SPRITE_TEXTURE, COUNT_COLOR);
+                }
+            }
+        }
+
+        let num_sprites = id - HOTBAR_SPRITE_BASE;
+        for i in num_sprites..self.num_sprites {
+            graphics.remove_sprite(HOTBAR_SPRITE_BASE + i);
+        }
+        self.num_sprites = num_sprites;
+    }
+}
+
+#[test]
+fn inventory_test() {
+    let mut inv = Inventory::new();
+    assert_eq!(inv.add(3, 100), 0);
+    assert_eq!(inv.slots()[0], Some(ItemStack { block: 3, count: 64 }));
+    assert_eq!(inv.slots()[1], Some(ItemStack { block: 3, count: 36 }));
+
+    // existing stacks are filled before empty slots
+    inv.add(5, 1);
+    assert_eq!(inv.add(3, 30), 0);
+    assert_eq!(inv.slots()[1], Some(ItemStack { block: 3, count: 64 }));
+    assert_eq!(inv.slots()[3], Some(ItemStack { block: 3, count: 2 }));
+
+    inv.select(2);
+    assert_eq!(inv.take_selected(), Some(5));
+    assert_eq!(inv.take_selected(), None);
+    assert_eq!(inv.selected(), None);
+
+    inv.scroll(-3);
+    assert_eq!(inv.selected_index(), 8);
+    inv.scroll(2);
+    assert_eq!(inv.selected_index(), 1);
+
+    // overflow is returned
+    assert_eq!(inv.add(7, MAX_STACK * INVENTORY_SIZE as u32), MAX_STACK * 3);
+}
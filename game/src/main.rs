@@ -11,48 +11,158 @@ extern crate winit;
 extern crate bytemuck;
 extern crate memoffset;
 extern crate image;
+extern crate gltf;
+extern crate tobj;
 
 extern crate rand;
+extern crate rand_pcg;
 extern crate mint;
 extern crate vek;
 extern crate num_traits;
+extern crate approx;
+
+extern crate serde;
+extern crate toml;
+extern crate flate2;
+extern crate bincode;
+extern crate laminar;
+extern crate egui;
+extern crate puffin;
 
 extern crate iter_vals;
 extern crate arraymap;
 extern crate futures;
 extern crate array_iterator;
 extern crate smallvec;
+extern crate game_derive;
+#[cfg(feature = "scripting")]
+extern crate rhai;
 
 pub mod graphics;
+pub mod assets;
+pub mod mesher;
+pub mod config;
 pub mod util;
 pub mod input;
+pub mod world;
+pub mod ecs;
+pub mod inventory;
+pub mod state;
+pub mod net;
+pub mod gui;
+pub mod profiler;
+pub mod time;
+#[cfg(feature = "scripting")]
+pub mod scripting;
 
-use std::time::{Instant, Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::PI,
+    mem,
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Instant, Duration},
+};
 use util::{
-    fps_tracker::FpsTracker,
     angle::*,
+    axis_unit::AxisUnit3,
     camera::YawPitch,
+    physics::{KinematicBody, raycast},
+    geom::Aabb,
+    timestep::FixedTimestep,
+    rng::Rng,
+    interp::lerp_vec3,
+    scheduler::Timer,
+};
+use world::{World, WorldGen, BlockRegistry, BlockId, AIR};
+use ecs::{
+    Ecs,
+    components::Transform,
+    systems::{movement_system, collision_system, render_system},
+    ai::{MobSpawner, wander_system},
 };
+use inventory::{Inventory, HotbarHud};
+use state::{GameState, InputMode, StateStack, StateHud, Command};
+use config::{Settings, Binds};
+use gui::{Gui, InspectorStats, inspector};
+use profiler::Profiler;
+use time::{GameTime, Timers};
+use net::{Server, Client, PlayerId};
+use assets::{Assets, AssetEvent, Handle, Model, Shader, Texture};
+use mesher::Mesher;
+#[cfg(feature = "scripting")]
+use scripting::ScriptHost;
 use graphics::{
     *,
     builder::GraphicsBuilder,
+    chunks::ChunkEvent,
 };
 use input::{
     Key,
     InputManagerBuilder,
     InputManager,
     InputEvent,
+    KeyBind,
+    AxisBind,
     MouseButton,
     WindowState,
 };
 use pear::*;
 use arraymap::ArrayMap;
 
-/// Target FPS. 
-const FPS: u32 = 60;
+/// Size of the player's body while walking. 
+const PLAYER_SIZE: [f32; 3] = [0.6, 1.8, 0.6];
+
+/// Height of the camera above the bottom of the player's body. 
+const EYE_HEIGHT: f32 = 1.6;
+
+/// Downwards speed above which landing shakes the camera. 
+const HARD_LANDING_SPEED: f32 = 15.0;
+
+/// Maximum distance from the camera at which blocks can be broken or
+/// placed. 
+const REACH: f32 = 6.0;
+
+/// Crosshair sprite IDs. 
+const CROSSHAIR_HORIZONTAL: u64 = 0;
+const CROSSHAIR_VERTICAL: u64 = 1;
+
+/// Model instance IDs of other players in multiplayer, offset by player ID,
+/// which are above any entity's instance ID. 
+const REMOTE_PLAYER_INSTANCE_BASE: u64 = 1 << 63;
 
 use vek::*;
 
+/// User settings file path. 
+const SETTINGS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml");
+
+/// Gameplay script path. 
+#[cfg(feature = "scripting")]
+const SCRIPT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/scripts/main.rhai");
+
+/// World save directory. 
+const SAVE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/saves/world");
+
+/// Range of chunks to generate terrain in for a new world, inclusive. 
+const GEN_MIN_CHUNK: Vec3<i32> = Vec3 { x: -6, y: -2, z: -6 };
+const GEN_MAX_CHUNK: Vec3<i32> = Vec3 { x: 5, y: 3, z: 5 };
+
+/// Seconds between checks for modified asset files, which are only done in
+/// debug builds. 
+const ASSET_RELOAD_INTERVAL: f32 = 1.0;
+
+/// Directory which asset paths are relative to. 
+const ASSET_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+
+/// Block type definition file path. 
+const BLOCK_DEFINITIONS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/blocks.toml");
+
+/// Block texture manifest path. 
+const BLOCK_TEXTURE_MANIFEST: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"), "/src/textures/manifest.toml",
+);
+
 fn main() {
     try_main()
         .unwrap_or_else(|e| {
@@ -68,214 +178,763 @@ fn try_main() -> Result<()> {
         .with_env_filter("warn,game=trace,pear=trace,floatilla=trace")
         //.with_env_filter("warn,game=trace,pear=trace,floatilla=trace,gfx_backend_vulkan=error")
         .init();
+
+    let args = parse_args()?;
+    if let Some(ref addr) = args.server {
+        return run_server(addr, args.seed);
+    }
     
     // initialize
+    let mut settings = Settings::load(SETTINGS_PATH)?;
+    // assets load in the background while the rest of initialization runs
+    let mut assets = Assets::new(ASSET_DIR);
+    let shaders = graphics::SHADERS
+        .iter()
+        .map(|&name| (name, assets.load::<Shader, _>(Path::new("graphics").join(name))))
+        .collect::<Vec<_>>();
+    // block textures are also loaded as sprites, for item icons
+    let manifest = TextureManifest::read(BLOCK_TEXTURE_MANIFEST)?;
+    let texture_sprites = manifest.layers
+        .iter()
+        .map(|layer| (
+            layer.name.clone(),
+            assets.load::<Texture, _>(layer.file_path(Path::new("textures"))),
+        ))
+        .collect::<Vec<_>>();
+    let mob_mesh = assets.load::<Model, _>("models/mob.obj");
+    let mob_texture = assets.load::<Texture, _>("textures/hellstone.png");
+
     let mut graphics = GraphicsBuilder::new();
-    graphics.add_block_texture(include_bytes!("textures/stone.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/dirt.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/grass.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/grass_side.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/sand.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/snow.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/ice.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/hellstone.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/gravel.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/coal_ore.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/iron_ore.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/gold_ore.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/diamond_ore.png"))?;
-    graphics.add_block_texture(include_bytes!("textures/red_ore.png"))?;
-    let mut fps_tracker = FpsTracker::default();
+    graphics.reverse_z(true);
+    settings.graphics.apply_builder(&mut graphics);
+    graphics.load_block_texture_manifest(BLOCK_TEXTURE_MANIFEST)?;
+    for (name, handle) in shaders {
+        // shaders fall back to the ones built into the binary
+        match assets.wait(handle) {
+            Ok(shader) => graphics.add_shader(name, shader),
+            Err(e) => warn!("using built-in shader {}:\n{}", name, e),
+        }
+    }
+    // block textures are reloaded when their files change
+    let block_textures = texture_sprites
+        .iter()
+        .map(|&(ref name, handle)| (handle, name.clone()))
+        .collect::<Vec<_>>();
+    let texture_sprites = texture_sprites
+        .into_iter()
+        .map(|(name, handle)| {
+            let texture = assets.wait(handle)?;
+            Ok((name, graphics.add_sprite_texture_image(texture.0.clone())))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut mob_model = assets.wait(mob_mesh)?.0.clone();
+    mob_model.set_texture(assets.wait(mob_texture)?.0.clone());
+    let mob_model = graphics.add_model(mob_model);
 
     let (mut graphics, hijacker) = graphics.build()?;
+    settings.graphics.apply(&mut graphics);
+    // the sky follows the game clock, rather than the wall clock
+    graphics.set_day_length(None);
+    let chunk_events = graphics.subscribe_chunk_events();
+    let blocks = BlockRegistry::read(BLOCK_DEFINITIONS, |name| graphics.block_texture_index(name))?;
+
+    // items are drawn as their block's top face
+    let layer_sprites = texture_sprites
+        .iter()
+        .filter_map(|&(ref name, sprite)| graphics
+            .block_texture_index(name)
+            .map(|layer| (layer, sprite)))
+        .collect::<HashMap<u32, u32>>();
+    let block_icons = blocks
+        .iter()
+        .filter(|&(id, _)| id != AIR)
+        .filter_map(|(id, def)| layer_sprites
+            .get(&def.faces[AxisUnit3::UP.to_index()])
+            .map(|&sprite| (id, sprite)))
+        .collect::<HashMap<BlockId, u32>>();
 
     let mut input = InputManagerBuilder::new();
-    let move_forward = input.bind(Key::W);
-    let move_backward = input.bind(Key::S);
-    let move_left = input.bind(Key::A);
-    let move_right = input.bind(Key::D);
-    let move_up = input.bind(Key::Space);
-    let move_down = input.bind(Key::LShift); 
-    let mut input = input.build(graphics.window().clone());
-
-    // put some bloxs
-    for x in -5i32..=5 {
-        for y in -5i32..=5 {
-            for z in -5i32..=5 {
-                let tex_index = x + y + z;
-                let tex_index = (tex_index + 14) % 14;
-                let pos = Vec3::new(x, y, z) * 4;
-                let geom = BLOCK_MESH_TEMPLATE
-                    .iter()
-                    .flat_map(|face| face.iter())
-                    .map(|prim| {
-                        prim.map(|vert| DrawBlocksVertex {
-                            pos: (vert.pos + pos).map(|n| n as f32),
-                            tex_coord: vert.tex.map(|n| n as f32),
-                            tex_index: tex_index as u32,
-                        })
-                    });
-                graphics.set_block_mesh(pos, geom);
+    let binds = settings.controls.keys.bind(&mut input);
+    let input = input.build(graphics.window().clone());
+
+    // in multiplayer, the world is streamed from the server instead
+    let (mut world, client) = match args.connect {
+        Some(ref addr) => (World::new(), Some(Client::connect(addr, &args.name)?)),
+        None => (load_world(&blocks, args.seed)?, None),
+    };
+    world.enable_light(&blocks);
+    // in multiplayer, this is replaced once the server's seed is known
+    let mut mesher = Mesher::new(&blocks, world.seed());
+
+    // mesh the world's blocks before the first frame
+    for chunk in world.take_remesh() {
+        mesher.queue(&world, chunk);
+    }
+    mesher.flush(&mut graphics);
+
+    #[cfg(feature = "scripting")]
+    let script = ScriptHost::read(SCRIPT_PATH, &blocks)?;
+
+    let rng = Rng::new(world.seed()).for_system("mobs");
+    let time = GameTime::default();
+    let mut timers = Timers::new();
+    let next_day = (time.day() + 1) * time.ticks_per_day();
+    timers.schedule_every(next_day, time.ticks_per_day(), |game: &mut Game| {
+        info!("day {} begins", game.time.day());
+    });
+
+    // main loop
+    hijacker.hijack(Game {
+        cam_pos: graphics.cam_pos(),
+        prev_cam_pos: graphics.cam_pos(),
+        graphics,
+        input,
+        binds,
+        settings,
+        chunk_events,
+        last_frame: Instant::now(),
+        states: StateStack::new(GameState::MainMenu),
+        state_hud: StateHud::new(),
+        gui: Gui::new(),
+        profiler: Profiler::new(),
+        exiting: false,
+        body: None,
+        world,
+        blocks,
+        mesher,
+        assets,
+        block_textures,
+        asset_reload: match cfg!(debug_assertions) {
+            true => Some(Timer::repeating(ASSET_RELOAD_INTERVAL)),
+            false => None,
+        },
+        inventory: Inventory::new(),
+        hotbar_hud: HotbarHud::new(),
+        block_icons,
+        ecs: Ecs::new(),
+        mob_spawner: MobSpawner::new(mob_model),
+        rng,
+        time,
+        timers,
+        #[cfg(feature = "scripting")]
+        script,
+        client,
+        player_model: mob_model,
+        remote_players: HashSet::new(),
+    })
+}
+
+/// Command line arguments. 
+#[derive(Debug, Clone)]
+struct Args {
+    // address to run a dedicated server on
+    server: Option<String>,
+    // address of a server to join
+    connect: Option<String>,
+    // player name in multiplayer
+    name: String,
+    // seed for a new world
+    seed: Option<u64>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut parsed = Args {
+        server: None,
+        connect: None,
+        name: "player".to_owned(),
+        seed: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next()
+            .ok_or_else(|| pear!({arg=arg}, "missing command line argument value"));
+        match arg.as_str() {
+            "--server" => parsed.server = Some(value()?),
+            "--connect" => parsed.connect = Some(value()?),
+            "--name" => parsed.name = value()?,
+            "--seed" => {
+                let seed = value()?;
+                parsed.seed = Some(seed.parse()
+                    .map_err(Error::from)
+                    .wrap_err(|| pear!({seed=seed}, "invalid seed"))?);
             }
+            _ => return Err(pear!(
+                {arg=arg},
+                "unknown command line argument, expected --server <addr>, \
+                --connect <addr>, --name <name>, or --seed <seed>",
+            )),
         }
     }
+    Ok(parsed)
+}
 
-    // main loop
-    hijacker.hijack(move |events: &[WinitEvent], delta: f32| {
-        let start_time = Instant::now();
-        fps_tracker.log_frame();
+/// Load the world, or generate terrain in a new one. 
+///
+/// `seed` is the seed for a new world, and is ignored for an existing one. 
+fn load_world(blocks: &BlockRegistry, seed: Option<u64>) -> Result<World> {
+    let mut world = World::load(SAVE_DIR)?;
+    if world.chunks().next().is_none() {
+        if let Some(seed) = seed {
+            world.set_seed(seed);
+        }
+        let gen = WorldGen::new(world.seed(), blocks)?;
+        gen.generate(&mut world, GEN_MIN_CHUNK, GEN_MAX_CHUNK);
+        world.save_all(SAVE_DIR)?;
+    } else if seed.is_some() {
+        warn!("ignoring seed for existing world");
+    }
+    Ok(world)
+}
+
+/// Run a dedicated multiplayer server, without graphics, until killed. 
+fn run_server(addr: &str, seed: Option<u64>) -> Result<()> {
+    // texture indices only matter for rendering, so any consistent mapping
+    // of names to indices works here
+    let manifest = TextureManifest::read(BLOCK_TEXTURE_MANIFEST)?;
+    let blocks = BlockRegistry::read(BLOCK_DEFINITIONS, |name| manifest.layers
+        .iter()
+        .position(|layer| layer.name == name)
+        .map(|i| i as u32))?;
+    let world = load_world(&blocks, seed)?;
+    let mut server = Server::bind(addr, world, blocks)?;
+    info!("serving on {}", server.local_addr()?);
+
+    let tick = FixedTimestep::default().tick_dt();
+    loop {
+        let start = Instant::now();
+        server.update()?;
+        if let Some(rest) = tick.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+}
+
+/// Construct the player's body for walking, with the camera at eye level. 
+fn player_body(cam_pos: Vec3<f32>) -> KinematicBody {
+    KinematicBody::new(cam_pos - Vec3::new(0.0, EYE_HEIGHT, 0.0), PLAYER_SIZE.into())
+}
+
+/// Set a block, and in multiplayer, also tell the server. 
+fn set_block(world: &mut World, client: &mut Option<Client>, pos: Vec3<i32>, block: BlockId) {
+    match *client {
+        Some(ref mut client) => client.set_block(world, pos, block),
+        None => {
+            world.set_block(pos, block);
+        }
+    }
+}
+
+/// Game state, which is driven by the `MainHijacker`. 
+struct Game {
+    graphics: Graphics,
+    input: InputManager,
+    binds: Binds,
+    settings: Settings,
+    chunk_events: mpsc::Receiver<ChunkEvent>,
+    // start of the last frame, for capping the frame rate
+    last_frame: Instant,
+    // menus and pausing, which decide which systems run
+    states: StateStack,
+    state_hud: StateHud,
+    // debug inspector, shown in the inspector state
+    gui: Gui,
+    // frame profiler, whose flame graph is shown while enabled
+    profiler: Profiler,
+    // set by the quit command
+    exiting: bool,
+    // player body while walking, or none while flying
+    body: Option<KinematicBody>,
+    world: World,
+    blocks: BlockRegistry,
+    // meshes modified chunks in the background
+    mesher: Mesher,
+    // assets loaded from disk, which may finish loading or reload later
+    assets: Assets,
+    // block texture names, by the assets they're loaded from
+    block_textures: Vec<(Handle<Texture>, String)>,
+    // polls for modified asset files, in debug builds
+    asset_reload: Option<Timer>,
+    // the selected hotbar item is placed by right clicking
+    inventory: Inventory,
+    hotbar_hud: HotbarHud,
+    // item icon sprite textures
+    block_icons: HashMap<BlockId, u32>,
+    // non-block objects in the world
+    ecs: Ecs,
+    mob_spawner: MobSpawner,
+    // mob spawning and wandering, derived from the world seed
+    rng: Rng,
+    // world clock, which advances while simulating
+    time: GameTime,
+    timers: Timers<Game>,
+    #[cfg(feature = "scripting")]
+    script: ScriptHost,
+    // connection to a server in multiplayer
+    client: Option<Client>,
+    player_model: ModelId,
+    // other players which have model instances
+    remote_players: HashSet<PlayerId>,
+    // simulated camera position, and its value one tick earlier
+    cam_pos: Vec3<f32>,
+    prev_cam_pos: Vec3<f32>,
+}
+
+impl FrameHandler for Game {
+    fn events(&mut self, events: &[WinitEvent]) -> Result<()> {
+        let input = &mut self.input;
+        let graphics = &mut self.graphics;
 
         input.update(events);
 
-        if input.events()
+        // blocks are targeted by a ray through the center of the screen
+        let origin = graphics.cam_pos();
+        let dir = graphics.cam_dir().to_forward_vec();
+        let input_events = input.events()
             .iter()
-            .any(|&(event, _)| event == InputEvent::Click(MouseButton::Left))
-        {
-            input.capture_mouse();
+            .map(|&(event, _)| event)
+            .collect::<Vec<_>>();
+        for event in input_events {
+            match self.states.current().input_mode() {
+                InputMode::Gameplay => (),
+                InputMode::Menu => {
+                    let resume = match event {
+                        InputEvent::Click(MouseButton::Left) => true,
+                        InputEvent::Press(bind) => bind == self.binds.pause
+                            && self.states.current() == GameState::Paused,
+                        _ => false,
+                    };
+                    if resume {
+                        if self.states.pop().is_none() {
+                            self.states.replace(GameState::Playing);
+                        }
+                        input.capture_mouse();
+                    }
+                    continue;
+                }
+                InputMode::Text => {
+                    if event == InputEvent::Press(self.binds.pause) {
+                        self.states.pop();
+                        input.end_text_input();
+                        input.capture_mouse();
+                    }
+                    continue;
+                }
+                InputMode::Gui => {
+                    let close = event == InputEvent::Press(self.binds.pause)
+                        || event == InputEvent::Press(self.binds.toggle_inspector);
+                    if close {
+                        self.states.pop();
+                        input.capture_mouse();
+                    }
+                    // clicks go to the GUI, rather than the world
+                    continue;
+                }
+            }
+            match event {
+                InputEvent::Click(MouseButton::Left) => {
+                    if let Some(hit) = raycast(&self.world, origin, dir, REACH) {
+                        let broken = self.world.block(hit.block);
+                        set_block(&mut self.world, &mut self.client, hit.block, AIR);
+                        self.inventory.add(broken, 1);
+                        #[cfg(feature = "scripting")]
+                        self.script.on_block_break(&mut self.world, hit.block, broken)
+                            .unwrap_or_else(|e| error!("{}", e));
+                    }
+                }
+                InputEvent::Click(MouseButton::Right) => {
+                    let target = raycast(&self.world, origin, dir, REACH)
+                        .and_then(|hit| hit.adjacent());
+                    if let Some(pos) = target {
+                        // don't place blocks inside the player
+                        let blocked = self.body
+                            .map(|body| {
+                                let body = body.aabb();
+                                let block = Aabb::block(pos).block_bounds();
+                                body.min.x < block.max.x && block.min.x < body.max.x
+                                    && body.min.y < block.max.y && block.min.y < body.max.y
+                                    && body.min.z < block.max.z && block.min.z < body.max.z
+                            })
+                            .unwrap_or(false);
+                        if !blocked {
+                            if let Some(block) = self.inventory.take_selected() {
+                                set_block(&mut self.world, &mut self.client, pos, block);
+                            }
+                        }
+                    }
+                }
+                InputEvent::Press(bind) if bind == self.binds.pause => {
+                    self.states.push(GameState::Paused);
+                    input.uncapture_mouse();
+                }
+                InputEvent::Press(bind) if bind == self.binds.console => {
+                    self.states.push(GameState::Console);
+                    input.uncapture_mouse();
+                    input.begin_text_input();
+                }
+                InputEvent::Press(bind) if bind == self.binds.toggle_inspector => {
+                    self.states.push(GameState::Inspector);
+                    input.uncapture_mouse();
+                }
+                InputEvent::Press(bind) => {
+                    if let Some(i) = self.binds.hotbar.iter().position(|&b| b == bind) {
+                        self.inventory.select(i);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // pause when the mouse is released some other way, such as by the
+        // window losing focus
+        if self.states.current() == GameState::Playing && input.state() != WindowState::Captured {
+            self.states.push(GameState::Paused);
         }
 
-        if input.state() == WindowState::Captured {
-        let cam_dir: &mut YawPitch<f32> = graphics.cam_dir_mut();
-        let look_speed: Angle<f32> = deg(0.1);
-        let mouse_movement: Vec2<f32> = input.mouse_captured_movement().map(|n| n as f32);
-        cam_dir.yaw += look_speed * mouse_movement.x;
-        cam_dir.yaw %= deg(360.0);
-        cam_dir.pitch += look_speed * mouse_movement.y;
-        cam_dir.pitch = cam_dir.pitch.clamp(deg(-90.0), deg(90.0));
-
-        let mut move_dir: Vec3<f32> = [0.0; 3].into();
-            let forward_dir = Vec3::new(
-                cam_dir.yaw.sin(), 
-                0.0, 
-                cam_dir.yaw.cos()
-            );
-            let right_dir = Vec3::new(
-                (cam_dir.yaw + deg(90.0)).sin(),
-                0.0,
-                (cam_dir.yaw + deg(90.0)).cos(), 
-            );
-            if input.is_pressed(move_forward) {
-                move_dir += forward_dir;
+        if let Some(line) = input.text_submitted() {
+            match Command::parse(line) {
+                Ok(Command::Teleport(pos)) => {
+                    self.cam_pos = pos;
+                    self.prev_cam_pos = pos;
+                    if let Some(ref mut body) = self.body {
+                        *body = player_body(pos);
+                    }
+                }
+                Ok(Command::Walk(walk)) => {
+                    if walk != self.body.is_some() {
+                        self.body = if walk { Some(player_body(self.cam_pos)) } else { None };
+                    }
+                }
+                Ok(Command::Quit) => self.exiting = true,
+                Err(e) => warn!("{}", e),
             }
-            if input.is_pressed(move_backward) {
-                move_dir -= forward_dir;
+            self.states.pop();
+            input.end_text_input();
+            input.capture_mouse();
+        }
+        let gameplay = self.states.current().input_mode() == InputMode::Gameplay;
+
+        for chunk in self.world.take_remesh() {
+            self.mesher.queue(&self.world, chunk);
+        }
+        // re-mesh chunks which came back within render distance
+        for event in self.chunk_events.try_iter() {
+            match event {
+                ChunkEvent::ChunkNeeded(chunk) => {
+                    if self.world.chunk(chunk).is_some() {
+                        self.mesher.queue(&self.world, chunk);
+                    }
+                }
+                // so that meshes finished after eviction aren't shown
+                ChunkEvent::ChunkEvicted(chunk) => self.mesher.cancel(chunk),
             }
-            if input.is_pressed(move_right) {
-                move_dir += right_dir;
+        }
+        self.mesher.maintain(graphics);
+
+        // scrolling down moves the hotbar selection right
+        if gameplay {
+            let scroll = input.mouse_scroll().y;
+            if scroll != 0.0 {
+                self.inventory.scroll(-scroll.signum() as i32);
             }
-            if input.is_pressed(move_left) {
-                move_dir -= right_dir;
+        }
+
+        // mouse look is applied per-frame, rather than per-tick, to avoid lag
+        if gameplay && input.state() == WindowState::Captured {
+            let cam_dir: &mut YawPitch<f32> = graphics.cam_dir_mut();
+            let look_speed: Angle<f32> = deg(self.settings.controls.mouse_sensitivity.get());
+            let mouse_movement: Vec2<f32> = input.mouse_captured_movement().map(|n| n as f32);
+            cam_dir.yaw += look_speed * mouse_movement.x;
+            cam_dir.pitch += look_speed * mouse_movement.y;
+            cam_dir.pitch = cam_dir.pitch.clamp(deg(-90.0), deg(90.0));
+        }
+
+        if input.events()
+            .iter()
+            .any(|&(event, _)| event == InputEvent::Press(self.binds.toggle_perf_overlay))
+        {
+            let show = &mut self.settings.graphics.show_perf_overlay;
+            *show = !*show;
+            graphics.show_perf_overlay(*show);
+            save_settings(&self.settings);
+        }
+
+        if input.events()
+            .iter()
+            .any(|&(event, _)| event == InputEvent::Press(self.binds.toggle_profiler))
+        {
+            self.profiler.set_enabled(!self.profiler.is_enabled());
+        }
+
+        if gameplay && input.events()
+            .iter()
+            .any(|&(event, _)| event == InputEvent::Press(self.binds.toggle_walk))
+        {
+            self.body = match self.body {
+                Some(_) => None,
+                None => Some(player_body(self.cam_pos)),
+            };
+        }
+
+        if input.fullscreen_toggled() {
+            graphics.toggle_fullscreen();
+            self.settings.graphics.fullscreen = graphics.fullscreen();
+            save_settings(&self.settings);
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, tick_dt: f32) -> Result<()> {
+        self.prev_cam_pos = self.cam_pos;
+
+        if let Some(ref mut timer) = self.asset_reload {
+            if timer.tick(tick_dt) > 0 {
+                self.assets.reload_modified();
             }
+        }
+
+        let state = self.states.current();
+        let gameplay = state.input_mode() == InputMode::Gameplay;
+        if gameplay && self.input.state() == WindowState::Captured {
+            let cam_dir = self.graphics.cam_dir();
+            let mut move_dir: Vec3<f32> = [0.0; 3].into();
+            // move horizontally, regardless of pitch
+            let flat_dir = YawPitch {
+                pitch: deg(0.0),
+                ..cam_dir
+            };
+            let forward_dir = flat_dir.to_forward_vec();
+            let right_dir = flat_dir.to_right_vec();
+            move_dir += forward_dir * self.input.axis(self.binds.move_forward) as f32;
+            move_dir += right_dir * self.input.axis(self.binds.move_right) as f32;
             if move_dir != [0.0; 3].into() {
                 move_dir.normalize();
             }
-            if input.is_pressed(move_up) {
-                move_dir.y += 1.0;
+
+            if let Some(ref mut body) = self.body {
+                let walk_speed: f32 = 5.0;
+                body.vel.x = move_dir.x * walk_speed;
+                body.vel.z = move_dir.z * walk_speed;
+                if self.input.axis(self.binds.move_up) > 0.0 {
+                    body.jump(9.0);
+                }
+            } else {
+                move_dir.y += self.input.axis(self.binds.move_up) as f32;
+                let move_speed: f32 = 17.5;
+                self.cam_pos += move_dir * move_speed * tick_dt;
             }
-            if input.is_pressed(move_down) {
-                move_dir.y -= 1.0;
+        } else if let Some(ref mut body) = self.body {
+            // stop walking, such as while typing in the console
+            body.vel.x = 0.0;
+            body.vel.z = 0.0;
+        }
+
+        if let Some(ref mut client) = self.client {
+            puffin::profile_scope!("network");
+            client.update(&mut self.world)?;
+            if let Some(spawn) = client.take_spawn() {
+                // the world now has the server's seed, so biomes are remeshed
+                // from it, including chunks which arrived first
+                self.mesher = Mesher::new(&self.blocks, self.world.seed());
+                for (chunk, _) in self.world.chunks() {
+                    self.mesher.queue(&self.world, chunk);
+                }
+                self.cam_pos = spawn + Vec3::new(0.0, EYE_HEIGHT, 0.0);
+                self.prev_cam_pos = self.cam_pos;
+                if let Some(ref mut body) = self.body {
+                    body.pos = spawn;
+                }
             }
-            let move_speed: f32 = 17.5;
-            *graphics.cam_pos_mut() += move_dir * move_speed * delta;
+            let feet = self.body
+                .map(|body| body.pos)
+                .unwrap_or(self.cam_pos - Vec3::new(0.0, EYE_HEIGHT, 0.0));
+            client.send_position(feet);
         }
 
-        if input.is_closing() {
+        // the world stops while paused, apart from what the server does
+        if !state.simulates() {
+            return self.world.maintain();
+        }
+
+        self.time.tick();
+        // timers are taken out while running, so they can borrow the game
+        let mut timers = mem::take(&mut self.timers);
+        timers.run(self.time.ticks(), self);
+        self.timers.merge(timers);
+
+        let (world, blocks) = (&self.world, &self.blocks);
+        let solid = |pos| blocks.is_solid(world.block(pos));
+        if let Some(ref mut body) = self.body {
+            let was_on_ground = body.on_ground;
+            let fall_speed = -body.vel.y;
+            body.move_and_slide(&solid, tick_dt);
+            self.cam_pos = body.pos + Vec3::new(0.0, EYE_HEIGHT, 0.0);
+
+            let effects = self.graphics.camera_effects_mut();
+            effects.set_movement(Vec2::new(body.vel.x, body.vel.z).magnitude(), body.on_ground);
+            if body.on_ground && !was_on_ground {
+                effects.add_landing(fall_speed);
+                if fall_speed > HARD_LANDING_SPEED {
+                    effects.add_shake(0.4);
+                }
+            }
+        } else {
+            self.graphics.camera_effects_mut().set_movement(0.0, false);
+        }
+        {
+            puffin::profile_scope!("mobs");
+            // mobs aren't synchronized, so they're only spawned in singleplayer
+            if self.client.is_none() {
+                self.mob_spawner.update(&mut self.ecs, &solid, &mut self.rng, self.cam_pos, tick_dt);
+            }
+            wander_system(&mut self.ecs, &solid, &mut self.rng, tick_dt);
+            movement_system(&mut self.ecs, tick_dt);
+            collision_system(&mut self.ecs, &solid, tick_dt);
+        }
+
+        #[cfg(feature = "scripting")]
+        {
+            puffin::profile_scope!("scripts");
+            // script errors are logged, rather than ending the game
+            self.script.on_tick(&mut self.world, tick_dt)
+                .unwrap_or_else(|e| error!("{}", e));
+            for pos in self.script.take_spawns() {
+                self.mob_spawner.spawn(&mut self.ecs, pos);
+            }
+        }
+
+        self.world.maintain()?;
+
+        Ok(())
+    }
+
+    fn render(&mut self, alpha: f32) -> Result<ControlFlow> {
+        self.assets.maintain();
+        for event in self.assets.take_events() {
+            match event {
+                AssetEvent::Loaded(_) => (),
+                AssetEvent::Reloaded(id) => {
+                    let block_texture = self.block_textures
+                        .iter()
+                        .find(|&&(handle, _)| handle.id() == id);
+                    if let Some(&(handle, ref name)) = block_texture {
+                        let texture = self.assets.get(handle).unwrap();
+                        match self.graphics.reload_block_texture(name, texture.0.clone()) {
+                            Ok(()) => info!("reloaded block texture {}", name),
+                            Err(e) => error!("failed to reload block texture {}:\n{}", name, e),
+                        }
+                    }
+                }
+                AssetEvent::Failed(id) => {
+                    let e = self.assets.error(id).unwrap();
+                    error!("failed to load asset {}:\n{}", self.assets.path(id).display(), e);
+                }
+            }
+        }
+
+        let graphics = &mut self.graphics;
+
+        if self.input.is_closing() || self.exiting {
             info!("exit by request");
             return Ok(ControlFlow::Exit);
         }
 
-        graphics.draw()?;
+        *graphics.cam_pos_mut() = lerp_vec3(self.prev_cam_pos, self.cam_pos, alpha);
+        graphics.set_time_of_day(self.time.time_of_day_at(alpha).rad() / (2.0 * PI));
 
-        let wait_until = start_time + (Duration::from_secs(1) / FPS);
-        Ok(ControlFlow::WaitUntil(wait_until))  
-    })
-}
+        // crosshair
+        let window_size = graphics.window().inner_size();
+        let center = Vec2::new(window_size.width as f32, window_size.height as f32) / 2.0;
+        let crosshair_color = Rgba::new(1.0, 1.0, 1.0, 0.8);
+        graphics.set_sprite(
+            CROSSHAIR_HORIZONTAL,
+            Rect::new(center.x - 10.0, center.y - 1.0, 20.0, 2.0),
+            WHITE_SPRITE_TEXTURE,
+            crosshair_color,
+        );
+        graphics.set_sprite(
+            CROSSHAIR_VERTICAL,
+            Rect::new(center.x - 1.0, center.y - 10.0, 2.0, 20.0),
+            WHITE_SPRITE_TEXTURE,
+            crosshair_color,
+        );
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-pub struct PosTex { pos: Vec3<i32>, tex: Vec2<i32> }
-
-macro_rules! pos_tex_dsl {
-    ($(
-    $pos_x_1:expr, $pos_y_1:expr, $pos_z_1:expr, $tex_u_1:expr, $tex_v_1:expr,
-    $pos_x_2:expr, $pos_y_2:expr, $pos_z_2:expr, $tex_u_2:expr, $tex_v_2:expr,
-    $pos_x_3:expr, $pos_y_3:expr, $pos_z_3:expr, $tex_u_3:expr, $tex_v_3:expr,
-    )*)=>{
-        [$(
-            [
-                PosTex {
-                    pos: Vec3 { x: $pos_x_1 as _, y: $pos_y_1 as _, z: $pos_z_1 as _ },
-                    tex: Vec2 { x: $tex_u_1 as _, y: $tex_v_1 as _ },
-                },
-                PosTex {
-                    pos: Vec3 { x: $pos_x_2 as _, y: $pos_y_2 as _, z: $pos_z_2 as _ },
-                    tex: Vec2 { x: $tex_u_2 as _, y: $tex_v_2 as _ },
-                },
-                PosTex {
-                    pos: Vec3 { x: $pos_x_3 as _, y: $pos_y_3 as _, z: $pos_z_3 as _ },
-                    tex: Vec2 { x: $tex_u_3 as _, y: $tex_v_3 as _ },
+        self.hotbar_hud.update(graphics, &self.inventory, &self.block_icons);
+        self.state_hud.update(graphics, self.states.current(), self.input.composition());
+
+        // the inspector takes input, whereas the flame graph is only shown
+        let inspecting = self.states.current() == GameState::Inspector;
+        if inspecting || self.profiler.is_enabled() {
+            puffin::profile_scope!("gui");
+            let window_size = Vec2::new(window_size.width, window_size.height);
+            let ctx = self.gui.begin_frame(&self.input, window_size, inspecting);
+            if inspecting {
+                let stats = InspectorStats {
+                    cam_pos: graphics.cam_pos(),
+                    cam_dir: graphics.cam_dir(),
+                    walking: self.body.is_some(),
+                    chunks: self.world.chunks().count(),
+                    dirty_chunks: self.world.dirty_count(),
+                    block_mesh: graphics.block_mesh_stats(),
+                    draw_calls: graphics.draw_calls(),
+                };
+                if inspector(ctx, &stats, &mut self.settings) {
+                    self.settings.graphics.apply(graphics);
+                    save_settings(&self.settings);
                 }
-            ],
-        )*]
-    };
+            }
+            self.profiler.flame_graph(ctx);
+            self.gui.end_frame(graphics, &mut self.input);
+        } else {
+            self.gui.hide(graphics, &mut self.input);
+        }
+
+        render_system(&mut self.ecs, graphics);
+        if let Some(ref mut client) = self.client {
+            let positions = client.player_positions();
+            // players borrow the mob model, which is a 0.8 block cube
+            let scale = Vec3::from(net::PLAYER_SIZE) / 0.8;
+            for &(id, pos) in &positions {
+                let transform = Transform {
+                    scale,
+                    ..Transform::from_pos(pos)
+                };
+                graphics.set_model_instance(
+                    REMOTE_PLAYER_INSTANCE_BASE + id as u64,
+                    self.player_model,
+                    transform.to_mat(),
+                );
+            }
+            let drawn = positions.iter().map(|&(id, _)| id).collect::<HashSet<_>>();
+            for &id in self.remote_players.difference(&drawn) {
+                graphics.remove_model_instance(REMOTE_PLAYER_INSTANCE_BASE + id as u64);
+            }
+            self.remote_players = drawn;
+        }
+        graphics.draw()?;
+
+        // cap the frame rate by delaying the next frame
+        let frame_start = self.last_frame;
+        self.last_frame = Instant::now();
+        Ok(match self.settings.graphics.fps_cap {
+            Some(fps_cap) if fps_cap > 0 => {
+                let next_frame = frame_start + Duration::from_secs(1) / fps_cap;
+                if next_frame > self.last_frame {
+                    self.last_frame = next_frame;
+                    ControlFlow::WaitUntil(next_frame)
+                } else {
+                    ControlFlow::Poll
+                }
+            }
+            _ => ControlFlow::Poll,
+        })
+    }
 }
 
-/// Boilerplate position and texture data for cube-shape blocks. 
-///
-/// 6 faces, with indices corresponding to `AxisUnit3::(to|from)_index`. 
-/// Each face is two triangles. Front faces are counter-clockwise. 
-pub const BLOCK_MESH_TEMPLATE: [[[PosTex; 3]; 2]; 6] = [
-    pos_tex_dsl![
-        // +X
-        1, 0, 0, 0, 1,
-        1, 0, 1, 1, 1,
-        1, 1, 0, 0, 0,
-        1, 1, 0, 0, 0,
-        1, 0, 1, 1, 1,
-        1, 1, 1, 1, 0,
-    ],
-    pos_tex_dsl![
-        // +Y
-        0, 1, 0, 0, 1,
-        1, 1, 0, 1, 1,
-        0, 1, 1, 0, 0,
-        0, 1, 1, 0, 0,
-        1, 1, 0, 1, 1,
-        1, 1, 1, 1, 0,
-    ],
-    pos_tex_dsl![
-        // +Z
-        1, 0, 1, 0, 1,
-        0, 0, 1, 1, 1,
-        1, 1, 1, 0, 0,
-        1, 1, 1, 0, 0,
-        0, 0, 1, 1, 1,
-        0, 1, 1, 1, 0,
-    ],
-    pos_tex_dsl![
-        // -X
-        0, 0, 1, 0, 1,
-        0, 0, 0, 1, 1,
-        0, 1, 1, 0, 0,
-        0, 1, 1, 0, 0,
-        0, 0, 0, 1, 1,
-        0, 1, 0, 1, 0,
-    ],
-    pos_tex_dsl![
-        // -Y
-        0, 0, 1, 0, 1,
-        1, 0, 1, 1, 1,
-        0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0,
-        1, 0, 1, 1, 1,
-        1, 0, 0, 1, 0,
-    ],
-    pos_tex_dsl![
-        // -Z
-        0, 0, 0, 0, 1,
-        1, 0, 0, 1, 1,
-        0, 1, 0, 0, 0,
-        0, 1, 0, 0, 0,
-        1, 0, 0, 1, 1,
-        1, 1, 0, 1, 0,
-    ],
-];
+/// Save settings after they were changed in-game, logging rather than
+/// propagating errors, since failing to save them shouldn't end the game. 
+fn save_settings(settings: &Settings) {
+    if let Err(e) = settings.save(SETTINGS_PATH) {
+        error!("failed to save settings:\n{}", e);
+    }
+}
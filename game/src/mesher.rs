@@ -0,0 +1,499 @@
+//! Meshing chunks on background threads. 
+//!
+//! Meshing a chunk reads every block in it, and the light around each block,
+//! which is too slow to do for many chunks in one frame, such as after a large
+//! edit or when a region comes within render distance. Instead, `Mesher`
+//! copies the blocks and light of each chunk to be meshed into a
+//! `ChunkSnapshot`, which worker threads turn into block meshes. Finished
+//! meshes are sent back over a channel, and applied to the graphics a limited
+//! number of chunks per frame, so that frame times stay stable. 
+
+use crate::{
+    graphics::{Graphics, DrawBlocksVertex},
+    util::{
+        axis_unit::AxisUnit3,
+        scheduler::TaskQueue,
+    },
+    world::{
+        BiomeMap,
+        BlockId,
+        BlockRegistry,
+        LightLevel,
+        World,
+        AIR,
+        CHUNK_SIZE,
+        MAX_LIGHT,
+    },
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc,
+        Arc,
+        Mutex,
+    },
+    thread,
+};
+use vek::*;
+
+/// Default number of worker threads. 
+pub const DEFAULT_WORKERS: usize = 2;
+
+/// Default number of chunk meshes applied per frame. 
+pub const DEFAULT_BUDGET: usize = 8;
+
+/// Side length of a chunk snapshot, which has a border of neighboring
+/// blocks, since meshes depend on the light around each block. 
+const SNAPSHOT_SIZE: i32 = CHUNK_SIZE + 2;
+
+/// Copy of the blocks and light in and around a chunk, which can be meshed
+/// without access to the world. 
+#[derive(Clone, Debug)]
+pub struct ChunkSnapshot {
+    chunk: Vec3<i32>,
+    // indexed x-major, then y, then z
+    blocks: Vec<BlockId>,
+    // none if the world isn't lit
+    light: Option<Vec<LightLevel>>,
+}
+
+impl ChunkSnapshot {
+    /// Copy a chunk, and the blocks bordering it, from the world. 
+    pub fn new(world: &World, chunk: Vec3<i32>) -> Self {
+        puffin::profile_function!();
+        let origin = chunk * CHUNK_SIZE - Vec3::one();
+        let positions = (0..SNAPSHOT_SIZE)
+            .flat_map(|x| (0..SNAPSHOT_SIZE)
+                .flat_map(move |y| (0..SNAPSHOT_SIZE)
+                    .map(move |z| origin + Vec3::new(x, y, z))));
+        ChunkSnapshot {
+            chunk,
+            blocks: positions.clone().map(|pos| world.block(pos)).collect(),
+            light: world.light().map(|light| positions.map(|pos| light.get(pos)).collect()),
+        }
+    }
+
+    /// Get the position of the chunk. 
+    pub fn chunk(&self) -> Vec3<i32> {
+        self.chunk
+    }
+
+    fn index(&self, pos: Vec3<i32>) -> usize {
+        let local = pos - self.chunk * CHUNK_SIZE + Vec3::one();
+        debug_assert!(local.iter().all(|&n| n >= 0 && n < SNAPSHOT_SIZE), "block not in snapshot");
+        ((local.x * SNAPSHOT_SIZE + local.y) * SNAPSHOT_SIZE + local.z) as usize
+    }
+
+    /// Get the block at a position in or bordering the chunk. 
+    pub fn block(&self, pos: Vec3<i32>) -> BlockId {
+        self.blocks[self.index(pos)]
+    }
+
+    /// Get the light at a position in or bordering the chunk, or none if the
+    /// world isn't lit. 
+    pub fn light(&self, pos: Vec3<i32>) -> Option<LightLevel> {
+        self.light.as_ref().map(|light| light[self.index(pos)])
+    }
+}
+
+/// Meshes of every block in a chunk. 
+#[derive(Clone, Debug)]
+pub struct ChunkMesh {
+    pub chunk: Vec3<i32>,
+    /// Each block's triangles, or none if it's air. 
+    pub blocks: Vec<(Vec3<i32>, Option<Vec<[DrawBlocksVertex; 3]>>)>,
+}
+
+impl ChunkMesh {
+    /// Set the block meshes in the graphics. 
+    pub fn apply(self, graphics: &mut Graphics) {
+        puffin::profile_function!();
+        for (pos, mesh) in self.blocks {
+            match mesh {
+                Some(mesh) => graphics.set_block_mesh(pos, mesh),
+                None => graphics.remove_block_mesh(pos),
+            }
+        }
+    }
+}
+
+/// Mesh every block in a chunk. 
+pub fn mesh_chunk(
+    snapshot: &ChunkSnapshot,
+    blocks: &BlockRegistry,
+    biomes: &BiomeMap,
+) -> ChunkMesh {
+    puffin::profile_function!();
+    let origin = snapshot.chunk() * CHUNK_SIZE;
+    let mut mesh = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize);
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let pos = origin + Vec3::new(x, y, z);
+                let block = snapshot.block(pos);
+                if block == AIR {
+                    mesh.push((pos, None));
+                    continue;
+                }
+                let def = blocks.get(block);
+                let face_tex = def.faces;
+                let top_tint = match def.tinted {
+                    true => biomes.grass_color(Vec2::new(pos.x, pos.z)),
+                    false => Rgb::white(),
+                };
+                let geom = BLOCK_MESH_TEMPLATE
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, face)| face.iter().map(move |prim| (i, prim)))
+                    .map(|(i, prim)| {
+                        prim.map(|vert| DrawBlocksVertex {
+                            pos: (vert.pos + pos).map(|n| n as f32),
+                            tex_coord: vert.tex.map(|n| n as f32),
+                            tex_index: face_tex[i],
+                            tint: match AxisUnit3::from_index(i) {
+                                AxisUnit3::UP => top_tint,
+                                _ => Rgb::white(),
+                            },
+                            light: vertex_light(
+                                snapshot,
+                                blocks,
+                                pos,
+                                AxisUnit3::from_index(i),
+                                vert.pos,
+                            ),
+                        })
+                    })
+                    .collect();
+                mesh.push((pos, Some(geom)));
+            }
+        }
+    }
+    ChunkMesh {
+        chunk: snapshot.chunk(),
+        blocks: mesh,
+    }
+}
+
+/// Get the light at a corner of a block's face, as skylight and block light
+/// from 0 to 1. 
+///
+/// This is the average light of the non-opaque blocks which touch the corner
+/// in front of the face, so that light is smooth across faces. 
+fn vertex_light(
+    snapshot: &ChunkSnapshot,
+    blocks: &BlockRegistry,
+    block: Vec3<i32>,
+    face: AxisUnit3,
+    corner: Vec3<i32>,
+) -> Vec2<f32> {
+    if snapshot.light.is_none() {
+        return Vec2::new(1.0, 0.0);
+    }
+    let normal = face.to_vec();
+    let mut sum = Vec2::zero();
+    let mut count = 0;
+    for x in corner.x - 1..=corner.x {
+        for y in corner.y - 1..=corner.y {
+            for z in corner.z - 1..=corner.z {
+                let offset = Vec3::new(x, y, z);
+                if offset * normal.map(i32::abs) != normal {
+                    continue;
+                }
+                let pos = block + offset;
+                if !blocks.get(snapshot.block(pos)).transparent {
+                    continue;
+                }
+                let level = snapshot.light(pos).unwrap();
+                sum += Vec2::new(level.sky, level.block).map(|n| n as f32);
+                count += 1;
+            }
+        }
+    }
+    if count == 0 {
+        return Vec2::zero();
+    }
+    sum / (count * MAX_LIGHT as i32) as f32
+}
+
+struct Job {
+    id: u64,
+    snapshot: ChunkSnapshot,
+}
+
+/// Meshes chunks on background threads. 
+pub struct Mesher {
+    jobs: mpsc::Sender<Job>,
+    done_recv: mpsc::Receiver<(u64, ChunkMesh)>,
+    // latest job of each chunk being meshed, so older results are dropped
+    meshing: HashMap<Vec3<i32>, u64>,
+    next_job: u64,
+    // finished meshes waiting to be applied, oldest first
+    ready: TaskQueue<ChunkMesh>,
+}
+
+impl Mesher {
+    /// Construct with `DEFAULT_WORKERS` worker threads, which mesh with the
+    /// given block types, and the biomes of the given world seed. 
+    pub fn new(blocks: &BlockRegistry, seed: u64) -> Self {
+        Mesher::with_workers(blocks, seed, DEFAULT_WORKERS)
+    }
+
+    /// Construct with a number of worker threads, which mesh with the given
+    /// block types, and the biomes of the given world seed. 
+    ///
+    /// Panics if `workers` is 0. 
+    pub fn with_workers(blocks: &BlockRegistry, seed: u64, workers: usize) -> Self {
+        assert!(workers > 0, "mesher needs at least one worker");
+        let blocks = Arc::new(blocks.clone());
+        let (jobs, jobs_recv) = mpsc::channel::<Job>();
+        let jobs_recv = Arc::new(Mutex::new(jobs_recv));
+        let (done_send, done_recv) = mpsc::channel();
+        for i in 0..workers {
+            let jobs_recv = jobs_recv.clone();
+            let done_send = done_send.clone();
+            let blocks = blocks.clone();
+            thread::Builder::new()
+                .name(format!("mesher {}", i))
+                .spawn(move || mesh_loop(jobs_recv, done_send, &blocks, BiomeMap::new(seed)))
+                .expect("failed to spawn mesher thread");
+        }
+        Mesher {
+            jobs,
+            done_recv,
+            meshing: HashMap::new(),
+            next_job: 0,
+            ready: TaskQueue::new(DEFAULT_BUDGET),
+        }
+    }
+
+    /// Set the maximum number of chunk meshes applied per frame. 
+    pub fn set_budget(&mut self, chunks: usize) {
+        self.ready.set_budget(chunks);
+    }
+
+    /// Start meshing a chunk, as it currently is in the world. 
+    ///
+    /// If the chunk is already being meshed, that mesh is discarded once it's
+    /// finished, since it's out of date. 
+    pub fn queue(&mut self, world: &World, chunk: Vec3<i32>) {
+        let id = self.next_job;
+        self.next_job += 1;
+        self.meshing.insert(chunk, id);
+        let snapshot = ChunkSnapshot::new(world, chunk);
+        // workers only exit when this is dropped
+        let _ = self.jobs.send(Job { id, snapshot });
+    }
+
+    /// Discard any meshes of a chunk which haven't been applied yet, such as
+    /// after it went out of render distance. 
+    pub fn cancel(&mut self, chunk: Vec3<i32>) {
+        self.meshing.remove(&chunk);
+        self.ready.retain(|mesh| mesh.chunk != chunk);
+    }
+
+    /// Get the number of chunks which are being meshed, or whose meshes
+    /// haven't been applied yet. 
+    pub fn pending(&self) -> usize {
+        self.meshing.len() + self.ready.len()
+    }
+
+    fn finish(&mut self, id: u64, mesh: ChunkMesh) {
+        if self.meshing.get(&mesh.chunk) == Some(&id) {
+            self.meshing.remove(&mesh.chunk);
+            self.ready.push(mesh);
+        }
+    }
+
+    /// Receive finished meshes, and apply up to the budget of them to the
+    /// graphics. Return how many were applied. 
+    ///
+    /// This should be called once per frame. 
+    pub fn maintain(&mut self, graphics: &mut Graphics) -> usize {
+        puffin::profile_function!();
+        while let Ok((id, mesh)) = self.done_recv.try_recv() {
+            self.finish(id, mesh);
+        }
+        self.ready.run(|mesh| mesh.apply(graphics))
+    }
+
+    /// Block until every queued chunk is meshed, then apply them all to the
+    /// graphics, regardless of the budget. 
+    pub fn flush(&mut self, graphics: &mut Graphics) {
+        puffin::profile_function!();
+        while !self.meshing.is_empty() {
+            // never disconnects, since workers only exit when this is dropped
+            let (id, mesh) = self.done_recv.recv().unwrap();
+            self.finish(id, mesh);
+        }
+        self.ready.run_all(|mesh| mesh.apply(graphics));
+    }
+}
+
+/// Worker thread loop, which exits once the `Mesher` is dropped. 
+fn mesh_loop(
+    jobs: Arc<Mutex<mpsc::Receiver<Job>>>,
+    done: mpsc::Sender<(u64, ChunkMesh)>,
+    blocks: &BlockRegistry,
+    biomes: BiomeMap,
+) {
+    loop {
+        let job = match jobs.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(mpsc::RecvError) => return,
+        };
+        let mesh = mesh_chunk(&job.snapshot, blocks, &biomes);
+        if done.send((job.id, mesh)).is_err() {
+            return;
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PosTex { pos: Vec3<i32>, tex: Vec2<i32> }
+
+macro_rules! pos_tex_dsl {
+    ($(
+    $pos_x_1:expr, $pos_y_1:expr, $pos_z_1:expr, $tex_u_1:expr, $tex_v_1:expr,
+    $pos_x_2:expr, $pos_y_2:expr, $pos_z_2:expr, $tex_u_2:expr, $tex_v_2:expr,
+    $pos_x_3:expr, $pos_y_3:expr, $pos_z_3:expr, $tex_u_3:expr, $tex_v_3:expr,
+    )*)=>{
+        [$(
+            [
+                PosTex {
+                    pos: Vec3 { x: $pos_x_1 as _, y: $pos_y_1 as _, z: $pos_z_1 as _ },
+                    tex: Vec2 { x: $tex_u_1 as _, y: $tex_v_1 as _ },
+                },
+                PosTex {
+                    pos: Vec3 { x: $pos_x_2 as _, y: $pos_y_2 as _, z: $pos_z_2 as _ },
+                    tex: Vec2 { x: $tex_u_2 as _, y: $tex_v_2 as _ },
+                },
+                PosTex {
+                    pos: Vec3 { x: $pos_x_3 as _, y: $pos_y_3 as _, z: $pos_z_3 as _ },
+                    tex: Vec2 { x: $tex_u_3 as _, y: $tex_v_3 as _ },
+                }
+            ],
+        )*]
+    };
+}
+
+/// Boilerplate position and texture data for cube-shape blocks. 
+///
+/// 6 faces, with indices corresponding to `AxisUnit3::(to|from)_index`. 
+/// Each face is two triangles. Front faces are counter-clockwise. 
+pub const BLOCK_MESH_TEMPLATE: [[[PosTex; 3]; 2]; 6] = [
+    pos_tex_dsl![
+        // +X
+        1, 0, 0, 0, 1,
+        1, 0, 1, 1, 1,
+        1, 1, 0, 0, 0,
+        1, 1, 0, 0, 0,
+        1, 0, 1, 1, 1,
+        1, 1, 1, 1, 0,
+    ],
+    pos_tex_dsl![
+        // +Y
+        0, 1, 0, 0, 1,
+        1, 1, 0, 1, 1,
+        0, 1, 1, 0, 0,
+        0, 1, 1, 0, 0,
+        1, 1, 0, 1, 1,
+        1, 1, 1, 1, 0,
+    ],
+    pos_tex_dsl![
+        // +Z
+        1, 0, 1, 0, 1,
+        0, 0, 1, 1, 1,
+        1, 1, 1, 0, 0,
+        1, 1, 1, 0, 0,
+        0, 0, 1, 1, 1,
+        0, 1, 1, 1, 0,
+    ],
+    pos_tex_dsl![
+        // -X
+        0, 0, 1, 0, 1,
+        0, 0, 0, 1, 1,
+        0, 1, 1, 0, 0,
+        0, 1, 1, 0, 0,
+        0, 0, 0, 1, 1,
+        0, 1, 0, 1, 0,
+    ],
+    pos_tex_dsl![
+        // -Y
+        0, 0, 1, 0, 1,
+        1, 0, 1, 1, 1,
+        0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0,
+        1, 0, 1, 1, 1,
+        1, 0, 0, 1, 0,
+    ],
+    pos_tex_dsl![
+        // -Z
+        0, 0, 0, 0, 1,
+        1, 0, 0, 1, 1,
+        0, 1, 0, 0, 0,
+        0, 1, 0, 0, 0,
+        1, 0, 0, 1, 1,
+        1, 1, 0, 1, 0,
+    ],
+];
+
+
+#[test]
+fn mesh_chunk_test() {
+    use crate::world::BlockDef;
+
+    let mut blocks = BlockRegistry::new();
+    for &(name, tinted) in &[("stone", false), ("grass", true)] {
+        blocks
+            .register(BlockDef {
+                name: name.to_string(),
+                faces: [1, 2, 3, 4, 5, 6],
+                solid: true,
+                transparent: false,
+                hardness: 1.0,
+                light: 0,
+                tinted,
+            })
+            .unwrap();
+    }
+    let stone = blocks.id("stone").unwrap();
+    let grass = blocks.id("grass").unwrap();
+    let mut world = World::new();
+    world.set_block(Vec3::new(0, 0, 0), grass);
+    world.set_block(Vec3::new(0, -1, 0), stone);
+    world.set_block(Vec3::new(CHUNK_SIZE, 0, 0), stone);
+    world.enable_light(&blocks);
+
+    // snapshots include the bordering blocks
+    let snapshot = ChunkSnapshot::new(&world, Vec3::zero());
+    for &pos in &[Vec3::new(0, 0, 0), Vec3::new(0, -1, 0), Vec3::new(CHUNK_SIZE, 0, 0)] {
+        assert_eq!(snapshot.block(pos), world.block(pos));
+        assert_eq!(snapshot.light(pos), world.light().map(|light| light.get(pos)));
+    }
+
+    let biomes = BiomeMap::new(world.seed());
+    let mesh = mesh_chunk(&snapshot, &blocks, &biomes);
+    assert_eq!(mesh.blocks.len(), (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize);
+    let (_, ref grass_mesh) = mesh.blocks
+        .iter()
+        .find(|&&(pos, _)| pos == Vec3::zero())
+        .unwrap();
+    let grass_mesh = grass_mesh.as_ref().unwrap();
+    assert_eq!(grass_mesh.len(), 12);
+    assert!(mesh.blocks.iter().filter(|&&(pos, _)| pos != Vec3::zero()).all(|(_, mesh)| mesh.is_none()));
+    for (i, tri) in grass_mesh.iter().enumerate() {
+        let face = AxisUnit3::from_index(i / 2);
+        for vert in tri {
+            assert_eq!(vert.tex_index, i as u32 / 2 + 1);
+            assert!(vert.light.iter().all(|&n| n >= 0.0 && n <= 1.0));
+            match face {
+                AxisUnit3::UP => {
+                    assert_eq!(vert.tint, biomes.grass_color(Vec2::zero()));
+                    // the top face is exposed to the sky
+                    assert_eq!(vert.light.x, 1.0);
+                }
+                _ => assert_eq!(vert.tint, Rgb::white()),
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Multiplayer client. 
+
+use super::*;
+use crate::world::{
+    World,
+    save::decompress_chunk,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{SocketAddr, ToSocketAddrs},
+    time::Instant,
+};
+use laminar::{Socket, SocketEvent};
+
+/// Recent positions of a moving object, which can be sampled at any time
+/// between them by interpolating. 
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotBuffer {
+    // in increasing time order
+    snapshots: VecDeque<(Instant, Vec3<f32>)>,
+}
+
+impl SnapshotBuffer {
+    /// Construct with no snapshots. 
+    pub fn new() -> Self {
+        SnapshotBuffer::default()
+    }
+
+    /// Add a snapshot. Snapshots older than the latest one are ignored. 
+    pub fn push(&mut self, time: Instant, pos: Vec3<f32>) {
+        if self.snapshots.back().map(|&(last, _)| time >= last).unwrap_or(true) {
+            self.snapshots.push_back((time, pos));
+        }
+    }
+
+    /// Get the position at a time, by interpolating between the snapshots
+    /// around it, or clamping to the first or last snapshot. 
+    ///
+    /// Snapshots which are no longer needed to sample at that time, or later
+    /// times, are discarded. 
+    pub fn sample(&mut self, time: Instant) -> Option<Vec3<f32>> {
+        while self.snapshots.len() > 2 && self.snapshots[1].0 <= time {
+            self.snapshots.pop_front();
+        }
+        let &(t0, p0) = self.snapshots.front()?;
+        let (t1, p1) = match self.snapshots.get(1) {
+            Some(&next) if time > t0 => next,
+            _ => return Some(p0),
+        };
+        if time >= t1 || t1 == t0 {
+            return Some(p1);
+        }
+        let alpha = (time - t0).as_secs_f32() / (t1 - t0).as_secs_f32();
+        Some(p0 + (p1 - p0) * alpha)
+    }
+}
+
+/// Another player, as seen by a client. 
+#[derive(Debug, Clone)]
+pub struct RemotePlayer {
+    /// Name the player connected with. 
+    pub name: String,
+    /// Recent positions of the bottom center of the player's body. 
+    pub snapshots: SnapshotBuffer,
+}
+
+/// Connection to a server. 
+pub struct Client {
+    socket: Socket,
+    server: SocketAddr,
+    id: Option<PlayerId>,
+    spawn: Option<Vec3<f32>>,
+    players: HashMap<PlayerId, RemotePlayer>,
+    last_sync: Instant,
+}
+
+impl Client {
+    /// Begin connecting to a server. 
+    pub fn connect<A: ToSocketAddrs>(addr: A, name: &str) -> Result<Self> {
+        let server = addr.to_socket_addrs()
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "failed to resolve server address"))?
+            .next()
+            .ok_or_else(|| pear!({}, "server address resolved to nothing"))?;
+        let local: SocketAddr = if server.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = Socket::bind_with_config(local, socket_config())
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "failed to bind client socket"))?;
+        let mut client = Client {
+            socket,
+            server,
+            id: None,
+            spawn: None,
+            players: HashMap::new(),
+            last_sync: Instant::now(),
+        };
+        client.send(ClientMsg::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_owned(),
+        });
+        Ok(client)
+    }
+
+    /// Get the server's address. 
+    pub fn server_addr(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Get this client's player ID, once the server has accepted it. 
+    pub fn id(&self) -> Option<PlayerId> {
+        self.id
+    }
+
+    /// Take the position the server told the player to start at, once the
+    /// server has accepted it. 
+    ///
+    /// By then, the world has the server's seed. 
+    pub fn take_spawn(&mut self) -> Option<Vec3<f32>> {
+        self.spawn.take()
+    }
+
+    /// Get the other connected players. 
+    pub fn players(&self) -> &HashMap<PlayerId, RemotePlayer> {
+        &self.players
+    }
+
+    /// Get the other players' positions at the current time, delayed by
+    /// `INTERP_DELAY`. 
+    pub fn player_positions(&mut self) -> Vec<(PlayerId, Vec3<f32>)> {
+        let time = Instant::now() - INTERP_DELAY;
+        self.players.iter_mut()
+            .filter_map(|(&id, player)| player.snapshots.sample(time).map(|pos| (id, pos)))
+            .collect()
+    }
+
+    /// Handle messages from the server, applying world changes to a world. 
+    ///
+    /// Errors if the server refused the connection or stopped responding. 
+    pub fn update(&mut self, world: &mut World) -> Result<()> {
+        self.socket.manual_poll(Instant::now());
+        while let Some(event) = self.socket.recv() {
+            match event {
+                SocketEvent::Packet(packet) => {
+                    if packet.addr() != self.server {
+                        continue;
+                    }
+                    let msg = decode::<ServerMsg>(packet.payload())
+                        .wrap_err(|| pear!({}, "invalid message from server"))?;
+                    self.handle(world, msg)?;
+                }
+                SocketEvent::Timeout(addr) | SocketEvent::Disconnect(addr)
+                    if addr == self.server =>
+                {
+                    return Err(pear!({server=self.server}, "lost connection to server"));
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the server the player's position, at most every `SYNC_INTERVAL`. 
+    pub fn send_position(&mut self, pos: Vec3<f32>) {
+        if self.id.is_some() && self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.last_sync = Instant::now();
+            self.send(ClientMsg::Move { pos });
+        }
+    }
+
+    /// Change a block, both in the local world immediately and on the
+    /// server, which may refuse and correct it. 
+    pub fn set_block(&mut self, world: &mut World, pos: Vec3<i32>, block: BlockId) {
+        world.set_block(pos, block);
+        self.send(ClientMsg::SetBlock { pos, block });
+    }
+
+    fn handle(&mut self, world: &mut World, msg: ServerMsg) -> Result<()> {
+        match msg {
+            ServerMsg::Welcome { id, spawn, seed } => {
+                info!("joined server {} as player {}", self.server, id);
+                self.id = Some(id);
+                self.spawn = Some(spawn);
+                world.set_seed(seed);
+            }
+            ServerMsg::Rejected { reason } => {
+                return Err(pear!({server=self.server, reason=reason}, "server refused connection"));
+            }
+            ServerMsg::Chunk { pos, data } => {
+                let chunk = match data {
+                    Some(data) => Some(decompress_chunk(&data)
+                        .wrap_err(|| pear!({pos=pos}, "invalid chunk from server"))?),
+                    None => None,
+                };
+                world.replace_chunk(pos, chunk);
+            }
+            ServerMsg::UnloadChunk { pos } => world.replace_chunk(pos, None),
+            ServerMsg::SetBlock { pos, block } => {
+                world.set_block(pos, block);
+            }
+            ServerMsg::PlayerJoined { id, name } => {
+                info!("player {:?} joined", name);
+                self.players.insert(id, RemotePlayer {
+                    name,
+                    snapshots: SnapshotBuffer::new(),
+                });
+            }
+            ServerMsg::PlayerLeft { id } => {
+                if let Some(player) = self.players.remove(&id) {
+                    info!("player {:?} left", player.name);
+                }
+            }
+            ServerMsg::PlayerMoved { id, pos } => {
+                if let Some(player) = self.players.get_mut(&id) {
+                    player.snapshots.push(Instant::now(), pos);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, msg: ClientMsg) {
+        if let Err(e) = self.socket.send(msg.packet(self.server)) {
+            warn!("failed to send to server: {}", e);
+        }
+    }
+}
+
+#[test]
+fn snapshot_buffer_test() {
+    use std::time::Duration;
+
+    let t = Instant::now();
+    let ms = |n| t + Duration::from_millis(n);
+    let mut buf = SnapshotBuffer::new();
+    assert_eq!(buf.sample(t), None);
+
+    buf.push(ms(0), Vec3::new(0.0, 0.0, 0.0));
+    assert_eq!(buf.sample(ms(50)), Some(Vec3::new(0.0, 0.0, 0.0)));
+    buf.push(ms(100), Vec3::new(10.0, 0.0, 0.0));
+    buf.push(ms(200), Vec3::new(10.0, 20.0, 0.0));
+    // out of order snapshots are ignored
+    buf.push(ms(150), Vec3::new(-100.0, 0.0, 0.0));
+
+    let close = |a: Option<Vec3<f32>>, b: Vec3<f32>| (a.unwrap() - b).magnitude() < 1e-3;
+    assert!(close(buf.sample(ms(50)), Vec3::new(5.0, 0.0, 0.0)));
+    assert!(close(buf.sample(ms(150)), Vec3::new(10.0, 10.0, 0.0)));
+    // samples past the last snapshot hold still
+    assert!(close(buf.sample(ms(500)), Vec3::new(10.0, 20.0, 0.0)));
+    // older snapshots are discarded once passed
+    assert_eq!(buf.snapshots.len(), 2);
+}
@@ -0,0 +1,219 @@
+//! Client/server multiplayer over UDP, using laminar for reliability. 
+//!
+//! The server owns the world. It streams chunks to each client in order of
+//! distance from the player, relays block changes after validating them,
+//! and broadcasts player positions, which clients interpolate between. 
+//!
+//! Messages are serialized with bincode, and sent on one of several laminar
+//! streams, so that, for example, a lost position update never delays a
+//! block change:
+//!
+//! - World messages, such as chunks and block changes, are reliable and
+//!   ordered with respect to each other, so a block change is never applied
+//!   before the chunk containing it. 
+//! - Player join and leave messages are reliable and ordered. 
+//! - Position updates are unreliable, and older ones are dropped. 
+
+use crate::world::BlockId;
+use std::{
+    net::SocketAddr,
+    time::Duration,
+};
+use laminar::{Packet, Config};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use vek::*;
+use pear::*;
+
+pub mod server;
+pub mod client;
+
+pub use self::{
+    server::Server,
+    client::Client,
+};
+
+/// Default UDP port of servers. 
+pub const DEFAULT_PORT: u16 = 28_565;
+
+/// Protocol version, which clients and servers must agree on. 
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Size of player bodies, which the server keeps blocks from being placed
+/// inside of. 
+pub const PLAYER_SIZE: [f32; 3] = [0.6, 1.8, 0.6];
+
+/// Maximum distance from a player's position at which the server allows
+/// them to change blocks. 
+pub const MAX_REACH: f32 = 8.0;
+
+/// Interval between position updates, from clients and from the server. 
+pub const SYNC_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How far in the past remote players are shown, so that there's usually a
+/// position update on both sides of the time being shown to interpolate
+/// between. 
+pub const INTERP_DELAY: Duration = Duration::from_millis(100);
+
+/// Maximum size of a message, in bytes. 
+pub const MAX_MESSAGE_SIZE: u64 = 1 << 16;
+
+const STREAM_WORLD: u8 = 0;
+const STREAM_PLAYERS: u8 = 1;
+const STREAM_MOVE: u8 = 2;
+
+/// Identifier of a connected player, unique for the server's lifetime. 
+pub type PlayerId = u32;
+
+/// Message from a client to the server. 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClientMsg {
+    /// First message of a connection. 
+    Hello {
+        version: u32,
+        name: String,
+    },
+    /// The player's current position, which is the bottom center of their
+    /// body. 
+    Move {
+        pos: Vec3<f32>,
+    },
+    /// Break or place a block. 
+    SetBlock {
+        pos: Vec3<i32>,
+        block: BlockId,
+    },
+}
+
+/// Message from the server to a client. 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ServerMsg {
+    /// Response to a `Hello` which was accepted. 
+    Welcome {
+        id: PlayerId,
+        spawn: Vec3<f32>,
+        /// Seed of the server's world, which biomes derive from. 
+        seed: u64,
+    },
+    /// Response to a `Hello` which was refused. 
+    Rejected {
+        reason: String,
+    },
+    /// Contents of a chunk, compressed with `save::compress_chunk`, or
+    /// `None` if it's empty. 
+    Chunk {
+        pos: Vec3<i32>,
+        data: Option<Vec<u8>>,
+    },
+    /// A chunk went out of view, and the client may forget it. 
+    UnloadChunk {
+        pos: Vec3<i32>,
+    },
+    /// A block changed, or the client's change to it was refused. 
+    SetBlock {
+        pos: Vec3<i32>,
+        block: BlockId,
+    },
+    /// Another player connected. 
+    PlayerJoined {
+        id: PlayerId,
+        name: String,
+    },
+    /// Another player disconnected. 
+    PlayerLeft {
+        id: PlayerId,
+    },
+    /// Another player's position. 
+    PlayerMoved {
+        id: PlayerId,
+        pos: Vec3<f32>,
+    },
+}
+
+impl ClientMsg {
+    /// Serialize into a packet to the server, on the appropriate stream. 
+    pub fn packet(&self, addr: SocketAddr) -> Packet {
+        let payload = encode(self);
+        match *self {
+            ClientMsg::Move { .. } => {
+                Packet::unreliable_sequenced(addr, payload, Some(STREAM_MOVE))
+            }
+            ClientMsg::Hello { .. } => {
+                Packet::reliable_ordered(addr, payload, Some(STREAM_PLAYERS))
+            }
+            ClientMsg::SetBlock { .. } => {
+                Packet::reliable_ordered(addr, payload, Some(STREAM_WORLD))
+            }
+        }
+    }
+}
+
+impl ServerMsg {
+    /// Serialize into a packet to a client, on the appropriate stream. 
+    pub fn packet(&self, addr: SocketAddr) -> Packet {
+        let payload = encode(self);
+        match *self {
+            ServerMsg::PlayerMoved { .. } => {
+                Packet::unreliable_sequenced(addr, payload, Some(STREAM_MOVE))
+            }
+            ServerMsg::Welcome { .. }
+            | ServerMsg::Rejected { .. }
+            | ServerMsg::PlayerJoined { .. }
+            | ServerMsg::PlayerLeft { .. } => {
+                Packet::reliable_ordered(addr, payload, Some(STREAM_PLAYERS))
+            }
+            ServerMsg::Chunk { .. }
+            | ServerMsg::UnloadChunk { .. }
+            | ServerMsg::SetBlock { .. } => {
+                Packet::reliable_ordered(addr, payload, Some(STREAM_WORLD))
+            }
+        }
+    }
+}
+
+/// Serialize a message. 
+pub fn encode<T: Serialize>(msg: &T) -> Vec<u8> {
+    // serializing into a vec can't fail
+    bincode::serialize(msg).unwrap()
+}
+
+/// Deserialize a message, rejecting ones over `MAX_MESSAGE_SIZE`. 
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    use bincode::Options;
+
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(MAX_MESSAGE_SIZE)
+        .deserialize(bytes)
+        .map_err(Error::from)
+        .wrap_err(|| pear!({len=bytes.len()}, "invalid message"))
+}
+
+/// Laminar socket configuration used by both clients and servers. 
+fn socket_config() -> Config {
+    Config {
+        idle_connection_timeout: Duration::from_secs(10),
+        heartbeat_interval: Some(Duration::from_secs(1)),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn message_roundtrip_test() {
+    let msgs = vec![
+        ServerMsg::Welcome { id: 3, spawn: Vec3::new(0.5, 10.0, -2.0), seed: 42 },
+        ServerMsg::Chunk { pos: Vec3::new(-1, 0, 2), data: Some(vec![1, 2, 3]) },
+        ServerMsg::SetBlock { pos: Vec3::new(5, -6, 7), block: 9 },
+    ];
+    for msg in msgs {
+        assert_eq!(decode::<ServerMsg>(&encode(&msg)).unwrap(), msg);
+    }
+    let msg = ClientMsg::Hello { version: PROTOCOL_VERSION, name: "steve".into() };
+    assert_eq!(decode::<ClientMsg>(&encode(&msg)).unwrap(), msg);
+
+    assert!(decode::<ServerMsg>(&[0xff; 3]).is_err());
+    // a huge length prefix doesn't cause a huge allocation
+    let mut huge = encode(&ClientMsg::Hello { version: 1, name: String::new() });
+    huge.truncate(huge.len() - 8);
+    huge.extend_from_slice(&u64::MAX.to_le_bytes());
+    assert!(decode::<ClientMsg>(&huge).is_err());
+}
@@ -0,0 +1,280 @@
+//! Multiplayer server, which can run without graphics. 
+
+use super::*;
+use crate::{
+    world::{
+        World,
+        BlockRegistry,
+        block_chunk,
+        save::compress_chunk,
+    },
+    util::geom::Aabb,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{SocketAddr, ToSocketAddrs},
+    time::Instant,
+};
+use laminar::{Socket, SocketEvent};
+
+/// Default view distance, in chunks. 
+pub const DEFAULT_VIEW_DISTANCE: i32 = 6;
+
+/// Default maximum number of chunks sent to each client per update. 
+pub const DEFAULT_CHUNKS_PER_UPDATE: usize = 8;
+
+/// A connected client. 
+#[derive(Debug)]
+struct Player {
+    id: PlayerId,
+    name: String,
+    pos: Vec3<f32>,
+    // chunks which the client has been sent
+    sent: HashSet<Vec3<i32>>,
+}
+
+impl Player {
+    /// Whether the player's body overlaps a box, not including touching. 
+    fn overlaps(&self, other: &Aabb<f32>) -> bool {
+        let size = Vec3::from(PLAYER_SIZE);
+        let min = self.pos - Vec3::new(size.x / 2.0, 0.0, size.z / 2.0);
+        let max = min + size;
+        (0..3).all(|i| min[i] < other.max[i] && other.min[i] < max[i])
+    }
+}
+
+/// Server which owns a world and synchronizes it with clients. 
+pub struct Server {
+    socket: Socket,
+    world: World,
+    blocks: BlockRegistry,
+    players: HashMap<SocketAddr, Player>,
+    next_id: PlayerId,
+    last_sync: Instant,
+    /// Distance, in chunks, around each player within which chunks are sent
+    /// to them. 
+    pub view_distance: i32,
+    /// Maximum number of chunks sent to each client per update, so that
+    /// joining doesn't flood the network. 
+    pub chunks_per_update: usize,
+    /// Position which players start at. 
+    pub spawn: Vec3<f32>,
+}
+
+impl Server {
+    /// Listen on an address, serving a world. 
+    pub fn bind<A: ToSocketAddrs>(addr: A, world: World, blocks: BlockRegistry) -> Result<Self> {
+        let socket = Socket::bind_with_config(addr, socket_config())
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "failed to bind server socket"))?;
+        Ok(Server {
+            socket,
+            world,
+            blocks,
+            players: HashMap::new(),
+            next_id: 0,
+            last_sync: Instant::now(),
+            view_distance: DEFAULT_VIEW_DISTANCE,
+            chunks_per_update: DEFAULT_CHUNKS_PER_UPDATE,
+            spawn: Vec3::new(0.0, 30.0, 0.0),
+        })
+    }
+
+    /// Get the address the server is listening on. 
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr().map_err(Error::from)
+    }
+
+    /// Get the world. 
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Get the number of connected players. 
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    /// Handle received messages and disconnects, send chunks and positions,
+    /// and flush the world. This should be called regularly, such as once
+    /// per tick. 
+    pub fn update(&mut self) -> Result<()> {
+        self.socket.manual_poll(Instant::now());
+        while let Some(event) = self.socket.recv() {
+            match event {
+                SocketEvent::Packet(packet) => {
+                    let addr = packet.addr();
+                    match decode::<ClientMsg>(packet.payload()) {
+                        Ok(msg) => self.handle(addr, msg),
+                        Err(e) => warn!("from {}:\n{}", addr, e),
+                    }
+                }
+                SocketEvent::Timeout(addr) | SocketEvent::Disconnect(addr) => {
+                    self.disconnect(addr);
+                }
+                SocketEvent::Connect(_) => (),
+            }
+        }
+
+        self.stream_chunks();
+        if self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.last_sync = Instant::now();
+            self.sync_positions();
+        }
+        self.world.maintain()
+    }
+
+    fn handle(&mut self, addr: SocketAddr, msg: ClientMsg) {
+        match msg {
+            ClientMsg::Hello { version, name } => self.connect(addr, version, name),
+            ClientMsg::Move { pos } => {
+                if let Some(player) = self.players.get_mut(&addr) {
+                    if pos.iter().all(|n| n.is_finite()) {
+                        player.pos = pos;
+                    }
+                }
+            }
+            ClientMsg::SetBlock { pos, block } => self.set_block(addr, pos, block),
+        }
+    }
+
+    fn connect(&mut self, addr: SocketAddr, version: u32, name: String) {
+        if self.players.contains_key(&addr) {
+            return;
+        }
+        if version != PROTOCOL_VERSION {
+            let reason = format!(
+                "protocol version {} is not supported, server uses {}",
+                version, PROTOCOL_VERSION,
+            );
+            self.send(ServerMsg::Rejected { reason }.packet(addr));
+            return;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        info!("player {:?} joined from {} as {}", name, addr, id);
+        let seed = self.world.seed();
+        self.send(ServerMsg::Welcome { id, spawn: self.spawn, seed }.packet(addr));
+        let others = self.players.iter()
+            .map(|(&other_addr, other)| (other_addr, other.id, other.name.clone()))
+            .collect::<Vec<_>>();
+        for (other_addr, other_id, other_name) in others {
+            self.send(ServerMsg::PlayerJoined { id, name: name.clone() }.packet(other_addr));
+            self.send(ServerMsg::PlayerJoined { id: other_id, name: other_name }.packet(addr));
+        }
+        self.players.insert(addr, Player {
+            id,
+            name,
+            pos: self.spawn,
+            sent: HashSet::new(),
+        });
+    }
+
+    fn disconnect(&mut self, addr: SocketAddr) {
+        if let Some(player) = self.players.remove(&addr) {
+            info!("player {:?} ({}) left", player.name, player.id);
+            self.broadcast(&ServerMsg::PlayerLeft { id: player.id });
+        }
+    }
+
+    fn set_block(&mut self, addr: SocketAddr, pos: Vec3<i32>, block: BlockId) {
+        let player = match self.players.get(&addr) {
+            Some(player) => player,
+            None => return,
+        };
+        let bounds = Aabb::block(pos).block_bounds();
+        let in_reach = bounds.center().distance(player.pos) <= MAX_REACH;
+        let known = (block as usize) < self.blocks.len();
+        // don't place solid blocks inside players
+        let blocked = self.blocks.is_solid(block) && self.players
+            .values()
+            .any(|other| other.overlaps(&bounds));
+        if !in_reach || !known || blocked {
+            // correct the client's optimistic change
+            let current = self.world.block(pos);
+            self.send(ServerMsg::SetBlock { pos, block: current }.packet(addr));
+            return;
+        }
+
+        self.world.set_block(pos, block);
+        let chunk = block_chunk(pos);
+        let msg = ServerMsg::SetBlock { pos, block };
+        let addrs = self.players.iter()
+            .filter(|&(_, player)| player.sent.contains(&chunk))
+            .map(|(&addr, _)| addr)
+            .collect::<Vec<_>>();
+        for addr in addrs {
+            self.send(msg.packet(addr));
+        }
+    }
+
+    /// Send each client the nearest chunks in view they don't have yet, and
+    /// unload ones which went out of view. 
+    fn stream_chunks(&mut self) {
+        let mut packets = Vec::new();
+        for (&addr, player) in &mut self.players {
+            let center = block_chunk(player.pos.map(|n| n.floor() as i32));
+            let r = self.view_distance;
+            let in_view = |chunk: Vec3<i32>| (chunk - center).map(i32::abs).reduce_max() <= r;
+
+            let unload = player.sent.iter()
+                .copied()
+                .filter(|&chunk| !in_view(chunk))
+                .collect::<Vec<_>>();
+            for pos in unload {
+                player.sent.remove(&pos);
+                packets.push(ServerMsg::UnloadChunk { pos }.packet(addr));
+            }
+
+            let mut missing = Vec::new();
+            for x in -r..=r {
+                for y in -r..=r {
+                    for z in -r..=r {
+                        let chunk = center + Vec3::new(x, y, z);
+                        if !player.sent.contains(&chunk) {
+                            missing.push(chunk);
+                        }
+                    }
+                }
+            }
+            missing.sort_by_key(|&chunk| (chunk - center).map(|n| n * n).sum());
+            for pos in missing.into_iter().take(self.chunks_per_update) {
+                player.sent.insert(pos);
+                let data = self.world.chunk(pos).map(compress_chunk);
+                packets.push(ServerMsg::Chunk { pos, data }.packet(addr));
+            }
+        }
+        for packet in packets {
+            self.send(packet);
+        }
+    }
+
+    fn sync_positions(&mut self) {
+        let positions = self.players.values()
+            .map(|player| (player.id, player.pos))
+            .collect::<Vec<_>>();
+        let addrs = self.players.keys().copied().collect::<Vec<_>>();
+        for (id, pos) in positions {
+            for &addr in &addrs {
+                if self.players[&addr].id != id {
+                    self.send(ServerMsg::PlayerMoved { id, pos }.packet(addr));
+                }
+            }
+        }
+    }
+
+    fn broadcast(&mut self, msg: &ServerMsg) {
+        let addrs = self.players.keys().copied().collect::<Vec<_>>();
+        for addr in addrs {
+            self.send(msg.packet(addr));
+        }
+    }
+
+    fn send(&mut self, packet: laminar::Packet) {
+        let addr = packet.addr();
+        if let Err(e) = self.socket.send(packet) {
+            warn!("failed to send to {}: {}", addr, e);
+        }
+    }
+}
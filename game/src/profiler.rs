@@ -0,0 +1,256 @@
+//! Frame profiling with puffin, and a flame graph of the latest frame drawn
+//! with the GUI. 
+//!
+//! Profiling scopes are placed with `puffin::profile_scope!` and
+//! `puffin::profile_function!`, and cost almost nothing while the profiler
+//! is disabled. Frames are delimited by the render loop. 
+
+use puffin::{
+    GlobalFrameView,
+    Reader,
+    ScopeCollection,
+    Stream,
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// Height of a row of the flame graph, in points. 
+const ROW_HEIGHT: f32 = 16.0;
+/// Spans narrower than this, in points, aren't labeled. 
+const MIN_LABEL_WIDTH: f32 = 32.0;
+
+/// Collects profiled frames while enabled. 
+pub struct Profiler {
+    // none while disabled
+    view: Option<GlobalFrameView>,
+}
+
+/// A profiled frame, arranged for drawing as a flame graph. 
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlameFrame {
+    /// Index of the frame, counting from when the game started. 
+    pub index: u64,
+    /// Time from the start of the frame to the end of its last scope. 
+    pub duration_ns: i64,
+    pub threads: Vec<FlameThread>,
+}
+
+/// Profiled scopes of a single thread in a frame. 
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlameThread {
+    pub name: String,
+    /// Scopes, with each scope before the scopes nested within it. 
+    pub spans: Vec<FlameSpan>,
+}
+
+/// A single profiled scope. 
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlameSpan {
+    pub name: String,
+    /// Extra information given to the scope, such as the name of the render
+    /// node being prepared, or an empty string. 
+    pub data: String,
+    /// How many scopes this is nested within. 
+    pub depth: usize,
+    /// Start time, relative to the start of the frame. 
+    pub start_ns: i64,
+    pub duration_ns: i64,
+}
+
+impl Profiler {
+    /// Construct, disabled. 
+    pub fn new() -> Self {
+        Profiler {
+            view: None,
+        }
+    }
+
+    /// Whether profiling scopes are being recorded. 
+    pub fn is_enabled(&self) -> bool {
+        self.view.is_some()
+    }
+
+    /// Start or stop recording profiling scopes. Frames recorded before
+    /// disabling are discarded. 
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled == self.is_enabled() {
+            return;
+        }
+        puffin::set_scopes_on(enabled);
+        self.view = match enabled {
+            true => Some(GlobalFrameView::default()),
+            false => None,
+        };
+    }
+
+    /// Get the latest completed frame, if enabled and any frame has
+    /// completed since enabling. 
+    pub fn latest_frame(&self) -> Option<FlameFrame> {
+        let view = self.view.as_ref()?.lock();
+        let frame = view.latest_frame()?;
+        let frame = frame.unpacked().ok()?;
+        let (frame_start, frame_end) = frame.range_ns();
+
+        let mut threads = Vec::new();
+        for (thread, stream) in &frame.thread_streams {
+            let mut spans = Vec::new();
+            let result = read_spans(
+                &stream.stream,
+                0,
+                0,
+                frame_start,
+                view.scope_collection(),
+                &mut spans,
+            );
+            if let Err(e) = result {
+                warn!(thread=%thread.name, "invalid profiler stream: {:?}", e);
+                continue;
+            }
+            threads.push(FlameThread {
+                name: thread.name.clone(),
+                spans,
+            });
+        }
+        Some(FlameFrame {
+            index: frame.frame_index(),
+            duration_ns: frame_end - frame_start,
+            threads,
+        })
+    }
+
+    /// Show the flame graph window, if enabled. 
+    pub fn flame_graph(&self, ctx: &egui::CtxRef) {
+        if !self.is_enabled() {
+            return;
+        }
+        let frame = self.latest_frame();
+        egui::Window::new("profiler")
+            .default_width(600.0)
+            .show(ctx, |ui| match frame {
+                Some(ref frame) => flame_graph_frame(ui, frame),
+                None => {
+                    ui.label("waiting for a frame");
+                }
+            });
+    }
+}
+
+/// Read all spans in a stream from the scope at `offset` and its siblings,
+/// recursively. 
+fn read_spans(
+    stream: &Stream,
+    offset: u64,
+    depth: usize,
+    frame_start: i64,
+    scopes: &ScopeCollection,
+    spans: &mut Vec<FlameSpan>,
+) -> puffin::Result<()> {
+    for scope in Reader::with_offset(stream, offset)? {
+        let scope = scope?;
+        let name = scopes.fetch_by_id(&scope.id)
+            .map(|details| details.name().to_string())
+            .unwrap_or_default();
+        spans.push(FlameSpan {
+            name,
+            data: scope.record.data.to_owned(),
+            depth,
+            start_ns: scope.record.start_ns - frame_start,
+            duration_ns: scope.record.duration_ns,
+        });
+        read_spans(stream, scope.child_begin_position, depth + 1, frame_start, scopes, spans)?;
+    }
+    Ok(())
+}
+
+/// Draw a frame's flame graph, with a row per nesting depth and a graph per
+/// thread. 
+fn flame_graph_frame(ui: &mut egui::Ui, frame: &FlameFrame) {
+    ui.label(format!("frame {}: {:.2} ms", frame.index, frame.duration_ns as f64 / 1e6));
+    for thread in &frame.threads {
+        ui.separator();
+        ui.label(&thread.name);
+
+        let depth = thread.spans.iter().map(|span| span.depth + 1).max().unwrap_or(0);
+        let size = egui::vec2(ui.available_width(), depth as f32 * ROW_HEIGHT);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+        let origin = response.rect.min;
+        let points_per_ns = size.x / frame.duration_ns.max(1) as f32;
+
+        let mut hovered = None;
+        for span in &thread.spans {
+            let rect = egui::Rect::from_min_size(
+                origin + egui::vec2(
+                    span.start_ns as f32 * points_per_ns,
+                    span.depth as f32 * ROW_HEIGHT,
+                ),
+                egui::vec2(
+                    (span.duration_ns as f32 * points_per_ns).max(1.0),
+                    ROW_HEIGHT - 1.0,
+                ),
+            );
+            painter.rect_filled(rect, 2.0, span_color(&span.name));
+            if rect.width() >= MIN_LABEL_WIDTH {
+                painter.text(
+                    rect.left_center() + egui::vec2(4.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    &span.name,
+                    egui::TextStyle::Small,
+                    egui::Color32::BLACK,
+                );
+            }
+            if response.hover_pos().map(|pos| rect.contains(pos)).unwrap_or(false) {
+                hovered = Some(span);
+            }
+        }
+
+        if let Some(span) = hovered {
+            let mut text = format!("{}: {:.3} ms", span.name, span.duration_ns as f64 / 1e6);
+            if !span.data.is_empty() {
+                text = format!("{}\n{}", text, span.data);
+            }
+            response.on_hover_text(text);
+        }
+    }
+}
+
+/// Pick a color for a span, which is the same for spans with the same name. 
+fn span_color(name: &str) -> egui::Color32 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let hue = (hasher.finish() % 1000) as f32 / 1000.0;
+    egui::color::Hsva::new(hue, 0.5, 0.9, 1.0).into()
+}
+
+#[test]
+fn profiler_test() {
+    let mut profiler = Profiler::new();
+    assert_eq!(profiler.latest_frame(), None);
+
+    profiler.set_enabled(true);
+    for _ in 0..2 {
+        {
+            puffin::profile_scope!("outer");
+            puffin::profile_scope!("inner", "data");
+        }
+        puffin::GlobalProfiler::lock().new_frame();
+    }
+    let frame = profiler.latest_frame().unwrap();
+    let spans = frame.threads
+        .iter()
+        .flat_map(|thread| thread.spans.iter())
+        .filter(|span| span.name == "outer" || span.name == "inner")
+        .map(|span| (span.name.as_str(), span.data.as_str(), span.depth))
+        .collect::<Vec<_>>();
+    assert_eq!(spans, vec![("outer", "", 0), ("inner", "data", 1)]);
+
+    let mut ctx = egui::CtxRef::default();
+    ctx.begin_frame(egui::RawInput::default());
+    profiler.flame_graph(&ctx);
+    let (_, shapes) = ctx.end_frame();
+    assert!(!ctx.tessellate(shapes).is_empty());
+
+    profiler.set_enabled(false);
+    assert_eq!(profiler.latest_frame(), None);
+}
@@ -0,0 +1,270 @@
+//! Gameplay scripting with [rhai](https://rhai.rs), enabled by the
+//! `scripting` feature. 
+//!
+//! A script is compiled once, and its top-level statements are run when it's
+//! loaded. After that, the host calls these functions, if the script defines
+//! them:
+//!
+//! - `on_tick(dt)`: after each simulation tick, with the tick duration in
+//!   seconds. 
+//! - `on_block_break(x, y, z, id)`: after the player breaks a block. 
+//!
+//! Scripts can only affect the game through the functions registered here:
+//!
+//! - `get_block(x, y, z)`, `set_block(x, y, z, id)`: read and write blocks. 
+//! - `block_id(name)`, `block_name(id)`: look up block types. `block_id`
+//!   returns -1 for unknown names. 
+//! - `spawn_mob(x, y, z)`: spawn a mob at a position, or at the center of
+//!   the top of a block if given integers. 
+//!
+//! Scripts have no access to the filesystem or to modules, can't `eval`,
+//! and are limited in how many operations each call may run, so a broken
+//! script can't hang the game. 
+
+use crate::world::{World, BlockRegistry, BlockId, AIR};
+use std::{
+    path::Path,
+    fs,
+    ptr::NonNull,
+    sync::{Arc, Mutex},
+};
+use rhai::{
+    Engine,
+    AST,
+    Scope,
+    EvalAltResult,
+    FuncArgs,
+    Dynamic,
+    INT,
+    FLOAT,
+};
+use vek::*;
+use pear::*;
+
+/// Maximum number of operations a single script call may run. 
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Maximum function call depth of scripts. 
+pub const MAX_CALL_LEVELS: usize = 64;
+
+/// Maximum length of strings, arrays, and maps created by scripts. 
+pub const MAX_COLLECTION_SIZE: usize = 1 << 16;
+
+/// Compiled script, and the engine which runs it. 
+pub struct ScriptHost {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    world: WorldSlot,
+    spawns: Arc<Mutex<Vec<Vec3<f32>>>>,
+}
+
+impl ScriptHost {
+    /// Compile a script, and run its top-level statements. 
+    ///
+    /// Blocks can't be accessed by top-level statements. 
+    pub fn new(source: &str, blocks: &BlockRegistry) -> Result<Self> {
+        let world = WorldSlot::default();
+        let spawns = Arc::new(Mutex::new(Vec::new()));
+        let engine = create_engine(&world, &spawns, blocks);
+        let ast = engine.compile(source)
+            .map_err(|e| pear!({error=e.to_string()}, "failed to compile script"))?;
+        let mut scope = Scope::new();
+        engine.consume_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| pear!({error=e.to_string()}, "script failed to run"))?;
+        Ok(ScriptHost {
+            engine,
+            ast,
+            scope,
+            world,
+            spawns,
+        })
+    }
+
+    /// Read, compile, and run a script file. 
+    pub fn read<P: AsRef<Path>>(path: P, blocks: &BlockRegistry) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({path=path}, "failed to read script file"))?;
+        Self::new(&source, blocks)
+            .wrap_err(|| pear!({path=path}, "failed to load script"))
+    }
+
+    /// Call the script's `on_tick` function, if it has one. 
+    pub fn on_tick(&mut self, world: &mut World, dt: f32) -> Result<()> {
+        self.call(world, "on_tick", (dt as FLOAT,))
+    }
+
+    /// Call the script's `on_block_break` function, if it has one. 
+    pub fn on_block_break(
+        &mut self,
+        world: &mut World,
+        pos: Vec3<i32>,
+        block: BlockId,
+    ) -> Result<()> {
+        let args = (pos.x as INT, pos.y as INT, pos.z as INT, block as INT);
+        self.call(world, "on_block_break", args)
+    }
+
+    /// Take the positions of mobs which the script spawned since the last
+    /// call. 
+    pub fn take_spawns(&mut self) -> Vec<Vec3<f32>> {
+        std::mem::replace(&mut *self.spawns.lock().unwrap(), Vec::new())
+    }
+
+    /// Call a script function, if it's defined, with the world lent to the
+    /// script for the duration of the call. 
+    fn call<A: FuncArgs>(&mut self, world: &mut World, name: &str, args: A) -> Result<()> {
+        let (engine, ast, scope) = (&self.engine, &self.ast, &mut self.scope);
+        let result: std::result::Result<Dynamic, _> = self.world.lend(world, || {
+            engine.call_fn(scope, ast, name, args)
+        });
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => match *e {
+                EvalAltResult::ErrorFunctionNotFound(ref f, _) if f.starts_with(name) => Ok(()),
+                ref e => Err(pear!({function=name, error=e.to_string()}, "script error")),
+            },
+        }
+    }
+}
+
+/// Create an engine with the game's functions registered, and sandboxing
+/// limits set. 
+fn create_engine(
+    world: &WorldSlot,
+    spawns: &Arc<Mutex<Vec<Vec3<f32>>>>,
+    blocks: &BlockRegistry,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_COLLECTION_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.disable_symbol("eval");
+    engine.on_print(|s| info!("script: {}", s));
+    engine.on_debug(|s| debug!("script: {}", s));
+
+    let w = world.clone();
+    engine.register_fn("get_block", move |x: INT, y: INT, z: INT| -> INT {
+        w.with(|world| world.block(block_pos(x, y, z)) as INT).unwrap_or(AIR as INT)
+    });
+    let w = world.clone();
+    engine.register_fn("set_block", move |x: INT, y: INT, z: INT, id: INT| {
+        let id = if id >= 0 && id <= BlockId::MAX as INT { id as BlockId } else { AIR };
+        if w.with(|world| world.set_block(block_pos(x, y, z), id)).is_none() {
+            warn!("script tried to set a block outside of a callback");
+        }
+    });
+
+    let registry = Arc::new(blocks.clone());
+    let r = Arc::clone(&registry);
+    engine.register_fn("block_id", move |name: &str| -> INT {
+        r.id(name).map(|id| id as INT).unwrap_or(-1)
+    });
+    let r = registry;
+    engine.register_fn("block_name", move |id: INT| -> String {
+        let id = if id >= 0 && id <= BlockId::MAX as INT { id as BlockId } else { AIR };
+        r.get(id).name.clone()
+    });
+
+    let s = Arc::clone(spawns);
+    engine.register_fn("spawn_mob", move |x: FLOAT, y: FLOAT, z: FLOAT| {
+        s.lock().unwrap().push(Vec3::new(x as f32, y as f32, z as f32));
+    });
+    let s = Arc::clone(spawns);
+    engine.register_fn("spawn_mob", move |x: INT, y: INT, z: INT| {
+        let pos = block_pos(x, y, z).map(|n| n as f32) + Vec3::new(0.5, 1.0, 0.5);
+        s.lock().unwrap().push(pos);
+    });
+
+    engine
+}
+
+fn block_pos(x: INT, y: INT, z: INT) -> Vec3<i32> {
+    Vec3::new(x, y, z).map(|n| n as i32)
+}
+
+/// Slot which the world is lent to scripts through, for the duration of a
+/// callback. 
+///
+/// Registered functions must be `'static`, so they can't hold a reference to
+/// the world. Instead, they share this slot, which holds a pointer to the
+/// world only while `lend` is running. The pointer is taken out of the slot
+/// while it's dereferenced, so it's never aliased. 
+#[derive(Clone, Default)]
+struct WorldSlot(Arc<Mutex<Option<WorldPtr>>>);
+
+#[derive(Copy, Clone)]
+struct WorldPtr(NonNull<World>);
+
+// safe because the pointer is only dereferenced while the `&mut World` it was
+// created from is borrowed by `WorldSlot::lend`
+unsafe impl Send for WorldPtr {}
+
+impl WorldSlot {
+    /// Make the world accessible through `with` while running `f`. 
+    fn lend<R, F: FnOnce() -> R>(&self, world: &mut World, f: F) -> R {
+        let prev = self.0.lock().unwrap().replace(WorldPtr(NonNull::from(world)));
+        let _restore = Restore(&self.0, prev);
+        f()
+    }
+
+    /// Access the world, if it's currently lent. 
+    fn with<R, F: FnOnce(&mut World) -> R>(&self, f: F) -> Option<R> {
+        let ptr = self.0.lock().unwrap().take()?;
+        let _restore = Restore(&self.0, Some(ptr));
+        Some(f(unsafe { &mut *ptr.0.as_ptr() }))
+    }
+}
+
+/// Puts a pointer back into a `WorldSlot` when dropped, even if unwinding. 
+struct Restore<'a>(&'a Mutex<Option<WorldPtr>>, Option<WorldPtr>);
+
+impl<'a> Drop for Restore<'a> {
+    fn drop(&mut self) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = self.1;
+        }
+    }
+}
+
+#[test]
+fn script_test() {
+    let mut world = World::new();
+    let blocks = BlockRegistry::new();
+    let mut script = ScriptHost::new(r#"
+        // stack up a column of blocks, one per tick
+        fn on_tick(dt) {
+            let y = 1;
+            while get_block(0, y, 0) != 0 {
+                y += 1;
+            }
+            set_block(0, y, 0, 1);
+        }
+
+        fn on_block_break(x, y, z, id) {
+            set_block(x, y + 1, z, get_block(x, y, z) + id);
+            spawn_mob(x, y, z);
+        }
+    "#, &blocks).unwrap();
+
+    script.on_tick(&mut world, 0.1).unwrap();
+    script.on_tick(&mut world, 0.1).unwrap();
+    assert_eq!(world.block(Vec3::new(0, 1, 0)), 1);
+    assert_eq!(world.block(Vec3::new(0, 2, 0)), 1);
+
+    script.on_block_break(&mut world, Vec3::new(5, 0, 5), 2).unwrap();
+    assert_eq!(world.block(Vec3::new(5, 1, 5)), 2);
+    assert_eq!(script.take_spawns(), vec![Vec3::new(5.5, 1.0, 5.5)]);
+    assert!(script.take_spawns().is_empty());
+
+    // undefined callbacks are skipped, but runaway scripts are stopped
+    let mut script = ScriptHost::new("", &blocks).unwrap();
+    script.on_tick(&mut world, 0.1).unwrap();
+    let mut script = ScriptHost::new("fn on_tick(dt) { loop {} }", &blocks).unwrap();
+    assert!(script.on_tick(&mut world, 0.1).is_err());
+    assert!(ScriptHost::new("eval(\"1\")", &blocks).is_err());
+}
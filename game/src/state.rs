@@ -0,0 +1,272 @@
+//! Stack of game states, such as menus and pausing, and their overlay HUD. 
+//!
+//! The state at the top of the stack decides which systems update and how
+//! input is handled. States pushed over another state, such as pausing over
+//! playing, return to it when popped. 
+
+use crate::graphics::{Graphics, text_rects, WHITE_SPRITE_TEXTURE};
+use vek::*;
+use pear::*;
+
+/// State of the game, which decides which systems run. 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GameState {
+    /// Before the game has started. The scene is shown dimmed behind it. 
+    MainMenu,
+    /// Playing, with the mouse captured. 
+    Playing,
+    /// Paused while playing. The world stops updating, the mouse is
+    /// released, and the scene is dimmed. 
+    Paused,
+    /// Typing a console command. The world keeps updating. 
+    Console,
+    /// Using the inspector GUI, with the mouse released. The world keeps
+    /// updating. 
+    Inspector,
+}
+
+/// How input is handled in a state. 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InputMode {
+    /// The mouse is captured, and moves the camera, and keys control the
+    /// player. 
+    Gameplay,
+    /// The cursor is free, and clicking continues. 
+    Menu,
+    /// Typed characters are collected as text. 
+    Text,
+    /// The cursor is free, and input goes to the GUI. 
+    Gui,
+}
+
+impl GameState {
+    /// Get how input is handled in this state. 
+    pub fn input_mode(self) -> InputMode {
+        match self {
+            GameState::Playing => InputMode::Gameplay,
+            GameState::MainMenu | GameState::Paused => InputMode::Menu,
+            GameState::Console => InputMode::Text,
+            GameState::Inspector => InputMode::Gui,
+        }
+    }
+
+    /// Whether the world, including physics, mobs, and scripts, updates in
+    /// this state. 
+    pub fn simulates(self) -> bool {
+        match self {
+            GameState::Playing | GameState::Console | GameState::Inspector => true,
+            GameState::MainMenu | GameState::Paused => false,
+        }
+    }
+
+    /// Whether the scene is dimmed behind this state's overlay. 
+    pub fn dims_scene(self) -> bool {
+        match self {
+            GameState::MainMenu | GameState::Paused => true,
+            GameState::Playing | GameState::Console | GameState::Inspector => false,
+        }
+    }
+
+    /// Text shown over the scene in this state, if any. 
+    pub fn title(self) -> Option<&'static str> {
+        match self {
+            GameState::MainMenu => Some("click to play"),
+            GameState::Paused => Some("paused\n\nclick to resume"),
+            GameState::Playing | GameState::Console | GameState::Inspector => None,
+        }
+    }
+}
+
+/// Non-empty stack of game states. 
+#[derive(Debug, Clone)]
+pub struct StateStack {
+    // never empty
+    stack: Vec<GameState>,
+}
+
+impl StateStack {
+    /// Construct with a single state. 
+    pub fn new(state: GameState) -> Self {
+        StateStack {
+            stack: vec![state],
+        }
+    }
+
+    /// Get the state at the top of the stack, which is in control. 
+    pub fn current(&self) -> GameState {
+        *self.stack.last().unwrap()
+    }
+
+    /// Get the states, from the bottom of the stack to the top. 
+    pub fn states(&self) -> &[GameState] {
+        &self.stack
+    }
+
+    /// Push a state over the current one. 
+    pub fn push(&mut self, state: GameState) {
+        self.stack.push(state);
+    }
+
+    /// Pop the current state, returning to the one beneath it, and return the
+    /// popped state. 
+    ///
+    /// The bottom state is never popped, and `None` is returned instead. 
+    pub fn pop(&mut self) -> Option<GameState> {
+        if self.stack.len() > 1 {
+            self.stack.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Replace the current state. 
+    pub fn replace(&mut self, state: GameState) {
+        *self.stack.last_mut().unwrap() = state;
+    }
+}
+
+/// Command typed into the console. 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// `tp <x> <y> <z>`: move the camera to a position. 
+    Teleport(Vec3<f32>),
+    /// `walk` or `fly`: start or stop walking. 
+    Walk(bool),
+    /// `quit`: exit the game. 
+    Quit,
+}
+
+impl Command {
+    /// Parse a command. 
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let args = words.collect::<Vec<_>>();
+        let command = match (name, args.len()) {
+            ("tp", 3) => {
+                let mut pos = Vec3::zero();
+                for (i, arg) in args.iter().enumerate() {
+                    pos[i] = arg.parse::<f32>()
+                        .map_err(Error::from)
+                        .wrap_err(|| pear!({arg=arg}, "invalid coordinate"))?;
+                }
+                Command::Teleport(pos)
+            }
+            ("walk", 0) => Command::Walk(true),
+            ("fly", 0) => Command::Walk(false),
+            ("quit", 0) => Command::Quit,
+            _ => return Err(pear!(
+                {command=line},
+                "unknown command, expected tp <x> <y> <z>, walk, fly, or quit",
+            )),
+        };
+        Ok(command)
+    }
+}
+
+/// First sprite ID used by the state overlay HUD. 
+///
+/// Sprite IDs from this up to `STATE_SPRITE_BASE + 0xffff` are reserved. 
+pub const STATE_SPRITE_BASE: u64 = 0x20000;
+
+/// Size of a font pixel in titles, in pixels. 
+const TITLE_SCALE: f32 = 4.0;
+/// Size of a font pixel in the console, in pixels. 
+const CONSOLE_SCALE: f32 = 2.0;
+/// Space around the console text, in pixels. 
+const CONSOLE_MARGIN: f32 = 8.0;
+const DIM_COLOR: Rgba<f32> = Rgba { r: 0.0, g: 0.0, b: 0.0, a: 0.6 };
+const CONSOLE_COLOR: Rgba<f32> = Rgba { r: 0.0, g: 0.0, b: 0.0, a: 0.7 };
+const TEXT_COLOR: Rgba<f32> = Rgba { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+
+/// Draws the current state's overlay, such as dimming and titles, with
+/// sprites. 
+#[derive(Debug, Clone, Default)]
+pub struct StateHud {
+    num_sprites: u64,
+}
+
+impl StateHud {
+    /// Construct, with no sprites drawn yet. 
+    pub fn new() -> Self {
+        StateHud::default()
+    }
+
+    /// Update the overlay's sprites to show a state. 
+    ///
+    /// `console` is the text being typed into the console, which is shown
+    /// in the console state. 
+    pub fn update(&mut self, graphics: &mut Graphics, state: GameState, console: &str) {
+        let window_size = graphics.window().inner_size();
+        let window_size = Vec2::new(window_size.width as f32, window_size.height as f32);
+
+        let mut id = STATE_SPRITE_BASE;
+        let mut sprite = |graphics: &mut Graphics, rect, color| {
+            graphics.set_sprite(id, rect, WHITE_SPRITE_TEXTURE, color);
+            id += 1;
+        };
+        if state.dims_scene() {
+            sprite(graphics, Rect::new(0.0, 0.0, window_size.x, window_size.y), DIM_COLOR);
+        }
+        if let Some(title) = state.title() {
+            // center each line
+            for (i, line) in title.lines().enumerate() {
+                let text_width = (line.len() as f32 * 4.0 - 1.0) * TITLE_SCALE;
+                let origin = Vec2::new(
+                    (window_size.x - text_width) / 2.0,
+                    window_size.y / 3.0 + i as f32 * 7.0 * TITLE_SCALE,
+                );
+                for rect in text_rects(line, origin, TITLE_SCALE) {
+                    sprite(graphics, rect, TEXT_COLOR);
+                }
+            }
+        }
+        if state == GameState::Console {
+            let height = 5.0 * CONSOLE_SCALE + CONSOLE_MARGIN * 2.0;
+            sprite(graphics, Rect::new(0.0, 0.0, window_size.x, height), CONSOLE_COLOR);
+            let text = format!("> {}_", console);
+            for rect in text_rects(&text, Vec2::broadcast(CONSOLE_MARGIN), CONSOLE_SCALE) {
+                sprite(graphics, rect, TEXT_COLOR);
+            }
+        }
+
+        let num_sprites = id - STATE_SPRITE_BASE;
+        for i in num_sprites..self.num_sprites {
+            graphics.remove_sprite(STATE_SPRITE_BASE + i);
+        }
+        self.num_sprites = num_sprites;
+    }
+}
+
+#[test]
+fn state_stack_test() {
+    let mut states = StateStack::new(GameState::MainMenu);
+    assert_eq!(states.pop(), None);
+    states.replace(GameState::Playing);
+    assert_eq!(states.current().input_mode(), InputMode::Gameplay);
+    assert!(states.current().simulates());
+
+    states.push(GameState::Paused);
+    assert!(!states.current().simulates());
+    assert!(states.current().dims_scene());
+    assert_eq!(states.states(), &[GameState::Playing, GameState::Paused]);
+    assert_eq!(states.pop(), Some(GameState::Paused));
+    assert_eq!(states.current(), GameState::Playing);
+
+    states.push(GameState::Inspector);
+    assert_eq!(states.current().input_mode(), InputMode::Gui);
+    assert!(states.current().simulates());
+    assert!(!states.current().dims_scene());
+}
+
+#[test]
+fn command_test() {
+    assert_eq!(
+        Command::parse(" tp 1 -2.5  3 ").unwrap(),
+        Command::Teleport(Vec3::new(1.0, -2.5, 3.0)),
+    );
+    assert_eq!(Command::parse("fly").unwrap(), Command::Walk(false));
+    assert!(Command::parse("tp 1 2").is_err());
+    assert!(Command::parse("tp 1 2 x").is_err());
+    assert!(Command::parse("").is_err());
+}
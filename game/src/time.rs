@@ -0,0 +1,256 @@
+//! World time, which advances by simulation ticks, and timers scheduled in
+//! ticks. 
+//!
+//! Time of day is measured as an angle around the day, which is 0 at
+//! midnight, a quarter turn at sunrise, a half turn at noon, and three
+//! quarters of a turn at sunset. 
+
+use crate::util::{
+    angle::*,
+    timestep::DEFAULT_TICK_RATE,
+};
+use std::{
+    collections::BTreeMap,
+    f32::consts::PI,
+    fmt::{self, Debug, Formatter},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Default number of ticks in a day, which is 20 minutes at the default
+/// tick rate. 
+pub const DEFAULT_TICKS_PER_DAY: u64 = 20 * 60 * DEFAULT_TICK_RATE as u64;
+
+/// Fraction of the way through the first day that a new world starts at,
+/// which is mid-morning. 
+pub const START_TIME_OF_DAY: f64 = 0.35;
+
+/// World clock, counted in ticks. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GameTime {
+    // ticks since the first midnight
+    ticks: u64,
+    ticks_per_day: u64,
+}
+
+impl GameTime {
+    /// Construct at the start of a new world, with a day length in ticks. 
+    pub fn new(ticks_per_day: u64) -> Self {
+        assert!(ticks_per_day > 0, "day length must be non-zero");
+        GameTime {
+            ticks: (ticks_per_day as f64 * START_TIME_OF_DAY) as u64,
+            ticks_per_day,
+        }
+    }
+
+    /// Advance by a single tick. 
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// Get the number of ticks since midnight of the first day. 
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Set the number of ticks since midnight of the first day. 
+    pub fn set_ticks(&mut self, ticks: u64) {
+        self.ticks = ticks;
+    }
+
+    /// Get the length of a day, in ticks. 
+    pub fn ticks_per_day(&self) -> u64 {
+        self.ticks_per_day
+    }
+
+    /// Get the number of days which have fully passed, so the first day is
+    /// day 0. 
+    pub fn day(&self) -> u64 {
+        self.ticks / self.ticks_per_day
+    }
+
+    /// Get the time of day. 
+    pub fn time_of_day(&self) -> Angle<f32> {
+        self.time_of_day_at(0.0)
+    }
+
+    /// Get the time of day, a fraction `alpha` of a tick after the current
+    /// tick, for smoothly interpolating between ticks. 
+    pub fn time_of_day_at(&self, alpha: f32) -> Angle<f32> {
+        let ticks = (self.ticks % self.ticks_per_day) as f64 + alpha as f64;
+        rad((ticks / self.ticks_per_day as f64) as f32 * 2.0 * PI)
+    }
+
+    /// Set the time of day, without changing the day. 
+    pub fn set_time_of_day(&mut self, time_of_day: Angle<f32>) {
+        let fraction = (time_of_day.rad() / (2.0 * PI)).rem_euclid(1.0);
+        let ticks = (fraction as f64 * self.ticks_per_day as f64).round() as u64;
+        self.ticks = self.day() * self.ticks_per_day + ticks % self.ticks_per_day;
+    }
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        GameTime::new(DEFAULT_TICKS_PER_DAY)
+    }
+}
+
+/// Handle to a scheduled timer, for cancelling it. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerId(u64);
+
+// timer IDs are unique across all timer sets, so they can be merged
+static NEXT_TIMER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Callbacks scheduled to run on particular ticks, with a context `C`. 
+///
+/// Timers scheduled for the same tick run in the order they were scheduled. 
+pub struct Timers<C> {
+    // keyed by the tick they're due, then by ID
+    timers: BTreeMap<(u64, TimerId), Timer<C>>,
+}
+
+struct Timer<C> {
+    callback: Box<dyn FnMut(&mut C) + Send>,
+    // ticks between runs, if recurring
+    period: Option<u64>,
+}
+
+impl<C> Timers<C> {
+    /// Construct with no timers. 
+    pub fn new() -> Self {
+        Timers {
+            timers: BTreeMap::new(),
+        }
+    }
+
+    /// Schedule `callback` to run once, `ticks` ticks after `now`. 
+    pub fn schedule_in<F>(&mut self, now: u64, ticks: u64, callback: F) -> TimerId
+    where
+        F: FnMut(&mut C) + Send + 'static,
+    {
+        self.insert(now + ticks, Timer {
+            callback: Box::new(callback),
+            period: None,
+        })
+    }
+
+    /// Schedule `callback` to run every `period` ticks, first at `first`. 
+    ///
+    /// Panics if `period` is 0. 
+    pub fn schedule_every<F>(&mut self, first: u64, period: u64, callback: F) -> TimerId
+    where
+        F: FnMut(&mut C) + Send + 'static,
+    {
+        assert!(period > 0, "timer period must be non-zero");
+        self.insert(first, Timer {
+            callback: Box::new(callback),
+            period: Some(period),
+        })
+    }
+
+    /// Cancel a timer, returning whether it was still scheduled. 
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let key = self.timers.keys().find(|&&(_, timer_id)| timer_id == id).copied();
+        key.map(|key| self.timers.remove(&key)).is_some()
+    }
+
+    /// Get the number of scheduled timers. 
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Whether no timers are scheduled. 
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// Run every timer due at or before tick `now`, in order of when they're
+    /// due, and re-schedule recurring timers. 
+    pub fn run(&mut self, now: u64, context: &mut C) {
+        let later = self.timers.split_off(&(now + 1, TimerId(0)));
+        let due = std::mem::replace(&mut self.timers, later);
+        for ((tick, id), mut timer) in due {
+            (timer.callback)(context);
+            if let Some(period) = timer.period {
+                // a recurring timer which fell behind skips the runs it missed
+                let mut next = tick + period;
+                if next <= now {
+                    next += (now - next) / period * period + period;
+                }
+                self.timers.insert((next, id), timer);
+            }
+        }
+    }
+
+    /// Move the timers from `other` into this. 
+    ///
+    /// This is useful for running timers whose context contains them, by
+    /// taking them out, running them, and merging them back in. 
+    pub fn merge(&mut self, other: Timers<C>) {
+        self.timers.extend(other.timers);
+    }
+
+    fn insert(&mut self, tick: u64, timer: Timer<C>) -> TimerId {
+        let id = TimerId(NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed));
+        self.timers.insert((tick, id), timer);
+        id
+    }
+}
+
+impl<C> Default for Timers<C> {
+    fn default() -> Self {
+        Timers::new()
+    }
+}
+
+impl<C> Debug for Timers<C> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.timers.keys().map(|&(tick, _)| tick))
+            .finish()
+    }
+}
+
+#[test]
+fn game_time_test() {
+    let mut time = GameTime::new(100);
+    assert_eq!(time.day(), 0);
+    assert_eq!(time.ticks(), 35);
+    for _ in 0..65 {
+        time.tick();
+    }
+    assert_eq!(time.day(), 1);
+    assert!(time.time_of_day().rad().abs() < 1e-6);
+    assert!((time.time_of_day_at(0.5).rad() - PI / 100.0).abs() < 1e-6);
+
+    time.set_time_of_day(deg(180.0));
+    assert_eq!(time.ticks(), 150);
+    time.set_time_of_day(deg(-90.0));
+    assert_eq!(time.ticks(), 175);
+}
+
+#[test]
+fn timers_test() {
+    let mut timers = Timers::<Vec<&'static str>>::new();
+    let mut log = Vec::new();
+    timers.schedule_in(0, 5, |log: &mut Vec<_>| log.push("once"));
+    let cancelled = timers.schedule_in(0, 5, |log: &mut Vec<_>| log.push("cancelled"));
+    timers.schedule_every(2, 3, |log: &mut Vec<_>| log.push("every"));
+    assert!(timers.cancel(cancelled));
+    assert!(!timers.cancel(cancelled));
+
+    for tick in 0..=8 {
+        timers.run(tick, &mut log);
+    }
+    assert_eq!(log, vec!["every", "once", "every", "every"]);
+    assert_eq!(timers.len(), 1);
+
+    // a recurring timer skips runs it fell behind on
+    log.clear();
+    timers.run(20, &mut log);
+    assert_eq!(log, vec!["every"]);
+    timers.run(22, &mut log);
+    assert_eq!(log, vec!["every"]);
+    timers.run(23, &mut log);
+    assert_eq!(log, vec!["every", "every"]);
+}
@@ -9,6 +9,13 @@ use num_traits::{
     identities::One,
     cast::NumCast,
 };
+use serde::{
+    Serialize,
+    Serializer,
+    Deserialize,
+    Deserializer,
+};
+use approx::AbsDiffEq;
 
 /// Geometric angle type. 
 ///
@@ -155,6 +162,33 @@ impl<F: Real> Debug for Angle<F> {
     }
 }
 
+/// Serializes as radians. 
+impl<F: Real + Serialize> Serialize for Angle<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+/// Deserializes from radians. 
+impl<'de, F: Real + Deserialize<'de>> Deserialize<'de> for Angle<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        F::deserialize(deserializer).map(Angle)
+    }
+}
+
+impl<F: Real + AbsDiffEq<Epsilon=F>> AbsDiffEq for Angle<F> {
+    type Epsilon = F;
+
+    fn default_epsilon() -> F {
+        F::default_epsilon()
+    }
+
+    /// Compares radians. 
+    fn abs_diff_eq(&self, other: &Self, epsilon: F) -> bool {
+        self.0.abs_diff_eq(&other.0, epsilon)
+    }
+}
+
 impl<F: Real> Neg for Angle<F> {
     type Output = Angle<F>;
 
@@ -240,3 +274,226 @@ impl<F: Real> RemAssign<Angle<F>> for Angle<F> {
         self.0 = self.0 % rhs.0;
     }
 }
+
+/// Angle which is always normalized to [0°, 360°). 
+///
+/// Arithmetic wraps around, and comparisons behave as expected around the 
+/// wrap point, since equal orientations always have equal representations. 
+#[derive(Copy, Clone, PartialEq, PartialOrd, Default)]
+#[repr(transparent)]
+pub struct WrappedAngle<F: Real>(Angle<F>);
+
+impl<F: Real> WrappedAngle<F> {
+    /// Normalize an angle. 
+    pub fn new(angle: Angle<F>) -> Self {
+        let turn = full_turn::<F>();
+        let mut n = angle.0 % turn;
+        if n < F::zero() {
+            n = n + turn;
+        }
+        // a tiny negative remainder can round up to a full turn
+        if n >= turn {
+            n = F::zero();
+        }
+        WrappedAngle(Angle(n))
+    }
+
+    /// Get as an `Angle` in the range [0°, 360°). 
+    pub fn angle(&self) -> Angle<F> {
+        self.0
+    }
+
+    /// Get as an `Angle` in the range (-180°, 180°]. 
+    pub fn signed(&self) -> Angle<F> {
+        let half_turn = full_turn::<F>() / (F::one() + F::one());
+        if (self.0).0 > half_turn {
+            Angle((self.0).0 - full_turn::<F>())
+        } else {
+            self.0
+        }
+    }
+
+    /// Get the shortest signed rotation from `self` to `other`, in the range 
+    /// (-180°, 180°]. 
+    pub fn delta(&self, other: WrappedAngle<F>) -> Angle<F> {
+        WrappedAngle::new(other.0 - self.0).signed()
+    }
+
+    /// Cast the inner float to a different float type. 
+    ///
+    /// Panics if the cast fails.
+    pub fn cast<T: NumCast + Real>(self) -> WrappedAngle<T> {
+        WrappedAngle::new(self.0.cast())
+    }
+
+    /// Get as degrees, in the range [0, 360). 
+    pub fn deg(&self) -> F {
+        self.0.deg()
+    }
+
+    /// Get as radians, in the range [0, 2π). 
+    pub fn rad(&self) -> F {
+        self.0.rad()
+    }
+
+    /// Sine function. 
+    pub fn sin(&self) -> F {
+        self.0.sin()
+    }
+
+    /// Cosine function. 
+    pub fn cos(&self) -> F {
+        self.0.cos()
+    }
+
+    /// Tangent function. 
+    pub fn tan(&self) -> F {
+        self.0.tan()
+    }
+}
+
+/// A full turn, in radians. 
+fn full_turn<F: Real>() -> F {
+    <F as NumCast>::from(std::f64::consts::PI * 2.0).unwrap()
+}
+
+impl<F: Real> From<Angle<F>> for WrappedAngle<F> {
+    fn from(angle: Angle<F>) -> Self {
+        WrappedAngle::new(angle)
+    }
+}
+
+impl<F: Real> From<WrappedAngle<F>> for Angle<F> {
+    fn from(angle: WrappedAngle<F>) -> Self {
+        angle.0
+    }
+}
+
+impl<F: Real> Display for WrappedAngle<F> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<F: Real> Debug for WrappedAngle<F> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+/// Serializes as radians. 
+impl<F: Real + Serialize> Serialize for WrappedAngle<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+/// Deserializes from radians, normalizing them. 
+impl<'de, F: Real + Deserialize<'de>> Deserialize<'de> for WrappedAngle<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Angle::deserialize(deserializer).map(WrappedAngle::new)
+    }
+}
+
+impl<F: Real + AbsDiffEq<Epsilon=F>> AbsDiffEq for WrappedAngle<F> {
+    type Epsilon = F;
+
+    fn default_epsilon() -> F {
+        F::default_epsilon()
+    }
+
+    /// Compares radians along the shortest rotation, so angles on either side 
+    /// of the wrap point can be approximately equal. 
+    fn abs_diff_eq(&self, other: &Self, epsilon: F) -> bool {
+        self.delta(*other).0.abs() <= epsilon
+    }
+}
+
+impl<F: Real> Neg for WrappedAngle<F> {
+    type Output = WrappedAngle<F>;
+
+    fn neg(self) -> WrappedAngle<F> {
+        WrappedAngle::new(-self.0)
+    }
+}
+
+impl<F: Real> Add<Angle<F>> for WrappedAngle<F> {
+    type Output = WrappedAngle<F>;
+
+    fn add(self, rhs: Angle<F>) -> WrappedAngle<F> {
+        WrappedAngle::new(self.0 + rhs)
+    }
+}
+
+impl<F: Real> Sub<Angle<F>> for WrappedAngle<F> {
+    type Output = WrappedAngle<F>;
+
+    fn sub(self, rhs: Angle<F>) -> WrappedAngle<F> {
+        WrappedAngle::new(self.0 - rhs)
+    }
+}
+
+impl<F: Real> Add<WrappedAngle<F>> for WrappedAngle<F> {
+    type Output = WrappedAngle<F>;
+
+    fn add(self, rhs: WrappedAngle<F>) -> WrappedAngle<F> {
+        WrappedAngle::new(self.0 + rhs.0)
+    }
+}
+
+impl<F: Real> Sub<WrappedAngle<F>> for WrappedAngle<F> {
+    type Output = WrappedAngle<F>;
+
+    fn sub(self, rhs: WrappedAngle<F>) -> WrappedAngle<F> {
+        WrappedAngle::new(self.0 - rhs.0)
+    }
+}
+
+impl<F: Real> AddAssign<Angle<F>> for WrappedAngle<F> {
+    fn add_assign(&mut self, rhs: Angle<F>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<F: Real> SubAssign<Angle<F>> for WrappedAngle<F> {
+    fn sub_assign(&mut self, rhs: Angle<F>) {
+        *self = *self - rhs;
+    }
+}
+
+#[test]
+fn wrapped_angle_test() {
+    let close = |a: Angle<f64>, b: Angle<f64>| (a.deg() - b.deg()).abs() < 1e-9;
+
+    assert!(close(WrappedAngle::new(deg(370.0)).angle(), deg(10.0)));
+    assert!(close(WrappedAngle::new(deg(-10.0)).angle(), deg(350.0)));
+    assert!(close(WrappedAngle::new(deg(-720.0)).angle(), deg(0.0)));
+    assert!(close(WrappedAngle::new(deg(270.0)).signed(), deg(-90.0)));
+    assert!(close(WrappedAngle::new(deg(180.0)).signed(), deg(180.0)));
+
+    let mut yaw = WrappedAngle::new(deg(350.0));
+    yaw += deg(20.0);
+    assert!(close(yaw.angle(), deg(10.0)));
+    yaw -= deg(30.0);
+    assert!(close(yaw.angle(), deg(340.0)));
+
+    // shortest rotation crosses the wrap point
+    let a = WrappedAngle::new(deg(350.0));
+    let b = WrappedAngle::new(deg(10.0));
+    assert!(close(a.delta(b), deg(20.0)));
+    assert!(close(b.delta(a), deg(-20.0)));
+}
+
+#[test]
+fn angle_approx_test() {
+    use approx::assert_abs_diff_eq;
+
+    assert_abs_diff_eq!(deg(90.0f64), rad(std::f64::consts::FRAC_PI_2));
+    assert_abs_diff_eq!(
+        WrappedAngle::new(deg(359.9999f64)),
+        WrappedAngle::new(deg(0.0)),
+        epsilon = 1e-5,
+    );
+    assert!(WrappedAngle::new(deg(350.0f64))
+        .abs_diff_ne(&WrappedAngle::new(deg(10.0)), 1e-5));
+}
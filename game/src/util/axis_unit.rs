@@ -1,6 +1,8 @@
 //! Geometric axis-related utilities. 
 
+use super::angle::{Angle, WrappedAngle};
 use vek::*;
+use std::ops::Neg;
 
 
 /// Negativeness of a number. Enum over positive and negative. 
@@ -20,6 +22,22 @@ impl Sign {
             Sign::Neg => -1,
         }
     }
+
+    /// Get the opposite sign. 
+    pub fn opposite(self) -> Sign {
+        match self {
+            Sign::Pos => Sign::Neg,
+            Sign::Neg => Sign::Pos,
+        }
+    }
+}
+
+impl Neg for Sign {
+    type Output = Sign;
+
+    fn neg(self) -> Sign {
+        self.opposite()
+    }
 }
 
 
@@ -82,11 +100,86 @@ impl AxisUnit3 {
         AxisUnit3 { axis, sign }
     }
 
+    /// All 6 variants, in index order. 
+    pub const ALL: [AxisUnit3; 6] = [
+        AxisUnit3::POSX,
+        AxisUnit3::POSY,
+        AxisUnit3::POSZ,
+        AxisUnit3::NEGX,
+        AxisUnit3::NEGY,
+        AxisUnit3::NEGZ,
+    ];
+
+    /// Iterate over all 6 variants, in index order. 
+    pub fn iter() -> impl Iterator<Item=AxisUnit3> + Clone {
+        AxisUnit3::ALL.iter().copied()
+    }
+
+    /// Get the axis. 
+    pub fn axis(self) -> Axis3 {
+        self.axis
+    }
+
+    /// Get the sign. 
+    pub fn sign(self) -> Sign {
+        self.sign
+    }
+
     /// Convert to a `Vek3<i32>`. 
     pub fn to_vec(self) -> Vec3<i32> {
         self.axis.to_unit_vec() * self.sign.to_i32()
     }
 
+    /// Convert from a `Vec3<i32>`, if it is an axis-aligned unit vector. 
+    pub fn from_vec(vec: Vec3<i32>) -> Option<Self> {
+        AxisUnit3::iter().find(|unit| unit.to_vec() == vec)
+    }
+
+    /// Convert from an arbitrary vector, to the axis-aligned unit vector 
+    /// closest to its direction. 
+    ///
+    /// The component with the largest magnitude wins. Ties are broken in 
+    /// favor of X, then Y, then Z, and a zero component is considered 
+    /// positive, so a zero vector becomes +X. 
+    pub fn from_vec_dominant(vec: Vec3<f32>) -> Self {
+        let (axis, n) = [(Axis3::Y, vec.y), (Axis3::Z, vec.z)]
+            .iter()
+            .copied()
+            .fold((Axis3::X, vec.x), |best, (axis, n)| {
+                if n.abs() > best.1.abs() {
+                    (axis, n)
+                } else {
+                    best
+                }
+            });
+        let sign = if n < 0.0 { Sign::Neg } else { Sign::Pos };
+        AxisUnit3::new(axis, sign)
+    }
+
+    /// Get the unit vector pointing the opposite direction. 
+    pub fn opposite(self) -> Self {
+        AxisUnit3::new(self.axis, self.sign.opposite())
+    }
+
+    /// Compute the cross product of two unit vectors, or `None` if they are 
+    /// parallel. 
+    pub fn cross(self, other: AxisUnit3) -> Option<Self> {
+        AxisUnit3::from_vec(self.to_vec().cross(other.to_vec()))
+    }
+
+    /// Rotate by some number of quarter turns about an axis. 
+    ///
+    /// A positive quarter turn about `axis` maps `v` to `axis.cross(v)`, such 
+    /// that a quarter turn about +Y maps +Z to +X. Negative turns rotate the 
+    /// other way. Unit vectors parallel to `axis` are unaffected. 
+    pub fn rotate_about(self, axis: AxisUnit3, quarter_turns: i32) -> Self {
+        let mut unit = self;
+        for _ in 0..quarter_turns.rem_euclid(4) {
+            unit = axis.cross(unit).unwrap_or(unit);
+        }
+        unit
+    }
+
     /// Convert to an integer in [0, 6). 
     pub fn to_index(self) -> usize {
         match self {
@@ -115,6 +208,26 @@ impl AxisUnit3 {
     }
 }
 
+impl Neg for AxisUnit3 {
+    type Output = AxisUnit3;
+
+    fn neg(self) -> AxisUnit3 {
+        self.opposite()
+    }
+}
+
+impl From<AxisUnit3> for Vec3<i32> {
+    fn from(unit: AxisUnit3) -> Vec3<i32> {
+        unit.to_vec()
+    }
+}
+
+impl From<AxisUnit3> for Vec3<f32> {
+    fn from(unit: AxisUnit3) -> Vec3<f32> {
+        unit.to_vec().map(|n| n as f32)
+    }
+}
+
 /// Two dimensional axis. Enum over X, Y. 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
 pub enum Axis2 {
@@ -191,4 +304,80 @@ impl AxisUnit2 {
             _ => panic!("index must be in [0, 4): {}", index),
         }
     }
-}
\ No newline at end of file
+
+    /// Convert from an arbitrary vector, to the axis-aligned unit vector 
+    /// closest to its direction. 
+    ///
+    /// The component with the largest magnitude wins. Ties are broken in 
+    /// favor of X, and a zero component is considered positive, so a zero 
+    /// vector becomes +X. 
+    pub fn from_vec_dominant(vec: Vec2<f32>) -> Self {
+        let (axis, n) = if vec.y.abs() > vec.x.abs() {
+            (Axis2::Y, vec.y)
+        } else {
+            (Axis2::X, vec.x)
+        };
+        let sign = if n < 0.0 { Sign::Neg } else { Sign::Pos };
+        AxisUnit2::new(axis, sign)
+    }
+
+    /// Convert from an angle, to the axis-aligned unit vector closest to it. 
+    ///
+    /// 0° is +X, and angles increase towards +Y, as with `atan2(y, x)`. 
+    /// Angles exactly between two unit vectors round towards the one further 
+    /// counterclockwise, so 45° becomes +Y. 
+    pub fn from_angle(angle: Angle<f32>) -> Self {
+        let quarter_turns = (WrappedAngle::new(angle).deg() / 90.0).round() as usize;
+        // indices are in counterclockwise order, starting from +X
+        AxisUnit2::from_index(quarter_turns % 4)
+    }
+}
+
+#[test]
+fn axis_unit3_algebra_test() {
+    assert_eq!(AxisUnit3::iter().count(), 6);
+    for (i, unit) in AxisUnit3::iter().enumerate() {
+        assert_eq!(unit.to_index(), i);
+        assert_eq!(unit.opposite().to_vec(), -unit.to_vec());
+        assert_eq!(-(-unit), unit);
+        assert_eq!(unit.cross(unit), None);
+        assert_eq!(unit.cross(unit.opposite()), None);
+        assert_eq!(AxisUnit3::from_vec(unit.to_vec()), Some(unit));
+        assert_eq!(Vec3::<f32>::from(unit), unit.to_vec().map(|n| n as f32));
+    }
+    assert_eq!(AxisUnit3::from_vec(Vec3::new(1, 1, 0)), None);
+
+    assert_eq!(AxisUnit3::POSX.cross(AxisUnit3::POSY), Some(AxisUnit3::POSZ));
+    assert_eq!(AxisUnit3::POSY.cross(AxisUnit3::POSX), Some(AxisUnit3::NEGZ));
+
+    let up = AxisUnit3::UP;
+    assert_eq!(AxisUnit3::POSZ.rotate_about(up, 1), AxisUnit3::POSX);
+    assert_eq!(AxisUnit3::POSZ.rotate_about(up, 2), AxisUnit3::NEGZ);
+    assert_eq!(AxisUnit3::POSZ.rotate_about(up, -1), AxisUnit3::NEGX);
+    assert_eq!(AxisUnit3::POSZ.rotate_about(up, 4), AxisUnit3::POSZ);
+    assert_eq!(AxisUnit3::UP.rotate_about(up, 1), AxisUnit3::UP);
+}
+
+#[test]
+fn dominant_axis_test() {
+    use super::angle::deg;
+
+    assert_eq!(AxisUnit3::from_vec_dominant(Vec3::new(0.2, -0.9, 0.4)), AxisUnit3::NEGY);
+    assert_eq!(AxisUnit3::from_vec_dominant(Vec3::new(0.0, 0.0, 3.0)), AxisUnit3::POSZ);
+    assert_eq!(AxisUnit3::from_vec_dominant(Vec3::new(-1.0, 1.0, 1.0)), AxisUnit3::NEGX);
+    assert_eq!(AxisUnit3::from_vec_dominant(Vec3::new(0.0, -1.0, 1.0)), AxisUnit3::NEGY);
+    assert_eq!(AxisUnit3::from_vec_dominant(Vec3::zero()), AxisUnit3::POSX);
+    for unit in AxisUnit3::iter() {
+        assert_eq!(AxisUnit3::from_vec_dominant(unit.into()), unit);
+    }
+
+    assert_eq!(AxisUnit2::from_vec_dominant(Vec2::new(0.5, -0.6)), AxisUnit2::NEGY);
+    assert_eq!(AxisUnit2::from_vec_dominant(Vec2::new(-0.5, 0.5)), AxisUnit2::NEGX);
+
+    assert_eq!(AxisUnit2::from_angle(deg(10.0)), AxisUnit2::POSX);
+    assert_eq!(AxisUnit2::from_angle(deg(-10.0)), AxisUnit2::POSX);
+    assert_eq!(AxisUnit2::from_angle(deg(45.0)), AxisUnit2::POSY);
+    assert_eq!(AxisUnit2::from_angle(deg(190.0)), AxisUnit2::NEGX);
+    assert_eq!(AxisUnit2::from_angle(deg(-100.0)), AxisUnit2::NEGY);
+    assert_eq!(AxisUnit2::from_angle(deg(350.0)), AxisUnit2::POSX);
+}
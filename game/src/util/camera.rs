@@ -1,33 +1,245 @@
 //! Camera handling. 
 
-use super::angle::{Angle, deg};
+use super::{
+    angle::{Angle, WrappedAngle, deg, rad, atan2},
+    geom::Frustum,
+};
 use num_traits::real::Real;
+use serde::{Serialize, Deserialize};
+use approx::AbsDiffEq;
 use vek::*;
 
 /// Yaw and pitch, which defines the orientation of a camera. 
-#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct YawPitch<F: Real> {
-    /// Yaw angle. 
-    pub yaw: Angle<F>,
+    /// Yaw angle, which wraps around. 
+    pub yaw: WrappedAngle<F>,
     /// Pitch angle. 
     pub pitch: Angle<F>,
 }
 
+/// Yaw, pitch, and roll, which defines the orientation of a camera which can 
+/// tilt sideways. 
+///
+/// This is the orientation of a `YawPitch`, rotated by `roll` about its 
+/// forward direction. Positive roll tilts the right direction upwards. 
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct YawPitchRoll<F: Real> {
+    /// Yaw angle, which wraps around. 
+    pub yaw: WrappedAngle<F>,
+    /// Pitch angle. 
+    pub pitch: Angle<F>,
+    /// Roll angle. 
+    pub roll: Angle<F>,
+}
+
+impl<F: Real> YawPitchRoll<F> {
+    /// Construct from a yaw and pitch, and a roll. 
+    pub fn new(dir: YawPitch<F>, roll: Angle<F>) -> Self {
+        YawPitchRoll {
+            yaw: dir.yaw,
+            pitch: dir.pitch,
+            roll,
+        }
+    }
+
+    /// Get the yaw and pitch, discarding the roll. 
+    pub fn yaw_pitch(&self) -> YawPitch<F> {
+        YawPitch {
+            yaw: self.yaw,
+            pitch: self.pitch,
+        }
+    }
+
+    /// Compute the rotation from the default orientation, looking along +Z, 
+    /// to this orientation. 
+    ///
+    /// This is a roll about the Z axis, followed by a pitch about the X axis, 
+    /// followed by a yaw about the Y axis. 
+    pub fn to_quaternion(&self) -> Quaternion<F> {
+        let two = F::one() + F::one();
+        let (sr, cr) = ((self.roll.rad() / two).sin(), (self.roll.rad() / two).cos());
+        let roll = Quaternion::from_xyzw(F::zero(), F::zero(), sr, cr);
+        self.yaw_pitch().to_quaternion() * roll
+    }
+
+    /// Compute the unit vector this orientation looks along, which is 
+    /// unaffected by roll. 
+    pub fn to_forward_vec(&self) -> Vec3<F> {
+        self.yaw_pitch().to_forward_vec()
+    }
+
+    /// Compute the unit vector to the right of this orientation. 
+    pub fn to_right_vec(&self) -> Vec3<F> {
+        let dir = self.yaw_pitch();
+        let flat_right = dir.to_right_vec();
+        let flat_up = dir.to_forward_vec().cross(flat_right);
+        flat_right * self.roll.cos() + flat_up * self.roll.sin()
+    }
+
+    /// Compute the unit vector upwards from this orientation. 
+    pub fn to_up_vec(&self) -> Vec3<F> {
+        let dir = self.yaw_pitch();
+        let flat_right = dir.to_right_vec();
+        let flat_up = dir.to_forward_vec().cross(flat_right);
+        flat_up * self.roll.cos() - flat_right * self.roll.sin()
+    }
+}
+
+impl<F: Real> From<YawPitch<F>> for YawPitchRoll<F> {
+    fn from(dir: YawPitch<F>) -> Self {
+        YawPitchRoll::new(dir, rad(F::zero()))
+    }
+}
+
+impl<F: Real> From<YawPitchRoll<F>> for YawPitch<F> {
+    fn from(orientation: YawPitchRoll<F>) -> Self {
+        orientation.yaw_pitch()
+    }
+}
+
+impl<F: Real + AbsDiffEq<Epsilon=F>> AbsDiffEq for YawPitchRoll<F> {
+    type Epsilon = F;
+
+    fn default_epsilon() -> F {
+        F::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: F) -> bool {
+        self.yaw_pitch().abs_diff_eq(&other.yaw_pitch(), epsilon)
+            && self.roll.abs_diff_eq(&other.roll, epsilon)
+    }
+}
+
+/// Camera projection mode. 
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Projection {
+    /// Perspective projection. 
+    Perspective {
+        /// Horizontal field of view. 
+        fov: Angle<f32>,
+    },
+    /// Orthographic projection. 
+    Orthographic {
+        /// Height of the view volume, in world units. The width is derived 
+        /// from the aspect ratio. 
+        height: f32,
+    },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective {
+            fov: deg(90.0),
+        }
+    }
+}
+
+impl<F: Real> YawPitch<F> {
+    /// Compute the orientation from a direction, which need not be 
+    /// normalized. 
+    ///
+    /// A vertical direction has an arbitrary yaw. 
+    pub fn from_direction(dir: Vec3<F>) -> Self {
+        let horizontal = (dir.x * dir.x + dir.z * dir.z).sqrt();
+        YawPitch {
+            yaw: atan2(dir.x, dir.z).into(),
+            pitch: atan2(-dir.y, horizontal),
+        }
+    }
+
+    /// Compute the rotation from the default orientation, looking along +Z, 
+    /// to this orientation. 
+    ///
+    /// This is a pitch about the X axis, followed by a yaw about the Y axis. 
+    pub fn to_quaternion(&self) -> Quaternion<F> {
+        let two = F::one() + F::one();
+        let (sy, cy) = ((self.yaw.rad() / two).sin(), (self.yaw.rad() / two).cos());
+        let (sp, cp) = ((self.pitch.rad() / two).sin(), (self.pitch.rad() / two).cos());
+        Quaternion::from_xyzw(cy * sp, sy * cp, -sy * sp, cy * cp)
+    }
+
+    /// Compute the unit vector this orientation looks along. 
+    ///
+    /// Positive pitch looks down, towards -Y. 
+    pub fn to_forward_vec(&self) -> Vec3<F> {
+        Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            -self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        )
+    }
+
+    /// Compute the unit vector to the right of this orientation. 
+    ///
+    /// This is always horizontal. 
+    pub fn to_right_vec(&self) -> Vec3<F> {
+        Vec3::new(self.yaw.cos(), F::zero(), -self.yaw.sin())
+    }
+}
+
+impl<F: Real + AbsDiffEq<Epsilon=F>> AbsDiffEq for YawPitch<F> {
+    type Epsilon = F;
+
+    fn default_epsilon() -> F {
+        F::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: F) -> bool {
+        self.yaw.abs_diff_eq(&other.yaw, epsilon)
+            && self.pitch.abs_diff_eq(&other.pitch, epsilon)
+    }
+}
+
+impl AbsDiffEq for Projection {
+    type Epsilon = f32;
+
+    fn default_epsilon() -> f32 {
+        f32::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: f32) -> bool {
+        match (*self, *other) {
+            (
+                Projection::Perspective { fov: a },
+                Projection::Perspective { fov: b },
+            ) => a.abs_diff_eq(&b, epsilon),
+            (
+                Projection::Orthographic { height: a },
+                Projection::Orthographic { height: b },
+            ) => a.abs_diff_eq(&b, epsilon),
+            _ => false,
+        }
+    }
+}
+
 /// Defining information for a camera (location, orientation, etc). 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Camera {
     /// Camera position. 
     pub pos: Vec3<f32>,
     /// Camera direction. 
     pub dir: YawPitch<f32>,
-    /// Camera horizontal field of view. 
-    pub fov: Angle<f32>,
+    /// Camera roll about its forward direction, applied after `dir`. See 
+    /// `YawPitchRoll`. 
+    #[serde(default)]
+    pub roll: Angle<f32>,
+    /// Camera projection mode. 
+    pub projection: Projection,
     /// Camer near plane distance. 
     pub near: f32,
     /// Camera far plane distance. 
     pub far: f32,
     /// Camera aspect ratio, width / height. 
     pub aspect_ratio: f32,
+    /// Whether to use a reversed depth range, where the near plane is at 
+    /// depth 1 and the far plane is at depth 0. 
+    ///
+    /// This gives much better depth precision far from the camera. In this 
+    /// mode, perspective projections have no far plane, and `far` is only 
+    /// used for orthographic projections. 
+    pub reverse_z: bool,
 }
 
 impl Default for Camera {
@@ -35,31 +247,301 @@ impl Default for Camera {
         Camera {
             pos: Default::default(),
             dir: Default::default(),
-            fov: deg(90.0),
+            roll: Default::default(),
+            projection: Default::default(),
             near: 0.1,
             far: 100.0,
             aspect_ratio: 1.0,
+            reverse_z: false,
         }
     }
 }
 
+impl AbsDiffEq for Camera {
+    type Epsilon = f32;
+
+    fn default_epsilon() -> f32 {
+        f32::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: f32) -> bool {
+        self.pos.abs_diff_eq(&other.pos, epsilon)
+            && self.dir.abs_diff_eq(&other.dir, epsilon)
+            && self.roll.abs_diff_eq(&other.roll, epsilon)
+            && self.projection.abs_diff_eq(&other.projection, epsilon)
+            && self.near.abs_diff_eq(&other.near, epsilon)
+            && self.far.abs_diff_eq(&other.far, epsilon)
+            && self.aspect_ratio.abs_diff_eq(&other.aspect_ratio, epsilon)
+            && self.reverse_z == other.reverse_z
+    }
+}
+
 impl Camera {
-    /// Compute the camera's perspective projection matrix. 
+    /// Compute the camera's projection matrix. 
     ///
-    /// Left handed, zero-to-one. 
+    /// Left handed, zero-to-one. If `reverse_z` is true, the depth range is 
+    /// reversed, and perspective projections have an infinite far plane. 
     pub fn proj(&self) -> Mat4<f32> {
-        Mat4::perspective_lh_zo(
-            (self.fov).clamp(deg(30.0), deg(150.0)).rad(),
-            self.aspect_ratio,
-            self.near,
-            self.far,
-        )
+        if self.reverse_z {
+            return self.proj_reverse_z();
+        }
+        match self.projection {
+            Projection::Perspective { fov } => Mat4::perspective_lh_zo(
+                fov.clamp(deg(30.0), deg(150.0)).rad(),
+                self.aspect_ratio,
+                self.near,
+                self.far,
+            ),
+            Projection::Orthographic { height } => {
+                let half_h = height / 2.0;
+                let half_w = half_h * self.aspect_ratio;
+                Mat4::orthographic_lh_zo(FrustumPlanes {
+                    left: -half_w,
+                    right: half_w,
+                    bottom: -half_h,
+                    top: half_h,
+                    near: self.near,
+                    far: self.far,
+                })
+            }
+        }
+    }
+
+    /// Compute the reverse-Z projection matrix. 
+    fn proj_reverse_z(&self) -> Mat4<f32> {
+        match self.projection {
+            Projection::Perspective { fov } => {
+                // depth = near / z, which approaches 0 at infinity
+                let f = 1.0 / (fov.clamp(deg(30.0), deg(150.0)).rad() / 2.0).tan();
+                Mat4::new(
+                    f / self.aspect_ratio, 0.0, 0.0, 0.0,
+                    0.0, f, 0.0, 0.0,
+                    0.0, 0.0, 0.0, self.near,
+                    0.0, 0.0, 1.0, 0.0,
+                )
+            }
+            Projection::Orthographic { height } => {
+                // depth = (far - z) / (far - near)
+                let half_h = height / 2.0;
+                let half_w = half_h * self.aspect_ratio;
+                let depth = self.far - self.near;
+                Mat4::new(
+                    1.0 / half_w, 0.0, 0.0, 0.0,
+                    0.0, 1.0 / half_h, 0.0, 0.0,
+                    0.0, 0.0, -1.0 / depth, self.far / depth,
+                    0.0, 0.0, 0.0, 1.0,
+                )
+            }
+        }
+    }
+
+    /// Get the camera's direction and roll. 
+    pub fn orientation(&self) -> YawPitchRoll<f32> {
+        YawPitchRoll::new(self.dir, self.roll)
+    }
+
+    /// Set the camera's direction and roll. 
+    pub fn set_orientation(&mut self, orientation: YawPitchRoll<f32>) {
+        self.dir = orientation.yaw_pitch();
+        self.roll = orientation.roll;
     }
 
     /// Compute the camera's view matrix. 
     pub fn view(&self) -> Mat4<f32> {
-        Mat4::rotation_x(-self.dir.pitch.rad())
+        Mat4::rotation_z(-self.roll.rad())
+            * Mat4::rotation_x(-self.dir.pitch.rad())
             * Mat4::rotation_y(-self.dir.yaw.rad())
             * Mat4::translation_3d(-self.pos)
     }
-}
\ No newline at end of file
+
+    /// Compute the view-projection matrix, which transforms to clip space 
+    /// with a zero-to-one depth range, as used by the GPU. 
+    pub fn view_proj(&self) -> Mat4<f32> {
+        self.proj_zo() * self.view()
+    }
+
+    /// Compute the projection matrix, corrected to a zero-to-one depth range 
+    /// if needed. 
+    fn proj_zo(&self) -> Mat4<f32> {
+        let proj = self.proj();
+        if self.reverse_z {
+            return proj;
+        }
+        // vek's zero-to-one projections may actually have a depth range of 
+        // -1 to 1, which is what `CORR` corrects for in the renderer
+        let near = proj * Vec4::new(0.0, 0.0, self.near, 1.0);
+        if near.z / near.w < -0.5 {
+            Mat4::new(
+                1.0, 0.0, 0.0, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 0.5, 0.5,
+                0.0, 0.0, 0.0, 1.0,
+            ) * proj
+        } else {
+            proj
+        }
+    }
+
+    /// Compute the camera's view frustum, in world space. 
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.view_proj())
+    }
+
+    /// Compute the world-space ray through a point in normalized device 
+    /// coordinates, where x and y range from -1 to 1, and +y is up. 
+    ///
+    /// Returns (origin, direction), where the origin is on the near plane and 
+    /// the direction is normalized. 
+    pub fn ray_from_ndc(&self, ndc: Vec2<f32>) -> (Vec3<f32>, Vec3<f32>) {
+        // the far plane may be at infinity, so unproject a nearer depth
+        let (near_depth, far_depth) = if self.reverse_z {
+            (1.0, 0.5)
+        } else {
+            (0.0, 1.0)
+        };
+        let inv = self.view_proj().inverted();
+        let unproject = |depth: f32| {
+            let p = inv * Vec4::new(ndc.x, ndc.y, depth, 1.0);
+            Vec3::from(p) / p.w
+        };
+        let origin = unproject(near_depth);
+        let dir = (unproject(far_depth) - origin).normalized();
+        (origin, dir)
+    }
+
+    /// Compute the world-space ray through the center of the screen, such as 
+    /// for picking the block under the crosshair. 
+    ///
+    /// Returns (origin, direction), as `ray_from_ndc`. 
+    pub fn ray_center(&self) -> (Vec3<f32>, Vec3<f32>) {
+        self.ray_from_ndc(Vec2::zero())
+    }
+}
+
+#[test]
+fn reverse_z_test() {
+    let depth = |cam: &Camera, z: f32| {
+        let clip = cam.proj() * Vec4::new(0.0, 0.0, z, 1.0);
+        clip.z / clip.w
+    };
+
+    let cam = Camera {
+        reverse_z: true,
+        ..Default::default()
+    };
+    assert!((depth(&cam, cam.near) - 1.0).abs() < 1e-6);
+    assert!(depth(&cam, 10.0) > depth(&cam, 1000.0));
+    assert!(depth(&cam, 1.0e6) > 0.0 && depth(&cam, 1.0e6) < 1.0e-6);
+
+    let cam = Camera {
+        reverse_z: true,
+        projection: Projection::Orthographic { height: 10.0 },
+        ..Default::default()
+    };
+    assert!((depth(&cam, cam.near) - 1.0).abs() < 1e-6);
+    assert!(depth(&cam, cam.far).abs() < 1e-6);
+}
+
+#[test]
+fn camera_serde_test() {
+    use approx::assert_abs_diff_eq;
+
+    let cam = Camera {
+        pos: Vec3::new(1.0, 2.0, 3.0),
+        dir: YawPitch {
+            yaw: deg(270.0).into(),
+            pitch: deg(-30.0),
+        },
+        ..Default::default()
+    };
+    // going through a `Value` puts the nested tables after the plain values, 
+    // which toml requires
+    let toml = toml::to_string(&toml::Value::try_from(&cam).unwrap()).unwrap();
+    let cam2: Camera = toml::from_str(&toml).unwrap();
+    assert_abs_diff_eq!(cam, cam2);
+}
+
+#[test]
+fn yaw_pitch_conversion_test() {
+    use approx::assert_abs_diff_eq;
+
+    for &(yaw, pitch) in &[(0.0, 0.0), (90.0, 0.0), (200.0, 30.0), (315.0, -60.0)] {
+        let dir = YawPitch {
+            yaw: deg(yaw).into(),
+            pitch: deg(pitch),
+        };
+        let forward = dir.to_forward_vec();
+        assert_abs_diff_eq!(forward.magnitude(), 1.0, epsilon = 1e-5);
+        assert_abs_diff_eq!(forward.dot(dir.to_right_vec()), 0.0, epsilon = 1e-5);
+        assert_abs_diff_eq!(YawPitch::from_direction(forward * 3.0), dir, epsilon = 1e-5);
+
+        let quat = dir.to_quaternion();
+        assert_abs_diff_eq!(quat * Vec3::unit_z(), forward, epsilon = 1e-5);
+        assert_abs_diff_eq!(quat * Vec3::unit_x(), dir.to_right_vec(), epsilon = 1e-5);
+    }
+
+    // looking along +X, as in the view matrix
+    let dir = YawPitch::from_direction(Vec3::<f32>::unit_x());
+    assert_abs_diff_eq!(dir.yaw.angle(), deg(90.0), epsilon = 1e-5);
+}
+
+#[test]
+fn ray_test() {
+    use approx::assert_abs_diff_eq;
+
+    for &reverse_z in &[false, true] {
+        let mut cam = Camera {
+            pos: Vec3::new(1.0, 2.0, 3.0),
+            dir: YawPitch {
+                yaw: deg(120.0).into(),
+                pitch: deg(20.0),
+            },
+            reverse_z,
+            ..Default::default()
+        };
+        let forward = cam.dir.to_forward_vec();
+
+        let (origin, dir) = cam.ray_center();
+        assert_abs_diff_eq!(origin, cam.pos + forward * cam.near, epsilon = 1e-4);
+        assert_abs_diff_eq!(dir, forward, epsilon = 1e-4);
+
+        // rays through the top of the screen point upwards of forward
+        let (_, dir) = cam.ray_from_ndc(Vec2::new(0.0, 1.0));
+        assert!(dir.y > forward.y);
+
+        // orthographic rays are parallel, but offset
+        cam.projection = Projection::Orthographic { height: 10.0 };
+        let (origin, dir) = cam.ray_from_ndc(Vec2::new(0.0, 1.0));
+        assert_abs_diff_eq!(dir, forward, epsilon = 1e-4);
+        assert_abs_diff_eq!((origin - cam.pos).dot(forward), cam.near, epsilon = 1e-4);
+        assert_abs_diff_eq!(
+            (origin - cam.pos).magnitude_squared(),
+            5.0 * 5.0 + cam.near * cam.near,
+            epsilon = 1e-3,
+        );
+    }
+}
+
+#[test]
+fn yaw_pitch_roll_test() {
+    use approx::assert_abs_diff_eq;
+
+    let orientation = YawPitchRoll {
+        yaw: deg(30.0f32).into(),
+        pitch: deg(-20.0),
+        roll: deg(15.0),
+    };
+    let quat = orientation.to_quaternion();
+    assert_abs_diff_eq!(quat * Vec3::unit_z(), orientation.to_forward_vec(), epsilon = 1e-5);
+    assert_abs_diff_eq!(quat * Vec3::unit_x(), orientation.to_right_vec(), epsilon = 1e-5);
+    assert_abs_diff_eq!(quat * Vec3::unit_y(), orientation.to_up_vec(), epsilon = 1e-5);
+    assert!(orientation.to_right_vec().y > 0.0);
+
+    // the view matrix undoes the orientation
+    let mut cam = Camera::default();
+    cam.set_orientation(orientation);
+    assert_abs_diff_eq!(cam.orientation(), orientation, epsilon = 1e-6);
+    let view_dir = |v: Vec3<f32>| Vec3::<f32>::from(cam.view() * Vec4::from_direction(v));
+    assert_abs_diff_eq!(view_dir(orientation.to_forward_vec()), Vec3::unit_z(), epsilon = 1e-5);
+    assert_abs_diff_eq!(view_dir(orientation.to_up_vec()), Vec3::unit_y(), epsilon = 1e-5);
+}
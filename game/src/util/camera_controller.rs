@@ -0,0 +1,226 @@
+//! Smoothed camera movement. 
+
+use super::{
+    angle::{Angle, deg, rad},
+    camera::{Camera, YawPitch, Projection},
+};
+use vek::*;
+
+/// How a `CameraController` approaches its target. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Smoothing {
+    /// Jump to the target immediately. 
+    None,
+    /// Close a fixed fraction of the remaining distance over time, such that
+    /// half of it is closed every `half_life` seconds. 
+    Exponential {
+        half_life: f32,
+    },
+    /// Follow a critically damped spring, which starts and stops smoothly,
+    /// and reaches the target in roughly `smooth_time` seconds without
+    /// overshooting. 
+    Spring {
+        smooth_time: f32,
+    },
+}
+
+/// State which a `CameraController` moves towards. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraTarget {
+    /// Camera position. 
+    pub pos: Vec3<f32>,
+    /// Camera direction. 
+    pub dir: YawPitch<f32>,
+    /// Field of view, if using a perspective projection. 
+    pub fov: Angle<f32>,
+}
+
+impl CameraTarget {
+    /// Get the current state of a camera. 
+    ///
+    /// Orthographic cameras have a field of view of 90°, which is never
+    /// applied. 
+    pub fn from_camera(cam: &Camera) -> Self {
+        let fov = match cam.projection {
+            Projection::Perspective { fov } => fov,
+            Projection::Orthographic { .. } => deg(90.0),
+        };
+        CameraTarget {
+            pos: cam.pos,
+            dir: cam.dir,
+            fov,
+        }
+    }
+}
+
+/// Utility for smoothly moving a camera's position, direction, and field of
+/// view towards a target, such as for following a character or widening the
+/// field of view while sprinting. 
+///
+/// Each component is smoothed independently. Yaw is smoothed along the
+/// shortest rotation, so it doesn't spin the long way around the wrap point. 
+#[derive(Debug, Clone)]
+pub struct CameraController {
+    /// Smoothing of the camera's position. 
+    pub pos_smoothing: Smoothing,
+    /// Smoothing of the camera's yaw and pitch. 
+    pub dir_smoothing: Smoothing,
+    /// Smoothing of the camera's field of view. 
+    pub fov_smoothing: Smoothing,
+    current: CameraTarget,
+    // spring velocities
+    pos_vel: Vec3<f32>,
+    dir_vel: Vec2<f32>,
+    fov_vel: f32,
+}
+
+impl CameraController {
+    /// Construct, starting at the given state. 
+    pub fn new(start: CameraTarget) -> Self {
+        CameraController {
+            pos_smoothing: Smoothing::Spring { smooth_time: 0.15 },
+            dir_smoothing: Smoothing::None,
+            fov_smoothing: Smoothing::Exponential { half_life: 0.1 },
+            current: start,
+            pos_vel: Vec3::zero(),
+            dir_vel: Vec2::zero(),
+            fov_vel: 0.0,
+        }
+    }
+
+    /// Get the current, smoothed state. 
+    pub fn current(&self) -> CameraTarget {
+        self.current
+    }
+
+    /// Jump to a state immediately, such as after teleporting. 
+    pub fn snap(&mut self, target: CameraTarget) {
+        self.current = target;
+        self.pos_vel = Vec3::zero();
+        self.dir_vel = Vec2::zero();
+        self.fov_vel = 0.0;
+    }
+
+    /// Advance the smoothing by `dt` seconds towards `target`, and return the
+    /// new current state. 
+    pub fn update(&mut self, dt: f32, target: CameraTarget) -> CameraTarget {
+        let cur = &mut self.current;
+
+        for i in 0..3 {
+            cur.pos[i] = smooth(
+                self.pos_smoothing,
+                cur.pos[i],
+                target.pos[i],
+                &mut self.pos_vel[i],
+                dt,
+            );
+        }
+
+        // approach the target yaw along the shortest rotation
+        let yaw_delta = cur.dir.yaw.delta(target.dir.yaw).rad();
+        let yaw_step = smooth(self.dir_smoothing, 0.0, yaw_delta, &mut self.dir_vel.x, dt);
+        cur.dir.yaw += rad(yaw_step);
+        cur.dir.pitch = rad(smooth(
+            self.dir_smoothing,
+            cur.dir.pitch.rad(),
+            target.dir.pitch.rad(),
+            &mut self.dir_vel.y,
+            dt,
+        ));
+
+        cur.fov = rad(smooth(
+            self.fov_smoothing,
+            cur.fov.rad(),
+            target.fov.rad(),
+            &mut self.fov_vel,
+            dt,
+        ));
+
+        *cur
+    }
+
+    /// Apply the current state to a camera. 
+    ///
+    /// The field of view is only applied to perspective projections. 
+    pub fn apply(&self, cam: &mut Camera) {
+        cam.pos = self.current.pos;
+        cam.dir = self.current.dir;
+        if let Projection::Perspective { ref mut fov } = cam.projection {
+            *fov = self.current.fov;
+        }
+    }
+}
+
+/// Advance a single value towards a target. 
+fn smooth(smoothing: Smoothing, current: f32, target: f32, vel: &mut f32, dt: f32) -> f32 {
+    match smoothing {
+        Smoothing::None => {
+            *vel = 0.0;
+            target
+        }
+        Smoothing::Exponential { half_life } => {
+            if half_life <= 0.0 {
+                return target;
+            }
+            let remaining = 0.5f32.powf(dt / half_life);
+            target + (current - target) * remaining
+        }
+        Smoothing::Spring { smooth_time } => {
+            if smooth_time <= 0.0 {
+                *vel = 0.0;
+                return target;
+            }
+            // closed-form approximation of a critically damped spring
+            let omega = 2.0 / smooth_time;
+            let x = omega * dt;
+            let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+            let change = current - target;
+            let temp = (*vel + omega * change) * dt;
+            *vel = (*vel - omega * temp) * decay;
+            target + (change + temp) * decay
+        }
+    }
+}
+
+#[test]
+fn camera_controller_test() {
+    let start = CameraTarget {
+        pos: Vec3::zero(),
+        dir: YawPitch {
+            yaw: deg(350.0).into(),
+            pitch: deg(0.0),
+        },
+        fov: deg(90.0),
+    };
+    let target = CameraTarget {
+        pos: Vec3::new(10.0, 0.0, 0.0),
+        dir: YawPitch {
+            yaw: deg(10.0).into(),
+            pitch: deg(30.0),
+        },
+        fov: deg(100.0),
+    };
+
+    let mut controller = CameraController::new(start);
+    controller.dir_smoothing = Smoothing::Exponential { half_life: 0.5 };
+
+    // one half-life closes half the distance, the short way around
+    let cur = controller.update(0.5, target);
+    assert!(cur.dir.yaw.delta(deg(0.0).into()).deg().abs() < 1e-3);
+    assert!((cur.dir.pitch.deg() - 15.0).abs() < 1e-3);
+
+    // the spring approaches without overshooting
+    let mut last_x = 0.0;
+    for _ in 0..300 {
+        let cur = controller.update(1.0 / 60.0, target);
+        assert!(cur.pos.x >= last_x && cur.pos.x <= 10.0);
+        last_x = cur.pos.x;
+    }
+    let cur = controller.current();
+    assert!((cur.pos.x - 10.0).abs() < 1e-2);
+    assert!((cur.fov.deg() - 100.0).abs() < 1e-2);
+    assert!(cur.dir.yaw.delta(target.dir.yaw).deg().abs() < 0.05);
+
+    controller.snap(start);
+    assert_eq!(controller.current(), start);
+}
@@ -0,0 +1,298 @@
+//! Camera shake, view bob, and landing dip. 
+//!
+//! These are gameplay feedback layered over the camera when it's rendered,
+//! without changing the camera's actual position or direction, so they don't
+//! affect movement, picking, or what gets saved. 
+
+use super::{
+    angle::deg,
+    camera::Camera,
+    interp::{clamp01, lerp, smoothstep},
+};
+use vek::*;
+
+/// Trauma lost per second. 
+const TRAUMA_DECAY: f32 = 0.8;
+/// Rate at which the shake noise changes, in samples per second. 
+const SHAKE_FREQUENCY: f32 = 18.0;
+/// Yaw and pitch offset at full trauma, in degrees. 
+const MAX_SHAKE_ANGLE: f32 = 3.0;
+/// Roll offset at full trauma, in degrees. 
+const MAX_SHAKE_ROLL: f32 = 4.0;
+/// Position offset at full trauma. 
+const MAX_SHAKE_OFFSET: f32 = 0.08;
+
+/// Distance walked per step, in blocks. 
+const BOB_STEP_LENGTH: f32 = 1.4;
+/// Vertical bob at the reference speed, in blocks. 
+const BOB_HEIGHT: f32 = 0.05;
+/// Sideways sway at the reference speed, in blocks. 
+const BOB_SWAY: f32 = 0.035;
+/// Sideways tilt at the reference speed, in degrees. 
+const BOB_TILT: f32 = 0.6;
+/// Walking speed at which bob is at full strength. 
+const BOB_REFERENCE_SPEED: f32 = 5.0;
+/// Rate at which bob fades in and out, per second. 
+const BOB_FADE_RATE: f32 = 8.0;
+
+/// Dip per unit of landing speed, in blocks. 
+const LANDING_DIP_PER_SPEED: f32 = 0.015;
+/// Landing speed below which there's no dip. 
+const MIN_LANDING_SPEED: f32 = 4.0;
+/// Maximum landing dip, in blocks. 
+const MAX_LANDING_DIP: f32 = 0.35;
+/// Stiffness of the spring which recovers from a landing dip. 
+const LANDING_STIFFNESS: f32 = 120.0;
+/// Longest step the landing spring is simulated with, for stability. 
+const MAX_SPRING_STEP: f32 = 1.0 / 120.0;
+
+/// Visual camera effects, composited onto a camera when it's rendered. 
+///
+/// Effects are driven by gameplay, such as `add_shake` when something hits
+/// the player, and advanced once per frame by `update`. 
+#[derive(Debug, Clone)]
+pub struct CameraEffects {
+    enabled: bool,
+    // seconds of effects time, for sampling noise
+    time: f32,
+
+    // from 0 to 1, with shake proportional to its square
+    trauma: f32,
+
+    // movement, as last set
+    speed: f32,
+    on_ground: bool,
+    // walk cycle, in steps
+    bob_phase: f32,
+    // bob strength, from 0 to 1
+    bob_amount: f32,
+
+    // vertical offset and velocity of the landing dip spring
+    dip: f32,
+    dip_vel: f32,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        CameraEffects::new()
+    }
+}
+
+impl CameraEffects {
+    /// Construct with no effects active. 
+    pub fn new() -> Self {
+        CameraEffects {
+            enabled: true,
+            time: 0.0,
+            trauma: 0.0,
+            speed: 0.0,
+            on_ground: false,
+            bob_phase: 0.0,
+            bob_amount: 0.0,
+            dip: 0.0,
+            dip_vel: 0.0,
+        }
+    }
+
+    /// Whether effects are applied. 
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Set whether effects are applied, such as for players who find them
+    /// uncomfortable. Effects still advance while disabled. 
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Add trauma, from 0 to 1, which shakes the camera and decays over time. 
+    ///
+    /// Trauma is capped at 1, and shake is proportional to its square, so
+    /// small hits barely register, while repeated ones add up. 
+    pub fn add_shake(&mut self, trauma: f32) {
+        self.trauma = clamp01(self.trauma + trauma);
+    }
+
+    /// Get the current trauma, from 0 to 1. 
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Set how fast the player is moving horizontally, and whether they're
+    /// on the ground, which drives the walking bob. 
+    pub fn set_movement(&mut self, speed: f32, on_ground: bool) {
+        self.speed = speed;
+        self.on_ground = on_ground;
+    }
+
+    /// Dip the camera, as upon landing at `speed` downwards. 
+    pub fn add_landing(&mut self, speed: f32) {
+        if speed < MIN_LANDING_SPEED {
+            return;
+        }
+        let dip = ((speed - MIN_LANDING_SPEED) * LANDING_DIP_PER_SPEED).min(MAX_LANDING_DIP);
+        // a downwards kick of the spring, which dips by about `dip`
+        self.dip_vel -= dip * LANDING_STIFFNESS.sqrt() * std::f32::consts::E;
+    }
+
+    /// Advance effects by `dt` seconds. 
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+
+        // bob advances with distance walked, and fades in and out
+        let target = if self.on_ground {
+            clamp01(self.speed / BOB_REFERENCE_SPEED)
+        } else {
+            0.0
+        };
+        let fade = 1.0 - (-BOB_FADE_RATE * dt).exp();
+        self.bob_amount = lerp(self.bob_amount, target, fade);
+        if self.on_ground {
+            self.bob_phase = (self.bob_phase + self.speed * dt / BOB_STEP_LENGTH).rem_euclid(2.0);
+        }
+
+        // critically damped spring back to zero dip
+        let damping = 2.0 * LANDING_STIFFNESS.sqrt();
+        let mut remaining = dt;
+        while remaining > 0.0 {
+            let step = remaining.min(MAX_SPRING_STEP);
+            self.dip_vel += (-LANDING_STIFFNESS * self.dip - damping * self.dip_vel) * step;
+            self.dip += self.dip_vel * step;
+            remaining -= step;
+        }
+    }
+
+    /// Get the current shake, as yaw, pitch, and roll offsets in degrees,
+    /// and a position offset along the camera's right, up, and forward. 
+    fn shake(&self) -> (Vec3<f32>, Vec3<f32>) {
+        let shake = self.trauma * self.trauma;
+        if shake == 0.0 {
+            return (Vec3::zero(), Vec3::zero());
+        }
+        let t = self.time * SHAKE_FREQUENCY;
+        let angles = Vec3::new(
+            value_noise(0, t) * MAX_SHAKE_ANGLE,
+            value_noise(1, t) * MAX_SHAKE_ANGLE,
+            value_noise(2, t) * MAX_SHAKE_ROLL,
+        );
+        let offset = Vec3::new(
+            value_noise(3, t),
+            value_noise(4, t),
+            value_noise(5, t),
+        ) * MAX_SHAKE_OFFSET;
+        (angles * shake, offset * shake)
+    }
+
+    /// Get the current bob and dip, as a position offset along the camera's
+    /// flat right and world up, and a roll offset in degrees. 
+    fn bob(&self) -> (Vec2<f32>, f32) {
+        let angle = self.bob_phase * std::f32::consts::PI;
+        // one sway per two steps, and one bounce per step
+        let sway = angle.sin() * self.bob_amount;
+        let bounce = -angle.sin().abs() * BOB_HEIGHT * self.bob_amount;
+        (Vec2::new(sway * BOB_SWAY, bounce + self.dip), -sway * BOB_TILT)
+    }
+
+    /// Composite effects onto a camera, returning the camera to render with. 
+    pub fn apply(&self, cam: &Camera) -> Camera {
+        if !self.enabled {
+            return *cam;
+        }
+        let (shake_angles, shake_offset) = self.shake();
+        let (bob_offset, bob_roll) = self.bob();
+
+        let orientation = cam.orientation();
+        let right = orientation.to_right_vec();
+        let up = orientation.to_up_vec();
+        let forward = orientation.to_forward_vec();
+        let flat_right = cam.dir.to_right_vec();
+
+        let mut out = *cam;
+        out.pos += right * shake_offset.x + up * shake_offset.y + forward * shake_offset.z;
+        out.pos += flat_right * bob_offset.x + Vec3::unit_y() * bob_offset.y;
+        out.dir.yaw += deg(shake_angles.x);
+        out.dir.pitch = (out.dir.pitch + deg(shake_angles.y)).clamp(deg(-90.0), deg(90.0));
+        out.roll = out.roll + deg(shake_angles.z + bob_roll);
+        out
+    }
+}
+
+/// Smooth noise from -1 to 1, in one dimension, with a separate sequence for
+/// each channel. 
+fn value_noise(channel: u32, t: f32) -> f32 {
+    let cell = t.floor();
+    let a = lattice(channel, cell as i32);
+    let b = lattice(channel, cell as i32 + 1);
+    lerp(a, b, smoothstep(0.0, 1.0, t - cell))
+}
+
+/// Pseudo-random value from -1 to 1 for a lattice point. 
+fn lattice(channel: u32, i: i32) -> f32 {
+    let mut h = (i as u32).wrapping_mul(0x9E37_79B1) ^ channel.wrapping_mul(0x85EB_CA77);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297A_2D39);
+    h ^= h >> 15;
+    (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+#[test]
+fn camera_effects_test() {
+    use approx::assert_abs_diff_eq;
+
+    let cam = Camera {
+        pos: Vec3::new(1.0, 2.0, 3.0),
+        ..Default::default()
+    };
+    let mut effects = CameraEffects::new();
+    effects.update(0.1);
+    assert_abs_diff_eq!(effects.apply(&cam), cam);
+
+    // shake moves the camera, and decays
+    effects.add_shake(0.6);
+    effects.add_shake(0.6);
+    assert_eq!(effects.trauma(), 1.0);
+    effects.update(0.05);
+    let shaken = effects.apply(&cam);
+    assert!(shaken.pos != cam.pos);
+    assert!(shaken.pos.distance(cam.pos) <= MAX_SHAKE_OFFSET * 3.0f32.sqrt());
+    effects.update(2.0);
+    assert_eq!(effects.trauma(), 0.0);
+    assert_abs_diff_eq!(effects.apply(&cam), cam);
+
+    // walking bobs, but not in the air
+    effects.set_movement(5.0, false);
+    effects.update(1.0);
+    assert_abs_diff_eq!(effects.apply(&cam), cam);
+    effects.set_movement(5.0, true);
+    let mut lowest = cam.pos.y;
+    for _ in 0..60 {
+        effects.update(1.0 / 60.0);
+        lowest = lowest.min(effects.apply(&cam).pos.y);
+    }
+    assert!(lowest < cam.pos.y - BOB_HEIGHT * 0.5);
+    assert!(lowest >= cam.pos.y - BOB_HEIGHT - 1e-5);
+
+    // landing dips, then recovers
+    let mut effects = CameraEffects::new();
+    effects.add_landing(2.0);
+    effects.update(0.05);
+    assert_abs_diff_eq!(effects.apply(&cam), cam);
+    effects.add_landing(20.0);
+    let mut lowest = cam.pos.y;
+    for _ in 0..30 {
+        effects.update(1.0 / 60.0);
+        lowest = lowest.min(effects.apply(&cam).pos.y);
+    }
+    assert!(lowest < cam.pos.y - 0.1);
+    assert!(lowest >= cam.pos.y - MAX_LANDING_DIP - 1e-3);
+    effects.update(2.0);
+    assert_abs_diff_eq!(effects.apply(&cam).pos, cam.pos, epsilon = 1e-3);
+
+    // disabling leaves the camera alone
+    effects.add_shake(1.0);
+    effects.set_enabled(false);
+    assert_abs_diff_eq!(effects.apply(&cam), cam);
+}
@@ -0,0 +1,313 @@
+//! Geometric primitives, for culling, picking, and physics. 
+
+use std::ops::{Add, Sub};
+use vek::*;
+
+/// Axis-aligned bounding box, spanning from `min` to `max` inclusive. 
+///
+/// `Aabb<f32>` is a region of space, and `Aabb<i32>` is typically a range 
+/// of blocks. See `Aabb::<i32>::block_bounds` to convert between them. 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Aabb<T> {
+    /// Minimum corner. 
+    pub min: Vec3<T>,
+    /// Maximum corner. 
+    pub max: Vec3<T>,
+}
+
+impl<T: Copy + PartialOrd> Aabb<T> {
+    /// Construct from two opposite corners, in any order. 
+    pub fn new(a: Vec3<T>, b: Vec3<T>) -> Self {
+        let pick = |a: T, b: T, less: bool| if (a < b) == less { a } else { b };
+        Aabb {
+            min: Vec3::new(pick(a.x, b.x, true), pick(a.y, b.y, true), pick(a.z, b.z, true)),
+            max: Vec3::new(pick(a.x, b.x, false), pick(a.y, b.y, false), pick(a.z, b.z, false)),
+        }
+    }
+
+    /// Whether a point is within this box. 
+    pub fn contains_point(&self, point: Vec3<T>) -> bool {
+        self.min.x <= point.x && point.x <= self.max.x
+            && self.min.y <= point.y && point.y <= self.max.y
+            && self.min.z <= point.z && point.z <= self.max.z
+    }
+
+    /// Whether this box and another overlap, including touching. 
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+            && self.min.z <= other.max.z && other.min.z <= self.max.z
+    }
+
+    /// Smallest box which contains both this box and another. 
+    pub fn union(&self, other: &Self) -> Self {
+        let min = |a: T, b: T| if b < a { b } else { a };
+        let max = |a: T, b: T| if b > a { b } else { a };
+        Aabb {
+            min: Vec3::new(
+                min(self.min.x, other.min.x),
+                min(self.min.y, other.min.y),
+                min(self.min.z, other.min.z),
+            ),
+            max: Vec3::new(
+                max(self.max.x, other.max.x),
+                max(self.max.y, other.max.y),
+                max(self.max.z, other.max.z),
+            ),
+        }
+    }
+
+    /// The 8 corners of this box. 
+    pub fn corners(&self) -> [Vec3<T>; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z),
+            Vec3::new(b.x, a.y, a.z),
+            Vec3::new(a.x, b.y, a.z),
+            Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z),
+            Vec3::new(b.x, a.y, b.z),
+            Vec3::new(a.x, b.y, b.z),
+            Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+}
+
+impl<T: Copy + Add<Output=T> + Sub<Output=T>> Aabb<T> {
+    /// Size along each axis. 
+    pub fn size(&self) -> Vec3<T> {
+        self.max - self.min
+    }
+
+    /// Move by an offset. 
+    pub fn translated(&self, offset: Vec3<T>) -> Self {
+        Aabb {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+}
+
+impl Aabb<i32> {
+    /// Box containing a single block. 
+    pub fn block(pos: Vec3<i32>) -> Self {
+        Aabb {
+            min: pos,
+            max: pos,
+        }
+    }
+
+    /// Region of space which the blocks in this box occupy. 
+    ///
+    /// Each block spans one unit in the positive direction from its 
+    /// position. 
+    pub fn block_bounds(&self) -> Aabb<f32> {
+        Aabb {
+            min: self.min.map(|n| n as f32),
+            max: self.max.map(|n| (n + 1) as f32),
+        }
+    }
+
+    /// Number of blocks in this box. 
+    pub fn volume(&self) -> i64 {
+        let size = self.size().map(|n| n as i64 + 1);
+        size.x * size.y * size.z
+    }
+}
+
+impl Aabb<f32> {
+    /// Center point. 
+    pub fn center(&self) -> Vec3<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Range of blocks which overlap this box. 
+    pub fn blocks(&self) -> Aabb<i32> {
+        Aabb {
+            min: self.min.map(|n| n.floor() as i32),
+            max: self.max.map(|n| n.ceil() as i32 - 1).map2(
+                self.min.map(|n| n.floor() as i32),
+                |max, min| max.max(min),
+            ),
+        }
+    }
+}
+
+/// Plane, with a normal pointing towards its positive side. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane {
+    /// Unit normal, or zero for a degenerate plane. 
+    pub normal: Vec3<f32>,
+    /// Signed distance from the plane to the origin, along the normal. 
+    pub dist: f32,
+}
+
+impl Plane {
+    /// Construct from the coefficients of `ax + by + cz + d = 0`, 
+    /// normalizing them. 
+    ///
+    /// If `a`, `b`, and `c` are all zero, the plane is degenerate, and every 
+    /// point is on its positive side if `d` is non-negative, or negative side 
+    /// otherwise. 
+    pub fn from_coefficients(coefficients: Vec4<f32>) -> Self {
+        let normal = Vec3::new(coefficients.x, coefficients.y, coefficients.z);
+        let len = normal.magnitude();
+        if len > 0.0 {
+            Plane {
+                normal: normal / len,
+                dist: coefficients.w / len,
+            }
+        } else {
+            Plane {
+                normal: Vec3::zero(),
+                dist: coefficients.w.signum(),
+            }
+        }
+    }
+
+    /// Signed distance from the plane to a point, positive on the side the 
+    /// normal points towards. 
+    pub fn signed_distance(&self, point: Vec3<f32>) -> f32 {
+        self.normal.dot(point) + self.dist
+    }
+}
+
+/// View frustum, as the region on the positive side of 6 planes. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, and far planes, facing inwards. 
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extract the frustum from a view-projection matrix, which transforms 
+    /// to clip space with a zero-to-one depth range. 
+    ///
+    /// Either depth direction works, including reverse-Z with an infinite 
+    /// far plane, which results in a degenerate plane which contains 
+    /// everything. 
+    pub fn from_matrix(m: Mat4<f32>) -> Self {
+        let c = m.cols;
+        let row = |i: usize| match i {
+            0 => Vec4::new(c.x.x, c.y.x, c.z.x, c.w.x),
+            1 => Vec4::new(c.x.y, c.y.y, c.z.y, c.w.y),
+            2 => Vec4::new(c.x.z, c.y.z, c.z.z, c.w.z),
+            _ => Vec4::new(c.x.w, c.y.w, c.z.w, c.w.w),
+        };
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        // a clip space point is visible if -w <= x <= w, -w <= y <= w, 
+        // and 0 <= z <= w
+        Frustum {
+            planes: [
+                Plane::from_coefficients(r3 + r0),
+                Plane::from_coefficients(r3 - r0),
+                Plane::from_coefficients(r3 + r1),
+                Plane::from_coefficients(r3 - r1),
+                Plane::from_coefficients(r2),
+                Plane::from_coefficients(r3 - r2),
+            ],
+        }
+    }
+
+    /// Whether a point is within the frustum. 
+    pub fn contains_point(&self, point: Vec3<f32>) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    /// Whether a sphere may intersect the frustum. 
+    ///
+    /// This is conservative in the same way as `intersects_aabb`. 
+    pub fn intersects_sphere(&self, center: Vec3<f32>, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(center) >= -radius)
+    }
+
+    /// Whether a box may intersect the frustum. 
+    ///
+    /// This is conservative: it never returns false for a box which 
+    /// intersects, but may return true for a box near a corner of the 
+    /// frustum which doesn't. 
+    pub fn intersects_aabb(&self, aabb: &Aabb<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the normal
+            let corner = Vec3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            plane.signed_distance(corner) >= 0.0
+        })
+    }
+}
+
+#[test]
+fn aabb_test() {
+    let a = Aabb::new(Vec3::new(2, 0, 5), Vec3::new(0, 3, 1));
+    assert_eq!(a.min, Vec3::new(0, 0, 1));
+    assert_eq!(a.max, Vec3::new(2, 3, 5));
+    assert!(a.contains_point(Vec3::new(2, 3, 5)));
+    assert!(!a.contains_point(Vec3::new(3, 3, 5)));
+    assert_eq!(a.volume(), 3 * 4 * 5);
+
+    let b = Aabb::block(Vec3::new(2, 3, 6));
+    assert!(!a.intersects(&b));
+    assert!(a.intersects(&b.translated(Vec3::new(0, 0, -1))));
+    assert_eq!(a.union(&b), Aabb::new(Vec3::new(0, 0, 1), Vec3::new(2, 3, 6)));
+
+    let bounds = b.block_bounds();
+    assert_eq!(bounds.min, Vec3::new(2.0, 3.0, 6.0));
+    assert_eq!(bounds.max, Vec3::new(3.0, 4.0, 7.0));
+    assert_eq!(bounds.blocks(), b);
+    assert_eq!(bounds.center(), Vec3::new(2.5, 3.5, 6.5));
+}
+
+#[test]
+fn frustum_test() {
+    use super::{
+        angle::deg,
+        camera::{Camera, YawPitch},
+    };
+
+    for &reverse_z in &[false, true] {
+        // looking along +X
+        let cam = Camera {
+            dir: YawPitch {
+                yaw: deg(90.0).into(),
+                pitch: deg(0.0),
+            },
+            reverse_z,
+            ..Default::default()
+        };
+        let frustum = cam.frustum();
+
+        assert!(frustum.contains_point(Vec3::new(10.0, 0.0, 0.0)));
+        assert!(!frustum.contains_point(Vec3::new(-10.0, 0.0, 0.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.05, 0.0, 0.0)));
+        assert!(!frustum.contains_point(Vec3::new(10.0, 0.0, 50.0)));
+        assert_eq!(frustum.contains_point(Vec3::new(1000.0, 0.0, 0.0)), reverse_z);
+
+        let block = |x, y, z| Aabb::block(Vec3::new(x, y, z)).block_bounds();
+        assert!(frustum.intersects_aabb(&block(10, 0, 0)));
+        assert!(frustum.intersects_aabb(&block(0, -1, -1)));
+        assert!(!frustum.intersects_aabb(&block(-10, 0, 0)));
+        assert!(!frustum.intersects_aabb(&block(10, 0, 50)));
+        assert!(!frustum.intersects_aabb(&block(10, 50, 0)));
+
+        assert!(frustum.intersects_sphere(Vec3::new(10.0, 0.0, 0.0), 0.5));
+        assert!(frustum.intersects_sphere(Vec3::new(-1.0, 0.0, 0.0), 2.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(-1.0, 0.0, 0.0), 0.5));
+        assert!(!frustum.intersects_sphere(Vec3::new(10.0, 0.0, 50.0), 5.0));
+    }
+
+    // the identity matrix's frustum is the clip space volume itself
+    let frustum = Frustum::from_matrix(Mat4::identity());
+    assert!(frustum.contains_point(Vec3::new(0.99, -0.99, 0.99)));
+    assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 0.0)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.01)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 1.01, 0.5)));
+    assert!(frustum.intersects_sphere(Vec3::new(1.5, 0.0, 0.5), 0.6));
+    assert!(!frustum.intersects_sphere(Vec3::new(1.5, 0.0, 0.5), 0.4));
+    let aabb = |min: [f32; 3], max: [f32; 3]| Aabb::new(min.into(), max.into());
+    assert!(frustum.intersects_aabb(&aabb([0.9, 0.9, 0.9], [2.0, 2.0, 2.0])));
+    assert!(!frustum.intersects_aabb(&aabb([1.1, 0.0, 0.0], [2.0, 1.0, 1.0])));
+}
@@ -0,0 +1,235 @@
+//! Interpolation and easing functions. 
+//!
+//! Scalar functions are generic over `num_traits::real::Real`, so they work
+//! with `f32`, `f64`, and checked float types like `floatilla::Real`. 
+//! Interpolation parameters are from 0 to 1, and aren't clamped unless
+//! stated otherwise. 
+
+use super::angle::{Angle, WrappedAngle};
+use num_traits::{
+    real::Real,
+    cast::NumCast,
+};
+use vek::*;
+
+/// Convert a constant to a float type. 
+fn c<F: Real>(n: f64) -> F {
+    <F as NumCast>::from(n).unwrap()
+}
+
+/// Clamp to the range [0, 1]. 
+pub fn clamp01<F: Real>(x: F) -> F {
+    x.max(F::zero()).min(F::one())
+}
+
+/// Linearly interpolate from `a` to `b`. 
+pub fn lerp<F: Real>(a: F, b: F, t: F) -> F {
+    a + (b - a) * t
+}
+
+/// Inverse of `lerp`: get how far `x` is from `a` to `b`. 
+pub fn inv_lerp<F: Real>(a: F, b: F, x: F) -> F {
+    (x - a) / (b - a)
+}
+
+/// Map `x` from the range `from` to the range `to`, linearly. 
+pub fn remap<F: Real>(from: (F, F), to: (F, F), x: F) -> F {
+    lerp(to.0, to.1, inv_lerp(from.0, from.1, x))
+}
+
+/// GLSL-style smoothstep: 0 at `edge0` and below, 1 at `edge1` and above,
+/// and a smooth curve between with zero slope at the ends. 
+pub fn smoothstep<F: Real>(edge0: F, edge1: F, x: F) -> F {
+    let t = clamp01(inv_lerp(edge0, edge1, x));
+    t * t * (c::<F>(3.0) - c::<F>(2.0) * t)
+}
+
+/// Like `smoothstep`, but with zero curvature at the ends too, so that it
+/// starts and stops more gently. 
+pub fn smootherstep<F: Real>(edge0: F, edge1: F, x: F) -> F {
+    let t = clamp01(inv_lerp(edge0, edge1, x));
+    t * t * t * (t * (t * c::<F>(6.0) - c::<F>(15.0)) + c::<F>(10.0))
+}
+
+/// Cubic Hermite interpolation from `p0` with tangent `m0` to `p1` with
+/// tangent `m1`. 
+///
+/// Tangents are rates of change per unit of `t`. 
+pub fn hermite<F: Real>(p0: F, m0: F, p1: F, m1: F, t: F) -> F {
+    let [h00, h10, h01, h11] = hermite_basis(t);
+    h00 * p0 + h10 * m0 + h01 * p1 + h11 * m1
+}
+
+/// Cubic Hermite basis functions at `t`, as the weights of `p0`, `m0`, `p1`,
+/// and `m1`. 
+fn hermite_basis<F: Real>(t: F) -> [F; 4] {
+    let (one, two, three) = (F::one(), c::<F>(2.0), c::<F>(3.0));
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        two * t3 - three * t2 + one,
+        t3 - two * t2 + t,
+        three * t2 - two * t3,
+        t3 - t2,
+    ]
+}
+
+/// Linearly interpolate between vectors. 
+pub fn lerp_vec3<F: Real>(a: Vec3<F>, b: Vec3<F>, t: F) -> Vec3<F> {
+    a + (b - a) * t
+}
+
+/// Cubic Hermite interpolation between vectors. See `hermite`. 
+pub fn hermite_vec3<F: Real>(
+    p0: Vec3<F>,
+    m0: Vec3<F>,
+    p1: Vec3<F>,
+    m1: Vec3<F>,
+    t: F,
+) -> Vec3<F> {
+    let [h00, h10, h01, h11] = hermite_basis(t);
+    p0 * h00 + m0 * h10 + p1 * h01 + m1 * h11
+}
+
+/// Linearly interpolate between angles. 
+///
+/// This doesn't wrap around, so from 350° to 10° passes through 180°. See
+/// `lerp_wrapped_angle` for the shortest rotation. 
+pub fn lerp_angle<F: Real>(a: Angle<F>, b: Angle<F>, t: F) -> Angle<F> {
+    a + (b - a) * t
+}
+
+/// Interpolate between orientations along the shortest rotation. 
+pub fn lerp_wrapped_angle<F: Real>(
+    a: WrappedAngle<F>,
+    b: WrappedAngle<F>,
+    t: F,
+) -> WrappedAngle<F> {
+    a + a.delta(b) * t
+}
+
+/// Easing curve, which maps progress from 0 to 1 to a smoothed progress from
+/// 0 to 1, such as for animation. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Easing {
+    /// Constant speed. 
+    Linear,
+    /// Accelerate from zero speed. 
+    QuadIn,
+    /// Decelerate to zero speed. 
+    QuadOut,
+    /// Accelerate, then decelerate. 
+    QuadInOut,
+    /// Accelerate from zero speed, more sharply than `QuadIn`. 
+    CubicIn,
+    /// Decelerate to zero speed, more sharply than `QuadOut`. 
+    CubicOut,
+    /// Accelerate, then decelerate, more sharply than `QuadInOut`. 
+    CubicInOut,
+    /// Accelerate, then decelerate, along a sine wave. 
+    SineInOut,
+    /// Accelerate, then decelerate, along `smoothstep`. 
+    Smooth,
+    /// Accelerate, then decelerate, along `smootherstep`. 
+    Smoother,
+}
+
+impl Easing {
+    /// Ease progress `t`, which is clamped to [0, 1]. 
+    pub fn apply<F: Real>(self, t: F) -> F {
+        let t = clamp01(t);
+        let (one, two, half) = (F::one(), c::<F>(2.0), c::<F>(0.5));
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => one - (one - t) * (one - t),
+            Easing::QuadInOut => if t < half {
+                two * t * t
+            } else {
+                one - (two - two * t).powi(2) / two
+            },
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => one - (one - t).powi(3),
+            Easing::CubicInOut => if t < half {
+                c::<F>(4.0) * t.powi(3)
+            } else {
+                one - (two - two * t).powi(3) / two
+            },
+            Easing::SineInOut => {
+                let pi = c::<F>(std::f64::consts::PI);
+                (one - (t * pi).cos()) / two
+            }
+            Easing::Smooth => smoothstep(F::zero(), one, t),
+            Easing::Smoother => smootherstep(F::zero(), one, t),
+        }
+    }
+
+    /// Interpolate from `a` to `b` along this curve. 
+    pub fn lerp<F: Real>(self, a: F, b: F, t: F) -> F {
+        lerp(a, b, self.apply(t))
+    }
+}
+
+#[test]
+fn interp_test() {
+    use super::angle::deg;
+
+    let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+    assert!(close(lerp(2.0, 4.0, 0.25), 2.5));
+    assert!(close(inv_lerp(2.0, 4.0, 2.5), 0.25));
+    assert!(close(remap((0.0, 10.0), (100.0, 200.0), 5.0), 150.0));
+    assert_eq!(lerp(1.0f64, 3.0, 0.5), 2.0);
+
+    // steps are clamped, and symmetric about the middle
+    for &step in &[smoothstep::<f32>, smootherstep::<f32>] {
+        assert_eq!(step(1.0, 2.0, 0.0), 0.0);
+        assert_eq!(step(1.0, 2.0, 3.0), 1.0);
+        assert!(close(step(1.0, 2.0, 1.5), 0.5));
+        assert!(close(step(1.0, 2.0, 1.2) + step(1.0, 2.0, 1.8), 1.0));
+    }
+
+    // hermite passes through its endpoints, and with the tangents of a line
+    // it's the line
+    assert!(close(hermite(1.0, 5.0, 3.0, -2.0, 0.0), 1.0));
+    assert!(close(hermite(1.0, 5.0, 3.0, -2.0, 1.0), 3.0));
+    assert!(close(hermite(1.0, 2.0, 3.0, 2.0, 0.3), 1.6));
+    let v = hermite_vec3(Vec3::zero(), Vec3::one(), Vec3::one(), Vec3::one(), 0.5);
+    assert!(v.iter().all(|&n| close(n, 0.5)));
+    assert!(close(lerp_vec3(Vec3::zero(), Vec3::new(2.0, 4.0, 6.0), 0.5).y, 2.0));
+
+    // angles
+    assert!(close(lerp_angle(deg(350.0), deg(10.0), 0.5).deg(), 180.0));
+    let wrapped = lerp_wrapped_angle(deg(350.0).into(), deg(10.0).into(), 0.25);
+    assert!(close(wrapped.deg(), 355.0));
+
+    // easings go from 0 to 1, and are clamped
+    let easings = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::SineInOut,
+        Easing::Smooth,
+        Easing::Smoother,
+    ];
+    for &easing in &easings {
+        assert!(close(easing.apply(0.0), 0.0), "{:?}", easing);
+        assert!(close(easing.apply(1.0), 1.0), "{:?}", easing);
+        assert!(close(easing.apply(2.0), 1.0), "{:?}", easing);
+        // monotonic
+        let mut last = 0.0f32;
+        for i in 1..=20 {
+            let eased = easing.apply(i as f32 / 20.0);
+            assert!(eased >= last - 1e-6, "{:?}", easing);
+            last = eased;
+        }
+    }
+    assert!(close(Easing::QuadInOut.apply(0.5), 0.5));
+    assert!(close(Easing::CubicInOut.apply(0.5), 0.5));
+    assert!(Easing::QuadIn.apply(0.5f32) < 0.5);
+    assert!(Easing::QuadOut.apply(0.5f32) > 0.5);
+    assert!(close(Easing::QuadOut.lerp(10.0, 20.0, 0.5), 17.5));
+}
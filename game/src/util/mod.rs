@@ -4,4 +4,13 @@ pub mod angle;
 pub mod pool;
 pub mod fps_tracker;
 pub mod camera;
+pub mod camera_controller;
 pub mod axis_unit;
+pub mod geom;
+pub mod timestep;
+pub mod physics;
+pub mod rng;
+pub mod spatial;
+pub mod interp;
+pub mod scheduler;
+pub mod camera_effects;
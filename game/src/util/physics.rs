@@ -0,0 +1,298 @@
+//! Character physics against a grid of solid blocks. 
+
+use super::{
+    geom::Aabb,
+    axis_unit::{AxisUnit3, Axis3, Sign},
+};
+use vek::*;
+
+/// Distance within which a box is considered touching, rather than
+/// overlapping, a block face. 
+const EPSILON: f32 = 1e-4;
+
+/// A grid of blocks, which may be solid. 
+///
+/// This is implemented for closures, so a world can be queried however is
+/// convenient. 
+pub trait VoxelGrid {
+    /// Whether the block at a position is solid. 
+    fn is_solid(&self, block: Vec3<i32>) -> bool;
+}
+
+impl<F: Fn(Vec3<i32>) -> bool> VoxelGrid for F {
+    fn is_solid(&self, block: Vec3<i32>) -> bool {
+        self(block)
+    }
+}
+
+/// Box-shaped body which is moved by velocity and gravity, and collides with
+/// solid blocks. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KinematicBody {
+    /// Position of the bottom center of the box. 
+    pub pos: Vec3<f32>,
+    /// Size of the box. 
+    pub size: Vec3<f32>,
+    /// Velocity, in blocks per second. 
+    pub vel: Vec3<f32>,
+    /// Downwards acceleration, in blocks per second squared. 
+    pub gravity: f32,
+    /// Maximum downwards speed, in blocks per second. 
+    pub max_fall_speed: f32,
+    /// Whether the body was resting on a solid block after the last move. 
+    pub on_ground: bool,
+}
+
+impl KinematicBody {
+    /// Construct a body with a given bottom center position and size, at
+    /// rest, with default gravity. 
+    pub fn new(pos: Vec3<f32>, size: Vec3<f32>) -> Self {
+        KinematicBody {
+            pos,
+            size,
+            vel: Vec3::zero(),
+            gravity: 32.0,
+            max_fall_speed: 60.0,
+            on_ground: false,
+        }
+    }
+
+    /// Get the region of space the body occupies. 
+    pub fn aabb(&self) -> Aabb<f32> {
+        let half = Vec3::new(self.size.x / 2.0, 0.0, self.size.z / 2.0);
+        Aabb {
+            min: self.pos - half,
+            max: self.pos + half + Vec3::new(0.0, self.size.y, 0.0),
+        }
+    }
+
+    /// Jump with an upwards speed, if on the ground. 
+    ///
+    /// Returns whether the body jumped. 
+    pub fn jump(&mut self, speed: f32) -> bool {
+        if self.on_ground {
+            self.vel.y = speed;
+            self.on_ground = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Apply gravity, then move by the velocity for `dt` seconds, stopping
+    /// at solid blocks. 
+    ///
+    /// Movement is resolved one axis at a time, vertical first, so movement
+    /// into a wall slides along it. Velocity along an axis is zeroed when
+    /// movement along it is blocked. 
+    pub fn move_and_slide<W: VoxelGrid>(&mut self, world: &W, dt: f32) {
+        self.vel.y = (self.vel.y - self.gravity * dt).max(-self.max_fall_speed);
+
+        self.on_ground = false;
+        for &axis in &[1, 0, 2] {
+            let want = self.vel[axis] * dt;
+            let moved = sweep(world, self.aabb(), axis, want);
+            self.pos[axis] += moved;
+            if moved != want {
+                if axis == 1 && want < 0.0 {
+                    self.on_ground = true;
+                }
+                self.vel[axis] = 0.0;
+            }
+        }
+    }
+}
+
+/// Compute how far a box can move along an axis, up to a desired
+/// displacement, before hitting a solid block. 
+pub fn sweep<W: VoxelGrid>(world: &W, aabb: Aabb<f32>, axis: usize, want: f32) -> f32 {
+    if want == 0.0 {
+        return 0.0;
+    }
+
+    // range of blocks the box's cross-section covers on the other axes
+    let (a, b) = match axis {
+        0 => (1, 2),
+        1 => (0, 2),
+        _ => (0, 1),
+    };
+    let cross_range = |i: usize| {
+        (aabb.min[i] + EPSILON).floor() as i32..=(aabb.max[i] - EPSILON).floor() as i32
+    };
+    let layer_solid = |layer: i32| {
+        cross_range(a).any(|i| cross_range(b).any(|j| {
+            let mut block = Vec3::zero();
+            block[axis] = layer;
+            block[a] = i;
+            block[b] = j;
+            world.is_solid(block)
+        }))
+    };
+
+    if want > 0.0 {
+        let face = aabb.max[axis];
+        let first = (face - EPSILON).floor() as i32 + 1;
+        let last = (face + want - EPSILON).floor() as i32;
+        for layer in first..=last {
+            if layer_solid(layer) {
+                return (layer as f32 - face).max(0.0);
+            }
+        }
+    } else {
+        let face = aabb.min[axis];
+        let first = (face + EPSILON).floor() as i32 - 1;
+        let last = (face + want + EPSILON).floor() as i32;
+        for layer in (last..=first).rev() {
+            if layer_solid(layer) {
+                return ((layer + 1) as f32 - face).min(0.0);
+            }
+        }
+    }
+    want
+}
+
+/// Solid block hit by a ray. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayHit {
+    /// Position of the block. 
+    pub block: Vec3<i32>,
+    /// Face of the block which the ray entered through, or none if the ray
+    /// started inside the block. 
+    pub face: Option<AxisUnit3>,
+    /// Distance along the ray to the hit, in multiples of the ray
+    /// direction's length. 
+    pub dist: f32,
+}
+
+impl RayHit {
+    /// Get the position of the block adjacent to the face which was hit,
+    /// such as for placing a block against it. 
+    pub fn adjacent(&self) -> Option<Vec3<i32>> {
+        self.face.map(|face| self.block + face.to_vec())
+    }
+}
+
+/// Find the first solid block along a ray, up to a maximum distance, in
+/// multiples of the direction's length. 
+///
+/// This steps through every block the ray passes through, so it never skips
+/// over corners. 
+pub fn raycast<W: VoxelGrid>(
+    world: &W,
+    origin: Vec3<f32>,
+    dir: Vec3<f32>,
+    max_dist: f32,
+) -> Option<RayHit> {
+    let mut block = origin.map(|n| n.floor() as i32);
+    if world.is_solid(block) {
+        return Some(RayHit {
+            block,
+            face: None,
+            dist: 0.0,
+        });
+    }
+
+    // per axis: direction to step, distance between block boundaries, and
+    // distance to the next block boundary
+    let step = dir.map(|n| if n > 0.0 { 1 } else if n < 0.0 { -1 } else { 0 });
+    let delta = dir.map(|n| if n != 0.0 { 1.0 / n.abs() } else { f32::INFINITY });
+    let mut next = Vec3::zero();
+    for i in 0..3 {
+        next[i] = if step[i] > 0 {
+            (block[i] as f32 + 1.0 - origin[i]) * delta[i]
+        } else if step[i] < 0 {
+            (origin[i] - block[i] as f32) * delta[i]
+        } else {
+            f32::INFINITY
+        };
+    }
+
+    loop {
+        // step across whichever block boundary is closest
+        let i = if next.x <= next.y && next.x <= next.z {
+            0
+        } else if next.y <= next.z {
+            1
+        } else {
+            2
+        };
+        let dist = next[i];
+        if dist > max_dist {
+            return None;
+        }
+        block[i] += step[i];
+        next[i] += delta[i];
+
+        if world.is_solid(block) {
+            let axis = [Axis3::X, Axis3::Y, Axis3::Z][i];
+            let sign = if step[i] > 0 { Sign::Neg } else { Sign::Pos };
+            return Some(RayHit {
+                block,
+                face: Some(AxisUnit3::new(axis, sign)),
+                dist,
+            });
+        }
+    }
+}
+
+#[test]
+fn move_and_slide_test() {
+    // a floor at y = 0, with a wall at x = 3
+    let world = |block: Vec3<i32>| block.y == 0 || (block.x == 3 && block.y < 5);
+
+    let mut body = KinematicBody::new(Vec3::new(0.5, 3.0, 0.5), Vec3::new(0.6, 1.8, 0.6));
+    for _ in 0..120 {
+        body.move_and_slide(&world, 1.0 / 60.0);
+    }
+    assert!(body.on_ground);
+    assert!((body.pos.y - 1.0).abs() < 1e-4);
+    assert_eq!(body.vel.y, 0.0);
+
+    // walk into the wall, while sliding along it
+    for _ in 0..120 {
+        body.vel.x = 5.0;
+        body.vel.z = 1.0;
+        body.move_and_slide(&world, 1.0 / 60.0);
+    }
+    assert!((body.pos.x - (3.0 - 0.3)).abs() < 1e-4);
+    assert!(body.pos.z > 2.0);
+    assert!(body.on_ground);
+
+    // jump, which only works from the ground
+    assert!(body.jump(8.0));
+    assert!(!body.jump(8.0));
+    body.move_and_slide(&world, 1.0 / 60.0);
+    assert!(body.pos.y > 1.0);
+    assert!(!body.on_ground);
+
+    // fast movement doesn't tunnel through blocks
+    let mut body = KinematicBody::new(Vec3::new(0.5, 50.0, 0.5), Vec3::new(0.6, 1.8, 0.6));
+    body.vel.y = -1000.0;
+    body.max_fall_speed = 1000.0;
+    body.move_and_slide(&world, 1.0);
+    assert!((body.pos.y - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn raycast_test() {
+    let world = |block: Vec3<i32>| block == Vec3::new(3, 0, 0) || block.y < -5;
+
+    let hit = raycast(&world, Vec3::new(0.5, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0), 10.0).unwrap();
+    assert_eq!(hit.block, Vec3::new(3, 0, 0));
+    assert_eq!(hit.face, Some(AxisUnit3::WEST));
+    assert!((hit.dist - 2.5).abs() < 1e-4);
+    assert_eq!(hit.adjacent(), Some(Vec3::new(2, 0, 0)));
+
+    // out of reach
+    assert!(raycast(&world, Vec3::new(0.5, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0), 2.0).is_none());
+
+    // diagonally downwards onto the floor
+    let hit = raycast(&world, Vec3::new(0.5, 0.5, 0.5), Vec3::new(0.3, -1.0, 0.2), 100.0).unwrap();
+    assert_eq!(hit.block.y, -6);
+    assert_eq!(hit.face, Some(AxisUnit3::UP));
+
+    // starting inside a block
+    let hit = raycast(&world, Vec3::new(3.5, 0.5, 0.5), Vec3::new(0.0, 1.0, 0.0), 10.0).unwrap();
+    assert_eq!(hit.face, None);
+    assert_eq!(hit.adjacent(), None);
+}
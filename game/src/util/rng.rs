@@ -0,0 +1,112 @@
+//! Deterministic random numbers, derived from a world seed. 
+//!
+//! Rather than sharing one generator, each system and each chunk draws from
+//! its own stream, split off from the seed by name or position. A stream's
+//! numbers don't depend on how much any other stream has been used, so the
+//! world plays out the same way from the same seed. 
+
+use rand::{RngCore, Error};
+use rand_pcg::Pcg32;
+use vek::*;
+
+// domains of split streams, so a system and a chunk never share a stream
+const SYSTEM_DOMAIN: u64 = 1;
+const CHUNK_DOMAIN: u64 = 2;
+
+/// Seedable random number generator, which can be split into independent
+/// streams. 
+#[derive(Clone, Debug)]
+pub struct Rng {
+    seed: u64,
+    inner: Pcg32,
+}
+
+impl Rng {
+    /// Construct from a seed. 
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            seed,
+            inner: Pcg32::new(mix(seed, 0), mix(seed, 1)),
+        }
+    }
+
+    /// Construct from a random seed, for when reproducibility doesn't
+    /// matter. 
+    pub fn from_entropy() -> Self {
+        Rng::new(rand::random())
+    }
+
+    /// Get the seed this was constructed from. 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Split off the stream for a named system, such as `"ore_gen"`. 
+    pub fn for_system(&self, name: &str) -> Rng {
+        // FNV-1a, which unlike std's hasher is stable across releases
+        let hash = name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        Rng::new(mix(mix(self.seed, SYSTEM_DOMAIN), hash))
+    }
+
+    /// Split off the stream for a chunk. 
+    pub fn for_chunk(&self, pos: Vec3<i32>) -> Rng {
+        let seed = pos
+            .iter()
+            .fold(mix(self.seed, CHUNK_DOMAIN), |seed, &n| mix(seed, n as u32 as u64));
+        Rng::new(seed)
+    }
+}
+
+impl RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        self.inner.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.inner.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.inner.try_fill_bytes(dest)
+    }
+}
+
+/// Combine two numbers into a well-distributed seed, with the splitmix64
+/// finalizer. 
+fn mix(a: u64, b: u64) -> u64 {
+    let mut z = a ^ b.wrapping_add(0x9e3779b97f4a7c15).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[test]
+fn rng_test() {
+    let sequence = |mut rng: Rng| (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>();
+
+    // same seed, same numbers
+    assert_eq!(sequence(Rng::new(42)), sequence(Rng::new(42)));
+    assert_ne!(sequence(Rng::new(42)), sequence(Rng::new(43)));
+
+    // streams don't depend on use of the parent
+    let mut world = Rng::new(42);
+    let ore = sequence(world.for_system("ore_gen"));
+    world.next_u64();
+    assert_eq!(sequence(world.for_system("ore_gen")), ore);
+    let chunk = Vec3::new(1, -2, 3);
+    assert_eq!(sequence(world.for_chunk(chunk)), sequence(Rng::new(42).for_chunk(chunk)));
+
+    // distinct streams
+    assert_ne!(sequence(world.for_system("mobs")), ore);
+    assert_ne!(
+        sequence(world.for_chunk(Vec3::new(1, 2, 3))),
+        sequence(world.for_chunk(Vec3::new(3, 2, 1))),
+    );
+    assert_ne!(sequence(world.for_chunk(Vec3::zero())), sequence(world.clone()));
+}
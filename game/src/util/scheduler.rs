@@ -0,0 +1,237 @@
+//! Timers and frame-budgeted deferred work. 
+//!
+//! These are driven by the caller once per frame or tick, and measure time
+//! in seconds of `dt`, unlike the game clock's tick-based timers in
+//! `crate::time`. 
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Timer which fires after a duration, once or repeatedly, as it's advanced
+/// by `dt`. 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Timer {
+    duration: f32,
+    remaining: f32,
+    repeating: bool,
+    finished: bool,
+}
+
+impl Timer {
+    /// Construct a timer which fires once, after `duration` seconds. 
+    pub fn once(duration: f32) -> Self {
+        Timer {
+            duration,
+            remaining: duration,
+            repeating: false,
+            finished: false,
+        }
+    }
+
+    /// Construct a timer which fires every `duration` seconds. 
+    ///
+    /// Panics if `duration` isn't positive. 
+    pub fn repeating(duration: f32) -> Self {
+        assert!(duration > 0.0, "repeating timer duration must be positive");
+        Timer {
+            repeating: true,
+            ..Timer::once(duration)
+        }
+    }
+
+    /// Advance by `dt` seconds, and return how many times the timer fired. 
+    ///
+    /// A repeating timer can fire several times in one long step. A one-shot
+    /// timer fires at most once, and then stays finished until reset. 
+    pub fn tick(&mut self, dt: f32) -> u32 {
+        if self.finished {
+            return 0;
+        }
+        self.remaining -= dt;
+        if self.remaining > 0.0 {
+            return 0;
+        }
+        if self.repeating {
+            let fired = (-self.remaining / self.duration).floor() as u32 + 1;
+            self.remaining += self.duration * fired as f32;
+            fired
+        } else {
+            self.remaining = 0.0;
+            self.finished = true;
+            1
+        }
+    }
+
+    /// Restart from the full duration. 
+    pub fn reset(&mut self) {
+        self.remaining = self.duration;
+        self.finished = false;
+    }
+
+    /// Change the duration, and restart. 
+    pub fn set_duration(&mut self, duration: f32) {
+        self.duration = duration;
+        self.reset();
+    }
+
+    /// Get the duration, in seconds. 
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Get the seconds until the timer next fires. 
+    pub fn remaining(&self) -> f32 {
+        self.remaining
+    }
+
+    /// Get progress towards the timer next firing, from 0 to 1. 
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (1.0 - self.remaining / self.duration).max(0.0).min(1.0)
+    }
+
+    /// Whether this is a one-shot timer which has fired. 
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Queue of deferred work, of which a limited amount is done per frame. 
+///
+/// Each frame, `run` does tasks in the order they were pushed, until either
+/// the count budget or the time budget is used up, and leaves the rest for
+/// later frames. This spreads bursts of work, such as uploading many chunk
+/// meshes after a large edit, over several frames. 
+#[derive(Debug, Clone)]
+pub struct TaskQueue<T> {
+    tasks: VecDeque<T>,
+    budget: usize,
+    time_budget: Option<Duration>,
+}
+
+impl<T> TaskQueue<T> {
+    /// Construct with a budget of `budget` tasks per frame, and no time
+    /// budget. 
+    pub fn new(budget: usize) -> Self {
+        TaskQueue {
+            tasks: VecDeque::new(),
+            budget,
+            time_budget: None,
+        }
+    }
+
+    /// Set the maximum number of tasks done per frame. 
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+
+    /// Get the maximum number of tasks done per frame. 
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Set the maximum time spent on tasks per frame, or none for no limit. 
+    ///
+    /// At least one task is done per frame, however long it takes, unless the
+    /// count budget is 0. 
+    pub fn set_time_budget(&mut self, time_budget: Option<Duration>) {
+        self.time_budget = time_budget;
+    }
+
+    /// Add a task to the back of the queue. 
+    pub fn push(&mut self, task: T) {
+        self.tasks.push_back(task);
+    }
+
+    /// Get the number of tasks waiting. 
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Whether no tasks are waiting. 
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Keep only the tasks for which `f` returns true, such as to cancel
+    /// tasks which are no longer needed. 
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        self.tasks.retain(f);
+    }
+
+    /// Do tasks, within the budget. Return how many were done. 
+    ///
+    /// This should be called once per frame. 
+    pub fn run<F: FnMut(T)>(&mut self, mut f: F) -> usize {
+        let start = Instant::now();
+        let mut done = 0;
+        while done < self.budget {
+            if done > 0 && self.time_budget.map(|budget| start.elapsed() >= budget).unwrap_or(false) {
+                break;
+            }
+            let task = match self.tasks.pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+            f(task);
+            done += 1;
+        }
+        done
+    }
+
+    /// Do every task, regardless of the budget. Return how many were done. 
+    pub fn run_all<F: FnMut(T)>(&mut self, f: F) -> usize {
+        let count = self.tasks.len();
+        self.tasks.drain(..).for_each(f);
+        count
+    }
+}
+
+#[test]
+fn timer_test() {
+    let mut once = Timer::once(1.0);
+    assert_eq!(once.tick(0.6), 0);
+    assert!((once.progress() - 0.6).abs() < 1e-5);
+    assert_eq!(once.tick(0.6), 1);
+    assert!(once.is_finished());
+    assert_eq!(once.tick(5.0), 0);
+    once.reset();
+    assert_eq!(once.tick(1.0), 1);
+
+    let mut repeating = Timer::repeating(0.5);
+    assert_eq!(repeating.tick(0.25), 0);
+    assert_eq!(repeating.tick(0.25), 1);
+    // a long step fires several times, and keeps the remainder
+    assert_eq!(repeating.tick(1.1), 2);
+    assert!((repeating.remaining() - 0.4).abs() < 1e-5);
+    assert!(!repeating.is_finished());
+}
+
+#[test]
+fn task_queue_test() {
+    let mut queue = TaskQueue::new(2);
+    for i in 0..5 {
+        queue.push(i);
+    }
+    let mut done = Vec::new();
+    assert_eq!(queue.run(|i| done.push(i)), 2);
+    assert_eq!(done, vec![0, 1]);
+    queue.retain(|&i| i != 3);
+    assert_eq!(queue.run(|i| done.push(i)), 2);
+    assert_eq!(queue.run(|i| done.push(i)), 0);
+    assert_eq!(done, vec![0, 1, 2, 4]);
+
+    // the time budget still allows one task
+    queue.set_budget(10);
+    queue.set_time_budget(Some(Duration::from_secs(0)));
+    queue.push(5);
+    queue.push(6);
+    assert_eq!(queue.run(|i| done.push(i)), 1);
+    assert_eq!(queue.run_all(|i| done.push(i)), 1);
+    assert!(queue.is_empty());
+    assert_eq!(done, vec![0, 1, 2, 4, 5, 6]);
+}
@@ -0,0 +1,311 @@
+//! Spatial hash grid, for finding things near a point or region. 
+//!
+//! Space is divided into cubic cells of a fixed size. Each item has an
+//! axis-aligned bounding box, and is listed in every cell its box overlaps,
+//! so a query only looks at items in the cells it overlaps. This works well
+//! for broad-phase collision between many small things, such as entities or
+//! particles, when the cell size is around the size of the things. 
+
+use super::geom::Aabb;
+use std::collections::HashMap;
+use vek::*;
+
+/// Handle to an item in a `SpatialHash`. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SpatialKey {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    generation: u32,
+    // none if the slot is free
+    item: Option<(T, Aabb<f32>)>,
+}
+
+/// Spatial hash grid of items with bounding boxes. 
+#[derive(Debug, Clone)]
+pub struct SpatialHash<T> {
+    cell_size: f32,
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    cells: HashMap<Vec3<i32>, Vec<u32>>,
+    len: usize,
+}
+
+impl<T> SpatialHash<T> {
+    /// Construct empty, with cells `cell_size` units across. 
+    ///
+    /// Panics if `cell_size` isn't positive. 
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "spatial hash cell size must be positive");
+        SpatialHash {
+            cell_size,
+            slots: Vec::new(),
+            free: Vec::new(),
+            cells: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Get the size of cells. 
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Get the number of items. 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no items. 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the range of cells which a box overlaps. 
+    fn cell_range(&self, bounds: &Aabb<f32>) -> Aabb<i32> {
+        Aabb {
+            min: bounds.min.map(|n| (n / self.cell_size).floor() as i32),
+            max: bounds.max.map(|n| (n / self.cell_size).floor() as i32),
+        }
+    }
+
+    fn link(&mut self, index: u32, bounds: &Aabb<f32>) {
+        let range = self.cell_range(bounds);
+        for cell in cells_in(range) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    fn unlink(&mut self, index: u32, bounds: &Aabb<f32>) {
+        let range = self.cell_range(bounds);
+        for cell in cells_in(range) {
+            if let Some(items) = self.cells.get_mut(&cell) {
+                items.retain(|&i| i != index);
+                if items.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    fn slot(&self, key: SpatialKey) -> Option<&(T, Aabb<f32>)> {
+        self.slots
+            .get(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.item.as_ref())
+    }
+
+    /// Insert an item with a bounding box. 
+    pub fn insert(&mut self, item: T, bounds: Aabb<f32>) -> SpatialKey {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    item: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        self.link(index, &bounds);
+        let slot = &mut self.slots[index as usize];
+        slot.item = Some((item, bounds));
+        self.len += 1;
+        SpatialKey {
+            index,
+            generation: slot.generation,
+        }
+    }
+
+    /// Insert an item at a point. 
+    pub fn insert_point(&mut self, item: T, point: Vec3<f32>) -> SpatialKey {
+        self.insert(item, Aabb::new(point, point))
+    }
+
+    /// Remove an item, if it's still present. 
+    pub fn remove(&mut self, key: SpatialKey) -> Option<T> {
+        let (_, bounds) = *self.slot(key)?;
+        self.unlink(key.index, &bounds);
+        let slot = &mut self.slots[key.index as usize];
+        let (item, _) = slot.item.take().unwrap();
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(key.index);
+        self.len -= 1;
+        Some(item)
+    }
+
+    /// Move an item to a new bounding box. Return false if it's not present. 
+    pub fn update(&mut self, key: SpatialKey, bounds: Aabb<f32>) -> bool {
+        let old = match self.slot(key) {
+            Some(&(_, old)) => old,
+            None => return false,
+        };
+        // items usually move less than a cell between updates
+        if self.cell_range(&old) != self.cell_range(&bounds) {
+            self.unlink(key.index, &old);
+            self.link(key.index, &bounds);
+        }
+        self.slots[key.index as usize].item.as_mut().unwrap().1 = bounds;
+        true
+    }
+
+    /// Get an item, if it's still present. 
+    pub fn get(&self, key: SpatialKey) -> Option<&T> {
+        self.slot(key).map(|&(ref item, _)| item)
+    }
+
+    /// Get an item mutably, if it's still present. 
+    pub fn get_mut(&mut self, key: SpatialKey) -> Option<&mut T> {
+        self.slots
+            .get_mut(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.item.as_mut())
+            .map(|&mut (ref mut item, _)| item)
+    }
+
+    /// Get an item's bounding box, if it's still present. 
+    pub fn bounds(&self, key: SpatialKey) -> Option<Aabb<f32>> {
+        self.slot(key).map(|&(_, bounds)| bounds)
+    }
+
+    /// Iterate over all items, in no particular order. 
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item=(SpatialKey, &'s T)> + 's {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.item.as_ref().map(|&(ref item, _)| (
+                SpatialKey {
+                    index: i as u32,
+                    generation: slot.generation,
+                },
+                item,
+            )))
+    }
+
+    /// Iterate over the items whose bounding boxes intersect a box, including
+    /// touching, in no particular order. 
+    pub fn query_aabb<'s>(
+        &'s self,
+        bounds: Aabb<f32>,
+    ) -> impl Iterator<Item=(SpatialKey, &'s T)> + 's {
+        let range = self.cell_range(&bounds);
+        cells_in(range)
+            .filter_map(move |cell| self.cells.get(&cell).map(|items| (cell, items)))
+            .flat_map(move |(cell, items)| items.iter().map(move |&index| (cell, index)))
+            .filter_map(move |(cell, index)| {
+                let slot = &self.slots[index as usize];
+                let &(ref item, item_bounds) = slot.item.as_ref().unwrap();
+                // an item in several cells of the range is only yielded from
+                // the first of them
+                let first = Vec3::partial_max(self.cell_range(&item_bounds).min, range.min);
+                if cell == first && item_bounds.intersects(&bounds) {
+                    Some((
+                        SpatialKey {
+                            index,
+                            generation: slot.generation,
+                        },
+                        item,
+                    ))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Iterate over the items whose bounding boxes are within a distance of a
+    /// point, in no particular order. 
+    pub fn query_radius<'s>(
+        &'s self,
+        center: Vec3<f32>,
+        radius: f32,
+    ) -> impl Iterator<Item=(SpatialKey, &'s T)> + 's {
+        let bounds = Aabb {
+            min: center - radius,
+            max: center + radius,
+        };
+        self.query_aabb(bounds)
+            .filter(move |&(key, _)| {
+                let item_bounds = self.bounds(key).unwrap();
+                let closest = Vec3::partial_min(Vec3::partial_max(center, item_bounds.min), item_bounds.max);
+                closest.distance_squared(center) <= radius * radius
+            })
+    }
+
+    /// Remove all items. 
+    pub fn clear(&mut self) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            if slot.item.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(i as u32);
+            }
+        }
+        self.cells.clear();
+        self.len = 0;
+    }
+}
+
+/// Iterate over the cells in a range, inclusive. 
+fn cells_in(range: Aabb<i32>) -> impl Iterator<Item=Vec3<i32>> {
+    (range.min.x..=range.max.x)
+        .flat_map(move |x| (range.min.y..=range.max.y)
+            .flat_map(move |y| (range.min.z..=range.max.z)
+                .map(move |z| Vec3::new(x, y, z))))
+}
+
+#[test]
+fn spatial_hash_test() {
+    let mut hash = SpatialHash::new(2.0);
+    let a = hash.insert_point('a', Vec3::new(0.5, 0.5, 0.5));
+    let b = hash.insert_point('b', Vec3::new(10.0, 0.0, 0.0));
+    // spans many cells
+    let c = hash.insert('c', Aabb::new(Vec3::new(-5.0, -1.0, -1.0), Vec3::new(5.0, 1.0, 1.0)));
+    assert_eq!(hash.len(), 3);
+
+    let query = |hash: &SpatialHash<char>, min, max| {
+        let mut found = hash
+            .query_aabb(Aabb::new(min, max))
+            .map(|(_, &item)| item)
+            .collect::<Vec<_>>();
+        found.sort();
+        found
+    };
+    assert_eq!(query(&hash, Vec3::broadcast(-10.0), Vec3::broadcast(20.0)), vec!['a', 'b', 'c']);
+    assert_eq!(query(&hash, Vec3::broadcast(0.0), Vec3::broadcast(1.0)), vec!['a', 'c']);
+    assert_eq!(query(&hash, Vec3::new(4.0, 0.0, 0.0), Vec3::new(6.0, 0.0, 0.0)), vec!['c']);
+    assert!(query(&hash, Vec3::broadcast(30.0), Vec3::broadcast(40.0)).is_empty());
+
+    let mut near = hash
+        .query_radius(Vec3::new(9.0, 0.0, 0.0), 1.5)
+        .map(|(_, &item)| item)
+        .collect::<Vec<_>>();
+    near.sort();
+    assert_eq!(near, vec!['b']);
+    // the box corner is outside the radius, even though its cell is inside
+    assert_eq!(hash.query_radius(Vec3::new(7.0, 3.0, 0.0), 2.1).count(), 0);
+
+    // moving and removing
+    assert!(hash.update(a, Aabb::new(Vec3::broadcast(20.0), Vec3::broadcast(20.0))));
+    assert_eq!(query(&hash, Vec3::broadcast(0.0), Vec3::broadcast(1.0)), vec!['c']);
+    assert_eq!(query(&hash, Vec3::broadcast(19.0), Vec3::broadcast(21.0)), vec!['a']);
+    assert_eq!(hash.remove(b), Some('b'));
+    assert_eq!(hash.remove(b), None);
+    assert!(hash.get(b).is_none());
+    assert!(!hash.update(b, Aabb::new(Vec3::zero(), Vec3::zero())));
+
+    // keys of removed items aren't reused
+    let d = hash.insert_point('d', Vec3::new(10.0, 0.0, 0.0));
+    assert_ne!(b, d);
+    assert_eq!(hash.get(d), Some(&'d'));
+    *hash.get_mut(c).unwrap() = 'e';
+    let mut all = hash.iter().map(|(_, &item)| item).collect::<Vec<_>>();
+    all.sort();
+    assert_eq!(all, vec!['a', 'd', 'e']);
+
+    hash.clear();
+    assert!(hash.is_empty());
+    assert!(hash.get(a).is_none());
+    assert_eq!(hash.query_aabb(Aabb::new(Vec3::broadcast(-50.0), Vec3::broadcast(50.0))).count(), 0);
+}
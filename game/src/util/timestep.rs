@@ -0,0 +1,94 @@
+//! Fixed-timestep accumulator. 
+
+use std::time::Duration;
+
+/// Default number of simulation ticks per second. 
+pub const DEFAULT_TICK_RATE: u32 = 60;
+
+/// Default maximum number of ticks to run in a single frame. 
+pub const DEFAULT_MAX_TICKS_PER_FRAME: u32 = 5;
+
+/// Accumulator which converts variable frame times into a number of fixed
+/// simulation ticks. 
+///
+/// If frames take long enough that simulating them takes even longer, the
+/// number of ticks per frame would grow without bound. To prevent that, no
+/// more than a maximum number of ticks are run per frame, and time beyond
+/// that is dropped, which slows the simulation down instead. 
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    tick_dt: Duration,
+    max_ticks: u32,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    /// Construct with a tick rate, in ticks per second, and a maximum number
+    /// of ticks per frame. 
+    pub fn new(tick_rate: u32, max_ticks: u32) -> Self {
+        assert!(tick_rate > 0, "tick rate must be non-zero");
+        assert!(max_ticks > 0, "max ticks per frame must be non-zero");
+        FixedTimestep {
+            tick_dt: Duration::from_secs(1) / tick_rate,
+            max_ticks,
+            accumulator: Duration::from_secs(0),
+        }
+    }
+
+    /// Duration of a single tick. 
+    pub fn tick_dt(&self) -> Duration {
+        self.tick_dt
+    }
+
+    /// Duration of a single tick, in seconds. 
+    pub fn tick_secs(&self) -> f32 {
+        self.tick_dt.as_secs_f32()
+    }
+
+    /// Accumulate the time elapsed since the last frame, and return how many
+    /// ticks should be run this frame. 
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_dt && ticks < self.max_ticks {
+            self.accumulator -= self.tick_dt;
+            ticks += 1;
+        }
+        if self.accumulator >= self.tick_dt {
+            warn!("simulation falling behind, dropping {:?}", self.accumulator);
+            self.accumulator = Duration::from_secs(0);
+        }
+        ticks
+    }
+
+    /// Fraction of a tick accumulated but not yet run, from 0 to 1. 
+    ///
+    /// This can be used to interpolate between the last two simulated
+    /// states when rendering. 
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.tick_dt.as_secs_f64()) as f32
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        FixedTimestep::new(DEFAULT_TICK_RATE, DEFAULT_MAX_TICKS_PER_FRAME)
+    }
+}
+
+#[test]
+fn fixed_timestep_test() {
+    let mut timestep = FixedTimestep::new(10, 3);
+    let tick = timestep.tick_dt();
+    assert_eq!(tick, Duration::from_millis(100));
+
+    assert_eq!(timestep.advance(Duration::from_millis(50)), 0);
+    assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+    assert_eq!(timestep.advance(Duration::from_millis(175)), 2);
+    assert!((timestep.alpha() - 0.25).abs() < 1e-4);
+
+    // spiral-of-death protection
+    assert_eq!(timestep.advance(Duration::from_secs(10)), 3);
+    assert_eq!(timestep.alpha(), 0.0);
+    assert_eq!(timestep.advance(Duration::from_secs(0)), 0);
+}
@@ -0,0 +1,179 @@
+//! Biomes, which vary terrain by climate. 
+//!
+//! Each column of the world has a temperature and humidity from 0 to 1,
+//! which vary smoothly over hundreds of blocks. The climate picks the
+//! column's biome, which decides what blocks its terrain is made of, and the
+//! color grass is tinted. 
+
+use crate::util::rng::Rng;
+use noise::{Fbm, MultiFractal, NoiseFn, Seedable};
+use rand::RngCore;
+use vek::*;
+
+/// How many blocks across climate features are, roughly. 
+const CLIMATE_SCALE: f64 = 400.0;
+
+/// Kind of terrain, decided by climate. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    /// Cold, and covered in snow. 
+    Tundra,
+    /// Temperate grassland. 
+    Plains,
+    /// Temperate and humid grassland, with deeper soil. 
+    Forest,
+    /// Hot, dry, and covered in sand. 
+    Desert,
+}
+
+/// Temperature and humidity of a column, each from 0 to 1. 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Climate {
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+/// Blocks that a biome's terrain is made of, by name. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    /// Top block of the terrain. 
+    pub surface: &'static str,
+    /// Blocks under the surface block. 
+    pub subsurface: &'static str,
+    /// Number of subsurface blocks, above the stone. 
+    pub subsurface_depth: i32,
+    /// Surface block of terrain below sea level. 
+    pub underwater: &'static str,
+}
+
+impl Biome {
+    /// All biomes. 
+    pub const ALL: [Biome; 4] = [
+        Biome::Tundra,
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Desert,
+    ];
+
+    /// Pick the biome of a climate. 
+    pub fn from_climate(climate: Climate) -> Self {
+        if climate.temperature < 0.35 {
+            Biome::Tundra
+        } else if climate.temperature > 0.65 && climate.humidity < 0.5 {
+            Biome::Desert
+        } else if climate.humidity > 0.6 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+
+    /// Get the blocks the biome's terrain is made of. 
+    pub fn palette(self) -> Palette {
+        match self {
+            Biome::Tundra => Palette {
+                surface: "snow",
+                subsurface: "dirt",
+                subsurface_depth: 3,
+                underwater: "ice",
+            },
+            Biome::Plains => Palette {
+                surface: "grass",
+                subsurface: "dirt",
+                subsurface_depth: 3,
+                underwater: "sand",
+            },
+            Biome::Forest => Palette {
+                surface: "grass",
+                subsurface: "dirt",
+                subsurface_depth: 5,
+                underwater: "gravel",
+            },
+            Biome::Desert => Palette {
+                surface: "sand",
+                subsurface: "sand",
+                subsurface_depth: 6,
+                underwater: "sand",
+            },
+        }
+    }
+}
+
+/// Climate and biome of every column, derived from a world seed. 
+pub struct BiomeMap {
+    temperature: Fbm,
+    humidity: Fbm,
+}
+
+impl BiomeMap {
+    /// Construct from a world seed. 
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed).for_system("biomes");
+        let mut noise = || Fbm::new()
+            .set_seed(rng.next_u32())
+            .set_octaves(4)
+            .set_frequency(1.0 / CLIMATE_SCALE);
+        BiomeMap {
+            temperature: noise(),
+            humidity: noise(),
+        }
+    }
+
+    /// Get the climate of a column. 
+    pub fn climate(&self, column: Vec2<i32>) -> Climate {
+        let point = [column.x as f64, column.y as f64];
+        let sample = |noise: &Fbm| (noise.get(point) * 0.5 + 0.5).max(0.0).min(1.0) as f32;
+        Climate {
+            temperature: sample(&self.temperature),
+            humidity: sample(&self.humidity),
+        }
+    }
+
+    /// Get the biome of a column. 
+    pub fn biome(&self, column: Vec2<i32>) -> Biome {
+        Biome::from_climate(self.climate(column))
+    }
+
+    /// Get the color to tint grass in a column, which blends smoothly across
+    /// biome borders. 
+    pub fn grass_color(&self, column: Vec2<i32>) -> Rgb<f32> {
+        let climate = self.climate(column);
+        // tints of the extremes of climate, which grass textures are
+        // multiplied by
+        let cold_dry = Rgb::new(0.75, 0.85, 0.8);
+        let cold_wet = Rgb::new(0.65, 0.85, 0.85);
+        let hot_dry = Rgb::new(1.0, 0.9, 0.55);
+        let hot_wet = Rgb::new(0.6, 1.0, 0.5);
+        let dry = Lerp::lerp(cold_dry, hot_dry, climate.temperature);
+        let wet = Lerp::lerp(cold_wet, hot_wet, climate.temperature);
+        Lerp::lerp(dry, wet, climate.humidity)
+    }
+}
+
+#[test]
+fn biome_test() {
+    let climate = |temperature, humidity| Climate { temperature, humidity };
+    assert_eq!(Biome::from_climate(climate(0.1, 0.9)), Biome::Tundra);
+    assert_eq!(Biome::from_climate(climate(0.9, 0.1)), Biome::Desert);
+    assert_eq!(Biome::from_climate(climate(0.9, 0.9)), Biome::Forest);
+    assert_eq!(Biome::from_climate(climate(0.5, 0.5)), Biome::Plains);
+
+    // deterministic, and varies over space
+    let map = BiomeMap::new(7);
+    let columns = (0..64)
+        .map(|i| Vec2::new(i * 97, i * -61))
+        .collect::<Vec<_>>();
+    let climates = columns.iter().map(|&c| map.climate(c)).collect::<Vec<_>>();
+    let again = BiomeMap::new(7);
+    assert!(columns.iter().zip(&climates).all(|(&c, &climate)| again.climate(c) == climate));
+    assert!(climates.iter().any(|climate| *climate != climates[0]));
+    for climate in &climates {
+        assert!((0.0..=1.0).contains(&climate.temperature));
+        assert!((0.0..=1.0).contains(&climate.humidity));
+    }
+
+    // grass color is continuous
+    let a = map.grass_color(Vec2::new(100, 100));
+    let b = map.grass_color(Vec2::new(101, 100));
+    assert!((a - b).iter().all(|n| n.abs() < 0.05));
+}
@@ -0,0 +1,214 @@
+//! Terrain generation. 
+//!
+//! Terrain is a heightmap of rolling hills, derived from the world seed, and
+//! layered with the blocks of each column's biome:
+//!
+//! - Above the snow line, the surface is snow. 
+//! - Near sea level, the surface and subsurface are sand, for beaches. 
+//! - Below sea level, the surface is the biome's underwater block. 
+//! - Otherwise, the biome's surface block is over its subsurface blocks. 
+//!
+//! Under all of that is stone. 
+
+use super::{
+    BlockId,
+    BlockRegistry,
+    Chunk,
+    World,
+    CHUNK_SIZE,
+    biome::{Biome, BiomeMap},
+};
+use crate::util::rng::Rng;
+use noise::{Fbm, MultiFractal, NoiseFn, Seedable};
+use rand::RngCore;
+use std::collections::HashMap;
+use vek::*;
+use pear::*;
+
+/// Height of the sea, which is where beaches form. 
+pub const SEA_LEVEL: i32 = 0;
+/// Height above which terrain is covered in snow. 
+pub const SNOW_LINE: i32 = 36;
+/// Average height of terrain. 
+const BASE_HEIGHT: f64 = 6.0;
+/// How far above and below the average height hills reach. 
+const HILL_HEIGHT: f64 = 32.0;
+/// How many blocks across hills are, roughly. 
+const HILL_SCALE: f64 = 160.0;
+
+/// Terrain generator for a world seed. 
+pub struct WorldGen {
+    height: Fbm,
+    biomes: BiomeMap,
+    stone: BlockId,
+    beach: BlockId,
+    snow: BlockId,
+    // block IDs of each biome's palette
+    palettes: HashMap<Biome, PaletteIds>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct PaletteIds {
+    surface: BlockId,
+    subsurface: BlockId,
+    subsurface_depth: i32,
+    underwater: BlockId,
+}
+
+impl WorldGen {
+    /// Construct for a world seed, looking up block IDs in `blocks`. 
+    ///
+    /// Errors if any blocks used by terrain aren't defined. 
+    pub fn new(seed: u64, blocks: &BlockRegistry) -> Result<Self> {
+        let id = |name: &str| blocks
+            .id(name)
+            .ok_or_else(|| pear!({block=name}, "block used by terrain generation not defined"));
+        let mut palettes = HashMap::new();
+        for &biome in &Biome::ALL {
+            let palette = biome.palette();
+            palettes.insert(biome, PaletteIds {
+                surface: id(palette.surface)?,
+                subsurface: id(palette.subsurface)?,
+                subsurface_depth: palette.subsurface_depth,
+                underwater: id(palette.underwater)?,
+            });
+        }
+        Ok(WorldGen {
+            height: Fbm::new()
+                .set_seed(Rng::new(seed).for_system("terrain").next_u32())
+                .set_octaves(5)
+                .set_frequency(1.0 / HILL_SCALE),
+            biomes: BiomeMap::new(seed),
+            stone: id("stone")?,
+            beach: id("sand")?,
+            snow: id("snow")?,
+            palettes,
+        })
+    }
+
+    /// Get the biome map the generator uses. 
+    pub fn biomes(&self) -> &BiomeMap {
+        &self.biomes
+    }
+
+    /// Get the height of the highest block of terrain in a column. 
+    pub fn height(&self, column: Vec2<i32>) -> i32 {
+        let noise = self.height.get([column.x as f64, column.y as f64]);
+        (BASE_HEIGHT + noise * HILL_HEIGHT).round() as i32
+    }
+
+    /// Get the block of terrain in a column of a given height, `depth`
+    /// blocks below the surface. 
+    fn block(&self, biome: Biome, height: i32, depth: i32) -> BlockId {
+        let palette = self.palettes[&biome];
+        if depth > palette.subsurface_depth {
+            self.stone
+        } else if height > SNOW_LINE {
+            match depth {
+                0 => self.snow,
+                _ => palette.subsurface,
+            }
+        } else if height < SEA_LEVEL {
+            match depth {
+                0 => palette.underwater,
+                _ => palette.subsurface,
+            }
+        } else if height <= SEA_LEVEL + 2 && biome != Biome::Tundra {
+            self.beach
+        } else {
+            match depth {
+                0 => palette.surface,
+                _ => palette.subsurface,
+            }
+        }
+    }
+
+    /// Generate the terrain of a chunk, or `None` if it's empty. 
+    pub fn chunk(&self, pos: Vec3<i32>) -> Option<Chunk> {
+        let origin = pos * CHUNK_SIZE;
+        let mut chunk = Chunk::new();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let column = Vec2::new(origin.x + x, origin.z + z);
+                let height = self.height(column);
+                if height < origin.y {
+                    continue;
+                }
+                let biome = self.biomes.biome(column);
+                for y in 0..CHUNK_SIZE.min(height - origin.y + 1) {
+                    let depth = height - (origin.y + y);
+                    chunk.set(Vec3::new(x, y, z), self.block(biome, height, depth));
+                }
+            }
+        }
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+
+    /// Generate the terrain of every chunk from `min` to `max` inclusive, and
+    /// put it in the world. 
+    pub fn generate(&self, world: &mut World, min: Vec3<i32>, max: Vec3<i32>) {
+        puffin::profile_function!();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let pos = Vec3::new(x, y, z);
+                    world.set_chunk(pos, self.chunk(pos));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn world_gen_test() {
+    use super::{BlockDef, AIR};
+
+    let mut blocks = BlockRegistry::new();
+    for name in &["stone", "dirt", "grass", "sand", "snow", "ice", "gravel"] {
+        blocks
+            .register(BlockDef {
+                name: name.to_string(),
+                faces: [0; 6],
+                solid: true,
+                transparent: false,
+                hardness: 1.0,
+                light: 0,
+                tinted: false,
+            })
+            .unwrap();
+    }
+    let gen = WorldGen::new(3, &blocks).unwrap();
+    let stone = blocks.id("stone").unwrap();
+    let sand = blocks.id("sand").unwrap();
+
+    // blocks are layered down from the height of each column
+    let mut world = World::new();
+    gen.generate(&mut world, Vec3::new(-1, -3, -1), Vec3::new(0, 3, 0));
+    for x in -16..16 {
+        for z in -16..16 {
+            let column = Vec2::new(x, z);
+            let height = gen.height(column);
+            assert!(height >= -3 * CHUNK_SIZE && height < 4 * CHUNK_SIZE);
+            assert_eq!(world.block(Vec3::new(x, height + 1, z)), AIR);
+            let surface = world.block(Vec3::new(x, height, z));
+            assert_ne!(surface, AIR);
+            let beach = height >= SEA_LEVEL && height <= SEA_LEVEL + 2;
+            if beach && gen.biomes().biome(column) != Biome::Tundra {
+                assert_eq!(surface, sand);
+            }
+            assert_eq!(world.block(Vec3::new(x, height - 7, z)), stone);
+        }
+    }
+
+    // deterministic
+    let again = WorldGen::new(3, &blocks).unwrap();
+    assert_eq!(gen.chunk(Vec3::new(0, 0, 0)), again.chunk(Vec3::new(0, 0, 0)));
+    assert!(gen.chunk(Vec3::new(0, 10, 0)).is_none());
+
+    // missing blocks are an error
+    assert!(WorldGen::new(3, &BlockRegistry::new()).is_err());
+}
@@ -0,0 +1,481 @@
+//! Light levels of the world, propagated by flood fill. 
+//!
+//! There are two channels of light, each with levels from 0 to `MAX_LIGHT`:
+//!
+//! - Skylight is at full strength in any block with no opaque block above
+//!   it, and spreads sideways and down from there, such as under overhangs
+//!   and into caves. 
+//! - Block light spreads from blocks which emit light. 
+//!
+//! Light spreads through air and transparent blocks, losing a level with
+//! each block it passes through. When a block changes, only the light it
+//! affects is re-propagated. Skylight isn't spread sideways from below the
+//! lowest chunk of the world. 
+
+use super::{
+    BlockId,
+    BlockRegistry,
+    Chunk,
+    AIR,
+    CHUNK_SIZE,
+    block_chunk,
+    block_in_chunk,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use vek::*;
+
+/// Maximum level of light, in either channel. 
+pub const MAX_LIGHT: u8 = 15;
+
+/// Channel of light. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+/// Light levels at a block. 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct LightLevel {
+    pub sky: u8,
+    pub block: u8,
+}
+
+/// Directions light spreads in. 
+const NEIGHBORS: [Vec3<i32>; 6] = [
+    Vec3 { x: 1, y: 0, z: 0 },
+    Vec3 { x: -1, y: 0, z: 0 },
+    Vec3 { x: 0, y: 1, z: 0 },
+    Vec3 { x: 0, y: -1, z: 0 },
+    Vec3 { x: 0, y: 0, z: 1 },
+    Vec3 { x: 0, y: 0, z: -1 },
+];
+
+/// Light levels of a world, kept up to date with its blocks. 
+#[derive(Debug, Clone)]
+pub struct Light {
+    // light properties, indexed by block ID
+    props: Vec<BlockLight>,
+    // levels of chunks which differ from the default, indexed like blocks,
+    // with skylight in the high nibble and block light in the low nibble
+    chunks: HashMap<Vec3<i32>, Box<[u8]>>,
+    // y of the highest opaque block in each xz column which has any
+    heights: HashMap<Vec2<i32>, i32>,
+    // lowest y skylight is spread sideways from
+    floor: i32,
+    // chunks which contain blocks next to changed light
+    changed: HashSet<Vec3<i32>>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct BlockLight {
+    opaque: bool,
+    emits: u8,
+}
+
+impl Light {
+    /// Compute the light of a world's chunks from scratch. 
+    pub(super) fn new(blocks: &BlockRegistry, chunks: &HashMap<Vec3<i32>, Chunk>) -> Self {
+        let mut light = Light {
+            props: blocks
+                .iter()
+                .map(|(_, def)| BlockLight {
+                    opaque: !def.transparent,
+                    emits: def.light,
+                })
+                .collect(),
+            chunks: HashMap::new(),
+            heights: HashMap::new(),
+            floor: chunks
+                .keys()
+                .map(|pos| pos.y * CHUNK_SIZE - 1)
+                .min()
+                .unwrap_or(i32::MAX),
+            changed: HashSet::new(),
+        };
+
+        // heights come first, since they decide the default skylight of
+        // chunks which block light is stored in
+        for (&chunk_pos, chunk) in chunks {
+            for (i, &block) in chunk.blocks().iter().enumerate() {
+                if light.props(block).opaque {
+                    let pos = chunk_pos * CHUNK_SIZE + index_pos(i);
+                    let height = light.heights.entry(column(pos)).or_insert(pos.y);
+                    *height = (*height).max(pos.y);
+                }
+            }
+        }
+        let mut emitters = VecDeque::new();
+        for (&chunk_pos, chunk) in chunks {
+            for (i, &block) in chunk.blocks().iter().enumerate() {
+                let emits = light.props(block).emits;
+                if emits > 0 {
+                    let pos = chunk_pos * CHUNK_SIZE + index_pos(i);
+                    light.set(LightChannel::Block, pos, emits);
+                    emitters.push_back(pos);
+                }
+            }
+        }
+
+        // skylight spreads sideways from exposed blocks next to shadowed
+        // columns
+        let mut exposed = VecDeque::new();
+        for (&column, &height) in &light.heights {
+            for dir in &NEIGHBORS {
+                if dir.y != 0 {
+                    continue;
+                }
+                let next = column + Vec2::new(dir.x, dir.z);
+                let bottom = light.heights
+                    .get(&next)
+                    .map(|&h| h + 1)
+                    .unwrap_or(light.floor)
+                    .max(light.floor);
+                exposed.extend((bottom..height).map(|y| Vec3::new(next.x, y, next.y)));
+            }
+        }
+
+        light.spread(chunks, LightChannel::Sky, exposed);
+        light.spread(chunks, LightChannel::Block, emitters);
+        light.changed.clear();
+        light
+    }
+
+    /// Get the light levels at a block. 
+    pub fn get(&self, pos: Vec3<i32>) -> LightLevel {
+        LightLevel {
+            sky: self.level(LightChannel::Sky, pos),
+            block: self.level(LightChannel::Block, pos),
+        }
+    }
+
+    /// Get the level of a single channel of light at a block. 
+    pub fn level(&self, channel: LightChannel, pos: Vec3<i32>) -> u8 {
+        match self.chunks.get(&block_chunk(pos)) {
+            Some(levels) => unpack(levels[Chunk::index(block_in_chunk(pos))], channel),
+            None => self.default_level(channel, pos),
+        }
+    }
+
+    /// Whether a block is lit by the sky from directly above. 
+    pub fn is_exposed(&self, pos: Vec3<i32>) -> bool {
+        self.heights
+            .get(&column(pos))
+            .map(|&height| pos.y > height)
+            .unwrap_or(true)
+    }
+
+    /// Re-propagate light around a block which was changed from `old` to
+    /// what it is now. 
+    pub(super) fn block_changed(
+        &mut self,
+        chunks: &HashMap<Vec3<i32>, Chunk>,
+        pos: Vec3<i32>,
+        old: BlockId,
+    ) {
+        let new = block_at(chunks, pos);
+        let old_opaque = self.props(old).opaque;
+        let new_opaque = self.props(new).opaque;
+        self.floor = self.floor.min(block_chunk(pos).y * CHUNK_SIZE - 1);
+
+        let mut sky_removed = VecDeque::new();
+        let mut sky_added = VecDeque::new();
+        let column = column(pos);
+        let height = self.heights.get(&column).copied();
+        if new_opaque && height.map(|h| pos.y > h).unwrap_or(true) {
+            // the column below is now shadowed, down to the old highest block
+            self.heights.insert(column, pos.y);
+            let bottom = height.map(|h| h + 1).unwrap_or(self.floor).max(self.floor);
+            for y in bottom..=pos.y {
+                let below = Vec3::new(pos.x, y, pos.z);
+                self.mark(below);
+                self.set(LightChannel::Sky, below, 0);
+                sky_removed.push_back((below, MAX_LIGHT));
+            }
+        } else if old_opaque && !new_opaque && height == Some(pos.y) {
+            // the column below is now exposed, down to the next highest block
+            let next = (self.floor..pos.y)
+                .rev()
+                .find(|&y| self.props(block_at(chunks, Vec3::new(pos.x, y, pos.z))).opaque);
+            match next {
+                Some(y) => self.heights.insert(column, y),
+                None => self.heights.remove(&column),
+            };
+            let bottom = next.map(|y| y + 1).unwrap_or(self.floor);
+            for y in bottom..=pos.y {
+                let below = Vec3::new(pos.x, y, pos.z);
+                self.mark(below);
+                self.set(LightChannel::Sky, below, MAX_LIGHT);
+                sky_added.push_back(below);
+            }
+        }
+
+        let channels = vec![
+            (LightChannel::Sky, sky_removed, sky_added),
+            (LightChannel::Block, VecDeque::new(), VecDeque::new()),
+        ];
+        for (channel, mut removed, mut added) in channels {
+            let level = self.level(channel, pos);
+            let source = self.source(chunks, channel, pos);
+            if level != source {
+                self.set(channel, pos, source);
+            }
+            if level > source {
+                removed.push_back((pos, level));
+            }
+            if source > 0 {
+                added.push_back(pos);
+            }
+            // light spreads back in from around a block which stopped
+            // blocking it
+            if !new_opaque {
+                added.extend(NEIGHBORS.iter().map(|&dir| pos + dir));
+            }
+
+            added.extend(self.unspread(chunks, channel, removed));
+            self.spread(chunks, channel, added);
+        }
+    }
+
+    /// Take the set of chunks which contain blocks next to light which has
+    /// changed since the last call. 
+    pub(super) fn take_changed<'s>(&'s mut self) -> impl Iterator<Item=Vec3<i32>> + 's {
+        self.changed.drain()
+    }
+
+    fn props(&self, block: BlockId) -> BlockLight {
+        // unknown blocks are treated as air, like in the registry
+        self.props
+            .get(block as usize)
+            .copied()
+            .unwrap_or(self.props[AIR as usize])
+    }
+
+    /// Level of light a block produces by itself. 
+    fn source(&self, chunks: &HashMap<Vec3<i32>, Chunk>, channel: LightChannel, pos: Vec3<i32>) -> u8 {
+        match channel {
+            LightChannel::Sky => self.default_level(channel, pos),
+            LightChannel::Block => self.props(block_at(chunks, pos)).emits,
+        }
+    }
+
+    /// Level of light at blocks in chunks with no stored levels. 
+    fn default_level(&self, channel: LightChannel, pos: Vec3<i32>) -> u8 {
+        match channel {
+            LightChannel::Sky if self.is_exposed(pos) => MAX_LIGHT,
+            _ => 0,
+        }
+    }
+
+    fn set(&mut self, channel: LightChannel, pos: Vec3<i32>, level: u8) {
+        let chunk_pos = block_chunk(pos);
+        if !self.chunks.contains_key(&chunk_pos) {
+            if level == self.default_level(channel, pos) {
+                return;
+            }
+            let levels = (0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE)
+                .map(|i| {
+                    let pos = chunk_pos * CHUNK_SIZE + index_pos(i as usize);
+                    pack(self.default_level(LightChannel::Sky, pos), 0)
+                })
+                .collect();
+            self.chunks.insert(chunk_pos, levels);
+        }
+
+        let packed = &mut self.chunks.get_mut(&chunk_pos).unwrap()
+            [Chunk::index(block_in_chunk(pos))];
+        if unpack(*packed, channel) == level {
+            return;
+        }
+        *packed = match channel {
+            LightChannel::Sky => pack(level, unpack(*packed, LightChannel::Block)),
+            LightChannel::Block => pack(unpack(*packed, LightChannel::Sky), level),
+        };
+        self.mark(pos);
+    }
+
+    /// Mark the chunks of every block which a change of light at `pos`
+    /// could affect the mesh of. 
+    fn mark(&mut self, pos: Vec3<i32>) {
+        let min = block_chunk(pos - Vec3::one());
+        let max = block_chunk(pos + Vec3::one());
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    self.changed.insert(Vec3::new(x, y, z));
+                }
+            }
+        }
+    }
+
+    /// Spread light outwards from blocks, breadth-first. 
+    fn spread(
+        &mut self,
+        chunks: &HashMap<Vec3<i32>, Chunk>,
+        channel: LightChannel,
+        mut queue: VecDeque<Vec3<i32>>,
+    ) {
+        while let Some(pos) = queue.pop_front() {
+            let level = self.level(channel, pos);
+            if level <= 1 {
+                continue;
+            }
+            for &dir in &NEIGHBORS {
+                let next = pos + dir;
+                if !self.props(block_at(chunks, next)).opaque
+                    && self.level(channel, next) < level - 1
+                {
+                    self.set(channel, next, level - 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Remove light which spread from blocks whose light was removed, given
+    /// with the level they had, breadth-first. 
+    ///
+    /// Returns the blocks bordering the removed light which are lit from
+    /// elsewhere, to spread light back in from. 
+    fn unspread(
+        &mut self,
+        chunks: &HashMap<Vec3<i32>, Chunk>,
+        channel: LightChannel,
+        mut queue: VecDeque<(Vec3<i32>, u8)>,
+    ) -> VecDeque<Vec3<i32>> {
+        let mut refill = VecDeque::new();
+        while let Some((pos, level)) = queue.pop_front() {
+            for &dir in &NEIGHBORS {
+                let next = pos + dir;
+                let next_level = self.level(channel, next);
+                if next_level == 0 {
+                    continue;
+                }
+                if next_level < level {
+                    // may have been lit by the removed light
+                    let source = self.source(chunks, channel, next);
+                    if source < next_level {
+                        self.set(channel, next, source);
+                        queue.push_back((next, next_level));
+                    }
+                    if source > 0 {
+                        refill.push_back(next);
+                    }
+                } else {
+                    refill.push_back(next);
+                }
+            }
+        }
+        refill
+    }
+}
+
+fn block_at(chunks: &HashMap<Vec3<i32>, Chunk>, pos: Vec3<i32>) -> BlockId {
+    chunks.get(&block_chunk(pos))
+        .map(|chunk| chunk.get(block_in_chunk(pos)))
+        .unwrap_or(AIR)
+}
+
+/// Get the xz column a block is in. 
+fn column(pos: Vec3<i32>) -> Vec2<i32> {
+    Vec2::new(pos.x, pos.z)
+}
+
+/// Inverse of `Chunk::index`. 
+fn index_pos(i: usize) -> Vec3<i32> {
+    let i = i as i32;
+    Vec3::new(i / (CHUNK_SIZE * CHUNK_SIZE), i / CHUNK_SIZE % CHUNK_SIZE, i % CHUNK_SIZE)
+}
+
+fn pack(sky: u8, block: u8) -> u8 {
+    sky << 4 | block
+}
+
+fn unpack(packed: u8, channel: LightChannel) -> u8 {
+    match channel {
+        LightChannel::Sky => packed >> 4,
+        LightChannel::Block => packed & 0xf,
+    }
+}
+
+#[test]
+fn light_test() {
+    use super::{BlockDef, World};
+
+    let mut blocks = BlockRegistry::new();
+    let mut block = |name: &str, transparent, light| blocks
+        .register(BlockDef {
+            name: name.to_owned(),
+            faces: [0; 6],
+            solid: true,
+            transparent,
+            hardness: 1.0,
+            light,
+            tinted: false,
+        })
+        .unwrap();
+    let stone = block("stone", false, 0);
+    let glass = block("glass", true, 0);
+    let lamp = block("lamp", false, 14);
+
+    let mut world = World::new();
+    world.set_block(Vec3::new(0, 10, 0), stone);
+    world.enable_light(&blocks);
+    let light = |world: &World, pos: Vec3<i32>| world.light().unwrap().get(pos);
+    assert_eq!(light(&world, Vec3::new(0, 11, 0)).sky, MAX_LIGHT);
+    assert_eq!(light(&world, Vec3::new(0, 9, 0)).sky, MAX_LIGHT - 1);
+
+    // a closed room is dark
+    for x in -3..=3 {
+        for y in 20..=26 {
+            for z in -3..=3 {
+                if [x, z].iter().any(|&n: &i32| n.abs() == 3) || y == 20 || y == 26 {
+                    world.set_block(Vec3::new(x, y, z), stone);
+                }
+            }
+        }
+    }
+    world.take_remesh();
+    assert_eq!(light(&world, Vec3::new(0, 22, 0)), LightLevel::default());
+    assert_eq!(light(&world, Vec3::new(0, 27, 0)).sky, MAX_LIGHT);
+
+    world.set_block(Vec3::new(0, 22, 0), lamp);
+    assert_eq!(light(&world, Vec3::new(0, 22, 0)).block, 14);
+    assert_eq!(light(&world, Vec3::new(2, 22, 1)).block, 11);
+    assert_eq!(light(&world, Vec3::new(4, 22, 0)).block, 0);
+    assert!(world.take_remesh().contains(&Vec3::new(0, 1, 0)));
+
+    world.set_block(Vec3::new(2, 26, 2), glass);
+    assert_eq!(light(&world, Vec3::new(2, 21, 2)).sky, MAX_LIGHT);
+    assert_eq!(light(&world, Vec3::new(0, 21, 2)).sky, MAX_LIGHT - 2);
+
+    // incremental changes agree with computing from scratch
+    let check = |world: &World| {
+        let fresh = Light::new(&blocks, &world.chunks);
+        for x in -6..=6 {
+            for y in 5..=30 {
+                for z in -6..=6 {
+                    let pos = Vec3::new(x, y, z);
+                    assert_eq!(light(world, pos), fresh.get(pos), "at {}", pos);
+                }
+            }
+        }
+    };
+    check(&world);
+
+    world.set_block(Vec3::new(0, 22, 0), AIR);
+    world.set_block(Vec3::new(2, 26, 2), stone);
+    assert_eq!(light(&world, Vec3::new(0, 22, 0)), LightLevel::default());
+    check(&world);
+
+    world.set_block(Vec3::new(3, 22, 0), glass);
+    world.set_block(Vec3::new(1, 21, 1), lamp);
+    world.set_block(Vec3::new(0, 10, 0), glass);
+    check(&world);
+
+    let chunk = world.chunk(Vec3::new(0, 1, 0)).cloned();
+    world.replace_chunk(Vec3::new(0, 1, 0), None);
+    check(&world);
+    world.replace_chunk(Vec3::new(0, 1, 0), chunk);
+    check(&world);
+}
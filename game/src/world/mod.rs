@@ -0,0 +1,419 @@
+//! Block world storage. 
+//!
+//! The world is divided into cubic chunks of blocks, which are stored
+//! sparsely, and which can be persisted to disk with the `save` module. 
+
+use crate::util::physics::VoxelGrid;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use vek::*;
+use pear::*;
+
+pub mod save;
+pub mod registry;
+pub mod light;
+pub mod biome;
+pub mod gen;
+
+pub use crate::graphics::chunks::{CHUNK_SIZE, block_chunk};
+pub use registry::{BlockRegistry, BlockDef};
+pub use light::{Light, LightLevel, MAX_LIGHT};
+pub use biome::{Biome, BiomeMap};
+pub use gen::WorldGen;
+
+/// Number of blocks in a chunk. 
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Default interval between automatic flushes of modified chunks. 
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Numeric identifier of a block type. 
+pub type BlockId = u16;
+
+/// The empty block, which all chunks are initially filled with. 
+pub const AIR: BlockId = 0;
+
+/// Get a block's position relative to the chunk which contains it. 
+pub fn block_in_chunk(block: Vec3<i32>) -> Vec3<i32> {
+    block.map(|n| n.rem_euclid(CHUNK_SIZE))
+}
+
+/// Cube of blocks, `CHUNK_SIZE` on each side. 
+#[derive(Clone, PartialEq, Eq)]
+pub struct Chunk {
+    // indexed x-major, then y, then z
+    blocks: Box<[BlockId]>,
+}
+
+impl Chunk {
+    /// Construct a chunk filled with air. 
+    pub fn new() -> Self {
+        Chunk {
+            blocks: vec![AIR; CHUNK_VOLUME].into_boxed_slice(),
+        }
+    }
+
+    /// Construct a chunk from its blocks, in index order. 
+    ///
+    /// Panics if the number of blocks is not `CHUNK_VOLUME`. 
+    pub fn from_blocks(blocks: Vec<BlockId>) -> Self {
+        assert_eq!(blocks.len(), CHUNK_VOLUME, "wrong number of blocks for chunk");
+        Chunk {
+            blocks: blocks.into_boxed_slice(),
+        }
+    }
+
+    /// Get the blocks, in index order. 
+    pub fn blocks(&self) -> &[BlockId] {
+        &self.blocks
+    }
+
+    /// Get the block at a position within the chunk. 
+    pub fn get(&self, pos: Vec3<i32>) -> BlockId {
+        self.blocks[Self::index(pos)]
+    }
+
+    /// Set the block at a position within the chunk, and return the block
+    /// which was there before. 
+    pub fn set(&mut self, pos: Vec3<i32>, block: BlockId) -> BlockId {
+        let i = Self::index(pos);
+        let old = self.blocks[i];
+        self.blocks[i] = block;
+        old
+    }
+
+    /// Whether the chunk consists entirely of air. 
+    pub fn is_empty(&self) -> bool {
+        self.blocks.iter().all(|&block| block == AIR)
+    }
+
+    fn index(pos: Vec3<i32>) -> usize {
+        debug_assert!(
+            pos.iter().all(|&n| n >= 0 && n < CHUNK_SIZE),
+            "position out of chunk bounds",
+        );
+        ((pos.x * CHUNK_SIZE + pos.y) * CHUNK_SIZE + pos.z) as usize
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Chunk::new()
+    }
+}
+
+impl std::fmt::Debug for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Chunk")
+            .field("non_air", &self.blocks.iter().filter(|&&b| b != AIR).count())
+            .finish()
+    }
+}
+
+/// Sparse, unbounded grid of blocks. 
+///
+/// Modified chunks are tracked as dirty. If the world has a save directory,
+/// either from being loaded or saved, dirty chunks are written back to it by
+/// `flush`, periodically by `maintain`, and when the world is dropped. 
+///
+/// Modified and loaded chunks are separately tracked as needing to be
+/// re-meshed, until taken by `take_remesh`. 
+///
+/// Once enabled with `enable_light`, the world's light is kept up to date as
+/// its blocks change, and chunks whose lighting changed are also re-meshed. 
+#[derive(Debug)]
+pub struct World {
+    chunks: HashMap<Vec3<i32>, Chunk>,
+    dirty: HashSet<Vec3<i32>>,
+    remesh: HashSet<Vec3<i32>>,
+    save_dir: Option<PathBuf>,
+    // seed for world generation and gameplay randomness
+    seed: u64,
+    // whether the seed is in the save directory
+    seed_saved: bool,
+    // none until enabled
+    light: Option<Light>,
+    flush_interval: Duration,
+    last_flush: Instant,
+}
+
+impl World {
+    /// Construct an empty world, with no save directory, and a random seed. 
+    pub fn new() -> Self {
+        World {
+            chunks: HashMap::new(),
+            dirty: HashSet::new(),
+            remesh: HashSet::new(),
+            save_dir: None,
+            seed: rand::random(),
+            seed_saved: false,
+            light: None,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Load a world from a save directory, which becomes its save
+    /// directory. 
+    ///
+    /// A directory which doesn't exist yet loads as an empty world, with a
+    /// random seed. 
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let chunks = save::load_chunks(path)
+            .wrap_err(|| pear!({path=path}, "failed to load world"))?;
+        let seed = save::load_seed(path)
+            .wrap_err(|| pear!({path=path}, "failed to load world"))?;
+        let mut world = World::new();
+        world.remesh = chunks.keys().copied().collect();
+        world.chunks = chunks;
+        if let Some(seed) = seed {
+            world.seed = seed;
+            world.seed_saved = true;
+        }
+        world.save_dir = Some(path.to_owned());
+        Ok(world)
+    }
+
+    /// Write every chunk to a save directory, which becomes the world's save
+    /// directory. 
+    ///
+    /// Chunks already saved in that directory which the world doesn't have
+    /// are left alone. 
+    pub fn save_all<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let chunks = self.chunks.keys()
+            .chain(self.dirty.iter())
+            .map(|&pos| (pos, self.chunks.get(&pos)));
+        save::save_chunks(path, chunks)
+            .wrap_err(|| pear!({path=path}, "failed to save world"))?;
+        save::save_seed(path, self.seed)
+            .wrap_err(|| pear!({path=path}, "failed to save world"))?;
+        self.seed_saved = true;
+        self.dirty.clear();
+        self.save_dir = Some(path.to_owned());
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// Write dirty chunks to the save directory, if there is one. 
+    pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+        let path = match self.save_dir {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        if !self.seed_saved {
+            save::save_seed(path, self.seed)
+                .wrap_err(|| pear!({path=path}, "failed to flush world seed"))?;
+            self.seed_saved = true;
+        }
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+        let chunks = self.dirty.iter().map(|&pos| (pos, self.chunks.get(&pos)));
+        save::save_chunks(path, chunks)
+            .wrap_err(|| pear!({path=path}, "failed to flush world chunks"))?;
+        trace!("flushed {} world chunks", self.dirty.len());
+        self.dirty.clear();
+        Ok(())
+    }
+
+    /// Flush dirty chunks if the flush interval has elapsed since the last
+    /// flush. This should be called regularly, such as once per tick. 
+    pub fn maintain(&mut self) -> Result<()> {
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the save directory, if there is one. 
+    pub fn save_dir(&self) -> Option<&Path> {
+        self.save_dir.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Get the seed from which the world's randomness derives, which is
+    /// saved with the world. 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Set the world's seed, such as before generating a new world. 
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.seed_saved = false;
+    }
+
+    /// Set the interval between automatic flushes. 
+    pub fn set_flush_interval(&mut self, interval: Duration) {
+        self.flush_interval = interval;
+    }
+
+    /// Get the number of chunks modified since they were last saved. 
+    pub fn dirty_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Take the set of chunks which have been loaded or modified since the
+    /// last call, and so need to be re-meshed. 
+    pub fn take_remesh(&mut self) -> Vec<Vec3<i32>> {
+        if let Some(ref mut light) = self.light {
+            let chunks = &self.chunks;
+            self.remesh.extend(light.take_changed().filter(|pos| chunks.contains_key(pos)));
+        }
+        self.remesh.drain().collect()
+    }
+
+    /// Start keeping track of the world's light, computing it from scratch,
+    /// with block light properties from `blocks`. 
+    ///
+    /// This marks every chunk for re-meshing. 
+    pub fn enable_light(&mut self, blocks: &BlockRegistry) {
+        self.light = Some(Light::new(blocks, &self.chunks));
+        self.remesh.extend(self.chunks.keys().copied());
+    }
+
+    /// Get the world's light, if enabled. 
+    pub fn light(&self) -> Option<&Light> {
+        self.light.as_ref()
+    }
+
+    /// Get the block at a position. 
+    pub fn block(&self, pos: Vec3<i32>) -> BlockId {
+        self.chunks.get(&block_chunk(pos))
+            .map(|chunk| chunk.get(block_in_chunk(pos)))
+            .unwrap_or(AIR)
+    }
+
+    /// Set the block at a position, and return the block which was there
+    /// before. 
+    ///
+    /// Chunks which become entirely air are removed. 
+    pub fn set_block(&mut self, pos: Vec3<i32>, block: BlockId) -> BlockId {
+        let chunk_pos = block_chunk(pos);
+        if block == AIR && !self.chunks.contains_key(&chunk_pos) {
+            return AIR;
+        }
+        let chunk = self.chunks.entry(chunk_pos).or_default();
+        let old = chunk.set(block_in_chunk(pos), block);
+        if old != block {
+            self.dirty.insert(chunk_pos);
+            self.remesh.insert(chunk_pos);
+            if block == AIR && chunk.is_empty() {
+                self.chunks.remove(&chunk_pos);
+            }
+            if let Some(ref mut light) = self.light {
+                light.block_changed(&self.chunks, pos, old);
+            }
+        }
+        old
+    }
+
+    /// Replace a whole chunk, such as with one received from a server, or
+    /// remove it if `None` or empty. 
+    ///
+    /// The chunk is marked for re-meshing, but not as dirty, since it didn't
+    /// originate from this world. 
+    pub fn replace_chunk(&mut self, pos: Vec3<i32>, chunk: Option<Chunk>) {
+        let chunk = chunk.filter(|chunk| !chunk.is_empty());
+        let existed = self.chunks.contains_key(&pos);
+        match self.light {
+            // light is re-propagated per block, so the blocks are changed one
+            // at a time, top-down so each column is only shadowed once
+            Some(ref mut light) => {
+                let new = chunk.unwrap_or_default();
+                for y in (0..CHUNK_SIZE).rev() {
+                    for x in 0..CHUNK_SIZE {
+                        for z in 0..CHUNK_SIZE {
+                            let local = Vec3::new(x, y, z);
+                            let block = new.get(local);
+                            let old = self.chunks.entry(pos).or_default().set(local, block);
+                            if old != block {
+                                light.block_changed(&self.chunks, pos * CHUNK_SIZE + local, old);
+                            }
+                        }
+                    }
+                }
+                if self.chunks.get(&pos).map(Chunk::is_empty).unwrap_or(false) {
+                    self.chunks.remove(&pos);
+                }
+            }
+            None => {
+                match chunk {
+                    Some(chunk) => self.chunks.insert(pos, chunk),
+                    None => self.chunks.remove(&pos),
+                };
+            }
+        }
+        if existed || self.chunks.contains_key(&pos) {
+            self.remesh.insert(pos);
+        }
+    }
+
+    /// Set a whole chunk which originated in this world, such as one which
+    /// was just generated, or remove it if `None` or empty. 
+    ///
+    /// Unlike `replace_chunk`, the chunk is marked as dirty. 
+    pub fn set_chunk(&mut self, pos: Vec3<i32>, chunk: Option<Chunk>) {
+        let existed = self.chunks.contains_key(&pos);
+        self.replace_chunk(pos, chunk);
+        if existed || self.chunks.contains_key(&pos) {
+            self.dirty.insert(pos);
+        }
+    }
+
+    /// Get a chunk, if it contains any blocks. 
+    pub fn chunk(&self, pos: Vec3<i32>) -> Option<&Chunk> {
+        self.chunks.get(&pos)
+    }
+
+    /// Iterate over all chunks which contain any blocks. 
+    pub fn chunks<'s>(&'s self) -> impl Iterator<Item=(Vec3<i32>, &'s Chunk)> + 's {
+        self.chunks.iter().map(|(&pos, chunk)| (pos, chunk))
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
+}
+
+/// Treats every non-air block as solid. To respect the solidity of block
+/// types, query the world through a `BlockRegistry` instead. 
+impl VoxelGrid for World {
+    fn is_solid(&self, block: Vec3<i32>) -> bool {
+        self.block(block) != AIR
+    }
+}
+
+impl Drop for World {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("failed to flush world on drop:\n{}", e);
+        }
+    }
+}
+
+#[test]
+fn world_blocks_test() {
+    let mut world = World::new();
+    assert_eq!(world.block(Vec3::new(-1, 20, 3)), AIR);
+    assert_eq!(world.set_block(Vec3::new(-1, 20, 3), 5), AIR);
+    assert_eq!(world.block(Vec3::new(-1, 20, 3)), 5);
+    assert!(world.chunk(Vec3::new(-1, 1, 0)).is_some());
+    assert_eq!(world.dirty_count(), 1);
+    assert_eq!(world.take_remesh(), vec![Vec3::new(-1, 1, 0)]);
+    assert!(world.take_remesh().is_empty());
+    assert!(world.is_solid(Vec3::new(-1, 20, 3)));
+
+    // emptied chunks are removed, but stay dirty so the removal is saved
+    assert_eq!(world.set_block(Vec3::new(-1, 20, 3), AIR), 5);
+    assert!(world.chunk(Vec3::new(-1, 1, 0)).is_none());
+    assert_eq!(world.dirty_count(), 1);
+}
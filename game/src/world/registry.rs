@@ -0,0 +1,327 @@
+//! Block type definitions. 
+//!
+//! Block types are defined by a TOML file, which lists them in ID order:
+//!
+//! ```toml
+//! [[blocks]]
+//! name = "grass"
+//! # all optional, with these defaults
+//! solid = true
+//! transparent = false
+//! hardness = 1.0
+//! # light emitted, from 0 to 15
+//! light = 0
+//! # whether the top face is tinted with the biome's grass color
+//! tinted = false
+//!
+//! # texture layer names, with the same keys as texture manifest faces
+//! [blocks.faces]
+//! top = "grass"
+//! bottom = "dirt"
+//! sides = "grass_side"
+//! ```
+//!
+//! ID 0 is always air, and the blocks in the file are assigned IDs starting
+//! from 1. Since worlds are saved by block ID, blocks should only ever be
+//! appended to the file. 
+
+use super::{BlockId, AIR, light::MAX_LIGHT};
+use crate::{
+    graphics::ManifestFaces,
+    util::axis_unit::AxisUnit3,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+};
+use serde::Deserialize;
+use pear::*;
+
+/// Name of the built-in air block. 
+pub const AIR_NAME: &str = "air";
+
+/// Parsed contents of a block definition file. 
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockFile {
+    /// Block definitions, in ID order. 
+    #[serde(default)]
+    pub blocks: Vec<BlockFileEntry>,
+}
+
+/// A block in a block definition file. 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockFileEntry {
+    /// Name of the block. 
+    pub name: String,
+    /// Texture layer names of the block's faces. 
+    pub faces: ManifestFaces,
+    /// Whether bodies collide with the block. 
+    #[serde(default = "default_solid")]
+    pub solid: bool,
+    /// Whether blocks behind this one can be seen through it. 
+    #[serde(default)]
+    pub transparent: bool,
+    /// How long the block takes to break, in seconds. 
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+    /// Level of light the block emits, from 0 to `MAX_LIGHT`. 
+    #[serde(default)]
+    pub light: u8,
+    /// Whether the block's top face is tinted with the grass color of its
+    /// biome. 
+    #[serde(default)]
+    pub tinted: bool,
+}
+
+fn default_solid() -> bool {
+    true
+}
+
+fn default_hardness() -> f32 {
+    1.0
+}
+
+/// Properties of a block type. 
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockDef {
+    /// Name of the block. 
+    pub name: String,
+    /// Texture layer indices of the block's faces, indexed by
+    /// `AxisUnit3::to_index`. 
+    pub faces: [u32; 6],
+    /// Whether bodies collide with the block. 
+    pub solid: bool,
+    /// Whether blocks behind this one can be seen through it. 
+    pub transparent: bool,
+    /// How long the block takes to break, in seconds. 
+    pub hardness: f32,
+    /// Level of light the block emits, from 0 to `MAX_LIGHT`. 
+    pub light: u8,
+    /// Whether the block's top face is tinted with the grass color of its
+    /// biome. 
+    pub tinted: bool,
+}
+
+/// Mapping from block IDs to block type properties. 
+#[derive(Clone, Debug)]
+pub struct BlockRegistry {
+    // indexed by block id
+    defs: Vec<BlockDef>,
+    by_name: HashMap<String, BlockId>,
+}
+
+impl BlockRegistry {
+    /// Construct a registry containing only air. 
+    pub fn new() -> Self {
+        let air = BlockDef {
+            name: AIR_NAME.to_owned(),
+            faces: [0; 6],
+            solid: false,
+            transparent: true,
+            hardness: 0.0,
+            light: 0,
+            tinted: false,
+        };
+        let mut by_name = HashMap::new();
+        by_name.insert(air.name.clone(), AIR);
+        BlockRegistry {
+            defs: vec![air],
+            by_name,
+        }
+    }
+
+    /// Add a block type, and return its ID. 
+    ///
+    /// Errors if a block with that name already exists, or there are no
+    /// more IDs. 
+    pub fn register(&mut self, def: BlockDef) -> Result<BlockId> {
+        if self.by_name.contains_key(&def.name) {
+            return Err(pear!({name=def.name}, "duplicate block name"));
+        }
+        if self.defs.len() > BlockId::MAX as usize {
+            return Err(pear!({name=def.name}, "too many block types"));
+        }
+        let id = self.defs.len() as BlockId;
+        self.by_name.insert(def.name.clone(), id);
+        self.defs.push(def);
+        Ok(id)
+    }
+
+    /// Build a registry from a parsed block definition file, resolving
+    /// texture layer names to indices with `texture_index`. 
+    pub fn from_file<F>(file: &BlockFile, texture_index: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<u32>,
+    {
+        let mut registry = BlockRegistry::new();
+        let mut errors = Vec::new();
+        for entry in &file.blocks {
+            let mut faces = [0; 6];
+            for (i, index) in faces.iter_mut().enumerate() {
+                let face = AxisUnit3::from_index(i);
+                match entry.faces.face(face) {
+                    Some(name) => match texture_index(name) {
+                        Some(layer) => *index = layer,
+                        None => errors.push(pear!(
+                            {block=entry.name, face=face, layer=name},
+                            "block face references unknown texture",
+                        )),
+                    },
+                    None => errors.push(pear!(
+                        {block=entry.name, face=face},
+                        "block face has no texture",
+                    )),
+                }
+            }
+            if entry.light > MAX_LIGHT {
+                errors.push(pear!(
+                    {block=entry.name, light=entry.light, max=MAX_LIGHT},
+                    "block light level too high",
+                ));
+            }
+            registry
+                .register(BlockDef {
+                    name: entry.name.clone(),
+                    faces,
+                    solid: entry.solid,
+                    transparent: entry.transparent,
+                    hardness: entry.hardness,
+                    light: entry.light,
+                    tinted: entry.tinted,
+                })
+                .push_err(&mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(registry)
+        } else {
+            Err(errors.wrap(pear!({}, "invalid block definitions")))
+        }
+    }
+
+    /// Parse a registry from TOML, resolving texture layer names to indices
+    /// with `texture_index`. 
+    pub fn parse<F>(toml: &str, texture_index: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<u32>,
+    {
+        let file: BlockFile = toml::from_str(toml)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "invalid block definition file"))?;
+        Self::from_file(&file, texture_index)
+    }
+
+    /// Read and parse a block definition file, resolving texture layer names
+    /// to indices with `texture_index`. 
+    pub fn read<P, F>(path: P, texture_index: F) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        F: Fn(&str) -> Option<u32>,
+    {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({path=path}, "failed to read block definition file"))?;
+        Self::parse(&toml, texture_index)
+            .wrap_err(|| pear!({path=path}, "failed to load block definitions"))
+    }
+
+    /// Get a block type's properties. 
+    ///
+    /// Unknown IDs, such as from a world saved with more block types, are
+    /// treated as air. 
+    pub fn get(&self, id: BlockId) -> &BlockDef {
+        self.defs.get(id as usize).unwrap_or(&self.defs[AIR as usize])
+    }
+
+    /// Look up a block type's ID by name. 
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Whether a block type is solid. 
+    pub fn is_solid(&self, id: BlockId) -> bool {
+        self.get(id).solid
+    }
+
+    /// Number of block types, including air. 
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    /// Iterate block types and their IDs, in ID order, including air. 
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item=(BlockId, &'s BlockDef)> + 's {
+        self.defs.iter().enumerate().map(|(id, def)| (id as BlockId, def))
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        BlockRegistry::new()
+    }
+}
+
+#[test]
+fn block_registry_test() {
+    let textures = ["dirt", "grass", "grass_side", "glass"];
+    let texture_index = |name: &str| textures.iter().position(|&t| t == name).map(|i| i as u32);
+    let registry = BlockRegistry::parse(r#"
+        [[blocks]]
+        name = "grass"
+        hardness = 0.6
+        tinted = true
+        [blocks.faces]
+        top = "grass"
+        bottom = "dirt"
+        sides = "grass_side"
+
+        [[blocks]]
+        name = "glass"
+        transparent = true
+        light = 4
+        faces = { all = "glass" }
+    "#, texture_index).unwrap();
+
+    assert_eq!(registry.len(), 3);
+    assert_eq!(registry.id("air"), Some(AIR));
+    let grass = registry.id("grass").unwrap();
+    assert_eq!(grass, 1);
+    let grass = registry.get(grass);
+    assert_eq!(grass.faces[AxisUnit3::UP.to_index()], 1);
+    assert_eq!(grass.faces[AxisUnit3::DOWN.to_index()], 0);
+    assert_eq!(grass.faces[AxisUnit3::EAST.to_index()], 2);
+    assert_eq!(grass.hardness, 0.6);
+    assert!(grass.solid && !grass.transparent);
+    assert!(registry.get(2).transparent);
+    assert_eq!(grass.light, 0);
+    assert!(grass.tinted && !registry.get(2).tinted);
+    assert_eq!(registry.get(2).light, 4);
+    assert!(!registry.is_solid(AIR));
+    assert_eq!(registry.get(1000).name, AIR_NAME);
+
+    // unknown textures, missing faces, and duplicate names are errors
+    assert!(BlockRegistry::parse(r#"
+        [[blocks]]
+        name = "stone"
+        faces = { all = "stone" }
+    "#, texture_index).is_err());
+    assert!(BlockRegistry::parse(r#"
+        [[blocks]]
+        name = "dirt"
+        faces = { top = "dirt" }
+    "#, texture_index).is_err());
+    assert!(BlockRegistry::parse(r#"
+        [[blocks]]
+        name = "air"
+        faces = { all = "dirt" }
+    "#, texture_index).is_err());
+    assert!(BlockRegistry::parse(r#"
+        [[blocks]]
+        name = "dirt"
+        light = 16
+        faces = { all = "dirt" }
+    "#, texture_index).is_err());
+}
@@ -0,0 +1,392 @@
+//! Region files, for persisting world chunks to disk. 
+//!
+//! A save directory contains the world's seed in decimal, in a file named
+//! `seed`, and one file per region, which is a cube of `REGION_SIZE` chunks
+//! on each side, named `r.<x>.<y>.<z>.region`. Each region file is laid out
+//! as:
+//!
+//! - the magic bytes `REGION_MAGIC`
+//! - format version, as a little-endian `u32`
+//! - number of chunks, as a little-endian `u32`
+//! - for each chunk:
+//!   - index of the chunk within the region, as a little-endian `u16`
+//!   - length of the chunk data, as a little-endian `u32`
+//!   - chunk data, which is the chunk's blocks as little-endian `u16`s in
+//!     index order, zlib-compressed
+//!
+//! Chunks consisting entirely of air are not stored. 
+
+use super::{Chunk, BlockId, CHUNK_VOLUME};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+use flate2::{
+    Compression,
+    read::ZlibDecoder,
+    write::ZlibEncoder,
+};
+use vek::*;
+use pear::*;
+
+/// Side length of a region, in chunks. 
+pub const REGION_SIZE: i32 = 8;
+
+/// Magic bytes at the start of every region file. 
+pub const REGION_MAGIC: [u8; 4] = *b"BLXR";
+
+/// Current region file format version. 
+pub const REGION_VERSION: u32 = 1;
+
+const REGION_EXT: &str = "region";
+
+const SEED_FILE: &str = "seed";
+
+/// Get the region which contains a chunk. 
+pub fn chunk_region(chunk: Vec3<i32>) -> Vec3<i32> {
+    chunk.map(|n| n.div_euclid(REGION_SIZE))
+}
+
+/// Get the path of a region's file within a save directory. 
+pub fn region_path(dir: &Path, region: Vec3<i32>) -> PathBuf {
+    dir.join(format!("r.{}.{}.{}.{}", region.x, region.y, region.z, REGION_EXT))
+}
+
+/// Parse a region's position from its file name. 
+fn parse_region_path(path: &Path) -> Option<Vec3<i32>> {
+    let name = path.file_name()?.to_str()?;
+    let mut parts = name.split('.');
+    if parts.next()? != "r" {
+        return None;
+    }
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    if parts.next()? != REGION_EXT || parts.next().is_some() {
+        return None;
+    }
+    Some(Vec3::new(x, y, z))
+}
+
+/// Get the index of a chunk within a region, from its position relative to
+/// the region. 
+pub fn chunk_index(local: Vec3<i32>) -> u16 {
+    ((local.x * REGION_SIZE + local.y) * REGION_SIZE + local.z) as u16
+}
+
+/// Get a chunk's position relative to its region, from its index within the
+/// region. 
+pub fn index_chunk(index: u16) -> Vec3<i32> {
+    let index = index as i32;
+    Vec3::new(
+        index / (REGION_SIZE * REGION_SIZE),
+        index / REGION_SIZE % REGION_SIZE,
+        index % REGION_SIZE,
+    )
+}
+
+/// Contents of a single region file. 
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Region {
+    /// Chunks, by index within the region. 
+    pub chunks: BTreeMap<u16, Chunk>,
+}
+
+impl Region {
+    /// Serialize to the region file format. 
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&REGION_MAGIC);
+        bytes.extend_from_slice(&REGION_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.chunks.len() as u32).to_le_bytes());
+
+        for (&index, chunk) in &self.chunks {
+            let data = compress_chunk(chunk);
+            bytes.extend_from_slice(&index.to_le_bytes());
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&data);
+        }
+        bytes
+    }
+
+    /// Deserialize from the region file format. 
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)
+            .map_err(Error::from)
+            .wrap_err(|| pear!({}, "region file truncated"))?;
+        if magic != REGION_MAGIC {
+            return Err(pear!({magic=magic}, "not a region file"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != REGION_VERSION {
+            return Err(pear!(
+                {version=version, supported=REGION_VERSION},
+                "unsupported region file version",
+            ));
+        }
+        let count = read_u32(&mut reader)?;
+
+        let mut region = Region::default();
+        for _ in 0..count {
+            let index = read_u16(&mut reader)?;
+            if index as i32 >= REGION_SIZE * REGION_SIZE * REGION_SIZE {
+                return Err(pear!({index=index}, "region chunk index out of bounds"));
+            }
+            let pos = index_chunk(index);
+
+            let len = read_u32(&mut reader)? as usize;
+            if len > reader.len() {
+                return Err(pear!({pos=pos}, "region file truncated"));
+            }
+            let (data, rest) = reader.split_at(len);
+            reader = rest;
+
+            let chunk = decompress_chunk(data)
+                .wrap_err(|| pear!({pos=pos}, "invalid region chunk"))?;
+            region.chunks.insert(index, chunk);
+        }
+        Ok(region)
+    }
+
+    /// Read a region file, or an empty region if the file doesn't exist. 
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Region::default()),
+            Err(e) => return Err(Error::from(e)
+                .wrap(pear!({path=path}, "failed to read region file"))),
+        };
+        Self::decode(&bytes)
+            .wrap_err(|| pear!({path=path}, "invalid region file"))
+    }
+
+    /// Write a region file, or delete it if the region is empty. 
+    ///
+    /// The file is replaced atomically, so a crash mid-write doesn't corrupt
+    /// the existing file. 
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if self.chunks.is_empty() {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::from(e)
+                    .wrap(pear!({path=path}, "failed to delete region file"))),
+                _ => Ok(()),
+            };
+        }
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, self.encode())
+            .and_then(|()| fs::rename(&tmp_path, path))
+            .map_err(Error::from)
+            .wrap_err(|| pear!({path=path}, "failed to write region file"))
+    }
+}
+
+/// Compress a chunk's blocks, as stored in region files. 
+pub fn compress_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for &block in chunk.blocks() {
+        // writing to a vec can't fail
+        encoder.write_all(&block.to_le_bytes()).unwrap();
+    }
+    encoder.finish().unwrap()
+}
+
+/// Decompress a chunk from `compress_chunk`. 
+pub fn decompress_chunk(data: &[u8]) -> Result<Chunk> {
+    let mut raw = Vec::with_capacity(CHUNK_VOLUME * 2);
+    // data may come from the network, so it's not inflated past the one 
+    // extra byte which shows it's too long
+    ZlibDecoder::new(data)
+        .take(CHUNK_VOLUME as u64 * 2 + 1)
+        .read_to_end(&mut raw)
+        .map_err(Error::from)
+        .wrap_err(|| pear!({}, "failed to decompress chunk"))?;
+    if raw.len() != CHUNK_VOLUME * 2 {
+        return Err(pear!({len=raw.len()}, "chunk has wrong size"));
+    }
+    let blocks: Vec<BlockId> = raw
+        .chunks_exact(2)
+        .map(|b| BlockId::from_le_bytes([b[0], b[1]]))
+        .collect();
+    Ok(Chunk::from_blocks(blocks))
+}
+
+fn read_u16(reader: &mut &[u8]) -> Result<u16> {
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf)
+        .map_err(Error::from)
+        .wrap_err(|| pear!({}, "region file truncated"))?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(reader: &mut &[u8]) -> Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)
+        .map_err(Error::from)
+        .wrap_err(|| pear!({}, "region file truncated"))?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Write chunks to a save directory, creating it if necessary. 
+///
+/// Each chunk is paired with its contents, or none to remove it from the
+/// save. Each affected region file is read, updated, and rewritten. 
+pub fn save_chunks<'a, I>(dir: &Path, chunks: I) -> Result<()>
+where
+    I: IntoIterator<Item=(Vec3<i32>, Option<&'a Chunk>)>,
+{
+    fs::create_dir_all(dir)
+        .map_err(Error::from)
+        .wrap_err(|| pear!({dir=dir}, "failed to create save directory"))?;
+
+    let mut by_region: HashMap<Vec3<i32>, Vec<(Vec3<i32>, Option<&'a Chunk>)>> =
+        HashMap::new();
+    for (pos, chunk) in chunks {
+        by_region.entry(chunk_region(pos)).or_default().push((pos, chunk));
+    }
+
+    let mut errors = Vec::new();
+    for (region_pos, chunks) in by_region {
+        let path = region_path(dir, region_pos);
+        let result = Region::read(&path).and_then(|mut region| {
+            for (pos, chunk) in chunks {
+                let index = chunk_index(pos - region_pos * REGION_SIZE);
+                match chunk {
+                    Some(chunk) if !chunk.is_empty() => {
+                        region.chunks.insert(index, chunk.clone());
+                    }
+                    _ => {
+                        region.chunks.remove(&index);
+                    }
+                }
+            }
+            region.write(&path)
+        });
+        result.push_err(&mut errors);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.wrap(pear!({}, "failed to save some regions")))
+    }
+}
+
+/// Read every chunk from a save directory. 
+///
+/// A directory which doesn't exist contains no chunks. 
+pub fn load_chunks(dir: &Path) -> Result<HashMap<Vec3<i32>, Chunk>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(Error::from(e)
+            .wrap(pear!({dir=dir}, "failed to read save directory"))),
+    };
+
+    let mut chunks = HashMap::new();
+    for entry in entries {
+        let path = entry?.path();
+        let region_pos = match parse_region_path(&path) {
+            Some(region_pos) => region_pos,
+            None => continue,
+        };
+        let region = Region::read(&path)?;
+        for (index, chunk) in region.chunks {
+            chunks.insert(region_pos * REGION_SIZE + index_chunk(index), chunk);
+        }
+    }
+    Ok(chunks)
+}
+
+/// Read the world seed from a save directory, or none if it has no seed. 
+pub fn load_seed(dir: &Path) -> Result<Option<u64>> {
+    let path = dir.join(SEED_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::from(e)
+            .wrap(pear!({path=path}, "failed to read seed file"))),
+    };
+    let seed = text.trim()
+        .parse::<u64>()
+        .map_err(Error::from)
+        .wrap_err(|| pear!({path=path}, "invalid seed file"))?;
+    Ok(Some(seed))
+}
+
+/// Write the world seed to a save directory, creating it if necessary. 
+pub fn save_seed(dir: &Path, seed: u64) -> Result<()> {
+    fs::create_dir_all(dir)
+        .map_err(Error::from)
+        .wrap_err(|| pear!({dir=dir}, "failed to create save directory"))?;
+    let path = dir.join(SEED_FILE);
+    fs::write(&path, format!("{}\n", seed))
+        .map_err(Error::from)
+        .wrap_err(|| pear!({path=path}, "failed to write seed file"))
+}
+
+#[test]
+fn region_roundtrip_test() {
+    let mut chunk = Chunk::new();
+    chunk.set(Vec3::new(1, 2, 3), 7);
+    chunk.set(Vec3::new(15, 15, 15), 65535);
+    let mut region = Region::default();
+    region.chunks.insert(chunk_index(Vec3::new(0, 5, 7)), chunk.clone());
+    region.chunks.insert(chunk_index(Vec3::new(7, 0, 0)), Chunk::new());
+
+    let bytes = region.encode();
+    assert_eq!(&bytes[0..4], &REGION_MAGIC);
+    assert_eq!(Region::decode(&bytes).unwrap(), region);
+
+    // bad version and truncation are errors
+    let mut bad = bytes.clone();
+    bad[4] = 99;
+    assert!(Region::decode(&bad).is_err());
+    assert!(Region::decode(&bytes[..bytes.len() - 1]).is_err());
+
+    // so is a chunk which inflates to too much, such as a zip bomb
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&vec![0; 1 << 24]).unwrap();
+    let bomb = encoder.finish().unwrap();
+    assert!(decompress_chunk(&bomb).is_err());
+    assert_eq!(decompress_chunk(&compress_chunk(&chunk)).unwrap(), chunk);
+
+    assert_eq!(parse_region_path(Path::new("r.-1.0.12.region")), Some(Vec3::new(-1, 0, 12)));
+    assert_eq!(parse_region_path(Path::new("r.1.2.region")), None);
+    assert_eq!(index_chunk(chunk_index(Vec3::new(3, 7, 1))), Vec3::new(3, 7, 1));
+    assert_eq!(chunk_region(Vec3::new(-1, 8, 7)), Vec3::new(-1, 1, 0));
+}
+
+#[test]
+fn world_save_load_test() {
+    use super::World;
+
+    let dir = std::env::temp_dir()
+        .join(format!("world_save_load_test_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let mut world = World::new();
+    world.set_seed(1234);
+    world.set_block(Vec3::new(0, 0, 0), 1);
+    world.set_block(Vec3::new(-200, 3, 1000), 2);
+    world.save_all(&dir).unwrap();
+    assert_eq!(world.dirty_count(), 0);
+
+    // changes after saving are flushed to the same directory
+    world.set_block(Vec3::new(0, 0, 0), super::AIR);
+    world.set_block(Vec3::new(5, 5, 5), 3);
+    world.flush().unwrap();
+    drop(world);
+
+    let world = World::load(&dir).unwrap();
+    assert_eq!(world.block(Vec3::new(0, 0, 0)), super::AIR);
+    assert_eq!(world.block(Vec3::new(-200, 3, 1000)), 2);
+    assert_eq!(world.block(Vec3::new(5, 5, 5)), 3);
+    assert_eq!(world.chunks().count(), 2);
+    assert_eq!(world.seed(), 1234);
+    drop(world);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
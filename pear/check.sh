@@ -0,0 +1,10 @@
+#!/usr/bin/env bash
+
+SCRIPT_DIR=$(cd -P -- "$(dirname -- "$0")" && pwd -P)
+cd "${SCRIPT_DIR}"
+
+# test each feature combination, including no_std
+cargo test || exit 1
+cargo test --all-features || exit 1
+cargo test --no-default-features || exit 1
+cargo test --no-default-features --features serde || exit 1
@@ -0,0 +1,50 @@
+
+use crate::{Error, ErrorInner};
+
+impl Error {
+    /// Convert an `anyhow::Error` into a `crate::Error`. 
+    ///
+    /// If it was converted from a `crate::Error`, this recovers the original. 
+    /// Otherwise, its chain of causes is converted as with `from_std`. 
+    ///
+    /// This isn't a `From` implementation, because that would conflict with 
+    /// the blanket conversion from `std::error::Error`. 
+    ///
+    /// ```
+    /// use pear::pear;
+    /// 
+    /// let error = pear!({ x = 5 }, "outer");
+    /// let anyhow = error.into_anyhow();
+    /// assert!(anyhow.to_string().contains("outer"));
+    /// let error = pear::Error::from_anyhow(anyhow);
+    /// assert_eq!(error.num_fields(), 3);
+    ///
+    /// let anyhow = anyhow::anyhow!("inner").context("from anyhow");
+    /// let error = pear::Error::from_anyhow(anyhow);
+    /// assert_eq!(error.message(), "from anyhow");
+    /// assert_eq!(error.causes()[0].message(), "inner");
+    /// ```
+    #[track_caller]
+    pub fn from_anyhow(error: anyhow::Error) -> Self {
+        match error.downcast::<ErrorInner>() {
+            Ok(inner) => Error(Box::new(inner)),
+            Err(error) => Error::from_std(&*error),
+        }
+    }
+
+    /// Convert `self` into an `anyhow::Error`. 
+    ///
+    /// It displays the same as `self`, and its chain of sources follows the 
+    /// first of `self`'s causes, as with `as_std`. 
+    pub fn into_anyhow(self) -> anyhow::Error {
+        anyhow::Error::new(*self.0)
+    }
+}
+
+/// Convert into an `anyhow::Error`, such as for `?` in a function returning 
+/// `anyhow::Result`. 
+impl From<Error> for anyhow::Error {
+    fn from(error: Error) -> Self {
+        error.into_anyhow()
+    }
+}
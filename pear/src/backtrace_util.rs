@@ -1,5 +1,9 @@
 
-use std::env;
+use std::{
+    env,
+    fmt::Write,
+    sync::RwLock,
+};
 use backtrace::Backtrace;
 
 pub fn is_backtrace_enabled() -> bool {
@@ -14,4 +18,143 @@ pub fn capture_backtrace_if_enabled() -> Option<Backtrace> {
     } else {
         None
     }
-}
\ No newline at end of file
+}
+
+/// Symbol prefixes which are hidden by default: this library, the standard 
+/// library, and the runtime's entry and unwinding machinery. 
+const DEFAULT_DENY: &[&str] = &[
+    "pear::",
+    "backtrace::",
+    "std::",
+    "core::",
+    "alloc::",
+    "__rust",
+    "rust_begin_unwind",
+    "_start",
+    "__libc_start",
+    "__scrt_common_main",
+    "BaseThreadInitThunk",
+    "RtlUserThreadStart",
+];
+
+/// Which frames of a backtrace are displayed. 
+///
+/// A symbol is hidden if its demangled name starts with a prefix in the deny 
+/// list, unless it starts with a prefix in the allow list. Leading `<`, `&`, 
+/// and `dyn ` are skipped, so trait impls such as `<std::fs::File as 
+/// std::io::Read>::read` are matched by their self type. Frames with no shown 
+/// symbols are hidden. 
+///
+/// This only affects `Error`'s non-alternate `Display` mode. The alternate 
+/// mode (`{:#}`) displays every frame. 
+///
+/// ```
+/// use pear::BacktraceFilter;
+/// 
+/// let mut filter = BacktraceFilter::new();
+/// filter.deny("tokio::").allow("std::thread::");
+/// assert!(!filter.is_shown("tokio::runtime::park"));
+/// assert!(!filter.is_shown("std::rt::lang_start"));
+/// assert!(filter.is_shown("std::thread::spawn"));
+/// assert!(!filter.is_shown("<&dyn core::ops::Fn<()> as core::ops::FnOnce<()>>::call_once"));
+/// assert!(filter.is_shown("game::main"));
+/// pear::set_backtrace_filter(filter);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BacktraceFilter {
+    deny: Vec<String>,
+    allow: Vec<String>,
+}
+
+impl BacktraceFilter {
+    /// Construct the default filter, which hides frames from this library, 
+    /// the standard library, and the runtime. 
+    pub fn new() -> Self {
+        BacktraceFilter {
+            deny: DEFAULT_DENY.iter().map(|&s| s.to_owned()).collect(),
+            allow: Vec::new(),
+        }
+    }
+
+    /// Construct a filter which displays every frame. 
+    pub fn show_all() -> Self {
+        BacktraceFilter {
+            deny: Vec::new(),
+            allow: Vec::new(),
+        }
+    }
+
+    /// Hide frames whose symbols start with `prefix`. 
+    pub fn deny<P: Into<String>>(&mut self, prefix: P) -> &mut Self {
+        self.deny.push(prefix.into());
+        self
+    }
+
+    /// Show frames whose symbols start with `prefix`, even if they're 
+    /// denied. 
+    pub fn allow<P: Into<String>>(&mut self, prefix: P) -> &mut Self {
+        self.allow.push(prefix.into());
+        self
+    }
+
+    /// Whether a symbol, by demangled name without hash, is shown. 
+    pub fn is_shown(&self, symbol: &str) -> bool {
+        let mut symbol = symbol;
+        loop {
+            let trimmed = symbol
+                .trim_start_matches(['<', '&'])
+                .trim_start_matches("dyn ")
+                .trim_start_matches("mut ");
+            if trimmed.len() == symbol.len() {
+                break;
+            }
+            symbol = trimmed;
+        }
+        self.allow.iter().any(|prefix| symbol.starts_with(prefix.as_str()))
+            || !self.deny.iter().any(|prefix| symbol.starts_with(prefix.as_str()))
+    }
+}
+
+impl Default for BacktraceFilter {
+    fn default() -> Self {
+        BacktraceFilter::new()
+    }
+}
+
+static FILTER: RwLock<Option<BacktraceFilter>> = RwLock::new(None);
+
+/// Set the `BacktraceFilter` used when displaying errors. 
+pub fn set_backtrace_filter(filter: BacktraceFilter) {
+    *FILTER.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(filter);
+}
+
+/// Get the `BacktraceFilter` used when displaying errors. 
+pub fn backtrace_filter() -> BacktraceFilter {
+    FILTER.read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Render the frames of a backtrace which pass the current filter, in the 
+/// same layout as `Backtrace`'s `Debug` implementation. 
+pub fn fmt_filtered(backtrace: &Backtrace) -> String {
+    let filter = backtrace_filter();
+    let mut out = String::new();
+    for (i, frame) in backtrace.frames().iter().enumerate() {
+        for symbol in frame.symbols() {
+            let name = match symbol.name() {
+                Some(name) => format!("{:#}", name),
+                None => continue,
+            };
+            if !filter.is_shown(&name) {
+                continue;
+            }
+            let _ = writeln!(out, "{:4}: {}", i, name);
+            if let (Some(file), Some(line)) = (symbol.filename(), symbol.lineno()) {
+                let _ = writeln!(out, "             at {}:{}", file.display(), line);
+            }
+        }
+    }
+    out
+}
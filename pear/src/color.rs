@@ -0,0 +1,78 @@
+
+use core::{
+    fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicU8, Ordering},
+};
+#[cfg(feature = "std")]
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+/// Whether `Error`'s `Display` implementation uses ANSI colors. 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    /// Never use colors. This is the default. 
+    Never,
+    /// Use colors if stderr is a terminal, and the `NO_COLOR` environment 
+    /// variable isn't set. Without the `std` feature, this never uses colors. 
+    Auto,
+    /// Always use colors. 
+    Always,
+}
+
+static COLOR_MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Set whether displayed errors use ANSI colors. 
+///
+/// In color, messages are bold, field names are dimmed, and the `[ error ]` 
+/// and `[ caused by ]` markers are colored. 
+pub fn set_color_mode(mode: ColorMode) {
+    let n = match mode {
+        ColorMode::Never => 0,
+        ColorMode::Auto => 1,
+        ColorMode::Always => 2,
+    };
+    COLOR_MODE.store(n, Ordering::Relaxed);
+}
+
+/// Get whether displayed errors use ANSI colors. 
+pub fn color_mode() -> ColorMode {
+    match COLOR_MODE.load(Ordering::Relaxed) {
+        0 => ColorMode::Never,
+        1 => ColorMode::Auto,
+        _ => ColorMode::Always,
+    }
+}
+
+/// Resolve the current `ColorMode` to whether colors are used. 
+pub fn is_color_enabled() -> bool {
+    match color_mode() {
+        ColorMode::Never => false,
+        #[cfg(feature = "std")]
+        ColorMode::Auto => env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal(),
+        #[cfg(not(feature = "std"))]
+        ColorMode::Auto => false,
+        ColorMode::Always => true,
+    }
+}
+
+pub const BOLD: &str = "\x1b[1m";
+pub const DIM: &str = "\x1b[2m";
+pub const RED: &str = "\x1b[31m";
+pub const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Displays the inner value in a style, if colors are enabled. 
+pub struct Paint<T>(pub bool, pub &'static str, pub T);
+
+impl<T: Display> Display for Paint<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let &Paint(enabled, style, ref inner) = self;
+        if enabled {
+            write!(f, "{}{}{}", style, inner, RESET)
+        } else {
+            Display::fmt(inner, f)
+        }
+    }
+}
@@ -1,13 +1,37 @@
 
-use crate::Error;
-use std::fmt::{self, Display, Debug, Formatter};
+use crate::{
+    Error,
+    ErrorInner,
+    ErrorKind,
+    Severity,
+    color::{Paint, is_color_enabled, BOLD, DIM, RED, YELLOW},
+};
+#[cfg(feature = "std")]
+use crate::backtrace_util::fmt_filtered;
+use core::{
+    fmt::{self, Display, Debug, Formatter},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use alloc::{
+    borrow::Cow,
+    format,
+    vec::Vec,
+};
 use unicode_width::UnicodeWidthChar;
+#[cfg(feature = "std")]
 use ansi_parser::AnsiParser;
+#[cfg(feature = "std")]
 use textwrap::wrap;
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        fmt_error(f, self, Spaces(0), true, false)?;
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl Display for ErrorInner {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt_error(f, self, Spaces(0), true, false, is_color_enabled())?;
         Ok(())
     }
 }
@@ -20,16 +44,37 @@ impl Debug for Error {
 
 const INDENT_SPACES: usize = 4;
 
+static DEFAULT_WRAP_WIDTH: AtomicUsize = AtomicUsize::new(60);
+
+/// Set the width messages are word-wrapped to, in visible characters, for 
+/// errors which don't override it with `Error::set_wrap_width`. 
+///
+/// The default is 60. Without the `std` feature, messages aren't wrapped. 
+pub fn set_default_wrap_width(width: usize) {
+    DEFAULT_WRAP_WIDTH.store(width, Ordering::Relaxed);
+}
+
+/// Get the width messages are word-wrapped to by default. 
+pub fn default_wrap_width() -> usize {
+    DEFAULT_WRAP_WIDTH.load(Ordering::Relaxed)
+}
+
 fn display_width(s: &str) -> usize {
-    let mut max = 0;
-    let mut curr = 0;
-    for c in s.ansi_parse()
+    // without `std`, escape codes are counted, but errors only contain them 
+    // if the user put them there
+    #[cfg(feature = "std")]
+    let chars = s.ansi_parse()
         .filter_map(|output| match output {
             ansi_parser::Output::TextBlock(s) => Some(s),
             _ => None,
         })
-        .flat_map(str::chars)
-    {
+        .flat_map(str::chars);
+    #[cfg(not(feature = "std"))]
+    let chars = s.chars();
+
+    let mut max = 0;
+    let mut curr = 0;
+    for c in chars {
         if c == '\n' {
             curr = 0;
         } else {
@@ -40,40 +85,53 @@ fn display_width(s: &str) -> usize {
     max
 }
 
+/// Split an error's message into lines, word-wrapping if enabled. 
+#[cfg(feature = "std")]
+fn message_lines(error: &ErrorInner) -> Vec<Cow<'_, str>> {
+    if error.wrap_enabled {
+        let width = error.wrap_width.unwrap_or_else(default_wrap_width);
+        wrap(&error.message, width)
+    } else {
+        error.message.lines().map(Cow::Borrowed).collect()
+    }
+}
+
+/// Split an error's message into lines. 
+#[cfg(not(feature = "std"))]
+fn message_lines(error: &ErrorInner) -> Vec<Cow<'_, str>> {
+    error.message.lines().map(Cow::Borrowed).collect()
+}
+
 fn fmt_error(
     f: &mut Formatter, 
-    error: &Error, 
+    error: &ErrorInner, 
     indent: Spaces,
     braced: bool,
     end_of_error_newline: bool,
+    color: bool,
 ) -> fmt::Result {
     const DEBUG_ALT_TRIGGER: usize = 50;
     
     let indent2 = Spaces(indent.0 + INDENT_SPACES);
 
     if braced {
-        write!(f, "{}[ error ]\n", indent)?;
+        let marker = format!("[ {}{} ]", noun(error.severity), KindSuffix(error.kind.as_ref()));
+        writeln!(f, "{}{}", indent, Paint(color, marker_style(error.severity), marker))?;
     }
 
-    if error.0.wrap_enabled {
-        for line in wrap(&error.0.message, 60) {
-            write!(f, "{}{}\n", indent2, line)?;
-        }
-    } else {
-        for line in error.0.message.lines() {
-            write!(f, "{}{}\n", indent2, line)?;
-        }
+    for line in message_lines(error) {
+        writeln!(f, "{}{}", indent2, Paint(color, BOLD, line))?;
     }
 
-    for (key, val) in error.0.fields.iter() {
+    for (key, val) in error.fields.iter() {
         let key_width = display_width(key) + 5;
-        write!(f, "{}- {} = ", indent2, key)?;
+        write!(f, "{}- {} = ", indent2, Paint(color, DIM, key))?;
 
         let val_str = {
-            if display_width(&val.debug) <= DEBUG_ALT_TRIGGER {
-                &val.debug
+            if display_width(val.debug()) <= DEBUG_ALT_TRIGGER {
+                val.debug()
             } else {
-                &val.debug_alt
+                val.debug_alt()
             }
         };
 
@@ -81,41 +139,42 @@ fn fmt_error(
             if i > 0 {
                 write!(f, "{}{}", indent2, Spaces(key_width))?;
             }
-            write!(f, "{}\n", line)?;
+            writeln!(f, "{}", line)?;
         }
     }
 
-    if let Some(backtrace) = error.0.backtrace.as_ref() {
+    #[cfg(feature = "std")]
+    if let Some(backtrace) = error.backtrace.as_ref() {
         let backtrace_str = {
             if f.alternate() {
                 format!("{:#?}", backtrace)
             } else {
-                format!("{:?}", backtrace)
+                fmt_filtered(backtrace)
             }
         };
         for line in backtrace_str.lines() {
-            write!(f, "{}{}\n", indent2, line)?;
+            writeln!(f, "{}{}", indent2, line)?;
         }
     }
 
-    if error.0.causes.len() == 1 {
-        write!(f, "{}[ caused by ]\n", indent)?;
-        fmt_error(f, &error.0.causes[0], indent, false, true)?;
-    } else if error.0.causes.len() > 1 {
-        for (i, cause) in error.0.causes.iter().enumerate() {
-            write!(
-                f, 
-                "{}[ caused by ({}/{}) ]\n", 
-                indent, 
-                i + 1, 
-                error.0.causes.len(),
-            )?;
-            fmt_error(f, cause, indent2, true, true)?;
+    if error.causes.len() == 1 {
+        let cause = &error.causes[0].0;
+        let marker = match cause.severity {
+            Severity::Error => format!("[ caused by{} ]", KindSuffix(cause.kind.as_ref())),
+            severity => format!("[ caused by {}{} ]", noun(severity), KindSuffix(cause.kind.as_ref())),
+        };
+        writeln!(f, "{}{}", indent, Paint(color, YELLOW, marker))?;
+        fmt_error(f, &error.causes[0].0, indent, false, true, color)?;
+    } else if error.causes.len() > 1 {
+        for (i, cause) in error.causes.iter().enumerate() {
+            let marker = format!("[ caused by ({}/{}) ]", i + 1, error.causes.len());
+            writeln!(f, "{}{}", indent, Paint(color, YELLOW, marker))?;
+            fmt_error(f, &cause.0, indent2, true, true, color)?;
         }
     }
 
     if braced {
-        write!(f, "{}[ end of error ]", indent)?;
+        write!(f, "{}{}", indent, Paint(color, RED, "[ end of error ]"))?;
         if end_of_error_newline {
             f.write_str("\n")?;
         }
@@ -132,6 +191,35 @@ impl Display for Spaces {
         for _ in 0..self.0 {
             f.write_str(" ")?;
         }
-        Ok({})
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Name of an error of the given severity, for markers. 
+fn noun(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+        Severity::Fatal => "fatal error",
+    }
+}
+
+/// Style of the marker which begins an error of the given severity. 
+fn marker_style(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => YELLOW,
+        Severity::Error | Severity::Fatal => RED,
+    }
+}
+
+/// Displays as ` (kind)`, or nothing if there's no kind. 
+struct KindSuffix<'a>(Option<&'a ErrorKind>);
+
+impl<'a> Display for KindSuffix<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(kind) = self.0 {
+            write!(f, " ({})", kind)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+
+use crate::{Error, ErrorKind};
+use core::fmt::{self, Display, Formatter};
+use alloc::string::ToString;
+#[cfg(feature = "std")]
+use backtrace::Backtrace;
+
+/// `Display` wrapper which renders an `Error` as JSON. 
+///
+/// Constructed with `Error::json`. Renders an object of this form, with no 
+/// whitespace, where field values are their `Debug` representations, 
+/// `"kind"` is a string, integer, or `null`, `"severity"` is `"warning"`, 
+/// `"error"`, or `"fatal"`, `"location"` is the source 
+/// location the error was constructed at, or `null` if unknown, and 
+/// `"backtrace"` is `null` if no backtrace was captured: 
+///
+/// ```json
+/// {
+///     "message": "failed to load chunk",
+///     "kind": "not_found",
+///     "severity": "error",
+///     "location": "src/main.rs:38:20",
+///     "fields": {"pos": "[0, 1, 2]"},
+///     "backtrace": [
+///         {"ip": "0x55f1c2a3b4c5", "symbols": [
+///             {"name": "game::load", "file": "src/main.rs", "line": 40}
+///         ]}
+///     ],
+///     "causes": []
+/// }
+/// ```
+#[derive(Copy, Clone)]
+pub struct JsonDisplay<'a>(pub(crate) &'a Error);
+
+impl<'a> Display for JsonDisplay<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt_error(f, self.0)
+    }
+}
+
+fn fmt_error(f: &mut Formatter, error: &Error) -> fmt::Result {
+    f.write_str("{\"message\":")?;
+    fmt_str(f, &error.0.message)?;
+
+    f.write_str(",\"kind\":")?;
+    match error.0.kind.as_ref() {
+        Some(ErrorKind::Name(name)) => fmt_str(f, name)?,
+        Some(ErrorKind::Code(code)) => write!(f, "{}", code)?,
+        None => f.write_str("null")?,
+    };
+
+    f.write_str(",\"severity\":")?;
+    fmt_str(f, error.0.severity.name())?;
+
+    f.write_str(",\"location\":")?;
+    match error.0.location {
+        Some(location) => fmt_str(f, &location.to_string())?,
+        None => f.write_str("null")?,
+    };
+
+    f.write_str(",\"fields\":{")?;
+    for (i, (key, val)) in error.0.fields.iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+        fmt_str(f, key)?;
+        f.write_str(":")?;
+        fmt_str(f, val.debug())?;
+    }
+    f.write_str("}")?;
+
+    f.write_str(",\"backtrace\":")?;
+    #[cfg(feature = "std")]
+    match error.0.backtrace.as_ref() {
+        Some(backtrace) => fmt_backtrace(f, backtrace)?,
+        None => f.write_str("null")?,
+    };
+    #[cfg(not(feature = "std"))]
+    f.write_str("null")?;
+
+    f.write_str(",\"causes\":[")?;
+    for (i, cause) in error.0.causes.iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+        fmt_error(f, cause)?;
+    }
+    f.write_str("]}")?;
+
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn fmt_backtrace(f: &mut Formatter, backtrace: &Backtrace) -> fmt::Result {
+    // resolve a copy, since formatting can't mutate the error
+    let mut backtrace = backtrace.clone();
+    backtrace.resolve();
+
+    f.write_str("[")?;
+    for (i, frame) in backtrace.frames().iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+        write!(f, "{{\"ip\":\"{:?}\",\"symbols\":[", frame.ip())?;
+        for (j, symbol) in frame.symbols().iter().enumerate() {
+            if j > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str("{\"name\":")?;
+            match symbol.name() {
+                Some(name) => fmt_str(f, &name.to_string())?,
+                None => f.write_str("null")?,
+            };
+            f.write_str(",\"file\":")?;
+            match symbol.filename() {
+                Some(file) => fmt_str(f, &file.display().to_string())?,
+                None => f.write_str("null")?,
+            };
+            f.write_str(",\"line\":")?;
+            match symbol.lineno() {
+                Some(line) => write!(f, "{}", line)?,
+                None => f.write_str("null")?,
+            };
+            f.write_str("}")?;
+        }
+        f.write_str("]}")?;
+    }
+    f.write_str("]")?;
+
+    Ok(())
+}
+
+/// Write a JSON string literal. 
+fn fmt_str(f: &mut Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        };
+    }
+    f.write_str("\"")?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+
+use core::fmt::{self, Display, Formatter};
+use alloc::{
+    borrow::ToOwned,
+    string::String,
+};
+
+/// A programmatic category of `Error`, so that callers can branch on what 
+/// kind of error occurred without matching on the message. 
+///
+/// Kinds are either names, such as `"not_found"`, or integer codes. 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum ErrorKind {
+    /// A named kind.
+    Name(String),
+    /// An integer code.
+    Code(i64),
+}
+
+impl ErrorKind {
+    /// Get the name, if this is a named kind.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ErrorKind::Name(name) => Some(name),
+            ErrorKind::Code(_) => None,
+        }
+    }
+
+    /// Get the code, if this is an integer code.
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            ErrorKind::Name(_) => None,
+            ErrorKind::Code(code) => Some(*code),
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Name(name) => f.write_str(name),
+            ErrorKind::Code(code) => Display::fmt(code, f),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for ErrorKind {
+    fn from(name: &'a str) -> Self {
+        ErrorKind::Name(name.to_owned())
+    }
+}
+
+impl From<String> for ErrorKind {
+    fn from(name: String) -> Self {
+        ErrorKind::Name(name)
+    }
+}
+
+impl From<i64> for ErrorKind {
+    fn from(code: i64) -> Self {
+        ErrorKind::Code(code)
+    }
+}
+
+impl From<i32> for ErrorKind {
+    fn from(code: i32) -> Self {
+        ErrorKind::Code(code as i64)
+    }
+}
+
+impl From<u32> for ErrorKind {
+    fn from(code: u32) -> Self {
+        ErrorKind::Code(code as i64)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for ErrorKind {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.name() == Some(*other)
+    }
+}
+
+impl PartialEq<i64> for ErrorKind {
+    fn eq(&self, other: &i64) -> bool {
+        self.code() == Some(*other)
+    }
+}
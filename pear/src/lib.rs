@@ -3,74 +3,203 @@
 //! The primary type of this library is `Error`. It contains no boxed objects, 
 //! it's just simple old data. It contains three main sections of information: 
 //!
-//! 1. The _message_, a string summary of the error. If this is longer than 60 
-//!    visible characters, `Error` will automatically word-wrap it. 
+//! 1. The _message_, a string summary of the error. If this is longer than the 
+//!    wrap width, 60 visible characters by default, `Error` will automatically 
+//!    word-wrap it. 
 //! 2. The _fields_, which are (name, value) tuples of contextual information.
 //! 3. The _causes_, which is a `Vec<Error>`. Unlike most other error-handling 
 //!    libraries, this library can handle errors with more than one cause. 
 //!
 //! When a `Field` is constructed, or an underlying error is converted into a 
 //! `crate::Error`, the `std::fmt` API is used to convert it into a `String`.
+//! Fields constructed with `Field::lazy` are instead converted the first time 
+//! they're displayed or inspected. 
+//!
+//! The `std` feature, which is enabled by default, enables backtraces, 
+//! message word-wrapping, and conversions to and from `std::error::Error`. 
+//! Without it, this library works with `no_std` and `alloc`. 
 //!
 //! Set `RUST_BACKTRACE` to a non-`"0"` value to enable backtrace capturing. 
+//! Displayed backtraces hide frames from this library, the standard library, 
+//! and the runtime, which can be configured with `set_backtrace_filter`. 
+//!
+//! With the `tracing` feature, errors capture the current `tracing` span as 
+//! a `"span"` field, and `Error::emit` records an error as a `tracing` event. 
+//!
+//! With the `anyhow` feature, errors convert to and from `anyhow::Error`. 
+//!
+//! Errors can be displayed in ANSI colors, which is configured with 
+//! `set_color_mode`. 
+//!
+//! `Error` doesn't implement `std::error::Error` itself, since that would 
+//! conflict with the blanket `From<E: std::error::Error>` conversion, but it 
+//! converts into `Box<dyn std::error::Error>`, and `Error::as_std` borrows it 
+//! as one. 
+//!
+//! With the `serde` feature, `Error` and `Field` implement `Serialize` and 
+//! `Deserialize`, so errors can be sent between processes or stored in logs. 
+//! Backtraces are serialized as lists of resolved frames. 
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
 extern crate backtrace;
-extern crate map_vec;
+#[cfg(feature = "std")]
 extern crate textwrap;
 extern crate unicode_width;
+#[cfg(feature = "std")]
 extern crate ansi_parser;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "anyhow")]
+extern crate anyhow;
 
 /// `std::fmt::Display` implementation.
 mod display;
 
 /// Backtrace utilities.
+#[cfg(feature = "std")]
 mod backtrace_util;
 
-/// Eager conversion from `impl Debug` into `String`. 
+/// JSON rendering. 
+mod json;
+
+/// Error kinds. 
+mod kind;
+
+/// Error severities. 
+mod severity;
+
+/// ANSI color configuration. 
+mod color;
+
+/// Walking the cause tree. 
+mod tree;
+
+/// Eager or lazy conversion from `impl Debug` into `String`. 
 mod pre_debug;
 
+/// `no_std` replacement for `map_vec`. 
+mod vec_map;
+
+/// `serde` implementations. 
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+/// `tracing` integration. 
+#[cfg(feature = "tracing")]
+mod tracing_impl;
+
+/// `anyhow` interoperability. 
+#[cfg(feature = "anyhow")]
+mod anyhow_impl;
+
+pub use crate::{
+    json::JsonDisplay,
+    kind::ErrorKind,
+    severity::Severity,
+    tree::{
+        IterTree,
+        Visitor,
+    },
+    display::{
+        set_default_wrap_width,
+        default_wrap_width,
+    },
+    color::{
+        ColorMode,
+        set_color_mode,
+        color_mode,
+    },
+};
+#[cfg(feature = "std")]
+pub use crate::backtrace_util::{
+    BacktraceFilter,
+    set_backtrace_filter,
+    backtrace_filter,
+};
+
 use crate::{
     pre_debug::PreDebug,
-    backtrace_util::capture_backtrace_if_enabled,
+    vec_map::VecMap,
 };
-use std::{
+#[cfg(feature = "std")]
+use crate::backtrace_util::capture_backtrace_if_enabled;
+use core::{
+    any::Any,
     borrow::Borrow,
     iter,
     fmt::Debug,
+    panic::Location,
+};
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
 };
+#[cfg(feature = "std")]
 use backtrace::Backtrace;
-use map_vec::Map as VecMap;
+
+/// Internals for macros. 
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::format;
+}
 
 /// An error. 
 ///
 /// This is the primary type of this library. It contains no boxed objects, 
 /// it's just simple old data. It contains three main sections of information: 
 ///
-/// 1. The _message_, a string summary of the error. If this is longer than 60 
-///    visible characters, `Error` will automatically word-wrap it. 
+/// 1. The _message_, a string summary of the error. If this is longer than the 
+///    wrap width, 60 visible characters by default, `Error` will automatically 
+///    word-wrap it. 
 /// 2. The _fields_, which are (name, value) tuples of contextual information.
 /// 3. The _causes_, which is a `Vec<Error>`. Unlike most other error-handling 
 ///    libraries, this library can handle errors with more than one cause. 
 ///
 /// When a `Field` is constructed, or an underlying error is converted into a 
 /// `crate::Error`, the `std::fmt` API is used to convert it into a `String`.
+/// Fields constructed with `Field::lazy` are instead converted the first time 
+/// they're displayed or inspected. 
 ///
 /// Set `RUST_BACKTRACE` to a non-`"0"` value to enable backtrace capturing. 
 /// If `self` is formatted in alternative display mode (`{:#}`) then the 
 /// `Backtrace` will format with more verbose information.  
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Error(Box<ErrorInner>);
 
 /// Alias for `Result<I, crate::Error>`.
-pub type Result<I> = std::result::Result<I, Error>;
+pub type Result<I> = core::result::Result<I, Error>;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct ErrorInner {
     message: String,
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::fields"))]
     fields: VecMap<String, PreDebug>,
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(default, with = "serde_impl::backtrace"))]
     backtrace: Option<Backtrace>,
     causes: Vec<Error>,
     wrap_enabled: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    wrap_width: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    kind: Option<ErrorKind>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    severity: Severity,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    payload: Option<Arc<dyn Any + Send + Sync>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    location: Option<&'static Location<'static>>,
 }
 
 /// A captured (name, value) tuple of `Error` contextual information. 
@@ -79,18 +208,31 @@ pub struct Field(String, PreDebug);
 
 impl Error {
     /// Construct a new `Error` from message, fields, and causes. 
+    ///
+    /// The location this is called from is recorded, and available from 
+    /// `location`. 
+    #[track_caller]
     pub fn new<M, F, C>(message: M, fields: F, causes: C) -> Self
     where
         M: Into<String>,
         F: IntoIterator<Item=Field>,
         C: IntoCauses,
     {
+        #[cfg(feature = "tracing")]
+        let fields = tracing_impl::capture_span().into_iter().chain(fields);
+
         Error(Box::new(ErrorInner {
             message: message.into(),
             fields: fields.into_iter().map(|Field(k, v)| (k, v)).collect(),
+            #[cfg(feature = "std")]
             backtrace: capture_backtrace_if_enabled(),
             causes: causes.into_causes(),
             wrap_enabled: true,
+            wrap_width: None,
+            kind: None,
+            severity: Severity::Error,
+            payload: None,
+            location: Some(Location::caller()),
         }))
     }
 
@@ -99,6 +241,8 @@ impl Error {
     /// This will crawl the error's `.source()` chain and convert it 
     /// into a chain of single-cause `Error` by using `.to_string()` for
     /// the messages. 
+    #[cfg(feature = "std")]
+    #[track_caller]
     pub fn from_std(error: &dyn std::error::Error) -> Self
     {
         let mut head = Error::new(error.to_string(), iter::empty(), ());
@@ -117,6 +261,48 @@ impl Error {
         head
     }
 
+    /// Get the source location `self` was constructed at, if known. 
+    ///
+    /// This is where `Error::new` was called from, or where the `pear!` 
+    /// macro was invoked, or where a `std::error::Error` was converted with 
+    /// `?`. It's `None` for deserialized errors, since locations aren't 
+    /// serialized. 
+    ///
+    /// ```
+    /// use pear::{pear, Error, ResultExt};
+    /// 
+    /// let error = Error::new("failed", vec![], ());
+    /// let location = error.location().unwrap();
+    /// assert_eq!(location.file(), file!());
+    /// assert_eq!(location.line(), line!() - 3);
+    ///
+    /// fn fail() -> pear::Result<()> {
+    ///     Err(pear!({}, "failed"))
+    /// }
+    ///
+    /// let error = fail().context("context").unwrap_err();
+    /// assert_eq!(error.location().unwrap().file(), file!());
+    /// assert_eq!(error.location().unwrap().line(), line!() - 2);
+    ///
+    /// let error = fail().with_context(|| "context").unwrap_err();
+    /// assert_eq!(error.location().unwrap().file(), file!());
+    /// assert_eq!(error.location().unwrap().line(), line!() - 2);
+    ///
+    /// let error = fail().wrap_err(|| pear!({}, "context")).unwrap_err();
+    /// assert_eq!(error.location().unwrap().file(), file!());
+    /// assert_eq!(error.location().unwrap().line(), line!() - 2);
+    /// ```
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.0.location
+    }
+
+    /// Override the source location `self` was constructed at, such as 
+    /// for a helper function which constructs errors on behalf of its 
+    /// caller. 
+    pub fn set_location(&mut self, location: Option<&'static Location<'static>>) {
+        self.0.location = location;
+    }
+
     /// Get `self`'s message. 
     pub fn message(&self) -> &str { &self.0.message }
 
@@ -164,11 +350,13 @@ impl Error {
     }
 
     /// Get the captured `Backtrace` of `self`, if one was captured. 
+    #[cfg(feature = "std")]
     pub fn backtrace(&self) -> Option<&Backtrace> {
         self.0.backtrace.as_ref()
     }
 
     /// Mutably access the optional captured `Backtrace` of `self`.
+    #[cfg(feature = "std")]
     pub fn backtrace_mut(&mut self) -> &mut Option<Backtrace> {
         &mut self.0.backtrace
     }
@@ -183,14 +371,251 @@ impl Error {
         &mut self.0.causes
     }
 
+    /// Iterate over `self` and its causes, recursively, depth-first, along 
+    /// with their depths below `self`. 
+    pub fn iter_tree(&self) -> IterTree<'_> {
+        IterTree::new(self)
+    }
+
+    /// Walk `self` and its causes, recursively, depth-first, with a 
+    /// `Visitor`. 
+    pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        tree::visit(self, 0, visitor)
+    }
+
     /// Mutably access whether message word-wrapping is enabled.
     pub fn wrap_enabled_mut(&mut self) -> &mut bool {
         &mut self.0.wrap_enabled
     }
+
+    /// Get the width `self`'s message is word-wrapped to, in visible 
+    /// characters. 
+    ///
+    /// This is the global default set by `set_default_wrap_width`, unless 
+    /// overridden for `self` by `set_wrap_width`. 
+    pub fn wrap_width(&self) -> usize {
+        self.0.wrap_width.unwrap_or_else(default_wrap_width)
+    }
+
+    /// Override the width `self`'s message is word-wrapped to, or reset to 
+    /// the global default if `None`. Doesn't affect `self`'s causes. 
+    ///
+    /// ```
+    /// use pear::Error;
+    /// 
+    /// let mut error = Error::new("a b c d e f g h", vec![], ());
+    /// error.set_wrap_width(Some(4));
+    /// assert_eq!(error.wrap_width(), 4);
+    /// // messages are only wrapped with the `std` feature
+    /// # #[cfg(feature = "std")]
+    /// assert!(error.to_string().contains("    a b\n    c d\n"));
+    /// ```
+    pub fn set_wrap_width(&mut self, width: Option<usize>) {
+        self.0.wrap_width = width;
+    }
+
+    /// Get `self`'s kind, if it has one. 
+    pub fn kind(&self) -> Option<&ErrorKind> {
+        self.0.kind.as_ref()
+    }
+
+    /// Mutably access `self`'s optional kind. 
+    pub fn kind_mut(&mut self) -> &mut Option<ErrorKind> {
+        &mut self.0.kind
+    }
+
+    /// Set `self`'s kind, builder-style. 
+    ///
+    /// ```
+    /// use pear::{pear, Error, ResultExt};
+    /// 
+    /// fn open() -> pear::Result<()> {
+    ///     Err(pear!({}, "no such file").with_kind("not_found"))
+    /// }
+    ///
+    /// let error = open()
+    ///     .wrap_err(|| pear!({}, "failed to load settings"))
+    ///     .unwrap_err();
+    /// assert!(error.kind().is_none());
+    /// assert!(error.has_kind("not_found"));
+    /// assert_eq!(error.find_kind("not_found").unwrap().message(), "no such file");
+    /// assert!(!error.has_kind(404));
+    /// ```
+    pub fn with_kind<K: Into<ErrorKind>>(mut self, kind: K) -> Self {
+        self.0.kind = Some(kind.into());
+        self
+    }
+
+    /// Whether `self` itself is of the given kind. 
+    pub fn is_kind<K: Into<ErrorKind>>(&self, kind: K) -> bool {
+        self.0.kind == Some(kind.into())
+    }
+
+    /// Find the first error of the given kind, searching `self` and then its 
+    /// causes, depth-first. 
+    pub fn find_kind<K: Into<ErrorKind>>(&self, kind: K) -> Option<&Error> {
+        let kind = kind.into();
+        self.iter_tree()
+            .map(|(_, error)| error)
+            .find(|error| error.0.kind.as_ref() == Some(&kind))
+    }
+
+    /// Whether `self` or any of its causes, recursively, is of the given 
+    /// kind. 
+    pub fn has_kind<K: Into<ErrorKind>>(&self, kind: K) -> bool {
+        self.find_kind(kind).is_some()
+    }
+
+    /// Get `self`'s severity. 
+    pub fn severity(&self) -> Severity {
+        self.0.severity
+    }
+
+    /// Mutably access `self`'s severity. 
+    pub fn severity_mut(&mut self) -> &mut Severity {
+        &mut self.0.severity
+    }
+
+    /// Set `self`'s severity, builder-style. 
+    ///
+    /// ```
+    /// use pear::{pear, Error, Severity};
+    /// 
+    /// let error = Error::new("failed to load world", vec![], vec![
+    ///     pear!({}, "missing texture").with_severity(Severity::Warning),
+    ///     pear!({}, "corrupt region file").with_severity(Severity::Fatal),
+    /// ]);
+    /// assert_eq!(error.severity(), Severity::Error);
+    /// assert_eq!(error.max_severity(), Severity::Fatal);
+    /// assert!(error.to_string().contains("[ fatal error ]"));
+    /// assert!(error.to_string().contains("[ warning ]"));
+    /// ```
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.0.severity = severity;
+        self
+    }
+
+    /// Get the highest severity of `self` and its causes, recursively. 
+    pub fn max_severity(&self) -> Severity {
+        self.iter_tree()
+            .map(|(_, error)| error.0.severity)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Attach a typed payload to `self`, builder-style, replacing any 
+    /// existing payload. 
+    ///
+    /// Payloads carry structured data, such as a retry deadline, for callers 
+    /// to recover with `downcast_payload`. They're shared when `self` is 
+    /// cloned, and aren't displayed or serialized. 
+    ///
+    /// ```
+    /// use pear::{pear, ResultExt};
+    /// use std::time::Duration;
+    /// 
+    /// struct RetryAfter(Duration);
+    /// 
+    /// let result: pear::Result<()> = Err(pear!({}, "server busy")
+    ///     .with_payload(RetryAfter(Duration::from_secs(5))));
+    /// let error = result.context("failed to join server").unwrap_err();
+    /// assert!(error.payload().is_none());
+    /// let RetryAfter(delay) = error.downcast_payload::<RetryAfter>().unwrap();
+    /// assert_eq!(*delay, Duration::from_secs(5));
+    /// assert!(error.downcast_payload::<u32>().is_none());
+    /// ```
+    pub fn with_payload<T: Any + Send + Sync>(mut self, payload: T) -> Self {
+        self.set_payload(Box::new(payload));
+        self
+    }
+
+    /// Attach a payload to `self`, replacing any existing payload. 
+    pub fn set_payload(&mut self, payload: Box<dyn Any + Send + Sync>) {
+        self.0.payload = Some(Arc::from(payload));
+    }
+
+    /// Remove `self`'s payload. 
+    pub fn clear_payload(&mut self) {
+        self.0.payload = None;
+    }
+
+    /// Get `self`'s own payload, if it has one. 
+    pub fn payload(&self) -> Option<&(dyn Any + Send + Sync)> {
+        self.0.payload.as_deref()
+    }
+
+    /// Find the first payload of type `T`, searching `self` and then its 
+    /// causes, depth-first. 
+    pub fn downcast_payload<T: Any>(&self) -> Option<&T> {
+        self.iter_tree()
+            .filter_map(|(_, error)| error.payload())
+            .find_map(|payload| payload.downcast_ref::<T>())
+    }
+
+    /// Borrow `self` as a `std::error::Error`. 
+    ///
+    /// Its `source` is the first of `self`'s causes, since `std::error::Error` 
+    /// can only represent a chain. Use `std_causes` to iterate over all of 
+    /// them. 
+    ///
+    /// ```
+    /// use pear::Error;
+    /// 
+    /// let causes = vec![Error::new("first", vec![], ()), Error::new("second", vec![], ())];
+    /// let error = Error::new("outer", vec![], causes);
+    /// let source = error.as_std().source().unwrap();
+    /// assert!(source.to_string().contains("first"));
+    /// assert_eq!(error.std_causes().count(), 2);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn as_std(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        &*self.0
+    }
+
+    /// Iterate over all of `self`'s causes, as `std::error::Error`s. 
+    #[cfg(feature = "std")]
+    pub fn std_causes<'s>(&'s self) -> impl Iterator<Item = &'s (dyn std::error::Error + Send + Sync + 'static)> + 's {
+        self.0.causes.iter()
+            .map(Error::as_std)
+    }
+
+    /// Display `self` as machine-readable JSON, such as for log pipelines. 
+    ///
+    /// See `JsonDisplay` for the format. 
+    ///
+    /// ```
+    /// use pear::Error;
+    /// 
+    /// let mut error = Error::new("outer", vec![], Error::new("inner \"quoted\"", vec![], ()));
+    /// # #[cfg(feature = "std")] {
+    /// *error.backtrace_mut() = None;
+    /// *error.causes_mut()[0].backtrace_mut() = None;
+    /// # }
+    /// error.set_location(None);
+    /// error.causes_mut()[0].set_location(None);
+    /// assert_eq!(
+    ///     error.json().to_string(),
+    ///     r#"{"message":"outer","kind":null,"severity":"error","location":null,"fields":{},"backtrace":null,"causes":[{"message":"inner \"quoted\"","kind":null,"severity":"error","location":null,"fields":{},"backtrace":null,"causes":[]}]}"#,
+    /// );
+    /// ```
+    pub fn json(&self) -> JsonDisplay<'_> {
+        JsonDisplay(self)
+    }
+
+    /// Render `self` as machine-readable JSON. Shorthand for 
+    /// `self.json().to_string()`. 
+    pub fn to_json(&self) -> String {
+        self.json().to_string()
+    }
 }
 
 impl Field {
     /// Construct a new `Field` from key and value. 
+    ///
+    /// Unlike `Error::new`, this doesn't record where it's called from. A 
+    /// field is stored in an error as just its key and value, so the 
+    /// location would be discarded, and it's the same as the error's 
+    /// `location` when fields come from `pear!`. 
     pub fn new<K, V>(key: K, val: V) -> Self
     where
         K: Into<String>,
@@ -198,6 +623,34 @@ impl Field {
     {
         Field(key.into(), PreDebug::new(val))
     }
+
+    /// Construct a new `Field` from key and a function which produces the 
+    /// value, which is only called, and formatted, when the field is first 
+    /// displayed or inspected. 
+    ///
+    /// This avoids the cost of formatting values for errors which are 
+    /// handled without being displayed. In `pear!`, a field written as 
+    /// `key => value` is constructed with this, moving what `value` uses 
+    /// into the closure. 
+    ///
+    /// Without the `std` feature, the value is formatted immediately. 
+    ///
+    /// ```
+    /// use pear::pear;
+    /// 
+    /// let chunk = vec![0u8; 4096];
+    /// let error = pear!({ len = chunk.len(), chunk => chunk }, "bad chunk");
+    /// let (_, chunk) = error.fields().find(|&(key, _)| key == "chunk").unwrap();
+    /// assert!(format!("{:?}", chunk).starts_with("[0, 0"));
+    /// ```
+    pub fn lazy<K, V, F>(key: K, val: F) -> Self
+    where
+        K: Into<String>,
+        V: Debug,
+        F: FnOnce() -> V + Send + 'static,
+    {
+        Field(key.into(), PreDebug::lazy(val))
+    }
 }
 
 /// Types which can represent an `Error` cause or list of causes. 
@@ -253,7 +706,7 @@ pub trait ResultExt: Sized {
     ///
     /// `parent` is actually a function which is used to construct the parent 
     /// `Error`, if `self` is the `Err` variant. 
-    fn wrap_err<F>(self, parent: F) -> std::result::Result<Self::Item, Error>
+    fn wrap_err<F>(self, parent: F) -> core::result::Result<Self::Item, Error>
     where
         F: FnOnce() -> Error;
 
@@ -266,16 +719,57 @@ pub trait ResultExt: Sized {
     ///
     /// In the case that `self` is the `Err` variant, `vec` will be emptied 
     /// by the time this methods returns. 
-    fn pull_err(self, vec: &mut Vec<Error>) -> std::result::Result<Self::Item, Vec<Error>>;
+    fn pull_err(self, vec: &mut Vec<Error>) -> core::result::Result<Self::Item, Vec<Error>>;
+
+    /// If `self` is the `Err` variant, insert a field into the underlying 
+    /// error. 
+    ///
+    /// `val` is only called if `self` is the `Err` variant. If there are 
+    /// several underlying errors, or none, they're first wrapped in a new 
+    /// `Error`, which the field is inserted into. 
+    ///
+    /// ```
+    /// use pear::{pear, ResultExt};
+    /// 
+    /// let path = "settings.toml";
+    /// let result: pear::Result<()> = Err(pear!({}, "file not found"));
+    /// let error = result
+    ///     .with_field("path", || path)
+    ///     .context("failed to load settings")
+    ///     .unwrap_err();
+    /// assert_eq!(error.message(), "failed to load settings");
+    /// assert!(error.causes()[0].fields().any(|(key, _)| key == "path"));
+    /// ```
+    fn with_field<K, V, F>(self, key: K, val: F) -> core::result::Result<Self::Item, Error>
+    where
+        K: Into<String>,
+        V: Debug,
+        F: FnOnce() -> V;
+
+    /// If `self` is the `Err` variant, wrap the underlying error in a new 
+    /// `Error` with the given message, and no fields. 
+    fn context<M>(self, message: M) -> core::result::Result<Self::Item, Error>
+    where
+        M: Into<String>;
+
+    /// If `self` is the `Err` variant, wrap the underlying error in a new 
+    /// `Error` with the message returned by `message`, and no fields. 
+    ///
+    /// `message` is only called if `self` is the `Err` variant, so it may 
+    /// `format!` freely. 
+    fn with_context<M, F>(self, message: F) -> core::result::Result<Self::Item, Error>
+    where
+        M: Into<String>,
+        F: FnOnce() -> M;
 }
 
-impl<I, E> ResultExt for std::result::Result<I, E>
+impl<I, E> ResultExt for core::result::Result<I, E>
 where
     E: IntoCauses,
 {
     type Item = I;
 
-    fn wrap_err<F>(self, parent: F) -> std::result::Result<Self::Item, Error>
+    fn wrap_err<F>(self, parent: F) -> core::result::Result<Self::Item, Error>
     where
         F: FnOnce() -> Error
     {
@@ -290,21 +784,110 @@ where
         }
     }
 
-    fn pull_err(self, vec: &mut Vec<Error>) -> std::result::Result<Self::Item, Vec<Error>> {
+    fn pull_err(self, vec: &mut Vec<Error>) -> core::result::Result<Self::Item, Vec<Error>> {
         self.map_err(move |causes| {
             let mut causes = causes.into_causes();
-            causes.extend(vec.drain(..));
+            causes.append(vec);
             causes
         })
     }
+
+    #[track_caller]
+    fn with_field<K, V, F>(self, key: K, val: F) -> core::result::Result<Self::Item, Error>
+    where
+        K: Into<String>,
+        V: Debug,
+        F: FnOnce() -> V
+    {
+        let location = Location::caller();
+        self.map_err(move |causes| {
+            let mut causes = causes.into_causes();
+            let causes_len = causes.len();
+            let mut error = match causes_len {
+                1 => causes.pop().unwrap(),
+                0 => Error::new("unknown error", iter::empty(), ()),
+                _ => Error::new("multiple errors", iter::empty(), causes),
+            };
+            if causes_len != 1 {
+                // constructed here on behalf of the caller
+                error.set_location(Some(location));
+            }
+            error.put_field(Field::new(key, val()));
+            error
+        })
+    }
+
+    #[track_caller]
+    fn context<M>(self, message: M) -> core::result::Result<Self::Item, Error>
+    where
+        M: Into<String>
+    {
+        self.with_context(move || message)
+    }
+
+    #[track_caller]
+    fn with_context<M, F>(self, message: F) -> core::result::Result<Self::Item, Error>
+    where
+        M: Into<String>,
+        F: FnOnce() -> M
+    {
+        let location = Location::caller();
+        self.wrap_err(move || {
+            let mut error = Error::new(message(), iter::empty(), ());
+            error.set_location(Some(location));
+            error
+        })
+    }
 }
 
+#[cfg(feature = "std")]
 impl<E: std::error::Error> From<E> for Error {
+    #[track_caller]
     fn from(e: E) -> Self {
         Error::from_std(&e)
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for ErrorInner {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.causes.first()
+            .map(|cause| &*cause.0 as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Convert into a boxed `std::error::Error`, such as for `?` in a function 
+/// returning `Result<_, Box<dyn std::error::Error>>`. 
+///
+/// ```
+/// use pear::pear;
+/// 
+/// fn load() -> pear::Result<()> {
+///     Err(pear!({}, "failed to load"))
+/// }
+///
+/// fn run() -> Result<(), Box<dyn std::error::Error>> {
+///     load()?;
+///     Ok(())
+/// }
+///
+/// assert!(run().unwrap_err().to_string().contains("failed to load"));
+/// ```
+#[cfg(feature = "std")]
+impl From<Error> for Box<dyn std::error::Error + Send + Sync + 'static> {
+    fn from(e: Error) -> Self {
+        e.0
+    }
+}
+
+/// Convert into a boxed `std::error::Error`. 
+#[cfg(feature = "std")]
+impl From<Error> for Box<dyn std::error::Error + 'static> {
+    fn from(e: Error) -> Self {
+        e.0
+    }
+}
+
 /// Construct an `Error` from a field list, followed by the standard string 
 /// interpolation syntax for the message. 
 ///
@@ -312,6 +895,9 @@ impl<E: std::error::Error> From<E> for Error {
 /// `"rust_module"` and `"rust_line"` fields using the `module_path!()` and 
 /// `line!()` macros. 
 ///
+/// Fields written as `key => value` rather than `key = value` are lazy, as 
+/// with `Field::lazy`. 
+///
 /// ```
 /// use pear::pear;
 /// 
@@ -336,26 +922,166 @@ impl<E: std::error::Error> From<E> for Error {
 #[macro_export]
 macro_rules! pear {
     (
-        { $($key:ident = $val:expr),* $(,)? },
+        { $($key:ident $op:tt $val:expr),* $(,)? },
         $($fmt:tt)*
     )=>{{
         let mut error = $crate::Error::new(
-            ::std::format!($($fmt)*),
-            ::std::iter::empty(),
+            $crate::__private::format!($($fmt)*),
+            ::core::iter::empty(),
             (),
         );
         $(
-            error.put_field($crate::Field::new(
-                stringify!($key),
-                &$val,
-            ));
+            error.put_field($crate::__pear_field!($key $op $val));
         )*
         error.put_field($crate::Field::new(
-            "rust_module", ::std::module_path!()
+            "rust_module", ::core::module_path!()
         ));
         error.put_field($crate::Field::new(
-            "rust_line", ::std::line!()
+            "rust_line", ::core::line!()
         ));
         error
     }};
 }
+
+/// Construct a `Field` from a `pear!` field list entry. 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pear_field {
+    ($key:ident = $val:expr)=>{
+        $crate::Field::new(stringify!($key), &$val)
+    };
+    ($key:ident => $val:expr)=>{
+        $crate::Field::lazy(stringify!($key), move || $val)
+    };
+}
+
+/// Return an `Error` from the enclosing function if a condition is false, 
+/// rather than panicking. 
+///
+/// The arguments after the condition are a field list and message, as with 
+/// `pear!`. If they're omitted, the message is the stringified condition. In 
+/// either case, the stringified condition is put in the `"assertion"` field. 
+///
+/// The enclosing function must return a `Result` whose error type can be 
+/// converted from `Error`. 
+///
+/// ```
+/// use pear::{pear_assert, Result};
+///
+/// fn check_len(len: usize) -> Result<()> {
+///     pear_assert!(len % 3 == 0, { len = len }, "mesh length not a multiple of 3");
+///     Ok(())
+/// }
+///
+/// assert!(check_len(6).is_ok());
+/// let error = check_len(7).unwrap_err();
+/// assert_eq!(error.message(), "mesh length not a multiple of 3");
+/// let fields = error.fields()
+///     .map(|(key, val)| format!("{} = {:?}", key, val))
+///     .collect::<Vec<_>>();
+/// assert_eq!(fields[0], "assertion = \"len % 3 == 0\"");
+/// assert_eq!(fields[1], "len = 7");
+/// ```
+#[macro_export]
+macro_rules! pear_assert {
+    ($cond:expr $(,)?) => {
+        $crate::pear_assert!(
+            $cond,
+            {},
+            "assertion failed: {}",
+            ::core::stringify!($cond),
+        )
+    };
+    (
+        $cond:expr,
+        { $($key:ident $op:tt $val:expr),* $(,)? },
+        $($fmt:tt)+
+    ) => {
+        if !$cond {
+            let error = $crate::pear!({
+                assertion = ::core::stringify!($cond),
+                $($key $op $val,)*
+            }, $($fmt)+);
+            return ::core::result::Result::Err(::core::convert::From::from(error));
+        }
+    };
+}
+
+/// Return an `Error` from the enclosing function if two values aren't equal, 
+/// rather than panicking. 
+///
+/// This is like `pear_assert!`, but the values are also put in the `"left"` 
+/// and `"right"` fields. Each value is evaluated once. 
+///
+/// ```
+/// use pear::{pear_assert_eq, Result};
+///
+/// fn check_sync(buffer_len: usize, mesh_len: usize) -> Result<()> {
+///     pear_assert_eq!(buffer_len, mesh_len, {}, "buffer out of sync with mesh");
+///     Ok(())
+/// }
+///
+/// assert!(check_sync(3, 3).is_ok());
+/// let error = check_sync(3, 4).unwrap_err();
+/// let (_, right) = error.fields().find(|&(key, _)| key == "right").unwrap();
+/// assert_eq!(format!("{:?}", right), "4");
+/// ```
+#[macro_export]
+macro_rules! pear_assert_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::pear_assert_eq!(
+            $left,
+            $right,
+            {},
+            "assertion failed: {} == {}",
+            ::core::stringify!($left),
+            ::core::stringify!($right),
+        )
+    };
+    (
+        $left:expr,
+        $right:expr,
+        { $($key:ident $op:tt $val:expr),* $(,)? },
+        $($fmt:tt)+
+    ) => {
+        match (&$left, &$right) {
+            (left, right) => if !(*left == *right) {
+                let error = $crate::pear!({
+                    assertion = ::core::concat!(
+                        ::core::stringify!($left),
+                        " == ",
+                        ::core::stringify!($right),
+                    ),
+                    left = left,
+                    right = right,
+                    $($key $op $val,)*
+                }, $($fmt)+);
+                return ::core::result::Result::Err(::core::convert::From::from(error));
+            }
+        }
+    };
+}
+
+/// Like `pear_assert!`, but only checked in builds with debug assertions. 
+///
+/// In other builds, nothing is evaluated. 
+#[macro_export]
+macro_rules! pear_debug_assert {
+    ($($arg:tt)*) => {
+        if ::core::cfg!(debug_assertions) {
+            $crate::pear_assert!($($arg)*);
+        }
+    };
+}
+
+/// Like `pear_assert_eq!`, but only checked in builds with debug assertions. 
+///
+/// In other builds, nothing is evaluated. 
+#[macro_export]
+macro_rules! pear_debug_assert_eq {
+    ($($arg:tt)*) => {
+        if ::core::cfg!(debug_assertions) {
+            $crate::pear_assert_eq!($($arg)*);
+        }
+    };
+}
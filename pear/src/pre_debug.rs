@@ -1,30 +1,115 @@
 
-use std::fmt::{self, Debug, Formatter};
+use core::fmt::{self, Debug, Formatter};
+use alloc::{
+    format,
+    string::String,
+};
+#[cfg(feature = "std")]
+use alloc::{
+    boxed::Box,
+    sync::Arc,
+};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, OnceLock};
 
 #[derive(Clone)]
-pub struct PreDebug {
+pub enum PreDebug {
+    Eager(Strings),
+    #[cfg(feature = "std")]
+    Lazy(Arc<LazyDebug>),
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Strings {
     pub debug: String,
     pub debug_alt: String,
 }
 
-impl PreDebug {
-    pub fn new<F>(f: F) -> Self 
+/// A value which is formatted the first time it's accessed. 
+#[cfg(feature = "std")]
+pub struct LazyDebug {
+    strings: OnceLock<Strings>,
+    // taken when formatted
+    value: Mutex<Option<Box<dyn FnOnce() -> Strings + Send>>>,
+}
+
+impl Strings {
+    fn new<F>(f: F) -> Self 
     where
         F: Debug,
     {
-        PreDebug {
+        Strings {
             debug: format!("{:?}", f),
             debug_alt: format!("{:#?}", f),
         }
     }
 }
 
+impl PreDebug {
+    pub fn new<F>(f: F) -> Self 
+    where
+        F: Debug,
+    {
+        PreDebug::Eager(Strings::new(f))
+    }
+
+    /// Without the `std` feature, this isn't actually lazy. 
+    #[cfg(not(feature = "std"))]
+    pub fn lazy<V, F>(f: F) -> Self
+    where
+        V: Debug,
+        F: FnOnce() -> V + Send + 'static,
+    {
+        PreDebug::new(f())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn lazy<V, F>(f: F) -> Self
+    where
+        V: Debug,
+        F: FnOnce() -> V + Send + 'static,
+    {
+        PreDebug::Lazy(Arc::new(LazyDebug {
+            strings: OnceLock::new(),
+            value: Mutex::new(Some(Box::new(move || Strings::new(f())))),
+        }))
+    }
+
+    /// Get the strings, formatting them if this is lazy and they haven't 
+    /// been yet. 
+    pub fn strings(&self) -> &Strings {
+        match self {
+            PreDebug::Eager(strings) => strings,
+            #[cfg(feature = "std")]
+            PreDebug::Lazy(lazy) => lazy.strings.get_or_init(|| {
+                let value = lazy.value.lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .take();
+                match value {
+                    Some(value) => value(),
+                    // formatting panicked on another thread
+                    None => Strings::new(format_args!("<panicked>")),
+                }
+            }),
+        }
+    }
+
+    pub fn debug(&self) -> &str {
+        &self.strings().debug
+    }
+
+    pub fn debug_alt(&self) -> &str {
+        &self.strings().debug_alt
+    }
+}
+
 impl Debug for PreDebug {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         if f.alternate() {
-            f.write_str(&self.debug_alt)
+            f.write_str(self.debug_alt())
         } else {
-            f.write_str(&self.debug)
+            f.write_str(self.debug())
         }
     }
 }
@@ -0,0 +1,101 @@
+
+use crate::{
+    Field,
+    pre_debug::{PreDebug, Strings},
+};
+use alloc::{
+    string::String,
+    vec::Vec,
+};
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+
+#[derive(Serialize)]
+struct FieldRef<'a> {
+    key: &'a str,
+    value: &'a PreDebug,
+}
+
+#[derive(Deserialize)]
+struct FieldOwned {
+    key: String,
+    value: PreDebug,
+}
+
+// lazy values are formatted, and deserialize as eager
+impl Serialize for PreDebug {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.strings().serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for PreDebug {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        Strings::deserialize(d).map(PreDebug::Eager)
+    }
+}
+
+impl Serialize for Field {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        FieldRef { key: &self.0, value: &self.1 }.serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let FieldOwned { key, value } = FieldOwned::deserialize(d)?;
+        Ok(Field(key, value))
+    }
+}
+
+/// `ErrorInner::fields`, as a list of `Field`.
+pub mod fields {
+    use super::*;
+    use crate::vec_map::VecMap;
+
+    pub fn serialize<S>(fields: &VecMap<String, PreDebug>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.collect_seq(fields.iter()
+            .map(|(key, value)| FieldRef { key, value }))
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<VecMap<String, PreDebug>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields = Vec::<FieldOwned>::deserialize(d)?;
+        Ok(fields.into_iter()
+            .map(|FieldOwned { key, value }| (key, value))
+            .collect())
+    }
+}
+
+/// `ErrorInner::backtrace`, as a list of resolved frames. 
+///
+/// Symbols are resolved before serializing, so that the frames are still 
+/// meaningful in a different process. 
+#[cfg(feature = "std")]
+pub mod backtrace {
+    use super::*;
+    use ::backtrace::Backtrace;
+
+    pub fn serialize<S>(backtrace: &Option<Backtrace>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let resolved = backtrace.clone()
+            .map(|mut backtrace| {
+                backtrace.resolve();
+                backtrace
+            });
+        resolved.serialize(s)
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<Option<Backtrace>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Backtrace>::deserialize(d)
+    }
+}
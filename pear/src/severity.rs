@@ -0,0 +1,37 @@
+
+use core::fmt::{self, Display, Formatter};
+
+/// How severe an `Error` is, so that an error with many causes can 
+/// distinguish fatal causes from recoverable ones. 
+///
+/// Severities are ordered from least to most severe. Errors are of 
+/// `Severity::Error` unless set otherwise. 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// Something went wrong, but it can be recovered from. 
+    Warning,
+    /// An ordinary error. 
+    #[default]
+    Error,
+    /// Something went wrong which can't be recovered from. 
+    Fatal,
+}
+
+impl Severity {
+    /// Get the lowercase name of this severity. 
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Fatal => "fatal",
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
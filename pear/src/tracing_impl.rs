@@ -0,0 +1,83 @@
+
+use crate::{Error, Field, Severity};
+use std::fmt::{self, Display, Formatter};
+use tracing::{Level, Span};
+
+/// Capture the current `tracing` span as a `"span"` field, if there is one. 
+pub fn capture_span() -> Option<Field> {
+    let span = Span::current();
+    let meta = span.metadata()?;
+    Some(Field::new("span", format!("{}::{}", meta.target(), meta.name())))
+}
+
+/// Displays an error's fields as `key = value` pairs. 
+struct Fields<'a>(&'a Error);
+
+impl<'a> Display for Fields<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, (key, val)) in self.0.fields().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} = {:?}", key, val)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    /// Record `self` as a `tracing` event at the `WARN` level if it's a 
+    /// warning, or the `ERROR` level otherwise. 
+    ///
+    /// See `emit_at`. 
+    pub fn emit(&self) {
+        match self.severity() {
+            Severity::Warning => self.emit_at(Level::WARN),
+            Severity::Error | Severity::Fatal => self.emit_at(Level::ERROR),
+        }
+    }
+
+    /// Record `self` as a `tracing` event at the given level. 
+    ///
+    /// The event's message is `self`'s message, and it has these fields: 
+    ///
+    /// - `error.fields`: `self`'s fields, as `key = value` pairs. `tracing` 
+    ///   only supports field names known at compile time, so these can't be 
+    ///   separate event fields. 
+    /// - `error.kind`: `self`'s kind, if it has one. 
+    /// - `error.severity`: `self`'s severity. 
+    /// - `error.location`: the source location `self` was constructed at, 
+    ///   if known. 
+    /// - `error.causes`: the number of `self`'s direct causes. 
+    /// - `error.display`: `self` displayed in full, including its causes. 
+    pub fn emit_at(&self, level: Level) {
+        let fields = Fields(self);
+        let kind = self.kind().map(tracing::field::display);
+        let severity = self.severity().name();
+        let location = self.location().map(tracing::field::display);
+        let causes = self.causes().len();
+        macro_rules! emit {
+            ($level:expr)=>{
+                tracing::event!(
+                    $level,
+                    error.fields = %fields,
+                    error.kind = kind,
+                    error.severity = severity,
+                    error.location = location,
+                    error.causes = causes,
+                    error.display = %self,
+                    "{}",
+                    self.message(),
+                )
+            };
+        }
+        // the level must be a constant
+        match level {
+            Level::ERROR => emit!(Level::ERROR),
+            Level::WARN => emit!(Level::WARN),
+            Level::INFO => emit!(Level::INFO),
+            Level::DEBUG => emit!(Level::DEBUG),
+            Level::TRACE => emit!(Level::TRACE),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+
+use crate::Error;
+use core::fmt::Debug;
+use alloc::{
+    vec,
+    vec::Vec,
+};
+
+/// Depth-first iterator over an `Error` and its causes, recursively. 
+///
+/// Constructed with `Error::iter_tree`. Yields each error with its depth, 
+/// where the root is depth 0, before its causes, in order. 
+#[derive(Clone)]
+pub struct IterTree<'a> {
+    stack: Vec<(usize, &'a Error)>,
+}
+
+impl<'a> IterTree<'a> {
+    pub(crate) fn new(root: &'a Error) -> Self {
+        IterTree {
+            stack: vec![(0, root)],
+        }
+    }
+}
+
+impl<'a> Iterator for IterTree<'a> {
+    type Item = (usize, &'a Error);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, error) = self.stack.pop()?;
+        self.stack.extend(error.causes().iter()
+            .rev()
+            .map(|cause| (depth + 1, cause)));
+        Some((depth, error))
+    }
+}
+
+/// Hooks for walking an `Error`'s cause tree, with `Error::visit`. 
+///
+/// Errors are visited depth-first. For each error, `visit_error` is called, 
+/// then `visit_field` for each of its fields, then its causes are visited, 
+/// then `leave_error` is called. All methods do nothing by default. 
+///
+/// ```
+/// use pear::{pear, Error, Visitor};
+/// use std::fmt::Debug;
+/// 
+/// #[derive(Default)]
+/// struct FieldCounter {
+///     errors: usize,
+///     fields: usize,
+/// }
+///
+/// impl Visitor for FieldCounter {
+///     fn visit_error(&mut self, _: &Error, _: usize) -> bool {
+///         self.errors += 1;
+///         true
+///     }
+///
+///     fn visit_field(&mut self, _: &Error, _: &str, _: &dyn Debug) {
+///         self.fields += 1;
+///     }
+/// }
+///
+/// let inner = Error::new("inner", vec![], ());
+/// let error = Error::new("outer", vec![pear::Field::new("a", 1)], inner);
+/// let mut counter = FieldCounter::default();
+/// error.visit(&mut counter);
+/// assert_eq!((counter.errors, counter.fields), (2, 1));
+///
+/// let messages: Vec<_> = error.iter_tree()
+///     .map(|(depth, error)| (depth, error.message()))
+///     .collect();
+/// assert_eq!(messages, [(0, "outer"), (1, "inner")]);
+/// ```
+pub trait Visitor {
+    /// Called upon entering an error, at `depth` below the root. Return 
+    /// `false` to skip its fields and causes, in which case `leave_error` 
+    /// isn't called for it either. 
+    fn visit_error(&mut self, error: &Error, depth: usize) -> bool {
+        let _ = (error, depth);
+        true
+    }
+
+    /// Called for each field of an error. 
+    fn visit_field(&mut self, error: &Error, key: &str, value: &dyn Debug) {
+        let _ = (error, key, value);
+    }
+
+    /// Called upon leaving an error, after its causes have been visited. 
+    fn leave_error(&mut self, error: &Error, depth: usize) {
+        let _ = (error, depth);
+    }
+}
+
+pub(crate) fn visit<V: Visitor + ?Sized>(error: &Error, depth: usize, visitor: &mut V) {
+    if !visitor.visit_error(error, depth) {
+        return;
+    }
+    for (key, value) in error.fields() {
+        visitor.visit_field(error, key, &value);
+    }
+    for cause in error.causes() {
+        visit(cause, depth + 1, visitor);
+    }
+    visitor.leave_error(error, depth);
+}
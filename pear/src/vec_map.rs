@@ -0,0 +1,71 @@
+
+use core::{
+    borrow::Borrow,
+    iter::FromIterator,
+};
+use alloc::vec::Vec;
+
+/// Map backed by a `Vec`, in insertion order. 
+///
+/// Errors have few fields, so a linear search beats hashing, and this works 
+/// without `std`. 
+#[derive(Debug, Clone)]
+pub struct VecMap<K, V>(Vec<(K, V)>);
+
+impl<K: Eq, V> VecMap<K, V> {
+    pub fn new() -> Self {
+        VecMap(Vec::new())
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.0.iter()
+            .find(|(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Insert, overriding and returning the old value if already present. 
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => Some(core::mem::replace(v, val)),
+            None => {
+                self.0.push((key, val));
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let i = self.0.iter().position(|(k, _)| k.borrow() == key)?;
+        Some(self.0.remove(i).1)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.0.iter().map(|(k, v)| (k, v))
+    }
+}
+
+impl<K: Eq, V> FromIterator<(K, V)> for VecMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = VecMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}